@@ -2,22 +2,31 @@
 use proc_macro2::TokenStream as TokenStream2;
 use quote::{quote, ToTokens};
 use std::fmt;
-use std::str::FromStr;
+use std::ops::Range;
 use syn::{
     bracketed,
     parse::{Parse, ParseStream},
     Result, Token,
 };
 
+pub mod syntax;
+
 #[derive(Debug)]
 pub enum DaxToken {
     Function(String),
     Number(f64),
     Operator(char),
+    /// One of `=`, `<>`, `<`, `<=`, `>` and `>=`
+    Comparison(&'static str),
     Column(String),
+    /// `Table[Column]`, or `'Table Name'[Column]` when the table name needs quoting
+    QualifiedColumn(String, String),
+    Text(String),
     Comma,
     ParenOpen,
     ParenClose,
+    BraceOpen,
+    BraceClose,
     Whitespace,
 }
 
@@ -27,10 +36,25 @@ impl fmt::Display for DaxToken {
             DaxToken::Function(name) => write!(f, "{}", name),
             DaxToken::Number(n) => write!(f, "{}", n),
             DaxToken::Operator(op) => write!(f, "{}", op),
-            DaxToken::Column(name) => write!(f, "{}", name),
+            DaxToken::Comparison(op) => write!(f, "{}", op),
+            DaxToken::Column(name) => write!(f, "[{}]", name),
+            DaxToken::QualifiedColumn(table, column) => {
+                let plain = table.starts_with(|c: char| c.is_ascii_alphabetic())
+                    && table
+                        .chars()
+                        .all(|c| c.is_alphanumeric() || c == '_' || c == '.');
+                if plain {
+                    write!(f, "{}[{}]", table, column)
+                } else {
+                    write!(f, "'{}'[{}]", table.replace('\'', "''"), column)
+                }
+            }
+            DaxToken::Text(text) => write!(f, "\"{}\"", text.replace('"', "\"\"")),
             DaxToken::Comma => write!(f, ","),
             DaxToken::ParenOpen => write!(f, "("),
             DaxToken::ParenClose => write!(f, ")"),
+            DaxToken::BraceOpen => write!(f, "{{"),
+            DaxToken::BraceClose => write!(f, "}}"),
             DaxToken::Whitespace => write!(f, " "),
         }
     }
@@ -38,54 +62,440 @@ impl fmt::Display for DaxToken {
 
 impl ToTokens for DaxToken {
     fn to_tokens(&self, tokens: &mut TokenStream2) {
-        let token_str = match self {
-            DaxToken::Function(name) => format!("DaxToken::Function(\"{}\".to_string())", name),
-            DaxToken::Number(n) => format!("DaxToken::Number({:?})", n),
-            DaxToken::Operator(op) => format!("DaxToken::Operator('{}')", op),
-            DaxToken::Column(name) => format!("DaxToken::Column(\"{}\".to_string())", name),
-            DaxToken::Comma => "DaxToken::Comma".to_string(),
-            DaxToken::ParenOpen => "DaxToken::ParenOpen".to_string(),
-            DaxToken::ParenClose => "DaxToken::ParenClose".to_string(),
-            DaxToken::Whitespace => "DaxToken::Whitespace".to_string(),
-        };
-        tokens.extend(TokenStream2::from_str(&token_str).unwrap());
+        // Names and text go in as string literals, so quotes, backslashes and
+        // any other characters in them come out as they went in
+        tokens.extend(match self {
+            DaxToken::Function(name) => quote! { DaxToken::Function(#name.to_string()) },
+            DaxToken::Number(n) => {
+                let n = syntax::number_tokens(*n);
+                quote! { DaxToken::Number(#n) }
+            }
+            DaxToken::Operator(op) => quote! { DaxToken::Operator(#op) },
+            DaxToken::Comparison(op) => quote! { DaxToken::Comparison(#op) },
+            DaxToken::Column(name) => quote! { DaxToken::Column(#name.to_string()) },
+            DaxToken::QualifiedColumn(table, column) => quote! {
+                DaxToken::QualifiedColumn(#table.to_string(), #column.to_string())
+            },
+            DaxToken::Text(text) => quote! { DaxToken::Text(#text.to_string()) },
+            DaxToken::Comma => quote! { DaxToken::Comma },
+            DaxToken::ParenOpen => quote! { DaxToken::ParenOpen },
+            DaxToken::ParenClose => quote! { DaxToken::ParenClose },
+            DaxToken::BraceOpen => quote! { DaxToken::BraceOpen },
+            DaxToken::BraceClose => quote! { DaxToken::BraceClose },
+            DaxToken::Whitespace => quote! { DaxToken::Whitespace },
+        });
     }
 }
 
+/// Split an expression into tokens, dropping `//` and `/* */` comments. An
+/// unterminated string literal, quoted table name, column name or block
+/// comment takes the rest of the input, and characters that can't start a
+/// token or numbers that don't parse are skipped without a word, so the
+/// tokens may not be the expression that was written.
+#[deprecated(
+    since = "0.1.0",
+    note = "skips what it can't read; use `try_tokenize`, which reports it"
+)]
 pub fn tokenize(input: &str) -> Vec<DaxToken> {
+    scan(input).0.into_iter().map(|(token, _)| token).collect()
+}
+
+/// Split an expression into tokens, dropping `//` and `/* */` comments. An
+/// unterminated string literal, quoted table name, column name or block
+/// comment, a stray character or a malformed number is an error naming the
+/// character offset where it starts.
+pub fn try_tokenize(input: &str) -> std::result::Result<Vec<DaxToken>, String> {
+    let tokens = tokenize_spanned(input)?;
+    Ok(tokens.into_iter().map(|(token, _)| token).collect())
+}
+
+/// Like `try_tokenize`, with each token's range of character offsets in the input
+pub fn tokenize_spanned(input: &str) -> std::result::Result<Vec<(DaxToken, Range<usize>)>, String> {
+    match scan(input) {
+        (tokens, None) => Ok(tokens),
+        (_, Some((error, _))) => Err(error),
+    }
+}
+
+/// Every function the evaluator implements itself. The evaluator is in the
+/// main crate, so this is kept by hand, and a test there checks it against
+/// the names the evaluator dispatches on.
+pub const BUILTIN_FUNCTIONS: &[&str] = &[
+    "ADDCOLUMNS",
+    "APPROXIMATEDISTINCTCOUNT",
+    "AVERAGE",
+    "AVERAGEA",
+    "BLANK",
+    "CALCULATE",
+    "CALENDAR",
+    "CALENDARAUTO",
+    "CHISQ.DIST",
+    "CLOSINGBALANCEMONTH",
+    "CLOSINGBALANCEQUARTER",
+    "CLOSINGBALANCEYEAR",
+    "COMBINEVALUES",
+    "COUNTBLANK",
+    "COUNTROWS",
+    "CROSSFILTER",
+    "CURRENCY",
+    "DATATABLE",
+    "DATE",
+    "DATEADD",
+    "DATEDIFF",
+    "DATESBETWEEN",
+    "DATESINPERIOD",
+    "DATESMTD",
+    "DATESQTD",
+    "DATESYTD",
+    "DAY",
+    "DISTINCTCOUNT",
+    "DIVIDE",
+    "EDATE",
+    "ENDOFMONTH",
+    "ENDOFQUARTER",
+    "ENDOFYEAR",
+    "EOMONTH",
+    "ERROR",
+    "EXACT",
+    "FALSE",
+    "FILTER",
+    "FIRSTDATE",
+    "HOUR",
+    "IF",
+    "IFERROR",
+    "INDEX",
+    "ISEMPTY",
+    "KEEPFILTERS",
+    "LASTDATE",
+    "MAX",
+    "MAXA",
+    "MIN",
+    "MINA",
+    "MINUTE",
+    "MONTH",
+    "NETWORKDAYS",
+    "NEXTDAY",
+    "NEXTMONTH",
+    "NEXTQUARTER",
+    "NEXTYEAR",
+    "NORM.DIST",
+    "NORM.INV",
+    "NORM.S.DIST",
+    "NORM.S.INV",
+    "NOW",
+    "OFFSET",
+    "OPENINGBALANCEMONTH",
+    "OPENINGBALANCEQUARTER",
+    "OPENINGBALANCEYEAR",
+    "ORDERBY",
+    "PREVIOUSDAY",
+    "PREVIOUSMONTH",
+    "PREVIOUSQUARTER",
+    "PREVIOUSYEAR",
+    "RANK",
+    "REMOVEFILTERS",
+    "REPT",
+    "ROWNUMBER",
+    "SAMEPERIODLASTYEAR",
+    "SAMPLE",
+    "SECOND",
+    "STARTOFMONTH",
+    "STARTOFQUARTER",
+    "STARTOFYEAR",
+    "SUM",
+    "SUMMARIZE",
+    "SUMMARIZECOLUMNS",
+    "SUMX",
+    "SWITCH",
+    "T.DIST",
+    "TIME",
+    "TODAY",
+    "TOPN",
+    "TOTALMTD",
+    "TOTALQTD",
+    "TOTALYTD",
+    "TREATAS",
+    "TRUE",
+    "UNICHAR",
+    "UNICODE",
+    "USERELATIONSHIP",
+    "WEEKDAY",
+    "WEEKNUM",
+    "WINDOW",
+    "YEAR",
+    "YEARFRAC",
+];
+
+/// How many arguments built-in functions take, as the fewest and the most,
+/// for those where that doesn't depend on what the arguments are
+pub const BUILTIN_ARITIES: &[(&str, usize, usize)] = &[
+    ("APPROXIMATEDISTINCTCOUNT", 1, 1),
+    ("AVERAGE", 1, 1),
+    ("AVERAGEA", 1, 1),
+    ("BLANK", 0, 0),
+    ("CALENDAR", 2, 2),
+    ("CALENDARAUTO", 0, 1),
+    ("CHISQ.DIST", 3, 3),
+    ("COUNTBLANK", 1, 1),
+    ("COUNTROWS", 0, 1),
+    ("CURRENCY", 1, 1),
+    ("DATE", 3, 3),
+    ("DATEADD", 3, 3),
+    ("DATEDIFF", 3, 3),
+    ("DATESBETWEEN", 3, 3),
+    ("DATESINPERIOD", 4, 4),
+    ("DAY", 1, 1),
+    ("DISTINCTCOUNT", 1, 1),
+    ("DIVIDE", 2, 3),
+    ("EDATE", 2, 2),
+    ("EOMONTH", 2, 2),
+    ("ERROR", 1, 1),
+    ("EXACT", 2, 2),
+    ("FALSE", 0, 0),
+    ("FILTER", 2, 2),
+    ("FIRSTDATE", 1, 1),
+    ("HOUR", 1, 1),
+    ("IF", 2, 3),
+    ("IFERROR", 2, 2),
+    ("ISEMPTY", 1, 1),
+    ("LASTDATE", 1, 1),
+    ("MAX", 1, 1),
+    ("MAXA", 1, 1),
+    ("MIN", 1, 1),
+    ("MINA", 1, 1),
+    ("MINUTE", 1, 1),
+    ("MONTH", 1, 1),
+    ("NETWORKDAYS", 2, 4),
+    ("NORM.DIST", 4, 4),
+    ("NORM.INV", 3, 3),
+    ("NORM.S.DIST", 2, 2),
+    ("NORM.S.INV", 1, 1),
+    ("NOW", 0, 0),
+    ("REPT", 2, 2),
+    ("SAMEPERIODLASTYEAR", 1, 1),
+    ("SAMPLE", 3, 4),
+    ("SECOND", 1, 1),
+    ("SUM", 1, 1),
+    ("SUMX", 2, 2),
+    ("T.DIST", 3, 3),
+    ("TIME", 3, 3),
+    ("TODAY", 0, 0),
+    ("TOPN", 3, 4),
+    ("TRUE", 0, 0),
+    ("UNICHAR", 1, 1),
+    ("UNICODE", 1, 1),
+    ("WEEKDAY", 1, 2),
+    ("WEEKNUM", 1, 2),
+    ("YEAR", 1, 1),
+    ("YEARFRAC", 2, 3),
+];
+
+/// Check tokens for mistakes that show without evaluating them: brackets
+/// that don't match, calls to functions that aren't built in, unless
+/// `allow_unknown`, and built-ins called with the wrong number of arguments.
+/// A mistake is a message and the range of character offsets it covers.
+pub fn check_tokens(
+    tokens: &[(DaxToken, Range<usize>)],
+    allow_unknown: bool,
+) -> std::result::Result<(), (String, Range<usize>)> {
+    // An open bracket, the function it calls if it's a call's, and the commas
+    // and whether anything at all has come inside it so far
+    struct Open<'a> {
+        token: &'a DaxToken,
+        span: Range<usize>,
+        call: Option<(&'a str, Range<usize>)>,
+        commas: usize,
+        empty: bool,
+    }
+    let mut open: Vec<Open> = Vec::new();
+    // A name that's a call if a parenthesis comes next
+    let mut name = None;
+
+    for (token, span) in tokens {
+        if let DaxToken::Whitespace = token {
+            continue;
+        }
+        let called = name.take();
+        if let Some(inner) = open.last_mut() {
+            match token {
+                DaxToken::Comma => inner.commas += 1,
+                DaxToken::ParenClose | DaxToken::BraceClose => {}
+                _ => inner.empty = false,
+            }
+        }
+        match token {
+            DaxToken::Function(function) => name = Some((function.as_str(), span.clone())),
+            DaxToken::ParenOpen | DaxToken::BraceOpen => {
+                let call = match (token, called) {
+                    (DaxToken::ParenOpen, Some((function, name_span))) => {
+                        let known = BUILTIN_FUNCTIONS
+                            .binary_search(&function.to_ascii_uppercase().as_str())
+                            .is_ok();
+                        if !known && !allow_unknown {
+                            return Err((
+                                format!(
+                                    "Unknown function {} at character {}",
+                                    function, name_span.start
+                                ),
+                                name_span,
+                            ));
+                        }
+                        Some((function, name_span))
+                    }
+                    _ => None,
+                };
+                open.push(Open {
+                    token,
+                    span: span.clone(),
+                    call,
+                    commas: 0,
+                    empty: true,
+                });
+            }
+            DaxToken::ParenClose | DaxToken::BraceClose => {
+                let opening = match open.pop() {
+                    Some(opening) => opening,
+                    None => {
+                        return Err((
+                            format!("Unmatched '{}' at character {}", token, span.start),
+                            span.clone(),
+                        ))
+                    }
+                };
+                let matches = matches!(
+                    (opening.token, token),
+                    (DaxToken::ParenOpen, DaxToken::ParenClose)
+                        | (DaxToken::BraceOpen, DaxToken::BraceClose)
+                );
+                if !matches {
+                    return Err((
+                        format!(
+                            "'{}' at character {} doesn't close the '{}' at character {}",
+                            token, span.start, opening.token, opening.span.start
+                        ),
+                        opening.span.start..span.end,
+                    ));
+                }
+                if let Some((function, name_span)) = opening.call {
+                    let got = if opening.empty { 0 } else { opening.commas + 1 };
+                    check_arity(function, got)
+                        .map_err(|message| (message, name_span.start..span.end))?;
+                }
+            }
+            _ => {}
+        }
+    }
+    match open.pop() {
+        Some(opening) => Err((
+            format!(
+                "Unclosed '{}' at character {}",
+                opening.token, opening.span.start
+            ),
+            opening.span,
+        )),
+        None => Ok(()),
+    }
+}
+
+// Whether a built-in whose number of arguments is known takes `got` of them
+fn check_arity(function: &str, got: usize) -> std::result::Result<(), String> {
+    let upper = function.to_ascii_uppercase();
+    let Ok(i) = BUILTIN_ARITIES.binary_search_by(|(name, _, _)| name.cmp(&upper.as_str())) else {
+        return Ok(());
+    };
+    let (_, min, max) = BUILTIN_ARITIES[i];
+    if (min..=max).contains(&got) {
+        return Ok(());
+    }
+    let expected = if min == max {
+        min.to_string()
+    } else {
+        format!("{} to {}", min, max)
+    };
+    Err(format!(
+        "{} requires {} arguments, got {}",
+        function, expected, got
+    ))
+}
+
+type Chars<'a> = std::iter::Peekable<std::iter::Enumerate<std::str::Chars<'a>>>;
+
+// The tokens with their spans, and the first problem found if there was one,
+// with the characters it's about
+type Scanned = (
+    Vec<(DaxToken, Range<usize>)>,
+    Option<(String, Range<usize>)>,
+);
+
+fn scan(input: &str) -> Scanned {
     let mut tokens = Vec::new();
-    let mut chars = input.chars().peekable();
+    let mut spans = Vec::new();
+    let mut error = None;
+    // Only the first problem is reported
+    let mut report = |message: String, chars: Range<usize>| {
+        error.get_or_insert((message, chars));
+    };
+    let unterminated = |what: &str, position: usize| {
+        format!("Unterminated {} starting at character {}", what, position)
+    };
+    let length = input.chars().count();
+    let mut chars = input.chars().enumerate().peekable();
 
-    while let Some(&c) = chars.peek() {
+    while let Some(&(position, c)) = chars.peek() {
         match c {
             '0'..='9' => {
                 let mut num = String::new();
-                while let Some(&d) = chars.peek() {
-                    if d.is_digit(10) || d == '.' {
+                while let Some(&(_, d)) = chars.peek() {
+                    if d.is_ascii_digit() || d == '.' {
                         num.push(d);
                         chars.next();
                     } else {
                         break;
                     }
                 }
-                if let Ok(n) = num.parse() {
-                    tokens.push(DaxToken::Number(n));
+                num.push_str(&exponent(&mut chars));
+                match num.parse() {
+                    Ok(n) => tokens.push(DaxToken::Number(n)),
+                    Err(_) => report(
+                        format!("Invalid number '{}' at character {}", num, position),
+                        position..position + num.chars().count(),
+                    ),
                 }
             }
             '[' => {
                 chars.next();
-                let mut column = String::new();
-                while let Some(&c) = chars.peek() {
-                    if c != ']' {
-                        column.push(c);
-                        chars.next();
-                    } else {
-                        chars.next();
-                        break;
-                    }
+                let (column, closed) = column_name(&mut chars);
+                if !closed {
+                    report(
+                        unterminated("column name", position),
+                        position..position + 1,
+                    );
                 }
                 tokens.push(DaxToken::Column(column));
             }
+            '"' => {
+                chars.next();
+                let (text, closed) = quoted(&mut chars, '"');
+                if !closed {
+                    report(unterminated("string", position), position..position + 1);
+                }
+                tokens.push(DaxToken::Text(text));
+            }
+            '\'' => {
+                chars.next();
+                let (table, closed) = quoted(&mut chars, '\'');
+                if !closed {
+                    report(unterminated("table name", position), position..position + 1);
+                }
+                let (token, closed) = table_or_column(table, &mut chars);
+                if !closed {
+                    report(
+                        unterminated("column name", position),
+                        position..position + 1,
+                    );
+                }
+                tokens.push(token);
+            }
             '(' => {
                 chars.next();
                 tokens.push(DaxToken::ParenOpen);
@@ -94,36 +504,407 @@ pub fn tokenize(input: &str) -> Vec<DaxToken> {
                 chars.next();
                 tokens.push(DaxToken::ParenClose);
             }
+            '{' => {
+                chars.next();
+                tokens.push(DaxToken::BraceOpen);
+            }
+            '}' => {
+                chars.next();
+                tokens.push(DaxToken::BraceClose);
+            }
             ',' => {
                 chars.next();
                 tokens.push(DaxToken::Comma);
             }
-            '+' | '-' | '*' | '/' => {
+            '/' if matches!(chars.clone().nth(1), Some((_, '/' | '*'))) => {
+                chars.next();
+                if let Some((_, '/')) = chars.next() {
+                    // A line comment runs up to the end of the line
+                    while chars.next_if(|&(_, c)| c != '\n').is_some() {}
+                } else if !block_comment(&mut chars) {
+                    report(
+                        unterminated("block comment", position),
+                        position..position + 1,
+                    );
+                }
+            }
+            '+' | '-' | '*' | '/' | '^' | '%' => {
                 chars.next();
                 tokens.push(DaxToken::Operator(c));
             }
+            '=' | '<' | '>' => {
+                chars.next();
+                let op = match (c, chars.peek().map(|&(_, next)| next)) {
+                    ('<', Some('=')) => "<=",
+                    ('<', Some('>')) => "<>",
+                    ('>', Some('=')) => ">=",
+                    ('<', _) => "<",
+                    ('>', _) => ">",
+                    _ => "=",
+                };
+                if op.len() == 2 {
+                    chars.next();
+                }
+                tokens.push(DaxToken::Comparison(op));
+            }
             ' ' | '\t' | '\n' | '\r' => {
                 chars.next();
                 tokens.push(DaxToken::Whitespace);
             }
-            'A'..='Z' | 'a'..='z' => {
+            'A'..='Z' | 'a'..='z' | '_' => {
                 let mut function = String::new();
-                while let Some(&c) = chars.peek() {
-                    if c.is_alphabetic() {
+                while let Some(&(_, c)) = chars.peek() {
+                    // Dots appear inside names such as NORM.S.INV
+                    if c.is_alphanumeric() || c == '_' || c == '.' {
                         function.push(c);
                         chars.next();
                     } else {
                         break;
                     }
                 }
-                tokens.push(DaxToken::Function(function));
+                let (token, closed) = table_or_column(function, &mut chars);
+                if !closed {
+                    report(
+                        unterminated("column name", position),
+                        position..position + 1,
+                    );
+                }
+                tokens.push(token);
             }
             _ => {
                 chars.next();
+                report(
+                    format!("Unexpected character '{}' at character {}", c, position),
+                    position..position + 1,
+                );
             }
         }
+        let end = chars.peek().map_or(length, |&(next, _)| next);
+        spans.resize(tokens.len(), position..end);
+    }
+    (tokens.into_iter().zip(spans).collect(), error)
+}
+
+// The `e-3` of `1.5e-3`, if the digits go on that way. An `e` not followed by
+// digits is left alone, to start a name.
+fn exponent(chars: &mut Chars) -> String {
+    let mut ahead = chars.clone();
+    let mut exponent = match ahead.next() {
+        Some((_, e @ ('e' | 'E'))) => e.to_string(),
+        _ => return String::new(),
+    };
+    if let Some((_, sign)) = ahead.next_if(|&(_, c)| c == '+' || c == '-') {
+        exponent.push(sign);
+    }
+    if !ahead.peek().is_some_and(|&(_, c)| c.is_ascii_digit()) {
+        return String::new();
+    }
+    while let Some((_, d)) = ahead.next_if(|&(_, c)| c.is_ascii_digit()) {
+        exponent.push(d);
+    }
+    *chars = ahead;
+    exponent
+}
+
+// Skip a block comment after its `/*`, and whether it was closed
+fn block_comment(chars: &mut Chars) -> bool {
+    while let Some((_, c)) = chars.next() {
+        if c == '*' && chars.next_if(|&(_, next)| next == '/').is_some() {
+            return true;
+        }
+    }
+    false
+}
+
+// A column name after its opening bracket, up to the closing one, and
+// whether it was closed
+fn column_name(chars: &mut Chars) -> (String, bool) {
+    let mut column = String::new();
+    for (_, c) in chars.by_ref() {
+        if c == ']' {
+            return (column, true);
+        }
+        column.push(c);
+    }
+    (column, false)
+}
+
+// Text after an opening quote up to the matching closing one, where a doubled
+// quote stands for one quote inside the text, and whether it was closed
+fn quoted(chars: &mut Chars, quote: char) -> (String, bool) {
+    let mut text = String::new();
+    while let Some((_, c)) = chars.next() {
+        if c == quote && chars.next_if(|&(_, next)| next == quote).is_none() {
+            return (text, true);
+        }
+        text.push(c);
+    }
+    (text, false)
+}
+
+// A name directly followed by `[` qualifies that column; otherwise it stands
+// alone. False if the column's bracket was left open.
+fn table_or_column(name: String, chars: &mut Chars) -> (DaxToken, bool) {
+    if chars.next_if(|&(_, next)| next == '[').is_some() {
+        let (column, closed) = column_name(chars);
+        (DaxToken::QualifiedColumn(name, column), closed)
+    } else {
+        (DaxToken::Function(name), true)
+    }
+}
+
+/// The expansion of `parse_dax!`: the tokens of a string literal, as a
+/// `Vec<DaxToken>`, or the compile error for the first mistake in it.
+/// Calls to functions that aren't built in are allowed if `allow_unknown`.
+pub fn expand_parse_dax(input: TokenStream2, allow_unknown: bool) -> TokenStream2 {
+    let tokens =
+        syn::parse2::<syn::LitStr>(input).and_then(|input| checked_tokens(&input, allow_unknown));
+    let tokens = match tokens {
+        Ok(tokens) => tokens.into_iter().map(|(token, _)| token),
+        Err(error) => return error.to_compile_error(),
+    };
+    quote! {{
+        use ::dax_rust::__private::DaxToken;
+        vec![#(#tokens),*]
+    }}
+}
+
+// The arguments of `dax!`: a table and an expression
+struct DaxInput {
+    table: syn::Expr,
+    expression: syn::LitStr,
+}
+
+impl Parse for DaxInput {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let table = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let expression = input.parse()?;
+        // A trailing comma is fine
+        if !input.is_empty() {
+            input.parse::<Token![,]>()?;
+        }
+        Ok(DaxInput { table, expression })
+    }
+}
+
+/// The expansion of `dax!`: the expression parsed when the macro expands,
+/// as the code that builds its tree, compiled into a `CompiledExpression`
+/// the first time it runs and kept for every time after, evaluated against
+/// the table. A column the table doesn't have is an `EvaluationError`.
+pub fn expand_dax(input: TokenStream2, allow_unknown: bool) -> TokenStream2 {
+    let DaxInput { table, expression } = match syn::parse2(input) {
+        Ok(input) => input,
+        Err(error) => return error.to_compile_error(),
+    };
+    let tree = checked_tokens(&expression, allow_unknown).and_then(|tokens| {
+        syntax::parse(&tokens).map_err(|(message, range)| {
+            let span = span_within(&expression, range).unwrap_or_else(|| expression.span());
+            syn::Error::new(span, message)
+        })
+    });
+    let tree = match tree {
+        Ok(tree) => tree,
+        Err(error) => return error.to_compile_error(),
+    };
+
+    quote! {{
+        static COMPILED: ::std::sync::OnceLock<::dax_rust::CompiledExpression> =
+            ::std::sync::OnceLock::new();
+        let compiled = COMPILED.get_or_init(|| {
+            use ::dax_rust::__private::{BinaryOperator, DaxExpr, UnaryOperator, Value};
+            ::dax_rust::CompiledExpression::from_ast(#expression, #tree)
+        });
+        let table: &::dax_rust::Table = &#table;
+        compiled
+            .evaluate(table)
+            .map_err(::dax_rust::__private::unknown_column_as_evaluation_error)
+    }}
+}
+
+// An expression's tokens, after checking them for mistakes that show without
+// evaluating it
+fn checked_tokens(
+    input: &syn::LitStr,
+    allow_unknown: bool,
+) -> Result<Vec<(DaxToken, Range<usize>)>> {
+    let (tokens, error) = scan(&input.value());
+    if let Some((message, range)) = error.or_else(|| check_tokens(&tokens, allow_unknown).err()) {
+        let span = span_within(input, range).unwrap_or_else(|| input.span());
+        return Err(syn::Error::new(span, message));
+    }
+    Ok(tokens)
+}
+
+// The span of some characters of a string literal's value, where the compiler
+// can give one and the literal has no escapes to throw the offsets out
+fn span_within(literal: &syn::LitStr, chars: Range<usize>) -> Option<proc_macro2::Span> {
+    let value = literal.value();
+    let source = literal.token().to_string();
+    // After the `"`, or the `r#"` of a raw string
+    let start = source.find('"')? + 1;
+    if source.get(start..start + value.len())? != value {
+        return None;
+    }
+    let byte = |char: usize| {
+        value
+            .char_indices()
+            .nth(char)
+            .map_or(value.len(), |(i, _)| i)
+    };
+    literal
+        .token()
+        .subspan(start + byte(chars.start)..start + byte(chars.end))
+}
+
+/// The expansion of `table!`: a block that builds the table, or the compile
+/// errors for everything wrong with the columns
+pub fn expand_table(input: TokenStream2) -> TokenStream2 {
+    // Every row of the wrong length is an error, so there may be several
+    match syn::parse2::<TableData>(input) {
+        Ok(table_data) => generate_table_tokens(&table_data),
+        Err(error) => expression_errors(error),
     }
-    tokens
+}
+
+// The arguments of `measures!`: the table, then each measure's name and
+// expression
+struct MeasuresInput {
+    table: syn::Expr,
+    measures: Vec<(syn::LitStr, syn::LitStr)>,
+}
+
+impl Parse for MeasuresInput {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let on = input.parse::<syn::Ident>()?;
+        if on != "on" {
+            return Err(syn::Error::new(on.span(), "expected `on <table>:`"));
+        }
+        let table = input.parse()?;
+        input.parse::<Token![:]>()?;
+        let mut measures = Vec::new();
+        while !input.is_empty() {
+            let name = input.parse()?;
+            input.parse::<Token![=>]>()?;
+            measures.push((name, input.parse()?));
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        Ok(MeasuresInput { table, measures })
+    }
+}
+
+/// The expansion of `measures!`: the `define_measure` calls for each measure,
+/// those it refers to first, then a check that every other name the
+/// measures refer to is a column of the table. The block evaluates to
+/// `Result<(), DaxError>`.
+///
+/// Each expression is checked as `parse_dax!` checks one, and a measure
+/// declared twice, or measures that refer to each other in a cycle, are
+/// compile errors too.
+pub fn expand_measures(input: TokenStream2, allow_unknown: bool) -> TokenStream2 {
+    let MeasuresInput { table, measures } = match syn::parse2(input) {
+        Ok(input) => input,
+        Err(error) => return error.to_compile_error(),
+    };
+    let order = match measure_order(&measures, allow_unknown) {
+        Ok(order) => order,
+        Err(error) => return expression_errors(error),
+    };
+    let names = order.iter().map(|&i| &measures[i].0);
+    let expressions: Vec<_> = order.iter().map(|&i| &measures[i].1).collect();
+
+    quote! {{
+        let table: &mut ::dax_rust::Table = &mut #table;
+        (|| -> ::std::result::Result<(), ::dax_rust::DaxError> {
+            #(table.define_measure(#names, #expressions)?;)*
+            // Any name that isn't a measure must be a column
+            #(table.compile(#expressions)?;)*
+            ::std::result::Result::Ok(())
+        })()
+    }}
+}
+
+// The order to define measures in, as indexes into them, so that each comes
+// after those it refers to
+fn measure_order(
+    measures: &[(syn::LitStr, syn::LitStr)],
+    allow_unknown: bool,
+) -> Result<Vec<usize>> {
+    let names: Vec<String> = measures.iter().map(|(name, _)| name.value()).collect();
+    let mut errors: Option<syn::Error> = None;
+    let mut report = |error: syn::Error| match &mut errors {
+        Some(errors) => errors.combine(error),
+        None => errors = Some(error),
+    };
+
+    // The measures each one refers to
+    let mut references = Vec::with_capacity(measures.len());
+    for (i, (name, expression)) in measures.iter().enumerate() {
+        if names[..i].contains(&names[i]) {
+            report(syn::Error::new(
+                name.span(),
+                format!("measure {:?} is declared twice", names[i]),
+            ));
+        }
+        let tokens = checked_tokens(expression, allow_unknown).unwrap_or_else(|error| {
+            report(error);
+            Vec::new()
+        });
+        let mut refers_to = Vec::new();
+        for (token, _) in tokens {
+            if let DaxToken::Column(column) = token {
+                if let Some(j) = names.iter().position(|name| *name == column) {
+                    if !refers_to.contains(&j) {
+                        refers_to.push(j);
+                    }
+                }
+            }
+        }
+        references.push(refers_to);
+    }
+    if let Some(errors) = errors {
+        return Err(errors);
+    }
+
+    // Depth first, in the order they're declared
+    fn visit(
+        i: usize,
+        references: &[Vec<usize>],
+        path: &mut Vec<usize>,
+        order: &mut Vec<usize>,
+    ) -> std::result::Result<(), Vec<usize>> {
+        if order.contains(&i) {
+            return Ok(());
+        }
+        if let Some(start) = path.iter().position(|&j| j == i) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(i);
+            return Err(cycle);
+        }
+        path.push(i);
+        for &j in &references[i] {
+            visit(j, references, path, order)?;
+        }
+        path.pop();
+        order.push(i);
+        Ok(())
+    }
+    let mut order = Vec::with_capacity(measures.len());
+    for i in 0..measures.len() {
+        visit(i, &references, &mut Vec::new(), &mut order).map_err(|cycle| {
+            let names: Vec<&str> = cycle.iter().map(|&j| names[j].as_str()).collect();
+            syn::Error::new(
+                measures[cycle[0]].0.span(),
+                format!(
+                    "measures refer to each other in a cycle: {}",
+                    names.join(" -> ")
+                ),
+            )
+        })?;
+    }
+    Ok(order)
 }
 
 // Table-related structures
@@ -133,15 +914,20 @@ pub struct TableData {
 
 pub struct ColumnDef {
     pub name: syn::LitStr,
+    /// The kind of value declared after the name, as in `"Qty": int`
+    pub column_type: Option<syn::Ident>,
     pub values: Vec<syn::Expr>,
 }
 
 impl Parse for TableData {
     fn parse(input: ParseStream) -> Result<Self> {
+        if input.peek(syn::Ident) && input.peek2(Token![:]) {
+            return parse_rows(input);
+        }
         let mut columns = Vec::new();
 
         while !input.is_empty() {
-            let name = input.parse::<syn::LitStr>()?;
+            let (name, column_type) = column_header(input)?;
             input.parse::<Token![=>]>()?;
 
             let content;
@@ -150,6 +936,7 @@ impl Parse for TableData {
 
             columns.push(ColumnDef {
                 name,
+                column_type,
                 values: values.into_iter().collect(),
             });
 
@@ -162,20 +949,723 @@ impl Parse for TableData {
     }
 }
 
+// A column's name, and its type if one follows it as in `"Qty": int`
+fn column_header(input: ParseStream) -> Result<(syn::LitStr, Option<syn::Ident>)> {
+    let name = input.parse::<syn::LitStr>()?;
+    let column_type = if input.peek(Token![:]) {
+        input.parse::<Token![:]>()?;
+        Some(input.parse::<syn::Ident>()?)
+    } else {
+        None
+    };
+    Ok((name, column_type))
+}
+
+// `columns: ["A", "B"], rows: [[1, 2], [3, 4]]`, the same table written a
+// row at a time. Each row must have a value for every column, which is an
+// error at each row that doesn't.
+fn parse_rows(input: ParseStream) -> Result<TableData> {
+    let keyword = |input: ParseStream, expected: &str| -> Result<()> {
+        let ident = input.parse::<syn::Ident>()?;
+        if ident != expected {
+            return Err(syn::Error::new(
+                ident.span(),
+                format!("expected `{}`", expected),
+            ));
+        }
+        input.parse::<Token![:]>()?;
+        Ok(())
+    };
+
+    keyword(input, "columns")?;
+    let header;
+    bracketed!(header in input);
+    let mut columns: Vec<ColumnDef> = header
+        .parse_terminated(column_header, Token![,])?
+        .into_iter()
+        .map(|(name, column_type)| ColumnDef {
+            name,
+            column_type,
+            values: Vec::new(),
+        })
+        .collect();
+    input.parse::<Token![,]>()?;
+
+    keyword(input, "rows")?;
+    let rows;
+    bracketed!(rows in input);
+    let mut errors: Option<syn::Error> = None;
+    let mut number = 0;
+    while !rows.is_empty() {
+        number += 1;
+        let row;
+        let brackets = bracketed!(row in rows);
+        let values = row.parse_terminated(syn::Expr::parse, Token![,])?;
+        if values.len() == columns.len() {
+            for (column, value) in columns.iter_mut().zip(values) {
+                column.values.push(value);
+            }
+        } else {
+            let error = syn::Error::new(
+                brackets.span.join(),
+                format!(
+                    "row {} has {} values, but there are {} columns",
+                    number,
+                    values.len(),
+                    columns.len()
+                ),
+            );
+            match &mut errors {
+                Some(errors) => errors.combine(error),
+                None => errors = Some(error),
+            }
+        }
+        if !rows.is_empty() {
+            rows.parse::<Token![,]>()?;
+        }
+    }
+    if !input.is_empty() {
+        input.parse::<Token![,]>()?;
+    }
+    match errors {
+        Some(errors) => Err(errors),
+        None => Ok(TableData { columns }),
+    }
+}
+
 pub fn generate_table_tokens(table_data: &TableData) -> TokenStream2 {
+    let lengths = table_data
+        .columns
+        .iter()
+        .map(|col| (&col.name, col.values.len()));
+    if let Err(error) = check_lengths(lengths) {
+        return expression_errors(error);
+    }
     let column_names = table_data.columns.iter().map(|col| &col.name);
-    let column_values = table_data.columns.iter().map(|col| &col.values);
+    let column_values = match table_tokens(
+        table_data
+            .columns
+            .iter()
+            .map(|col| (col.column_type.as_ref(), col.values.as_slice())),
+    ) {
+        Ok(values) => values,
+        Err(error) => return expression_errors(error),
+    };
 
     quote! {
         {
-            let mut table = Table::new();
+            let mut table = ::dax_rust::Table::new();
             #(
                 table.add_column(
                     #column_names.to_string(),
-                    vec![#(Value::from(#column_values)),*]
-                );
+                    vec![#(#column_values),*]
+                ).expect("table! columns must all have the same length");
             )*
             table
         }
     }
 }
+
+// Errors where an expression is expected, in a block so that more than one
+// `compile_error!` can stand there
+fn expression_errors(error: syn::Error) -> TokenStream2 {
+    let errors = error.to_compile_error();
+    quote! {{ #errors }}
+}
+
+/// Check that every `table!` column, given by its name and number of values,
+/// has as many values as the first. Each one that doesn't is an error at its
+/// name.
+pub fn check_lengths<'a>(
+    columns: impl IntoIterator<Item = (&'a syn::LitStr, usize)>,
+) -> Result<()> {
+    let mut columns = columns.into_iter();
+    let Some((first, expected)) = columns.next() else {
+        return Ok(());
+    };
+    let mut errors: Option<syn::Error> = None;
+    for (name, len) in columns.filter(|&(_, len)| len != expected) {
+        let error = syn::Error::new(
+            name.span(),
+            format!(
+                "column {:?} has {} values, but {:?} has {}",
+                name.value(),
+                len,
+                first.value(),
+                expected
+            ),
+        );
+        match &mut errors {
+            Some(errors) => errors.combine(error),
+            None => errors = Some(error),
+        }
+    }
+    errors.map_or(Ok(()), Err)
+}
+
+/// A `table!` cell as a `Value`. A bare `None` or `null` has no type to
+/// convert from, so it is Null directly, and a whole number without a suffix
+/// is an `i64` rather than the `i32` it would default to; anything else goes
+/// through `Value::from`.
+pub fn value_tokens(expr: &syn::Expr) -> TokenStream2 {
+    match expr {
+        syn::Expr::Path(path) if path.path.is_ident("None") || path.path.is_ident("null") => {
+            quote! { ::dax_rust::Value::Null }
+        }
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Int(int),
+            ..
+        }) if int.suffix().is_empty() => quote! { ::dax_rust::Value::Integer(#int) },
+        syn::Expr::Unary(syn::ExprUnary {
+            op: syn::UnOp::Neg(_),
+            expr: inner,
+            ..
+        }) if matches!(
+            &**inner,
+            syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Int(int), .. }) if int.suffix().is_empty()
+        ) =>
+        {
+            quote! { ::dax_rust::Value::Integer(#expr) }
+        }
+        expr => quote! { ::dax_rust::Value::from(#expr) },
+    }
+}
+
+/// A `table!` column's cells as `Value`s. Without a declared type each goes
+/// through `value_tokens`. With one, each must be of that kind, which is a
+/// compile error for a literal that isn't:
+///
+/// - `number`: `Value::Number`, from any number literal or an `f64`
+/// - `int`: `Value::Integer`, from a whole number literal or an `i64`
+/// - `text`: `Value::Text`, from anything with `to_string`
+/// - `bool`: `Value::Boolean`
+/// - `date`: `Value::DateTime`, from a literal like `"2024-01-05"` or
+///   `"2024-01-05 09:30:00"`, read now, or a `NaiveDate` or `NaiveDateTime`
+///
+/// `null` and `None` are Null in a column of any type.
+pub fn column_tokens(
+    column_type: Option<&syn::Ident>,
+    values: &[syn::Expr],
+) -> Result<Vec<TokenStream2>> {
+    let Some(column_type) = column_type else {
+        return Ok(values.iter().map(value_tokens).collect());
+    };
+    let convert: fn(&syn::Expr) -> Result<TokenStream2> = match column_type.to_string().as_str() {
+        "number" => number_tokens,
+        "int" => int_tokens,
+        "text" => |expr| Ok(quote! { ::dax_rust::Value::Text((#expr).to_string()) }),
+        "bool" => |expr| Ok(quote! { ::dax_rust::Value::Boolean(#expr) }),
+        "date" => date_tokens,
+        other => {
+            return Err(syn::Error::new(
+                column_type.span(),
+                format!(
+                    "unknown column type `{}`; expected number, int, text, bool or date",
+                    other
+                ),
+            ))
+        }
+    };
+    let mut cells = Vec::with_capacity(values.len());
+    let mut errors: Option<syn::Error> = None;
+    for expr in values {
+        let cell = if is_null(expr) {
+            Ok(quote! { ::dax_rust::Value::Null })
+        } else {
+            convert(expr)
+        };
+        match (cell, &mut errors) {
+            (Ok(cell), _) => cells.push(cell),
+            (Err(error), Some(errors)) => errors.combine(error),
+            (Err(error), None) => errors = Some(error),
+        }
+    }
+    errors.map_or(Ok(cells), Err)
+}
+
+/// Every `table!` column's cells, as `column_tokens` gives them, with the
+/// errors in all the columns together if there are any
+pub fn table_tokens<'a>(
+    columns: impl IntoIterator<Item = (Option<&'a syn::Ident>, &'a [syn::Expr])>,
+) -> Result<Vec<Vec<TokenStream2>>> {
+    let mut cells = Vec::new();
+    let mut errors: Option<syn::Error> = None;
+    for (column_type, values) in columns {
+        match (column_tokens(column_type, values), &mut errors) {
+            (Ok(column), _) => cells.push(column),
+            (Err(error), Some(errors)) => errors.combine(error),
+            (Err(error), None) => errors = Some(error),
+        }
+    }
+    errors.map_or(Ok(cells), Err)
+}
+
+fn is_null(expr: &syn::Expr) -> bool {
+    matches!(expr, syn::Expr::Path(path) if path.path.is_ident("None") || path.path.is_ident("null"))
+}
+
+// A literal, or a negated one, as the literal and whether it's negated
+fn literal(expr: &syn::Expr) -> Option<(&syn::Lit, bool)> {
+    match expr {
+        syn::Expr::Lit(syn::ExprLit { lit, .. }) => Some((lit, false)),
+        syn::Expr::Unary(syn::ExprUnary {
+            op: syn::UnOp::Neg(_),
+            expr,
+            ..
+        }) => match &**expr {
+            syn::Expr::Lit(syn::ExprLit { lit, .. }) => Some((lit, true)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn number_tokens(expr: &syn::Expr) -> Result<TokenStream2> {
+    match literal(expr) {
+        Some((syn::Lit::Int(int), negated)) => {
+            let value = int.base10_parse::<f64>()?;
+            let value = if negated { -value } else { value };
+            Ok(quote! { ::dax_rust::Value::Number(#value) })
+        }
+        Some((syn::Lit::Float(_), _)) | None => Ok(quote! { ::dax_rust::Value::Number(#expr) }),
+        Some(_) => Err(syn::Error::new_spanned(expr, "expected a number")),
+    }
+}
+
+fn int_tokens(expr: &syn::Expr) -> Result<TokenStream2> {
+    match literal(expr) {
+        Some((syn::Lit::Int(_), _)) | None => Ok(quote! { ::dax_rust::Value::Integer(#expr) }),
+        Some(_) => Err(syn::Error::new_spanned(expr, "expected a whole number")),
+    }
+}
+
+fn date_tokens(expr: &syn::Expr) -> Result<TokenStream2> {
+    use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike};
+    let text = match literal(expr) {
+        Some((syn::Lit::Str(text), false)) => text,
+        None => return Ok(quote! { ::dax_rust::Value::from(#expr) }),
+        Some(_) => {
+            return Err(syn::Error::new_spanned(
+                expr,
+                "expected a date such as \"2024-01-05\"",
+            ))
+        }
+    };
+    let value = text.value();
+    let date = NaiveDate::parse_from_str(&value, "%Y-%m-%d")
+        .map(|date| date.and_hms_opt(0, 0, 0).expect("midnight is a time"))
+        .or_else(|_| NaiveDateTime::parse_from_str(&value, "%Y-%m-%dT%H:%M:%S"))
+        .or_else(|_| NaiveDateTime::parse_from_str(&value, "%Y-%m-%d %H:%M:%S"))
+        .map_err(|_| {
+            let message = format!(
+                "invalid date {:?}; expected a real date as YYYY-MM-DD or YYYY-MM-DD HH:MM:SS",
+                value
+            );
+            syn::Error::new(text.span(), message)
+        })?;
+    let (year, month, day) = (date.year() as i64, date.month() as i64, date.day() as i64);
+    let (hour, minute, second) = (date.hour(), date.minute(), date.second());
+    Ok(quote! {
+        ::dax_rust::Value::DateTime(
+            ::dax_rust::dates::date(#year, #month, #day)
+                .and_then(|day| day.date().and_hms_opt(#hour, #minute, #second))
+                .expect("table! checks its dates")
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(input: &str) -> std::result::Result<(), String> {
+        let table: TableData = syn::parse_str(input).unwrap();
+        check_lengths(
+            table
+                .columns
+                .iter()
+                .map(|col| (&col.name, col.values.len())),
+        )
+        .map_err(|error| {
+            error
+                .into_iter()
+                .map(|error| error.to_string())
+                .collect::<Vec<_>>()
+                .join("; ")
+        })
+    }
+
+    fn check_expression(input: &str) -> std::result::Result<(), (String, Range<usize>)> {
+        check_tokens(&tokenize_spanned(input).unwrap(), false)
+    }
+
+    #[test]
+    fn test_check_tokens() {
+        assert_eq!(
+            check_expression("SUM([Sales]) / DIVIDE(1, 2, BLANK())"),
+            Ok(())
+        );
+        assert_eq!(check_expression("sum ( [Sales] ) + COUNTROWS()"), Ok(()));
+        assert_eq!(check_expression("FILTER({1, 2}, TRUE) + [x] + x"), Ok(()));
+        assert_eq!(
+            check_expression("AVERAGE([Quantity]"),
+            Err(("Unclosed '(' at character 7".to_string(), 7..8))
+        );
+        assert_eq!(
+            check_expression("SUM([Sales]))"),
+            Err(("Unmatched ')' at character 12".to_string(), 12..13))
+        );
+        assert_eq!(
+            check_expression("{1, 2)"),
+            Err((
+                "')' at character 5 doesn't close the '{' at character 0".to_string(),
+                0..6
+            ))
+        );
+        assert_eq!(
+            check_expression("1 + FISCALQUARTER([Date])"),
+            Err((
+                "Unknown function FISCALQUARTER at character 4".to_string(),
+                4..17
+            ))
+        );
+        assert_eq!(
+            check_expression("IF(TRUE, 1, 2, 3)"),
+            Err(("IF requires 2 to 3 arguments, got 4".to_string(), 0..17))
+        );
+        assert_eq!(
+            check_expression("Sum()"),
+            Err(("Sum requires 1 arguments, got 0".to_string(), 0..5))
+        );
+
+        // Lenient checking lets unknown functions through, but not brackets
+        let tokens = tokenize_spanned("FISCALQUARTER([Date], 1)").unwrap();
+        assert_eq!(check_tokens(&tokens, true), Ok(()));
+        let tokens = tokenize_spanned("FISCALQUARTER([Date]").unwrap();
+        assert!(check_tokens(&tokens, true).is_err());
+    }
+
+    #[test]
+    fn test_scan_errors() {
+        let error = |input: &str| scan(input).1;
+        assert_eq!(error("SUM([Sales]) * 2"), None);
+        assert_eq!(
+            error("SUM([Sales]) @ 2"),
+            Some((
+                "Unexpected character '@' at character 13".to_string(),
+                13..14
+            ))
+        );
+        assert_eq!(
+            error("1 + SUM([Sales) + 2"),
+            Some((
+                "Unterminated column name starting at character 8".to_string(),
+                8..9
+            ))
+        );
+        assert_eq!(
+            error("[Qty] * 1.2.5"),
+            Some(("Invalid number '1.2.5' at character 8".to_string(), 8..13))
+        );
+        // Only the first problem is reported
+        assert_eq!(error("€ 1 @").map(|(_, chars)| chars), Some(0..1));
+    }
+
+    #[test]
+    fn test_column_lengths() {
+        assert_eq!(check(r#""A" => [1, 2], "B" => [null, 3]"#), Ok(()));
+        assert_eq!(check(""), Ok(()));
+        assert_eq!(
+            check(r#""A" => [1, 2], "B" => [3], "C" => [4, 5], "D" => []"#),
+            Err(
+                r#"column "B" has 1 values, but "A" has 2; column "D" has 0 values, but "A" has 2"#
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_expand_table() {
+        let expanded = expand_table(quote! { "Sales" => [1.5, null], "Qty": int => [2, 3] });
+        assert_eq!(
+            expanded.to_string(),
+            quote! {{
+                let mut table = ::dax_rust::Table::new();
+                table.add_column(
+                    "Sales".to_string(),
+                    vec![::dax_rust::Value::from(1.5), ::dax_rust::Value::Null]
+                ).expect("table! columns must all have the same length");
+                table.add_column(
+                    "Qty".to_string(),
+                    vec![::dax_rust::Value::Integer(2), ::dax_rust::Value::Integer(3)]
+                ).expect("table! columns must all have the same length");
+                table
+            }}
+            .to_string()
+        );
+
+        // Each mistake is its own compile_error!, together in a block
+        let expanded = expand_table(quote! { "A" => [1], "B" => [], "C" => [1, 2] });
+        let expanded = expanded.to_string();
+        assert!(expanded.starts_with('{'), "{}", expanded);
+        assert_eq!(expanded.matches("compile_error").count(), 2, "{}", expanded);
+        assert!(expand_table(quote! { "A" => 1 })
+            .to_string()
+            .contains("compile_error"));
+    }
+
+    #[test]
+    fn test_expand_parse_dax() {
+        assert_eq!(
+            expand_parse_dax(quote! { "SUM([Sales])" }, false).to_string(),
+            quote! {{
+                use ::dax_rust::__private::DaxToken;
+                vec![
+                    DaxToken::Function("SUM".to_string()),
+                    DaxToken::ParenOpen,
+                    DaxToken::Column("Sales".to_string()),
+                    DaxToken::ParenClose
+                ]
+            }}
+            .to_string()
+        );
+        let error = expand_parse_dax(quote! { "FISCALQUARTER(1)" }, false).to_string();
+        assert!(
+            error.contains("Unknown function FISCALQUARTER"),
+            "{}",
+            error
+        );
+        let lenient = expand_parse_dax(quote! { "FISCALQUARTER(1)" }, true).to_string();
+        assert!(!lenient.contains("compile_error"), "{}", lenient);
+        assert!(expand_parse_dax(quote! { 42 }, false)
+            .to_string()
+            .contains("compile_error"));
+
+        let expanded = expand_dax(quote! { sales, "SUM([Sales])" }, false).to_string();
+        assert!(
+            expanded.contains("let table : & :: dax_rust :: Table = & sales"),
+            "{}",
+            expanded
+        );
+        let tree = quote! {
+            DaxExpr::BinaryOp {
+                op: BinaryOperator::Divide,
+                left: ::std::boxed::Box::new(DaxExpr::FunctionCall {
+                    name: "SUM".to_string(),
+                    args: vec![DaxExpr::ColumnRef {
+                        table: ::std::option::Option::None,
+                        name: "Sales".to_string(),
+                        span: 4usize..11usize
+                    }],
+                    span: 0usize..12usize
+                }),
+                right: ::std::boxed::Box::new(DaxExpr::Literal(Value::Number(- 2f64))),
+            }
+        };
+        let expanded = expand_dax(quote! { sales, "SUM([Sales]) / -2" }, false).to_string();
+        assert!(expanded.contains(&tree.to_string()), "{}", expanded);
+        // What only the parser catches is a compile error too
+        let error = expand_dax(quote! { sales, "SUM([Sales]) +" }, false).to_string();
+        assert!(error.contains("compile_error"), "{}", error);
+        assert!(
+            error.contains("Unexpected end of expression at character 14"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_measure_order() {
+        let order = |input: TokenStream2| {
+            let input: MeasuresInput = syn::parse2(input).unwrap();
+            measure_order(&input.measures, false)
+                .map(|order| {
+                    order
+                        .iter()
+                        .map(|&i| input.measures[i].0.value())
+                        .collect::<Vec<_>>()
+                })
+                .map_err(|error| error.into_iter().map(|e| e.to_string()).collect::<Vec<_>>())
+        };
+        assert_eq!(
+            order(quote! {
+                on sales:
+                "Margin %" => "DIVIDE([Margin], [Total Sales])",
+                "Margin" => "[Total Sales] - [Total Cost]",
+                "Total Sales" => "SUM([Sales])",
+                "Total Cost" => "SUM([Cost])",
+            }),
+            Ok(vec![
+                "Total Sales".to_string(),
+                "Total Cost".to_string(),
+                "Margin".to_string(),
+                "Margin %".to_string()
+            ])
+        );
+        assert_eq!(
+            order(quote! { on sales: "A" => "[B] + 1", "B" => "[C]", "C" => "[A] * 2" }),
+            Err(vec![
+                "measures refer to each other in a cycle: A -> B -> C -> A".to_string()
+            ])
+        );
+        assert_eq!(
+            order(quote! { on sales: "A" => "[A]" }),
+            Err(vec![
+                "measures refer to each other in a cycle: A -> A".to_string()
+            ])
+        );
+        assert_eq!(
+            order(quote! { on sales: "A" => "SUM([x]", "B" => "1", "B" => "FOO(1)" }),
+            Err(vec![
+                "Unclosed '(' at character 3".to_string(),
+                "measure \"B\" is declared twice".to_string(),
+                "Unknown function FOO at character 0".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_row_form() {
+        let table: TableData = syn::parse_str(
+            r#"columns: ["Product", "Qty": int], rows: [["Apple", 10], ["Pear", null],]"#,
+        )
+        .unwrap();
+        let columns: Vec<_> = table
+            .columns
+            .iter()
+            .map(|col| {
+                let values: Vec<_> = col.values.iter().map(|v| quote!(#v).to_string()).collect();
+                (col.name.value(), col.column_type.is_some(), values)
+            })
+            .collect();
+        assert_eq!(
+            columns,
+            [
+                (
+                    "Product".to_string(),
+                    false,
+                    vec!["\"Apple\"".to_string(), "\"Pear\"".to_string()]
+                ),
+                (
+                    "Qty".to_string(),
+                    true,
+                    vec!["10".to_string(), "null".to_string()]
+                ),
+            ]
+        );
+
+        let error = |input: &str| match syn::parse_str::<TableData>(input) {
+            Ok(_) => panic!("{} parsed", input),
+            Err(error) => error.into_iter().map(|e| e.to_string()).collect::<Vec<_>>(),
+        };
+        assert_eq!(
+            error(r#"columns: ["A", "B"], rows: [[1, 2], [3], [4, 5, 6]]"#),
+            [
+                "row 2 has 1 values, but there are 2 columns",
+                "row 3 has 3 values, but there are 2 columns"
+            ]
+        );
+        assert_eq!(
+            error(r#"columns: ["A"], values: [[1]]"#),
+            ["expected `rows`"]
+        );
+    }
+
+    #[test]
+    fn test_typed_columns() {
+        let tokens = |column_type: &str, cells: &str| {
+            let column_type = syn::Ident::new(column_type, proc_macro2::Span::call_site());
+            let cells: TableData = syn::parse_str(&format!("\"A\" => [{}]", cells)).unwrap();
+            column_tokens(Some(&column_type), &cells.columns[0].values)
+                .map(|cells| {
+                    cells
+                        .iter()
+                        .map(|cell| cell.to_string())
+                        .collect::<Vec<_>>()
+                })
+                .map_err(|error| error.to_string())
+        };
+        assert_eq!(
+            tokens("number", "1, -2, 2.5, null").unwrap(),
+            [
+                ":: dax_rust :: Value :: Number (1f64)",
+                ":: dax_rust :: Value :: Number (- 2f64)",
+                ":: dax_rust :: Value :: Number (2.5)",
+                ":: dax_rust :: Value :: Null"
+            ]
+        );
+        assert_eq!(
+            tokens("int", "3, x").unwrap(),
+            [
+                ":: dax_rust :: Value :: Integer (3)",
+                ":: dax_rust :: Value :: Integer (x)"
+            ]
+        );
+        assert_eq!(tokens("int", "1.5").unwrap_err(), "expected a whole number");
+        assert_eq!(
+            tokens("text", "\"a\"").unwrap(),
+            [":: dax_rust :: Value :: Text ((\"a\") . to_string ())"]
+        );
+        let date = &tokens("date", "\"2024-02-29 09:30:00\"").unwrap()[0];
+        assert!(date.contains("date (2024i64 , 2i64 , 29i64)"), "{}", date);
+        assert!(
+            date.contains("and_hms_opt (9u32 , 30u32 , 0u32)"),
+            "{}",
+            date
+        );
+        assert_eq!(
+            tokens("date", "\"2023-02-29\"").unwrap_err(),
+            "invalid date \"2023-02-29\"; expected a real date as YYYY-MM-DD or YYYY-MM-DD HH:MM:SS"
+        );
+        assert_eq!(
+            tokens("date", "20240105").unwrap_err(),
+            "expected a date such as \"2024-01-05\""
+        );
+        assert!(tokens("decimal", "1")
+            .unwrap_err()
+            .starts_with("unknown column type `decimal`"));
+    }
+
+    #[test]
+    fn test_dax_token_to_tokens() {
+        let tokens = |token: DaxToken| quote!(#token).to_string();
+        assert_eq!(
+            tokens(DaxToken::Column("Size \"L\"".to_string())),
+            r#"DaxToken :: Column ("Size \"L\"" . to_string ())"#
+        );
+        assert_eq!(
+            tokens(DaxToken::QualifiedColumn(
+                "C:\\Data".to_string(),
+                "Größe".to_string()
+            )),
+            r#"DaxToken :: QualifiedColumn ("C:\\Data" . to_string () , "Größe" . to_string ())"#
+        );
+        assert_eq!(
+            tokens(DaxToken::Text("\") + \"".to_string())),
+            r#"DaxToken :: Text ("\") + \"" . to_string ())"#
+        );
+        assert_eq!(
+            tokens(DaxToken::Comparison("<>")),
+            r#"DaxToken :: Comparison ("<>")"#
+        );
+        assert_eq!(tokens(DaxToken::Number(1.5)), "DaxToken :: Number (1.5f64)");
+        assert_eq!(
+            tokens(DaxToken::Number(f64::INFINITY)),
+            "DaxToken :: Number (:: std :: f64 :: INFINITY)"
+        );
+    }
+
+    #[test]
+    fn test_value_tokens() {
+        let tokens = |cell: &str| value_tokens(&syn::parse_str(cell).unwrap()).to_string();
+        assert_eq!(tokens("null"), ":: dax_rust :: Value :: Null");
+        assert_eq!(tokens("None"), ":: dax_rust :: Value :: Null");
+        assert_eq!(
+            tokens("3000000000"),
+            ":: dax_rust :: Value :: Integer (3000000000)"
+        );
+        assert_eq!(tokens("-7"), ":: dax_rust :: Value :: Integer (- 7)");
+        assert_eq!(tokens("7u8"), ":: dax_rust :: Value :: from (7u8)");
+        assert_eq!(tokens("1.5"), ":: dax_rust :: Value :: from (1.5)");
+    }
+}
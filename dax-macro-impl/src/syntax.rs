@@ -0,0 +1,576 @@
+// dax-macro-impl/src/syntax.rs
+
+//! Recursive-descent parser from the `DaxToken` stream to a `Syntax` tree.
+//!
+//! Precedence, loosest first: comparisons, then `+` and `-`, then `*` and `/`,
+//! then a leading sign, then `^`, then a trailing `%`, so `-2^2` is -4 and
+//! `-50%` is -0.5. Binary operators of equal precedence group from the left,
+//! except `^`, which groups from the right as `2^3^2` is `2^9`. Whitespace tokens are skipped, and positions
+//! in errors are character offsets into the expression.
+//!
+//! `VAR <name> = <expr>`, repeated, then `RETURN <expr>` can stand wherever an
+//! expression can.
+//!
+//! An expression can nest at most `MAX_DEPTH` levels deep, each bracket, call,
+//! sign and operator a level, so that neither parsing nor evaluating it can
+//! run out of stack.
+//!
+//! The parser lives here rather than in `dax_rust` so that `dax!` can run it
+//! when it expands. `dax_rust::parser` turns the tree into a `DaxExpr`, and
+//! `dax!` expands to the code that builds that `DaxExpr`.
+use crate::DaxToken;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, ToTokens};
+use std::fmt;
+use std::ops::Range;
+
+/// A parsed expression, shaped as `dax_rust::parser::DaxExpr` is, with the
+/// only literals the parser makes, numbers and text
+#[derive(Debug, Clone, PartialEq)]
+pub enum Syntax {
+    Number(f64),
+    Text(String),
+    /// `[Column]`, or `Table[Column]`, with character offsets of the reference
+    Column {
+        table: Option<String>,
+        name: String,
+        span: Range<usize>,
+    },
+    /// A bare name: a table, a keyword argument such as `DESC`, or a variable
+    Name(String),
+    /// A call, with character offsets from its name to its `)`
+    Call {
+        name: String,
+        args: Vec<Syntax>,
+        span: Range<usize>,
+    },
+    Binary {
+        op: BinaryOperator,
+        left: Box<Syntax>,
+        right: Box<Syntax>,
+    },
+    Unary {
+        op: UnaryOperator,
+        operand: Box<Syntax>,
+    },
+    /// `{<value>, ...}`
+    Table(Vec<Syntax>),
+    /// `VAR <name> = <expr> ... RETURN <expr>`
+    VarBlock {
+        variables: Vec<(String, Syntax)>,
+        body: Box<Syntax>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOperator {
+    Plus,
+    Minus,
+}
+
+impl BinaryOperator {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Power => "^",
+            BinaryOperator::Equal => "=",
+            BinaryOperator::NotEqual => "<>",
+            BinaryOperator::Less => "<",
+            BinaryOperator::LessEqual => "<=",
+            BinaryOperator::Greater => ">",
+            BinaryOperator::GreaterEqual => ">=",
+        }
+    }
+
+    pub fn is_comparison(self) -> bool {
+        matches!(
+            self,
+            BinaryOperator::Equal
+                | BinaryOperator::NotEqual
+                | BinaryOperator::Less
+                | BinaryOperator::LessEqual
+                | BinaryOperator::Greater
+                | BinaryOperator::GreaterEqual
+        )
+    }
+
+    fn from_comparison(symbol: &str) -> Option<Self> {
+        match symbol {
+            "=" => Some(BinaryOperator::Equal),
+            "<>" => Some(BinaryOperator::NotEqual),
+            "<" => Some(BinaryOperator::Less),
+            "<=" => Some(BinaryOperator::LessEqual),
+            ">" => Some(BinaryOperator::Greater),
+            ">=" => Some(BinaryOperator::GreaterEqual),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for BinaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+/// Parse a token stream, with each token's character offsets in the
+/// expression, into a single expression. Unbalanced brackets, misplaced tokens,
+/// anything left over after the expression and nesting more than `MAX_DEPTH`
+/// levels deep are errors: a message naming the offending token's position,
+/// and the characters it covers.
+pub fn parse(tokens: &[(DaxToken, Range<usize>)]) -> Result<Syntax, (String, Range<usize>)> {
+    let mut parser = Parser {
+        tokens: tokens
+            .iter()
+            .filter(|(token, _)| !matches!(token, DaxToken::Whitespace))
+            .collect(),
+        pos: 0,
+        depth: 0,
+    };
+    let expr = parser.expression()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(token) => Err(parser.error(format!("Unexpected '{}' after the expression", token))),
+    }
+}
+
+/// How deep an expression can nest, in brackets, calls, signs and operators
+pub const MAX_DEPTH: usize = 256;
+
+type Error = (String, Range<usize>);
+
+struct Parser<'a> {
+    tokens: Vec<&'a (DaxToken, Range<usize>)>,
+    pos: usize,
+    // How deep in the tree the expression being parsed is
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a DaxToken> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Option<&'a DaxToken> {
+        let token = self.peek();
+        self.pos += 1;
+        token
+    }
+
+    // The current token's characters, or the point where the last one ended
+    // at the end
+    fn span(&self) -> Range<usize> {
+        match self.tokens.get(self.pos) {
+            Some((_, span)) => span.clone(),
+            None => self.end()..self.end(),
+        }
+    }
+
+    // Where the token before the current one ends
+    fn end(&self) -> usize {
+        self.pos
+            .checked_sub(1)
+            .and_then(|last| self.tokens.get(last))
+            .map_or(0, |(_, span)| span.end)
+    }
+
+    fn error(&self, message: String) -> Error {
+        let span = self.span();
+        (format!("{} at character {}", message, span.start), span)
+    }
+
+    // One level deeper for the current token, and an error naming it past
+    // MAX_DEPTH. Each parsing function puts `depth` back as it found it.
+    fn descend(&mut self) -> Result<(), Error> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error(format!(
+                "The expression nests more than {} levels deep",
+                MAX_DEPTH
+            )));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn expression(&mut self) -> Result<Syntax, Error> {
+        if self.keyword("VAR") {
+            return self.var_block();
+        }
+        self.binding(0)
+    }
+
+    // Whether the current token is `word`, written in any case, and not a call
+    fn keyword(&self, word: &str) -> bool {
+        match self.peek() {
+            Some(DaxToken::Function(name)) => {
+                name.eq_ignore_ascii_case(word)
+                    && !matches!(
+                        self.tokens.get(self.pos + 1),
+                        Some((DaxToken::ParenOpen, _))
+                    )
+            }
+            _ => false,
+        }
+    }
+
+    // One or more `VAR <name> = <expr>`, then `RETURN <expr>`. The body reaches
+    // as far as an expression can, as it does in DAX.
+    fn var_block(&mut self) -> Result<Syntax, Error> {
+        self.descend()?;
+        let mut variables = Vec::new();
+        while self.keyword("VAR") {
+            self.pos += 1;
+            let name = match self.peek() {
+                Some(DaxToken::Function(name)) if !self.keyword("RETURN") => name.clone(),
+                _ => return Err(self.error("VAR must be followed by a name".to_string())),
+            };
+            self.pos += 1;
+            match self.peek() {
+                Some(DaxToken::Comparison("=")) => self.pos += 1,
+                _ => return Err(self.error(format!("Expected '=' after VAR {}", name))),
+            }
+            variables.push((name, self.expression()?));
+        }
+        if !self.keyword("RETURN") {
+            return Err(self.error("Expected RETURN after the variables".to_string()));
+        }
+        self.pos += 1;
+        let body = self.expression()?;
+        self.depth -= 1;
+        Ok(Syntax::VarBlock {
+            variables,
+            body: Box::new(body),
+        })
+    }
+
+    // An expression whose operators bind at least as tightly as `min`. A
+    // leading sign takes the operand after it, `^` included; a trailing `%`
+    // takes the value just before it.
+    fn binding(&mut self, min: u8) -> Result<Syntax, Error> {
+        let depth = self.depth;
+        let mut left = match self.sign() {
+            Some(op) => self.signed(op)?,
+            None => self.primary()?,
+        };
+        loop {
+            let power = match self.peek() {
+                Some(DaxToken::Operator('%')) => u8::MAX,
+                Some(token) => match infix(token) {
+                    Some((_, power)) if power >= min => power,
+                    _ => break,
+                },
+                None => break,
+            };
+            left = self.operator(left, power)?;
+        }
+        self.depth = depth;
+        Ok(left)
+    }
+
+    fn signed(&mut self, op: UnaryOperator) -> Result<Syntax, Error> {
+        self.descend()?;
+        self.pos += 1;
+        let operand = self.binding(SIGN)?;
+        self.depth -= 1;
+        Ok(signed(op, operand))
+    }
+
+    // The operator at the current token applied to `left`, with the operand
+    // after it for a binary one. Each makes the tree a level deeper.
+    fn operator(&mut self, left: Syntax, power: u8) -> Result<Syntax, Error> {
+        self.descend()?;
+        let Some((op, _)) = self.next().and_then(infix) else {
+            return Ok(hundredth(left));
+        };
+        // `^` groups from the right, the rest from the left
+        let right = self.binding(if op == BinaryOperator::Power {
+            power
+        } else {
+            power + 1
+        })?;
+        Ok(binary(op, left, right))
+    }
+
+    // The sign the current token is, if it's one. Only where a value is
+    // expected is `-` a sign; after a value it's subtraction.
+    fn sign(&self) -> Option<UnaryOperator> {
+        match self.peek()? {
+            DaxToken::Operator('+') => Some(UnaryOperator::Plus),
+            DaxToken::Operator('-') => Some(UnaryOperator::Minus),
+            _ => None,
+        }
+    }
+
+    // A bracketed expression, table constructor or call, each a level deeper
+    // than the expression around it, or a single token. The nesting cases are
+    // kept in functions of their own so that each level holds little stack.
+    fn primary(&mut self) -> Result<Syntax, Error> {
+        match self.peek() {
+            Some(DaxToken::ParenOpen) => self.group(),
+            Some(DaxToken::BraceOpen) => self.constructor(),
+            Some(DaxToken::Function(_))
+                if matches!(
+                    self.tokens.get(self.pos + 1),
+                    Some((DaxToken::ParenOpen, _))
+                ) =>
+            {
+                self.call()
+            }
+            _ => self.single(),
+        }
+    }
+
+    fn group(&mut self) -> Result<Syntax, Error> {
+        self.descend()?;
+        self.pos += 1;
+        let inner = self.expression()?;
+        self.expect_close(DaxToken::ParenClose)?;
+        self.depth -= 1;
+        Ok(inner)
+    }
+
+    fn constructor(&mut self) -> Result<Syntax, Error> {
+        self.descend()?;
+        self.pos += 1;
+        let items = self.list(DaxToken::BraceClose)?;
+        self.depth -= 1;
+        Ok(Syntax::Table(items))
+    }
+
+    fn call(&mut self) -> Result<Syntax, Error> {
+        let (DaxToken::Function(name), span) = self.tokens[self.pos] else {
+            unreachable!("primary only calls this on a function name");
+        };
+        self.descend()?;
+        self.pos += 2;
+        let args = self.list(DaxToken::ParenClose)?;
+        self.depth -= 1;
+        Ok(Syntax::Call {
+            name: name.clone(),
+            args,
+            span: span.start..self.end(),
+        })
+    }
+
+    // A literal, column reference or bare name
+    fn single(&mut self) -> Result<Syntax, Error> {
+        let Some((token, span)) = self.tokens.get(self.pos).copied() else {
+            return Err(self.error("Unexpected end of expression".to_string()));
+        };
+        let expr = match token {
+            DaxToken::Number(n) => Syntax::Number(*n),
+            DaxToken::Text(text) => Syntax::Text(text.clone()),
+            DaxToken::Column(name) => Syntax::Column {
+                table: None,
+                name: name.clone(),
+                span: span.clone(),
+            },
+            DaxToken::QualifiedColumn(table, name) => Syntax::Column {
+                table: Some(table.clone()),
+                name: name.clone(),
+                span: span.clone(),
+            },
+            DaxToken::Function(name) => Syntax::Name(name.clone()),
+            _ => return Err(self.error(format!("Unexpected '{}'", token))),
+        };
+        self.pos += 1;
+        Ok(expr)
+    }
+
+    // Comma-separated expressions up to a closing bracket, which may come straight away
+    fn list(&mut self, close: DaxToken) -> Result<Vec<Syntax>, Error> {
+        let mut items = Vec::new();
+        if self.peek().is_some_and(|token| same_kind(token, &close)) {
+            self.pos += 1;
+            return Ok(items);
+        }
+        loop {
+            items.push(self.expression()?);
+            match self.peek() {
+                Some(DaxToken::Comma) => self.pos += 1,
+                _ => {
+                    self.expect_close(close)?;
+                    return Ok(items);
+                }
+            }
+        }
+    }
+
+    fn expect_close(&mut self, close: DaxToken) -> Result<(), Error> {
+        match self.peek() {
+            Some(token) if same_kind(token, &close) => {
+                self.next();
+                Ok(())
+            }
+            Some(token) => Err(self.error(format!("Expected '{}' but found '{}'", close, token))),
+            None => Err(self.error(format!("Expected '{}' before the end", close))),
+        }
+    }
+}
+
+// How tightly a leading sign binds: looser than `^`, tighter than `*`
+const SIGN: u8 = 4;
+
+// A binary operator and how tightly it binds
+fn infix(token: &DaxToken) -> Option<(BinaryOperator, u8)> {
+    match token {
+        DaxToken::Comparison(symbol) => BinaryOperator::from_comparison(symbol).map(|op| (op, 1)),
+        DaxToken::Operator('+') => Some((BinaryOperator::Add, 2)),
+        DaxToken::Operator('-') => Some((BinaryOperator::Subtract, 2)),
+        DaxToken::Operator('*') => Some((BinaryOperator::Multiply, 3)),
+        DaxToken::Operator('/') => Some((BinaryOperator::Divide, 3)),
+        DaxToken::Operator('^') => Some((BinaryOperator::Power, 5)),
+        _ => None,
+    }
+}
+
+fn binary(op: BinaryOperator, left: Syntax, right: Syntax) -> Syntax {
+    Syntax::Binary {
+        op,
+        left: Box::new(left),
+        right: Box::new(right),
+    }
+}
+
+// A value with `%` after it, which for a number literal folds into the
+// literal
+fn hundredth(value: Syntax) -> Syntax {
+    match value {
+        Syntax::Number(n) => Syntax::Number(n / 100.0),
+        other => binary(BinaryOperator::Divide, other, Syntax::Number(100.0)),
+    }
+}
+
+// A signed number literal folds into a single literal, so `-2` is the number -2
+fn signed(op: UnaryOperator, operand: Syntax) -> Syntax {
+    match (op, operand) {
+        (UnaryOperator::Plus, Syntax::Number(n)) => Syntax::Number(n),
+        (UnaryOperator::Minus, Syntax::Number(n)) => Syntax::Number(-n),
+        (op, operand) => Syntax::Unary {
+            op,
+            operand: Box::new(operand),
+        },
+    }
+}
+
+fn same_kind(a: &DaxToken, b: &DaxToken) -> bool {
+    std::mem::discriminant(a) == std::mem::discriminant(b)
+}
+
+// The code that builds the `DaxExpr` a tree stands for, with `DaxExpr`,
+// `BinaryOperator`, `UnaryOperator` and `Value` in scope
+impl ToTokens for Syntax {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let range = |span: &Range<usize>| {
+            let (start, end) = (span.start, span.end);
+            quote! { #start..#end }
+        };
+        let table = |table: &Option<String>| match table {
+            Some(table) => quote! { ::std::option::Option::Some(#table.to_string()) },
+            None => quote! { ::std::option::Option::None },
+        };
+        tokens.extend(match self {
+            Syntax::Number(n) => {
+                let number = number_tokens(*n);
+                quote! { DaxExpr::Literal(Value::Number(#number)) }
+            }
+            Syntax::Text(text) => quote! { DaxExpr::Literal(Value::Text(#text.to_string())) },
+            Syntax::Column {
+                table: qualifier,
+                name,
+                span,
+            } => {
+                let (qualifier, span) = (table(qualifier), range(span));
+                quote! {
+                    DaxExpr::ColumnRef { table: #qualifier, name: #name.to_string(), span: #span }
+                }
+            }
+            Syntax::Name(name) => quote! { DaxExpr::Variable(#name.to_string()) },
+            Syntax::Call { name, args, span } => {
+                let span = range(span);
+                quote! {
+                    DaxExpr::FunctionCall { name: #name.to_string(), args: vec![#(#args),*], span: #span }
+                }
+            }
+            Syntax::Binary { op, left, right } => quote! {
+                DaxExpr::BinaryOp {
+                    op: #op,
+                    left: ::std::boxed::Box::new(#left),
+                    right: ::std::boxed::Box::new(#right),
+                }
+            },
+            Syntax::Unary { op, operand } => {
+                let op = match op {
+                    UnaryOperator::Plus => quote! { UnaryOperator::Plus },
+                    UnaryOperator::Minus => quote! { UnaryOperator::Minus },
+                };
+                quote! {
+                    DaxExpr::UnaryOp { op: #op, operand: ::std::boxed::Box::new(#operand) }
+                }
+            }
+            Syntax::Table(items) => quote! { DaxExpr::TableConstructor(vec![#(#items),*]) },
+            Syntax::VarBlock { variables, body } => {
+                let variables = variables
+                    .iter()
+                    .map(|(name, expr)| quote! { (#name.to_string(), #expr) });
+                quote! {
+                    DaxExpr::VarBlock {
+                        variables: vec![#(#variables),*],
+                        body: ::std::boxed::Box::new(#body),
+                    }
+                }
+            }
+        });
+    }
+}
+
+impl ToTokens for BinaryOperator {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        tokens.extend(match self {
+            BinaryOperator::Add => quote! { BinaryOperator::Add },
+            BinaryOperator::Subtract => quote! { BinaryOperator::Subtract },
+            BinaryOperator::Multiply => quote! { BinaryOperator::Multiply },
+            BinaryOperator::Divide => quote! { BinaryOperator::Divide },
+            BinaryOperator::Power => quote! { BinaryOperator::Power },
+            BinaryOperator::Equal => quote! { BinaryOperator::Equal },
+            BinaryOperator::NotEqual => quote! { BinaryOperator::NotEqual },
+            BinaryOperator::Less => quote! { BinaryOperator::Less },
+            BinaryOperator::LessEqual => quote! { BinaryOperator::LessEqual },
+            BinaryOperator::Greater => quote! { BinaryOperator::Greater },
+            BinaryOperator::GreaterEqual => quote! { BinaryOperator::GreaterEqual },
+        });
+    }
+}
+
+// An f64 as an expression. A literal can't be negative or infinite, and a
+// number too big for an f64, such as 1e999, reads as infinity.
+pub(crate) fn number_tokens(n: f64) -> TokenStream2 {
+    let magnitude = if n.is_finite() {
+        proc_macro2::Literal::f64_suffixed(n.abs()).into_token_stream()
+    } else {
+        quote! { ::std::f64::INFINITY }
+    };
+    if n.is_sign_negative() {
+        quote! { -#magnitude }
+    } else {
+        magnitude
+    }
+}
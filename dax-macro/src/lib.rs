@@ -2,84 +2,211 @@
 use proc_macro::TokenStream;
 use quote::quote;
 use syn::parse_macro_input;
+use syn::Result;
 
-// use syn::parse::Parser;
-use syn::{
-    bracketed,
-    parse::{Parse, ParseStream},
-    Result, Token,
-};
+// With the lenient feature, calls to functions that aren't built in are left
+// for registered functions to answer at run time
+const ALLOW_UNKNOWN: bool = cfg!(feature = "lenient");
 
+/// Split a DAX expression into `DaxToken`s at compile time, checking it for
+/// unbalanced brackets, unknown functions and built-ins given the wrong
+/// number of arguments.
 #[proc_macro]
 pub fn parse_dax(input: TokenStream) -> TokenStream {
-    let input = parse_macro_input!(input as syn::LitStr);
-    let dax_str = input.value();
-
-    // Use the implementation from dax_macro_impl
-    let tokens = dax_macro_impl::tokenize(&dax_str);
-
-    let expanded = quote! {{
-        use dax_macro_impl::DaxToken;
-        vec![#(#tokens),*]
-    }};
-
-    TokenStream::from(expanded)
+    dax_macro_impl::expand_parse_dax(input.into(), ALLOW_UNKNOWN).into()
 }
 
-struct TableData {
-    columns: Vec<ColumnDef>,
+/// Evaluate an expression against a table: `dax!(sales, "SUM([Sales])")`.
+///
+/// The expression is checked as with `parse_dax!` and parsed when the macro
+/// expands, so a mistake only the parser finds, such as `"SUM([Sales]) +"`,
+/// is a compile error too. The expansion builds the expression tree the
+/// first time it runs and keeps it, so evaluating it, against the same table
+/// or any other, never tokenizes or parses. A column the table doesn't have
+/// can only be found when it's evaluated, and is a
+/// `DaxError::EvaluationError`.
+#[proc_macro]
+pub fn dax(input: TokenStream) -> TokenStream {
+    dax_macro_impl::expand_dax(input.into(), ALLOW_UNKNOWN).into()
 }
 
-struct ColumnDef {
-    name: syn::LitStr,
-    values: Vec<syn::Expr>,
+/// Define a table's measures, checked at compile time:
+/// `measures! { on sales: "Total" => "SUM([Sales])", "Average" => "[Total] / COUNTROWS()" }`.
+///
+/// Each expression is checked as with `parse_dax!`, and measures that refer
+/// to each other in a cycle are a compile error. The measures are defined in
+/// an order where each comes after those it refers to, and then every other
+/// name they refer to is checked to be a column of the table, so the macro
+/// evaluates to a `Result<(), DaxError>`.
+#[proc_macro]
+pub fn measures(input: TokenStream) -> TokenStream {
+    dax_macro_impl::expand_measures(input.into(), ALLOW_UNKNOWN).into()
 }
 
-impl Parse for TableData {
-    fn parse(input: ParseStream) -> Result<Self> {
-        let mut columns = Vec::new();
+/// Build a `Table` from columns, `"Sales" => [100.0, 150.0]`, or from rows,
+/// `columns: ["Product", "Sales"], rows: [["Apple", 100.0]]`.
+#[proc_macro]
+pub fn table(input: TokenStream) -> TokenStream {
+    dax_macro_impl::expand_table(input.into()).into()
+}
 
-        while !input.is_empty() {
-            let name = input.parse::<syn::LitStr>()?;
-            input.parse::<Token![=>]>()?;
+// A struct's named fields, each with the column it goes in
+fn row_fields(input: &syn::DeriveInput, derive: &str) -> Result<Vec<(syn::Field, syn::LitStr)>> {
+    let fields = match &input.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(fields),
+            ..
+        }) => &fields.named,
+        _ => {
+            let message = format!(
+                "{} can only be derived for a struct with named fields",
+                derive
+            );
+            return Err(syn::Error::new_spanned(&input.ident, message));
+        }
+    };
+    fields
+        .iter()
+        .map(|field| {
+            let ident = field.ident.as_ref().expect("named fields have names");
+            let mut column = syn::LitStr::new(&ident.to_string(), ident.span());
+            for attr in field
+                .attrs
+                .iter()
+                .filter(|attr| attr.path().is_ident("dax"))
+            {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("rename") {
+                        column = meta.value()?.parse()?;
+                        Ok(())
+                    } else {
+                        Err(meta.error("expected `rename = \"...\"`"))
+                    }
+                })?;
+            }
+            Ok((field.clone(), column))
+        })
+        .collect()
+}
 
-            let content;
-            bracketed!(content in input);
-            let values = content.parse_terminated(syn::Expr::parse, Token![,])?;
+// The `T` of a field declared as `Option<T>`
+fn option_inner(ty: &syn::Type) -> Option<&syn::Type> {
+    let syn::Type::Path(path) = ty else {
+        return None;
+    };
+    let last = path.path.segments.last()?;
+    if last.ident != "Option" {
+        return None;
+    }
+    match &last.arguments {
+        syn::PathArguments::AngleBracketed(args) => match args.args.first()? {
+            syn::GenericArgument::Type(inner) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
 
-            columns.push(ColumnDef {
-                name,
-                values: values.into_iter().collect(),
-            });
+/// Derive `dax_rust::IntoTable` for a struct with named fields, each of a
+/// type with a `From` conversion into `Value`, such as `f64`, `String`,
+/// `bool` or an `Option` of one. Each field is a column, named after it or
+/// after `#[dax(rename = "Unit Price")]`.
+#[proc_macro_derive(IntoTable, attributes(dax))]
+pub fn derive_into_table(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as syn::DeriveInput);
+    let fields = match row_fields(&input, "IntoTable") {
+        Ok(fields) => fields,
+        Err(error) => return error.to_compile_error().into(),
+    };
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let idents: Vec<_> = fields.iter().map(|(field, _)| &field.ident).collect();
+    let columns = fields.iter().map(|(_, column)| column);
 
-            if !input.is_empty() {
-                input.parse::<Token![,]>()?;
+    let expanded = quote! {
+        impl #impl_generics ::dax_rust::IntoTable for #name #type_generics #where_clause {
+            fn into_table(rows: impl ::std::iter::IntoIterator<Item = Self>) -> ::dax_rust::Table {
+                #(let mut #idents = ::std::vec::Vec::new();)*
+                for row in rows {
+                    #(#idents.push(::dax_rust::Value::from(row.#idents));)*
+                }
+                let mut table = ::dax_rust::Table::new();
+                #(
+                    table
+                        .add_column(#columns.to_string(), #idents)
+                        .expect("every field has a value in every row");
+                )*
+                table
             }
         }
-
-        Ok(TableData { columns })
-    }
+    };
+    TokenStream::from(expanded)
 }
 
-#[proc_macro]
-pub fn table(input: TokenStream) -> TokenStream {
-    let table_data = parse_macro_input!(input as TableData);
-
-    let column_names = table_data.columns.iter().map(|col| &col.name);
-    let column_values = table_data.columns.iter().map(|col| &col.values);
+/// Derive `dax_rust::FromTable` for a struct with named fields, each of a
+/// type with a `TryFrom<Value>` conversion, such as `f64`, `i64`, `String`
+/// or `bool`, or an `Option` of one, which is None for a Null cell. Each
+/// field is read from the column named after it or after
+/// `#[dax(rename = "Unit Price")]`.
+#[proc_macro_derive(FromTable, attributes(dax))]
+pub fn derive_from_table(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as syn::DeriveInput);
+    let fields = match row_fields(&input, "FromTable") {
+        Ok(fields) => fields,
+        Err(error) => return error.to_compile_error().into(),
+    };
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let lookups = fields.iter().map(|(field, column)| {
+        let ident = &field.ident;
+        let field_name = ident.as_ref().map(|ident| ident.to_string());
+        quote! {
+            let #ident = table.get_column(#column).ok_or_else(|| {
+                ::dax_rust::DaxError::from(::std::format!(
+                    "Field {}: column {} not found",
+                    #field_name,
+                    #column
+                ))
+            })?;
+        }
+    });
+    let conversions = fields.iter().map(|(field, _)| {
+        let ident = &field.ident;
+        let field_name = ident.as_ref().map(|ident| ident.to_string());
+        let convert = match option_inner(&field.ty) {
+            Some(inner) => quote! {
+                match value {
+                    ::dax_rust::Value::Null => ::std::result::Result::Ok(::std::option::Option::None),
+                    value => <#inner as ::std::convert::TryFrom<::dax_rust::Value>>::try_from(value)
+                        .map(::std::option::Option::Some),
+                }
+            },
+            None => {
+                let ty = &field.ty;
+                quote! { <#ty as ::std::convert::TryFrom<::dax_rust::Value>>::try_from(value) }
+            }
+        };
+        quote! {
+            #ident: {
+                let value = #ident.get(row).unwrap_or(::dax_rust::Value::Null);
+                #convert.map_err(|e| {
+                    ::dax_rust::DaxError::from(::std::format!("Row {}, field {}: {}", row, #field_name, e))
+                })?
+            }
+        }
+    });
 
     let expanded = quote! {
-        {
-            let mut table = Table::new();
-            #(
-                table.add_column(
-                    #column_names.to_string(),
-                    vec![#(Value::from(#column_values)),*]
-                );
-            )*
-            table
+        impl #impl_generics ::dax_rust::FromTable for #name #type_generics #where_clause {
+            fn from_table(
+                table: &::dax_rust::Table,
+            ) -> ::std::result::Result<::std::vec::Vec<Self>, ::dax_rust::DaxError> {
+                #(#lookups)*
+                (0..table.num_rows())
+                    .map(|row| ::std::result::Result::Ok(#name { #(#conversions),* }))
+                    .collect()
+            }
         }
     };
-
-    expanded.into()
+    TokenStream::from(expanded)
 }
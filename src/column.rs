@@ -0,0 +1,600 @@
+// column.rs
+
+//! How a table stores each column.
+//!
+//! A column of numbers, of text or of booleans is kept densely as a vector of
+//! that type, so aggregating it is a loop over plain `f64`s rather than a
+//! match on every `Value`. Numbers may be mixed with Null, which is tracked in
+//! a validity bitmap; any other mixture of kinds, and a column of only Null,
+//! is kept as `Value`s. Integer, Currency and DateTime columns stay as
+//! `Value`s too, so that every value reads back exactly as it went in.
+//!
+//! A text column that repeats its values, such as a country or a status, is
+//! dictionary encoded: each distinct string is kept once, and each row holds
+//! a `u32` code into the dictionary.
+//!
+//! Reading a column gives a `ColumnValues`, which builds each `Value` from
+//! the typed values as it's read rather than copying the column. Only
+//! indexing a table, which hands out a `&Value`, builds the column's values
+//! and keeps them until it next changes.
+use crate::types::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::mem::size_of;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+/// One bit per row, set where the row has a value
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Bitmap {
+    words: Vec<u64>,
+    len: usize,
+    unset: usize,
+}
+
+impl Bitmap {
+    fn push(&mut self, bit: bool) {
+        if self.len.is_multiple_of(64) {
+            self.words.push(0);
+        }
+        if bit {
+            self.words[self.len / 64] |= 1 << (self.len % 64);
+        } else {
+            self.unset += 1;
+        }
+        self.len += 1;
+    }
+
+    pub fn get(&self, index: usize) -> bool {
+        (self.words[index / 64] >> (index % 64)) & 1 == 1
+    }
+
+    fn set(&mut self, index: usize, bit: bool) {
+        match (self.get(index), bit) {
+            (false, true) => self.unset -= 1,
+            (true, false) => self.unset += 1,
+            _ => return,
+        }
+        self.words[index / 64] ^= 1 << (index % 64);
+    }
+
+    /// Number of set bits
+    pub fn count(&self) -> usize {
+        self.len - self.unset
+    }
+}
+
+/// The distinct strings of a dictionary-encoded column, and each one's code
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Dictionary {
+    strings: Vec<String>,
+    codes: HashMap<String, u32>,
+}
+
+impl Dictionary {
+    // The code for a string, adding it if it's new
+    fn encode(&mut self, s: String) -> u32 {
+        if let Some(&code) = self.codes.get(&s) {
+            return code;
+        }
+        let code = self.strings.len() as u32;
+        self.strings.push(s.clone());
+        self.codes.insert(s, code);
+        code
+    }
+
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    fn memory_usage(&self) -> usize {
+        let heap: usize = self.strings.iter().map(String::capacity).sum();
+        self.strings.capacity() * size_of::<String>()
+            + self.codes.capacity() * (size_of::<String>() + size_of::<u32>())
+            + 2 * heap
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Column {
+    /// Numbers, with 0.0 and a cleared bit where the value is Null
+    F64 {
+        values: Vec<f64>,
+        valid: Bitmap,
+        cache: OnceLock<Vec<Value>>,
+    },
+    Str {
+        values: Vec<String>,
+        cache: OnceLock<Vec<Value>>,
+    },
+    /// Text as codes into a dictionary of the distinct strings, which may
+    /// also hold strings no row uses any more
+    Dict {
+        codes: Vec<u32>,
+        dictionary: Dictionary,
+        cache: OnceLock<Vec<Value>>,
+    },
+    Bool {
+        values: Vec<bool>,
+        cache: OnceLock<Vec<Value>>,
+    },
+    Mixed(Vec<Value>),
+}
+
+impl Column {
+    /// Store values in the densest form that holds all of them exactly
+    pub fn from_values(values: Vec<Value>) -> Column {
+        let is = |kind: fn(&Value) -> bool| !values.is_empty() && values.iter().all(kind);
+        if is(|v| matches!(v, Value::Number(_) | Value::Null))
+            && values.iter().any(|v| matches!(v, Value::Number(_)))
+        {
+            let mut valid = Bitmap::default();
+            let numbers = values
+                .iter()
+                .map(|value| {
+                    valid.push(matches!(value, Value::Number(_)));
+                    match value {
+                        Value::Number(n) => *n,
+                        _ => 0.0,
+                    }
+                })
+                .collect();
+            Column::F64 {
+                values: numbers,
+                valid,
+                cache: OnceLock::new(),
+            }
+        } else if is(|v| matches!(v, Value::Text(_))) {
+            Column::text(
+                values
+                    .into_iter()
+                    .map(|value| match value {
+                        Value::Text(s) => s,
+                        _ => unreachable!("checked above"),
+                    })
+                    .collect(),
+            )
+        } else if is(|v| matches!(v, Value::Boolean(_))) {
+            Column::Bool {
+                values: values
+                    .iter()
+                    .map(|value| matches!(value, Value::Boolean(true)))
+                    .collect(),
+                cache: OnceLock::new(),
+            }
+        } else {
+            Column::Mixed(values)
+        }
+    }
+
+    // A text column, dictionary encoded if at most half its values are distinct
+    fn text(values: Vec<String>) -> Column {
+        let mut dictionary = Dictionary::default();
+        let mut codes = Vec::with_capacity(values.len());
+        for s in &values {
+            codes.push(dictionary.encode(s.clone()));
+            if dictionary.strings.len() * 2 > values.len() {
+                return Column::Str {
+                    values,
+                    cache: OnceLock::new(),
+                };
+            }
+        }
+        Column::Dict {
+            codes,
+            dictionary,
+            cache: OnceLock::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Column::F64 { values, .. } => values.len(),
+            Column::Str { values, .. } => values.len(),
+            Column::Dict { codes, .. } => codes.len(),
+            Column::Bool { values, .. } => values.len(),
+            Column::Mixed(values) => values.len(),
+        }
+    }
+
+    /// The column as `Value`s, built once and kept until it next changes.
+    /// Only indexing needs this; everything else reads a `ColumnValues`
+    pub fn cached_values(&self) -> &Vec<Value> {
+        match self {
+            Column::Mixed(values) => values,
+            Column::F64 { cache, .. }
+            | Column::Str { cache, .. }
+            | Column::Dict { cache, .. }
+            | Column::Bool { cache, .. } => {
+                cache.get_or_init(|| (0..self.len()).map(|row| self.typed_value(row)).collect())
+            }
+        }
+    }
+
+    pub fn into_values(self) -> Vec<Value> {
+        match self {
+            Column::Mixed(values) => values,
+            Column::F64 { ref cache, .. }
+            | Column::Str { ref cache, .. }
+            | Column::Dict { ref cache, .. }
+            | Column::Bool { ref cache, .. } => match cache.get() {
+                Some(values) => values.clone(),
+                None => (0..self.len()).map(|row| self.typed_value(row)).collect(),
+            },
+        }
+    }
+
+    /// One value, without building the whole column as `Value`s
+    pub fn value(&self, row: usize) -> Option<Value> {
+        match self {
+            Column::Mixed(values) => values.get(row).cloned(),
+            _ if row < self.len() => Some(self.typed_value(row)),
+            _ => None,
+        }
+    }
+
+    fn typed_value(&self, row: usize) -> Value {
+        match self {
+            Column::F64 { values, valid, .. } if valid.get(row) => Value::Number(values[row]),
+            Column::F64 { .. } => Value::Null,
+            Column::Str { values, .. } => Value::Text(values[row].clone()),
+            Column::Dict {
+                codes, dictionary, ..
+            } => Value::Text(dictionary.strings[codes[row] as usize].clone()),
+            Column::Bool { values, .. } => Value::Boolean(values[row]),
+            Column::Mixed(values) => values[row].clone(),
+        }
+    }
+
+    /// The codes of a dictionary-encoded column, and the strings they stand for
+    pub fn codes(&self) -> Option<(&[u32], &[String])> {
+        match self {
+            Column::Dict {
+                codes, dictionary, ..
+            } => Some((codes, &dictionary.strings)),
+            _ => None,
+        }
+    }
+
+    /// Roughly how many bytes the column takes, counting the spare capacity
+    /// of its vectors, the text its strings own, and the `Value`s built for
+    /// it if there are any
+    pub fn memory_usage(&self) -> usize {
+        let values = |values: &[Value], capacity: usize| {
+            let text: usize = values
+                .iter()
+                .map(|value| match value {
+                    Value::Text(s) => s.capacity(),
+                    _ => 0,
+                })
+                .sum();
+            capacity * size_of::<Value>() + text
+        };
+        let strings = |strings: &Vec<String>| {
+            strings.capacity() * size_of::<String>()
+                + strings.iter().map(String::capacity).sum::<usize>()
+        };
+        let (data, cache) = match self {
+            Column::F64 {
+                values,
+                valid,
+                cache,
+            } => (
+                values.capacity() * size_of::<f64>() + valid.words.capacity() * size_of::<u64>(),
+                cache,
+            ),
+            Column::Str { values, cache } => (strings(values), cache),
+            Column::Dict {
+                codes,
+                dictionary,
+                cache,
+            } => (
+                codes.capacity() * size_of::<u32>() + dictionary.memory_usage(),
+                cache,
+            ),
+            Column::Bool { values, cache } => (values.capacity(), cache),
+            Column::Mixed(list) => return values(list, list.capacity()),
+        };
+        data + cache.get().map_or(0, |list| values(list, list.capacity()))
+    }
+
+    /// The numbers of a numeric column, and which of them aren't Null
+    pub fn numbers(&self) -> Option<(&[f64], &Bitmap)> {
+        match self {
+            Column::F64 { values, valid, .. } => Some((values, valid)),
+            _ => None,
+        }
+    }
+
+    /// Replace one value, keeping the column typed if the value fits
+    pub fn set(&mut self, row: usize, value: Value) {
+        self.clear_cache();
+        match (&mut *self, value) {
+            (Column::F64 { values, valid, .. }, Value::Number(n)) => {
+                values[row] = n;
+                valid.set(row, true);
+            }
+            (Column::F64 { values, valid, .. }, Value::Null) => {
+                values[row] = 0.0;
+                valid.set(row, false);
+            }
+            (Column::Str { values, .. }, Value::Text(s)) => values[row] = s,
+            (
+                Column::Dict {
+                    codes, dictionary, ..
+                },
+                Value::Text(s),
+            ) => codes[row] = dictionary.encode(s),
+            (Column::Bool { values, .. }, Value::Boolean(b)) => values[row] = b,
+            (Column::Mixed(values), value) => values[row] = value,
+            (column, value) => {
+                let mut values = std::mem::replace(column, Column::Mixed(Vec::new())).into_values();
+                values[row] = value;
+                *column = Column::from_values(values);
+            }
+        }
+    }
+
+    /// Add a value at the end, keeping the column typed if the value fits
+    pub fn push(&mut self, value: Value) {
+        self.clear_cache();
+        match (&mut *self, value) {
+            (Column::F64 { values, valid, .. }, Value::Number(n)) => {
+                values.push(n);
+                valid.push(true);
+            }
+            (Column::F64 { values, valid, .. }, Value::Null) => {
+                values.push(0.0);
+                valid.push(false);
+            }
+            (Column::Str { values, .. }, Value::Text(s)) => values.push(s),
+            (
+                Column::Dict {
+                    codes, dictionary, ..
+                },
+                Value::Text(s),
+            ) => codes.push(dictionary.encode(s)),
+            (Column::Bool { values, .. }, Value::Boolean(b)) => values.push(b),
+            (Column::Mixed(values), value) => {
+                // A column that had been empty or all Null may now hold a single kind
+                let retype = !matches!(value, Value::Null)
+                    && values.iter().all(|value| matches!(value, Value::Null));
+                values.push(value);
+                if retype {
+                    *self = Column::from_values(std::mem::take(values));
+                }
+            }
+            (column, value) => {
+                let mut values = std::mem::replace(column, Column::Mixed(Vec::new())).into_values();
+                values.push(value);
+                *column = Column::from_values(values);
+            }
+        }
+    }
+
+    /// Add Nulls at the end until the column has `len` values
+    pub fn pad_to(&mut self, len: usize) {
+        while self.len() < len {
+            self.push(Value::Null);
+        }
+    }
+
+    /// A new column of the given rows, in order, with Null for rows past the end
+    pub fn take(&self, rows: &[usize]) -> Column {
+        let in_range = rows.iter().all(|&row| row < self.len());
+        match self {
+            Column::F64 { values, valid, .. } if in_range => {
+                let mut taken = Bitmap::default();
+                let numbers = rows
+                    .iter()
+                    .map(|&row| {
+                        taken.push(valid.get(row));
+                        values[row]
+                    })
+                    .collect();
+                Column::F64 {
+                    values: numbers,
+                    valid: taken,
+                    cache: OnceLock::new(),
+                }
+            }
+            Column::Str { values, .. } if in_range => Column::Str {
+                values: rows.iter().map(|&row| values[row].clone()).collect(),
+                cache: OnceLock::new(),
+            },
+            Column::Dict {
+                codes, dictionary, ..
+            } if in_range => Column::Dict {
+                codes: rows.iter().map(|&row| codes[row]).collect(),
+                dictionary: dictionary.clone(),
+                cache: OnceLock::new(),
+            },
+            Column::Bool { values, .. } if in_range => Column::Bool {
+                values: rows.iter().map(|&row| values[row]).collect(),
+                cache: OnceLock::new(),
+            },
+            _ => Column::from_values(
+                rows.iter()
+                    .map(|&row| self.value(row).unwrap_or(Value::Null))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Store the column as compactly as it can be: without spare capacity,
+    /// built `Value`s or unused dictionary strings, and typed if it now fits
+    pub fn shrink_to_fit(&mut self) {
+        self.clear_cache();
+        match self {
+            Column::F64 { values, valid, .. } => {
+                values.shrink_to_fit();
+                valid.words.shrink_to_fit();
+            }
+            Column::Str { values, .. } => {
+                values.shrink_to_fit();
+                values.iter_mut().for_each(String::shrink_to_fit);
+                *self = Column::text(std::mem::take(values));
+            }
+            Column::Dict {
+                codes, dictionary, ..
+            } => {
+                // Renumber the strings rows still use, in order of first use
+                let mut compact = Dictionary::default();
+                let mut renumbered: Vec<Option<u32>> = vec![None; dictionary.len()];
+                for code in codes.iter_mut() {
+                    let new = renumbered[*code as usize].get_or_insert_with(|| {
+                        compact.encode(dictionary.strings[*code as usize].clone())
+                    });
+                    *code = *new;
+                }
+                codes.shrink_to_fit();
+                compact.strings.shrink_to_fit();
+                compact.codes.shrink_to_fit();
+                *dictionary = compact;
+            }
+            Column::Bool { values, .. } => values.shrink_to_fit(),
+            Column::Mixed(values) => {
+                *self = Column::from_values(std::mem::take(values));
+                if let Column::Mixed(values) = self {
+                    values.shrink_to_fit();
+                }
+            }
+        }
+    }
+
+    // Forget the `Value`s built for a typed column, before it changes
+    fn clear_cache(&mut self) {
+        match self {
+            Column::F64 { cache, .. }
+            | Column::Str { cache, .. }
+            | Column::Dict { cache, .. }
+            | Column::Bool { cache, .. } => {
+                cache.take();
+            }
+            Column::Mixed(_) => {}
+        }
+    }
+}
+
+/// A column's values, borrowed from the table. Each `Value` is built from
+/// however the column is stored as it's read, so a column of numbers or of
+/// text isn't copied to be read.
+#[derive(Clone, Copy)]
+pub struct ColumnValues<'a> {
+    column: &'a Column,
+}
+
+impl<'a> ColumnValues<'a> {
+    pub(crate) fn new(column: &'a Column) -> Self {
+        ColumnValues { column }
+    }
+
+    pub fn len(&self) -> usize {
+        self.column.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.column.len() == 0
+    }
+
+    /// The value of one row, or None past the end
+    pub fn get(&self, row: usize) -> Option<Value> {
+        self.column.value(row)
+    }
+
+    /// The values in order
+    pub fn iter(&self) -> ColumnIter<'a> {
+        ColumnIter {
+            column: self.column,
+            rows: 0..self.column.len(),
+        }
+    }
+
+    /// The values copied out into a vector
+    pub fn to_vec(&self) -> Vec<Value> {
+        self.iter().collect()
+    }
+
+    /// Whether the value of a row is Null, without building it
+    pub fn is_null(&self, row: usize) -> bool {
+        match self.column {
+            Column::F64 { valid, .. } => !valid.get(row),
+            Column::Mixed(values) => values[row].is_null(),
+            _ => false,
+        }
+    }
+
+    pub(crate) fn column(&self) -> &'a Column {
+        self.column
+    }
+}
+
+impl<'a> IntoIterator for ColumnValues<'a> {
+    type Item = Value;
+    type IntoIter = ColumnIter<'a>;
+
+    fn into_iter(self) -> ColumnIter<'a> {
+        self.iter()
+    }
+}
+
+impl fmt::Debug for ColumnValues<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl PartialEq for ColumnValues<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl PartialEq<[Value]> for ColumnValues<'_> {
+    fn eq(&self, other: &[Value]) -> bool {
+        self.len() == other.len() && self.iter().zip(other).all(|(a, b)| a == *b)
+    }
+}
+
+impl PartialEq<Vec<Value>> for ColumnValues<'_> {
+    fn eq(&self, other: &Vec<Value>) -> bool {
+        *self == **other
+    }
+}
+
+impl<const N: usize> PartialEq<[Value; N]> for ColumnValues<'_> {
+    fn eq(&self, other: &[Value; N]) -> bool {
+        *self == other[..]
+    }
+}
+
+/// The values of a `ColumnValues`, built one at a time
+#[derive(Clone)]
+pub struct ColumnIter<'a> {
+    column: &'a Column,
+    rows: Range<usize>,
+}
+
+impl Iterator for ColumnIter<'_> {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        self.rows.next().and_then(|row| self.column.value(row))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.rows.size_hint()
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Value> {
+        self.rows.nth(n).and_then(|row| self.column.value(row))
+    }
+}
+
+impl DoubleEndedIterator for ColumnIter<'_> {
+    fn next_back(&mut self) -> Option<Value> {
+        self.rows.next_back().and_then(|row| self.column.value(row))
+    }
+}
+
+impl ExactSizeIterator for ColumnIter<'_> {}
@@ -0,0 +1,164 @@
+// compiled.rs
+
+//! Expressions parsed once and evaluated many times.
+//!
+//! `Table::compile` parses an expression and checks the columns it refers to
+//! against the table, so a measure evaluated against a table refreshed every
+//! few seconds pays for tokenizing and parsing only once. A compiled
+//! expression isn't tied to the table it was compiled against: evaluating it
+//! against another table checks that table has the columns it needs first.
+use crate::context::EvalContext;
+use crate::error::DaxError;
+use crate::functions::FunctionRegistry;
+use crate::parser::{self, DaxExpr};
+use crate::table::{locate, DaxResult};
+use crate::types::Value;
+use crate::Table;
+use dax_macro_impl::{tokenize_spanned, DaxToken};
+use std::ops::Range;
+
+/// A parsed DAX expression and the table columns and measures it refers to.
+///
+/// # Examples
+///
+/// ```
+/// use dax_rust::table::{DaxResult, Table};
+/// use dax_rust::types::Value;
+///
+/// let mut table = Table::new();
+/// table.add_column("Sales".to_string(), vec![Value::Number(100.0)]).unwrap();
+/// let total = table.compile("SUM([Sales])").unwrap();
+///
+/// table.add_column("Sales".to_string(), vec![Value::Number(100.0), Value::Number(50.0)]).unwrap();
+/// assert!(matches!(total.evaluate(&table), Ok(DaxResult::Number(n)) if n == 150.0));
+/// ```
+#[derive(Debug, Clone)]
+pub struct CompiledExpression {
+    expression: String,
+    expr: DaxExpr,
+    columns: Vec<String>,
+}
+
+impl CompiledExpression {
+    /// Evaluate against a table, which needs every column the expression
+    /// refers to but may otherwise differ from the one it was compiled against
+    pub fn evaluate(&self, table: &Table) -> Result<DaxResult, DaxError> {
+        self.run(table, &table.root_context())
+    }
+
+    /// Evaluate against a table, with the functions in `registry` available
+    pub fn evaluate_with(
+        &self,
+        table: &Table,
+        registry: &FunctionRegistry,
+    ) -> Result<DaxResult, DaxError> {
+        let ctx = EvalContext {
+            functions: Some(registry),
+            ..table.root_context()
+        };
+        self.run(table, &ctx)
+    }
+
+    /// The expression as it was written
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    pub fn ast(&self) -> &DaxExpr {
+        &self.expr
+    }
+
+    /// The table columns and measures the expression refers to, in the order
+    /// they appear
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    fn run(&self, table: &Table, ctx: &EvalContext) -> Result<DaxResult, DaxError> {
+        self.check_columns(table)?;
+        table
+            .evaluate_expr(&self.expr, ctx)
+            .map_err(|e| locate(e, &self.expr, &self.expression))
+    }
+
+    fn check_columns(&self, table: &Table) -> Result<(), DaxError> {
+        table
+            .check_table_names(&self.expr)
+            .map_err(|e| locate(e, &self.expr, &self.expression))?;
+        match self
+            .columns
+            .iter()
+            .find(|name| !table.has_column(name) && table.get_measure(name).is_none())
+        {
+            Some(name) => Err(locate(
+                table.unknown_column(name),
+                &self.expr,
+                &self.expression,
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Table {
+    /// Parse an expression once, to be evaluated any number of times.
+    ///
+    /// Every column the expression refers to must be in the table or be one of
+    /// its measures, apart from those the expression makes itself: names given
+    /// to `ADDCOLUMNS` or `SUMMARIZECOLUMNS`, `Value` of a table constructor
+    /// and `Date` of `CALENDAR`.
+    pub fn compile(&self, expression: &str) -> Result<CompiledExpression, DaxError> {
+        let tokens = tokenize_spanned(expression).map_err(DaxError::ParseError)?;
+        let compiled = CompiledExpression::from_tokens(expression, &tokens)?;
+        compiled.check_columns(self)?;
+        Ok(compiled)
+    }
+}
+
+impl CompiledExpression {
+    /// Parse an expression already split into tokens, with each token's
+    /// character offsets in it, as `tokenize_spanned` gives them. Nothing is
+    /// checked against a table until the expression is evaluated.
+    pub fn from_tokens(
+        expression: &str,
+        tokens: &[(DaxToken, Range<usize>)],
+    ) -> Result<CompiledExpression, DaxError> {
+        Ok(CompiledExpression::from_ast(
+            expression,
+            parser::parse(tokens)?,
+        ))
+    }
+
+    /// An expression already parsed, with the text it was parsed from, which
+    /// errors quote. Nothing is checked against a table until the expression
+    /// is evaluated; `dax!` expands to this, with the tree built at compile
+    /// time.
+    pub fn from_ast(expression: &str, expr: DaxExpr) -> CompiledExpression {
+        let columns = expr
+            .columns()
+            .into_iter()
+            .filter(|name| !introduces(&expr, name))
+            .collect();
+        CompiledExpression {
+            expression: expression.to_string(),
+            expr,
+            columns,
+        }
+    }
+}
+
+// Whether a column comes from a table the expression builds, rather than from
+// the table it's evaluated against
+fn introduces(expr: &DaxExpr, column: &str) -> bool {
+    expr.find(&|node| match node {
+        DaxExpr::Literal(Value::Text(name)) => name == column,
+        DaxExpr::TableConstructor(_) => column == "Value",
+        DaxExpr::FunctionCall { name, .. } => {
+            column == "Date"
+                && (name.eq_ignore_ascii_case("CALENDAR")
+                    || name.eq_ignore_ascii_case("CALENDARAUTO"))
+        }
+        _ => false,
+    })
+    .is_some()
+}
@@ -0,0 +1,340 @@
+// context.rs
+
+//! Evaluation state threaded through DAX expressions.
+//!
+//! A filter context is a list of filters, each of which is a small table: a set
+//! of columns plus the combinations of values allowed on them. A row is visible
+//! when its values pass every filter. This mirrors how CALCULATE filter
+//! arguments behave in DAX, where each argument is a table of allowed values.
+use crate::explain::Tracer;
+use crate::format::literal;
+use crate::functions::FunctionRegistry;
+use crate::parser::DaxExpr;
+use crate::table::{ColumnValues, DaxResult, Measure};
+use crate::types::Value;
+use crate::Table;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+/// State carried through evaluation: the active filters, the rows being
+/// iterated, any user-defined functions, and the measures `[Name]` may refer to
+#[derive(Debug, Clone, Default)]
+pub(crate) struct EvalContext<'a> {
+    /// The rows being iterated, innermost last. An iterator inside another
+    /// pushes its row on top, so the outer rows can still be referred to.
+    pub rows: Vec<RowContext<'a>>,
+    pub filters: FilterContext,
+    pub functions: Option<&'a FunctionRegistry>,
+    pub measures: Option<&'a HashMap<String, Measure>>,
+    /// The measures being evaluated, outermost first
+    pub measure_chain: Vec<&'a String>,
+    /// Where the steps are recorded when explaining an evaluation
+    pub trace: Option<&'a RefCell<Tracer>>,
+    /// Variables defined by a query, in the order they were defined
+    pub variables: Option<&'a [(String, DaxExpr)]>,
+    /// The values of the `VAR`s of the blocks being evaluated, innermost last
+    pub locals: Vec<(&'a str, &'a DaxResult)>,
+}
+
+/// One row being iterated: the table it belongs to and its index there
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RowContext<'a> {
+    pub table: &'a Table,
+    pub row: usize,
+}
+
+impl<'a> EvalContext<'a> {
+    /// The same context, iterating `row` of `table` inside any rows already
+    /// being iterated
+    pub fn with_row<'b>(&self, table: &'b Table, row: usize) -> EvalContext<'b>
+    where
+        'a: 'b,
+    {
+        let mut ctx: EvalContext<'b> = self.clone();
+        ctx.rows.push(RowContext { table, row });
+        ctx
+    }
+
+    /// The same context with more `VAR` values in view, shadowing any
+    /// earlier ones of the same name
+    pub fn with_locals<'b>(&self, locals: &'b [(&'b str, DaxResult)]) -> EvalContext<'b>
+    where
+        'a: 'b,
+    {
+        let mut ctx: EvalContext<'b> = self.clone();
+        ctx.locals
+            .extend(locals.iter().map(|(name, value)| (*name, value)));
+        ctx
+    }
+
+    /// The value of the innermost `VAR` with the name, which is matched
+    /// without regard to case
+    pub fn local(&self, name: &str) -> Option<&'a DaxResult> {
+        self.locals
+            .iter()
+            .rev()
+            .find(|(defined, _)| defined.eq_ignore_ascii_case(name))
+            .map(|(_, value)| *value)
+    }
+
+    /// The index of the innermost row being iterated
+    pub fn row(&self) -> Option<usize> {
+        self.rows.last().map(|current| current.row)
+    }
+
+    /// The current row's cell in a column, from the innermost row context
+    /// whose table has it. None when no row being iterated has the column.
+    pub fn cell(&self, column: &str) -> Option<Value> {
+        self.rows
+            .iter()
+            .rev()
+            .find_map(|current| current.table.cell(column, current.row))
+    }
+}
+
+/// The filters currently narrowing the rows visible to an expression
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FilterContext {
+    filters: Vec<Filter>,
+}
+
+/// A single filter: the allowed value combinations for a set of columns
+#[derive(Debug, Clone)]
+pub(crate) struct Filter {
+    columns: Vec<String>,
+    rows: HashSet<Vec<Value>>,
+}
+
+/// How a CALCULATE filter argument changes the filter context
+#[derive(Debug, Clone)]
+pub(crate) enum FilterModifier {
+    /// A plain filter, replacing existing filters on the same columns
+    Replace(Filter),
+    /// `KEEPFILTERS`: a filter intersected with the existing ones
+    Keep(Filter),
+    /// `REMOVEFILTERS`: clear the filters on some columns, or on all of them
+    Remove(Option<Vec<String>>),
+}
+
+impl Filter {
+    pub fn new(columns: Vec<String>, rows: HashSet<Vec<Value>>) -> Self {
+        Filter { columns, rows }
+    }
+
+    /// The allowed value combinations, in the order of the filter's columns
+    pub fn rows(&self) -> &HashSet<Vec<Value>> {
+        &self.rows
+    }
+
+    /// Build a filter from every row of a table
+    pub fn from_table(table: &Table, columns: Vec<String>) -> Self {
+        let rows = (0..table.num_rows())
+            .map(|row| row_key(table, &columns, row).unwrap_or_default())
+            .collect();
+        Filter { columns, rows }
+    }
+
+    // Drop the given columns, keeping the allowed combinations of the rest
+    fn without_columns(&self, removed: &[String]) -> Filter {
+        let keep: Vec<usize> = (0..self.columns.len())
+            .filter(|&i| !removed.contains(&self.columns[i]))
+            .collect();
+        Filter {
+            columns: keep.iter().map(|&i| self.columns[i].clone()).collect(),
+            rows: self
+                .rows
+                .iter()
+                .map(|row| keep.iter().map(|&i| row[i].clone()).collect())
+                .collect(),
+        }
+    }
+}
+
+impl FilterContext {
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+    }
+
+    /// Apply a filter the way CALCULATE does: any existing filter on the same
+    /// columns is replaced rather than combined
+    pub fn replace(&mut self, filter: Filter) {
+        self.remove(Some(&filter.columns));
+        self.filters.push(filter);
+    }
+
+    /// Add a filter alongside the existing ones, so both must pass
+    pub fn keep(&mut self, filter: Filter) {
+        self.filters.push(filter);
+    }
+
+    /// Remove filtering on the given columns, or every filter when `None`
+    pub fn remove(&mut self, columns: Option<&[String]>) {
+        let Some(columns) = columns else {
+            self.filters.clear();
+            return;
+        };
+        self.filters = self
+            .filters
+            .iter()
+            .filter_map(|existing| {
+                if !existing.columns.iter().any(|c| columns.contains(c)) {
+                    return Some(existing.clone());
+                }
+                let narrowed = existing.without_columns(columns);
+                (!narrowed.columns.is_empty()).then_some(narrowed)
+            })
+            .collect();
+    }
+
+    /// Apply CALCULATE filter arguments: removals first, then the filters in order
+    pub fn apply(&mut self, modifiers: Vec<FilterModifier>) {
+        let (removals, filters): (Vec<_>, Vec<_>) = modifiers
+            .into_iter()
+            .partition(|modifier| matches!(modifier, FilterModifier::Remove(_)));
+        for modifier in removals.into_iter().chain(filters) {
+            match modifier {
+                FilterModifier::Replace(filter) => self.replace(filter),
+                FilterModifier::Keep(filter) => self.keep(filter),
+                FilterModifier::Remove(columns) => self.remove(columns.as_deref()),
+            }
+        }
+    }
+
+    /// Each filter as its columns and the values it allows, such as
+    /// `[Product] in {"Apple"}`, listing only the first few values of a long filter
+    pub fn describe(&self) -> Vec<String> {
+        const SHOWN: usize = 5;
+        self.filters
+            .iter()
+            .map(|filter| {
+                let columns: Vec<String> =
+                    filter.columns.iter().map(|c| format!("[{}]", c)).collect();
+                let mut values: Vec<String> = filter
+                    .rows
+                    .iter()
+                    .map(|row| {
+                        let cells: Vec<String> = row.iter().map(literal).collect();
+                        match cells.as_slice() {
+                            [cell] => cell.clone(),
+                            _ => format!("({})", cells.join(", ")),
+                        }
+                    })
+                    .collect();
+                values.sort();
+                let more = values.len().saturating_sub(SHOWN);
+                values.truncate(SHOWN);
+                if more > 0 {
+                    values.push(format!("{} more", more));
+                }
+                let columns = match columns.as_slice() {
+                    [column] => column.clone(),
+                    _ => format!("({})", columns.join(", ")),
+                };
+                format!("{} in {{{}}}", columns, values.join(", "))
+            })
+            .collect()
+    }
+
+    /// The rows of a table that pass every filter that applies to it.
+    /// Filters on columns the table doesn't have are ignored.
+    pub fn visible_rows(&self, table: &Table) -> Vec<usize> {
+        let checks: Vec<Check> = self
+            .filters
+            .iter()
+            .filter_map(|filter| Check::new(filter, table))
+            .collect();
+        (0..table.num_rows())
+            .filter(|&row| checks.iter().all(|check| check.allows(row)))
+            .collect()
+    }
+}
+
+// A filter made ready to check the rows of one table
+enum Check<'a> {
+    /// Every column is dictionary encoded, so rows are checked by their
+    /// codes, against the combinations of codes the filter's values have
+    Codes {
+        columns: Vec<&'a [u32]>,
+        allowed: HashSet<Vec<u32>>,
+    },
+    Values {
+        columns: Vec<ColumnValues<'a>>,
+        allowed: &'a HashSet<Vec<Value>>,
+    },
+}
+
+impl<'a> Check<'a> {
+    // None when the table lacks one of the filter's columns
+    fn new(filter: &'a Filter, table: &'a Table) -> Option<Check<'a>> {
+        let columns: Vec<ColumnValues> = filter
+            .columns
+            .iter()
+            .map(|name| table.get_column(name))
+            .collect::<Option<_>>()?;
+        let Some(encoded) = columns
+            .iter()
+            .map(|values| values.column().codes())
+            .collect::<Option<Vec<_>>>()
+        else {
+            return Some(Check::Values {
+                columns,
+                allowed: &filter.rows,
+            });
+        };
+        let lookups: Vec<HashMap<&str, u32>> = encoded
+            .iter()
+            .map(|(_, strings)| {
+                (0..)
+                    .zip(strings.iter())
+                    .map(|(code, s)| (s.as_str(), code))
+                    .collect()
+            })
+            .collect();
+        // A combination with a value no row has can't match, so it's dropped
+        let allowed = filter
+            .rows
+            .iter()
+            .filter_map(|key| {
+                key.iter()
+                    .zip(&lookups)
+                    .map(|(value, lookup)| match value {
+                        Value::Text(s) => lookup.get(s.as_str()).copied(),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .collect();
+        Some(Check::Codes {
+            columns: encoded.into_iter().map(|(codes, _)| codes).collect(),
+            allowed,
+        })
+    }
+
+    fn allows(&self, row: usize) -> bool {
+        match self {
+            Check::Codes { columns, allowed } => {
+                let key: Vec<u32> = columns.iter().map(|codes| codes[row]).collect();
+                allowed.contains(&key)
+            }
+            Check::Values { columns, allowed } => {
+                let key: Vec<Value> = columns
+                    .iter()
+                    .map(|values| values.get(row).unwrap_or(Value::Null))
+                    .collect();
+                allowed.contains(&key)
+            }
+        }
+    }
+}
+
+// Values of the given columns in a row, or None if a column is missing
+fn row_key(table: &Table, columns: &[String], row: usize) -> Option<Vec<Value>> {
+    columns
+        .iter()
+        .map(|name| {
+            table
+                .get_column(name)
+                .map(|column| column.get(row).unwrap_or(Value::Null))
+        })
+        .collect()
+}
@@ -0,0 +1,192 @@
+// datatable.rs
+
+//! `DATATABLE`, a table written out in full in an expression, and the
+//! methods that write a table as one and read it back, for pasting sample
+//! data into Power BI and out again.
+use crate::context::EvalContext;
+use crate::error::DaxError;
+use crate::parser::{self, DaxExpr};
+use crate::table::{scalar_value, ColumnValues, DaxResult};
+use crate::types::{currency_amount, DType, Value};
+use crate::{dates, Table};
+
+// The DAX type each kind of column is declared as
+const TYPES: [(&str, DType); 6] = [
+    ("STRING", DType::Text),
+    ("INTEGER", DType::Integer),
+    ("DOUBLE", DType::Number),
+    ("CURRENCY", DType::Currency),
+    ("BOOLEAN", DType::Boolean),
+    ("DATETIME", DType::Date),
+];
+
+impl Table {
+    /// This table as a DAX `DATATABLE` expression, one row to a line, with
+    /// each column's type the kind of its values.
+    ///
+    /// A column of mixed kinds is written as text, and Null, NaN and the
+    /// infinities as `BLANK()`. Dates are written as ISO text, which is how
+    /// `DATATABLE` takes them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dax_rust::{Table, Value};
+    ///
+    /// let mut table = Table::new();
+    /// table.add_column("Product".to_string(), vec![Value::from("Pear \"Conference\"")]).unwrap();
+    /// table.add_column("Sales".to_string(), vec![Value::Number(2.5)]).unwrap();
+    /// assert_eq!(
+    ///     table.to_datatable_expr(),
+    ///     "DATATABLE(\n    \"Product\", STRING,\n    \"Sales\", DOUBLE,\n    {\n        {\"Pear \"\"Conference\"\"\", 2.5}\n    }\n)"
+    /// );
+    /// assert_eq!(Table::from_datatable_expr(&table.to_datatable_expr()).unwrap(), table);
+    /// ```
+    pub fn to_datatable_expr(&self) -> String {
+        let names = self.column_names();
+        let columns: Vec<(ColumnValues, DType)> = names
+            .iter()
+            .filter_map(|name| {
+                let dtype = match self.dtype(name)? {
+                    DType::Mixed | DType::Null => DType::Text,
+                    dtype => dtype,
+                };
+                Some((self.get_column(name)?, dtype))
+            })
+            .collect();
+
+        let mut expr = String::from("DATATABLE(\n");
+        for (name, (_, dtype)) in names.iter().zip(&columns) {
+            let dax_type = TYPES
+                .iter()
+                .find(|(_, t)| t == dtype)
+                .map_or("STRING", |(name, _)| name);
+            expr.push_str(&format!("    {}, {},\n", text_literal(name), dax_type));
+        }
+        expr.push_str("    {\n");
+        for row in 0..self.num_rows() {
+            let cells: Vec<String> = columns
+                .iter()
+                .map(|(values, dtype)| literal(&values.get(row).unwrap_or(Value::Null), *dtype))
+                .collect();
+            let comma = if row + 1 < self.num_rows() { "," } else { "" };
+            expr.push_str(&format!("        {{{}}}{}\n", cells.join(", "), comma));
+        }
+        expr.push_str("    }\n)");
+        expr
+    }
+
+    /// Read a DAX `DATATABLE` expression into a table, as evaluating it
+    /// would give. `to_datatable_expr` writes one that reads back the same.
+    pub fn from_datatable_expr(expression: &str) -> Result<Table, DaxError> {
+        match parser::parse_str(expression)? {
+            DaxExpr::FunctionCall { name, args, .. } if name.eq_ignore_ascii_case("DATATABLE") => {
+                let args: Vec<&DaxExpr> = args.iter().collect();
+                let empty = Table::new();
+                empty.datatable(&args, &empty.root_context())
+            }
+            _ => Err(DaxError::ParseError(
+                "Expected a DATATABLE expression".to_string(),
+            )),
+        }
+    }
+
+    pub(crate) fn evaluate_datatable(
+        &self,
+        args: &[&DaxExpr],
+        ctx: &EvalContext,
+    ) -> Result<DaxResult, DaxError> {
+        self.datatable(args, ctx).map(DaxResult::Table)
+    }
+
+    // The table DATATABLE's arguments describe: a name and a type for each
+    // column, then the rows in braces, each in braces of its own
+    fn datatable(&self, args: &[&DaxExpr], ctx: &EvalContext) -> Result<Table, DaxError> {
+        let Some((rows, declarations)) = args.split_last() else {
+            return Err("DATATABLE expects columns and rows".into());
+        };
+        if declarations.is_empty() || !declarations.len().is_multiple_of(2) {
+            return Err("DATATABLE expects a name and a type for each column".into());
+        }
+        let mut columns = Vec::new();
+        for pair in declarations.chunks(2) {
+            let name = self.text_argument("DATATABLE", pair[0], ctx)?;
+            let dtype = match pair[1] {
+                DaxExpr::Variable(written) => TYPES
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(written))
+                    .map(|(_, dtype)| *dtype),
+                _ => None,
+            }
+            .ok_or_else(|| {
+                format!(
+                    "DATATABLE column {} needs a type: STRING, INTEGER, DOUBLE, CURRENCY, BOOLEAN or DATETIME",
+                    name
+                )
+            })?;
+            columns.push((name, dtype, Vec::new()));
+        }
+
+        let DaxExpr::TableConstructor(rows) = rows else {
+            return Err("DATATABLE expects its rows in braces".into());
+        };
+        for (i, row) in rows.iter().enumerate() {
+            let cells = match row {
+                DaxExpr::TableConstructor(cells) => cells.as_slice(),
+                // A single column's rows can leave out their own braces
+                cell if columns.len() == 1 => std::slice::from_ref(cell),
+                _ => return Err(format!("DATATABLE row {} isn't in braces", i + 1).into()),
+            };
+            if cells.len() != columns.len() {
+                return Err(format!(
+                    "DATATABLE row {} has {} values for {} columns",
+                    i + 1,
+                    cells.len(),
+                    columns.len()
+                )
+                .into());
+            }
+            for (cell, (name, dtype, values)) in cells.iter().zip(&mut columns) {
+                let value = scalar_value(self.evaluate_expr(cell, ctx)?)
+                    .ok_or("DATATABLE values must be single values")?;
+                let cast = match value {
+                    Value::Null => Some(Value::Null),
+                    value => value.cast(*dtype),
+                };
+                values.push(cast.ok_or_else(|| {
+                    format!(
+                        "DATATABLE row {} has a value for {} that isn't {}",
+                        i + 1,
+                        name,
+                        dtype
+                    )
+                })?);
+            }
+        }
+
+        let mut table = Table::new();
+        for (name, _, values) in columns {
+            table.add_column(name, values)?;
+        }
+        Ok(table)
+    }
+}
+
+// A value as a DATATABLE literal of the column's type
+fn literal(value: &Value, dtype: DType) -> String {
+    match value {
+        Value::Null => "BLANK()".to_string(),
+        Value::Number(n) if !n.is_finite() => "BLANK()".to_string(),
+        Value::Text(text) => text_literal(text),
+        _ if dtype == DType::Text => text_literal(&value.to_string()),
+        Value::Number(n) => n.to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Currency(units) => currency_amount(*units).to_string(),
+        Value::Boolean(b) => (if *b { "TRUE" } else { "FALSE" }).to_string(),
+        Value::DateTime(date) => text_literal(&dates::format(date)),
+    }
+}
+
+fn text_literal(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
@@ -0,0 +1,405 @@
+// dates.rs
+
+//! Date and time helpers backing the DAX date functions.
+//!
+//! DAX stores dates as a serial number of days since 1899-12-30, with the time
+//! of day as the fractional part. Values are kept as `chrono::NaiveDateTime`
+//! here and converted to and from that serial form where DAX semantics need it.
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Weekday};
+use std::collections::HashSet;
+
+/// The day DAX serial date `0` refers to
+pub fn epoch() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(1899, 12, 30)
+        .unwrap()
+        .and_time(NaiveTime::MIN)
+}
+
+/// Build a date like DAX's `DATE`: months and days outside their usual range
+/// roll over into the neighbouring months and years.
+pub fn date(year: i64, month: i64, day: i64) -> Option<NaiveDateTime> {
+    let month = month.checked_sub(1)?;
+    let year = year.checked_add(month.div_euclid(12))?;
+    let month = month.rem_euclid(12) + 1;
+    let first = NaiveDate::from_ymd_opt(i32::try_from(year).ok()?, month as u32, 1)?;
+    first
+        .checked_add_signed(Duration::try_days(day.checked_sub(1)?)?)
+        .map(|d| d.and_time(NaiveTime::MIN))
+}
+
+/// Build a time of day like DAX's `TIME`, wrapping around after 24 hours.
+/// The result sits on the DAX epoch date.
+pub fn time(hour: i64, minute: i64, second: i64) -> Option<NaiveDateTime> {
+    let seconds = hour
+        .checked_mul(3600)?
+        .checked_add(minute.checked_mul(60)?)?
+        .checked_add(second)?
+        .rem_euclid(86_400);
+    epoch().checked_add_signed(Duration::try_seconds(seconds)?)
+}
+
+/// Parse an ISO `YYYY-MM-DD` date, optionally followed by a `HH:MM:SS` time
+pub fn parse_iso(s: &str) -> Option<NaiveDateTime> {
+    if let Ok(d) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Some(d.and_time(NaiveTime::MIN));
+    }
+    ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S"]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(s, fmt).ok())
+}
+
+/// Render a date as ISO `YYYY-MM-DD`, adding the time only when it isn't midnight
+pub fn format(dt: &NaiveDateTime) -> String {
+    if dt.time() == NaiveTime::MIN {
+        dt.format("%Y-%m-%d").to_string()
+    } else {
+        dt.format("%Y-%m-%d %H:%M:%S").to_string()
+    }
+}
+
+/// Convert a date to its DAX serial number
+pub fn to_serial(dt: &NaiveDateTime) -> f64 {
+    let delta = *dt - epoch();
+    delta.num_milliseconds() as f64 / 86_400_000.0
+}
+
+/// Convert a DAX serial number back into a date
+pub fn from_serial(serial: f64) -> Option<NaiveDateTime> {
+    if !serial.is_finite() {
+        return None;
+    }
+    let millis = (serial * 86_400_000.0).round() as i64;
+    epoch().checked_add_signed(Duration::try_milliseconds(millis)?)
+}
+
+/// Day of the week following DAX's `WEEKDAY` return types:
+/// 1 = Sunday (1) to Saturday (7), 2 = Monday (1) to Sunday (7),
+/// 3 = Monday (0) to Sunday (6)
+pub fn weekday(dt: &NaiveDateTime, return_type: i64) -> Option<u32> {
+    let weekday = dt.weekday();
+    match return_type {
+        1 => Some(weekday.number_from_sunday()),
+        2 => Some(weekday.number_from_monday()),
+        3 => Some(weekday.num_days_from_monday()),
+        _ => None,
+    }
+}
+
+/// Week of the year following DAX's `WEEKNUM`: the week containing January 1st
+/// is week 1, with weeks starting on Sunday (return type 1) or Monday (2).
+/// Return type 21 gives the ISO 8601 week number.
+pub fn weeknum(dt: &NaiveDateTime, return_type: i64) -> Option<u32> {
+    let jan1 = NaiveDate::from_ymd_opt(dt.year(), 1, 1)?;
+    let offset = match return_type {
+        1 => jan1.weekday().num_days_from_sunday(),
+        2 => jan1.weekday().num_days_from_monday(),
+        21 => return Some(dt.iso_week().week()),
+        _ => return None,
+    };
+    Some((dt.ordinal0() + offset) / 7 + 1)
+}
+
+/// Interval keywords accepted by `DATEDIFF` and `DATEADD`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interval {
+    Second,
+    Minute,
+    Hour,
+    Day,
+    Week,
+    Month,
+    Quarter,
+    Year,
+}
+
+impl Interval {
+    /// Look up an interval from its DAX keyword
+    pub fn from_name(name: &str) -> Option<Interval> {
+        match name.to_ascii_uppercase().as_str() {
+            "SECOND" => Some(Interval::Second),
+            "MINUTE" => Some(Interval::Minute),
+            "HOUR" => Some(Interval::Hour),
+            "DAY" => Some(Interval::Day),
+            "WEEK" => Some(Interval::Week),
+            "MONTH" => Some(Interval::Month),
+            "QUARTER" => Some(Interval::Quarter),
+            "YEAR" => Some(Interval::Year),
+            _ => None,
+        }
+    }
+}
+
+/// Shift a date by whole months, clamping the day to the end of shorter months
+/// (January 31st plus one month is the last day of February)
+pub fn add_months(dt: &NaiveDateTime, months: i64) -> Option<NaiveDateTime> {
+    let index = (dt.year() as i64 * 12 + dt.month0() as i64).checked_add(months)?;
+    let year = i32::try_from(index.div_euclid(12)).ok()?;
+    let month = index.rem_euclid(12) as u32 + 1;
+    let day = dt.day().min(days_in_month(year, month)?);
+    NaiveDate::from_ymd_opt(year, month, day).map(|d| d.and_time(dt.time()))
+}
+
+/// Last day of the month `months` away from the given date, at midnight
+pub fn end_of_month(dt: &NaiveDateTime, months: i64) -> Option<NaiveDateTime> {
+    let shifted = add_months(dt, months)?;
+    let last = days_in_month(shifted.year(), shifted.month())?;
+    NaiveDate::from_ymd_opt(shifted.year(), shifted.month(), last)
+        .map(|d| d.and_time(NaiveTime::MIN))
+}
+
+/// Number of days in the given month
+pub fn days_in_month(year: i32, month: u32) -> Option<u32> {
+    let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+    let next = if month == 12 {
+        NaiveDate::from_ymd_opt(year + 1, 1, 1)?
+    } else {
+        NaiveDate::from_ymd_opt(year, month + 1, 1)?
+    };
+    Some((next - first).num_days() as u32)
+}
+
+/// First day of the fiscal year containing the date, for a fiscal year ending
+/// in `year_end_month` (12 for calendar years)
+pub fn fiscal_year_start(dt: &NaiveDateTime, year_end_month: i64) -> Option<NaiveDateTime> {
+    year_start(dt, u32::try_from(year_end_month).ok()?, 31)
+}
+
+/// First day of the year containing the date, for a year ending on the given
+/// month and day. Days past the end of the month clamp to its last day.
+pub fn year_start(dt: &NaiveDateTime, end_month: u32, end_day: u32) -> Option<NaiveDateTime> {
+    let year_end = |year: i32| {
+        let day = end_day.min(days_in_month(year, end_month)?);
+        NaiveDate::from_ymd_opt(year, end_month, day)
+    };
+    let this_year_end = year_end(dt.year())?;
+    let previous_end = if dt.date() <= this_year_end {
+        year_end(dt.year() - 1)?
+    } else {
+        this_year_end
+    };
+    previous_end.succ_opt().map(|d| d.and_time(NaiveTime::MIN))
+}
+
+/// Parse a year-end argument such as `"6/30"` into its month and day
+pub fn parse_year_end(text: &str) -> Option<(u32, u32)> {
+    let (month, day) = text.trim().split_once(['/', '-'])?;
+    let month: u32 = month.trim().parse().ok()?;
+    let day: u32 = day.trim().parse().ok()?;
+    ((1..=12).contains(&month) && (1..=31).contains(&day)).then_some((month, day))
+}
+
+/// Count the interval boundaries crossed between two dates, like DAX's `DATEDIFF`.
+/// The result is negative when `end` is before `start`.
+pub fn datediff(start: &NaiveDateTime, end: &NaiveDateTime, interval: Interval) -> i64 {
+    let months = |dt: &NaiveDateTime| dt.year() as i64 * 12 + dt.month0() as i64;
+    let seconds = |dt: &NaiveDateTime| dt.and_utc().timestamp();
+    match interval {
+        Interval::Second => seconds(end) - seconds(start),
+        Interval::Minute => seconds(end).div_euclid(60) - seconds(start).div_euclid(60),
+        Interval::Hour => seconds(end).div_euclid(3600) - seconds(start).div_euclid(3600),
+        Interval::Day => (end.date() - start.date()).num_days(),
+        Interval::Week => {
+            // Weeks start on Sunday
+            let week_start = |dt: &NaiveDateTime| {
+                dt.date() - Duration::days(dt.weekday().num_days_from_sunday() as i64)
+            };
+            (week_start(end) - week_start(start)).num_days() / 7
+        }
+        Interval::Month => months(end) - months(start),
+        Interval::Quarter => months(end).div_euclid(3) - months(start).div_euclid(3),
+        Interval::Year => (end.year() - start.year()) as i64,
+    }
+}
+
+/// Move a date by `count` intervals, clamping month-based shifts like `add_months`
+pub fn shift(dt: &NaiveDateTime, count: i64, interval: Interval) -> Option<NaiveDateTime> {
+    match interval {
+        Interval::Second => dt.checked_add_signed(Duration::try_seconds(count)?),
+        Interval::Minute => dt.checked_add_signed(Duration::try_minutes(count)?),
+        Interval::Hour => dt.checked_add_signed(Duration::try_hours(count)?),
+        Interval::Day => dt.checked_add_signed(Duration::try_days(count)?),
+        Interval::Week => dt.checked_add_signed(Duration::try_weeks(count)?),
+        Interval::Month => add_months(dt, count),
+        Interval::Quarter => add_months(dt, count.checked_mul(3)?),
+        Interval::Year => add_months(dt, count.checked_mul(12)?),
+    }
+}
+
+/// Days treated as the weekend by `NETWORKDAYS`: codes 1 to 7 are pairs of days
+/// starting with Saturday and Sunday, codes 11 to 17 single days starting with Sunday
+pub fn weekend_days(code: i64) -> Option<Vec<Weekday>> {
+    const DAYS: [Weekday; 8] = [
+        Weekday::Sat,
+        Weekday::Sun,
+        Weekday::Mon,
+        Weekday::Tue,
+        Weekday::Wed,
+        Weekday::Thu,
+        Weekday::Fri,
+        Weekday::Sat,
+    ];
+    match code {
+        1..=7 => Some(DAYS[code as usize - 1..=code as usize].to_vec()),
+        11..=17 => Some(vec![DAYS[code as usize - 10]]),
+        _ => None,
+    }
+}
+
+/// Working days from `start` to `end`, both included, skipping weekend days and
+/// holidays. The count is negative when `end` is before `start`.
+pub fn networkdays(
+    start: &NaiveDateTime,
+    end: &NaiveDateTime,
+    weekend: &[Weekday],
+    holidays: &HashSet<NaiveDate>,
+) -> i64 {
+    let (first, last, sign) = if start <= end {
+        (start.date(), end.date(), 1)
+    } else {
+        (end.date(), start.date(), -1)
+    };
+    let days = first
+        .iter_days()
+        .take_while(|day| *day <= last)
+        .filter(|day| !weekend.contains(&day.weekday()) && !holidays.contains(day))
+        .count() as i64;
+    sign * days
+}
+
+/// Fraction of a year between two dates, like DAX's `YEARFRAC`, using the day
+/// count bases 0 (US 30/360), 1 (actual/actual), 2 (actual/360), 3 (actual/365)
+/// and 4 (European 30/360)
+pub fn yearfrac(start: &NaiveDateTime, end: &NaiveDateTime, basis: i64) -> Option<f64> {
+    let (start, end) = if start <= end {
+        (start.date(), end.date())
+    } else {
+        (end.date(), start.date())
+    };
+    let actual_days = (end - start).num_days() as f64;
+    let is_leap = |year: i32| NaiveDate::from_ymd_opt(year, 2, 29).is_some();
+    let days_360 = |d1: u32, d2: u32| {
+        ((end.year() - start.year()) * 360 + (end.month() as i32 - start.month() as i32) * 30)
+            as f64
+            + d2 as f64
+            - d1 as f64
+    };
+
+    match basis {
+        0 => {
+            let last_of_february =
+                |d: NaiveDate| d.month() == 2 && d.succ_opt().is_some_and(|next| next.month() == 3);
+            let (mut d1, mut d2) = (start.day(), end.day());
+            if last_of_february(start) && last_of_february(end) {
+                d2 = 30;
+            }
+            if last_of_february(start) {
+                d1 = 30;
+            }
+            if d2 == 31 && d1 >= 30 {
+                d2 = 30;
+            }
+            if d1 == 31 {
+                d1 = 30;
+            }
+            Some(days_360(d1, d2) / 360.0)
+        }
+        1 => {
+            let year_length = if start.year() == end.year() {
+                if is_leap(start.year()) {
+                    366.0
+                } else {
+                    365.0
+                }
+            } else if end <= add_months(&start.and_time(NaiveTime::MIN), 12)?.date() {
+                // Within a year: count a leap day only if the span covers one
+                let covers_leap_day = [start.year(), end.year()].iter().any(|&year| {
+                    NaiveDate::from_ymd_opt(year, 2, 29).is_some_and(|d| start <= d && d <= end)
+                });
+                if covers_leap_day {
+                    366.0
+                } else {
+                    365.0
+                }
+            } else {
+                // Spanning several years: the average length of the years touched
+                let years = (start.year()..=end.year())
+                    .map(|year| if is_leap(year) { 366.0 } else { 365.0 })
+                    .collect::<Vec<f64>>();
+                years.iter().sum::<f64>() / years.len() as f64
+            };
+            Some(actual_days / year_length)
+        }
+        2 => Some(actual_days / 360.0),
+        3 => Some(actual_days / 365.0),
+        4 => Some(days_360(start.day().min(30), end.day().min(30)) / 360.0),
+        _ => None,
+    }
+}
+
+/// Split a date into the hour, minute and second parts used by `HOUR`, `MINUTE` and `SECOND`
+pub fn time_parts(dt: &NaiveDateTime) -> (u32, u32, u32) {
+    (dt.hour(), dt.minute(), dt.second())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date_rolls_over() {
+        assert_eq!(date(2024, 14, 1), date(2025, 2, 1));
+        assert_eq!(date(2024, 3, 0), date(2024, 2, 29));
+        assert_eq!(date(2024, 0, 1), date(2023, 12, 1));
+    }
+
+    #[test]
+    fn test_out_of_range_arguments() {
+        for (year, month, day) in [
+            (2024, i64::MIN, 1),
+            (2024, i64::MAX, 1),
+            (i64::MAX, 13, 1),
+            (2024, 1, i64::MIN),
+            (2024, 1, i64::MAX),
+        ] {
+            assert_eq!(date(year, month, day), None);
+        }
+        for (hour, minute, second) in [(i64::MAX, 0, 0), (0, i64::MIN, 0), (1, 0, i64::MAX)] {
+            assert_eq!(time(hour, minute, second), None);
+        }
+        assert_eq!(time(25, 0, -1), time(0, 59, 59));
+    }
+
+    #[test]
+    fn test_add_months_clamps_day() {
+        assert_eq!(
+            add_months(&date(2024, 1, 31).unwrap(), 1),
+            date(2024, 2, 29)
+        );
+        assert_eq!(
+            add_months(&date(2023, 1, 31).unwrap(), 1),
+            date(2023, 2, 28)
+        );
+        assert_eq!(
+            add_months(&date(2024, 3, 31).unwrap(), -1),
+            date(2024, 2, 29)
+        );
+    }
+
+    #[test]
+    fn test_add_months_out_of_range() {
+        let start = date(2024, 1, 1).unwrap();
+        for months in [i64::MAX, i64::MIN, 1 << 40] {
+            assert_eq!(add_months(&start, months), None);
+            assert_eq!(end_of_month(&start, months), None);
+            assert_eq!(shift(&start, months, Interval::Month), None);
+            assert_eq!(shift(&start, months, Interval::Year), None);
+        }
+    }
+
+    #[test]
+    fn test_serial_round_trip() {
+        let dt = date(2024, 1, 15).unwrap();
+        assert_eq!(to_serial(&dt), 45306.0);
+        assert_eq!(from_serial(45306.0), Some(dt));
+    }
+}
@@ -0,0 +1,123 @@
+// describe.rs
+
+//! `Table::describe`, a summary of every column in one table.
+use crate::types::Value;
+use crate::Table;
+use std::collections::HashSet;
+
+impl Table {
+    /// A table with a row per column of this one, in column order, and the
+    /// columns `name`, `dtype`, `count`, `nulls`, `distinct`, `min`, `max`,
+    /// `mean` and `stddev`.
+    ///
+    /// `dtype` is the kind every non-Null value has, such as `number` or
+    /// `text`, `mixed` if they differ, or `null` if there are none. `count`
+    /// and `distinct` leave out Nulls. `min` and `max` are given for numbers
+    /// and dates, and `mean` and `stddev`, the sample standard deviation, for
+    /// numbers; they are blank otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dax_rust::{Table, Value};
+    ///
+    /// let mut table = Table::new();
+    /// table.add_column("Sales".to_string(), vec![Value::Number(1.0), Value::Number(3.0), Value::Null]).unwrap();
+    /// let summary = table.describe();
+    /// assert_eq!(summary.get("dtype", 0), Some(Value::from("number")));
+    /// assert_eq!(summary.get("nulls", 0), Some(Value::Integer(1)));
+    /// assert_eq!(summary.get("mean", 0), Some(Value::Number(2.0)));
+    /// ```
+    pub fn describe(&self) -> Table {
+        let rows = self
+            .column_names()
+            .iter()
+            .map(|name| {
+                // One column at a time, so only one is ever copied out
+                let values = self.get_column(name).map(|values| values.to_vec());
+                describe_column(name, &values.unwrap_or_default())
+            })
+            .collect::<Vec<_>>();
+
+        let mut summary = Table::new();
+        for (i, field) in FIELDS.iter().enumerate() {
+            let values = rows.iter().map(|row| row[i].clone()).collect();
+            summary
+                .add_column(field.to_string(), values)
+                .expect("every summary row has every field");
+        }
+        summary
+    }
+}
+
+const FIELDS: [&str; 9] = [
+    "name", "dtype", "count", "nulls", "distinct", "min", "max", "mean", "stddev",
+];
+
+fn describe_column(name: &str, values: &[Value]) -> [Value; 9] {
+    let present: Vec<&Value> = values.iter().filter(|value| !value.is_null()).collect();
+    let kinds: HashSet<&str> = present.iter().map(|value| dtype(value)).collect();
+    let dtype = match kinds.len() {
+        0 => "null",
+        1 => kinds.into_iter().next().unwrap_or_default(),
+        _ => "mixed",
+    };
+    let distinct: HashSet<&Value> = present.iter().copied().collect();
+
+    let numbers: Vec<f64> = present
+        .iter()
+        .filter_map(|value| value.as_number())
+        .collect();
+    let dates: Vec<&Value> = present
+        .iter()
+        .copied()
+        .filter(|value| matches!(value, Value::DateTime(_)))
+        .collect();
+    let (min, max) = if !numbers.is_empty() {
+        let min = numbers.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = numbers.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        (Value::Number(min), Value::Number(max))
+    } else {
+        match (dates.iter().min(), dates.iter().max()) {
+            (Some(&min), Some(&max)) => (min.clone(), max.clone()),
+            _ => (Value::Null, Value::Null),
+        }
+    };
+    let (mean, stddev) = match numbers.len() {
+        0 => (Value::Null, Value::Null),
+        n => {
+            let mean = numbers.iter().sum::<f64>() / n as f64;
+            let stddev = if n > 1 {
+                let squares: f64 = numbers.iter().map(|x| (x - mean).powi(2)).sum();
+                Value::Number((squares / (n - 1) as f64).sqrt())
+            } else {
+                Value::Null
+            };
+            (Value::Number(mean), stddev)
+        }
+    };
+
+    [
+        Value::from(name),
+        Value::from(dtype),
+        Value::Integer(present.len() as i64),
+        Value::Integer((values.len() - present.len()) as i64),
+        Value::Integer(distinct.len() as i64),
+        min,
+        max,
+        mean,
+        stddev,
+    ]
+}
+
+fn dtype(value: &Value) -> &'static str {
+    match value {
+        Value::Number(_) => "number",
+        Value::Integer(_) => "integer",
+        Value::Currency(_) => "currency",
+        Value::Text(_) => "text",
+        Value::Boolean(_) => "boolean",
+        Value::DateTime(_) => "datetime",
+        Value::Null => "null",
+    }
+}
@@ -0,0 +1,432 @@
+// display.rs
+
+//! Rendering a table as a box-drawn grid of text.
+//!
+//! `Display` for `Table` uses `TableDisplayOptions::default()`, which shows
+//! the first ten and last five rows of a long table and cuts long cells short.
+//! `Table::display_with` takes options for the row limit, column width, number
+//! precision, row numbers and how Null reads. The same options give Markdown
+//! and HTML tables, with their special characters escaped.
+//!
+//! Columns are sized by how many terminal cells their text takes rather than
+//! its length, so wide characters such as CJK ideographs keep the borders
+//! lined up.
+use crate::types::Value;
+use crate::Table;
+use std::fmt;
+
+const ELLIPSIS: char = '…';
+
+/// How `Table::display_with` renders a table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableDisplayOptions {
+    /// Most rows to show. A longer table shows its first two thirds of these
+    /// and its last third, with a line saying how many are left out. None
+    /// shows every row.
+    pub max_rows: Option<usize>,
+    /// Widest a column gets, in terminal cells; longer cells end in `…`
+    pub max_col_width: Option<usize>,
+    /// Decimal places for numbers; None writes them as `Value` does
+    pub float_precision: Option<usize>,
+    /// Whether to start each row with its index
+    pub show_row_numbers: bool,
+    /// What a Null cell shows
+    pub null_text: String,
+}
+
+impl Default for TableDisplayOptions {
+    fn default() -> Self {
+        TableDisplayOptions {
+            max_rows: Some(15),
+            max_col_width: Some(40),
+            float_precision: None,
+            show_row_numbers: false,
+            null_text: String::new(),
+        }
+    }
+}
+
+/// A table with the options to render it with; see `Table::display_with`
+#[derive(Debug, Clone)]
+pub struct TableDisplay<'a> {
+    table: &'a Table,
+    options: TableDisplayOptions,
+}
+
+impl Table {
+    /// The table, to render with the given options.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dax_rust::display::TableDisplayOptions;
+    /// use dax_rust::{Table, Value};
+    ///
+    /// let mut table = Table::new();
+    /// table.add_column("Ratio".to_string(), vec![Value::Number(1.0 / 3.0), Value::Null]).unwrap();
+    /// let options = TableDisplayOptions {
+    ///     float_precision: Some(2),
+    ///     null_text: "(blank)".to_string(),
+    ///     ..Default::default()
+    /// };
+    /// let rendered = table.display_with(options).to_string();
+    /// assert!(rendered.contains("    0.33 "));
+    /// assert!(rendered.contains(" (blank) "));
+    /// ```
+    pub fn display_with(&self, options: TableDisplayOptions) -> TableDisplay<'_> {
+        TableDisplay {
+            table: self,
+            options,
+        }
+    }
+}
+
+impl Table {
+    /// The table as a Markdown pipe table; see `TableDisplay::to_markdown`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dax_rust::{Table, Value};
+    ///
+    /// let mut table = Table::new();
+    /// table.add_column("Product".to_string(), vec!["A|B".into()]).unwrap();
+    /// table.add_column("Sales".to_string(), vec![Value::Number(1.5)]).unwrap();
+    /// assert_eq!(
+    ///     table.to_markdown(),
+    ///     "| Product | Sales |\n| :--- | ---: |\n| A\\|B | 1.5 |\n"
+    /// );
+    /// ```
+    pub fn to_markdown(&self) -> String {
+        self.display_with(TableDisplayOptions::default())
+            .to_markdown()
+    }
+
+    /// The table as an HTML table; see `TableDisplay::to_html`
+    pub fn to_html(&self) -> String {
+        self.display_with(TableDisplayOptions::default()).to_html()
+    }
+}
+
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.display_with(TableDisplayOptions::default()).fmt(f)
+    }
+}
+
+// One rendered cell, and whether it lines up on the left
+struct Cell {
+    text: String,
+    left: bool,
+}
+
+impl TableDisplay<'_> {
+    // The rows to show, and how many are left out after the first `head` of them
+    fn shown_rows(&self) -> (Vec<usize>, usize, usize) {
+        let rows = self.table.num_rows();
+        match self.options.max_rows {
+            Some(max) if rows > max => {
+                let tail = max / 3;
+                let head = max - tail;
+                let shown = (0..head).chain(rows - tail..rows).collect();
+                (shown, head, rows - max)
+            }
+            _ => ((0..rows).collect(), rows, 0),
+        }
+    }
+
+    fn render(&self, value: Option<Value>) -> Cell {
+        let text = match &value {
+            None | Some(Value::Null) => self.options.null_text.clone(),
+            Some(Value::Number(n)) if n.is_finite() => match self.options.float_precision {
+                Some(precision) => format!("{:.*}", precision, n),
+                None => Value::Number(*n).to_string(),
+            },
+            Some(value) => value.to_string(),
+        };
+        Cell {
+            text: truncate(text, self.options.max_col_width),
+            left: matches!(value, Some(Value::Text(_))),
+        }
+    }
+}
+
+// The shown part of a table: each column's header and cells, with the row
+// numbers first if asked for, and where the left-out rows go
+struct Grid {
+    columns: Vec<(String, Vec<Cell>)>,
+    rows: usize,
+    head: usize,
+    hidden: usize,
+}
+
+impl Grid {
+    // Whether a column lines up on the left, as a column with any text does
+    fn left(cells: &[Cell]) -> bool {
+        cells.iter().any(|cell| cell.left)
+    }
+
+    fn marker(&self) -> String {
+        format!("{} {} more rows", ELLIPSIS, thousands(self.hidden))
+    }
+}
+
+impl TableDisplay<'_> {
+    fn grid(&self) -> Grid {
+        let (rows, head, hidden) = self.shown_rows();
+        let limit = self.options.max_col_width;
+        let mut columns: Vec<(String, Vec<Cell>)> = Vec::new();
+        if self.options.show_row_numbers {
+            let numbers = rows
+                .iter()
+                .map(|row| Cell {
+                    text: row.to_string(),
+                    left: false,
+                })
+                .collect();
+            columns.push(("#".to_string(), numbers));
+        }
+        for name in self.table.column_names() {
+            let cells = rows
+                .iter()
+                .map(|&row| self.render(self.table.cell(name, row)))
+                .collect();
+            columns.push((truncate(name.clone(), limit), cells));
+        }
+        Grid {
+            columns,
+            rows: rows.len(),
+            head,
+            hidden,
+        }
+    }
+
+    /// The table as a GitHub-flavored Markdown pipe table, with text columns
+    /// aligned left and the rest right
+    pub fn to_markdown(&self) -> String {
+        let grid = self.grid();
+        let mut out = String::new();
+        let line = |cells: Vec<String>| format!("| {} |\n", cells.join(" | "));
+        out += &line(
+            grid.columns
+                .iter()
+                .map(|(name, _)| escape_markdown(name))
+                .collect(),
+        );
+        out += &line(
+            grid.columns
+                .iter()
+                .map(|(_, cells)| if Grid::left(cells) { ":---" } else { "---:" }.to_string())
+                .collect(),
+        );
+        for i in 0..grid.rows {
+            if grid.hidden > 0 && i == grid.head {
+                let mut marker = vec![String::new(); grid.columns.len()];
+                marker[0] = grid.marker();
+                out += &line(marker);
+            }
+            out += &line(
+                grid.columns
+                    .iter()
+                    .map(|(_, cells)| escape_markdown(&cells[i].text))
+                    .collect(),
+            );
+        }
+        out
+    }
+
+    /// The table as an HTML `<table>`, with numbers aligned right
+    pub fn to_html(&self) -> String {
+        let grid = self.grid();
+        let mut out = String::from("<table>\n  <thead>\n    <tr>");
+        for (name, _) in &grid.columns {
+            out += &format!("<th>{}</th>", escape_html(name));
+        }
+        out += "</tr>\n  </thead>\n  <tbody>\n";
+        for i in 0..grid.rows {
+            if grid.hidden > 0 && i == grid.head {
+                out += &format!(
+                    "    <tr><td colspan=\"{}\">{}</td></tr>\n",
+                    grid.columns.len(),
+                    grid.marker()
+                );
+            }
+            out += "    <tr>";
+            for (_, cells) in &grid.columns {
+                let cell = &cells[i];
+                if Grid::left(cells) {
+                    out += &format!("<td>{}</td>", escape_html(&cell.text));
+                } else {
+                    out += &format!(
+                        "<td style=\"text-align: right\">{}</td>",
+                        escape_html(&cell.text)
+                    );
+                }
+            }
+            out += "</tr>\n";
+        }
+        out += "  </tbody>\n</table>\n";
+        out
+    }
+}
+
+impl fmt::Display for TableDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let grid = self.grid();
+        let widths: Vec<usize> = grid
+            .columns
+            .iter()
+            .map(|(name, cells)| {
+                cells
+                    .iter()
+                    .map(|cell| width(&cell.text))
+                    .chain(std::iter::once(width(name)))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        let inner =
+            (widths.iter().map(|width| width + 2).sum::<usize>() + widths.len()).saturating_sub(1);
+        let rule = "─".repeat(inner);
+
+        writeln!(f, "┌{}┐", rule)?;
+        let header: Vec<Cell> = grid
+            .columns
+            .iter()
+            .map(|(name, _)| Cell {
+                text: name.clone(),
+                left: true,
+            })
+            .collect();
+        write_line(f, header.iter(), &widths)?;
+        writeln!(f, "├{}┤", rule)?;
+        for i in 0..grid.rows {
+            if grid.hidden > 0 && i == grid.head {
+                let marker = format!(" {}", grid.marker());
+                let padding = inner.saturating_sub(width(&marker));
+                writeln!(f, "{}{}", marker, " ".repeat(padding))?;
+            }
+            write_line(f, grid.columns.iter().map(|(_, cells)| &cells[i]), &widths)?;
+        }
+        writeln!(f, "└{}┘", rule)
+    }
+}
+
+fn write_line<'c>(
+    f: &mut fmt::Formatter<'_>,
+    cells: impl Iterator<Item = &'c Cell>,
+    widths: &[usize],
+) -> fmt::Result {
+    for (i, (cell, width)) in cells.zip(widths).enumerate() {
+        if i > 0 {
+            write!(f, "│")?;
+        }
+        let padding = " ".repeat(width.saturating_sub(self::width(&cell.text)));
+        if cell.left {
+            write!(f, " {}{} ", cell.text, padding)?;
+        } else {
+            write!(f, " {}{} ", padding, cell.text)?;
+        }
+    }
+    writeln!(f)
+}
+
+// Markdown that shows the text as it is, within one table cell
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '|' => escaped.push_str("\\|"),
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '\n' => escaped.push_str("<br>"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Text cut to fit `limit` terminal cells, ending in an ellipsis if it was cut
+fn truncate(text: String, limit: Option<usize>) -> String {
+    let limit = match limit {
+        Some(limit) if width(&text) > limit => limit,
+        _ => return text,
+    };
+    let mut cut = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        if used + char_width(c) + 1 > limit {
+            break;
+        }
+        used += char_width(c);
+        cut.push(c);
+    }
+    cut.push(ELLIPSIS);
+    cut
+}
+
+// Terminal cells a piece of text takes
+fn width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+// Two cells for East Asian wide and fullwidth characters and emoji, none for
+// combining marks and other zero-width characters, and one for the rest
+fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x0300..=0x036F
+        | 0x0483..=0x0489
+        | 0x0591..=0x05BD
+        | 0x200B..=0x200F
+        | 0x20D0..=0x20FF
+        | 0xFE00..=0xFE0F
+        | 0xFE20..=0xFE2F => 0,
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x3FFFD => 2,
+        _ if c.is_control() => 0,
+        _ => 1,
+    }
+}
+
+// A count with commas between groups of three digits
+fn thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
@@ -6,16 +6,161 @@ use std::fmt;
 pub enum DaxError {
     ParseError(String),
     EvaluationError(String),
+    UnknownFunction {
+        name: String,
+        span: Option<Span>,
+    },
+    /// A column the table doesn't have, with the closest names it does have
+    UnknownColumn {
+        name: String,
+        span: Option<Span>,
+        suggestions: Vec<String>,
+    },
+    /// A table other than the one the expression is evaluated against
+    UnknownTable {
+        name: String,
+        span: Option<Span>,
+    },
+    WrongArity {
+        function: String,
+        /// How many arguments the function takes, such as `2` or `1 to 3`
+        expected: String,
+        got: usize,
+        span: Option<Span>,
+    },
+    /// Measures that end up referring to themselves, as the chain of
+    /// references from the first back to it
+    CircularMeasure {
+        cycle: Vec<String>,
+    },
+    /// A column whose number of values doesn't match the table's rows
+    ColumnLength {
+        column: String,
+        expected: usize,
+        got: usize,
+    },
+    /// A value of one kind where another was needed, such as text for a number
+    TypeMismatch {
+        expected: String,
+        got: String,
+    },
+    /// A record of a CSV file that can't be read, such as one with more
+    /// fields than the header, with the line and the field it's at,
+    /// counting both from 1
+    CsvError {
+        line: usize,
+        column: usize,
+        message: String,
+    },
     IoError(std::io::Error),
 }
 
+/// The part of an expression an error refers to, as character offsets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub expression: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl DaxError {
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            DaxError::UnknownFunction { span, .. }
+            | DaxError::UnknownColumn { span, .. }
+            | DaxError::UnknownTable { span, .. }
+            | DaxError::WrongArity { span, .. } => span.as_ref(),
+            _ => None,
+        }
+    }
+}
+
 impl fmt::Display for DaxError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             DaxError::ParseError(msg) => write!(f, "Parse error: {}", msg),
             DaxError::EvaluationError(msg) => write!(f, "Evaluation error: {}", msg),
+            DaxError::UnknownFunction { name, .. } => {
+                write!(f, "Evaluation error: Unsupported function: {}", name)
+            }
+            DaxError::UnknownColumn {
+                name, suggestions, ..
+            } => {
+                write!(f, "Evaluation error: Column {} not found", name)?;
+                if !suggestions.is_empty() {
+                    write!(f, " (did you mean {}?)", suggestions.join(", "))?;
+                }
+                Ok(())
+            }
+            DaxError::UnknownTable { name, .. } => {
+                write!(f, "Evaluation error: Table {} not found", name)
+            }
+            DaxError::WrongArity {
+                function,
+                expected,
+                got,
+                ..
+            } => write!(
+                f,
+                "Evaluation error: {} requires {} arguments, got {}",
+                function, expected, got
+            ),
+            DaxError::CircularMeasure { cycle } => write!(
+                f,
+                "Evaluation error: Measures refer to each other in a cycle: {}",
+                cycle.join(" -> ")
+            ),
+            DaxError::ColumnLength {
+                column,
+                expected,
+                got,
+            } => write!(
+                f,
+                "Table error: Column {} has {} values, but the table has {} rows",
+                column, got, expected
+            ),
+            DaxError::TypeMismatch { expected, got } => {
+                write!(f, "Type mismatch: expected {}, got {}", expected, got)
+            }
+            DaxError::CsvError {
+                line,
+                column,
+                message,
+            } => write!(
+                f,
+                "Parse error: CSV line {}, column {}: {}",
+                line, column, message
+            ),
             DaxError::IoError(err) => write!(f, "IO error: {}", err),
+        }?;
+        match self.span() {
+            Some(span) => write!(f, "\n{}", span),
+            None => Ok(()),
+        }
+    }
+}
+
+// The line of the expression where the span starts, underlined by carets on
+// the line below
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut line_start = 0;
+        for (i, line) in self.expression.split('\n').enumerate() {
+            let length = line.chars().count();
+            if self.start <= line_start + length || i == self.expression.matches('\n').count() {
+                let column = self.start - line_start;
+                let width = self.end.min(line_start + length).saturating_sub(self.start);
+                return write!(
+                    f,
+                    "  {}\n  {}{}",
+                    line.trim_end(),
+                    " ".repeat(column),
+                    "^".repeat(width.max(1))
+                );
+            }
+            line_start += length + 1;
         }
+        Ok(())
     }
 }
 
@@ -26,3 +171,15 @@ impl From<std::io::Error> for DaxError {
         DaxError::IoError(err)
     }
 }
+
+impl From<String> for DaxError {
+    fn from(msg: String) -> Self {
+        DaxError::EvaluationError(msg)
+    }
+}
+
+impl From<&str> for DaxError {
+    fn from(msg: &str) -> Self {
+        DaxError::EvaluationError(msg.to_string())
+    }
+}
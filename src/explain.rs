@@ -0,0 +1,203 @@
+// explain.rs
+
+//! Evaluation traces, for seeing how an expression arrived at its result.
+//!
+//! `Table::explain_dax` evaluates an expression while recording a step for
+//! each function call, operator and measure it evaluates: the expression, the
+//! filters in effect, how many rows they leave visible and the result. Steps
+//! nest the way the calls do, so a step's children are its arguments as they
+//! were resolved. Anything evaluated once per row, such as a FILTER condition,
+//! is left out rather than repeated for every row.
+use crate::context::EvalContext;
+use crate::error::DaxError;
+use crate::format::{format_inline, literal};
+use crate::parser::{self, DaxExpr};
+use crate::table::{locate, DaxResult};
+use crate::types::Value;
+use crate::Table;
+use std::cell::RefCell;
+use std::fmt;
+
+/// The steps of an evaluation, along with its result.
+///
+/// # Examples
+///
+/// ```
+/// use dax_rust::table::Table;
+/// use dax_rust::types::Value;
+///
+/// let mut table = Table::new();
+/// table.add_column("Product".to_string(), vec!["Apple".into(), "Pear".into()]).unwrap();
+/// table.add_column("Sales".to_string(), vec![Value::Number(10.0), Value::Number(5.0)]).unwrap();
+///
+/// let trace = table
+///     .explain_dax("CALCULATE(SUM([Sales]), [Product] = \"Apple\")")
+///     .unwrap();
+/// assert_eq!(
+///     trace.to_string(),
+///     "CALCULATE(SUM([Sales]), [Product] = \"Apple\") = 10 (2 of 2 rows)\n  \
+///      SUM([Sales]) = 10 (1 of 2 rows, [Product] in {\"Apple\"})\n"
+/// );
+/// ```
+#[derive(Debug)]
+pub struct EvaluationTrace {
+    pub result: DaxResult,
+    pub root: TraceStep,
+}
+
+/// One evaluated call, operator or measure
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceStep {
+    /// The expression, formatted on one line
+    pub expression: String,
+    /// The result, with a table summarized as its number of rows
+    pub result: String,
+    /// The filters in effect, as described by the filter context
+    pub filters: Vec<String>,
+    /// Rows of the table the filters leave visible
+    pub visible_rows: usize,
+    pub total_rows: usize,
+    /// The steps evaluated on the way to this one's result
+    pub children: Vec<TraceStep>,
+}
+
+// Finished steps, grouped by the step still being evaluated they belong to
+#[derive(Debug, Default)]
+pub(crate) struct Tracer {
+    open: Vec<Vec<TraceStep>>,
+}
+
+impl Table {
+    /// Evaluate a DAX expression, recording each step along the way
+    pub fn explain_dax(&self, expression: &str) -> Result<EvaluationTrace, DaxError> {
+        let expr = parser::parse_str(expression)?;
+        let tracer = RefCell::new(Tracer::default());
+        let ctx = EvalContext {
+            trace: Some(&tracer),
+            ..self.root_context()
+        };
+        // The whole expression is always a step, even a lone literal
+        let result = self
+            .check_table_names(&expr)
+            .and_then(|()| self.evaluate_step(&expr, &ctx, &tracer))
+            .map_err(|e| locate(e, &expr, expression))?;
+        let root = tracer
+            .into_inner()
+            .open
+            .pop()
+            .and_then(|mut steps| steps.pop());
+        Ok(EvaluationTrace {
+            result,
+            root: root.expect("the whole expression was traced"),
+        })
+    }
+
+    // Evaluate, recording a step if the expression is one worth showing. Within a
+    // row context nothing more is recorded.
+    pub(crate) fn evaluate_traced(
+        &self,
+        expr: &DaxExpr,
+        ctx: &EvalContext,
+        tracer: &RefCell<Tracer>,
+    ) -> Result<DaxResult, DaxError> {
+        if !ctx.rows.is_empty() {
+            let untraced = EvalContext {
+                trace: None,
+                ..ctx.clone()
+            };
+            return self.evaluate_node(expr, &untraced);
+        }
+        let is_step = match expr {
+            DaxExpr::FunctionCall { .. }
+            | DaxExpr::BinaryOp { .. }
+            | DaxExpr::UnaryOp { .. }
+            | DaxExpr::TableConstructor(_) => true,
+            // A reference to a measure rather than a column
+            DaxExpr::ColumnRef { name, .. } => !self.has_column(name),
+            _ => false,
+        };
+        if is_step {
+            self.evaluate_step(expr, ctx, tracer)
+        } else {
+            self.evaluate_node(expr, ctx)
+        }
+    }
+
+    fn evaluate_step(
+        &self,
+        expr: &DaxExpr,
+        ctx: &EvalContext,
+        tracer: &RefCell<Tracer>,
+    ) -> Result<DaxResult, DaxError> {
+        tracer.borrow_mut().open.push(Vec::new());
+        let result = self.evaluate_node(expr, ctx);
+        let mut tracer = tracer.borrow_mut();
+        let children = tracer.open.pop().unwrap_or_default();
+        let step = TraceStep {
+            expression: format_inline(expr),
+            result: match &result {
+                Ok(value) => summarize(value),
+                Err(e) => format!("error: {}", e),
+            },
+            filters: ctx.filters.describe(),
+            visible_rows: self.visible_rows(ctx).len(),
+            total_rows: self.num_rows(),
+            children,
+        };
+        match tracer.open.last_mut() {
+            Some(parent) => parent.push(step),
+            None => tracer.open.push(vec![step]),
+        }
+        result
+    }
+}
+
+// A scalar as a DAX literal, and a table as its size
+fn summarize(result: &DaxResult) -> String {
+    let value = match result {
+        DaxResult::Table(table) => return format!("Table: {} rows", table.num_rows()),
+        DaxResult::Number(n) => Value::Number(*n),
+        DaxResult::Integer(i) => Value::Integer(*i),
+        DaxResult::Currency(c) => Value::Currency(*c),
+        DaxResult::Text(s) => Value::Text(s.clone()),
+        DaxResult::Boolean(b) => Value::Boolean(*b),
+        DaxResult::DateTime(d) => Value::DateTime(*d),
+        DaxResult::Blank => Value::Null,
+    };
+    literal(&value)
+}
+
+impl fmt::Display for EvaluationTrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.root)
+    }
+}
+
+// One line per step, each indented under the step it's part of
+impl fmt::Display for TraceStep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write_indented(f, 0)
+    }
+}
+
+impl TraceStep {
+    fn write_indented(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        write!(
+            f,
+            "{}{} = {} ({} of {} rows",
+            "  ".repeat(depth),
+            self.expression,
+            self.result,
+            self.visible_rows,
+            self.total_rows
+        )?;
+        for filter in &self.filters {
+            write!(f, ", {}", filter)?;
+        }
+        writeln!(f, ")")?;
+        for child in &self.children {
+            child.write_indented(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
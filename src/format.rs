@@ -0,0 +1,214 @@
+// format.rs
+
+//! Canonical formatting of DAX expressions.
+//!
+//! The formatter works from the parsed expression rather than its text, so
+//! comments and redundant brackets are dropped and the output depends only on
+//! what the expression means. Function names are upper-cased, binary operators
+//! get one space either side, and a call or table constructor that doesn't fit
+//! on one line has each argument on its own indented line. Each `VAR` of a
+//! block and its `RETURN` start their own line. Formatting the
+//! output again gives the same text.
+use crate::error::DaxError;
+use crate::parser::{self, BinaryOperator, DaxExpr, UnaryOperator};
+use crate::types::{currency_amount, Value};
+use chrono::{Datelike, NaiveTime, Timelike};
+use dax_macro_impl::DaxToken;
+
+// Longest a call is kept on one line, counting its indentation
+const MAX_WIDTH: usize = 80;
+const INDENT: &str = "    ";
+
+/// Parse an expression and re-emit it with canonical formatting.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(
+///     dax_rust::format_dax("divide(sum([Sales])-sum([Cost]),sum([Sales]))").unwrap(),
+///     "DIVIDE(SUM([Sales]) - SUM([Cost]), SUM([Sales]))"
+/// );
+/// ```
+pub fn format_dax(expression: &str) -> Result<String, DaxError> {
+    Ok(format_expr(&parser::parse_str(expression)?))
+}
+
+/// Format a parsed expression the way `format_dax` does
+pub fn format_expr(expr: &DaxExpr) -> String {
+    format_at(expr, 0, MAX_WIDTH)
+}
+
+/// Format a parsed expression on a single line, however long
+pub fn format_inline(expr: &DaxExpr) -> String {
+    format_at(expr, 0, usize::MAX)
+}
+
+// How tightly an expression binds, following the parser from comparisons up
+// to single tokens and bracketed lists
+fn precedence(expr: &DaxExpr) -> u8 {
+    match expr {
+        DaxExpr::BinaryOp { op, .. } => match op {
+            op if op.is_comparison() => 1,
+            BinaryOperator::Add | BinaryOperator::Subtract => 2,
+            BinaryOperator::Multiply | BinaryOperator::Divide => 3,
+            _ => 5,
+        },
+        // A negative number reads back as a sign in front of the number
+        DaxExpr::UnaryOp { .. } => 4,
+        DaxExpr::Literal(Value::Number(n)) if n.is_sign_negative() => 4,
+        DaxExpr::Literal(Value::DateTime(d)) if d.time() != NaiveTime::MIN => 2,
+        // Its body reaches as far as it can, so it's bracketed as any operand
+        DaxExpr::VarBlock { .. } => 0,
+        _ => 6,
+    }
+}
+
+// `depth` is how many levels of indentation the expression starts at, which
+// decides whether a call still fits in `width`
+fn format_at(expr: &DaxExpr, depth: usize, width: usize) -> String {
+    match expr {
+        DaxExpr::Literal(value) => literal(value),
+        DaxExpr::ColumnRef {
+            table: Some(table),
+            name,
+            ..
+        } => DaxToken::QualifiedColumn(table.clone(), name.clone()).to_string(),
+        DaxExpr::ColumnRef { name, .. } => DaxToken::Column(name.clone()).to_string(),
+        DaxExpr::Variable(name) => name.clone(),
+        DaxExpr::FunctionCall { name, args, .. } => list(
+            &format!("{}(", name.to_ascii_uppercase()),
+            args,
+            ")",
+            depth,
+            width,
+        ),
+        DaxExpr::TableConstructor(items) => list("{", items, "}", depth, width),
+        DaxExpr::VarBlock { variables, body } => {
+            // Each VAR and the RETURN start a line, and the body is indented
+            // under RETURN; on a single line they're only spaced apart
+            let (line, body_line) = match width {
+                usize::MAX => (" ".to_string(), " ".to_string()),
+                _ => (
+                    format!("\n{}", INDENT.repeat(depth)),
+                    format!("\n{}", INDENT.repeat(depth + 1)),
+                ),
+            };
+            let mut text = String::new();
+            for (name, value) in variables {
+                text.push_str(&format!(
+                    "VAR {} = {}{}",
+                    name,
+                    format_at(value, depth, width),
+                    line
+                ));
+            }
+            format!(
+                "{}RETURN{}{}",
+                text,
+                body_line,
+                format_at(body, depth + 1, width)
+            )
+        }
+        DaxExpr::UnaryOp { op, operand } => {
+            let sign = match op {
+                UnaryOperator::Plus => "+",
+                UnaryOperator::Minus => "-",
+            };
+            let inner = format_at(operand, depth, width);
+            // Never two signs in a row
+            if precedence(operand) < 4 || inner.starts_with(['+', '-']) {
+                format!("{}({})", sign, inner)
+            } else {
+                format!("{}{}", sign, inner)
+            }
+        }
+        DaxExpr::BinaryOp { op, left, right } => {
+            let own = precedence(expr);
+            // `^` groups from the right, so a power as its base needs brackets
+            let left_text = match op {
+                BinaryOperator::Power => operand(left, depth, width, precedence(left) <= own),
+                _ => operand(left, depth, width, precedence(left) < own),
+            };
+            // Other operators group from the left, so an equal right side
+            // needs brackets; the exponent of `^` can only be a power, maybe signed
+            let right_text = match op {
+                BinaryOperator::Power => operand(right, depth, width, !is_signed_value(right)),
+                _ => operand(right, depth, width, precedence(right) <= own),
+            };
+            format!("{} {} {}", left_text, op, right_text)
+        }
+    }
+}
+
+fn is_signed_value(expr: &DaxExpr) -> bool {
+    match expr {
+        DaxExpr::UnaryOp { operand, .. } => precedence(operand) >= 5,
+        _ => precedence(expr) >= 4,
+    }
+}
+
+fn operand(expr: &DaxExpr, depth: usize, width: usize, bracket: bool) -> String {
+    let text = format_at(expr, depth, width);
+    if bracket {
+        format!("({})", text)
+    } else {
+        text
+    }
+}
+
+pub(crate) fn literal(value: &Value) -> String {
+    match value {
+        Value::Text(text) => DaxToken::Text(text.clone()).to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Boolean(b) => format!("{}()", if *b { "TRUE" } else { "FALSE" }),
+        Value::Null => "BLANK()".to_string(),
+        Value::Currency(units) => format!("CURRENCY({})", currency_amount(*units)),
+        Value::DateTime(d) if d.time() == NaiveTime::MIN => {
+            format!("DATE({}, {}, {})", d.year(), d.month(), d.day())
+        }
+        Value::DateTime(d) => format!(
+            "DATE({}, {}, {}) + TIME({}, {}, {})",
+            d.year(),
+            d.month(),
+            d.day(),
+            d.hour(),
+            d.minute(),
+            d.second()
+        ),
+    }
+}
+
+// Items between brackets, on one line if they fit and each on its own line if not
+fn list(open: &str, items: &[DaxExpr], close: &str, depth: usize, width: usize) -> String {
+    // Formatted once on a single line, since formatting each item to fit
+    // and then again on its own line would double the work at every level.
+    // A VAR block takes more than one line whenever the width is limited
+    let inline: Vec<String> = items
+        .iter()
+        .map(|item| format_at(item, depth, usize::MAX))
+        .collect();
+    let one_line = format!("{}{}{}", open, inline.join(", "), close);
+    let has_block = |item: &DaxExpr| {
+        item.find(&|e| matches!(e, DaxExpr::VarBlock { .. }))
+            .is_some()
+    };
+    if !one_line.contains('\n')
+        && INDENT.len() * depth + one_line.len() <= width
+        && (width == usize::MAX || !items.iter().any(has_block))
+    {
+        return one_line;
+    }
+    let indent = INDENT.repeat(depth + 1);
+    let lines: Vec<String> = items
+        .iter()
+        .map(|item| format!("{}{}", indent, format_at(item, depth + 1, width)))
+        .collect();
+    format!(
+        "{}\n{}\n{}{}",
+        open,
+        lines.join(",\n"),
+        INDENT.repeat(depth),
+        close
+    )
+}
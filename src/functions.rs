@@ -0,0 +1,210 @@
+// functions.rs
+
+//! User-defined scalar functions.
+//!
+//! A `FunctionRegistry` knows the names of the built-in functions and holds
+//! any functions registered alongside them, such as a company-specific
+//! `FISCALQUARTER`. Registered functions receive their arguments already
+//! evaluated, so they work anywhere a built-in scalar does, including inside
+//! iterators like `SUMX` and `ADDCOLUMNS`. Names are case-insensitive, like
+//! the built-ins.
+use crate::error::DaxError;
+use crate::table::DaxResult;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+
+/// The signature of a registered function: its evaluated arguments in, a value out
+pub type ScalarFunction = dyn Fn(&[DaxResult]) -> Result<DaxResult, DaxError> + Send + Sync;
+
+// The list lives with the tokenizer so that `parse_dax!` can check names
+// against it at compile time
+pub use dax_macro_impl::BUILTIN_FUNCTIONS;
+
+#[derive(Clone)]
+pub(crate) struct RegisteredFunction {
+    pub min_args: usize,
+    pub max_args: usize,
+    pub function: Arc<ScalarFunction>,
+}
+
+/// Functions available to an expression on top of the built-ins.
+///
+/// # Examples
+///
+/// ```
+/// use dax_rust::functions::FunctionRegistry;
+/// use dax_rust::table::{DaxResult, Table};
+/// use dax_rust::types::Value;
+///
+/// let mut registry = FunctionRegistry::new();
+/// registry
+///     .register("SQUARE", 1..=1, |args| match args[0] {
+///         DaxResult::Number(n) => Ok(DaxResult::Number(n * n)),
+///         _ => Err("SQUARE expects a number".into()),
+///     })
+///     .unwrap();
+///
+/// let mut table = Table::new();
+/// table.add_column("Side".to_string(), vec![Value::Number(2.0), Value::Number(3.0)]).unwrap();
+/// let area = table.evaluate_dax_with(&registry, "SUMX(Sides, SQUARE([Side]))");
+/// assert!(matches!(area, Ok(DaxResult::Number(n)) if n == 13.0));
+/// ```
+#[derive(Clone, Default)]
+pub struct FunctionRegistry {
+    functions: HashMap<String, RegisteredFunction>,
+    allow_overrides: bool,
+}
+
+impl FunctionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Let `register` replace built-in functions, which it refuses to by default
+    pub fn allow_overrides(&mut self, allow: bool) -> &mut Self {
+        self.allow_overrides = allow;
+        self
+    }
+
+    /// Register a function under a name, taking `arity` arguments. Registering
+    /// a name again replaces the earlier function; a built-in name is an error
+    /// unless overrides are allowed.
+    pub fn register<F>(
+        &mut self,
+        name: &str,
+        arity: RangeInclusive<usize>,
+        function: F,
+    ) -> Result<(), DaxError>
+    where
+        F: Fn(&[DaxResult]) -> Result<DaxResult, DaxError> + Send + Sync + 'static,
+    {
+        let upper = name.to_ascii_uppercase();
+        if is_builtin(&upper) && !self.allow_overrides {
+            return Err(format!(
+                "{} is a built-in function; allow overrides to replace it",
+                name
+            )
+            .into());
+        }
+        self.functions.insert(
+            upper,
+            RegisteredFunction {
+                min_args: *arity.start(),
+                max_args: *arity.end(),
+                function: Arc::new(function),
+            },
+        );
+        Ok(())
+    }
+
+    /// Whether a name is a built-in or registered function
+    pub fn contains(&self, name: &str) -> bool {
+        let upper = name.to_ascii_uppercase();
+        is_builtin(&upper) || self.functions.contains_key(&upper)
+    }
+
+    /// Names of the registered functions, upper-cased and sorted
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.functions.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    pub(crate) fn get(&self, upper: &str) -> Option<&RegisteredFunction> {
+        self.functions.get(upper)
+    }
+}
+
+impl fmt::Debug for FunctionRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FunctionRegistry")
+            .field("functions", &self.names())
+            .field("allow_overrides", &self.allow_overrides)
+            .finish()
+    }
+}
+
+/// Whether the evaluator implements a function itself, ignoring case
+pub fn is_builtin(name: &str) -> bool {
+    let upper = name.to_ascii_uppercase();
+    BUILTIN_FUNCTIONS.binary_search(&upper.as_str()).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtins_are_sorted() {
+        assert!(BUILTIN_FUNCTIONS.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(is_builtin("sum") && is_builtin("NORM.S.INV"));
+        assert!(!is_builtin("FISCALQUARTER"));
+    }
+
+    // The macro crate can't see the evaluator, so its list is kept by hand;
+    // this reads the names off the arms of the evaluator's dispatch instead
+    #[test]
+    fn test_builtins_match_the_evaluator_dispatch() {
+        use std::collections::BTreeSet;
+
+        let source = include_str!("table.rs");
+        let start = source.find("    fn evaluate_function(").unwrap();
+        let end = start + source[start..].find("\n    }\n").unwrap();
+        // TRUE and FALSE are matched before the dispatch, by boolean_keyword
+        let mut dispatched = BTreeSet::from(["TRUE", "FALSE"]);
+        for line in source[start..end].lines() {
+            // An arm's pattern starts a line at the match's indentation
+            let Some(pattern) = line.strip_prefix(&" ".repeat(12)) else {
+                continue;
+            };
+            let pattern = pattern.strip_prefix("| ").unwrap_or(pattern);
+            if !pattern.starts_with('"') {
+                continue;
+            }
+            let pattern = pattern.split("=>").next().unwrap();
+            let pattern = pattern.split(" if ").next().unwrap();
+            dispatched.extend(pattern.split('"').skip(1).step_by(2));
+        }
+        assert!(dispatched.len() > 2);
+        let listed: BTreeSet<&str> = BUILTIN_FUNCTIONS.iter().copied().collect();
+        assert_eq!(
+            dispatched.difference(&listed).collect::<Vec<_>>(),
+            Vec::<&&str>::new(),
+            "dispatched but not in BUILTIN_FUNCTIONS"
+        );
+        assert_eq!(
+            listed.difference(&dispatched).collect::<Vec<_>>(),
+            Vec::<&&str>::new(),
+            "in BUILTIN_FUNCTIONS but never dispatched"
+        );
+        for &(name, _, _) in dax_macro_impl::BUILTIN_ARITIES {
+            assert!(dispatched.contains(name), "{}", name);
+        }
+    }
+
+    // parse_dax! checks calls against these at compile time, so they must be
+    // what the evaluator itself insists on
+    #[test]
+    fn test_builtin_arities_match_the_evaluator() {
+        use dax_macro_impl::BUILTIN_ARITIES;
+        assert!(BUILTIN_ARITIES.windows(2).all(|pair| pair[0].0 < pair[1].0));
+
+        let mut table = crate::table::Table::new();
+        table
+            .add_column("Sales".to_string(), vec![crate::types::Value::Number(1.0)])
+            .unwrap();
+        for &(name, min, max) in BUILTIN_ARITIES {
+            assert!(is_builtin(name), "{}", name);
+            let call = |n: usize| format!("{}({})", name, vec!["[Sales]"; n].join(", "));
+            let wrong = (min > 0).then(|| min - 1).into_iter().chain([max + 1]);
+            for n in wrong {
+                match table.evaluate_dax(&call(n)) {
+                    Err(DaxError::WrongArity { .. }) => {}
+                    other => panic!("{} gave {:?}", call(n), other),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,229 @@
+// group.rs
+
+//! Grouping and aggregating a table from Rust, without writing DAX.
+//!
+//! `Table::group_by` splits the rows by the combinations of values in some key
+//! columns, with Null a key value like any other, and `GroupedTable::agg`
+//! turns each group into one row: the key values followed by one column per
+//! aggregation, named the way a pivot table would, such as `Sum of Sales`.
+//! Groups come out in the order their first row appears in the table.
+use crate::error::DaxError;
+use crate::table::numeric_value;
+use crate::types::{Value, CURRENCY_SCALE};
+use crate::Table;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// How a group's values in a column are combined into one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Agg {
+    /// Total of the numbers, which stays an Integer or Currency if every
+    /// number is one. Null if there are no numbers.
+    Sum,
+    /// Average of the numbers, or Null if there are none
+    Mean,
+    /// Smallest value by the `Value` ordering, ignoring Null
+    Min,
+    /// Largest value by the `Value` ordering, ignoring Null
+    Max,
+    /// Number of values that aren't Null
+    Count,
+    /// Number of different values, Null included
+    DistinctCount,
+    /// The first value that isn't Null
+    First,
+    /// The last value that isn't Null
+    Last,
+}
+
+/// A table's rows split into groups by some key columns
+///
+/// # Examples
+///
+/// ```
+/// use dax_rust::group::Agg;
+/// use dax_rust::table::Table;
+/// use dax_rust::types::Value;
+///
+/// let mut table = Table::new();
+/// table.add_column("Product".to_string(), vec!["Apple".into(), "Pear".into(), "Apple".into()]).unwrap();
+/// table.add_column("Sales".to_string(), vec![Value::Integer(10), Value::Integer(5), Value::Integer(20)]).unwrap();
+///
+/// let totals = table.group_by(&["Product"]).unwrap().agg(&[("Sales", Agg::Sum)]).unwrap();
+/// assert_eq!(totals.get_column("Sum of Sales").unwrap(), [Value::Integer(30), Value::Integer(5)]);
+/// ```
+#[derive(Debug)]
+pub struct GroupedTable<'a> {
+    table: &'a Table,
+    keys: Vec<String>,
+    /// Each group's key values and rows, in order of its first row
+    groups: Vec<(Vec<Value>, Vec<usize>)>,
+}
+
+impl Table {
+    /// Group the rows by the values of the key columns
+    pub fn group_by(&self, keys: &[&str]) -> Result<GroupedTable<'_>, DaxError> {
+        let mut key_columns = Vec::with_capacity(keys.len());
+        for key in keys {
+            key_columns.push(
+                self.get_column(key)
+                    .ok_or_else(|| self.unknown_column(key))?,
+            );
+        }
+        let mut groups: Vec<(Vec<Value>, Vec<usize>)> = Vec::new();
+        let mut positions: HashMap<Vec<KeyPart>, usize> = HashMap::new();
+        for row in 0..self.num_rows() {
+            let key: Vec<KeyPart> = key_columns
+                .iter()
+                .map(|values| match values.column().codes() {
+                    Some((codes, _)) => KeyPart::Code(codes[row]),
+                    None => KeyPart::Value(values.get(row).unwrap_or(Value::Null)),
+                })
+                .collect();
+            match positions.get(&key) {
+                Some(&position) => groups[position].1.push(row),
+                None => {
+                    let values = key_columns
+                        .iter()
+                        .zip(&key)
+                        .map(|(values, part)| match part {
+                            KeyPart::Code(_) => values.get(row).unwrap_or(Value::Null),
+                            KeyPart::Value(value) => value.clone(),
+                        })
+                        .collect();
+                    positions.insert(key, groups.len());
+                    groups.push((values, vec![row]));
+                }
+            }
+        }
+        Ok(GroupedTable {
+            table: self,
+            keys: keys.iter().map(|key| key.to_string()).collect(),
+            groups,
+        })
+    }
+}
+
+// A key column's part of a row's group key. A dictionary-encoded column
+// gives its code, which stands for one string, so the string isn't built or
+// hashed for every row; any other column gives the value.
+#[derive(PartialEq, Eq, Hash)]
+enum KeyPart {
+    Code(u32),
+    Value(Value),
+}
+
+impl GroupedTable<'_> {
+    /// Number of groups
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// One row per group: its key values, then each aggregation of a column
+    pub fn agg(&self, aggregations: &[(&str, Agg)]) -> Result<Table, DaxError> {
+        let mut table = Table::new();
+        for (i, key) in self.keys.iter().enumerate() {
+            let values = self.groups.iter().map(|(values, _)| values[i].clone());
+            table.add_column(key.clone(), values.collect())?;
+        }
+        for (column, agg) in aggregations {
+            let values = self
+                .table
+                .get_column(column)
+                .ok_or_else(|| self.table.unknown_column(column))?;
+            let name = format!("{} of {}", agg, column);
+            if table.has_column(&name) {
+                return Err(format!("{} is asked for twice", name).into());
+            }
+            let results = self
+                .groups
+                .iter()
+                .map(|(_, rows)| {
+                    let cells: Vec<Value> = rows
+                        .iter()
+                        .map(|&row| values.get(row).unwrap_or(Value::Null))
+                        .collect();
+                    aggregate(*agg, cells.iter())
+                })
+                .collect();
+            table.add_column(name, results)?;
+        }
+        Ok(table)
+    }
+}
+
+pub(crate) fn aggregate<'v>(agg: Agg, values: impl Iterator<Item = &'v Value> + Clone) -> Value {
+    let mut present = values.clone().filter(|value| **value != Value::Null);
+    match agg {
+        Agg::Sum => sum(values),
+        Agg::Mean => {
+            let numbers: Vec<f64> = values.filter_map(numeric_value).collect();
+            match numbers.len() {
+                0 => Value::Null,
+                n => Value::Number(numbers.iter().sum::<f64>() / n as f64),
+            }
+        }
+        Agg::Min => present.min().cloned().unwrap_or(Value::Null),
+        Agg::Max => present.max().cloned().unwrap_or(Value::Null),
+        Agg::Count => Value::Integer(present.count() as i64),
+        Agg::DistinctCount => Value::Integer(values.collect::<HashSet<_>>().len() as i64),
+        Agg::First => present.next().cloned().unwrap_or(Value::Null),
+        Agg::Last => present.last().cloned().unwrap_or(Value::Null),
+    }
+}
+
+// Integers add up as integers and amounts of currency as currency, exactly,
+// unless a plain number is among them or the total overflows
+fn sum<'v>(values: impl Iterator<Item = &'v Value> + Clone) -> Value {
+    let numbers = values.filter(|value| numeric_value(value).is_some());
+    if numbers.clone().next().is_none() {
+        return Value::Null;
+    }
+    let exact = if numbers
+        .clone()
+        .all(|value| matches!(value, Value::Integer(_)))
+    {
+        numbers
+            .clone()
+            .try_fold(0i64, |acc, value| match value {
+                Value::Integer(i) => acc.checked_add(*i),
+                _ => None,
+            })
+            .map(Value::Integer)
+    } else if !numbers
+        .clone()
+        .any(|value| matches!(value, Value::Number(_)))
+    {
+        numbers
+            .clone()
+            .try_fold(0i64, |acc, value| match value {
+                Value::Currency(c) => acc.checked_add(*c),
+                Value::Integer(i) => acc.checked_add(i.checked_mul(CURRENCY_SCALE)?),
+                _ => None,
+            })
+            .map(Value::Currency)
+    } else {
+        None
+    };
+    exact.unwrap_or_else(|| Value::Number(numbers.filter_map(numeric_value).sum()))
+}
+
+impl fmt::Display for Agg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Agg::Sum => "Sum",
+            Agg::Mean => "Average",
+            Agg::Min => "Min",
+            Agg::Max => "Max",
+            Agg::Count => "Count",
+            Agg::DistinctCount => "Distinct Count",
+            Agg::First => "First",
+            Agg::Last => "Last",
+        };
+        write!(f, "{}", name)
+    }
+}
@@ -1,49 +1,1553 @@
-use crate::{DaxError, Table, Value};
+use crate::group::{aggregate, Agg};
+use crate::table::numeric_value;
+use crate::types::DType;
+use crate::{dates, DaxError, Table, Value};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
 use std::path::Path;
 
+mod deflate;
+mod gzip;
+mod inflate;
+#[cfg(feature = "serde")]
+mod json;
+#[cfg(feature = "parquet")]
+mod parquet;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+#[cfg(feature = "excel")]
+mod xlsx;
+#[cfg(feature = "zstd")]
+mod zstd;
+
+#[cfg(feature = "serde")]
+pub use json::{
+    read_json, read_ndjson, read_ndjson_chunks, read_ndjson_chunks_with, read_ndjson_with,
+    write_json, write_json_with, write_ndjson, write_ndjson_with, JsonLayout, NdjsonOptions,
+};
+#[cfg(feature = "parquet")]
+pub use parquet::{read_parquet, write_parquet};
+#[cfg(feature = "sqlite")]
+pub use sqlite::{read_sqlite, read_sqlite_params};
+#[cfg(feature = "excel")]
+pub use xlsx::read_xlsx;
+
+/// How `read_csv_with` reads a CSV file and `write_csv_with` writes one, so
+/// that a file can be written back in the form it was read. Each setting has
+/// a method to set it by, so that options can be built up in a chain.
+///
+/// # Examples
+///
+/// ```
+/// use dax_rust::io::CsvOptions;
+///
+/// // A European Excel export
+/// let options = CsvOptions::default().delimiter(';').decimal_separator(',');
+/// assert_eq!(options.delimiter, ';');
+/// assert!(options.has_header);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvOptions {
+    /// What separates fields, such as `,`, `;`, `\t` or `|`
+    pub delimiter: char,
+    /// What a field holding the delimiter or a line break is put in, such as
+    /// `"` or `'`. A quote inside a quoted field is written twice.
+    pub quote: char,
+    /// Whether the first record names the columns. Without a header they are
+    /// named `Column1`, `Column2` and so on.
+    pub has_header: bool,
+    /// Lines to skip at the start of the file, before the header
+    pub skip_rows: usize,
+    /// Most records to read after the header; None reads them all
+    pub max_rows: Option<usize>,
+    /// The columns to keep, in the file's order; None keeps every column
+    pub usecols: Option<Vec<String>>,
+    /// What separates a number's whole part from its fraction, such as the
+    /// `,` in `12,5`
+    pub decimal_separator: char,
+    /// What groups the digits of a number's whole part in threes, such as
+    /// the `.` in `1.234,56`; None if they aren't grouped
+    pub thousands_separator: Option<char>,
+    /// Whether negative numbers are in parentheses, as accounting exports
+    /// write `(123,45)` for `-123,45`
+    pub negative_parentheses: bool,
+    /// Fields that stand for a missing value, read as Null whatever their
+    /// case. An empty field is one unless it's left out of these; a quoted
+    /// field never is, unless `""` is one of them and the field is empty.
+    pub null_values: Vec<String>,
+    /// What a missing value is written as
+    pub null_as: String,
+    /// How dates are written, in chrono's `strftime` syntax such as
+    /// `%d.%m.%Y`; None writes ISO dates. ISO dates are read either way.
+    pub date_format: Option<String>,
+    /// What ends each record written. Reading takes either.
+    pub line_ending: LineEnding,
+    /// Columns to read as one kind of value whatever their text looks like,
+    /// such as ZIP codes as text so they keep their leading zeros. Columns
+    /// left out have their type inferred.
+    pub dtypes: HashMap<String, DType>,
+    /// Infer one type for each column from this many records, then read the
+    /// rest as that type too. None infers each field's type on its own, so a
+    /// column of numbers with one stray word in it is a mixed column.
+    pub infer_schema_rows: Option<usize>,
+    /// When a field doesn't fit its column's type, whether that's an error
+    /// or the field is read as Null
+    pub strict: bool,
+    /// How the file is compressed as it's written. Reading goes by the
+    /// file's name instead.
+    pub compression: Compression,
+    /// What to do with a record that has more or fewer fields than the
+    /// header, or bytes that aren't UTF-8
+    pub on_error: ErrorPolicy,
+}
+
+/// What reading does with a record of a CSV file that can't be read as it
+/// is. `read_csv_lossy` gives back what was done to which records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorPolicy {
+    /// Stop with a `DaxError::CsvError`
+    #[default]
+    Fail,
+    /// Leave the record out
+    SkipRow,
+    /// Keep the record, with Null for missing fields and fields that aren't
+    /// UTF-8, and without fields past the header's
+    NullFill,
+}
+
+/// A record `read_csv_lossy` skipped or filled in, with the line and the
+/// field the problem is at, counting both from 1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvWarning {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for CsvWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl From<CsvWarning> for DaxError {
+    fn from(warning: CsvWarning) -> Self {
+        DaxError::CsvError {
+            line: warning.line,
+            column: warning.column,
+            message: warning.message,
+        }
+    }
+}
+
+/// The line break after each record of a CSV file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    /// `\n`, as on Linux and macOS
+    #[default]
+    Lf,
+    /// `\r\n`, as on Windows and in RFC 4180
+    CrLf,
+}
+
+impl LineEnding {
+    fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+/// How a file is compressed as it's written. Files are read as their name
+/// says they're compressed, so one ending in `.gz` or `.zst` is decompressed
+/// as it's read, a piece at a time.
+///
+/// Zstd needs the `zstd` feature, for reading and writing: without it, a
+/// file ending in `.zst` is a `DaxError::ParseError` saying so.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    #[default]
+    None,
+    /// Gzip, as `gzip` and most tools that export `.csv.gz` write it
+    Gzip,
+    /// Zstandard, as `zstd` writes it, with a checksum
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+// A file to read, decompressed as it's read if its name ends in `.gz` or
+// `.zst`
+fn open(path: &Path) -> Result<Box<dyn BufRead>, DaxError> {
+    let file = BufReader::new(File::open(path)?);
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("gz") => Ok(Box::new(BufReader::new(gzip::GzipReader::new(file)?))),
+        #[cfg(feature = "zstd")]
+        Some("zst") => Ok(Box::new(BufReader::new(zstd::ZstdReader::new(file)?))),
+        #[cfg(not(feature = "zstd"))]
+        Some("zst") => Err(DaxError::ParseError(format!(
+            "{} is compressed with zstd, which needs the `zstd` feature",
+            path.display()
+        ))),
+        _ => Ok(Box::new(file)),
+    }
+}
+
+// A file being written, compressed as it's written if asked for
+enum Output {
+    Plain(BufWriter<File>),
+    Gzip(gzip::GzipWriter<BufWriter<File>>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::ZstdWriter<BufWriter<File>>),
+}
+
+impl Output {
+    fn create(path: &Path, compression: Compression) -> Result<Self, DaxError> {
+        let file = BufWriter::new(File::create(path)?);
+        Ok(match compression {
+            Compression::None => Output::Plain(file),
+            Compression::Gzip => Output::Gzip(gzip::GzipWriter::new(file)?),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Output::Zstd(zstd::ZstdWriter::new(file)?),
+        })
+    }
+
+    // Write the end of the compressed data, if any, and everything buffered
+    fn finish(self) -> Result<(), DaxError> {
+        let mut file = match self {
+            Output::Plain(file) => file,
+            Output::Gzip(gzip) => gzip.finish()?,
+            #[cfg(feature = "zstd")]
+            Output::Zstd(zstd) => zstd.finish()?,
+        };
+        file.flush()?;
+        Ok(())
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Output::Plain(file) => file.write(buf),
+            Output::Gzip(gzip) => gzip.write(buf),
+            #[cfg(feature = "zstd")]
+            Output::Zstd(zstd) => zstd.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Output::Plain(file) => file.flush(),
+            Output::Gzip(gzip) => gzip.flush(),
+            #[cfg(feature = "zstd")]
+            Output::Zstd(zstd) => zstd.flush(),
+        }
+    }
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            delimiter: ',',
+            quote: '"',
+            has_header: true,
+            skip_rows: 0,
+            max_rows: None,
+            usecols: None,
+            decimal_separator: '.',
+            thousands_separator: None,
+            negative_parentheses: false,
+            date_format: None,
+            null_values: vec![String::new()],
+            null_as: String::new(),
+            line_ending: LineEnding::Lf,
+            dtypes: HashMap::new(),
+            infer_schema_rows: None,
+            strict: true,
+            compression: Compression::None,
+            on_error: ErrorPolicy::Fail,
+        }
+    }
+}
+
+impl CsvOptions {
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    pub fn quote(mut self, quote: char) -> Self {
+        self.quote = quote;
+        self
+    }
+
+    pub fn has_header(mut self, has_header: bool) -> Self {
+        self.has_header = has_header;
+        self
+    }
+
+    pub fn skip_rows(mut self, skip_rows: usize) -> Self {
+        self.skip_rows = skip_rows;
+        self
+    }
+
+    pub fn max_rows(mut self, max_rows: usize) -> Self {
+        self.max_rows = Some(max_rows);
+        self
+    }
+
+    pub fn usecols<S: Into<String>>(mut self, columns: impl IntoIterator<Item = S>) -> Self {
+        self.usecols = Some(columns.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn decimal_separator(mut self, separator: char) -> Self {
+        self.decimal_separator = separator;
+        self
+    }
+
+    pub fn thousands_separator(mut self, separator: char) -> Self {
+        self.thousands_separator = Some(separator);
+        self
+    }
+
+    pub fn negative_parentheses(mut self, negative_parentheses: bool) -> Self {
+        self.negative_parentheses = negative_parentheses;
+        self
+    }
+
+    /// Read these fields as Null, in place of the empty field
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dax_rust::io::{read_csv_from_with, CsvOptions};
+    /// use dax_rust::Value;
+    ///
+    /// let options = CsvOptions::default().null_values(["", "NA", "-"]);
+    /// let table = read_csv_from_with("Sales,Region\nna,\"NA\"\n".as_bytes(), &options).unwrap();
+    /// assert_eq!(table.get("Sales", 0), Some(Value::Null));
+    /// assert_eq!(table.get("Region", 0), Some(Value::from("NA")));
+    /// ```
+    pub fn null_values<S: Into<String>>(mut self, values: impl IntoIterator<Item = S>) -> Self {
+        self.null_values = values.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn null_as(mut self, text: impl Into<String>) -> Self {
+        self.null_as = text.into();
+        self
+    }
+
+    pub fn date_format(mut self, format: impl Into<String>) -> Self {
+        self.date_format = Some(format.into());
+        self
+    }
+
+    pub fn line_ending(mut self, line_ending: LineEnding) -> Self {
+        self.line_ending = line_ending;
+        self
+    }
+
+    /// Read the named columns as the given types
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dax_rust::io::CsvOptions;
+    /// use dax_rust::DType;
+    ///
+    /// let options = CsvOptions::default().with_dtypes([("zip", DType::Text), ("amount", DType::Number)]);
+    /// assert_eq!(options.dtypes["zip"], DType::Text);
+    /// ```
+    pub fn with_dtypes<S: Into<String>>(
+        mut self,
+        dtypes: impl IntoIterator<Item = (S, DType)>,
+    ) -> Self {
+        self.dtypes
+            .extend(dtypes.into_iter().map(|(name, dtype)| (name.into(), dtype)));
+        self
+    }
+
+    pub fn infer_schema_rows(mut self, rows: usize) -> Self {
+        self.infer_schema_rows = Some(rows);
+        self
+    }
+
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    pub fn on_error(mut self, policy: ErrorPolicy) -> Self {
+        self.on_error = policy;
+        self
+    }
+
+    // Whether an unquoted field stands for a missing value
+    fn is_null(&self, text: &str) -> bool {
+        self.null_values
+            .iter()
+            .any(|null| null.eq_ignore_ascii_case(text))
+    }
+
+    // Whether the delimiter, quote and separators can be told apart from
+    // each other and from the rest of a file, and the date format is one
+    fn check(&self) -> Result<(), DaxError> {
+        if matches!(self.delimiter, '\n' | '\r') || self.delimiter == self.quote {
+            return Err(DaxError::ParseError(format!(
+                "{:?} can't separate CSV fields",
+                self.delimiter
+            )));
+        }
+        if matches!(self.quote, '\n' | '\r') || self.quote == self.decimal_separator {
+            return Err(DaxError::ParseError(format!(
+                "{:?} can't quote CSV fields",
+                self.quote
+            )));
+        }
+        if self.decimal_separator == self.delimiter {
+            return Err(DaxError::ParseError(format!(
+                "{:?} can't both separate fields and mark decimals",
+                self.delimiter
+            )));
+        }
+        if self.thousands_separator == Some(self.decimal_separator) {
+            return Err(DaxError::ParseError(format!(
+                "{:?} can't both group thousands and mark decimals",
+                self.decimal_separator
+            )));
+        }
+        if let Some(format) = &self.date_format {
+            if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+                return Err(DaxError::ParseError(format!(
+                    "{:?} isn't a date format",
+                    format
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Read a CSV file into a table, with its first record as the column names.
+/// A file whose name ends in `.gz` is decompressed as it's read, as is one
+/// ending in `.zst` with the `zstd` feature. See `read_csv_with` for how
+/// fields are read.
 pub fn read_csv(path: &Path) -> Result<Table, DaxError> {
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
+    read_csv_with(path, &CsvOptions::default())
+}
+
+/// Read a CSV file into a table as `options` say.
+///
+/// Fields are split as RFC 4180 has it: a field in quotes, `"` unless
+/// `quote` says otherwise, may hold delimiters, line breaks and quotes written twice (`""`). Each field's type
+/// is inferred, so whole numbers become integers and ISO dates dates. An empty
+/// field is Null, but an empty quoted field (`""`) is empty text; other
+/// fields can stand for Null too with `null_values`. A field in quotes it
+/// doesn't need, such as `"12"`, is text. Blank lines are skipped, except in
+/// a file of one column, where a blank line is a Null. Numbers are read with the separators the options give, and
+/// dates in `date_format` too, so `1.234,56` and `31.12.2024` can be read
+/// as they're written in Germany.
+///
+/// A record with more or fewer fields than the header, or with bytes that
+/// aren't UTF-8, is a `DaxError::CsvError` giving its line and field, unless
+/// `on_error` says to skip it or fill it in. A header that names a column
+/// twice is a `DaxError::CsvError` at the second one, whatever `on_error` says.
+///
+/// Malformed quoting, such as a quote inside an unquoted field or a quoted
+/// field that never ends, is a `DaxError::ParseError` giving the line. So is
+/// a delimiter or decimal separator that can't be told apart from the rest
+/// of the file, and a column in `usecols` or `dtypes` the file doesn't have.
+///
+/// A column named in `dtypes` is read as that type; with `infer_schema_rows`
+/// the other columns get the type of their first records. A field that
+/// doesn't fit its column's type is an error giving the line, or Null if
+/// `strict` is off.
+///
+/// # Examples
+///
+/// ```
+/// use dax_rust::io::{read_csv_with, CsvOptions};
+/// use dax_rust::Value;
+///
+/// let path = std::env::temp_dir().join("dax_rust_read_csv_with_doc.csv");
+/// std::fs::write(&path, "Exported by Excel\nProduct;Price\nPear;2,5\n").unwrap();
+/// let options = CsvOptions::default()
+///     .delimiter(';')
+///     .decimal_separator(',')
+///     .skip_rows(1);
+/// let table = read_csv_with(&path, &options).unwrap();
+/// std::fs::remove_file(&path).unwrap();
+/// assert_eq!(table.get("Price", 0), Some(Value::Number(2.5)));
+/// ```
+pub fn read_csv_with(path: &Path, options: &CsvOptions) -> Result<Table, DaxError> {
+    read_csv_from_with(open(path)?, options)
+}
+
+/// Read a CSV file as `options` say, giving back the records that were
+/// skipped or filled in along with the table. With `on_error` left at
+/// `ErrorPolicy::Fail` there are none, as the first is an error instead.
+/// Each is also logged as a warning.
+///
+/// # Examples
+///
+/// ```
+/// use dax_rust::io::{read_csv_lossy, CsvOptions, ErrorPolicy};
+///
+/// let path = std::env::temp_dir().join("dax_rust_read_csv_lossy_doc.csv");
+/// std::fs::write(&path, "Product,Qty\nPear,3\nPlum\nFig,4\n").unwrap();
+/// let options = CsvOptions::default().on_error(ErrorPolicy::SkipRow);
+/// let (table, skipped) = read_csv_lossy(&path, &options).unwrap();
+/// std::fs::remove_file(&path).unwrap();
+/// assert_eq!(table.num_rows(), 2);
+/// assert_eq!(skipped[0].line, 3);
+/// assert_eq!(skipped[0].to_string(), "line 3, column 2: expected 2 fields, found 1");
+/// ```
+pub fn read_csv_lossy(
+    path: &Path,
+    options: &CsvOptions,
+) -> Result<(Table, Vec<CsvWarning>), DaxError> {
+    let mut reader = CsvReader::new(open(path)?, options)?;
+    let table = reader.read(usize::MAX)?;
+    Ok((table, reader.warnings))
+}
+
+/// Read a tab-separated file into a table, with its first record as the
+/// column names, as `read_csv` reads a CSV file
+pub fn read_tsv(path: &Path) -> Result<Table, DaxError> {
+    read_csv_with(path, &CsvOptions::default().delimiter('\t'))
+}
+
+/// Read a CSV file with the options `sniff_csv` guesses for it
+pub fn read_csv_auto(path: &Path) -> Result<Table, DaxError> {
+    read_csv_with(path, &sniff_csv(path)?)
+}
+
+// How much of a file sniff_csv looks at
+const SNIFF_BYTES: u64 = 8192;
+
+/// Guess how a CSV file is written from its first few kilobytes: its
+/// delimiter, out of `,`, `;`, tab and `|`, its quote, `"` or `'`, the lines
+/// before its first record, whether that record is a header, and whether
+/// its decimals are written with a comma. The guess is the options that
+/// split the most records into the same number of fields; a file they
+/// can't be told apart for gets the defaults.
+///
+/// The options can be looked at and changed before reading the file with
+/// them, or `read_csv_auto` reads it with them as they are.
+///
+/// # Examples
+///
+/// ```
+/// use dax_rust::io::{read_csv_with, sniff_csv};
+/// use dax_rust::Value;
+///
+/// let path = std::env::temp_dir().join("dax_rust_sniff_csv_doc.csv");
+/// std::fs::write(&path, "Exported by Excel\nProduct;Price\nPear;2,5\nFig;3\n").unwrap();
+/// let options = sniff_csv(&path).unwrap();
+/// assert_eq!((options.delimiter, options.skip_rows, options.decimal_separator), (';', 1, ','));
+/// let table = read_csv_with(&path, &options).unwrap();
+/// std::fs::remove_file(&path).unwrap();
+/// assert_eq!(table.get("Price", 0), Some(Value::Number(2.5)));
+/// ```
+pub fn sniff_csv(path: &Path) -> Result<CsvOptions, DaxError> {
+    let mut sample = Vec::new();
+    open(path)?.take(SNIFF_BYTES).read_to_end(&mut sample)?;
+    // The last line of a sample cut short is left out
+    if sample.len() as u64 == SNIFF_BYTES {
+        if let Some(end) = sample.iter().rposition(|&b| b == b'\n') {
+            sample.truncate(end + 1);
+        }
+    }
+    Ok(sniff(&String::from_utf8_lossy(&sample)))
+}
+
+// The options that read a sample most consistently
+fn sniff(sample: &str) -> CsvOptions {
+    let mut best = CsvOptions::default();
+    let mut best_score = 0;
+    let mut best_records = Vec::new();
+    for quote in ['"', '\''] {
+        for delimiter in [',', ';', '\t', '|'] {
+            // The records up to the first that can't be read, with the line
+            // each starts on
+            let mut records = Records::new(sample.as_bytes(), delimiter, quote);
+            let mut read = Vec::new();
+            while let Ok(Some(fields)) = records.next_record() {
+                read.push((records.start, fields));
+            }
+
+            // The most common number of fields, and how many records have it
+            let mut counts: HashMap<usize, usize> = HashMap::new();
+            for (_, fields) in read.iter().filter(|(_, fields)| fields.len() > 1) {
+                *counts.entry(fields.len()).or_default() += 1;
+            }
+            let Some((width, score)) = counts
+                .into_iter()
+                .max_by_key(|&(width, count)| (count, width))
+            else {
+                continue;
+            };
+            if score > best_score {
+                best_score = score;
+                best = CsvOptions::default().delimiter(delimiter).quote(quote);
+                // Lines before the first record of that width are a preamble
+                best_records = read
+                    .into_iter()
+                    .skip_while(|(_, fields)| fields.len() != width)
+                    .collect();
+                best.skip_rows = best_records.first().map_or(0, |(start, _)| start - 1);
+            }
+        }
+    }
+
+    // Numbers such as 2,5 are decimals if commas don't separate fields
+    let comma_decimals = best.delimiter != ','
+        && best_records.iter().skip(1).any(|(_, fields)| {
+            fields.iter().any(|field| {
+                field.text.contains(',')
+                    && field.text.parse::<f64>().is_err()
+                    && parse_decimal(&field.text, ',').is_some()
+            })
+        });
+    if comma_decimals {
+        best.decimal_separator = ',';
+    }
+
+    // A header is distinct names, none of them blank or a number or date
+    if let Some((_, first)) = best_records.first() {
+        let mut names = HashSet::new();
+        best.has_header = first.iter().all(|field| {
+            !field.text.trim().is_empty()
+                && names.insert(field.text.as_str())
+                && matches!(field.parse(None, &best), Some(Value::Text(_)))
+        });
+    }
+    best
+}
+
+/// Read CSV from any reader, such as stdin, an HTTP body or bytes in memory,
+/// as `read_csv` reads a file
+///
+/// # Examples
+///
+/// ```
+/// use dax_rust::io::read_csv_from;
+/// use dax_rust::Value;
+///
+/// let table = read_csv_from("Product,Qty\nPear,3\n".as_bytes()).unwrap();
+/// assert_eq!(table.get("Qty", 0), Some(Value::Integer(3)));
+/// ```
+pub fn read_csv_from<R: BufRead>(reader: R) -> Result<Table, DaxError> {
+    read_csv_from_with(reader, &CsvOptions::default())
+}
+
+/// Read CSV from any reader as `options` say, as `read_csv_with` reads a file
+pub fn read_csv_from_with<R: BufRead>(reader: R, options: &CsvOptions) -> Result<Table, DaxError> {
+    CsvReader::new(reader, options)?.read(usize::MAX)
+}
+
+/// Read a CSV file a chunk at a time, as tables of at most `chunk_size` rows
+/// each, so that a file too big to hold in memory can still be gone through.
+/// See `read_csv_chunks_with` for the details.
+pub fn read_csv_chunks(
+    path: &Path,
+    chunk_size: usize,
+) -> impl Iterator<Item = Result<Table, DaxError>> {
+    read_csv_chunks_with(path, chunk_size, &CsvOptions::default())
+}
+
+/// Read a CSV file as `options` say, a chunk of at most `chunk_size` rows at
+/// a time. The header is read once, and every chunk has the same columns.
+///
+/// The rows are read as `read_csv_with` would read them, with `max_rows`
+/// counting rows over all the chunks. With `infer_schema_rows`, the types
+/// inferred from the start of the file hold for every chunk; without it, a
+/// column can be numbers in one chunk and mixed in the next. A file with no
+/// rows has no chunks. An error, such as the file not opening, ends the
+/// chunks.
+///
+/// Chunks can be put back together with `Table::append`.
+///
+/// # Examples
+///
+/// ```
+/// use dax_rust::io::{read_csv_chunks_with, CsvOptions};
+///
+/// let path = std::env::temp_dir().join("dax_rust_read_csv_chunks_doc.csv");
+/// std::fs::write(&path, "Sales\n1\n2\n3\n4\n5\n").unwrap();
+/// let sizes: Vec<usize> = read_csv_chunks_with(&path, 2, &CsvOptions::default())
+///     .map(|chunk| chunk.unwrap().num_rows())
+///     .collect();
+/// std::fs::remove_file(&path).unwrap();
+/// assert_eq!(sizes, [2, 2, 1]);
+/// ```
+pub fn read_csv_chunks_with(
+    path: &Path,
+    chunk_size: usize,
+    options: &CsvOptions,
+) -> impl Iterator<Item = Result<Table, DaxError>> {
+    let mut reader =
+        (chunk_size > 0).then(|| open(path).and_then(|file| CsvReader::new(file, options)));
+    std::iter::from_fn(move || {
+        let chunk = match reader.take()? {
+            Ok(mut csv) => {
+                let chunk = csv.read(chunk_size);
+                if matches!(&chunk, Ok(table) if table.num_rows() > 0) {
+                    reader = Some(Ok(csv));
+                }
+                chunk
+            }
+            Err(error) => Err(error),
+        };
+        match chunk {
+            Ok(table) if table.num_rows() == 0 => None,
+            chunk => Some(chunk),
+        }
+    })
+}
+
+/// Aggregate columns of a CSV file a chunk at a time, without holding more
+/// than one chunk in memory. The result is one row with a column per
+/// aggregation, named as `GroupedTable::agg` names them, such as
+/// `Sum of Sales`, and the same values as aggregating the whole table would
+/// give. `DistinctCount` has to remember the values it has seen, so it
+/// needs memory for every distinct value in the column.
+///
+/// # Examples
+///
+/// ```
+/// use dax_rust::group::Agg;
+/// use dax_rust::io::aggregate_csv;
+/// use dax_rust::Value;
+///
+/// let path = std::env::temp_dir().join("dax_rust_aggregate_csv_doc.csv");
+/// std::fs::write(&path, "Sales\n1\n2\n3\n").unwrap();
+/// let totals = aggregate_csv(&path, &[("Sales", Agg::Sum), ("Sales", Agg::Mean)]).unwrap();
+/// std::fs::remove_file(&path).unwrap();
+/// assert_eq!(totals.get("Sum of Sales", 0), Some(Value::Integer(6)));
+/// assert_eq!(totals.get("Average of Sales", 0), Some(Value::Number(2.0)));
+/// ```
+pub fn aggregate_csv(path: &Path, aggregations: &[(&str, Agg)]) -> Result<Table, DaxError> {
+    const CHUNK_SIZE: usize = 65_536;
+    let options = CsvOptions::default();
+    let mut reader = CsvReader::new(open(path)?, &options)?;
+    let mut names = Vec::with_capacity(aggregations.len());
+    for (column, agg) in aggregations {
+        if !reader.headers.iter().any(|name| name == column) {
+            return Err(format!("The CSV file has no column {}", column).into());
+        }
+        let name = format!("{} of {}", agg, column);
+        if names.contains(&name) {
+            return Err(format!("{} is asked for twice", name).into());
+        }
+        names.push(name);
+    }
+
+    let mut folds: Vec<Fold> = aggregations
+        .iter()
+        .map(|(_, agg)| Fold::new(*agg))
+        .collect();
+    loop {
+        let chunk = reader.read(CHUNK_SIZE)?;
+        if chunk.num_rows() == 0 {
+            break;
+        }
+        for ((column, _), fold) in aggregations.iter().zip(&mut folds) {
+            let values = chunk
+                .get_column(column)
+                .ok_or_else(|| chunk.unknown_column(column))?;
+            fold.add(&values.to_vec());
+        }
+    }
+
     let mut table = Table::new();
+    for (name, fold) in names.into_iter().zip(folds) {
+        table.add_column(name, vec![fold.finish()])?;
+    }
+    Ok(table)
+}
+
+// An aggregation of a column so far, from the chunks seen
+enum Fold {
+    // Sum, Min, Max, First and Last of the chunks' own results
+    Partial(Agg, Value),
+    Mean { total: f64, count: usize },
+    Count(i64),
+    Distinct(HashSet<Value>),
+}
+
+impl Fold {
+    fn new(agg: Agg) -> Self {
+        match agg {
+            Agg::Mean => Fold::Mean {
+                total: 0.0,
+                count: 0,
+            },
+            Agg::Count => Fold::Count(0),
+            Agg::DistinctCount => Fold::Distinct(HashSet::new()),
+            agg => Fold::Partial(agg, Value::Null),
+        }
+    }
+
+    fn add(&mut self, values: &[Value]) {
+        match self {
+            Fold::Partial(agg, so_far) => {
+                let chunk = aggregate(*agg, values.iter());
+                *so_far = aggregate(*agg, [&*so_far, &chunk].into_iter());
+            }
+            Fold::Mean { total, count } => {
+                for number in values.iter().filter_map(numeric_value) {
+                    *total += number;
+                    *count += 1;
+                }
+            }
+            Fold::Count(count) => {
+                *count += values.iter().filter(|value| !value.is_null()).count() as i64
+            }
+            Fold::Distinct(seen) => seen.extend(values.iter().cloned()),
+        }
+    }
 
-    let mut headers: Vec<String> = Vec::new();
-    let mut columns: Vec<Vec<Value>> = Vec::new();
+    fn finish(self) -> Value {
+        match self {
+            Fold::Partial(_, value) => value,
+            Fold::Mean { count: 0, .. } => Value::Null,
+            Fold::Mean { total, count } => Value::Number(total / count as f64),
+            Fold::Count(count) => Value::Integer(count),
+            Fold::Distinct(seen) => Value::Integer(seen.len() as i64),
+        }
+    }
+}
 
-    for (i, line) in reader.lines().enumerate() {
-        let line = line?;
-        let values: Vec<&str> = line.split(',').collect();
+// Reads rows of CSV as a table's columns, some rows at a time
+struct CsvReader<R> {
+    records: Records<R>,
+    options: CsvOptions,
+    headers: Vec<String>,
+    // Positions of the columns read, and the type each is read as, if any
+    kept: Vec<usize>,
+    types: Vec<Option<DType>>,
+    // Records read but not yet returned, with the line each starts on
+    pending: VecDeque<(usize, Vec<Field>)>,
+    // How many more rows max_rows allows
+    remaining: usize,
+    // The records skipped or filled in so far
+    warnings: Vec<CsvWarning>,
+}
 
-        if i == 0 {
-            headers = values.into_iter().map(String::from).collect();
-            columns = vec![Vec::new(); headers.len()];
+impl<R: BufRead> CsvReader<R> {
+    // Read the header, and the records types are inferred from
+    fn new(reader: R, options: &CsvOptions) -> Result<Self, DaxError> {
+        options.check()?;
+        let mut records = Records::new(reader, options.delimiter, options.quote);
+        records.skip_lines(options.skip_rows)?;
+        let mut pending = VecDeque::new();
+        let headers: Vec<String> = match records.next_record()? {
+            None => Vec::new(),
+            Some(first) if options.has_header => {
+                let invalid = first.iter().position(|field| field.invalid.is_some());
+                if let (Some(i), ErrorPolicy::Fail) = (invalid, options.on_error) {
+                    return Err(DaxError::CsvError {
+                        line: records.start,
+                        column: i + 1,
+                        message: "the column name isn't valid UTF-8".to_string(),
+                    });
+                }
+                let names: Vec<String> = first.into_iter().map(|field| field.text).collect();
+                // A table can't have two columns of one name
+                for (i, name) in names.iter().enumerate() {
+                    if let Some(earlier) = names[..i].iter().position(|other| other == name) {
+                        return Err(DaxError::CsvError {
+                            line: records.start,
+                            column: i + 1,
+                            message: format!(
+                                "the column name {:?} is already used by column {}",
+                                name,
+                                earlier + 1
+                            ),
+                        });
+                    }
+                }
+                names
+            }
+            // Without a header, the first record is the first row
+            Some(first) => {
+                let names = (1..=first.len()).map(|i| format!("Column{}", i)).collect();
+                pending.push_back((records.start, first));
+                names
+            }
+        };
+        // A Null in a file of one column is a blank line
+        records.blank_lines_are_records = headers.len() == 1;
+        let kept = if headers.is_empty() {
+            Vec::new()
         } else {
-            for (j, value) in values.iter().enumerate() {
-                if j < columns.len() {
-                    columns[j].push(parse_value(value));
+            kept_columns(&headers, options)?
+        };
+
+        let remaining = options.max_rows.unwrap_or(usize::MAX);
+        if let Some(rows) = options.infer_schema_rows {
+            while pending.len() < rows.min(remaining) {
+                match records.next_record()? {
+                    Some(fields) => pending.push_back((records.start, fields)),
+                    None => break,
                 }
             }
         }
+        let types = kept
+            .iter()
+            .map(|&i| match options.dtypes.get(&headers[i]) {
+                Some(dtype) => Some(*dtype),
+                None if options.infer_schema_rows.is_some() => infer_type(&pending, i, options),
+                None => None,
+            })
+            .collect();
+
+        Ok(CsvReader {
+            records,
+            options: options.clone(),
+            headers,
+            kept,
+            types,
+            pending,
+            remaining,
+            warnings: Vec::new(),
+        })
     }
 
-    for (header, column) in headers.into_iter().zip(columns.into_iter()) {
-        table.add_column(header, column);
+    // The next rows, at most `rows` of them; none once the file is read
+    fn read(&mut self, rows: usize) -> Result<Table, DaxError> {
+        let mut columns: Vec<Vec<Value>> = vec![Vec::new(); self.kept.len()];
+        let mut read = 0;
+        while read < rows && self.remaining > 0 {
+            let next = match self.pending.pop_front() {
+                Some(record) => Some(record),
+                None => self
+                    .records
+                    .next_record()?
+                    .map(|fields| (self.records.start, fields)),
+            };
+            let Some((line, fields)) = next else {
+                break;
+            };
+            if let Some(warning) = self.problem(line, &fields) {
+                match self.options.on_error {
+                    ErrorPolicy::Fail => return Err(warning.into()),
+                    ErrorPolicy::SkipRow => {
+                        log::warn!("Skipped CSV {}", warning);
+                        self.warnings.push(warning);
+                        continue;
+                    }
+                    ErrorPolicy::NullFill => {
+                        log::warn!("Filled in CSV {}", warning);
+                        self.warnings.push(warning);
+                    }
+                }
+            }
+            // A short record leaves its missing fields empty
+            let mut fields: Vec<Option<Field>> = fields.into_iter().map(Some).collect();
+            for ((column, &i), dtype) in columns.iter_mut().zip(&self.kept).zip(&self.types) {
+                let Some(field) = fields.get_mut(i).and_then(Option::take) else {
+                    column.push(Value::Null);
+                    continue;
+                };
+                if field.invalid.is_some() {
+                    column.push(Value::Null);
+                    continue;
+                }
+                match field.parse(*dtype, &self.options) {
+                    Some(value) => column.push(value),
+                    None if self.options.strict => {
+                        return Err(DaxError::ParseError(format!(
+                            "CSV line {}: {:?} in column {} can't be read as {}",
+                            line,
+                            field.text,
+                            self.headers[i],
+                            dtype.unwrap_or(DType::Mixed)
+                        )))
+                    }
+                    None => column.push(Value::Null),
+                }
+            }
+            read += 1;
+            self.remaining -= 1;
+        }
+
+        let mut table = Table::new();
+        for (column, &i) in columns.into_iter().zip(&self.kept) {
+            table.add_column(self.headers[i].clone(), column)?;
+        }
+        Ok(table)
     }
 
-    Ok(table)
+    // What's wrong with a record, if anything: a number of fields other than
+    // the header's, or a field that isn't UTF-8
+    fn problem(&self, line: usize, fields: &[Field]) -> Option<CsvWarning> {
+        let width = self.headers.len();
+        if fields.len() != width {
+            return Some(CsvWarning {
+                line,
+                column: fields.len().min(width) + 1,
+                message: format!("expected {} fields, found {}", width, fields.len()),
+            });
+        }
+        let (i, line) = fields
+            .iter()
+            .enumerate()
+            .find_map(|(i, field)| Some((i, field.invalid?)))?;
+        Some(CsvWarning {
+            line,
+            column: i + 1,
+            message: "the field isn't valid UTF-8".to_string(),
+        })
+    }
+}
+
+/// Write a table to a CSV file, with a header of the column names in order.
+/// See `write_csv_with` for how values are written.
+pub fn write_csv(table: &Table, path: &Path) -> Result<(), DaxError> {
+    write_csv_with(table, path, &CsvOptions::default())
+}
+
+/// Write a table to a CSV file as `options` say, using its delimiter,
+/// separators, date format, line ending, compression and whether to write
+/// a header, so that what's read with some options is written back the same
+/// way.
+///
+/// Null is written as `null_as`, an empty field by default, and empty text
+/// as `""`, so that the two read back apart; so is other text that would
+/// read back as Null, or as a number, date or boolean. Booleans are `true` and `false`, dates ISO dates, and
+/// numbers are as short as they can be while reading back the same. A field
+/// holding the delimiter, a quote or a line break is quoted.
+pub fn write_csv_with(table: &Table, path: &Path, options: &CsvOptions) -> Result<(), DaxError> {
+    options.check()?;
+    let mut writer = Output::create(path, options.compression)?;
+    write_records(table, &mut writer, options)?;
+    writer.finish()
 }
 
-fn parse_value(value: &str) -> Value {
-    if let Ok(num) = value.parse::<f64>() {
-        Value::Number(num)
+impl Table {
+    /// Read CSV text into a table, as `read_csv` reads a file
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dax_rust::{Table, Value};
+    ///
+    /// let table = Table::from_csv_str("Name,Amount\n\"Smith, John\",2\n").unwrap();
+    /// assert_eq!(table.get("Name", 0), Some(Value::from("Smith, John")));
+    /// ```
+    pub fn from_csv_str(text: &str) -> Result<Table, DaxError> {
+        read_csv_from(text.as_bytes())
+    }
+
+    /// The table as CSV text, as `write_csv` would write it
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dax_rust::{Table, Value};
+    ///
+    /// let mut table = Table::new();
+    /// table.add_column("Name".to_string(), vec!["Smith, John".into(), Value::Null]).unwrap();
+    /// table.add_column("Amount".to_string(), vec![Value::Number(2.0), Value::Number(0.5)]).unwrap();
+    /// assert_eq!(table.to_csv_string(), "Name,Amount\n\"Smith, John\",2\n,0.5\n");
+    /// ```
+    pub fn to_csv_string(&self) -> String {
+        let mut text = Vec::new();
+        write_records(self, &mut text, &CsvOptions::default())
+            .expect("writing to a Vec doesn't fail");
+        String::from_utf8(text).expect("every field is written as UTF-8")
+    }
+}
+
+fn write_records(
+    table: &Table,
+    writer: &mut impl Write,
+    options: &CsvOptions,
+) -> Result<(), DaxError> {
+    let ending = options.line_ending.as_str();
+    let mut line = String::new();
+    if options.has_header {
+        for (i, name) in table.column_names().iter().enumerate() {
+            if i > 0 {
+                line.push(options.delimiter);
+            }
+            push_field(&mut line, name, options, false);
+        }
+        line.push_str(ending);
+        writer.write_all(line.as_bytes())?;
+    }
+    for row in 0..table.num_rows() {
+        line.clear();
+        for (i, name) in table.column_names().iter().enumerate() {
+            if i > 0 {
+                line.push(options.delimiter);
+            }
+            let text = match table.cell(name, row).unwrap_or(Value::Null) {
+                Value::Null => {
+                    push_field(&mut line, &options.null_as, options, false);
+                    continue;
+                }
+                // Text that would read back as Null, or as anything but
+                // text, is quoted
+                Value::Text(s) => {
+                    let quote = s.is_empty()
+                        || s.eq_ignore_ascii_case(&options.null_as)
+                        || options.is_null(&s)
+                        || !matches!(parse_value(&s, options), Value::Text(_));
+                    push_field(&mut line, &s, options, quote);
+                    continue;
+                }
+                Value::Boolean(b) => (if b { "true" } else { "false" }).to_string(),
+                // The shortest text that reads back as the same number
+                Value::Number(n) => format_number(n.to_string(), options),
+                value @ (Value::Integer(_) | Value::Currency(_)) => {
+                    format_number(value.to_string(), options)
+                }
+                Value::DateTime(date) => match &options.date_format {
+                    Some(format) => date.format(format).to_string(),
+                    None => dates::format(&date),
+                },
+            };
+            // Separators can make a number or date need quotes
+            push_field(&mut line, &text, options, false);
+        }
+        line.push_str(ending);
+        writer.write_all(line.as_bytes())?;
+    }
+    Ok(())
+}
+
+// A number's text, as Rust writes it, with the separators and negative
+// sign the options ask for
+fn format_number(number: String, options: &CsvOptions) -> String {
+    let (negative, digits) = match number.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, number.as_str()),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    let mut text = String::new();
+    if negative && !options.negative_parentheses {
+        text.push('-');
+    }
+    match options.thousands_separator {
+        Some(separator) if whole.bytes().all(|b| b.is_ascii_digit()) => {
+            for (i, digit) in whole.chars().enumerate() {
+                if i > 0 && (whole.len() - i).is_multiple_of(3) {
+                    text.push(separator);
+                }
+                text.push(digit);
+            }
+        }
+        _ => text.push_str(whole),
+    }
+    if !fraction.is_empty() {
+        text.push(options.decimal_separator);
+        text.push_str(fraction);
+    }
+    if negative && options.negative_parentheses {
+        format!("({})", text)
+    } else {
+        text
+    }
+}
+
+// Add a field's text to a line, in quotes if it needs them or `quoted` says
+fn push_field(line: &mut String, text: &str, options: &CsvOptions, quoted: bool) {
+    let quote = options.quote;
+    let needs_quotes = quoted || text.contains([options.delimiter, quote, '\n', '\r']);
+    if needs_quotes {
+        line.push(quote);
+        line.push_str(&text.replace(quote, &format!("{}{}", quote, quote)));
+        line.push(quote);
+    } else {
+        line.push_str(text);
+    }
+}
+
+// Positions of the columns to read, after checking that the columns the
+// options name are in the file
+fn kept_columns(headers: &[String], options: &CsvOptions) -> Result<Vec<usize>, DaxError> {
+    let named = options
+        .usecols
+        .iter()
+        .flatten()
+        .chain(options.dtypes.keys());
+    for name in named {
+        if !headers.contains(name) {
+            return Err(DaxError::ParseError(format!(
+                "The CSV file has no column {}; it has {}",
+                name,
+                headers.join(", ")
+            )));
+        }
+    }
+    Ok(match &options.usecols {
+        None => (0..headers.len()).collect(),
+        Some(names) => (0..headers.len())
+            .filter(|&i| names.contains(&headers[i]))
+            .collect(),
+    })
+}
+
+// The one type the fields of a column have in some records, or None if they
+// have several or are all empty, so each field's type is inferred on its own
+fn infer_type(
+    records: &VecDeque<(usize, Vec<Field>)>,
+    column: usize,
+    options: &CsvOptions,
+) -> Option<DType> {
+    let values: Vec<Value> = records
+        .iter()
+        .filter_map(|(_, fields)| fields.get(column))
+        .map(|field| field.parse(None, options).unwrap_or(Value::Null))
+        .collect();
+    match DType::infer(&values) {
+        DType::Mixed | DType::Null => None,
+        dtype => Some(dtype),
+    }
+}
+
+// One field of a record, whether it was in quotes, and the line of a byte
+// in it that isn't UTF-8, if there is one
+struct Field {
+    text: String,
+    quoted: bool,
+    invalid: Option<usize>,
+}
+
+impl Field {
+    // The field's value as the given type, or with its type inferred if there
+    // isn't one; None if it doesn't fit the type. A field in the options'
+    // null values is Null, and an empty quoted field is empty text where
+    // text is allowed.
+    fn parse(&self, dtype: Option<DType>, options: &CsvOptions) -> Option<Value> {
+        let text = self.text.as_str();
+        let null = match self.quoted {
+            true => text.is_empty() && options.null_values.iter().any(|null| null == "\"\""),
+            false => options.is_null(text),
+        };
+        match dtype {
+            _ if null => Some(Value::Null),
+            None | Some(DType::Mixed) | Some(DType::Text) if text.is_empty() => {
+                Some(Value::from(""))
+            }
+            _ if text.is_empty() => Some(Value::Null),
+            // Quotes a field doesn't need mark it as text, as `write_csv`
+            // writes text that would otherwise read as a number or date
+            None | Some(DType::Mixed)
+                if self.quoted
+                    && !text.contains([options.delimiter, options.quote, '\n', '\r']) =>
+            {
+                Some(Value::from(text))
+            }
+            None | Some(DType::Mixed) => Some(parse_value(text, options)),
+            Some(DType::Text) => Some(Value::from(text)),
+            Some(dtype @ (DType::Number | DType::Integer | DType::Currency)) => {
+                parse_number(text.trim(), options)?.cast(dtype)
+            }
+            Some(DType::Date) => parse_date(text.trim(), options).map(Value::DateTime),
+            Some(dtype) => Value::from(text).cast(dtype),
+        }
+    }
+}
+
+// Where the parser is within a field
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    /// At the start of a field, before any of its characters
+    Start,
+    Unquoted,
+    Quoted,
+    /// Just past a quote in a quoted field, which either ends the field or,
+    /// doubled, stands for a quote
+    QuoteInQuoted,
+}
+
+// Records of CSV text, read a line at a time
+struct Records<R> {
+    reader: R,
+    delimiter: char,
+    quote: char,
+    /// The bytes of the last line read
+    bytes: Vec<u8>,
+    /// Where in the last line read bytes that aren't UTF-8 were replaced
+    invalid: Vec<usize>,
+    /// Number of the last line read, counting from 1
+    line: usize,
+    /// Number of the line the last record read starts on
+    start: usize,
+    /// Whether a blank line is a record of one empty field, as it is in a
+    /// file of one column
+    blank_lines_are_records: bool,
+}
+
+impl<R: BufRead> Records<R> {
+    fn new(reader: R, delimiter: char, quote: char) -> Self {
+        Records {
+            reader,
+            delimiter,
+            quote,
+            bytes: Vec::new(),
+            invalid: Vec::new(),
+            line: 0,
+            start: 0,
+            blank_lines_are_records: false,
+        }
+    }
+
+    // Pass over lines without reading them as records
+    fn skip_lines(&mut self, lines: usize) -> Result<(), DaxError> {
+        let mut text = String::new();
+        for _ in 0..lines {
+            if !self.read_line(&mut text)? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // Read the next line into `text`, or return false at the end of the
+    // input. The byte order mark Excel starts its files with is dropped, so
+    // it doesn't end up in the first column's name. Bytes that aren't UTF-8
+    // are replaced with U+FFFD, and where they were is kept in `invalid`.
+    fn read_line(&mut self, text: &mut String) -> Result<bool, DaxError> {
+        text.clear();
+        self.bytes.clear();
+        self.invalid.clear();
+        if self.reader.read_until(b'\n', &mut self.bytes)? == 0 {
+            return Ok(false);
+        }
+        self.line += 1;
+        let bom = "\u{feff}".as_bytes();
+        if self.line == 1 && self.bytes.starts_with(bom) {
+            self.bytes.drain(..bom.len());
+        }
+        for chunk in self.bytes.utf8_chunks() {
+            text.push_str(chunk.valid());
+            if !chunk.invalid().is_empty() {
+                self.invalid.push(text.len());
+                text.push(char::REPLACEMENT_CHARACTER);
+            }
+        }
+        Ok(true)
+    }
+
+    // The next record's fields, or None at the end of the input
+    fn next_record(&mut self) -> Result<Option<Vec<Field>>, DaxError> {
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        // The line of a byte in the field that isn't UTF-8
+        let mut invalid = None;
+        let mut state = State::Start;
+        // Line the last quoted field started on
+        let mut quote_line = 0;
+        let mut text = String::new();
+        loop {
+            if !self.read_line(&mut text)? {
+                return match state {
+                    State::Quoted => Err(self.error(
+                        quote_line,
+                        "a quoted field isn't closed before the end of the file",
+                    )),
+                    _ => Ok(None),
+                };
+            }
+            let content = text.trim_end_matches(['\n', '\r']);
+            if state == State::Start && fields.is_empty() && content.is_empty() {
+                if self.blank_lines_are_records {
+                    self.start = self.line;
+                    return Ok(Some(vec![Field {
+                        text: String::new(),
+                        quoted: false,
+                        invalid: None,
+                    }]));
+                }
+                // Blank lines between records are skipped
+                continue;
+            }
+            if state == State::Start && fields.is_empty() {
+                self.start = self.line;
+            }
+
+            for (offset, c) in content.char_indices() {
+                if invalid.is_none() && self.invalid.contains(&offset) {
+                    invalid = Some(self.line);
+                }
+                state = match (state, c) {
+                    (State::Start, c) if c == self.quote => {
+                        quote_line = self.line;
+                        State::Quoted
+                    }
+                    (State::Start | State::Unquoted | State::QuoteInQuoted, c)
+                        if c == self.delimiter =>
+                    {
+                        fields.push(Field {
+                            text: std::mem::take(&mut field),
+                            quoted: state == State::QuoteInQuoted,
+                            invalid: invalid.take(),
+                        });
+                        State::Start
+                    }
+                    (State::Unquoted, c) if c == self.quote => {
+                        return Err(self.error(
+                            self.line,
+                            "a quote inside an unquoted field; quote the whole field and double the quote",
+                        ))
+                    }
+                    (State::Start | State::Unquoted, c) => {
+                        field.push(c);
+                        State::Unquoted
+                    }
+                    (State::Quoted, c) if c == self.quote => State::QuoteInQuoted,
+                    (State::Quoted, c) => {
+                        field.push(c);
+                        State::Quoted
+                    }
+                    (State::QuoteInQuoted, c) if c == self.quote => {
+                        field.push(c);
+                        State::Quoted
+                    }
+                    (State::QuoteInQuoted, c) => {
+                        return Err(self.error(
+                            self.line,
+                            &format!("{:?} after the closing quote of a field", c),
+                        ))
+                    }
+                };
+            }
+
+            if state == State::Quoted {
+                // The line break is part of the quoted field
+                field.push_str(&text[content.len()..]);
+                continue;
+            }
+            fields.push(Field {
+                text: field,
+                quoted: state == State::QuoteInQuoted,
+                invalid,
+            });
+            return Ok(Some(fields));
+        }
+    }
+
+    fn error(&self, line: usize, message: &str) -> DaxError {
+        DaxError::ParseError(format!("CSV line {}: {}", line, message))
+    }
+}
+
+fn parse_value(value: &str, options: &CsvOptions) -> Value {
+    if let Some(number) = parse_number(value, options) {
+        number
     } else if value.eq_ignore_ascii_case("true") {
         Value::Boolean(true)
     } else if value.eq_ignore_ascii_case("false") {
         Value::Boolean(false)
+    } else if let Some(date) = parse_date(value, options) {
+        Value::DateTime(date)
     } else if value.is_empty() {
         Value::Null
     } else {
         Value::Text(value.to_string())
     }
 }
+
+// A number written as the options say, as an integer if it's whole, such
+// as an ID or a count
+fn parse_number(text: &str, options: &CsvOptions) -> Option<Value> {
+    if options.negative_parentheses {
+        if let Some(inner) = text.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+            if inner.starts_with(['-', '+']) {
+                return None;
+            }
+            return match parse_unsigned(inner, options)? {
+                Value::Integer(int) => Some(Value::Integer(-int)),
+                Value::Number(num) => Some(Value::Number(-num)),
+                _ => None,
+            };
+        }
+    }
+    parse_unsigned(text, options)
+}
+
+// A number written with the options' separators, outside of any parentheses
+fn parse_unsigned(text: &str, options: &CsvOptions) -> Option<Value> {
+    let ungrouped;
+    let text = match options.thousands_separator {
+        Some(separator) if text.contains(separator) => {
+            ungrouped = ungroup(text, separator, options.decimal_separator)?;
+            ungrouped.as_str()
+        }
+        _ => text,
+    };
+    if let Ok(int) = text.parse::<i64>() {
+        return Some(Value::Integer(int));
+    }
+    parse_decimal(text, options.decimal_separator).map(Value::Number)
+}
+
+// A number without the separators grouping its whole part, or None if they
+// don't come between groups of three digits
+fn ungroup(text: &str, separator: char, decimal_separator: char) -> Option<String> {
+    let (whole, fraction) = match text.split_once(decimal_separator) {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (text, None),
+    };
+    let digits = whole.strip_prefix(['-', '+']).unwrap_or(whole);
+    let mut groups = digits.split(separator);
+    let first = groups.next().unwrap_or_default();
+    let is_digits = |group: &str| group.bytes().all(|b| b.is_ascii_digit());
+    if first.is_empty() || first.len() > 3 || !is_digits(first) {
+        return None;
+    }
+    if !groups.all(|group| group.len() == 3 && is_digits(group)) {
+        return None;
+    }
+    let mut number = whole.replace(separator, "");
+    if let Some(fraction) = fraction {
+        number.push(decimal_separator);
+        number.push_str(fraction);
+    }
+    Some(number)
+}
+
+// A date in the options' format, or an ISO date
+fn parse_date(text: &str, options: &CsvOptions) -> Option<NaiveDateTime> {
+    if let Some(format) = &options.date_format {
+        if let Ok(date) = NaiveDateTime::parse_from_str(text, format) {
+            return Some(date);
+        }
+        if let Ok(date) = NaiveDate::parse_from_str(text, format) {
+            return Some(date.and_time(NaiveTime::MIN));
+        }
+    }
+    dates::parse_iso(text)
+}
+
+// A number written with the given decimal separator. Under any other
+// separator than `.`, a `.` means the text isn't a number.
+fn parse_decimal(value: &str, decimal_separator: char) -> Option<f64> {
+    if decimal_separator == '.' {
+        return value.parse().ok();
+    }
+    if value.contains('.') {
+        return None;
+    }
+    value.replace(decimal_separator, ".").parse().ok()
+}
@@ -0,0 +1,249 @@
+// io/deflate.rs
+
+//! Compression of data as DEFLATE (RFC 1951), as written inside gzip files.
+//! Repeats are found through chains of earlier places with the same three
+//! bytes, and written with the fixed Huffman codes, so no code tables need
+//! building.
+use super::inflate::{DISTANCE_BASE, DISTANCE_EXTRA, LENGTH_BASE, LENGTH_EXTRA};
+use std::io::{self, Write};
+
+// How far back a copy can reach
+const WINDOW: usize = 32_768;
+// How much input is compressed as a block
+const BLOCK: usize = 65_536;
+// How many earlier places are tried for each repeat
+const MAX_CHAIN: usize = 64;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const HASH_BITS: u32 = 15;
+
+/// Compresses what's written to it and writes the DEFLATE data to a writer,
+/// a block at a time
+pub(super) struct Deflate<W> {
+    writer: W,
+    // The last WINDOW bytes compressed, then the input not yet compressed
+    data: Vec<u8>,
+    // Where in `data` the input not yet compressed starts
+    start: usize,
+    bits: BitWriter,
+}
+
+impl<W: Write> Deflate<W> {
+    pub(super) fn new(writer: W) -> Self {
+        Deflate {
+            writer,
+            data: Vec::with_capacity(WINDOW + BLOCK),
+            start: 0,
+            bits: BitWriter::default(),
+        }
+    }
+
+    /// Compress the rest of the input as the last block, and give back the
+    /// writer
+    pub(super) fn finish(mut self) -> io::Result<W> {
+        self.compress(true)?;
+        Ok(self.writer)
+    }
+
+    // Compress the input not yet compressed as one block
+    fn compress(&mut self, last: bool) -> io::Result<()> {
+        let bits = &mut self.bits;
+        bits.put(last as u32, 1);
+        // Fixed Huffman codes
+        bits.put(1, 2);
+
+        let data = &self.data;
+        let mut head = vec![usize::MAX; 1 << HASH_BITS];
+        // The earlier place with the same hash as each place
+        let mut previous = vec![usize::MAX; data.len()];
+        for i in self.start.saturating_sub(WINDOW)..self.start {
+            insert(data, i, &mut head, &mut previous);
+        }
+
+        let mut i = self.start;
+        while i < data.len() {
+            let (len, distance) = longest_match(data, i, &head, &previous);
+            if len >= MIN_MATCH {
+                bits.put_length(len);
+                bits.put_distance(distance);
+                for j in i..i + len {
+                    insert(data, j, &mut head, &mut previous);
+                }
+                i += len;
+            } else {
+                bits.put_symbol(data[i] as usize);
+                insert(data, i, &mut head, &mut previous);
+                i += 1;
+            }
+        }
+        bits.put_symbol(256);
+        if last {
+            bits.align();
+        }
+
+        self.writer.write_all(&bits.bytes)?;
+        bits.bytes.clear();
+        // Keep what copies in the next block can refer back to
+        let keep = self.data.len().saturating_sub(WINDOW);
+        self.data.drain(..keep);
+        self.start = self.data.len();
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for Deflate<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = buf.len().min(self.start + BLOCK - self.data.len());
+        self.data.extend_from_slice(&buf[..n]);
+        if self.data.len() - self.start == BLOCK {
+            self.compress(false)?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+fn hash(bytes: &[u8]) -> usize {
+    let key = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+    (key.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize
+}
+
+// Add the place `i` to the front of the chain of places with its hash
+fn insert(data: &[u8], i: usize, head: &mut [usize], previous: &mut [usize]) {
+    if i + MIN_MATCH <= data.len() {
+        let hash = hash(&data[i..i + MIN_MATCH]);
+        previous[i] = head[hash];
+        head[hash] = i;
+    }
+}
+
+// The longest repeat of earlier data at `i`, as its length and how far back
+// it is; a length under MIN_MATCH if there's none
+fn longest_match(data: &[u8], i: usize, head: &[usize], previous: &[usize]) -> (usize, usize) {
+    let max = MAX_MATCH.min(data.len() - i);
+    if max < MIN_MATCH {
+        return (0, 0);
+    }
+    let (mut best, mut distance) = (0, 0);
+    let mut candidate = head[hash(&data[i..i + MIN_MATCH])];
+    for _ in 0..MAX_CHAIN {
+        if candidate == usize::MAX || i - candidate > WINDOW {
+            break;
+        }
+        let len = data[candidate..]
+            .iter()
+            .zip(&data[i..i + max])
+            .take_while(|(a, b)| a == b)
+            .count();
+        if len > best {
+            best = len;
+            distance = i - candidate;
+            if len == max {
+                break;
+            }
+        }
+        candidate = previous[candidate];
+    }
+    (best, distance)
+}
+
+// Bits of the output, lowest first
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    count: u32,
+}
+
+impl BitWriter {
+    fn put(&mut self, value: u32, n: u32) {
+        self.buffer |= value << self.count;
+        self.count += n;
+        while self.count >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    // A Huffman code, which goes highest bit first
+    fn put_code(&mut self, code: u32, n: u32) {
+        self.put(code.reverse_bits() >> (32 - n), n);
+    }
+
+    // A literal byte, the end of a block, or a length's code, with the fixed
+    // codes
+    fn put_symbol(&mut self, symbol: usize) {
+        let symbol = symbol as u32;
+        match symbol {
+            0..=143 => self.put_code(0x30 + symbol, 8),
+            144..=255 => self.put_code(0x190 + symbol - 144, 9),
+            256..=279 => self.put_code(symbol - 256, 7),
+            _ => self.put_code(0xc0 + symbol - 280, 8),
+        }
+    }
+
+    fn put_length(&mut self, len: usize) {
+        let i = LENGTH_BASE
+            .iter()
+            .rposition(|&base| base as usize <= len)
+            .unwrap_or(0);
+        self.put_symbol(257 + i);
+        self.put(len as u32 - LENGTH_BASE[i] as u32, LENGTH_EXTRA[i] as u32);
+    }
+
+    fn put_distance(&mut self, distance: usize) {
+        let i = DISTANCE_BASE
+            .iter()
+            .rposition(|&base| base as usize <= distance)
+            .unwrap_or(0);
+        self.put_code(i as u32, 5);
+        self.put(
+            distance as u32 - DISTANCE_BASE[i] as u32,
+            DISTANCE_EXTRA[i] as u32,
+        );
+    }
+
+    // Pad to a whole byte
+    fn align(&mut self) {
+        if self.count > 0 {
+            self.put(0, 8 - self.count);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::inflate::inflate;
+    use super::*;
+
+    fn deflate(data: &[u8]) -> Vec<u8> {
+        let mut deflate = Deflate::new(Vec::new());
+        deflate.write_all(data).unwrap();
+        deflate.finish().unwrap()
+    }
+
+    #[test]
+    fn test_compress_and_decompress() {
+        assert_eq!(inflate(&deflate(b""), 0).unwrap(), b"");
+        assert_eq!(
+            inflate(&deflate(b"hello hello hello"), 17).unwrap(),
+            b"hello hello hello"
+        );
+
+        // Over several blocks, with repeats reaching back into the one before
+        let data: Vec<u8> = (0..150_000u32)
+            .flat_map(|i| format!("{},{}\n", i % 500, i % 5).into_bytes())
+            .collect();
+        let compressed = deflate(&data);
+        assert!(compressed.len() < data.len() / 4);
+        assert_eq!(inflate(&compressed, data.len()).unwrap(), data);
+
+        // Long runs, which copy from just behind themselves
+        let data = vec![b'x'; 100_000];
+        assert_eq!(inflate(&deflate(&data), data.len()).unwrap(), data);
+    }
+}
@@ -0,0 +1,276 @@
+// io/gzip.rs
+
+//! Gzip files (RFC 1952): DEFLATE data between a header and a checksum,
+//! decompressed as they're read and compressed as they're written, so no
+//! more than a block of either is held in memory.
+use super::deflate::Deflate;
+use super::inflate::Inflate;
+use std::io::{self, BufRead, Read, Write};
+
+// The first bytes of every gzip file, and the number of its DEFLATE method
+const MAGIC: [u8; 2] = [0x1f, 0x8b];
+const DEFLATE: u8 = 8;
+
+// Bits of the header's flags for what follows the fixed part
+const FLAG_HEADER_CRC: u8 = 2;
+const FLAG_EXTRA: u8 = 4;
+const FLAG_NAME: u8 = 8;
+const FLAG_COMMENT: u8 = 16;
+
+/// Decompresses a gzip file as it's read. A file of several gzip members one
+/// after another, as `cat` makes of two, reads as their contents joined.
+pub(super) struct GzipReader<R> {
+    // The member being read, or None once the file is read
+    inflate: Option<Inflate<R>>,
+    // The checksum and size of what's been read of the member
+    crc: u32,
+    size: u32,
+}
+
+impl<R: BufRead> GzipReader<R> {
+    pub(super) fn new(mut reader: R) -> io::Result<Self> {
+        read_header(&mut reader)?;
+        Ok(GzipReader {
+            inflate: Some(Inflate::new(reader)),
+            crc: 0,
+            size: 0,
+        })
+    }
+}
+
+impl<R: BufRead> Read for GzipReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while !buf.is_empty() {
+            let Some(inflate) = &mut self.inflate else {
+                break;
+            };
+            let n = inflate.read(buf)?;
+            if n > 0 {
+                self.crc = crc32(self.crc, &buf[..n]);
+                self.size = self.size.wrapping_add(n as u32);
+                return Ok(n);
+            }
+
+            // The end of a member, which its checksum and size follow
+            let mut reader = self
+                .inflate
+                .take()
+                .expect("a member is being read")
+                .into_inner();
+            let mut trailer = [0; 8];
+            reader
+                .read_exact(&mut trailer)
+                .map_err(|_| invalid("the file ends before its checksum"))?;
+            if u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]) != self.crc
+                || u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]) != self.size
+            {
+                return Err(invalid("the data doesn't match its checksum"));
+            }
+            if !reader.fill_buf()?.is_empty() {
+                read_header(&mut reader)?;
+                self.inflate = Some(Inflate::new(reader));
+                self.crc = 0;
+                self.size = 0;
+            }
+        }
+        Ok(0)
+    }
+}
+
+// Read past a member's header, after checking it's gzip
+fn read_header(reader: &mut impl BufRead) -> io::Result<()> {
+    let mut magic = [0; 2];
+    if reader.read_exact(&mut magic).is_err() || magic != MAGIC {
+        return Err(invalid("the file isn't gzip"));
+    }
+    let mut header = [0; 8];
+    reader
+        .read_exact(&mut header)
+        .map_err(|_| invalid("the file ends in its header"))?;
+    if header[0] != DEFLATE {
+        return Err(invalid(
+            "the file is compressed with a method other than DEFLATE",
+        ));
+    }
+    let flags = header[1];
+    if flags & FLAG_EXTRA != 0 {
+        let mut len = [0; 2];
+        reader.read_exact(&mut len)?;
+        io::copy(
+            &mut reader.take(u16::from_le_bytes(len) as u64),
+            &mut io::sink(),
+        )?;
+    }
+    for flag in [FLAG_NAME, FLAG_COMMENT] {
+        if flags & flag != 0 {
+            // Text ending in a zero byte
+            reader.read_until(0, &mut Vec::new())?;
+        }
+    }
+    if flags & FLAG_HEADER_CRC != 0 {
+        reader.read_exact(&mut [0; 2])?;
+    }
+    Ok(())
+}
+
+/// Compresses what's written to it as a gzip file. `finish` writes the end
+/// of the file, without which it can't be read.
+pub(super) struct GzipWriter<W> {
+    deflate: Deflate<W>,
+    crc: u32,
+    size: u32,
+}
+
+impl<W: Write> GzipWriter<W> {
+    pub(super) fn new(mut writer: W) -> io::Result<Self> {
+        // No name or time, and an unknown operating system
+        writer.write_all(&[MAGIC[0], MAGIC[1], DEFLATE, 0, 0, 0, 0, 0, 0, 255])?;
+        Ok(GzipWriter {
+            deflate: Deflate::new(writer),
+            crc: 0,
+            size: 0,
+        })
+    }
+
+    /// Compress the rest of what's been written, write the checksum after
+    /// it, and give back the writer
+    pub(super) fn finish(self) -> io::Result<W> {
+        let mut writer = self.deflate.finish()?;
+        writer.write_all(&self.crc.to_le_bytes())?;
+        writer.write_all(&self.size.to_le_bytes())?;
+        Ok(writer)
+    }
+}
+
+impl<W: Write> Write for GzipWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.deflate.write(buf)?;
+        self.crc = crc32(self.crc, &buf[..n]);
+        self.size = self.size.wrapping_add(n as u32);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.deflate.flush()
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("gzip: {}", message))
+}
+
+// The CRC-32 of each byte, for working out a checksum a byte at a time
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+// The CRC-32 of some data following data with the checksum `crc`
+fn crc32(crc: u32, data: &[u8]) -> u32 {
+    let crc = data.iter().fold(!crc, |crc, &byte| {
+        CRC_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    });
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gunzip(data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        GzipReader::new(data)?.read_to_end(&mut out)?;
+        Ok(out)
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(0, b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(crc32(0, b"1234"), b"56789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn test_read_gzip() {
+        // Python's gzip.compress(b"hello hello hello\n", mtime=0)
+        let hello = [
+            0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xcb, 0x48, 0xcd, 0xc9,
+            0xc9, 0x57, 0xc8, 0x40, 0x90, 0x5c, 0x00, 0x3b, 0x7c, 0x8a, 0xdf, 0x12, 0x00, 0x00,
+            0x00,
+        ];
+        assert_eq!(gunzip(&hello).unwrap(), b"hello hello hello\n");
+
+        // Two members read as one
+        let twice = [&hello[..], &hello[..]].concat();
+        assert_eq!(gunzip(&twice).unwrap(), b"hello hello hello\n".repeat(2));
+
+        let mut corrupt = hello;
+        corrupt[22] ^= 1;
+        assert!(gunzip(&corrupt).is_err());
+        assert!(gunzip(&hello[..20]).is_err());
+        assert!(gunzip(b"hello").is_err());
+    }
+
+    #[test]
+    fn test_damaged_files() {
+        let hello = [
+            0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xcb, 0x48, 0xcd, 0xc9,
+            0xc9, 0x57, 0xc8, 0x40, 0x90, 0x5c, 0x00, 0x3b, 0x7c, 0x8a, 0xdf, 0x12, 0x00, 0x00,
+            0x00,
+        ];
+        for len in 1..hello.len() {
+            assert!(gunzip(&hello[..len]).is_err(), "{:?}", &hello[..len]);
+        }
+        // Every byte changed, which reads or errs but doesn't panic
+        for i in 0..hello.len() {
+            for change in [0x01, 0x80, 0xff] {
+                let mut damaged = hello;
+                damaged[i] ^= change;
+                let _ = gunzip(&damaged);
+            }
+        }
+
+        // An extra field longer than the file
+        let mut extra = hello.to_vec();
+        extra[3] = FLAG_EXTRA;
+        extra.splice(10..10, [0xff, 0xff, 0x01]);
+        assert!(gunzip(&extra).is_err());
+
+        // A size that doesn't match the data
+        let mut size = hello;
+        size[25] = 0x13;
+        assert!(gunzip(&size).is_err());
+
+        let mut trailing = hello.to_vec();
+        trailing.extend_from_slice(b"junk");
+        assert!(gunzip(&trailing).is_err());
+    }
+
+    #[test]
+    fn test_write_and_read_back() {
+        let data: Vec<u8> = (0..200_000u32)
+            .flat_map(|i| format!("row {}\n", i % 1000).into_bytes())
+            .collect();
+        let mut writer = GzipWriter::new(Vec::new()).unwrap();
+        writer.write_all(&data).unwrap();
+        let compressed = writer.finish().unwrap();
+        assert!(compressed.len() < data.len() / 4);
+        assert_eq!(gunzip(&compressed).unwrap(), data);
+    }
+}
@@ -0,0 +1,527 @@
+// io/inflate.rs
+
+//! Decompression of DEFLATE data (RFC 1951), as found inside zip archives
+//! such as Excel workbooks, and inside gzip files.
+use std::io::{self, BufRead, Read};
+
+// Base lengths and extra bits of the length codes 257 to 285
+pub(super) const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+pub(super) const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+// Base distances and extra bits of the distance codes 0 to 29
+pub(super) const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+pub(super) const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+// The order code length code lengths come in, in a dynamic block's header
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// The bytes DEFLATE data decompresses to, which must be `size` of them.
+/// Decompressing stops once there are more, so data that says it's small
+/// can't fill memory.
+#[cfg(any(feature = "excel", test))]
+pub(super) fn inflate(data: &[u8], size: usize) -> Result<Vec<u8>, String> {
+    // DEFLATE can't shrink data more than 1032 times
+    let mut out = Vec::with_capacity(size.min(data.len().saturating_mul(1032)));
+    Inflate::new(data)
+        .take((size as u64).saturating_add(1))
+        .read_to_end(&mut out)
+        .map_err(|e| e.to_string())?;
+    match out.len() {
+        len if len > size => Err(format!("it decompresses to more than {} bytes", size)),
+        len if len < size => Err(format!(
+            "it decompresses to {} bytes rather than {}",
+            len, size
+        )),
+        _ => Ok(out),
+    }
+}
+
+// How far back a copy can reach
+const WINDOW: usize = 32_768;
+
+/// Decompresses DEFLATE data from a reader as it's read, holding no more of
+/// the output than copies can refer back to
+pub(super) struct Inflate<R> {
+    bits: Bits<R>,
+    // The output copies can still refer back to, then the output not yet read
+    out: Vec<u8>,
+    // Where in `out` the output not yet read starts
+    read: usize,
+    // How much output has been let go of from the front of `out`
+    dropped: usize,
+    block: Block,
+    // Whether the block being read is the last
+    last: bool,
+}
+
+// Where the decoder is in the data
+enum Block {
+    /// Before a block's header
+    Header,
+    /// In a stored block, with this many bytes of it left
+    Stored(usize),
+    Coded(Huffman, Huffman),
+    /// Past the last block
+    Done,
+}
+
+impl<R: BufRead> Inflate<R> {
+    pub(super) fn new(reader: R) -> Self {
+        Inflate {
+            bits: Bits {
+                reader,
+                buffer: 0,
+                count: 0,
+            },
+            out: Vec::new(),
+            read: 0,
+            dropped: 0,
+            block: Block::Header,
+            last: false,
+        }
+    }
+
+    /// The reader, just past the end of the DEFLATE data once it's all read
+    pub(super) fn into_inner(self) -> R {
+        self.bits.reader
+    }
+
+    // Decode some more output, up to the end of a block at most
+    fn decode(&mut self) -> Result<(), String> {
+        let Inflate {
+            bits, out, block, ..
+        } = self;
+        match block {
+            Block::Header if self.last => *block = Block::Done,
+            Block::Header => {
+                self.last = bits.take(1)? == 1;
+                *block = match bits.take(2)? {
+                    0 => {
+                        // Stored: the bytes as they are, after their length
+                        // and its complement
+                        bits.align();
+                        let len = bits.take(16)?;
+                        if bits.take(16)? != !len & 0xffff {
+                            return Err(
+                                "a stored block's length doesn't match its check".to_string()
+                            );
+                        }
+                        Block::Stored(len as usize)
+                    }
+                    1 => {
+                        let mut lengths = [0u8; 288];
+                        lengths[..144].fill(8);
+                        lengths[144..256].fill(9);
+                        lengths[256..280].fill(7);
+                        lengths[280..].fill(8);
+                        Block::Coded(Huffman::new(&lengths)?, Huffman::new(&[5; 30])?)
+                    }
+                    2 => {
+                        let (literals, distances) = dynamic_codes(bits)?;
+                        Block::Coded(literals, distances)
+                    }
+                    _ => return Err("a block has an unknown type".to_string()),
+                };
+            }
+            Block::Stored(0) => *block = Block::Header,
+            Block::Stored(left) => {
+                for _ in 0..(*left).min(WINDOW) {
+                    out.push(bits.take(8)? as u8);
+                    *left -= 1;
+                }
+            }
+            Block::Coded(literals, distances) => {
+                let end = out.len() + WINDOW;
+                while out.len() < end {
+                    match copy_or_literal(bits, out, self.dropped, literals, distances)? {
+                        true => continue,
+                        false => {
+                            *block = Block::Header;
+                            break;
+                        }
+                    }
+                }
+            }
+            Block::Done => {}
+        }
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Read for Inflate<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.out.len() - self.read < buf.len() && !matches!(self.block, Block::Done) {
+            self.decode()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        }
+        let n = buf.len().min(self.out.len() - self.read);
+        buf[..n].copy_from_slice(&self.out[self.read..self.read + n]);
+        self.read += n;
+        // Let go of the output that's been read and is out of reach of copies
+        if self.read > 2 * WINDOW {
+            let drop = self.read - WINDOW;
+            self.out.drain(..drop);
+            self.read -= drop;
+            self.dropped += drop;
+        }
+        Ok(n)
+    }
+}
+
+// Bits of the input, lowest first
+struct Bits<R> {
+    reader: R,
+    buffer: u32,
+    count: u32,
+}
+
+impl<R: BufRead> Bits<R> {
+    fn take(&mut self, n: u32) -> Result<u32, String> {
+        while self.count < n {
+            let byte = match self.reader.fill_buf() {
+                Ok([byte, ..]) => *byte,
+                Ok([]) => return Err("the compressed data ends early".to_string()),
+                Err(e) => return Err(e.to_string()),
+            };
+            self.reader.consume(1);
+            self.buffer |= (byte as u32) << self.count;
+            self.count += 8;
+        }
+        let value = self.buffer & ((1u64 << n) - 1) as u32;
+        self.buffer = self.buffer.checked_shr(n).unwrap_or(0);
+        self.count -= n;
+        Ok(value)
+    }
+
+    // Skip to the next whole byte
+    fn align(&mut self) {
+        let partial = self.count % 8;
+        self.buffer >>= partial;
+        self.count -= partial;
+    }
+}
+
+// A canonical Huffman code, as the number of codes of each length and the
+// symbols in code order
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, String> {
+        let mut counts = [0u16; 16];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                return Err("a Huffman code has too many codes".to_string());
+            }
+        }
+        let mut offsets = [0u16; 16];
+        for len in 1..15 {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[offsets[len as usize] as usize] = symbol as u16;
+                offsets[len as usize] += 1;
+            }
+        }
+        Ok(Huffman { counts, symbols })
+    }
+
+    fn decode<R: BufRead>(&self, bits: &mut Bits<R>) -> Result<u16, String> {
+        // The first code of each length, and the index of its symbol
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for &count in &self.counts[1..] {
+            code |= bits.take(1)? as i32;
+            let count = count as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err("the compressed data has a code that isn't in its table".to_string())
+    }
+}
+
+// The codes of a dynamic block, which its header describes
+fn dynamic_codes<R: BufRead>(bits: &mut Bits<R>) -> Result<(Huffman, Huffman), String> {
+    let literal_count = bits.take(5)? as usize + 257;
+    let distance_count = bits.take(5)? as usize + 1;
+    let code_length_count = bits.take(4)? as usize + 4;
+    let mut code_lengths = [0u8; 19];
+    for &i in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[i] = bits.take(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths)?;
+
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let (len, repeat) = match code_lengths.decode(bits)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths.last().ok_or("a length repeats before the first")?;
+                (previous, bits.take(2)? + 3)
+            }
+            17 => (0, bits.take(3)? + 3),
+            _ => (0, bits.take(7)? + 11),
+        };
+        if lengths.len() + repeat as usize > literal_count + distance_count {
+            return Err("a block's code lengths run past its codes".to_string());
+        }
+        lengths.extend(std::iter::repeat_n(len, repeat as usize));
+    }
+    if lengths[256] == 0 {
+        return Err("a block has no code for its end".to_string());
+    }
+    let literals = Huffman::new(&lengths[..literal_count])?;
+    let distances = Huffman::new(&lengths[literal_count..])?;
+    Ok((literals, distances))
+}
+
+// Decode a literal byte or a copy of earlier bytes into the output, or
+// return false at the end of the block
+fn copy_or_literal<R: BufRead>(
+    bits: &mut Bits<R>,
+    out: &mut Vec<u8>,
+    dropped: usize,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<bool, String> {
+    let symbol = literals.decode(bits)? as usize;
+    match symbol {
+        0..=255 => out.push(symbol as u8),
+        256 => return Ok(false),
+        _ => {
+            let i = symbol - 257;
+            if i >= LENGTH_BASE.len() {
+                return Err("the compressed data has an unknown length code".to_string());
+            }
+            let len = LENGTH_BASE[i] as usize + bits.take(LENGTH_EXTRA[i] as u32)? as usize;
+            let i = distances.decode(bits)? as usize;
+            if i >= DISTANCE_BASE.len() {
+                return Err("the compressed data has an unknown distance code".to_string());
+            }
+            let distance =
+                DISTANCE_BASE[i] as usize + bits.take(DISTANCE_EXTRA[i] as u32)? as usize;
+            if distance > out.len() + dropped {
+                return Err("the compressed data refers back past its start".to_string());
+            }
+            let start = out.len() - distance;
+            for i in 0..len {
+                out.push(out[start + i]);
+            }
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_and_dynamic_blocks() {
+        // zlib's raw deflate of "hello hello hello", with fixed codes
+        let fixed = [0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x90, 0x00];
+        assert_eq!(inflate(&fixed, 17).unwrap(), b"hello hello hello");
+
+        // And of some cells of a worksheet, with codes of its own
+        let dynamic = [
+            0x5d, 0xce, 0xcb, 0x09, 0xc0, 0x30, 0x0c, 0x03, 0xd0, 0x55, 0x4a, 0x16, 0xc8, 0xdf,
+            0x4d, 0xc0, 0x35, 0x64, 0x96, 0x6c, 0xd0, 0x43, 0xe6, 0x2f, 0xc8, 0xd0, 0x83, 0xae,
+            0x0f, 0x21, 0x49, 0xf7, 0xf5, 0x3e, 0x61, 0xa5, 0x60, 0x7a, 0x2c, 0x69, 0x3c, 0xa6,
+            0x71, 0x9b, 0xba, 0x66, 0x68, 0x26, 0x2d, 0xd0, 0x46, 0x5a, 0xa1, 0x93, 0xb4, 0x79,
+            0x83, 0x10, 0x77, 0x70, 0xe9, 0xc4, 0x02, 0xae, 0x9c, 0xbe, 0x7d, 0x90, 0xbb, 0x07,
+            0x58, 0xf8, 0xc8, 0x04, 0x8f, 0xff, 0xf5, 0x07,
+        ];
+        let cells: String = (0..10)
+            .map(|i| format!("<c r=\"A{}\"><v>{}</v></c>", i, i * i))
+            .collect();
+        assert_eq!(inflate(&dynamic, cells.len()).unwrap(), cells.as_bytes());
+
+        // Stored, "abc"
+        assert_eq!(
+            inflate(&[0x01, 0x03, 0x00, 0xfc, 0xff, b'a', b'b', b'c'], 3).unwrap(),
+            b"abc"
+        );
+        assert!(inflate(&fixed[..4], 17).is_err());
+        assert!(inflate(&dynamic[..40], cells.len()).is_err());
+    }
+
+    // Bits written lowest first, as DEFLATE reads them
+    #[derive(Default)]
+    struct BitWriter {
+        bytes: Vec<u8>,
+        count: usize,
+    }
+
+    impl BitWriter {
+        fn push(&mut self, value: u32, bits: usize) -> &mut Self {
+            for bit in 0..bits {
+                if self.count.is_multiple_of(8) {
+                    self.bytes.push(0);
+                }
+                let last = self.bytes.len() - 1;
+                self.bytes[last] |=
+                    ((value.checked_shr(bit as u32).unwrap_or(0) & 1) as u8) << (self.count % 8);
+                self.count += 1;
+            }
+            self
+        }
+
+        // A Huffman code, which is read from its highest bit
+        fn code(&mut self, code: u32, bits: usize) -> &mut Self {
+            for bit in (0..bits).rev() {
+                self.push(code >> bit & 1, 1);
+            }
+            self
+        }
+
+        // The start of the last block, dynamic, with 257 literal and 1
+        // distance codes and the code length code's lengths in header order
+        fn dynamic(lengths: &[u32]) -> Self {
+            let mut bits = BitWriter::default();
+            bits.push(1, 1).push(2, 2).push(0, 5).push(0, 5);
+            bits.push(lengths.len() as u32 - 4, 4);
+            for &len in lengths {
+                bits.push(len, 3);
+            }
+            bits
+        }
+    }
+
+    fn error(data: &[u8], size: usize) -> String {
+        inflate(data, size).unwrap_err()
+    }
+
+    #[test]
+    fn test_truncated_data() {
+        let fixed = [0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x90, 0x00];
+        let stored = [0x01, 0x03, 0x00, 0xfc, 0xff, b'a', b'b', b'c'];
+        for data in [&fixed[..], &stored[..]] {
+            let size = inflate(data, usize::MAX).unwrap_err();
+            assert!(size.contains("bytes rather than"), "{}", size);
+            for len in 0..data.len() {
+                assert!(inflate(&data[..len], 17).is_err(), "{:?}", &data[..len]);
+            }
+        }
+        assert_eq!(error(&fixed[..3], 17), "the compressed data ends early");
+        // A stored block longer than the data after it
+        assert_eq!(
+            error(&[0x01, 0xff, 0x00, 0x00, 0xff, b'a'], 255),
+            "the compressed data ends early"
+        );
+        assert_eq!(
+            error(&[0x01, 0x03, 0x00, 0xfc, 0xfe, b'a', b'b', b'c'], 3),
+            "a stored block's length doesn't match its check"
+        );
+    }
+
+    #[test]
+    fn test_output_is_held_to_its_size() {
+        // 100 KB of zeros in a few hundred bytes
+        let mut bits = BitWriter::default();
+        bits.push(1, 1).push(1, 2).code(0x30, 8);
+        for _ in 0..400 {
+            // Length 258, then distance 1
+            bits.code(0xc5, 8).code(0, 5);
+        }
+        bits.code(0, 7);
+        assert_eq!(inflate(&bits.bytes, 400 * 258 + 1).unwrap().len(), 103_201);
+        assert_eq!(
+            error(&bits.bytes, 1000),
+            "it decompresses to more than 1000 bytes"
+        );
+    }
+
+    #[test]
+    fn test_bad_huffman_tables() {
+        assert_eq!(error(&[0x07], 0), "a block has an unknown type");
+
+        // Nineteen code length codes of one bit each
+        let over = BitWriter::dynamic(&[1; 19]);
+        assert_eq!(error(&over.bytes, 0), "a Huffman code has too many codes");
+
+        // Codes for the lengths 0 and 1 only, and every length 0, so nothing
+        // codes the end of the block
+        let mut lengths = [0; 18];
+        lengths[3] = 1;
+        lengths[17] = 1;
+        let mut no_end = BitWriter::dynamic(&lengths);
+        no_end.push(0, 258);
+        assert_eq!(error(&no_end.bytes, 0), "a block has no code for its end");
+
+        // A repeat of the previous length, with none before it
+        let mut early = BitWriter::dynamic(&[1, 0, 0, 1]);
+        early.code(1, 1).push(0, 2);
+        assert_eq!(error(&early.bytes, 0), "a length repeats before the first");
+
+        // Zeros repeated past the number of codes
+        let mut long = BitWriter::dynamic(&[0, 0, 1, 1]);
+        for _ in 0..3 {
+            long.code(1, 1).push(127, 7);
+        }
+        assert_eq!(
+            error(&long.bytes, 0),
+            "a block's code lengths run past its codes"
+        );
+
+        // A code of one bit for length 0, so a 1 matches nothing
+        let mut missing = BitWriter::dynamic(&[0, 0, 0, 1]);
+        missing.push(1, 1).push(0, 15);
+        assert_eq!(
+            error(&missing.bytes, 0),
+            "the compressed data has a code that isn't in its table"
+        );
+
+        // A fixed block copying from before its start
+        let mut back = BitWriter::default();
+        back.push(1, 1).push(1, 2).code(1, 7).code(0, 5).code(0, 7);
+        assert_eq!(
+            error(&back.bytes, 3),
+            "the compressed data refers back past its start"
+        );
+
+        // Length and distance codes DEFLATE leaves unused, the fixed table
+        // leaving out the distance codes 30 and 31
+        let mut length = BitWriter::default();
+        length.push(1, 1).push(1, 2).code(0x30, 8).code(0xc6, 8);
+        assert_eq!(
+            error(&length.bytes, 1),
+            "the compressed data has an unknown length code"
+        );
+        let mut distance = BitWriter::default();
+        distance
+            .push(1, 1)
+            .push(1, 2)
+            .code(0x30, 8)
+            .code(1, 7)
+            .code(30, 5)
+            .push(0, 16);
+        assert_eq!(
+            error(&distance.bytes, 4),
+            "the compressed data has a code that isn't in its table"
+        );
+    }
+}
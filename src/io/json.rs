@@ -0,0 +1,514 @@
+// io/json.rs
+
+//! Tables from and to JSON files, behind the `serde` feature.
+//!
+//! A file is either an array of objects, one per row, or an object of arrays,
+//! one per column; reading tells them apart by the outermost bracket. Column
+//! order is the order keys first appear in the file. Cells are JSON scalars:
+//! numbers, strings, booleans and null. A nested array or object is an error
+//! naming where it is, as is an integer too big to hold exactly.
+//!
+//! NDJSON, also called JSON Lines, has one record per line instead, so a file
+//! can be read a line at a time.
+use super::{open, Compression, Output};
+use crate::error::DaxError;
+use crate::table::ColumnValues;
+use crate::types::{currency_amount, Value};
+use crate::Table;
+use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+use serde_json::Value as Json;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufWriter, Write};
+use std::path::Path;
+
+/// How a table is laid out as JSON
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JsonLayout {
+    /// An array with an object per row, `[{"Product": "Pear", "Sales": 3}]`
+    #[default]
+    Records,
+    /// An object with an array per column, `{"Product": ["Pear"], "Sales": [3]}`
+    Columns,
+}
+
+/// Read a JSON file into a table, in either layout.
+///
+/// With records, a key some objects have and others don't is Null where it's
+/// missing. With columns, every array must be as long as the others.
+///
+/// # Examples
+///
+/// ```
+/// use dax_rust::io::read_json;
+/// use dax_rust::Value;
+///
+/// let path = std::env::temp_dir().join("dax_rust_read_json_doc.json");
+/// std::fs::write(&path, r#"[{"Product": "Pear", "Sales": 3}, {"Product": "Plum"}]"#).unwrap();
+/// let table = read_json(&path).unwrap();
+/// std::fs::remove_file(&path).unwrap();
+/// assert_eq!(table.column_names(), ["Product", "Sales"]);
+/// assert_eq!(table.get("Sales", 1), Some(Value::Null));
+/// ```
+pub fn read_json(path: &Path) -> Result<Table, DaxError> {
+    let document: Document = serde_json::from_reader(open(path)?)
+        .map_err(|e| DaxError::ParseError(format!("JSON: {}", e)))?;
+    match document {
+        Document::Records(rows) => from_records(rows),
+        Document::Columns(columns) => from_columns(columns),
+    }
+}
+
+/// Write a table to a JSON file as an array of records.
+/// See `write_json_with` for how values are written.
+pub fn write_json(table: &Table, path: &Path) -> Result<(), DaxError> {
+    write_json_with(table, path, JsonLayout::Records)
+}
+
+/// Write a table to a JSON file in the given layout, with columns in order.
+///
+/// Null is `null`, and so are NaN and the infinities, which JSON has no
+/// numbers for. Amounts of currency are written as plain numbers and dates
+/// as ISO text, so they read back as numbers and text.
+pub fn write_json_with(table: &Table, path: &Path, layout: JsonLayout) -> Result<(), DaxError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    match layout {
+        JsonLayout::Records => serde_json::to_writer(&mut writer, &Records(table)),
+        JsonLayout::Columns => serde_json::to_writer(&mut writer, &Columns(table)),
+    }
+    .map_err(|e| DaxError::IoError(e.into()))?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// How `read_ndjson_with` reads a file and `write_ndjson_with` writes one
+#[derive(Debug, Clone, Default)]
+pub struct NdjsonOptions {
+    /// Whether a line that isn't a JSON object of scalars is logged as a
+    /// warning and left out, rather than being an error
+    pub skip_malformed: bool,
+    /// How `write_ndjson_with` compresses the file. Reading goes by the
+    /// file's name instead.
+    pub compression: Compression,
+}
+
+impl NdjsonOptions {
+    pub fn skip_malformed(mut self, skip_malformed: bool) -> Self {
+        self.skip_malformed = skip_malformed;
+        self
+    }
+
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+}
+
+/// Read an NDJSON file, one record per line, into a table.
+/// See `read_ndjson_with` for the details.
+pub fn read_ndjson(path: &Path) -> Result<Table, DaxError> {
+    read_ndjson_with(path, &NdjsonOptions::default())
+}
+
+/// Read an NDJSON file into a table as `options` say.
+///
+/// Each line is a JSON object, read as a row the way `read_json` reads
+/// records: there's a column for every key any line has, in the order they
+/// first appear, and a line without a key is Null there. Blank lines are
+/// skipped. A malformed line is an error giving its line number, or is left
+/// out if `skip_malformed` is on. A file whose name ends in `.gz` is
+/// decompressed as it's read, as is one ending in `.zst` with the `zstd`
+/// feature.
+///
+/// # Examples
+///
+/// ```
+/// use dax_rust::io::read_ndjson;
+/// use dax_rust::Value;
+///
+/// let path = std::env::temp_dir().join("dax_rust_read_ndjson_doc.ndjson");
+/// std::fs::write(&path, "{\"level\": \"info\"}\n{\"level\": \"warn\", \"code\": 7}\n").unwrap();
+/// let table = read_ndjson(&path).unwrap();
+/// std::fs::remove_file(&path).unwrap();
+/// assert_eq!(table.column_names(), ["level", "code"]);
+/// assert_eq!(table.get("code", 0), Some(Value::Null));
+/// ```
+pub fn read_ndjson_with(path: &Path, options: &NdjsonOptions) -> Result<Table, DaxError> {
+    NdjsonReader::new(open(path)?, options).read(usize::MAX)
+}
+
+/// Read an NDJSON file a chunk at a time, as `read_csv_chunks` reads CSV.
+/// See `read_ndjson_chunks_with` for the details.
+pub fn read_ndjson_chunks(
+    path: &Path,
+    chunk_size: usize,
+) -> impl Iterator<Item = Result<Table, DaxError>> {
+    read_ndjson_chunks_with(path, chunk_size, &NdjsonOptions::default())
+}
+
+/// Read an NDJSON file as `options` say, a chunk of at most `chunk_size`
+/// rows at a time.
+///
+/// A chunk has a column for every key seen so far in the file, so a key that
+/// first appears in a later chunk isn't in the chunks before it; putting the
+/// chunks together with `Table::append` gives the same table as reading the
+/// whole file. A file with no rows has no chunks, and an error ends them.
+pub fn read_ndjson_chunks_with(
+    path: &Path,
+    chunk_size: usize,
+    options: &NdjsonOptions,
+) -> impl Iterator<Item = Result<Table, DaxError>> {
+    let mut reader =
+        (chunk_size > 0).then(|| open(path).map(|file| NdjsonReader::new(file, options)));
+    std::iter::from_fn(move || {
+        let chunk = match reader.take()? {
+            Ok(mut ndjson) => {
+                let chunk = ndjson.read(chunk_size);
+                if matches!(&chunk, Ok(table) if table.num_rows() > 0) {
+                    reader = Some(Ok(ndjson));
+                }
+                chunk
+            }
+            Err(error) => Err(error),
+        };
+        match chunk {
+            Ok(table) if table.num_rows() == 0 => None,
+            chunk => Some(chunk),
+        }
+    })
+}
+
+/// Write a table to an NDJSON file, one record per row, with values written
+/// as `write_json_with` writes them
+pub fn write_ndjson(table: &Table, path: &Path) -> Result<(), DaxError> {
+    write_ndjson_with(table, path, &NdjsonOptions::default())
+}
+
+/// Write a table to an NDJSON file, compressed as `options` say
+pub fn write_ndjson_with(
+    table: &Table,
+    path: &Path,
+    options: &NdjsonOptions,
+) -> Result<(), DaxError> {
+    let mut writer = Output::create(path, options.compression)?;
+    let columns = named_columns(table);
+    for row in 0..table.num_rows() {
+        serde_json::to_writer(&mut writer, &Row(&columns, row))
+            .map_err(|e| DaxError::IoError(e.into()))?;
+        writer.write_all(b"\n")?;
+    }
+    writer.finish()
+}
+
+// Reads the lines of NDJSON as rows, some at a time
+struct NdjsonReader<R> {
+    reader: R,
+    options: NdjsonOptions,
+    // Number of the last line read, counting from 1
+    line: usize,
+    rows: KeyedRows,
+}
+
+impl<R: BufRead> NdjsonReader<R> {
+    fn new(reader: R, options: &NdjsonOptions) -> Self {
+        NdjsonReader {
+            reader,
+            options: options.clone(),
+            line: 0,
+            rows: KeyedRows::default(),
+        }
+    }
+
+    // The next rows, at most `rows` of them; none once the file is read
+    fn read(&mut self, rows: usize) -> Result<Table, DaxError> {
+        let mut text = String::new();
+        while self.rows.rows < rows {
+            text.clear();
+            if self.reader.read_line(&mut text)? == 0 {
+                break;
+            }
+            self.line += 1;
+            if text.trim().is_empty() {
+                continue;
+            }
+            let row = serde_json::from_str::<Ordered>(&text)
+                .map_err(|e| e.to_string())
+                .and_then(|Ordered(object)| {
+                    object
+                        .into_iter()
+                        .map(|(name, json)| {
+                            let value = cell(json, || name.clone()).map_err(|e| e.to_string())?;
+                            Ok((name, value))
+                        })
+                        .collect::<Result<Vec<_>, String>>()
+                })
+                .and_then(|row| self.rows.push(row));
+            match row {
+                Ok(()) => {}
+                Err(e) if self.options.skip_malformed => {
+                    log::warn!("Skipped NDJSON line {}: {}", self.line, e);
+                }
+                Err(e) => {
+                    return Err(DaxError::ParseError(format!(
+                        "NDJSON line {}: {}",
+                        self.line, e
+                    )))
+                }
+            }
+        }
+        self.rows.take()
+    }
+}
+
+// Rows of keys and values gathered into columns, one for every key a row
+// has had, with Null where a row doesn't have the key
+#[derive(Default)]
+struct KeyedRows {
+    names: Vec<String>,
+    positions: HashMap<String, usize>,
+    columns: Vec<Vec<Value>>,
+    rows: usize,
+}
+
+impl KeyedRows {
+    fn push(&mut self, row: Vec<(String, Value)>) -> Result<(), String> {
+        let mut keys = HashSet::new();
+        if let Some((name, _)) = row.iter().find(|(name, _)| !keys.insert(name)) {
+            return Err(format!("{} appears twice", name));
+        }
+        for (name, value) in row {
+            let position = match self.positions.get(&name) {
+                Some(&position) => position,
+                None => {
+                    self.positions.insert(name.clone(), self.names.len());
+                    self.names.push(name);
+                    self.columns.push(vec![Value::Null; self.rows]);
+                    self.names.len() - 1
+                }
+            };
+            self.columns[position].push(value);
+        }
+        self.rows += 1;
+        for values in &mut self.columns {
+            values.resize(self.rows, Value::Null);
+        }
+        Ok(())
+    }
+
+    // The rows so far as a table, keeping the keys for the rows after
+    fn take(&mut self) -> Result<Table, DaxError> {
+        let mut table = Table::new();
+        for (name, values) in self.names.iter().zip(&mut self.columns) {
+            table.add_column(name.clone(), std::mem::take(values))?;
+        }
+        self.rows = 0;
+        Ok(table)
+    }
+}
+
+// An object's keys and values, in the order they're written
+type Object = Vec<(String, Json)>;
+
+// A whole file, in one layout or the other
+enum Document {
+    Records(Vec<Object>),
+    Columns(Object),
+}
+
+struct DocumentVisitor;
+
+impl<'de> Visitor<'de> for DocumentVisitor {
+    type Value = Document;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of records or an object of columns")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Document, A::Error> {
+        let mut rows = Vec::new();
+        while let Some(Ordered(row)) = seq.next_element()? {
+            rows.push(row);
+        }
+        Ok(Document::Records(rows))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Document, A::Error> {
+        ObjectVisitor.visit_map(map).map(Document::Columns)
+    }
+}
+
+impl<'de> Deserialize<'de> for Document {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Document, D::Error> {
+        deserializer.deserialize_any(DocumentVisitor)
+    }
+}
+
+// An object read with its keys in order, which serde_json's own map doesn't keep
+struct Ordered(Object);
+
+struct ObjectVisitor;
+
+impl<'de> Visitor<'de> for ObjectVisitor {
+    type Value = Object;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an object for each record")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Object, A::Error> {
+        let mut object = Vec::new();
+        while let Some(entry) = map.next_entry()? {
+            object.push(entry);
+        }
+        Ok(object)
+    }
+}
+
+impl<'de> Deserialize<'de> for Ordered {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Ordered, D::Error> {
+        deserializer.deserialize_map(ObjectVisitor).map(Ordered)
+    }
+}
+
+fn from_records(rows: Vec<Object>) -> Result<Table, DaxError> {
+    let mut keyed = KeyedRows::default();
+    for (index, row) in rows.into_iter().enumerate() {
+        let row = row
+            .into_iter()
+            .map(|(name, json)| {
+                Ok((
+                    name.clone(),
+                    cell(json, || format!("[{}].{}", index, name))?,
+                ))
+            })
+            .collect::<Result<Vec<_>, DaxError>>()?;
+        keyed
+            .push(row)
+            .map_err(|e| DaxError::from(format!("Record {}: {}", index, e)))?;
+    }
+    keyed.take()
+}
+
+fn from_columns(columns: Object) -> Result<Table, DaxError> {
+    let mut table = Table::new();
+    for (name, json) in columns {
+        let Json::Array(items) = json else {
+            return Err(format!("Column {} isn't an array of values", name).into());
+        };
+        let values = items
+            .into_iter()
+            .enumerate()
+            .map(|(row, json)| cell(json, || format!("{}[{}]", name, row)))
+            .collect::<Result<_, _>>()?;
+        table.add_column(name, values)?;
+    }
+    Ok(table)
+}
+
+// A JSON scalar as a value, or an error naming where in the file it is
+fn cell(json: Json, path: impl Fn() -> String) -> Result<Value, DaxError> {
+    match json {
+        Json::Null => Ok(Value::Null),
+        Json::Bool(b) => Ok(Value::Boolean(b)),
+        Json::String(s) => Ok(Value::Text(s)),
+        Json::Number(n) => match (n.as_i64(), n.as_f64()) {
+            (Some(i), _) => Ok(Value::Integer(i)),
+            (None, Some(x)) if !n.is_u64() => Ok(Value::Number(x)),
+            _ => Err(format!(
+                "The JSON number {} at {} is too big to hold exactly",
+                n,
+                path()
+            )
+            .into()),
+        },
+        Json::Array(_) => {
+            Err(format!("Unsupported JSON at {}: a cell can't hold an array", path()).into())
+        }
+        Json::Object(_) => Err(format!(
+            "Unsupported JSON at {}: a cell can't hold an object",
+            path()
+        )
+        .into()),
+    }
+}
+
+// A value as plain JSON
+struct Cell<'a>(&'a Value);
+
+impl Serialize for Cell<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            Value::Number(n) if !n.is_finite() => serializer.serialize_none(),
+            Value::Number(n) => serializer.serialize_f64(*n),
+            Value::Integer(i) => serializer.serialize_i64(*i),
+            Value::Currency(units) => serializer.serialize_f64(currency_amount(*units)),
+            Value::Text(s) => serializer.serialize_str(s),
+            Value::Boolean(b) => serializer.serialize_bool(*b),
+            Value::DateTime(_) => serializer.collect_str(self.0),
+            Value::Null => serializer.serialize_none(),
+        }
+    }
+}
+
+struct Records<'a>(&'a Table);
+
+impl Serialize for Records<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let table = self.0;
+        let columns = named_columns(table);
+        let mut seq = serializer.serialize_seq(Some(table.num_rows()))?;
+        for row in 0..table.num_rows() {
+            seq.serialize_element(&Row(&columns, row))?;
+        }
+        seq.end()
+    }
+}
+
+// Each column's name and values, in order
+fn named_columns(table: &Table) -> Vec<(&String, ColumnValues<'_>)> {
+    table
+        .column_names()
+        .iter()
+        .filter_map(|name| Some((name, table.get_column(name)?)))
+        .collect()
+}
+
+struct Row<'a>(&'a [(&'a String, ColumnValues<'a>)], usize);
+
+impl Serialize for Row<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (name, values) in self.0 {
+            let value = values.get(self.1).unwrap_or(Value::Null);
+            map.serialize_entry(name, &Cell(&value))?;
+        }
+        map.end()
+    }
+}
+
+struct Columns<'a>(&'a Table);
+
+impl Serialize for Columns<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let table = self.0;
+        let mut map = serializer.serialize_map(Some(table.column_names().len()))?;
+        for (name, values) in named_columns(table) {
+            map.serialize_entry(name, &Cells(values))?;
+        }
+        map.end()
+    }
+}
+
+struct Cells<'a>(ColumnValues<'a>);
+
+impl Serialize for Cells<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+        for value in self.0 {
+            seq.serialize_element(&Cell(&value))?;
+        }
+        seq.end()
+    }
+}
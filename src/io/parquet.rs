@@ -0,0 +1,932 @@
+// io/parquet.rs
+
+//! Tables from and to Parquet files, behind the `parquet` feature.
+//!
+//! Files are written uncompressed, in row groups of up to a million rows with
+//! one page per column, every column optional so that it can hold Nulls and
+//! its values PLAIN encoded. Reading takes uncompressed files with a flat
+//! schema, values PLAIN or dictionary encoded, in version 1 or 2 data pages,
+//! which is what most writers produce with compression turned off. A
+//! compressed file, another encoding, or a nested column such as a list or a
+//! struct is an error saying so.
+mod thrift;
+
+use self::thrift::{write_struct, Reader, Thrift};
+use crate::error::DaxError;
+use crate::table::ColumnValues;
+use crate::types::{DType, Value};
+use crate::Table;
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, NaiveTime};
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"PAR1";
+const ROW_GROUP_ROWS: usize = 1 << 20;
+// Days from 1 January of year 1 to 1 January 1970
+const UNIX_EPOCH_DAYS_FROM_CE: i32 = 719_163;
+// Julian day number of 1 January 1970, which INT96 timestamps count from
+const UNIX_EPOCH_JULIAN_DAY: i64 = 2_440_588;
+
+// Physical types
+const BOOLEAN: i64 = 0;
+const INT32: i64 = 1;
+const INT64: i64 = 2;
+const INT96: i64 = 3;
+const FLOAT: i64 = 4;
+const DOUBLE: i64 = 5;
+const BYTE_ARRAY: i64 = 6;
+
+// Converted types, the older annotations on physical types
+const UTF8: i64 = 0;
+const DECIMAL: i64 = 5;
+const DATE: i64 = 6;
+const TIMESTAMP_MILLIS: i64 = 9;
+const TIMESTAMP_MICROS: i64 = 10;
+
+// Encodings
+const PLAIN: i64 = 0;
+const PLAIN_DICTIONARY: i64 = 2;
+const RLE: i64 = 3;
+const RLE_DICTIONARY: i64 = 8;
+
+// Page types
+const DATA_PAGE: i64 = 0;
+const DICTIONARY_PAGE: i64 = 2;
+const DATA_PAGE_V2: i64 = 3;
+
+// Repetition of a schema element
+const REQUIRED: i64 = 0;
+const OPTIONAL: i64 = 1;
+
+/// Read a Parquet file into a table, a column per leaf of its schema.
+///
+/// INT32 and INT64 columns read as integers, FLOAT and DOUBLE as numbers,
+/// BOOLEAN as booleans and BYTE_ARRAY as text. Dates and timestamps read as
+/// dates, decimals with four places as currency and other decimals as
+/// numbers. Nulls are Null.
+pub fn read_parquet(path: &Path) -> Result<Table, DaxError> {
+    let mut file = File::open(path)?;
+    read_file(&mut file).map_err(|e| DaxError::ParseError(format!("Parquet: {}", e)))
+}
+
+/// Write a table to a Parquet file, keeping each column's type.
+///
+/// Integers are written as INT64, numbers as DOUBLE, currency as a DECIMAL
+/// with four places, text as UTF-8 strings and booleans as BOOLEAN. A column
+/// of dates that all fall at midnight is a DATE, and one with times a
+/// TIMESTAMP in microseconds. A column of integers and numbers is written as
+/// numbers; one that mixes other kinds of value can't be written, and is an
+/// error until it's cast to one type.
+///
+/// # Examples
+///
+/// ```
+/// use dax_rust::io::{read_parquet, write_parquet};
+/// use dax_rust::{Table, Value};
+///
+/// let mut table = Table::new();
+/// table.add_column("Product".to_string(), vec!["Pear".into(), Value::Null]).unwrap();
+/// table.add_column("Qty".to_string(), vec![Value::Integer(3), Value::Integer(10)]).unwrap();
+/// let path = std::env::temp_dir().join("dax_rust_write_parquet_doc.parquet");
+/// write_parquet(&table, &path).unwrap();
+/// let back = read_parquet(&path).unwrap();
+/// std::fs::remove_file(&path).unwrap();
+/// assert_eq!(back, table);
+/// ```
+pub fn write_parquet(table: &Table, path: &Path) -> Result<(), DaxError> {
+    let mut columns = Vec::new();
+    for name in table.column_names() {
+        if let Some(values) = table.get_column(name) {
+            columns.push((name, values, kind_to_write(name, values)?));
+        }
+    }
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(MAGIC)?;
+    let mut offset = MAGIC.len();
+    let rows = table.num_rows();
+    let mut row_groups = Vec::new();
+    for start in (0..rows).step_by(ROW_GROUP_ROWS) {
+        let end = rows.min(start + ROW_GROUP_ROWS);
+        let mut chunks = Vec::new();
+        let mut group_size = 0;
+        for (name, values, kind) in &columns {
+            let page = encode_page(
+                &values
+                    .iter()
+                    .skip(start)
+                    .take(end - start)
+                    .collect::<Vec<_>>(),
+                *kind,
+            );
+            let mut header = Vec::new();
+            write_struct(
+                &mut header,
+                &[
+                    (1, Thrift::I32(DATA_PAGE as i32)),
+                    (2, Thrift::I32(page.len() as i32)),
+                    (3, Thrift::I32(page.len() as i32)),
+                    (
+                        5,
+                        Thrift::Struct(vec![
+                            (1, Thrift::I32((end - start) as i32)),
+                            (2, Thrift::I32(PLAIN as i32)),
+                            (3, Thrift::I32(RLE as i32)),
+                            (4, Thrift::I32(RLE as i32)),
+                        ]),
+                    ),
+                ],
+            );
+            writer.write_all(&header)?;
+            writer.write_all(&page)?;
+            let size = (header.len() + page.len()) as i64;
+            let metadata = Thrift::Struct(vec![
+                (1, Thrift::I32(kind.physical() as i32)),
+                (
+                    2,
+                    Thrift::List(vec![Thrift::I32(PLAIN as i32), Thrift::I32(RLE as i32)]),
+                ),
+                (
+                    3,
+                    Thrift::List(vec![Thrift::Binary(name.as_bytes().to_vec())]),
+                ),
+                (4, Thrift::I32(0)),
+                (5, Thrift::I64((end - start) as i64)),
+                (6, Thrift::I64(size)),
+                (7, Thrift::I64(size)),
+                (9, Thrift::I64(offset as i64)),
+            ]);
+            chunks.push(Thrift::Struct(vec![
+                (2, Thrift::I64(offset as i64)),
+                (3, metadata),
+            ]));
+            offset += size as usize;
+            group_size += size;
+        }
+        row_groups.push(Thrift::Struct(vec![
+            (1, Thrift::List(chunks)),
+            (2, Thrift::I64(group_size)),
+            (3, Thrift::I64((end - start) as i64)),
+        ]));
+    }
+
+    let mut schema = vec![Thrift::Struct(vec![
+        (4, Thrift::Binary(b"schema".to_vec())),
+        (5, Thrift::I32(columns.len() as i32)),
+    ])];
+    schema.extend(
+        columns
+            .iter()
+            .map(|(name, _, kind)| schema_element(name, *kind)),
+    );
+    let mut footer = Vec::new();
+    write_struct(
+        &mut footer,
+        &[
+            (1, Thrift::I32(1)),
+            (2, Thrift::List(schema)),
+            (3, Thrift::I64(rows as i64)),
+            (4, Thrift::List(row_groups)),
+            (6, Thrift::Binary(b"dax-rust".to_vec())),
+        ],
+    );
+    writer.write_all(&footer)?;
+    writer.write_all(&(footer.len() as u32).to_le_bytes())?;
+    writer.write_all(MAGIC)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// What a column's values are, as stored and as read
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Boolean,
+    Int32,
+    Int64,
+    Float,
+    Double,
+    Text,
+    /// INT64 decimal with four places
+    Currency,
+    /// Decimal with some other number of places
+    Decimal32(i32),
+    Decimal64(i32),
+    /// INT32 days since 1970
+    Date,
+    /// INT64 time since 1970, in units of this many to a second
+    Timestamp(i64),
+    /// INT96 nanoseconds of the day and Julian day
+    Int96,
+}
+
+impl Kind {
+    fn physical(self) -> i64 {
+        match self {
+            Kind::Boolean => BOOLEAN,
+            Kind::Int32 | Kind::Decimal32(_) | Kind::Date => INT32,
+            Kind::Int64 | Kind::Currency | Kind::Decimal64(_) | Kind::Timestamp(_) => INT64,
+            Kind::Int96 => INT96,
+            Kind::Float => FLOAT,
+            Kind::Double => DOUBLE,
+            Kind::Text => BYTE_ARRAY,
+        }
+    }
+}
+
+// How a column is written, or an error if its values are of kinds that
+// can't share one Parquet type
+fn kind_to_write(name: &str, values: ColumnValues) -> Result<Kind, DaxError> {
+    Ok(match DType::infer(values) {
+        DType::Boolean => Kind::Boolean,
+        DType::Integer => Kind::Int64,
+        DType::Number => Kind::Double,
+        DType::Currency => Kind::Currency,
+        DType::Text | DType::Null => Kind::Text,
+        DType::Date => {
+            let midnight = values.iter().all(|value| match value {
+                Value::DateTime(d) => d.time() == NaiveTime::MIN,
+                _ => true,
+            });
+            if midnight {
+                Kind::Date
+            } else {
+                Kind::Timestamp(1_000_000)
+            }
+        }
+        DType::Mixed => {
+            return Err(format!(
+                "Column {} holds more than one kind of value, which a Parquet column can't; \
+                 cast it to one type first",
+                name
+            )
+            .into())
+        }
+    })
+}
+
+fn schema_element(name: &str, kind: Kind) -> Thrift {
+    let mut fields = vec![
+        (1, Thrift::I32(kind.physical() as i32)),
+        (3, Thrift::I32(OPTIONAL as i32)),
+        (4, Thrift::Binary(name.as_bytes().to_vec())),
+    ];
+    match kind {
+        Kind::Text => {
+            fields.push((6, Thrift::I32(UTF8 as i32)));
+            fields.push((10, Thrift::Struct(vec![(1, Thrift::Struct(Vec::new()))])));
+        }
+        Kind::Currency => {
+            fields.push((6, Thrift::I32(DECIMAL as i32)));
+            fields.push((7, Thrift::I32(4)));
+            fields.push((8, Thrift::I32(18)));
+            let decimal = vec![(1, Thrift::I32(4)), (2, Thrift::I32(18))];
+            fields.push((10, Thrift::Struct(vec![(5, Thrift::Struct(decimal))])));
+        }
+        Kind::Date => {
+            fields.push((6, Thrift::I32(DATE as i32)));
+            fields.push((10, Thrift::Struct(vec![(6, Thrift::Struct(Vec::new()))])));
+        }
+        Kind::Timestamp(_) => {
+            fields.push((6, Thrift::I32(TIMESTAMP_MICROS as i32)));
+            let micros = Thrift::Struct(vec![(2, Thrift::Struct(Vec::new()))]);
+            let timestamp = vec![(1, Thrift::Bool(false)), (2, micros)];
+            fields.push((10, Thrift::Struct(vec![(8, Thrift::Struct(timestamp))])));
+        }
+        _ => {}
+    }
+    Thrift::Struct(fields)
+}
+
+// A data page: the definition levels, 1 for a value and 0 for Null, then the
+// values that aren't Null
+fn encode_page(values: &[Value], kind: Kind) -> Vec<u8> {
+    let mut levels = Vec::new();
+    let mut present = values.iter().map(|value| !value.is_null()).peekable();
+    while let Some(level) = present.next() {
+        let mut run = 1u64;
+        while present.next_if_eq(&level).is_some() {
+            run += 1;
+        }
+        write_varint(&mut levels, run << 1);
+        levels.push(level as u8);
+    }
+
+    let mut page = Vec::new();
+    page.extend((levels.len() as u32).to_le_bytes());
+    page.extend(levels);
+    let present = values.iter().filter(|value| !value.is_null());
+    if kind == Kind::Boolean {
+        let mut bits = Vec::new();
+        for (i, value) in present.enumerate() {
+            if i % 8 == 0 {
+                bits.push(0u8);
+            }
+            if matches!(value, Value::Boolean(true)) {
+                bits[i / 8] |= 1 << (i % 8);
+            }
+        }
+        page.extend(bits);
+        return page;
+    }
+    for value in present {
+        match (kind, value) {
+            (Kind::Int64, Value::Integer(i)) | (Kind::Currency, Value::Currency(i)) => {
+                page.extend(i.to_le_bytes())
+            }
+            (Kind::Text, Value::Text(s)) => {
+                page.extend((s.len() as u32).to_le_bytes());
+                page.extend(s.as_bytes());
+            }
+            (Kind::Date, Value::DateTime(d)) => {
+                let days = d.date().num_days_from_ce() - UNIX_EPOCH_DAYS_FROM_CE;
+                page.extend(days.to_le_bytes());
+            }
+            (Kind::Timestamp(_), Value::DateTime(d)) => {
+                page.extend(d.and_utc().timestamp_micros().to_le_bytes())
+            }
+            (_, value) => page.extend(value.as_number().unwrap_or(f64::NAN).to_le_bytes()),
+        }
+    }
+    page
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+// One column of the schema, as read
+struct Leaf {
+    name: String,
+    kind: Kind,
+    optional: bool,
+}
+
+fn read_file(file: &mut File) -> Result<Table, String> {
+    let len = file.seek(SeekFrom::End(0)).map_err(|e| e.to_string())?;
+    if len < 12 {
+        return Err("the file is too short to be Parquet".to_string());
+    }
+    let mut tail = [0u8; 8];
+    read_at(file, len - 8, &mut tail)?;
+    let mut head = [0u8; 4];
+    read_at(file, 0, &mut head)?;
+    if &tail[4..] != MAGIC || &head != MAGIC {
+        return Err("the file doesn't start and end with PAR1".to_string());
+    }
+    let footer_len = u32::from_le_bytes([tail[0], tail[1], tail[2], tail[3]]) as u64;
+    if footer_len + 12 > len {
+        return Err("the metadata is longer than the file".to_string());
+    }
+    let mut footer = vec![0u8; footer_len as usize];
+    read_at(file, len - 8 - footer_len, &mut footer)?;
+    let metadata = Reader::new(&footer).read_struct()?;
+
+    let leaves = read_schema(&metadata)?;
+    let mut columns: Vec<Vec<Value>> = vec![Vec::new(); leaves.len()];
+    let row_groups = metadata.field(4).and_then(Thrift::list).unwrap_or_default();
+    for row_group in row_groups {
+        let chunks = row_group
+            .field(1)
+            .and_then(Thrift::list)
+            .ok_or("a row group has no columns")?;
+        if chunks.len() != leaves.len() {
+            return Err("a row group's columns don't match the schema".to_string());
+        }
+        for ((chunk, leaf), column) in chunks.iter().zip(&leaves).zip(&mut columns) {
+            read_chunk(file, chunk, leaf, column)
+                .map_err(|e| format!("column {}: {}", leaf.name, e))?;
+        }
+    }
+
+    let mut table = Table::new();
+    for (leaf, values) in leaves.into_iter().zip(columns) {
+        table
+            .add_column(leaf.name, values)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(table)
+}
+
+fn read_at(file: &mut File, offset: u64, buf: &mut [u8]) -> Result<(), String> {
+    file.seek(SeekFrom::Start(offset))
+        .and_then(|_| file.read_exact(buf))
+        .map_err(|e| e.to_string())
+}
+
+// The columns of a flat schema, or an error naming the first nested one
+fn read_schema(metadata: &Thrift) -> Result<Vec<Leaf>, String> {
+    let elements = metadata
+        .field(2)
+        .and_then(Thrift::list)
+        .ok_or("the metadata has no schema")?;
+    let mut leaves = Vec::new();
+    // The first element is the root, which holds the rest
+    for element in elements.iter().skip(1) {
+        let name = element
+            .field(4)
+            .and_then(Thrift::binary)
+            .map(|name| String::from_utf8_lossy(name).into_owned())
+            .ok_or("a column has no name")?;
+        if element.field(5).and_then(Thrift::int).unwrap_or(0) > 0 {
+            return Err(format!(
+                "column {} is a nested group, such as a struct, list or map, which a table can't hold",
+                name
+            ));
+        }
+        let repetition = element.field(3).and_then(Thrift::int).unwrap_or(REQUIRED);
+        if repetition != REQUIRED && repetition != OPTIONAL {
+            return Err(format!(
+                "column {} is repeated, which a table can't hold",
+                name
+            ));
+        }
+        let kind = leaf_kind(element).map_err(|e| format!("column {} {}", name, e))?;
+        leaves.push(Leaf {
+            name,
+            kind,
+            optional: repetition == OPTIONAL,
+        });
+    }
+    Ok(leaves)
+}
+
+fn leaf_kind(element: &Thrift) -> Result<Kind, String> {
+    let physical = element
+        .field(1)
+        .and_then(Thrift::int)
+        .ok_or("has no type")?;
+    let converted = element.field(6).and_then(Thrift::int);
+    let logical = element.field(10);
+    let decimal_scale = match (converted, logical.and_then(|logical| logical.field(5))) {
+        (_, Some(decimal)) => decimal.field(1).and_then(Thrift::int),
+        (Some(DECIMAL), None) => Some(element.field(7).and_then(Thrift::int).unwrap_or(0)),
+        _ => None,
+    };
+    let is_date = converted == Some(DATE) || logical.and_then(|logical| logical.field(6)).is_some();
+    let timestamp = match (converted, logical.and_then(|logical| logical.field(8))) {
+        (_, Some(timestamp)) => Some(match timestamp.field(2) {
+            Some(unit) if unit.field(1).is_some() => 1_000,
+            Some(unit) if unit.field(3).is_some() => 1_000_000_000,
+            _ => 1_000_000,
+        }),
+        (Some(TIMESTAMP_MILLIS), None) => Some(1_000),
+        (Some(TIMESTAMP_MICROS), None) => Some(1_000_000),
+        _ => None,
+    };
+
+    Ok(match physical {
+        BOOLEAN => Kind::Boolean,
+        INT32 if is_date => Kind::Date,
+        INT32 => match decimal_scale {
+            Some(scale) => Kind::Decimal32(scale as i32),
+            None => Kind::Int32,
+        },
+        INT64 => match (decimal_scale, timestamp) {
+            (Some(4), _) => Kind::Currency,
+            (Some(scale), _) => Kind::Decimal64(scale as i32),
+            (None, Some(per_second)) => Kind::Timestamp(per_second),
+            (None, None) => Kind::Int64,
+        },
+        INT96 => Kind::Int96,
+        FLOAT => Kind::Float,
+        DOUBLE => Kind::Double,
+        BYTE_ARRAY if decimal_scale.is_some() => {
+            return Err("is a decimal stored as bytes, which isn't supported".to_string())
+        }
+        BYTE_ARRAY => Kind::Text,
+        _ => return Err("is a fixed-length byte array, which isn't supported".to_string()),
+    })
+}
+
+// Read one column chunk's values onto the end of a column
+fn read_chunk(
+    file: &mut File,
+    chunk: &Thrift,
+    leaf: &Leaf,
+    column: &mut Vec<Value>,
+) -> Result<(), String> {
+    let metadata = chunk.field(3).ok_or("a column chunk has no metadata")?;
+    let codec = metadata.field(4).and_then(Thrift::int).unwrap_or(0);
+    if codec != 0 {
+        let name = match codec {
+            1 => "Snappy",
+            2 => "gzip",
+            3 => "LZO",
+            4 => "Brotli",
+            5 | 7 => "LZ4",
+            6 => "Zstandard",
+            _ => "an unknown codec",
+        };
+        return Err(format!(
+            "it's compressed with {}, and only uncompressed files can be read",
+            name
+        ));
+    }
+    let num_values = count(metadata.field(5), "a column chunk")?;
+    let start = metadata
+        .field(11)
+        .or_else(|| metadata.field(9))
+        .and_then(Thrift::int)
+        .ok_or("a column chunk has no pages")?;
+    let size = metadata
+        .field(7)
+        .and_then(Thrift::int)
+        .ok_or("a column chunk has no size")?;
+    // Checked against the file before anything that big is allocated
+    let file_len = file.metadata().map_err(|e| e.to_string())?.len();
+    let (Ok(start), Ok(size)) = (u64::try_from(start), u64::try_from(size)) else {
+        return Err("a column chunk's place in the file is negative".to_string());
+    };
+    if start.saturating_add(size) > file_len {
+        return Err("a column chunk runs past the end of the file".to_string());
+    }
+    let mut bytes = vec![0u8; size as usize];
+    read_at(file, start, &mut bytes)?;
+
+    let wanted = column.len() + num_values;
+    let mut dictionary: Option<Vec<Value>> = None;
+    let mut position = 0;
+    while column.len() < wanted && position < bytes.len() {
+        let mut reader = Reader::new(&bytes[position..]);
+        let header = reader.read_struct()?;
+        position += reader.position();
+        let page_size = header
+            .field(3)
+            .and_then(Thrift::int)
+            .ok_or("a page has no size")?;
+        let page = usize::try_from(page_size)
+            .ok()
+            .and_then(|size| position.checked_add(size))
+            .and_then(|end| bytes.get(position..end))
+            .ok_or("a page runs past the end of its column")?;
+        position += page.len();
+        // No page can hold more than the values its chunk has left
+        let left = wanted - column.len();
+
+        match header.field(1).and_then(Thrift::int) {
+            Some(DICTIONARY_PAGE) => {
+                let dictionary_header = header.field(7).ok_or("a dictionary page has no header")?;
+                let count = count(dictionary_header.field(1), "a dictionary page")?;
+                dictionary = Some(decode_plain(page, count, leaf.kind)?);
+            }
+            Some(DATA_PAGE) => {
+                let data_header = header.field(5).ok_or("a data page has no header")?;
+                let count = page_count(data_header.field(1), left)?;
+                let encoding = data_header.field(2).and_then(Thrift::int).unwrap_or(PLAIN);
+                let (levels, values) = if leaf.optional {
+                    let len = page
+                        .get(..4)
+                        .map(|len| u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize)
+                        .ok_or("a data page ends early")?;
+                    let levels = page.get(4..4 + len).ok_or("a data page ends early")?;
+                    (Some(levels), &page[4 + len..])
+                } else {
+                    (None, page)
+                };
+                read_values(
+                    levels,
+                    values,
+                    count,
+                    encoding,
+                    leaf.kind,
+                    dictionary.as_deref(),
+                    column,
+                )?;
+            }
+            Some(DATA_PAGE_V2) => {
+                let data_header = header.field(8).ok_or("a data page has no header")?;
+                let field = |id| count(data_header.field(id), "a data page");
+                let count = page_count(data_header.field(1), left)?;
+                let encoding = data_header.field(4).and_then(Thrift::int).unwrap_or(PLAIN);
+                let (repetition_len, definition_len) = (field(6)?, field(5)?);
+                let end = repetition_len
+                    .checked_add(definition_len)
+                    .filter(|&end| end <= page.len())
+                    .ok_or("a data page ends early")?;
+                let levels = &page[repetition_len..end];
+                let values = &page[end..];
+                let levels = leaf.optional.then_some(levels);
+                read_values(
+                    levels,
+                    values,
+                    count,
+                    encoding,
+                    leaf.kind,
+                    dictionary.as_deref(),
+                    column,
+                )?;
+            }
+            _ => {}
+        }
+    }
+    if column.len() < wanted {
+        return Err("the pages hold fewer values than the metadata says".to_string());
+    }
+    Ok(())
+}
+
+// Read a data page's values, with Null wherever the definition levels say
+// there's no value
+fn read_values(
+    levels: Option<&[u8]>,
+    bytes: &[u8],
+    count: usize,
+    encoding: i64,
+    kind: Kind,
+    dictionary: Option<&[Value]>,
+    column: &mut Vec<Value>,
+) -> Result<(), String> {
+    let levels = levels
+        .map(|levels| decode_hybrid(levels, 1, count))
+        .transpose()?;
+    let present = match &levels {
+        Some(levels) => levels.iter().filter(|&&level| level == 1).count(),
+        None => count,
+    };
+    let values = match encoding {
+        PLAIN => decode_plain(bytes, present, kind)?,
+        PLAIN_DICTIONARY | RLE_DICTIONARY => {
+            let dictionary =
+                dictionary.ok_or("a page refers to a dictionary the column doesn't have")?;
+            let (&bit_width, indices) = bytes.split_first().ok_or("a data page ends early")?;
+            decode_hybrid(indices, bit_width as u32, present)?
+                .into_iter()
+                .map(|i| {
+                    dictionary.get(i as usize).cloned().ok_or_else(|| {
+                        "a page refers to a value past the end of the dictionary".to_string()
+                    })
+                })
+                .collect::<Result<_, _>>()?
+        }
+        encoding => {
+            return Err(format!(
+                "it uses encoding {}, and only plain and dictionary encodings can be read",
+                encoding
+            ))
+        }
+    };
+    let Some(levels) = levels else {
+        column.extend(values);
+        return Ok(());
+    };
+    let mut values = values.into_iter();
+    for level in levels {
+        column.push(match level {
+            1 => values.next().unwrap_or(Value::Null),
+            _ => Value::Null,
+        });
+    }
+    Ok(())
+}
+
+// Values of the RLE and bit-packed hybrid encoding, which holds definition
+// levels and dictionary indices
+fn decode_hybrid(bytes: &[u8], bit_width: u32, count: usize) -> Result<Vec<u32>, String> {
+    if bit_width > 32 {
+        return Err("a page's values are wider than 32 bits".to_string());
+    }
+    // Runs can hold far more values than bytes, but a count that's wrong
+    // shouldn't reserve more than the bytes could hold bit-packed
+    let mut values = Vec::with_capacity(count.min(bytes.len().saturating_mul(8)));
+    let mut position = 0;
+    while values.len() < count {
+        let header = read_varint(bytes, &mut position)?;
+        if header & 1 == 1 {
+            // Groups of eight values, bit_width bits each, lowest bit first
+            let len = usize::try_from(header >> 1)
+                .ok()
+                .and_then(|groups| groups.checked_mul(8))
+                .ok_or("a page's levels end early")?;
+            let packed = len
+                .checked_mul(bit_width as usize)
+                .map(|bits| bits.div_ceil(8))
+                .and_then(|size| position.checked_add(size))
+                .and_then(|end| bytes.get(position..end))
+                .ok_or("a page's levels end early")?;
+            let end = position + packed.len();
+            for i in 0..len.min(count - values.len()) {
+                let mut value = 0u32;
+                for bit in 0..bit_width as usize {
+                    let at = i * bit_width as usize + bit;
+                    value |= (((packed[at / 8] >> (at % 8)) & 1) as u32) << bit;
+                }
+                values.push(value);
+            }
+            position = end;
+        } else {
+            // One value repeated
+            let len = (header >> 1) as usize;
+            let width = bit_width.div_ceil(8) as usize;
+            let bytes = bytes
+                .get(position..position + width)
+                .ok_or("a page's levels end early")?;
+            let value = bytes
+                .iter()
+                .rev()
+                .fold(0u32, |value, &byte| value << 8 | byte as u32);
+            position += width;
+            if len == 0 {
+                return Err("a page's levels have an empty run".to_string());
+            }
+            values.extend(std::iter::repeat_n(value, len.min(count - values.len())));
+        }
+    }
+    Ok(values)
+}
+
+fn read_varint(bytes: &[u8], position: &mut usize) -> Result<u64, String> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *bytes.get(*position).ok_or("a page's levels end early")?;
+        *position += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err("a page has an integer that's too long".to_string())
+}
+
+// PLAIN encoded values, each as long as its physical type, except booleans,
+// a bit each, and byte arrays, each after its length
+fn decode_plain(bytes: &[u8], count: usize, kind: Kind) -> Result<Vec<Value>, String> {
+    let too_short = || "a page holds fewer values than it says".to_string();
+    if kind == Kind::Boolean {
+        let bits = bytes.get(..count.div_ceil(8)).ok_or_else(too_short)?;
+        return Ok((0..count)
+            .map(|i| Value::Boolean(bits[i / 8] >> (i % 8) & 1 == 1))
+            .collect());
+    }
+    if kind == Kind::Text {
+        // Each value takes at least the four bytes of its length
+        let mut values = Vec::with_capacity(count.min(bytes.len() / 4));
+        let mut position = 0;
+        for _ in 0..count {
+            let len = bytes.get(position..position + 4).ok_or_else(too_short)?;
+            let len = u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize;
+            let text = bytes
+                .get(position + 4..position + 4 + len)
+                .ok_or_else(too_short)?;
+            let text =
+                std::str::from_utf8(text).map_err(|_| "it holds bytes that aren't UTF-8 text")?;
+            values.push(Value::from(text));
+            position += 4 + len;
+        }
+        return Ok(values);
+    }
+
+    let width = match kind.physical() {
+        INT32 | FLOAT => 4,
+        INT96 => 12,
+        _ => 8,
+    };
+    let bytes = count
+        .checked_mul(width)
+        .and_then(|size| bytes.get(..size))
+        .ok_or_else(too_short)?;
+    bytes
+        .chunks_exact(width)
+        .map(|bytes| {
+            let int = || {
+                let mut buf = [0u8; 8];
+                buf[..width.min(8)].copy_from_slice(&bytes[..width.min(8)]);
+                match width {
+                    4 => i32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as i64,
+                    _ => i64::from_le_bytes(buf),
+                }
+            };
+            Ok(match kind {
+                Kind::Int32 | Kind::Int64 => Value::Integer(int()),
+                Kind::Currency => Value::Currency(int()),
+                Kind::Decimal32(scale) | Kind::Decimal64(scale) => {
+                    Value::Number(int() as f64 / 10f64.powi(scale))
+                }
+                Kind::Float => {
+                    Value::Number(
+                        f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64
+                    )
+                }
+                Kind::Date => {
+                    let date = i32::try_from(int())
+                        .ok()
+                        .and_then(|days| days.checked_add(UNIX_EPOCH_DAYS_FROM_CE))
+                        .and_then(NaiveDate::from_num_days_from_ce_opt)
+                        .ok_or("it holds a date out of range")?;
+                    Value::DateTime(date.and_time(NaiveTime::MIN))
+                }
+                Kind::Timestamp(per_second) => {
+                    let (seconds, fraction) =
+                        (int().div_euclid(per_second), int().rem_euclid(per_second));
+                    Value::DateTime(timestamp(seconds, fraction * (1_000_000_000 / per_second))?)
+                }
+                Kind::Int96 => {
+                    let nanos = int();
+                    let day = u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as i64;
+                    let seconds =
+                        (day - UNIX_EPOCH_JULIAN_DAY) * 86_400 + nanos.div_euclid(1_000_000_000);
+                    Value::DateTime(timestamp(seconds, nanos.rem_euclid(1_000_000_000))?)
+                }
+                _ => Value::Number(f64::from_le_bytes(
+                    bytes.try_into().map_err(|_| too_short())?,
+                )),
+            })
+        })
+        .collect()
+}
+
+// A count of values from the metadata, which can't be negative
+fn count(value: Option<&Thrift>, of: &str) -> Result<usize, String> {
+    usize::try_from(value.and_then(Thrift::int).unwrap_or(0))
+        .map_err(|_| format!("{} has a negative size", of))
+}
+
+// How many values a data page holds, which can't be more than its column
+// chunk has left
+fn page_count(value: Option<&Thrift>, left: usize) -> Result<usize, String> {
+    match count(value, "a data page")? {
+        count if count > left => {
+            Err("a page holds more values than its column chunk says".to_string())
+        }
+        count => Ok(count),
+    }
+}
+
+fn timestamp(seconds: i64, nanos: i64) -> Result<NaiveDateTime, String> {
+    DateTime::from_timestamp(seconds, nanos as u32)
+        .map(|d| d.naive_utc())
+        .ok_or_else(|| "it holds a timestamp out of range".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hybrid_runs_and_bit_packing() {
+        // A run of three 1s, then a group of eight 3-bit values
+        let bytes = [0x06, 0x01, 0x03, 0x88, 0xc6, 0xfa];
+        assert_eq!(decode_hybrid(&bytes, 1, 3).unwrap(), [1, 1, 1]);
+        assert_eq!(
+            decode_hybrid(&bytes[2..], 3, 8).unwrap(),
+            [0, 1, 2, 3, 4, 5, 6, 7]
+        );
+        assert!(decode_hybrid(&bytes[..1], 1, 3).is_err());
+    }
+
+    #[test]
+    fn test_oversized_lengths() {
+        // Bit-packed groups far past the page's end, and a run of four
+        // billion values for a page that wants three
+        let groups = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f];
+        assert!(decode_hybrid(&groups, 32, usize::MAX).is_err());
+        assert_eq!(
+            decode_hybrid(&[0xfe, 0xff, 0xff, 0xff, 0x0f, 0x01], 1, 3).unwrap(),
+            [1, 1, 1]
+        );
+        assert!(decode_hybrid(&[0x00, 0x01], 1, 3).is_err());
+        assert!(decode_hybrid(&[0x02], 8, 1).is_err());
+
+        for kind in [Kind::Boolean, Kind::Int64, Kind::Text] {
+            assert!(decode_plain(&[0; 16], usize::MAX, kind).is_err());
+        }
+        // A value whose length runs past the page
+        assert!(decode_plain(&[0xff, 0xff, 0xff, 0x7f, b'a'], 1, Kind::Text).is_err());
+
+        assert_eq!(
+            count(Some(&Thrift::I64(-1)), "a column chunk").unwrap_err(),
+            "a column chunk has a negative size"
+        );
+        assert_eq!(page_count(Some(&Thrift::I32(3)), 3).unwrap(), 3);
+        assert_eq!(
+            page_count(Some(&Thrift::I32(4)), 3).unwrap_err(),
+            "a page holds more values than its column chunk says"
+        );
+    }
+
+    #[test]
+    fn test_nested_schema_is_rejected() {
+        let schema = Thrift::List(vec![
+            Thrift::Struct(vec![
+                (4, Thrift::Binary(b"schema".to_vec())),
+                (5, Thrift::I32(1)),
+            ]),
+            Thrift::Struct(vec![
+                (3, Thrift::I32(OPTIONAL as i32)),
+                (4, Thrift::Binary(b"Address".to_vec())),
+                (5, Thrift::I32(2)),
+            ]),
+        ]);
+        let metadata = Thrift::Struct(vec![(2, schema)]);
+        let error = read_schema(&metadata).err().unwrap();
+        assert!(
+            error.contains("column Address is a nested group"),
+            "{}",
+            error
+        );
+    }
+}
@@ -0,0 +1,303 @@
+// io/parquet/thrift.rs
+
+//! Just enough of Thrift's compact protocol for Parquet's metadata. A struct
+//! is read whole, as a tree of fields by id, and written from one; which
+//! field means what is left to the caller.
+
+/// A value in the compact protocol. Bytes and 16-bit integers read as `I32`.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Thrift {
+    Bool(bool),
+    I32(i32),
+    I64(i64),
+    Double(f64),
+    Binary(Vec<u8>),
+    List(Vec<Thrift>),
+    Struct(Vec<(i16, Thrift)>),
+}
+
+// Type ids of the compact protocol
+const BOOL_TRUE: u8 = 1;
+const BOOL_FALSE: u8 = 2;
+const BYTE: u8 = 3;
+const I16: u8 = 4;
+const I32: u8 = 5;
+const I64: u8 = 6;
+const DOUBLE: u8 = 7;
+const BINARY: u8 = 8;
+const LIST: u8 = 9;
+const SET: u8 = 10;
+const STRUCT: u8 = 12;
+
+// Deeper than any Parquet metadata goes, so a corrupt file can't recurse forever
+const MAX_DEPTH: usize = 32;
+
+impl Thrift {
+    /// The field of a struct with the given id
+    pub(super) fn field(&self, id: i16) -> Option<&Thrift> {
+        match self {
+            Thrift::Struct(fields) => fields
+                .iter()
+                .find(|(field, _)| *field == id)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub(super) fn int(&self) -> Option<i64> {
+        match self {
+            Thrift::I32(i) => Some(*i as i64),
+            Thrift::I64(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub(super) fn binary(&self) -> Option<&[u8]> {
+        match self {
+            Thrift::Binary(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    pub(super) fn list(&self) -> Option<&[Thrift]> {
+        match self {
+            Thrift::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    fn type_id(&self) -> u8 {
+        match self {
+            Thrift::Bool(true) => BOOL_TRUE,
+            Thrift::Bool(false) => BOOL_FALSE,
+            Thrift::I32(_) => I32,
+            Thrift::I64(_) => I64,
+            Thrift::Double(_) => DOUBLE,
+            Thrift::Binary(_) => BINARY,
+            Thrift::List(_) => LIST,
+            Thrift::Struct(_) => STRUCT,
+        }
+    }
+}
+
+/// Reads compact protocol from a slice of bytes
+pub(super) struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(super) fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes, position: 0 }
+    }
+
+    /// How many bytes have been read
+    pub(super) fn position(&self) -> usize {
+        self.position
+    }
+
+    pub(super) fn read_struct(&mut self) -> Result<Thrift, String> {
+        self.read_struct_at(0)
+    }
+
+    fn read_struct_at(&mut self, depth: usize) -> Result<Thrift, String> {
+        if depth > MAX_DEPTH {
+            return Err("the metadata is nested too deeply".to_string());
+        }
+        let mut fields = Vec::new();
+        let mut last = 0i16;
+        loop {
+            let header = self.byte()?;
+            if header == 0 {
+                return Ok(Thrift::Struct(fields));
+            }
+            let delta = (header >> 4) as i16;
+            let id = if delta == 0 {
+                self.zigzag()? as i16
+            } else {
+                last.wrapping_add(delta)
+            };
+            last = id;
+            let value = match header & 0x0f {
+                BOOL_TRUE => Thrift::Bool(true),
+                BOOL_FALSE => Thrift::Bool(false),
+                kind => self.read_value(kind, depth)?,
+            };
+            fields.push((id, value));
+        }
+    }
+
+    fn read_value(&mut self, kind: u8, depth: usize) -> Result<Thrift, String> {
+        Ok(match kind {
+            // Booleans in a list are a byte each
+            BOOL_TRUE | BOOL_FALSE => Thrift::Bool(self.byte()? == BOOL_TRUE),
+            BYTE => Thrift::I32(self.byte()? as i8 as i32),
+            I16 | I32 => Thrift::I32(self.zigzag()? as i32),
+            I64 => Thrift::I64(self.zigzag()?),
+            DOUBLE => Thrift::Double(f64::from_le_bytes(
+                self.take(8)?.try_into().unwrap_or_default(),
+            )),
+            BINARY => {
+                let len = self.varint()? as usize;
+                Thrift::Binary(self.take(len)?.to_vec())
+            }
+            LIST | SET => {
+                let header = self.byte()?;
+                let len = match header >> 4 {
+                    15 => self.varint()? as usize,
+                    len => len as usize,
+                };
+                let mut items = Vec::new();
+                for _ in 0..len {
+                    items.push(self.read_value(header & 0x0f, depth + 1)?);
+                }
+                Thrift::List(items)
+            }
+            STRUCT => self.read_struct_at(depth + 1)?,
+            kind => return Err(format!("the metadata has a value of unknown type {}", kind)),
+        })
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .position
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or("the metadata ends early")?;
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn varint(&mut self) -> Result<u64, String> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("the metadata has an integer that's too long".to_string())
+    }
+
+    fn zigzag(&mut self) -> Result<i64, String> {
+        let value = self.varint()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+}
+
+/// Append a struct of the given fields, which must be in order of id
+pub(super) fn write_struct(out: &mut Vec<u8>, fields: &[(i16, Thrift)]) {
+    let mut last = 0i16;
+    for (id, value) in fields {
+        let delta = id - last;
+        if (1..=15).contains(&delta) {
+            out.push((delta as u8) << 4 | value.type_id());
+        } else {
+            out.push(value.type_id());
+            write_varint(out, zigzag(*id as i64));
+        }
+        last = *id;
+        if !matches!(value, Thrift::Bool(_)) {
+            write_value(out, value);
+        }
+    }
+    out.push(0);
+}
+
+fn write_value(out: &mut Vec<u8>, value: &Thrift) {
+    match value {
+        Thrift::Bool(b) => out.push(if *b { BOOL_TRUE } else { BOOL_FALSE }),
+        Thrift::I32(i) => write_varint(out, zigzag(*i as i64)),
+        Thrift::I64(i) => write_varint(out, zigzag(*i)),
+        Thrift::Double(d) => out.extend(d.to_le_bytes()),
+        Thrift::Binary(bytes) => {
+            write_varint(out, bytes.len() as u64);
+            out.extend(bytes);
+        }
+        Thrift::List(items) => {
+            let kind = match items.first() {
+                Some(Thrift::Bool(_)) => BOOL_TRUE,
+                Some(item) => item.type_id(),
+                None => STRUCT,
+            };
+            if items.len() < 15 {
+                out.push((items.len() as u8) << 4 | kind);
+            } else {
+                out.push(0xf0 | kind);
+                write_varint(out, items.len() as u64);
+            }
+            for item in items {
+                write_value(out, item);
+            }
+        }
+        Thrift::Struct(fields) => write_struct(out, fields),
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let value = Thrift::Struct(vec![
+            (1, Thrift::I32(-3)),
+            (2, Thrift::Bool(true)),
+            (4, Thrift::I64(i64::MIN)),
+            (5, Thrift::Binary(b"Sales".to_vec())),
+            (
+                6,
+                Thrift::List(
+                    (0..20)
+                        .map(|i| Thrift::Struct(vec![(1, Thrift::I32(i))]))
+                        .collect(),
+                ),
+            ),
+            (30, Thrift::Double(2.5)),
+            (
+                31,
+                Thrift::List(vec![Thrift::Bool(false), Thrift::Bool(true)]),
+            ),
+            (32, Thrift::List(Vec::new())),
+        ]);
+        let Thrift::Struct(fields) = &value else {
+            unreachable!()
+        };
+        let mut bytes = Vec::new();
+        write_struct(&mut bytes, fields);
+        let mut reader = Reader::new(&bytes);
+        assert_eq!(reader.read_struct().unwrap(), value);
+        assert_eq!(reader.position(), bytes.len());
+
+        assert!(Reader::new(&bytes[..bytes.len() - 1])
+            .read_struct()
+            .is_err());
+    }
+
+    #[test]
+    fn test_short_field_header() {
+        // Field 1, i32 -1, as Thrift's own libraries write it
+        assert_eq!(
+            Reader::new(&[0x15, 0x01, 0x00]).read_struct().unwrap(),
+            Thrift::Struct(vec![(1, Thrift::I32(-1))])
+        );
+    }
+}
@@ -0,0 +1,414 @@
+// io/sqlite.rs
+
+//! Reading SQLite databases, behind the `sqlite` feature.
+//!
+//! The database file is read directly, a page at a time, rather than through
+//! SQLite itself. A query is a single SELECT from one table, or from none,
+//! with WHERE, ORDER BY, LIMIT, OFFSET and DISTINCT, SQLite's operators and
+//! its common scalar functions. Joins, grouping, aggregates and subqueries
+//! are errors saying so: they're for the table once it's read.
+mod file;
+mod sql;
+
+use self::file::Database;
+use self::sql::{parse_create_table, parse_select, Affinity, ColumnDef, Expr, ResultColumn};
+use crate::error::DaxError;
+use crate::types::Value;
+use crate::Table;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fs::File;
+use std::path::Path;
+
+/// A value as SQLite stores it
+#[derive(Debug, Clone, PartialEq)]
+enum SqlValue {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+/// Run a SELECT against a SQLite database and read its result into a table.
+///
+/// Each column of the result is a column of the table, named by its alias,
+/// else by the table column it is, else by the expression as written. INTEGER
+/// values read as integers, REAL as numbers, TEXT as text and NULL as Null;
+/// a BLOB is an error for now. A query the reader can't run, a table the
+/// database doesn't have and a database with changes still in its
+/// write-ahead log are all a `DaxError::ParseError`.
+///
+/// # Examples
+///
+/// ```
+/// use dax_rust::io::read_sqlite;
+/// use dax_rust::Value;
+/// use std::path::Path;
+///
+/// let path = Path::new("tests/fixtures/shop.db");
+/// let table = read_sqlite(path, "SELECT name, price FROM products WHERE price > 2 ORDER BY price").unwrap();
+/// assert_eq!(table.get("name", 0), Some(Value::from("Pear")));
+/// ```
+pub fn read_sqlite(path: &Path, query: &str) -> Result<Table, DaxError> {
+    read_sqlite_params(path, query, &[])
+}
+
+/// Run a SELECT with parameters against a SQLite database, as `read_sqlite`
+/// does.
+///
+/// The query marks where each value goes with `?`, `?NNN`, or a name such as
+/// `:name`; a name used twice takes the same value, and `params` gives the
+/// values in order of their numbers. The values are bound, never written into
+/// the query, so text from a user can't change what it does. Integers go in
+/// as INTEGER, numbers and currency as REAL, booleans as 1 and 0 and dates as
+/// text such as `2024-01-31 00:00:00`.
+///
+/// # Examples
+///
+/// ```
+/// use dax_rust::io::read_sqlite_params;
+/// use dax_rust::Value;
+/// use std::path::Path;
+///
+/// let path = Path::new("tests/fixtures/shop.db");
+/// let name = Value::from("Pear'; DROP TABLE products; --");
+/// let table = read_sqlite_params(path, "SELECT * FROM products WHERE name = ?", &[name]).unwrap();
+/// assert_eq!(table.num_rows(), 0);
+/// ```
+pub fn read_sqlite_params(path: &Path, query: &str, params: &[Value]) -> Result<Table, DaxError> {
+    let file = File::open(path)?;
+    let error =
+        |e: String| DaxError::ParseError(format!("SQLite database {}: {}", path.display(), e));
+    let select = parse_select(query).map_err(|e| error(format!("query: {}", e)))?;
+    if params.len() != select.parameters {
+        return Err(error(format!(
+            "the query takes {} parameters, but {} were given",
+            select.parameters,
+            params.len()
+        )));
+    }
+    let params: Vec<SqlValue> = params.iter().map(to_sql).collect();
+    let mut database = Database::open(file, path).map_err(error)?;
+    let (names, rows) = run(&mut database, select, &params).map_err(error)?;
+    to_table(names, rows).map_err(error)
+}
+
+fn to_sql(value: &Value) -> SqlValue {
+    match value {
+        Value::Null => SqlValue::Null,
+        Value::Integer(n) => SqlValue::Integer(*n),
+        Value::Number(x) => SqlValue::Real(*x),
+        Value::Currency(amount) => SqlValue::Real(*amount as f64 / 10_000.0),
+        Value::Boolean(b) => SqlValue::Integer(*b as i64),
+        Value::Text(text) => SqlValue::Text(text.clone()),
+        Value::DateTime(datetime) => {
+            SqlValue::Text(datetime.format("%Y-%m-%d %H:%M:%S%.f").to_string())
+        }
+    }
+}
+
+// The columns of the schema table, which lists the others
+fn schema_columns() -> Vec<ColumnDef> {
+    [
+        ("type", Affinity::Text),
+        ("name", Affinity::Text),
+        ("tbl_name", Affinity::Text),
+        ("rootpage", Affinity::Integer),
+        ("sql", Affinity::Text),
+    ]
+    .into_iter()
+    .map(|(name, affinity)| ColumnDef {
+        name: name.to_string(),
+        affinity,
+        default: SqlValue::Null,
+        rowid: false,
+    })
+    .collect()
+}
+
+// Run a query: the names of its result's columns, and its rows
+fn run(
+    database: &mut Database,
+    select: sql::Select,
+    params: &[SqlValue],
+) -> Result<(Vec<String>, Vec<Vec<SqlValue>>), String> {
+    // The table's root page and columns; a row's values are the columns', then
+    // its rowid
+    let (root, columns, names) = match &select.from {
+        None => (None, Vec::new(), Vec::new()),
+        Some((table, alias)) => {
+            let mut names = vec![table.clone()];
+            names.extend(alias.clone());
+            if ["sqlite_schema", "sqlite_master"]
+                .iter()
+                .any(|schema| table.eq_ignore_ascii_case(schema))
+            {
+                (Some(1), schema_columns(), names)
+            } else {
+                let schema = database.schema()?;
+                let entry = schema
+                    .iter()
+                    .find(|entry| entry.name.eq_ignore_ascii_case(table))
+                    .filter(|entry| entry.kind == "table" || entry.kind == "view")
+                    .ok_or_else(|| format!("no such table: {}", table))?;
+                if entry.kind == "view" {
+                    return Err(format!(
+                        "{} is a view, which can't be read; select from its tables",
+                        entry.name
+                    ));
+                }
+                let sql = entry.sql.as_deref().unwrap_or_default();
+                let columns =
+                    parse_create_table(sql).map_err(|e| format!("table {}: {}", entry.name, e))?;
+                (Some(entry.root), columns, names)
+            }
+        }
+    };
+    let resolve = |table: Option<&str>, name: &str| -> Result<(usize, Affinity), String> {
+        let qualified = match table {
+            Some(table) => format!("{}.{}", table, name),
+            None => name.to_string(),
+        };
+        if table.is_some_and(|table| !names.iter().any(|n| n.eq_ignore_ascii_case(table))) {
+            return Err(format!("no such column: {}", qualified));
+        }
+        if let Some(i) = columns
+            .iter()
+            .position(|column| column.name.eq_ignore_ascii_case(name))
+        {
+            return Ok((i, columns[i].affinity));
+        }
+        if root.is_some()
+            && ["rowid", "oid", "_rowid_"]
+                .iter()
+                .any(|rowid| name.eq_ignore_ascii_case(rowid))
+        {
+            return Ok((columns.len(), Affinity::Integer));
+        }
+        Err(format!("no such column: {}", qualified))
+    };
+
+    // The result's columns, each named and bound to the row
+    let mut outputs: Vec<(String, Expr)> = Vec::new();
+    for column in select.columns {
+        match column {
+            ResultColumn::All(table) => {
+                if let Some(table) = table {
+                    if !names.iter().any(|n| n.eq_ignore_ascii_case(&table)) {
+                        return Err(format!("no such table: {}", table));
+                    }
+                }
+                if root.is_none() {
+                    return Err("* needs a table to select from".to_string());
+                }
+                for (index, column) in columns.iter().enumerate() {
+                    let affinity = column.affinity;
+                    outputs.push((column.name.clone(), Expr::Field { index, affinity }));
+                }
+            }
+            ResultColumn::Expr {
+                mut expr,
+                alias,
+                text,
+            } => {
+                let name = match (alias, &expr) {
+                    (Some(alias), _) => alias,
+                    // A column by the name it's declared with, the rowid by
+                    // its INTEGER PRIMARY KEY's
+                    (None, Expr::Column { table, name }) => {
+                        let (index, _) = resolve(table.as_deref(), name)?;
+                        columns
+                            .get(index)
+                            .or_else(|| columns.iter().find(|column| column.rowid))
+                            .map_or(name.clone(), |column| column.name.clone())
+                    }
+                    (None, _) => text,
+                };
+                expr.bind(&resolve)?;
+                outputs.push((name, expr));
+            }
+        }
+    }
+
+    // ORDER BY takes a result column by its number or its name as well
+    let mut order = Vec::new();
+    for mut term in select.order {
+        let output = match &term.expr {
+            Expr::Literal(SqlValue::Integer(n)) => Some(
+                usize::try_from(*n)
+                    .ok()
+                    .and_then(|n| n.checked_sub(1))
+                    .filter(|&i| i < outputs.len())
+                    .ok_or_else(|| format!("ORDER BY term {} is out of range", n))?,
+            ),
+            Expr::Column { table: None, name } => outputs
+                .iter()
+                .position(|(output, _)| output.eq_ignore_ascii_case(name)),
+            _ => None,
+        };
+        match output {
+            Some(i) => term.expr = outputs[i].1.clone(),
+            None => term.expr.bind(&resolve)?,
+        }
+        order.push(term);
+    }
+
+    let mut filter = select.filter;
+    if let Some(filter) = &mut filter {
+        filter.bind(&resolve)?;
+    }
+    let count = |expr: Option<Expr>, what: &str| -> Result<Option<usize>, String> {
+        let Some(mut expr) = expr else {
+            return Ok(None);
+        };
+        expr.bind(&|_, name| Err(format!("{} can't refer to the column {}", what, name)))?;
+        match sql::cast(expr.eval(&[], params)?, Affinity::Numeric) {
+            // A negative limit is none
+            SqlValue::Integer(n) => Ok(usize::try_from(n).ok()),
+            _ => Err(format!("{} must be a whole number", what)),
+        }
+    };
+    let limit = count(select.limit, "LIMIT")?.unwrap_or(usize::MAX);
+    let offset = count(select.offset, "OFFSET")?.unwrap_or(0);
+
+    // Without ORDER BY, rows come in rowid order, and the scan stops once it
+    // has enough of them
+    let mut rows: Vec<(Vec<SqlValue>, Vec<SqlValue>)> = Vec::new();
+    let mut seen = HashSet::new();
+    let mut skipped = 0;
+    let mut row = |rowid: i64, mut values: Vec<SqlValue>| -> Result<bool, String> {
+        // Rows written before a column was added don't have it
+        values.truncate(columns.len());
+        for column in &columns[values.len()..] {
+            values.push(column.default.clone());
+        }
+        // SQLite stores whole numbers in REAL columns as integers, to save
+        // space, and gives them back as reals
+        for (value, column) in values.iter_mut().zip(&columns) {
+            match value {
+                SqlValue::Null if column.rowid => *value = SqlValue::Integer(rowid),
+                SqlValue::Integer(n) if column.affinity == Affinity::Real => {
+                    *value = SqlValue::Real(*n as f64)
+                }
+                _ => {}
+            }
+        }
+        values.push(SqlValue::Integer(rowid));
+        if let Some(filter) = &filter {
+            if sql::truth(&filter.eval(&values, params)?) != Some(true) {
+                return Ok(true);
+            }
+        }
+        let result = outputs
+            .iter()
+            .map(|(_, expr)| expr.eval(&values, params))
+            .collect::<Result<Vec<_>, _>>()?;
+        if select.distinct && !seen.insert(result.iter().map(Key::of).collect::<Vec<_>>()) {
+            return Ok(true);
+        }
+        if !order.is_empty() {
+            let keys = order
+                .iter()
+                .map(|term| term.expr.eval(&values, params))
+                .collect::<Result<Vec<_>, _>>()?;
+            rows.push((keys, result));
+            return Ok(true);
+        }
+        if skipped < offset {
+            skipped += 1;
+            return Ok(true);
+        }
+        rows.push((Vec::new(), result));
+        Ok(rows.len() < limit)
+    };
+    if limit > 0 || !order.is_empty() {
+        match root {
+            Some(root) => database.scan(root, &mut row)?,
+            None => {
+                row(0, Vec::new())?;
+            }
+        }
+    }
+
+    if !order.is_empty() {
+        rows.sort_by(|(a, _), (b, _)| {
+            order
+                .iter()
+                .zip(a.iter().zip(b))
+                .map(|(term, (a, b))| match (a, b) {
+                    (SqlValue::Null, SqlValue::Null) => Ordering::Equal,
+                    (SqlValue::Null, _) if term.nulls_first => Ordering::Less,
+                    (SqlValue::Null, _) => Ordering::Greater,
+                    (_, SqlValue::Null) if term.nulls_first => Ordering::Greater,
+                    (_, SqlValue::Null) => Ordering::Less,
+                    _ if term.descending => sql::compare(b, a),
+                    _ => sql::compare(a, b),
+                })
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(Ordering::Equal)
+        });
+        rows = rows.into_iter().skip(offset).take(limit).collect();
+    }
+    let names = outputs.into_iter().map(|(name, _)| name).collect();
+    Ok((names, rows.into_iter().map(|(_, row)| row).collect()))
+}
+
+// A value as DISTINCT tells values apart: 1 and 1.0 are the same
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum Key {
+    Null,
+    Integer(i64),
+    Real(u64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+impl Key {
+    fn of(value: &SqlValue) -> Key {
+        match value {
+            SqlValue::Null => Key::Null,
+            SqlValue::Integer(n) => Key::Integer(*n),
+            SqlValue::Real(x) if x.fract() == 0.0 && x.abs() < 9.2e18 => Key::Integer(*x as i64),
+            SqlValue::Real(x) => Key::Real(x.to_bits()),
+            SqlValue::Text(text) => Key::Text(text.clone()),
+            SqlValue::Blob(bytes) => Key::Blob(bytes.clone()),
+        }
+    }
+}
+
+fn to_table(names: Vec<String>, rows: Vec<Vec<SqlValue>>) -> Result<Table, String> {
+    for (i, name) in names.iter().enumerate() {
+        if names[..i].contains(name) {
+            return Err(format!(
+                "the query gives two columns named {}; tell them apart with AS",
+                name
+            ));
+        }
+    }
+    let mut columns: Vec<Vec<Value>> = names
+        .iter()
+        .map(|_| Vec::with_capacity(rows.len()))
+        .collect();
+    for row in rows {
+        for ((value, column), name) in row.into_iter().zip(&mut columns).zip(&names) {
+            column.push(match value {
+                SqlValue::Null => Value::Null,
+                SqlValue::Integer(n) => Value::Integer(n),
+                SqlValue::Real(x) => Value::Number(x),
+                SqlValue::Text(text) => Value::Text(text),
+                SqlValue::Blob(_) => {
+                    return Err(format!(
+                        "the column {} holds a BLOB, which can't be read yet",
+                        name
+                    ))
+                }
+            });
+        }
+    }
+    let mut table = Table::new();
+    for (name, values) in names.into_iter().zip(columns) {
+        table.add_column(name, values).map_err(|e| e.to_string())?;
+    }
+    Ok(table)
+}
@@ -0,0 +1,308 @@
+// io/sqlite/file.rs
+
+//! The SQLite database file, read a page at a time: each table is a b-tree
+//! of pages keyed by rowid, whose leaves hold the rows as records, and the
+//! schema is a table rooted at the first page listing the others with the
+//! SQL that created them.
+use super::SqlValue;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+const MAGIC: &[u8; 16] = b"SQLite format 3\0";
+
+// Kinds of b-tree page
+const TABLE_INTERIOR: u8 = 0x05;
+const TABLE_LEAF: u8 = 0x0d;
+
+#[derive(Debug, Clone, Copy)]
+enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+/// A database file open for reading
+pub(super) struct Database {
+    file: File,
+    page_size: usize,
+    // The bytes of each page not reserved for extensions
+    usable: usize,
+    pages: u32,
+    encoding: Encoding,
+}
+
+/// A table or view as the schema lists it
+#[derive(Debug, Clone)]
+pub(super) struct SchemaEntry {
+    pub(super) kind: String,
+    pub(super) name: String,
+    pub(super) root: u32,
+    pub(super) sql: Option<String>,
+}
+
+impl Database {
+    /// Open the database in `file`, which is at `path`
+    pub(super) fn open(mut file: File, path: &Path) -> Result<Self, String> {
+        let mut header = [0; 100];
+        file.read_exact(&mut header)
+            .map_err(|_| "it isn't a SQLite database")?;
+        if &header[..16] != MAGIC {
+            return Err("it isn't a SQLite database".to_string());
+        }
+        let page_size = match u16::from_be_bytes([header[16], header[17]]) {
+            1 => 65536,
+            n => n as usize,
+        };
+        let usable = page_size.saturating_sub(header[20] as usize);
+        if !page_size.is_power_of_two() || page_size < 512 || usable < 480 {
+            return Err("its header gives an impossible page size".to_string());
+        }
+        // Committed changes can sit in the write-ahead log until a checkpoint
+        // copies them into the file, and reading without them would be stale
+        if header[18] == 2 {
+            let mut wal = path.as_os_str().to_owned();
+            wal.push("-wal");
+            if std::fs::metadata(&wal).is_ok_and(|wal| wal.len() > 0) {
+                return Err(
+                    "it has changes in its write-ahead log; checkpoint it first".to_string()
+                );
+            }
+        }
+        let encoding = match u32::from_be_bytes([header[56], header[57], header[58], header[59]]) {
+            0 | 1 => Encoding::Utf8,
+            2 => Encoding::Utf16Le,
+            3 => Encoding::Utf16Be,
+            _ => return Err("its header gives an unknown text encoding".to_string()),
+        };
+        let len = file.metadata().map_err(|e| e.to_string())?.len();
+        let pages = (len / page_size as u64).min(u32::MAX as u64) as u32;
+        Ok(Database {
+            file,
+            page_size,
+            usable,
+            pages,
+            encoding,
+        })
+    }
+
+    /// The tables, views, indexes and triggers the database has
+    pub(super) fn schema(&mut self) -> Result<Vec<SchemaEntry>, String> {
+        let mut entries = Vec::new();
+        self.scan(1, |_, row| {
+            let text = |i: usize| match row.get(i) {
+                Some(SqlValue::Text(text)) => Some(text.clone()),
+                _ => None,
+            };
+            let root = match row.get(3) {
+                Some(SqlValue::Integer(root)) => u32::try_from(*root).unwrap_or(0),
+                _ => 0,
+            };
+            entries.push(SchemaEntry {
+                kind: text(0).unwrap_or_default(),
+                name: text(1).unwrap_or_default(),
+                root,
+                sql: text(4),
+            });
+            Ok(true)
+        })?;
+        Ok(entries)
+    }
+
+    /// Pass every row of the table rooted at page `root`, in rowid order, to
+    /// `row` as its rowid and its record's values, until it returns false
+    pub(super) fn scan(
+        &mut self,
+        root: u32,
+        mut row: impl FnMut(i64, Vec<SqlValue>) -> Result<bool, String>,
+    ) -> Result<(), String> {
+        // The pages still to visit, the next on top
+        let mut stack = vec![root];
+        let mut visited = 0u32;
+        while let Some(number) = stack.pop() {
+            visited += 1;
+            if visited > self.pages {
+                return Err("a table's pages form a loop".to_string());
+            }
+            let page = self.page(number)?;
+            let start = if number == 1 { 100 } else { 0 };
+            let kind = page[start];
+            let cells = u16::from_be_bytes([page[start + 3], page[start + 4]]) as usize;
+            let pointers = start + if kind == TABLE_INTERIOR { 12 } else { 8 };
+            let cell = |i: usize| -> Result<usize, String> {
+                let at = pointers + 2 * i;
+                let bytes = page.get(at..at + 2).ok_or("a page has too many cells")?;
+                Ok(u16::from_be_bytes([bytes[0], bytes[1]]) as usize)
+            };
+            match kind {
+                TABLE_INTERIOR => {
+                    stack.push(read_u32(&page, start + 8)?);
+                    for i in (0..cells).rev() {
+                        stack.push(read_u32(&page, cell(i)?)?);
+                    }
+                }
+                TABLE_LEAF => {
+                    for i in 0..cells {
+                        let (size, at) = varint(&page, cell(i)?)?;
+                        let (rowid, at) = varint(&page, at)?;
+                        let payload = self.payload(&page, at, size)?;
+                        if !row(rowid as i64, self.record(&payload)?)? {
+                            return Ok(());
+                        }
+                    }
+                }
+                _ => return Err(format!("page {} isn't a table's", number)),
+            }
+        }
+        Ok(())
+    }
+
+    fn page(&mut self, number: u32) -> Result<Vec<u8>, String> {
+        if number == 0 || number > self.pages {
+            return Err(format!("page {} is past the end of the file", number));
+        }
+        let mut page = vec![0; self.page_size];
+        self.file
+            .seek(SeekFrom::Start((number - 1) as u64 * self.page_size as u64))
+            .and_then(|_| self.file.read_exact(&mut page))
+            .map_err(|e| e.to_string())?;
+        Ok(page)
+    }
+
+    // A cell's payload of `size` bytes starting at `at`: as much as fits on
+    // the page, then the rest from a chain of overflow pages
+    fn payload(&mut self, page: &[u8], at: usize, size: u64) -> Result<Vec<u8>, String> {
+        let usable = self.usable;
+        if size > self.pages as u64 * usable as u64 {
+            return Err("a row is larger than the file".to_string());
+        }
+        let size = size as usize;
+        let most = usable - 35;
+        let local = if size <= most {
+            size
+        } else {
+            let least = (usable - 12) * 32 / 255 - 23;
+            let local = least + (size - least) % (usable - 4);
+            if local <= most {
+                local
+            } else {
+                least
+            }
+        };
+        let mut payload = page
+            .get(at..at + local)
+            .ok_or("a row runs past the end of its page")?
+            .to_vec();
+        if local < size {
+            let mut next = read_u32(page, at + local)?;
+            let mut hops = 0;
+            while payload.len() < size {
+                hops += 1;
+                if next == 0 || hops > self.pages {
+                    return Err("a row's overflow pages end too soon".to_string());
+                }
+                let overflow = self.page(next)?;
+                next = read_u32(&overflow, 0)?;
+                let take = (size - payload.len()).min(usable - 4);
+                payload.extend_from_slice(&overflow[4..4 + take]);
+            }
+        }
+        Ok(payload)
+    }
+
+    // The values of a record: a header of each value's serial type, which
+    // gives its type and size, then the values one after another
+    fn record(&self, payload: &[u8]) -> Result<Vec<SqlValue>, String> {
+        let (header, mut at) = varint(payload, 0)?;
+        let header = usize::try_from(header)
+            .ok()
+            .filter(|&header| header <= payload.len())
+            .ok_or("a record's header is larger than the record")?;
+        let mut body = header;
+        let mut values = Vec::new();
+        while at < header {
+            let (serial, next) = varint(payload, at)?;
+            at = next;
+            let size = match serial {
+                0 | 8 | 9 => 0,
+                1..=4 => serial as usize,
+                5 => 6,
+                6 | 7 => 8,
+                10 | 11 => return Err("a record has a reserved serial type".to_string()),
+                n => ((n - 12) / 2) as usize,
+            };
+            let bytes = payload
+                .get(body..body.saturating_add(size))
+                .ok_or("a record's values run past its end")?;
+            body += size;
+            values.push(match serial {
+                0 => SqlValue::Null,
+                1..=6 => {
+                    // Big-endian two's complement, sign extended from its size
+                    let value = bytes.iter().fold(0u64, |n, &b| n << 8 | b as u64);
+                    let shift = 64 - 8 * size as u32;
+                    SqlValue::Integer(((value << shift) as i64) >> shift)
+                }
+                7 => SqlValue::Real(f64::from_be_bytes(bytes.try_into().unwrap())),
+                8 => SqlValue::Integer(0),
+                9 => SqlValue::Integer(1),
+                n if n % 2 == 0 => SqlValue::Blob(bytes.to_vec()),
+                _ => SqlValue::Text(self.text(bytes)),
+            });
+        }
+        Ok(values)
+    }
+
+    fn text(&self, bytes: &[u8]) -> String {
+        let units = |from: fn([u8; 2]) -> u16| {
+            let units: Vec<u16> = bytes
+                .chunks_exact(2)
+                .map(|pair| from([pair[0], pair[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        };
+        match self.encoding {
+            Encoding::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+            Encoding::Utf16Le => units(u16::from_le_bytes),
+            Encoding::Utf16Be => units(u16::from_be_bytes),
+        }
+    }
+}
+
+// A varint at `at`, and where what follows it starts: seven bits a byte, high
+// bits first, while the top bit is set, with all eight bits of a ninth byte
+fn varint(data: &[u8], at: usize) -> Result<(u64, usize), String> {
+    let mut value = 0u64;
+    for i in 0..9 {
+        let byte = *data.get(at + i).ok_or("a record is cut short")?;
+        if i == 8 {
+            return Ok((value << 8 | byte as u64, at + 9));
+        }
+        value = value << 7 | (byte & 0x7f) as u64;
+        if byte & 0x80 == 0 {
+            return Ok((value, at + i + 1));
+        }
+    }
+    unreachable!("the ninth byte returns")
+}
+
+fn read_u32(data: &[u8], at: usize) -> Result<u32, String> {
+    let bytes = data
+        .get(at..at + 4)
+        .ok_or("a page number runs past the end of its page")?;
+    Ok(u32::from_be_bytes(bytes.try_into().unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_varints() {
+        assert_eq!(varint(&[0x05], 0), Ok((5, 1)));
+        assert_eq!(varint(&[0x81, 0x00], 0), Ok((128, 2)));
+        assert_eq!(varint(&[0xff, 0x81, 0x00, 0x7f], 1), Ok((128, 3)));
+        assert_eq!(varint(&[0xff; 9], 0), Ok((u64::MAX, 9)));
+        assert!(varint(&[0x81], 0).is_err());
+    }
+}
@@ -0,0 +1,2186 @@
+// io/sqlite/sql.rs
+
+//! The SQL `read_sqlite` understands: a SELECT from one table, and the
+//! CREATE TABLE statements in the schema that give its columns. Expressions
+//! work out as SQLite's do, with its type affinities, three-valued logic and
+//! order of values of different types.
+use super::SqlValue;
+use std::cmp::Ordering;
+use std::ops::Range;
+
+/// How a column or CAST leans values: what a declared type means to SQLite
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Affinity {
+    Integer,
+    Real,
+    Numeric,
+    Text,
+    Blob,
+}
+
+impl Affinity {
+    /// The affinity of a declared type, by the words in it as SQLite has it
+    pub(super) fn of(declared: &str) -> Affinity {
+        let declared = declared.to_ascii_uppercase();
+        if declared.contains("INT") {
+            Affinity::Integer
+        } else if ["CHAR", "CLOB", "TEXT"]
+            .iter()
+            .any(|w| declared.contains(w))
+        {
+            Affinity::Text
+        } else if declared.is_empty() || declared.contains("BLOB") {
+            Affinity::Blob
+        } else if ["REAL", "FLOA", "DOUB"]
+            .iter()
+            .any(|w| declared.contains(w))
+        {
+            Affinity::Real
+        } else {
+            Affinity::Numeric
+        }
+    }
+
+    fn is_numeric(self) -> bool {
+        matches!(self, Affinity::Integer | Affinity::Real | Affinity::Numeric)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum BinaryOperator {
+    Concat,
+    Multiply,
+    Divide,
+    Remainder,
+    Add,
+    Subtract,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum UnaryOperator {
+    Negate,
+    Plus,
+    Not,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Expr {
+    Literal(SqlValue),
+    /// A parameter, by its place in the values given from 0
+    Parameter(usize),
+    /// A column as the query names it
+    Column {
+        table: Option<String>,
+        name: String,
+    },
+    /// A column once bound: its place in the row
+    Field {
+        index: usize,
+        affinity: Affinity,
+    },
+    Unary(UnaryOperator, Box<Expr>),
+    Binary(BinaryOperator, Box<Expr>, Box<Expr>),
+    Is {
+        left: Box<Expr>,
+        right: Box<Expr>,
+        negated: bool,
+    },
+    In {
+        expr: Box<Expr>,
+        list: Vec<Expr>,
+        negated: bool,
+    },
+    Between {
+        expr: Box<Expr>,
+        low: Box<Expr>,
+        high: Box<Expr>,
+        negated: bool,
+    },
+    Like {
+        expr: Box<Expr>,
+        pattern: Box<Expr>,
+        escape: Option<Box<Expr>>,
+        negated: bool,
+    },
+    Case {
+        operand: Option<Box<Expr>>,
+        branches: Vec<(Expr, Expr)>,
+        otherwise: Option<Box<Expr>>,
+    },
+    Cast(Box<Expr>, Affinity),
+    /// A scalar function, by its name in lower case
+    Function(String, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum ResultColumn {
+    /// `*`, or `table.*`
+    All(Option<String>),
+    Expr {
+        expr: Expr,
+        alias: Option<String>,
+        /// The expression as written, which names it without an alias
+        text: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct OrderTerm {
+    pub(super) expr: Expr,
+    pub(super) descending: bool,
+    pub(super) nulls_first: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Select {
+    pub(super) distinct: bool,
+    pub(super) columns: Vec<ResultColumn>,
+    /// The table, and the alias it's given
+    pub(super) from: Option<(String, Option<String>)>,
+    pub(super) filter: Option<Expr>,
+    pub(super) order: Vec<OrderTerm>,
+    pub(super) limit: Option<Expr>,
+    pub(super) offset: Option<Expr>,
+    /// How many parameter values the query takes
+    pub(super) parameters: usize,
+}
+
+/// A column of a table as its CREATE TABLE declares it
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct ColumnDef {
+    pub(super) name: String,
+    pub(super) affinity: Affinity,
+    /// The value of rows written before the column was added
+    pub(super) default: SqlValue,
+    /// Whether it's an INTEGER PRIMARY KEY, which is the rowid under another
+    /// name and stored as NULL in the record
+    pub(super) rowid: bool,
+}
+
+/// Parse a query, which must be a single SELECT from at most one table
+pub(super) fn parse_select(sql: &str) -> Result<Select, String> {
+    let mut parser = Parser::new(sql)?;
+    let select = parser.select()?;
+    parser.eat_symbol(";");
+    if parser.peek().is_some() {
+        return Err(format!(
+            "the query goes on after its end, {}",
+            parser.near()
+        ));
+    }
+    Ok(select)
+}
+
+/// Parse a table's CREATE TABLE statement for its columns
+pub(super) fn parse_create_table(sql: &str) -> Result<Vec<ColumnDef>, String> {
+    let mut parser = Parser::new(sql)?;
+    parser.expect_keyword("CREATE")?;
+    if !parser.eat_keyword("TEMP") {
+        parser.eat_keyword("TEMPORARY");
+    }
+    if parser.keyword("VIRTUAL") {
+        return Err("it's a virtual table, which can't be read".to_string());
+    }
+    parser.expect_keyword("TABLE")?;
+    if parser.eat_keyword("IF") {
+        parser.expect_keyword("NOT")?;
+        parser.expect_keyword("EXISTS")?;
+    }
+    parser.name()?;
+    if parser.eat_symbol(".") {
+        parser.name()?;
+    }
+    parser.expect_symbol("(")?;
+
+    let mut columns = Vec::new();
+    // The INTEGER PRIMARY KEY candidates: the declared primary key's columns,
+    // and whether a column's own PRIMARY KEY says DESC
+    let mut key: Vec<String> = Vec::new();
+    let mut descending = false;
+    let mut declared_types = Vec::new();
+    loop {
+        if ["CONSTRAINT", "PRIMARY", "UNIQUE", "CHECK", "FOREIGN"]
+            .iter()
+            .any(|word| parser.keyword(word))
+        {
+            if parser.eat_keyword("CONSTRAINT") {
+                parser.name()?;
+            }
+            if parser.eat_keyword("PRIMARY") {
+                parser.expect_keyword("KEY")?;
+                parser.expect_symbol("(")?;
+                loop {
+                    key.push(parser.name()?);
+                    parser.skip_until(&[",", ")"]);
+                    if !parser.eat_symbol(",") {
+                        break;
+                    }
+                }
+                parser.expect_symbol(")")?;
+            }
+            parser.skip_until(&[",", ")"]);
+        } else {
+            let name = parser.name()?;
+            let mut declared = Vec::new();
+            while let Some(Token::Word(word)) = parser.peek() {
+                if COLUMN_CONSTRAINTS
+                    .iter()
+                    .any(|constraint| word.eq_ignore_ascii_case(constraint))
+                {
+                    break;
+                }
+                declared.push(word.to_ascii_uppercase());
+                parser.at += 1;
+            }
+            if parser.eat_symbol("(") {
+                parser.skip_until(&[")"]);
+                parser.expect_symbol(")")?;
+            }
+            let mut default = SqlValue::Null;
+            loop {
+                if parser.eat_keyword("PRIMARY") {
+                    parser.expect_keyword("KEY")?;
+                    key.push(name.clone());
+                    descending = parser.eat_keyword("DESC");
+                } else if parser.eat_keyword("DEFAULT") {
+                    // A constant, or NULL if it isn't one, such as
+                    // CURRENT_TIMESTAMP
+                    default = parser
+                        .unary()
+                        .and_then(|expr| expr.eval(&[], &[]))
+                        .unwrap_or(SqlValue::Null);
+                } else if parser.keyword("GENERATED") || parser.keyword("AS") {
+                    return Err(format!(
+                        "its column {} is generated, which can't be read",
+                        name
+                    ));
+                } else if parser.eat_symbol("(") {
+                    parser.skip_until(&[")"]);
+                    parser.expect_symbol(")")?;
+                } else if parser.peek().is_none() || parser.symbol(",") || parser.symbol(")") {
+                    break;
+                } else {
+                    parser.at += 1;
+                }
+            }
+            let declared = declared.join(" ");
+            columns.push(ColumnDef {
+                name,
+                affinity: Affinity::of(&declared),
+                default,
+                rowid: false,
+            });
+            declared_types.push(declared);
+        }
+        if !parser.eat_symbol(",") {
+            break;
+        }
+    }
+    parser.expect_symbol(")")?;
+    while let Some(Token::Word(word)) = parser.peek() {
+        if word.eq_ignore_ascii_case("WITHOUT") {
+            return Err("it's a WITHOUT ROWID table, which can't be read".to_string());
+        }
+        parser.at += 1;
+        parser.eat_symbol(",");
+    }
+
+    if let ([key], false) = (&key[..], descending) {
+        for (column, declared) in columns.iter_mut().zip(&declared_types) {
+            column.rowid = column.name.eq_ignore_ascii_case(key) && declared == "INTEGER";
+        }
+    }
+    Ok(columns)
+}
+
+// The words that end a column's declared type
+const COLUMN_CONSTRAINTS: [&str; 12] = [
+    "CONSTRAINT",
+    "PRIMARY",
+    "NOT",
+    "NULL",
+    "UNIQUE",
+    "CHECK",
+    "DEFAULT",
+    "COLLATE",
+    "REFERENCES",
+    "GENERATED",
+    "AS",
+    "AUTOINCREMENT",
+];
+
+// Words that can't be a bare name, as they end or join the expressions
+// around them
+const RESERVED: [&str; 44] = [
+    "ALL",
+    "AND",
+    "AS",
+    "ASC",
+    "BETWEEN",
+    "BY",
+    "CASE",
+    "CAST",
+    "COLLATE",
+    "CROSS",
+    "DESC",
+    "DISTINCT",
+    "ELSE",
+    "END",
+    "ESCAPE",
+    "EXCEPT",
+    "EXISTS",
+    "FROM",
+    "GLOB",
+    "GROUP",
+    "HAVING",
+    "IN",
+    "INNER",
+    "INTERSECT",
+    "IS",
+    "ISNULL",
+    "JOIN",
+    "LEFT",
+    "LIKE",
+    "LIMIT",
+    "NATURAL",
+    "NOT",
+    "NOTNULL",
+    "NULL",
+    "OFFSET",
+    "ON",
+    "OR",
+    "ORDER",
+    "SELECT",
+    "THEN",
+    "UNION",
+    "USING",
+    "WHEN",
+    "WHERE",
+];
+
+// Aggregate functions, which need grouping the reader doesn't do
+const AGGREGATES: [&str; 8] = [
+    "avg",
+    "count",
+    "group_concat",
+    "max",
+    "min",
+    "string_agg",
+    "sum",
+    "total",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    // A name or keyword, as written
+    Word(String),
+    // A name in quotes, never a keyword
+    Quoted(String),
+    Literal(SqlValue),
+    Parameter(Parameter),
+    Symbol(&'static str),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Parameter {
+    // `?`, the one after the highest so far
+    Next,
+    // `?NNN`
+    Numbered(usize),
+    // `:name`, `@name` or `$name`, numbered where it first appears
+    Named(String),
+}
+
+// Longer symbols first, so `<=` isn't read as `<` then `=`
+const SYMBOLS: [&str; 24] = [
+    "||", "<=", ">=", "==", "!=", "<>", "<<", ">>", "*", "/", "%", "+", "-", "<", ">", "=", "(",
+    ")", ",", ".", ";", "&", "|", "~",
+];
+
+fn tokenize(sql: &str) -> Result<Vec<(Token, Range<usize>)>, String> {
+    let bytes = sql.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    // The end of the quoted text starting at `i`, and the text with doubled
+    // quotes made single
+    let quoted = |i: usize, close: u8| -> Result<(usize, String), String> {
+        let mut text = Vec::new();
+        let mut j = i + 1;
+        loop {
+            match bytes.get(j) {
+                None => return Err(format!("the query has an unclosed {}", bytes[i] as char)),
+                Some(&b) if b == close && close != b']' && bytes.get(j + 1) == Some(&close) => {
+                    text.push(b);
+                    j += 2;
+                }
+                Some(&b) if b == close => {
+                    return Ok((j + 1, String::from_utf8_lossy(&text).into_owned()))
+                }
+                Some(&b) => {
+                    text.push(b);
+                    j += 1;
+                }
+            }
+        }
+    };
+    let word_end = |mut j: usize| {
+        while j < bytes.len()
+            && (bytes[j].is_ascii_alphanumeric()
+                || bytes[j] == b'_'
+                || bytes[j] == b'$'
+                || bytes[j] >= 0x80)
+        {
+            j += 1;
+        }
+        j
+    };
+    while i < bytes.len() {
+        let b = bytes[i];
+        let start = i;
+        let token = match b {
+            _ if b.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                i = sql[i..].find('\n').map_or(sql.len(), |end| i + end + 1);
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = sql[i + 2..]
+                    .find("*/")
+                    .map_or(sql.len(), |end| i + 2 + end + 2);
+                continue;
+            }
+            b'\'' => {
+                let (end, text) = quoted(i, b'\'')?;
+                i = end;
+                Token::Literal(SqlValue::Text(text))
+            }
+            b'"' | b'`' | b'[' => {
+                let (end, text) = quoted(i, if b == b'[' { b']' } else { b })?;
+                i = end;
+                Token::Quoted(text)
+            }
+            b'x' | b'X' if bytes.get(i + 1) == Some(&b'\'') => {
+                let (end, hex) = quoted(i + 1, b'\'')?;
+                i = end;
+                let blob = (0..hex.len())
+                    .step_by(2)
+                    .map(|j| {
+                        hex.get(j..j + 2)
+                            .and_then(|h| u8::from_str_radix(h, 16).ok())
+                    })
+                    .collect::<Option<Vec<u8>>>()
+                    .filter(|_| hex.len() % 2 == 0)
+                    .ok_or_else(|| format!("the query has a malformed blob X'{}'", hex))?;
+                Token::Literal(SqlValue::Blob(blob))
+            }
+            b'0'..=b'9' | b'.' if b != b'.' || bytes.get(i + 1).is_some_and(u8::is_ascii_digit) => {
+                let (end, value) = number(sql, i)?;
+                i = end;
+                Token::Literal(value)
+            }
+            b'?' => {
+                let mut j = i + 1;
+                while j < bytes.len() && bytes[j].is_ascii_digit() {
+                    j += 1;
+                }
+                let parameter = if j == i + 1 {
+                    Parameter::Next
+                } else {
+                    match sql[i + 1..j].parse() {
+                        Ok(n @ 1..=32766) => Parameter::Numbered(n),
+                        _ => {
+                            return Err(format!(
+                                "the query has a parameter {} out of range",
+                                &sql[i..j]
+                            ))
+                        }
+                    }
+                };
+                i = j;
+                Token::Parameter(parameter)
+            }
+            b':' | b'@' | b'$' if word_end(i + 1) > i + 1 => {
+                i = word_end(i + 1);
+                Token::Parameter(Parameter::Named(sql[start..i].to_string()))
+            }
+            _ if b.is_ascii_alphabetic() || b == b'_' || b >= 0x80 => {
+                i = word_end(i);
+                Token::Word(sql[start..i].to_string())
+            }
+            _ => {
+                let symbol = SYMBOLS
+                    .iter()
+                    .find(|symbol| sql[i..].starts_with(**symbol))
+                    .ok_or_else(|| {
+                        let c = sql[i..].chars().next().unwrap_or_default();
+                        format!("the query has an unexpected {}", c)
+                    })?;
+                i += symbol.len();
+                Token::Symbol(symbol)
+            }
+        };
+        tokens.push((token, start..i));
+    }
+    Ok(tokens)
+}
+
+// A number starting at `at`, and where it ends: hexadecimal, or decimal with
+// a fraction or exponent making it real
+fn number(sql: &str, at: usize) -> Result<(usize, SqlValue), String> {
+    let bytes = sql.as_bytes();
+    let digits = |mut j: usize, hex: bool| {
+        while j < bytes.len() && (bytes[j].is_ascii_digit() || hex && bytes[j].is_ascii_hexdigit())
+        {
+            j += 1;
+        }
+        j
+    };
+    if bytes[at] == b'0' && matches!(bytes.get(at + 1), Some(b'x' | b'X')) {
+        let end = digits(at + 2, true);
+        let value = u64::from_str_radix(&sql[at + 2..end], 16)
+            .map_err(|_| format!("the query has a malformed number {}", &sql[at..end]))?;
+        return Ok((end, SqlValue::Integer(value as i64)));
+    }
+    let mut end = digits(at, false);
+    let mut real = false;
+    if bytes.get(end) == Some(&b'.') {
+        end = digits(end + 1, false);
+        real = true;
+    }
+    if matches!(bytes.get(end), Some(b'e' | b'E')) {
+        let mut j = end + 1;
+        if matches!(bytes.get(j), Some(b'+' | b'-')) {
+            j += 1;
+        }
+        if bytes.get(j).is_some_and(u8::is_ascii_digit) {
+            end = digits(j, false);
+            real = true;
+        }
+    }
+    let text = &sql[at..end];
+    if bytes
+        .get(end)
+        .is_some_and(|b| b.is_ascii_alphabetic() || *b == b'_')
+    {
+        return Err(format!(
+            "the query has a malformed number {}",
+            &sql[at..=end]
+        ));
+    }
+    let value = match (real, text.parse::<i64>()) {
+        (false, Ok(n)) => SqlValue::Integer(n),
+        _ => SqlValue::Real(
+            text.parse()
+                .map_err(|_| format!("the query has a malformed number {}", text))?,
+        ),
+    };
+    Ok((end, value))
+}
+
+// How deep expressions can nest in brackets, calls and CASEs, which the
+// parser recurses into, and how tall the tree of one can be, which evaluating
+// it recurses down, so that neither runs out of stack
+const MAX_DEPTH: usize = 64;
+const MAX_HEIGHT: usize = 200;
+
+struct Parser<'a> {
+    sql: &'a str,
+    tokens: Vec<(Token, Range<usize>)>,
+    at: usize,
+    // The highest parameter number so far, and the numbers given to names
+    parameters: usize,
+    names: Vec<(String, usize)>,
+    // How many expressions the parser is inside, and how tall the tree of the
+    // expression parsed last is
+    depth: usize,
+    height: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(sql: &'a str) -> Result<Self, String> {
+        Ok(Parser {
+            sql,
+            tokens: tokenize(sql)?,
+            at: 0,
+            parameters: 0,
+            names: Vec::new(),
+            depth: 0,
+            height: 0,
+        })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.at).map(|(token, _)| token)
+    }
+
+    // Where the parser is, for errors
+    fn near(&self) -> String {
+        match self.tokens.get(self.at) {
+            Some((_, span)) => format!("near \"{}\"", &self.sql[span.clone()]),
+            None => "at its end".to_string(),
+        }
+    }
+
+    fn keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.keyword(keyword);
+        self.at += found as usize;
+        found
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), String> {
+        if !self.eat_keyword(keyword) {
+            return Err(format!("expected {} {}", keyword, self.near()));
+        }
+        Ok(())
+    }
+
+    fn symbol(&self, symbol: &str) -> bool {
+        matches!(self.peek(), Some(Token::Symbol(found)) if *found == symbol)
+    }
+
+    fn eat_symbol(&mut self, symbol: &str) -> bool {
+        let found = self.symbol(symbol);
+        self.at += found as usize;
+        found
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), String> {
+        if !self.eat_symbol(symbol) {
+            return Err(format!("expected {} {}", symbol, self.near()));
+        }
+        Ok(())
+    }
+
+    // Skip to the next of `symbols` outside parentheses, or the end
+    fn skip_until(&mut self, symbols: &[&str]) {
+        let mut depth = 0;
+        while self.peek().is_some() {
+            if depth == 0 && symbols.iter().any(|s| self.symbol(s)) {
+                return;
+            }
+            if self.symbol("(") {
+                depth += 1;
+            } else if self.symbol(")") {
+                depth -= 1;
+            }
+            self.at += 1;
+        }
+    }
+
+    // A name: any word, quoted or not, or text in single quotes, which
+    // SQLite takes as a name where one must be
+    fn name(&mut self) -> Result<String, String> {
+        let name = match self.peek() {
+            Some(Token::Word(word) | Token::Quoted(word)) => word.clone(),
+            Some(Token::Literal(SqlValue::Text(text))) => text.clone(),
+            _ => return Err(format!("expected a name {}", self.near())),
+        };
+        self.at += 1;
+        Ok(name)
+    }
+
+    // A name that may stand without AS: a word that isn't reserved, or a
+    // quoted one
+    fn alias(&mut self) -> Result<Option<String>, String> {
+        if self.eat_keyword("AS") {
+            return self.name().map(Some);
+        }
+        match self.peek() {
+            Some(Token::Word(word)) if !reserved(word) => self.name().map(Some),
+            Some(Token::Quoted(_)) => self.name().map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    fn select(&mut self) -> Result<Select, String> {
+        if self.keyword("WITH") {
+            return Err("WITH isn't supported".to_string());
+        }
+        self.expect_keyword("SELECT")?;
+        let distinct = self.eat_keyword("DISTINCT");
+        if !distinct {
+            self.eat_keyword("ALL");
+        }
+        let mut columns = Vec::new();
+        loop {
+            let all_of = match (self.tokens.get(self.at), self.tokens.get(self.at + 1)) {
+                (Some((Token::Symbol("*"), _)), _) => Some(None),
+                (
+                    Some((Token::Word(table) | Token::Quoted(table), _)),
+                    Some((Token::Symbol("."), _)),
+                ) if matches!(self.tokens.get(self.at + 2), Some((Token::Symbol("*"), _))) => {
+                    Some(Some(table.clone()))
+                }
+                _ => None,
+            };
+            if let Some(table) = all_of {
+                self.at += if table.is_some() { 3 } else { 1 };
+                columns.push(ResultColumn::All(table));
+            } else {
+                let start = self
+                    .tokens
+                    .get(self.at)
+                    .map_or(self.sql.len(), |(_, span)| span.start);
+                let expr = self.expr()?;
+                let end = self.tokens[self.at - 1].1.end;
+                columns.push(ResultColumn::Expr {
+                    expr,
+                    alias: self.alias()?,
+                    text: self.sql[start..end].to_string(),
+                });
+            }
+            if !self.eat_symbol(",") {
+                break;
+            }
+        }
+
+        let mut from = None;
+        if self.eat_keyword("FROM") {
+            if self.symbol("(") {
+                return Err("subqueries aren't supported".to_string());
+            }
+            let mut table = self.name()?;
+            if self.eat_symbol(".") {
+                if !table.eq_ignore_ascii_case("main") {
+                    return Err(format!("only the main database can be read, not {}", table));
+                }
+                table = self.name()?;
+            }
+            let alias = self.alias()?;
+            if self.symbol(",")
+                || ["JOIN", "LEFT", "INNER", "CROSS", "NATURAL"]
+                    .iter()
+                    .any(|word| self.keyword(word))
+            {
+                return Err("joins aren't supported; read each table on its own".to_string());
+            }
+            from = Some((table, alias));
+        }
+        let filter = if self.eat_keyword("WHERE") {
+            Some(self.expr()?)
+        } else {
+            None
+        };
+        if self.keyword("GROUP") || self.keyword("HAVING") {
+            return Err("GROUP BY isn't supported; group the table once it's read".to_string());
+        }
+        if ["UNION", "EXCEPT", "INTERSECT"]
+            .iter()
+            .any(|word| self.keyword(word))
+        {
+            return Err("compound SELECTs aren't supported".to_string());
+        }
+        let mut order = Vec::new();
+        if self.eat_keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            loop {
+                let expr = self.expr()?;
+                let descending = self.eat_keyword("DESC");
+                if !descending {
+                    self.eat_keyword("ASC");
+                }
+                // NULL is the least value, so first going up
+                let mut nulls_first = !descending;
+                if self.eat_keyword("NULLS") {
+                    nulls_first = self.eat_keyword("FIRST");
+                    if !nulls_first {
+                        self.expect_keyword("LAST")?;
+                    }
+                }
+                order.push(OrderTerm {
+                    expr,
+                    descending,
+                    nulls_first,
+                });
+                if !self.eat_symbol(",") {
+                    break;
+                }
+            }
+        }
+        let (mut limit, mut offset) = (None, None);
+        if self.eat_keyword("LIMIT") {
+            limit = Some(self.expr()?);
+            if self.eat_keyword("OFFSET") {
+                offset = Some(self.expr()?);
+            } else if self.eat_symbol(",") {
+                // LIMIT offset, count
+                offset = limit.replace(self.expr()?);
+            }
+        }
+        Ok(Select {
+            distinct,
+            columns,
+            from,
+            filter,
+            order,
+            limit,
+            offset,
+            parameters: self.parameters,
+        })
+    }
+
+    fn too_deep(&self, levels: usize) -> String {
+        format!(
+            "an expression nests more than {} levels deep, {}",
+            levels,
+            self.near()
+        )
+    }
+
+    // `expr`, over operands `heights` tall, as the expression parsed last
+    fn node(&mut self, expr: Expr, heights: &[usize]) -> Result<Expr, String> {
+        self.height = 1 + heights.iter().max().unwrap_or(&0);
+        if self.height > MAX_HEIGHT {
+            return Err(self.too_deep(MAX_HEIGHT));
+        }
+        Ok(expr)
+    }
+
+    // `left`, which is `height` tall, and `right`, parsed last, joined by
+    // `operator`
+    fn binary(
+        &mut self,
+        operator: BinaryOperator,
+        left: Expr,
+        height: usize,
+        right: Expr,
+    ) -> Result<Expr, String> {
+        let heights = [height, self.height];
+        self.node(
+            Expr::Binary(operator, Box::new(left), Box::new(right)),
+            &heights,
+        )
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.too_deep(MAX_DEPTH));
+        }
+        self.depth += 1;
+        let mut left = self.and()?;
+        while self.eat_keyword("OR") {
+            let height = self.height;
+            let right = self.and()?;
+            left = self.binary(BinaryOperator::Or, left, height, right)?;
+        }
+        self.depth -= 1;
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut left = self.not()?;
+        while self.eat_keyword("AND") {
+            let height = self.height;
+            let right = self.not()?;
+            left = self.binary(BinaryOperator::And, left, height, right)?;
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        let mut nots = 0;
+        while self.eat_keyword("NOT") {
+            nots += 1;
+        }
+        let mut expr = self.equality()?;
+        for _ in 0..nots {
+            let height = self.height;
+            expr = self.node(Expr::Unary(UnaryOperator::Not, Box::new(expr)), &[height])?;
+        }
+        Ok(expr)
+    }
+
+    // =, IS, IN, BETWEEN, LIKE and the tests for NULL, which all bind alike
+    fn equality(&mut self) -> Result<Expr, String> {
+        let mut left = self.comparison()?;
+        loop {
+            let operator = match self.peek() {
+                Some(Token::Symbol("=" | "==")) => Some(BinaryOperator::Equal),
+                Some(Token::Symbol("!=" | "<>")) => Some(BinaryOperator::NotEqual),
+                _ => None,
+            };
+            if let Some(operator) = operator {
+                self.at += 1;
+                let height = self.height;
+                let right = self.comparison()?;
+                left = self.binary(operator, left, height, right)?;
+                continue;
+            }
+            let null_test = if self.eat_keyword("ISNULL") {
+                Some(false)
+            } else if self.eat_keyword("NOTNULL") {
+                Some(true)
+            } else {
+                None
+            };
+            if let Some(negated) = null_test {
+                let height = self.height;
+                let is = Expr::Is {
+                    left: Box::new(left),
+                    right: Box::new(Expr::Literal(SqlValue::Null)),
+                    negated,
+                };
+                left = self.node(is, &[height])?;
+                continue;
+            }
+            if self.eat_keyword("IS") {
+                let negated = self.eat_keyword("NOT");
+                if self.keyword("DISTINCT") {
+                    return Err("IS DISTINCT FROM isn't supported".to_string());
+                }
+                let height = self.height;
+                let is = Expr::Is {
+                    left: Box::new(left),
+                    right: Box::new(self.comparison()?),
+                    negated,
+                };
+                left = self.node(is, &[height, self.height])?;
+                continue;
+            }
+            let start = self.at;
+            let mut heights = vec![self.height];
+            let negated = self.eat_keyword("NOT");
+            let expr = if negated && self.eat_keyword("NULL") {
+                Expr::Is {
+                    left: Box::new(left),
+                    right: Box::new(Expr::Literal(SqlValue::Null)),
+                    negated: true,
+                }
+            } else if self.eat_keyword("IN") {
+                self.expect_symbol("(")?;
+                if self.keyword("SELECT") {
+                    return Err("subqueries aren't supported".to_string());
+                }
+                let mut list = Vec::new();
+                if !self.symbol(")") {
+                    loop {
+                        list.push(self.expr()?);
+                        heights.push(self.height);
+                        if !self.eat_symbol(",") {
+                            break;
+                        }
+                    }
+                }
+                self.expect_symbol(")")?;
+                Expr::In {
+                    expr: Box::new(left),
+                    list,
+                    negated,
+                }
+            } else if self.eat_keyword("BETWEEN") {
+                let low = self.comparison()?;
+                heights.push(self.height);
+                self.expect_keyword("AND")?;
+                let high = self.comparison()?;
+                heights.push(self.height);
+                Expr::Between {
+                    expr: Box::new(left),
+                    low: Box::new(low),
+                    high: Box::new(high),
+                    negated,
+                }
+            } else if self.eat_keyword("LIKE") {
+                let pattern = self.comparison()?;
+                heights.push(self.height);
+                let escape = if self.eat_keyword("ESCAPE") {
+                    let escape = self.comparison()?;
+                    heights.push(self.height);
+                    Some(Box::new(escape))
+                } else {
+                    None
+                };
+                Expr::Like {
+                    expr: Box::new(left),
+                    pattern: Box::new(pattern),
+                    escape,
+                    negated,
+                }
+            } else if ["GLOB", "MATCH", "REGEXP"]
+                .iter()
+                .any(|word| self.keyword(word))
+            {
+                return Err(format!("{} isn't supported", self.near()));
+            } else {
+                self.at = start;
+                return Ok(left);
+            };
+            left = self.node(expr, &heights)?;
+        }
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let mut left = self.additive()?;
+        loop {
+            let operator = match self.peek() {
+                Some(Token::Symbol("<")) => BinaryOperator::Less,
+                Some(Token::Symbol("<=")) => BinaryOperator::LessEqual,
+                Some(Token::Symbol(">")) => BinaryOperator::Greater,
+                Some(Token::Symbol(">=")) => BinaryOperator::GreaterEqual,
+                Some(Token::Symbol("&" | "|" | "<<" | ">>")) => {
+                    return Err(format!(
+                        "bitwise operators aren't supported, {}",
+                        self.near()
+                    ))
+                }
+                _ => return Ok(left),
+            };
+            self.at += 1;
+            let height = self.height;
+            let right = self.additive()?;
+            left = self.binary(operator, left, height, right)?;
+        }
+    }
+
+    fn additive(&mut self) -> Result<Expr, String> {
+        let mut left = self.multiplicative()?;
+        loop {
+            let operator = match self.peek() {
+                Some(Token::Symbol("+")) => BinaryOperator::Add,
+                Some(Token::Symbol("-")) => BinaryOperator::Subtract,
+                _ => return Ok(left),
+            };
+            self.at += 1;
+            let height = self.height;
+            let right = self.multiplicative()?;
+            left = self.binary(operator, left, height, right)?;
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr, String> {
+        let mut left = self.concat()?;
+        loop {
+            let operator = match self.peek() {
+                Some(Token::Symbol("*")) => BinaryOperator::Multiply,
+                Some(Token::Symbol("/")) => BinaryOperator::Divide,
+                Some(Token::Symbol("%")) => BinaryOperator::Remainder,
+                _ => return Ok(left),
+            };
+            self.at += 1;
+            let height = self.height;
+            let right = self.concat()?;
+            left = self.binary(operator, left, height, right)?;
+        }
+    }
+
+    fn concat(&mut self) -> Result<Expr, String> {
+        let mut left = self.collate()?;
+        while self.eat_symbol("||") {
+            let height = self.height;
+            let right = self.collate()?;
+            left = self.binary(BinaryOperator::Concat, left, height, right)?;
+        }
+        Ok(left)
+    }
+
+    // Only the default collation, which compares text byte by byte
+    fn collate(&mut self) -> Result<Expr, String> {
+        let expr = self.unary()?;
+        if self.keyword("COLLATE") {
+            return Err(format!("COLLATE isn't supported, {}", self.near()));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        let mut operators = Vec::new();
+        loop {
+            match self.peek() {
+                Some(Token::Symbol("-")) => operators.push(UnaryOperator::Negate),
+                Some(Token::Symbol("+")) => operators.push(UnaryOperator::Plus),
+                Some(Token::Symbol("~")) => {
+                    return Err(format!(
+                        "bitwise operators aren't supported, {}",
+                        self.near()
+                    ))
+                }
+                _ => break,
+            }
+            self.at += 1;
+        }
+        let mut expr = self.primary()?;
+        for operator in operators.into_iter().rev() {
+            let height = self.height;
+            expr = self.node(Expr::Unary(operator, Box::new(expr)), &[height])?;
+        }
+        Ok(expr)
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        let Some(token) = self.peek().cloned() else {
+            return Err("the query ends in the middle of an expression".to_string());
+        };
+        self.at += 1;
+        match token {
+            Token::Literal(value) => self.node(Expr::Literal(value), &[]),
+            Token::Parameter(parameter) => {
+                let number = match parameter {
+                    Parameter::Next => self.parameters + 1,
+                    Parameter::Numbered(n) => n,
+                    Parameter::Named(name) => {
+                        match self.names.iter().find(|(other, _)| *other == name) {
+                            Some(&(_, n)) => n,
+                            None => {
+                                self.names.push((name, self.parameters + 1));
+                                self.parameters + 1
+                            }
+                        }
+                    }
+                };
+                self.parameters = self.parameters.max(number);
+                self.node(Expr::Parameter(number - 1), &[])
+            }
+            Token::Symbol("(") => {
+                if self.keyword("SELECT") {
+                    return Err("subqueries aren't supported".to_string());
+                }
+                let expr = self.expr()?;
+                if self.symbol(",") {
+                    return Err("row values aren't supported".to_string());
+                }
+                self.expect_symbol(")")?;
+                Ok(expr)
+            }
+            Token::Quoted(name) => self.column(name),
+            Token::Word(word) => {
+                let upper = word.to_ascii_uppercase();
+                match upper.as_str() {
+                    "NULL" => self.node(Expr::Literal(SqlValue::Null), &[]),
+                    "TRUE" => self.node(Expr::Literal(SqlValue::Integer(1)), &[]),
+                    "FALSE" => self.node(Expr::Literal(SqlValue::Integer(0)), &[]),
+                    "CASE" => self.case(),
+                    "CAST" => {
+                        self.expect_symbol("(")?;
+                        let expr = self.expr()?;
+                        let height = self.height;
+                        self.expect_keyword("AS")?;
+                        let mut declared = Vec::new();
+                        while let Some(Token::Word(word)) = self.peek() {
+                            declared.push(word.clone());
+                            self.at += 1;
+                        }
+                        if self.eat_symbol("(") {
+                            self.skip_until(&[")"]);
+                            self.expect_symbol(")")?;
+                        }
+                        self.expect_symbol(")")?;
+                        let cast = Expr::Cast(Box::new(expr), Affinity::of(&declared.join(" ")));
+                        self.node(cast, &[height])
+                    }
+                    "EXISTS" | "SELECT" => Err("subqueries aren't supported".to_string()),
+                    "CURRENT_DATE" | "CURRENT_TIME" | "CURRENT_TIMESTAMP" | "RAISE" => {
+                        Err(format!("{} isn't supported", word))
+                    }
+                    _ if self.symbol("(") => self.function(word),
+                    _ if reserved(&word) => {
+                        self.at -= 1;
+                        Err(format!("expected an expression {}", self.near()))
+                    }
+                    _ => self.column(word),
+                }
+            }
+            Token::Symbol(_) => {
+                self.at -= 1;
+                Err(format!("expected an expression {}", self.near()))
+            }
+        }
+    }
+
+    // A column, maybe after its table's name
+    fn column(&mut self, name: String) -> Result<Expr, String> {
+        if self.eat_symbol(".") {
+            let column = self.name()?;
+            let column = Expr::Column {
+                table: Some(name),
+                name: column,
+            };
+            return self.node(column, &[]);
+        }
+        self.node(Expr::Column { table: None, name }, &[])
+    }
+
+    fn case(&mut self) -> Result<Expr, String> {
+        let mut heights = Vec::new();
+        let operand = if self.keyword("WHEN") {
+            None
+        } else {
+            let operand = self.expr()?;
+            heights.push(self.height);
+            Some(Box::new(operand))
+        };
+        let mut branches = Vec::new();
+        while self.eat_keyword("WHEN") {
+            let when = self.expr()?;
+            heights.push(self.height);
+            self.expect_keyword("THEN")?;
+            branches.push((when, self.expr()?));
+            heights.push(self.height);
+        }
+        if branches.is_empty() {
+            return Err(format!("expected WHEN {}", self.near()));
+        }
+        let otherwise = if self.eat_keyword("ELSE") {
+            let otherwise = self.expr()?;
+            heights.push(self.height);
+            Some(Box::new(otherwise))
+        } else {
+            None
+        };
+        self.expect_keyword("END")?;
+        let case = Expr::Case {
+            operand,
+            branches,
+            otherwise,
+        };
+        self.node(case, &heights)
+    }
+
+    fn function(&mut self, name: String) -> Result<Expr, String> {
+        self.expect_symbol("(")?;
+        let lower = name.to_ascii_lowercase();
+        let mut args = Vec::new();
+        let mut heights = Vec::new();
+        if self.keyword("DISTINCT") || self.symbol("*") {
+            args.push(Expr::Literal(SqlValue::Null));
+        } else if !self.symbol(")") {
+            loop {
+                args.push(self.expr()?);
+                heights.push(self.height);
+                if !self.eat_symbol(",") {
+                    break;
+                }
+            }
+        }
+        if AGGREGATES.contains(&lower.as_str())
+            && !(matches!(lower.as_str(), "min" | "max") && args.len() > 1)
+        {
+            return Err(format!(
+                "aggregate functions such as {} aren't supported; aggregate the table once it's read",
+                name
+            ));
+        }
+        self.expect_symbol(")")?;
+        if self.keyword("FILTER") || self.keyword("OVER") {
+            return Err(format!("{} isn't supported", self.near()));
+        }
+        let arity = match lower.as_str() {
+            "abs" | "length" | "lower" | "upper" | "typeof" => 1..=1,
+            "ifnull" | "instr" | "nullif" => 2..=2,
+            "iif" | "replace" => 3..=3,
+            "round" | "trim" | "ltrim" | "rtrim" => 1..=2,
+            "substr" | "substring" => 2..=3,
+            "coalesce" | "max" | "min" => 2..=usize::MAX,
+            _ => return Err(format!("the function {} isn't supported", name)),
+        };
+        if !arity.contains(&args.len()) {
+            return Err(format!("{} can't take {} arguments", name, args.len()));
+        }
+        self.node(Expr::Function(lower, args), &heights)
+    }
+}
+
+fn reserved(word: &str) -> bool {
+    RESERVED
+        .iter()
+        .any(|reserved| word.eq_ignore_ascii_case(reserved))
+}
+
+// A column's place in the row and its affinity, from its name and maybe its
+// table's
+type Resolve<'a> = dyn Fn(Option<&str>, &str) -> Result<(usize, Affinity), String> + 'a;
+
+impl Expr {
+    /// Replace each column with its place in the row, as `resolve` gives it
+    pub(super) fn bind(&mut self, resolve: &Resolve<'_>) -> Result<(), String> {
+        if let Expr::Column { table, name } = self {
+            let (index, affinity) = resolve(table.as_deref(), name)?;
+            *self = Expr::Field { index, affinity };
+            return Ok(());
+        }
+        for child in self.children() {
+            child.bind(resolve)?;
+        }
+        Ok(())
+    }
+
+    fn children(&mut self) -> Vec<&mut Expr> {
+        match self {
+            Expr::Literal(_) | Expr::Parameter(_) | Expr::Column { .. } | Expr::Field { .. } => {
+                vec![]
+            }
+            Expr::Unary(_, expr) | Expr::Cast(expr, _) => vec![&mut **expr],
+            Expr::Binary(_, left, right) | Expr::Is { left, right, .. } => {
+                vec![&mut **left, &mut **right]
+            }
+            Expr::In { expr, list, .. } => std::iter::once(&mut **expr).chain(list).collect(),
+            Expr::Between {
+                expr, low, high, ..
+            } => vec![&mut **expr, &mut **low, &mut **high],
+            Expr::Like {
+                expr,
+                pattern,
+                escape,
+                ..
+            } => [Some(expr), Some(pattern), escape.as_mut()]
+                .into_iter()
+                .flatten()
+                .map(|expr| &mut **expr)
+                .collect(),
+            Expr::Case {
+                operand,
+                branches,
+                otherwise,
+            } => operand
+                .iter_mut()
+                .map(|expr| &mut **expr)
+                .chain(branches.iter_mut().flat_map(|(when, then)| [when, then]))
+                .chain(otherwise.iter_mut().map(|expr| &mut **expr))
+                .collect(),
+            Expr::Function(_, args) => args.iter_mut().collect(),
+        }
+    }
+
+    // The affinity comparisons give the expression: its column's or CAST's,
+    // or none
+    fn affinity(&self) -> Option<Affinity> {
+        match self {
+            Expr::Field { affinity, .. } | Expr::Cast(_, affinity) => {
+                Some(*affinity).filter(|affinity| *affinity != Affinity::Blob)
+            }
+            _ => None,
+        }
+    }
+
+    /// The value of the expression for a row of values, with the query's
+    /// parameters
+    pub(super) fn eval(&self, row: &[SqlValue], params: &[SqlValue]) -> Result<SqlValue, String> {
+        let eval = |expr: &Expr| expr.eval(row, params);
+        Ok(match self {
+            Expr::Literal(value) => value.clone(),
+            Expr::Parameter(i) => params.get(*i).cloned().unwrap_or(SqlValue::Null),
+            Expr::Column { name, .. } => return Err(format!("no such column: {}", name)),
+            Expr::Field { index, .. } => row.get(*index).cloned().unwrap_or(SqlValue::Null),
+            Expr::Unary(operator, expr) => {
+                let value = eval(expr)?;
+                match operator {
+                    UnaryOperator::Plus => value,
+                    UnaryOperator::Not => truth_value(truth(&value).map(|truth| !truth)),
+                    UnaryOperator::Negate => match numeric(&value) {
+                        SqlValue::Integer(n) => n
+                            .checked_neg()
+                            .map_or(SqlValue::Real(-(n as f64)), SqlValue::Integer),
+                        SqlValue::Real(x) => SqlValue::Real(-x),
+                        other => other,
+                    },
+                }
+            }
+            Expr::Binary(BinaryOperator::And, left, right) => match truth(&eval(left)?) {
+                Some(false) => SqlValue::Integer(0),
+                left => match (left, truth(&eval(right)?)) {
+                    (_, Some(false)) => SqlValue::Integer(0),
+                    (Some(true), Some(true)) => SqlValue::Integer(1),
+                    _ => SqlValue::Null,
+                },
+            },
+            Expr::Binary(BinaryOperator::Or, left, right) => match truth(&eval(left)?) {
+                Some(true) => SqlValue::Integer(1),
+                left => match (left, truth(&eval(right)?)) {
+                    (_, Some(true)) => SqlValue::Integer(1),
+                    (Some(false), Some(false)) => SqlValue::Integer(0),
+                    _ => SqlValue::Null,
+                },
+            },
+            Expr::Binary(operator, left, right) => {
+                let (a, b) = (eval(left)?, eval(right)?);
+                match operator {
+                    BinaryOperator::Concat => match (&a, &b) {
+                        (SqlValue::Null, _) | (_, SqlValue::Null) => SqlValue::Null,
+                        _ => SqlValue::Text(text(&a) + &text(&b)),
+                    },
+                    BinaryOperator::Add
+                    | BinaryOperator::Subtract
+                    | BinaryOperator::Multiply
+                    | BinaryOperator::Divide
+                    | BinaryOperator::Remainder => arithmetic(*operator, &a, &b),
+                    _ => {
+                        let ordering = compare_as(left, right, a, b);
+                        truth_value(ordering.map(|ordering| match operator {
+                            BinaryOperator::Less => ordering.is_lt(),
+                            BinaryOperator::LessEqual => ordering.is_le(),
+                            BinaryOperator::Greater => ordering.is_gt(),
+                            BinaryOperator::GreaterEqual => ordering.is_ge(),
+                            BinaryOperator::Equal => ordering.is_eq(),
+                            _ => ordering.is_ne(),
+                        }))
+                    }
+                }
+            }
+            Expr::Is {
+                left,
+                right,
+                negated,
+            } => {
+                let (a, b) = (eval(left)?, eval(right)?);
+                let same = match (&a, &b) {
+                    (SqlValue::Null, SqlValue::Null) => true,
+                    (SqlValue::Null, _) | (_, SqlValue::Null) => false,
+                    _ => compare_as(left, right, a, b) == Some(Ordering::Equal),
+                };
+                SqlValue::Integer((same != *negated) as i64)
+            }
+            Expr::In {
+                expr,
+                list,
+                negated,
+            } => {
+                let value = eval(expr)?;
+                if value == SqlValue::Null {
+                    return Ok(SqlValue::Null);
+                }
+                let mut found = Some(false);
+                for item in list {
+                    match compare_as(expr, item, value.clone(), eval(item)?) {
+                        Some(Ordering::Equal) => {
+                            found = Some(true);
+                            break;
+                        }
+                        None => found = None,
+                        _ => {}
+                    }
+                }
+                truth_value(found.map(|found| found != *negated))
+            }
+            Expr::Between {
+                expr,
+                low,
+                high,
+                negated,
+            } => {
+                let value = eval(expr)?;
+                let above = compare_as(expr, low, value.clone(), eval(low)?).map(Ordering::is_ge);
+                let below = compare_as(expr, high, value, eval(high)?).map(Ordering::is_le);
+                let between = match (above, below) {
+                    (Some(false), _) | (_, Some(false)) => Some(false),
+                    (Some(true), Some(true)) => Some(true),
+                    _ => None,
+                };
+                truth_value(between.map(|between| between != *negated))
+            }
+            Expr::Like {
+                expr,
+                pattern,
+                escape,
+                negated,
+            } => {
+                let (value, pattern) = (eval(expr)?, eval(pattern)?);
+                let escape = match escape {
+                    Some(escape) => match eval(escape)? {
+                        SqlValue::Null => return Ok(SqlValue::Null),
+                        escape => {
+                            let escape: Vec<char> = text(&escape).chars().collect();
+                            match escape[..] {
+                                [c] => Some(c),
+                                _ => return Err("ESCAPE takes a single character".to_string()),
+                            }
+                        }
+                    },
+                    None => None,
+                };
+                if value == SqlValue::Null || pattern == SqlValue::Null {
+                    return Ok(SqlValue::Null);
+                }
+                let pattern: Vec<char> = text(&pattern).chars().collect();
+                let value: Vec<char> = text(&value).chars().collect();
+                SqlValue::Integer((like(&pattern, &value, escape) != *negated) as i64)
+            }
+            Expr::Case {
+                operand,
+                branches,
+                otherwise,
+            } => {
+                let operand = match operand {
+                    Some(operand) => Some(eval(operand)?),
+                    None => None,
+                };
+                for (when, then) in branches {
+                    let value = eval(when)?;
+                    let matched = match &operand {
+                        Some(operand) => {
+                            compare(operand, &value) == Ordering::Equal
+                                && *operand != SqlValue::Null
+                                && value != SqlValue::Null
+                        }
+                        None => truth(&value) == Some(true),
+                    };
+                    if matched {
+                        return eval(then);
+                    }
+                }
+                match otherwise {
+                    Some(otherwise) => eval(otherwise)?,
+                    None => SqlValue::Null,
+                }
+            }
+            Expr::Cast(expr, affinity) => cast(eval(expr)?, *affinity),
+            Expr::Function(name, args) => function(name, args, row, params)?,
+        })
+    }
+}
+
+// Compare two values as the expressions they came from have them compared:
+// a column leaning to numbers makes text a number to compare with it, and
+// one leaning to text makes a value with no affinity text. None if either
+// is NULL.
+fn compare_as(left: &Expr, right: &Expr, a: SqlValue, b: SqlValue) -> Option<Ordering> {
+    let (a, b) = match (left.affinity(), right.affinity()) {
+        (Some(l), r) if l.is_numeric() && !r.is_some_and(Affinity::is_numeric) => {
+            (a, apply(b, Affinity::Numeric))
+        }
+        (l, Some(r)) if r.is_numeric() && !l.is_some_and(Affinity::is_numeric) => {
+            (apply(a, Affinity::Numeric), b)
+        }
+        (Some(Affinity::Text), None) => (a, apply(b, Affinity::Text)),
+        (None, Some(Affinity::Text)) => (apply(a, Affinity::Text), b),
+        _ => (a, b),
+    };
+    if a == SqlValue::Null || b == SqlValue::Null {
+        return None;
+    }
+    Some(compare(&a, &b))
+}
+
+// A value leant by an affinity as a comparison leans it: text that reads as
+// a number becomes one, and a number becomes text
+fn apply(value: SqlValue, affinity: Affinity) -> SqlValue {
+    match (affinity, &value) {
+        (Affinity::Text, SqlValue::Integer(_) | SqlValue::Real(_)) => SqlValue::Text(text(&value)),
+        (Affinity::Integer | Affinity::Real | Affinity::Numeric, SqlValue::Text(text)) => {
+            let trimmed = text.trim();
+            match (trimmed.parse::<i64>(), trimmed.parse::<f64>()) {
+                (Ok(n), _) => SqlValue::Integer(n),
+                (_, Ok(x)) if x.is_finite() && looks_numeric(trimmed) => SqlValue::Real(x),
+                _ => value,
+            }
+        }
+        _ => value,
+    }
+}
+
+// Whether text is a number as SQL writes one, not Rust's "inf" or "NaN"
+fn looks_numeric(text: &str) -> bool {
+    text.bytes()
+        .all(|b| b.is_ascii_digit() || matches!(b, b'.' | b'e' | b'E' | b'+' | b'-'))
+}
+
+/// SQLite's order of values: NULL, then numbers, then text, then blobs
+pub(super) fn compare(a: &SqlValue, b: &SqlValue) -> Ordering {
+    let rank = |value: &SqlValue| match value {
+        SqlValue::Null => 0,
+        SqlValue::Integer(_) | SqlValue::Real(_) => 1,
+        SqlValue::Text(_) => 2,
+        SqlValue::Blob(_) => 3,
+    };
+    rank(a).cmp(&rank(b)).then_with(|| match (a, b) {
+        (SqlValue::Integer(x), SqlValue::Integer(y)) => x.cmp(y),
+        (SqlValue::Integer(x), SqlValue::Real(y)) => {
+            (*x as f64).partial_cmp(y).unwrap_or(Ordering::Equal)
+        }
+        (SqlValue::Real(x), SqlValue::Integer(y)) => {
+            x.partial_cmp(&(*y as f64)).unwrap_or(Ordering::Equal)
+        }
+        (SqlValue::Real(x), SqlValue::Real(y)) => x.partial_cmp(y).unwrap_or(Ordering::Equal),
+        (SqlValue::Text(x), SqlValue::Text(y)) => x.cmp(y),
+        (SqlValue::Blob(x), SqlValue::Blob(y)) => x.cmp(y),
+        _ => Ordering::Equal,
+    })
+}
+
+/// Whether a value is true: NULL is neither, and anything else is true if
+/// it's a number other than 0
+pub(super) fn truth(value: &SqlValue) -> Option<bool> {
+    match numeric(value) {
+        SqlValue::Null => None,
+        SqlValue::Integer(n) => Some(n != 0),
+        SqlValue::Real(x) => Some(x != 0.0),
+        _ => Some(false),
+    }
+}
+
+fn truth_value(truth: Option<bool>) -> SqlValue {
+    match truth {
+        Some(truth) => SqlValue::Integer(truth as i64),
+        None => SqlValue::Null,
+    }
+}
+
+// A value as arithmetic takes it: text or a blob as the number it starts
+// with, or 0 if it doesn't start with one
+fn numeric(value: &SqlValue) -> SqlValue {
+    let text = match value {
+        SqlValue::Text(text) => text.clone(),
+        SqlValue::Blob(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+        _ => return value.clone(),
+    };
+    let text = text.trim_start();
+    let bytes = text.as_bytes();
+    let digits = |mut j: usize| {
+        while j < bytes.len() && bytes[j].is_ascii_digit() {
+            j += 1;
+        }
+        j
+    };
+    let mut end = if matches!(bytes.first(), Some(b'+' | b'-')) {
+        1
+    } else {
+        0
+    };
+    let start = end;
+    end = digits(end);
+    let mut real = false;
+    if bytes.get(end) == Some(&b'.') {
+        end = digits(end + 1);
+        real = true;
+    }
+    if end == start || (real && end == start + 1) {
+        return SqlValue::Integer(0);
+    }
+    if matches!(bytes.get(end), Some(b'e' | b'E')) {
+        let mut j = end + 1;
+        if matches!(bytes.get(j), Some(b'+' | b'-')) {
+            j += 1;
+        }
+        if bytes.get(j).is_some_and(u8::is_ascii_digit) {
+            end = digits(j);
+            real = true;
+        }
+    }
+    let prefix = &text[..end];
+    match (real, prefix.parse::<i64>()) {
+        (false, Ok(n)) => SqlValue::Integer(n),
+        _ => SqlValue::Real(prefix.parse().unwrap_or(0.0)),
+    }
+}
+
+// A value as text, as SQLite writes it
+fn text(value: &SqlValue) -> String {
+    match value {
+        SqlValue::Null => String::new(),
+        SqlValue::Integer(n) => n.to_string(),
+        SqlValue::Real(x) => real_text(*x),
+        SqlValue::Text(text) => text.clone(),
+        SqlValue::Blob(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+// A real as SQLite's "%!.15g" writes it, with ".0" on whole numbers
+fn real_text(x: f64) -> String {
+    if !x.is_finite() {
+        return match x {
+            x if x.is_nan() => String::new(),
+            x if x > 0.0 => "Inf".to_string(),
+            _ => "-Inf".to_string(),
+        };
+    }
+    let scientific = format!("{:.14e}", x);
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    let mantissa = mantissa.trim_end_matches('0').trim_end_matches('.');
+    if !(-4..15).contains(&exponent) {
+        let mantissa = if mantissa.contains('.') {
+            mantissa.to_string()
+        } else {
+            format!("{}.0", mantissa)
+        };
+        return format!(
+            "{}e{}{:02}",
+            mantissa,
+            if exponent < 0 { '-' } else { '+' },
+            exponent.abs()
+        );
+    }
+    let decimals = (14 - exponent).max(0) as usize;
+    let fixed = format!("{:.*}", decimals, x);
+    let fixed = if fixed.contains('.') {
+        fixed.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        &fixed
+    };
+    if fixed.contains('.') {
+        fixed.to_string()
+    } else {
+        format!("{}.0", fixed)
+    }
+}
+
+fn arithmetic(operator: BinaryOperator, a: &SqlValue, b: &SqlValue) -> SqlValue {
+    if *a == SqlValue::Null || *b == SqlValue::Null {
+        return SqlValue::Null;
+    }
+    let result = match (numeric(a), numeric(b)) {
+        (SqlValue::Integer(x), SqlValue::Integer(y)) => {
+            // Whole numbers stay whole, unless they overflow
+            let whole = match operator {
+                BinaryOperator::Add => x.checked_add(y),
+                BinaryOperator::Subtract => x.checked_sub(y),
+                BinaryOperator::Multiply => x.checked_mul(y),
+                _ if y == 0 => return SqlValue::Null,
+                BinaryOperator::Divide => x.checked_div(y),
+                _ => Some(x.checked_rem(y).unwrap_or(0)),
+            };
+            match whole {
+                Some(n) => return SqlValue::Integer(n),
+                None => real_arithmetic(operator, x as f64, y as f64),
+            }
+        }
+        (x, y) => real_arithmetic(operator, as_real(&x), as_real(&y)),
+    };
+    match result {
+        Some(x) if !x.is_nan() => SqlValue::Real(x),
+        _ => SqlValue::Null,
+    }
+}
+
+fn real_arithmetic(operator: BinaryOperator, x: f64, y: f64) -> Option<f64> {
+    match operator {
+        BinaryOperator::Add => Some(x + y),
+        BinaryOperator::Subtract => Some(x - y),
+        BinaryOperator::Multiply => Some(x * y),
+        _ if y == 0.0 => None,
+        BinaryOperator::Divide => Some(x / y),
+        // The remainder of the whole parts, as SQLite has it
+        _ => {
+            let (x, y) = (x as i64, y as i64);
+            (y != 0).then(|| x.checked_rem(y).unwrap_or(0) as f64)
+        }
+    }
+}
+
+fn as_real(value: &SqlValue) -> f64 {
+    match value {
+        SqlValue::Integer(n) => *n as f64,
+        SqlValue::Real(x) => *x,
+        _ => 0.0,
+    }
+}
+
+/// A value CAST to an affinity
+pub(super) fn cast(value: SqlValue, affinity: Affinity) -> SqlValue {
+    if value == SqlValue::Null {
+        return value;
+    }
+    match affinity {
+        Affinity::Text => SqlValue::Text(text(&value)),
+        Affinity::Blob => match value {
+            SqlValue::Blob(_) => value,
+            _ => SqlValue::Blob(text(&value).into_bytes()),
+        },
+        Affinity::Real => SqlValue::Real(as_real(&numeric(&value))),
+        Affinity::Integer => match numeric(&value) {
+            // Saturating, as SQLite's is
+            SqlValue::Real(x) => SqlValue::Integer(x as i64),
+            other => other,
+        },
+        Affinity::Numeric => match numeric(&value) {
+            SqlValue::Real(x) if x.fract() == 0.0 && x.abs() < 9.2e18 => {
+                SqlValue::Integer(x as i64)
+            }
+            other => other,
+        },
+    }
+}
+
+// Whether text matches a LIKE pattern, in which `%` is any run of characters
+// and `_` any one, with ASCII letters matching either case
+fn like(pattern: &[char], text: &[char], escape: Option<char>) -> bool {
+    let same = |a: char, b: char| a.eq_ignore_ascii_case(&b);
+    let (mut p, mut t) = (0, 0);
+    // Where the last `%` was, and the text it's been matched against so far
+    let mut percent: Option<(usize, usize)> = None;
+    loop {
+        if let Some(&c) = pattern.get(p) {
+            if Some(c) == escape {
+                if let (Some(&literal), Some(&actual)) = (pattern.get(p + 1), text.get(t)) {
+                    if same(literal, actual) {
+                        p += 2;
+                        t += 1;
+                        continue;
+                    }
+                }
+            } else if c == '%' {
+                percent = Some((p, t));
+                p += 1;
+                continue;
+            } else if let Some(&actual) = text.get(t) {
+                if c == '_' || same(c, actual) {
+                    p += 1;
+                    t += 1;
+                    continue;
+                }
+            }
+        } else if t == text.len() {
+            return true;
+        }
+        // Let the last `%` take one more character and try again from there
+        match percent {
+            Some((at, taken)) if taken < text.len() => {
+                percent = Some((at, taken + 1));
+                p = at + 1;
+                t = taken + 1;
+            }
+            _ => return false,
+        }
+    }
+}
+
+fn function(
+    name: &str,
+    args: &[Expr],
+    row: &[SqlValue],
+    params: &[SqlValue],
+) -> Result<SqlValue, String> {
+    // The functions that don't need every argument
+    match name {
+        "coalesce" | "ifnull" => {
+            for arg in args {
+                let value = arg.eval(row, params)?;
+                if value != SqlValue::Null {
+                    return Ok(value);
+                }
+            }
+            return Ok(SqlValue::Null);
+        }
+        "iif" => {
+            let branch = match truth(&args[0].eval(row, params)?) {
+                Some(true) => &args[1],
+                _ => &args[2],
+            };
+            return branch.eval(row, params);
+        }
+        _ => {}
+    }
+    let values = args
+        .iter()
+        .map(|arg| arg.eval(row, params))
+        .collect::<Result<Vec<_>, _>>()?;
+    let first = &values[0];
+    if matches!(name, "typeof") {
+        let name = match first {
+            SqlValue::Null => "null",
+            SqlValue::Integer(_) => "integer",
+            SqlValue::Real(_) => "real",
+            SqlValue::Text(_) => "text",
+            SqlValue::Blob(_) => "blob",
+        };
+        return Ok(SqlValue::Text(name.to_string()));
+    }
+    if name == "nullif" {
+        return Ok(match compare(first, &values[1]) {
+            Ordering::Equal if *first != SqlValue::Null => SqlValue::Null,
+            _ => first.clone(),
+        });
+    }
+    if values.contains(&SqlValue::Null) {
+        return Ok(SqlValue::Null);
+    }
+    Ok(match name {
+        "abs" => match numeric(first) {
+            SqlValue::Integer(n) => SqlValue::Integer(
+                n.checked_abs()
+                    .ok_or_else(|| "integer overflow in abs".to_string())?,
+            ),
+            SqlValue::Real(x) => SqlValue::Real(x.abs()),
+            other => other,
+        },
+        "length" => match first {
+            SqlValue::Blob(bytes) => SqlValue::Integer(bytes.len() as i64),
+            _ => SqlValue::Integer(text(first).chars().count() as i64),
+        },
+        "lower" => SqlValue::Text(text(first).to_ascii_lowercase()),
+        "upper" => SqlValue::Text(text(first).to_ascii_uppercase()),
+        "min" | "max" => {
+            let mut best = first;
+            for value in &values[1..] {
+                let ordering = compare(value, best);
+                if (name == "min" && ordering.is_lt()) || (name == "max" && ordering.is_gt()) {
+                    best = value;
+                }
+            }
+            best.clone()
+        }
+        "round" => {
+            let digits = match values.get(1).map(numeric) {
+                Some(SqlValue::Integer(n)) => n.clamp(0, 30) as i32,
+                Some(SqlValue::Real(x)) => (x as i64).clamp(0, 30) as i32,
+                _ => 0,
+            };
+            let x = as_real(&numeric(first));
+            let scale = 10f64.powi(digits);
+            let rounded = (x * scale).round() / scale;
+            SqlValue::Real(if rounded.is_finite() { rounded } else { x })
+        }
+        "instr" => {
+            let (haystack, needle) = (text(first), text(&values[1]));
+            let at = haystack
+                .find(&needle)
+                .map_or(0, |at| haystack[..at].chars().count() + 1);
+            SqlValue::Integer(at as i64)
+        }
+        "replace" => {
+            let from = text(&values[1]);
+            if from.is_empty() {
+                SqlValue::Text(text(first))
+            } else {
+                SqlValue::Text(text(first).replace(&from, &text(&values[2])))
+            }
+        }
+        "trim" | "ltrim" | "rtrim" => {
+            let characters: Vec<char> = match values.get(1) {
+                Some(characters) => text(characters).chars().collect(),
+                None => vec![' '],
+            };
+            let value = text(first);
+            let trim = |c: char| characters.contains(&c);
+            SqlValue::Text(
+                match name {
+                    "ltrim" => value.trim_start_matches(trim),
+                    "rtrim" => value.trim_end_matches(trim),
+                    _ => value.trim_matches(trim),
+                }
+                .to_string(),
+            )
+        }
+        _ => substr(&values),
+    })
+}
+
+// substr(text, start, length): characters from the start'th, counting from
+// 1, or back from the end if it's negative; a negative length takes the
+// characters before the start instead
+fn substr(values: &[SqlValue]) -> SqlValue {
+    let whole = |value: &SqlValue| match numeric(value) {
+        SqlValue::Integer(n) => n,
+        SqlValue::Real(x) => x as i64,
+        _ => 0,
+    };
+    let chars: Vec<char> = text(&values[0]).chars().collect();
+    let len = chars.len() as i64;
+    let mut start = whole(&values[1]);
+    let mut count = values.get(2).map_or(i64::MAX / 2, whole);
+    let backwards = count < 0;
+    if backwards {
+        count = count.saturating_neg();
+    }
+    if start < 0 {
+        start += len;
+        if start < 0 {
+            count = (count + start).max(0);
+            start = 0;
+        }
+    } else if start > 0 {
+        start -= 1;
+    } else if count > 0 {
+        count -= 1;
+    }
+    if backwards {
+        start -= count;
+        if start < 0 {
+            count += start;
+            start = 0;
+        }
+    }
+    let from = start.clamp(0, len) as usize;
+    let to = start.saturating_add(count).clamp(0, len) as usize;
+    SqlValue::Text(chars[from..to.max(from)].iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(sql: &str) -> SqlValue {
+        let select = parse_select(&format!("SELECT {}", sql)).unwrap();
+        match &select.columns[0] {
+            ResultColumn::Expr { expr, .. } => expr.eval(&[], &[]).unwrap(),
+            ResultColumn::All(_) => panic!("{}", sql),
+        }
+    }
+
+    #[test]
+    fn test_expressions() {
+        use SqlValue::{Integer, Null, Real, Text};
+        assert_eq!(eval("1 + 2 * 3"), Integer(7));
+        assert_eq!(eval("7 / 2"), Integer(3));
+        assert_eq!(eval("7 / 2.0"), Real(3.5));
+        assert_eq!(eval("7 % 0"), Null);
+        assert_eq!(
+            eval("9223372036854775807 + 1"),
+            Real(9.223_372_036_854_776e18)
+        );
+        assert_eq!(eval("'3 apples' + 1"), Integer(4));
+        assert_eq!(eval("'a' || 1 || 2.5"), Text("a12.5".to_string()));
+        assert_eq!(eval("-(2)"), Integer(-2));
+        // Three-valued logic
+        assert_eq!(eval("NULL AND 0"), Integer(0));
+        assert_eq!(eval("NULL OR 0"), Null);
+        assert_eq!(eval("NOT NULL"), Null);
+        assert_eq!(eval("NULL = NULL"), Null);
+        assert_eq!(eval("NULL IS NULL"), Integer(1));
+        assert_eq!(eval("1 IS NOT 2"), Integer(1));
+        assert_eq!(eval("2 IN (1, NULL)"), Null);
+        assert_eq!(eval("2 NOT IN (1, 2)"), Integer(0));
+        assert_eq!(eval("5 BETWEEN 1 AND 10"), Integer(1));
+        // Numbers come before text, whatever the text says
+        assert_eq!(eval("10 < '9'"), Integer(1));
+        assert_eq!(eval("'Apple' LIKE 'a%E'"), Integer(1));
+        assert_eq!(eval("'100%' LIKE '100!%' ESCAPE '!'"), Integer(1));
+        assert_eq!(eval("'1000' LIKE '100!%' ESCAPE '!'"), Integer(0));
+        assert_eq!(eval("'abc' LIKE '_b'"), Integer(0));
+        assert_eq!(
+            eval("CASE 2 WHEN 1 THEN 'one' WHEN 2 THEN 'two' END"),
+            Text("two".to_string())
+        );
+        assert_eq!(eval("CASE WHEN 0 THEN 1 END"), Null);
+        assert_eq!(eval("CAST('12.5kg' AS INTEGER)"), Integer(12));
+        assert_eq!(eval("CAST(3.0 AS NUMERIC)"), Integer(3));
+        assert_eq!(eval("CAST(1 AS REAL) || ''"), Text("1.0".to_string()));
+        assert_eq!(eval("(0.1 + 0.2) || ''"), Text("0.3".to_string()));
+        assert_eq!(eval("coalesce(NULL, NULL, 3)"), Integer(3));
+        assert_eq!(eval("substr('hello', 2, 3)"), Text("ell".to_string()));
+        assert_eq!(eval("substr('hello', -3)"), Text("llo".to_string()));
+        assert_eq!(eval("substr('hello', 0, 2)"), Text("h".to_string()));
+        assert_eq!(eval("substr('hello', 3, -2)"), Text("he".to_string()));
+        assert_eq!(eval("round(2.5)"), Real(3.0));
+        assert_eq!(eval("round(1.23456, 2)"), Real(1.23));
+        assert_eq!(eval("max(1, 'a', 2)"), Text("a".to_string()));
+        assert_eq!(eval("instr('banana', 'na')"), Integer(3));
+        assert_eq!(eval("typeof(1.0)"), Text("real".to_string()));
+        assert_eq!(eval("length(x'0102')"), Integer(2));
+    }
+
+    #[test]
+    fn test_parse_select() {
+        let select = parse_select(
+            "select distinct a, b + 1 total, \"c d\" from main.t as x \
+             where a > ? and b = :b or c = :b \
+             order by 2 desc nulls last, a limit 10 offset ?3;",
+        )
+        .unwrap();
+        assert!(select.distinct);
+        assert_eq!(select.from, Some(("t".to_string(), Some("x".to_string()))));
+        assert_eq!(select.parameters, 3);
+        let names: Vec<_> = select
+            .columns
+            .iter()
+            .map(|column| match column {
+                ResultColumn::Expr { alias, text, .. } => alias.clone().unwrap_or(text.clone()),
+                ResultColumn::All(_) => "*".to_string(),
+            })
+            .collect();
+        assert_eq!(names, ["a", "total", "\"c d\""]);
+        assert!(select.order[0].descending && !select.order[0].nulls_first);
+        assert!(!select.order[1].descending && select.order[1].nulls_first);
+        assert_eq!(select.offset, Some(Expr::Parameter(2)));
+
+        let error = |sql: &str| parse_select(sql).unwrap_err();
+        assert!(error("SELECT * FROM a JOIN b").contains("joins"));
+        assert!(error("SELECT * FROM a, b").contains("joins"));
+        assert!(error("SELECT count(*) FROM a").contains("aggregate"));
+        assert!(error("SELECT a FROM t GROUP BY a").contains("GROUP BY"));
+        assert!(error("SELECT a FROM (SELECT 1)").contains("subqueries"));
+        assert!(error("SELECT a FROM t; DROP TABLE t").contains("goes on"));
+        assert!(error("SELECT 'open").contains("unclosed"));
+        assert!(error("SELECT nosuch(1)").contains("isn't supported"));
+        assert!(error("SELECT 1 +").contains("ends"));
+        assert!(error("DELETE FROM t").contains("expected SELECT"));
+    }
+
+    #[test]
+    fn test_nesting_depth() {
+        let deep = |n: usize| format!("SELECT {}1{}", "(".repeat(n), ")".repeat(n));
+        assert!(parse_select(&deep(MAX_DEPTH - 1)).is_ok());
+        assert!(parse_select(&deep(MAX_DEPTH))
+            .unwrap_err()
+            .contains("64 levels"));
+        let chain = |n: usize| format!("SELECT 1{}", " + 1".repeat(n));
+        let select = parse_select(&chain(MAX_HEIGHT - 1)).unwrap();
+        let ResultColumn::Expr { expr, .. } = &select.columns[0] else {
+            panic!("expected an expression")
+        };
+        assert_eq!(
+            expr.eval(&[], &[]),
+            Ok(SqlValue::Integer(MAX_HEIGHT as i64))
+        );
+        assert!(parse_select(&chain(MAX_HEIGHT))
+            .unwrap_err()
+            .contains("200 levels"));
+
+        // Far too deep to parse or evaluate by recursing, but an error
+        for sql in [
+            deep(100_000),
+            chain(100_000),
+            format!("SELECT {}1", "- ".repeat(100_000)),
+            format!("SELECT {}1", "NOT ".repeat(100_000)),
+            format!("SELECT {}1{}", "abs(".repeat(100_000), ")".repeat(100_000)),
+        ] {
+            assert!(parse_select(&sql).unwrap_err().contains("nests more than"));
+        }
+    }
+
+    #[test]
+    fn test_parse_create_table() {
+        let columns = parse_create_table(
+            "CREATE TABLE IF NOT EXISTS \"Orders\" (\
+             id INTEGER PRIMARY KEY AUTOINCREMENT, \
+             [customer name] VARCHAR(40) NOT NULL DEFAULT 'n/a', \
+             amount DECIMAL(10, 2) CHECK (amount >= 0), \
+             placed, \
+             weight REAL DEFAULT -1.5, \
+             CONSTRAINT fk FOREIGN KEY (placed) REFERENCES other(id))",
+        )
+        .unwrap();
+        let summary: Vec<_> = columns
+            .iter()
+            .map(|c| (c.name.as_str(), c.affinity, c.default.clone(), c.rowid))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("id", Affinity::Integer, SqlValue::Null, true),
+                (
+                    "customer name",
+                    Affinity::Text,
+                    SqlValue::Text("n/a".to_string()),
+                    false
+                ),
+                ("amount", Affinity::Numeric, SqlValue::Null, false),
+                ("placed", Affinity::Blob, SqlValue::Null, false),
+                ("weight", Affinity::Real, SqlValue::Real(-1.5), false),
+            ]
+        );
+
+        // Only a lone INTEGER key is the rowid
+        let columns = parse_create_table("CREATE TABLE t (a INT PRIMARY KEY, b)").unwrap();
+        assert!(!columns[0].rowid);
+        let columns =
+            parse_create_table("CREATE TABLE t (a INTEGER, b, PRIMARY KEY (a, b))").unwrap();
+        assert!(!columns[0].rowid);
+        let columns = parse_create_table("CREATE TABLE t (a INTEGER, b, PRIMARY KEY (a))").unwrap();
+        assert!(columns[0].rowid);
+        assert!(parse_create_table("CREATE TABLE t (a PRIMARY KEY) WITHOUT ROWID").is_err());
+        assert!(parse_create_table("CREATE VIRTUAL TABLE t USING fts5(a)").is_err());
+    }
+}
@@ -0,0 +1,455 @@
+// io/xlsx.rs
+
+//! Reading Excel workbooks, behind the `excel` feature.
+//!
+//! An .xlsx file is a zip archive of XML parts: the workbook lists the
+//! sheets, each sheet holds its cells, text is kept once in a table of shared
+//! strings that cells refer to, and styles say which numbers are dates.
+//! Formulas are read as the values Excel calculated and saved with them.
+mod xml;
+mod zip;
+
+use self::xml::{Event, Parser};
+use self::zip::Archive;
+use crate::error::DaxError;
+use crate::types::Value;
+use crate::{dates, Table};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Read a sheet of an Excel workbook into a table, the first sheet if none
+/// is named.
+///
+/// The sheet's first row holds the column names, and the table runs from the
+/// first column with anything in it to the last. Empty cells are Null, a
+/// merged range is its first cell's value throughout, and a formula is the
+/// value it had when the workbook was saved. Numbers formatted as dates or
+/// times read as dates, whole numbers as integers and text as text. A sheet
+/// the workbook doesn't have is an error listing the ones it does.
+pub fn read_xlsx(path: &Path, sheet: Option<&str>) -> Result<Table, DaxError> {
+    let bytes = std::fs::read(path)?;
+    let cells = read_sheet(bytes, sheet)
+        .map_err(|e| DaxError::ParseError(format!("Excel workbook {}: {}", path.display(), e)))?;
+    to_table(cells)
+}
+
+// A sheet's cells that have values, by row and column from 0
+type Cells = HashMap<(u32, u32), Value>;
+
+fn read_sheet(bytes: Vec<u8>, sheet: Option<&str>) -> Result<Cells, String> {
+    let archive = Archive::new(bytes)?;
+    let workbook = part(&archive, "xl/workbook.xml")?.ok_or("it has no workbook part")?;
+    let mut sheets: Vec<(String, String)> = Vec::new();
+    let mut date1904 = false;
+    for event in Parser::new(&workbook) {
+        let event = event?;
+        match event {
+            Event::Start { name: "sheet", .. } => sheets.push((
+                event.attribute("name").unwrap_or_default().to_string(),
+                event.attribute("id").unwrap_or_default().to_string(),
+            )),
+            Event::Start {
+                name: "workbookPr", ..
+            } => date1904 = matches!(event.attribute("date1904"), Some("1" | "true")),
+            _ => {}
+        }
+    }
+    let (name, id) = match sheet {
+        Some(wanted) => sheets
+            .iter()
+            .find(|(name, _)| name == wanted)
+            .ok_or_else(|| {
+                let names: Vec<&str> = sheets.iter().map(|(name, _)| name.as_str()).collect();
+                format!(
+                    "it has no sheet {}; its sheets are {}",
+                    wanted,
+                    names.join(", ")
+                )
+            })?,
+        None => sheets.first().ok_or("it has no sheets")?,
+    };
+
+    let relationships =
+        part(&archive, "xl/_rels/workbook.xml.rels")?.ok_or("it has no workbook relationships")?;
+    let mut target = None;
+    for event in Parser::new(&relationships) {
+        let event = event?;
+        if event.attribute("Id") == Some(id.as_str()) {
+            target = event.attribute("Target").map(str::to_string);
+        }
+    }
+    let target = target.ok_or_else(|| format!("sheet {} has no part", name))?;
+    let path = match target.strip_prefix('/') {
+        Some(path) => path.to_string(),
+        None => format!("xl/{}", target),
+    };
+    let xml = part(&archive, &path)?.ok_or_else(|| format!("sheet {} is missing", name))?;
+
+    let strings = shared_strings(&archive)?;
+    let date_styles = date_styles(&archive)?;
+    let epoch_offset = if date1904 { 1462.0 } else { 0.0 };
+    read_cells(&xml, |cell| {
+        cell.value(&strings, &date_styles, epoch_offset)
+    })
+}
+
+// A part of the archive as text, or None if there isn't one
+fn part(archive: &Archive, name: &str) -> Result<Option<String>, String> {
+    archive
+        .file(name)?
+        .map(|bytes| String::from_utf8(bytes).map_err(|_| format!("{} isn't UTF-8", name)))
+        .transpose()
+}
+
+// The text of each shared string, leaving out phonetic guides
+fn shared_strings(archive: &Archive) -> Result<Vec<String>, String> {
+    let Some(xml) = part(archive, "xl/sharedStrings.xml")? else {
+        return Ok(Vec::new());
+    };
+    let mut strings = Vec::new();
+    let mut current = String::new();
+    let (mut in_text, mut in_phonetic) = (false, false);
+    for event in Parser::new(&xml) {
+        match event? {
+            Event::Start {
+                name: "si",
+                empty: true,
+                ..
+            } => strings.push(String::new()),
+            Event::Start { name: "si", .. } => current.clear(),
+            Event::End("si") => strings.push(std::mem::take(&mut current)),
+            Event::Start {
+                name: "t", empty, ..
+            } => in_text = !empty,
+            Event::End("t") => in_text = false,
+            Event::Start {
+                name: "rPh", empty, ..
+            } => in_phonetic = !empty,
+            Event::End("rPh") => in_phonetic = false,
+            Event::Text(text) if in_text && !in_phonetic => current.push_str(&text),
+            _ => {}
+        }
+    }
+    Ok(strings)
+}
+
+// Whether each cell style shows numbers as dates or times
+fn date_styles(archive: &Archive) -> Result<Vec<bool>, String> {
+    let Some(xml) = part(archive, "xl/styles.xml")? else {
+        return Ok(Vec::new());
+    };
+    let mut formats: HashMap<String, bool> = HashMap::new();
+    let mut styles = Vec::new();
+    let mut in_cell_styles = false;
+    for event in Parser::new(&xml) {
+        let event = event?;
+        match event {
+            Event::Start { name: "numFmt", .. } => {
+                if let (Some(id), Some(code)) =
+                    (event.attribute("numFmtId"), event.attribute("formatCode"))
+                {
+                    formats.insert(id.to_string(), is_date_format(code));
+                }
+            }
+            Event::Start {
+                name: "cellXfs",
+                empty,
+                ..
+            } => in_cell_styles = !empty,
+            Event::End("cellXfs") => in_cell_styles = false,
+            Event::Start { name: "xf", .. } if in_cell_styles => {
+                let id = event.attribute("numFmtId").unwrap_or("0");
+                let builtin = id
+                    .parse::<u32>()
+                    .is_ok_and(|id| matches!(id, 14..=22 | 45..=47));
+                styles.push(formats.get(id).copied().unwrap_or(builtin));
+            }
+            _ => {}
+        }
+    }
+    Ok(styles)
+}
+
+// Whether a number format shows a date or time, which it does if it has a
+// day, month, year, hour or second outside its quoted text and brackets
+fn is_date_format(code: &str) -> bool {
+    let mut quoted = false;
+    let mut bracketed = false;
+    let mut escaped = false;
+    for c in code.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            // The character after these is shown as it is, or used as padding
+            '\\' | '_' | '*' if !quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '[' if !quoted => bracketed = true,
+            ']' if !quoted => bracketed = false,
+            'd' | 'm' | 'y' | 'h' | 's' | 'D' | 'M' | 'Y' | 'H' | 'S' if !quoted && !bracketed => {
+                return true
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+// A cell as it's read from a sheet, before its value is worked out
+#[derive(Default)]
+struct Cell {
+    kind: Option<String>,
+    style: usize,
+    value: String,
+    inline: String,
+}
+
+impl Cell {
+    fn value(
+        &self,
+        strings: &[String],
+        date_styles: &[bool],
+        epoch_offset: f64,
+    ) -> Result<Value, String> {
+        let text = self.value.as_str();
+        if text.is_empty() && self.kind.as_deref() != Some("inlineStr") {
+            return Ok(Value::Null);
+        }
+        Ok(match self.kind.as_deref() {
+            Some("s") => text
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| strings.get(i))
+                .map(|s| Value::from(s.as_str()))
+                .ok_or_else(|| {
+                    format!("a cell refers to shared string {}, which isn't there", text)
+                })?,
+            Some("inlineStr") => Value::from(self.inline.as_str()),
+            Some("b") => Value::Boolean(text.trim() == "1"),
+            Some("d") => dates::parse_iso(text.trim())
+                .map(Value::DateTime)
+                .unwrap_or_else(|| Value::from(text)),
+            // Formula results that are text, and errors such as #N/A
+            Some("str" | "e") => Value::from(text),
+            _ => {
+                let number: f64 = text
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("a cell holds {:?}, which isn't a number", text))?;
+                if date_styles.get(self.style).copied().unwrap_or(false) {
+                    dates::from_serial(number + epoch_offset)
+                        .map(Value::DateTime)
+                        .ok_or_else(|| {
+                            format!("a cell holds date {}, which is out of range", number)
+                        })?
+                } else {
+                    match text.trim().parse::<i64>() {
+                        Ok(i) => Value::Integer(i),
+                        Err(_) => Value::Number(number),
+                    }
+                }
+            }
+        })
+    }
+}
+
+// The cells of a sheet with values, with each merged range filled in from
+// its first cell
+fn read_cells(xml: &str, value: impl Fn(&Cell) -> Result<Value, String>) -> Result<Cells, String> {
+    let mut cells = Cells::new();
+    let mut merges = Vec::new();
+    let mut position = (0u32, 0u32);
+    let mut next_row = 0;
+    let mut cell: Option<Cell> = None;
+    let (mut in_value, mut in_inline, mut in_text) = (false, false, false);
+    for event in Parser::new(xml) {
+        let event = event?;
+        match event {
+            Event::Start { name: "row", .. } => {
+                let row = match event.attribute("r") {
+                    Some(r) => {
+                        r.parse::<u32>()
+                            .ok()
+                            .filter(|row| (1..=MAX_ROWS).contains(row))
+                            .ok_or_else(|| format!("row {} isn't a row of a sheet", r))?
+                            - 1
+                    }
+                    None => next_row,
+                };
+                position = (row, 0);
+                next_row = row + 1;
+            }
+            Event::Start {
+                name: "c", empty, ..
+            } => {
+                if let Some(reference) = event.attribute("r") {
+                    position = parse_reference(reference)?;
+                }
+                let new = Cell {
+                    kind: event.attribute("t").map(str::to_string),
+                    style: event
+                        .attribute("s")
+                        .and_then(|s| s.parse().ok())
+                        .unwrap_or(0),
+                    ..Cell::default()
+                };
+                if empty {
+                    position.1 = position.1.saturating_add(1);
+                } else {
+                    cell = Some(new);
+                }
+            }
+            Event::End("c") => {
+                if let Some(cell) = cell.take() {
+                    let value =
+                        value(&cell).map_err(|e| format!("{}: {}", reference(position), e))?;
+                    if !value.is_null() {
+                        cells.insert(position, value);
+                    }
+                }
+                position.1 = position.1.saturating_add(1);
+            }
+            Event::Start {
+                name: "v", empty, ..
+            } => in_value = !empty,
+            Event::End("v") => in_value = false,
+            Event::Start {
+                name: "is", empty, ..
+            } => in_inline = !empty,
+            Event::End("is") => in_inline = false,
+            Event::Start {
+                name: "t", empty, ..
+            } => in_text = !empty,
+            Event::End("t") => in_text = false,
+            Event::Text(text) => {
+                if let Some(cell) = &mut cell {
+                    if in_value {
+                        cell.value.push_str(&text);
+                    } else if in_inline && in_text {
+                        cell.inline.push_str(&text);
+                    }
+                }
+            }
+            Event::Start {
+                name: "mergeCell", ..
+            } => {
+                if let Some((first, last)) = event.attribute("ref").and_then(|r| r.split_once(':'))
+                {
+                    merges.push((parse_reference(first)?, parse_reference(last)?));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Merged ranges only go as far as the cells with values, so that a whole
+    // merged column doesn't fill a million rows
+    let last_row = cells.keys().map(|(row, _)| *row).max().unwrap_or(0);
+    let last_column = cells.keys().map(|(_, column)| *column).max().unwrap_or(0);
+    for (first, last) in merges {
+        let Some(value) = cells.get(&first).cloned() else {
+            continue;
+        };
+        for row in first.0..=last.0.min(last_row) {
+            for column in first.1..=last.1.min(last_column) {
+                cells.entry((row, column)).or_insert_with(|| value.clone());
+            }
+        }
+    }
+    Ok(cells)
+}
+
+// The last row and column a sheet can have, 1048576 and XFD
+const MAX_ROWS: u32 = 1 << 20;
+const MAX_COLUMNS: u32 = 1 << 14;
+
+// The row and column of a reference such as `B12`, from 0, which must be on
+// a sheet
+fn parse_reference(reference: &str) -> Result<(u32, u32), String> {
+    let invalid = || format!("{} isn't a cell reference", reference);
+    let split = reference
+        .find(|c: char| c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let (letters, digits) = reference.split_at(split);
+    if letters.is_empty() || !letters.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(invalid());
+    }
+    let column = letters.chars().try_fold(0u32, |column, c| {
+        column
+            .checked_mul(26)?
+            .checked_add(c.to_ascii_uppercase() as u32 - 'A' as u32 + 1)
+    });
+    let row = digits
+        .parse::<u32>()
+        .ok()
+        .filter(|row| (1..=MAX_ROWS).contains(row));
+    let column = column.filter(|column| (1..=MAX_COLUMNS).contains(column));
+    match (row, column) {
+        (Some(row), Some(column)) => Ok((row - 1, column - 1)),
+        _ => Err(invalid()),
+    }
+}
+
+// A cell reference such as `B12`, from the row and column from 0
+fn reference((row, column): (u32, u32)) -> String {
+    let mut letters = Vec::new();
+    let mut n = column + 1;
+    while n > 0 {
+        letters.push((b'A' + ((n - 1) % 26) as u8) as char);
+        n = (n - 1) / 26;
+    }
+    letters.iter().rev().collect::<String>() + &(row + 1).to_string()
+}
+
+// The first row's values as column names, and the rows below it as rows
+fn to_table(mut cells: Cells) -> Result<Table, DaxError> {
+    let mut table = Table::new();
+    let Some(first_row) = cells.keys().map(|(row, _)| *row).min() else {
+        return Ok(table);
+    };
+    let last_row = cells.keys().map(|(row, _)| *row).max().unwrap_or(first_row);
+    let first_column = cells.keys().map(|(_, column)| *column).min().unwrap_or(0);
+    let last_column = cells.keys().map(|(_, column)| *column).max().unwrap_or(0);
+    for column in first_column..=last_column {
+        let name = match cells.remove(&(first_row, column)) {
+            Some(Value::Text(name)) => name,
+            Some(value) => value.to_string(),
+            None => format!("Column{}", column - first_column + 1),
+        };
+        let values = (first_row + 1..=last_row)
+            .map(|row| cells.remove(&(row, column)).unwrap_or(Value::Null))
+            .collect();
+        table.add_column(name, values)?;
+    }
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_references() {
+        assert_eq!(parse_reference("A1").unwrap(), (0, 0));
+        assert_eq!(parse_reference("AB12").unwrap(), (11, 27));
+        assert_eq!(reference((11, 27)), "AB12");
+        assert_eq!(reference((0, 25)), "Z1");
+        assert!(parse_reference("12").is_err());
+        assert!(parse_reference("A0").is_err());
+        assert_eq!(parse_reference("XFD1048576").unwrap(), (1_048_575, 16_383));
+        assert!(parse_reference("XFE1").is_err());
+        assert!(parse_reference("A1048577").is_err());
+        assert!(parse_reference("ZZZZZZZZZZZZZZ1").is_err());
+    }
+
+    #[test]
+    fn test_date_formats() {
+        assert!(is_date_format("yyyy-mm-dd"));
+        assert!(is_date_format("[$-409]d-mmm-yy;@"));
+        assert!(is_date_format("h:mm AM/PM"));
+        assert!(!is_date_format("#,##0.00"));
+        assert!(!is_date_format("0.0\"days\""));
+        assert!(!is_date_format("[Red]0.00"));
+        assert!(!is_date_format("General"));
+        assert!(!is_date_format("_(* #,##0_);_(* \"-\"??_)"));
+    }
+}
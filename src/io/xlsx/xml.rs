@@ -0,0 +1,236 @@
+// io/xlsx/xml.rs
+
+//! A small XML reader, enough for the parts of a workbook: tags with their
+//! attributes and the text between them. Namespace prefixes are dropped from
+//! names, and declarations, comments and doctypes are skipped.
+
+#[derive(Debug, PartialEq)]
+pub(super) enum Event<'a> {
+    Start {
+        name: &'a str,
+        attributes: Vec<(&'a str, String)>,
+        // Whether the tag closes itself, as in `<c r="A1"/>`
+        empty: bool,
+    },
+    End(&'a str),
+    Text(String),
+}
+
+impl Event<'_> {
+    /// The value of an attribute of a start tag
+    pub(super) fn attribute(&self, name: &str) -> Option<&str> {
+        match self {
+            Event::Start { attributes, .. } => attributes
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.as_str()),
+            _ => None,
+        }
+    }
+}
+
+pub(super) struct Parser<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    pub(super) fn new(text: &'a str) -> Self {
+        Parser { text, position: 0 }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.position..]
+    }
+
+    // Move past the next `end`, or fail if there isn't one
+    fn skip_past(&mut self, end: &str) -> Result<&'a str, String> {
+        let rest = self.rest();
+        let at = rest
+            .find(end)
+            .ok_or_else(|| format!("the XML ends before {}", end))?;
+        self.position += at + end.len();
+        Ok(&rest[..at])
+    }
+
+    fn tag(&mut self) -> Result<Event<'a>, String> {
+        // The tag ends at the first `>` outside an attribute's quotes
+        let rest = self.rest();
+        let mut quote = None;
+        let end = rest
+            .char_indices()
+            .find(|&(_, c)| match quote {
+                Some(q) if c == q => {
+                    quote = None;
+                    false
+                }
+                Some(_) => false,
+                None if c == '"' || c == '\'' => {
+                    quote = Some(c);
+                    false
+                }
+                None => c == '>',
+            })
+            .map(|(at, _)| at)
+            .ok_or("the XML ends inside a tag")?;
+        self.position += end + 1;
+        let tag = &rest[1..end];
+        if let Some(name) = tag.strip_prefix('/') {
+            return Ok(Event::End(local(name.trim())));
+        }
+        let (tag, empty) = match tag.strip_suffix('/') {
+            Some(tag) => (tag, true),
+            None => (tag, false),
+        };
+        let name_end = tag.find(|c: char| c.is_whitespace()).unwrap_or(tag.len());
+        let name = local(&tag[..name_end]);
+        let mut attributes = Vec::new();
+        let mut rest = tag[name_end..].trim_start();
+        while !rest.is_empty() {
+            let equals = rest
+                .find('=')
+                .ok_or_else(|| format!("an attribute of {} has no value", name))?;
+            let key = local(rest[..equals].trim());
+            let value = rest[equals + 1..].trim_start();
+            let quote = value
+                .chars()
+                .next()
+                .filter(|c| matches!(c, '"' | '\''))
+                .ok_or_else(|| format!("attribute {} of {} isn't quoted", key, name))?;
+            let close = value[1..]
+                .find(quote)
+                .ok_or_else(|| format!("attribute {} of {} isn't closed", key, name))?;
+            attributes.push((key, unescape(&value[1..close + 1])));
+            rest = value[close + 2..].trim_start();
+        }
+        Ok(Event::Start {
+            name,
+            attributes,
+            empty,
+        })
+    }
+}
+
+impl<'a> Iterator for Parser<'a> {
+    type Item = Result<Event<'a>, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let rest = self.rest();
+            if rest.is_empty() {
+                return None;
+            }
+            if !rest.starts_with('<') {
+                let end = rest.find('<').unwrap_or(rest.len());
+                self.position += end;
+                return Some(Ok(Event::Text(unescape(&rest[..end]))));
+            }
+            let skipped = if rest.starts_with("<?") {
+                self.skip_past("?>")
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->")
+            } else if rest.starts_with("<![CDATA[") {
+                self.position += "<![CDATA[".len();
+                return Some(
+                    self.skip_past("]]>")
+                        .map(|text| Event::Text(text.to_string())),
+                );
+            } else if rest.starts_with("<!") {
+                self.skip_past(">")
+            } else {
+                return Some(self.tag());
+            };
+            if let Err(e) = skipped {
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+// A name without its namespace prefix
+fn local(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+// Text with its entities and character references replaced
+fn unescape(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find('&') {
+        out.push_str(&rest[..at]);
+        rest = &rest[at..];
+        let replaced = rest.find(';').and_then(|end| {
+            let c = match &rest[1..end] {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                reference => {
+                    let code = match reference.strip_prefix("#x") {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => reference.strip_prefix('#')?.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, end))
+        });
+        match replaced {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events() {
+        let xml = r#"<?xml version="1.0"?><!-- a --><x:row r="1"><c t='s' r="A1"/><t>a &amp; b&#x21;</t></x:row>"#;
+        let events: Vec<Event> = Parser::new(xml).collect::<Result<_, _>>().unwrap();
+        assert_eq!(
+            events,
+            [
+                Event::Start {
+                    name: "row",
+                    attributes: vec![("r", "1".to_string())],
+                    empty: false
+                },
+                Event::Start {
+                    name: "c",
+                    attributes: vec![("t", "s".to_string()), ("r", "A1".to_string())],
+                    empty: true
+                },
+                Event::Start {
+                    name: "t",
+                    attributes: Vec::new(),
+                    empty: false
+                },
+                Event::Text("a & b!".to_string()),
+                Event::End("t"),
+                Event::End("row"),
+            ]
+        );
+        assert_eq!(events[1].attribute("t"), Some("s"));
+        assert!(Parser::new("<row r=1>").next().unwrap().is_err());
+        let format = Parser::new(r#"<numFmt formatCode="[>=100]0"/>"#)
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(format.attribute("formatCode"), Some("[>=100]0"));
+    }
+}
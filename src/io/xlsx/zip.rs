@@ -0,0 +1,230 @@
+// io/xlsx/zip.rs
+
+//! Reading the files of a zip archive, stored or deflated, which is what an
+//! .xlsx workbook is. Zip64 archives, for files over 4 GB, aren't supported.
+use super::super::inflate::inflate;
+use std::collections::HashMap;
+
+pub(super) struct Archive {
+    bytes: Vec<u8>,
+    // Each file by its name in lowercase, since workbooks don't agree on case
+    entries: HashMap<String, Entry>,
+}
+
+struct Entry {
+    method: u16,
+    compressed_size: usize,
+    size: usize,
+    local_header: usize,
+}
+
+const END_OF_DIRECTORY: u32 = 0x0605_4b50;
+const DIRECTORY_ENTRY: u32 = 0x0201_4b50;
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+
+impl Archive {
+    pub(super) fn new(bytes: Vec<u8>) -> Result<Self, String> {
+        // The end of the central directory is the last thing in the file,
+        // before a comment of up to 64 KB
+        let earliest = bytes.len().saturating_sub(22 + 0xffff);
+        let end = (earliest..bytes.len().saturating_sub(21))
+            .rev()
+            .find(|&at| u32_at(&bytes, at) == Some(END_OF_DIRECTORY))
+            .ok_or("it isn't a zip archive")?;
+        let count = u16_at(&bytes, end + 10).ok_or("it isn't a zip archive")?;
+        let mut at = u32_at(&bytes, end + 16).ok_or("it isn't a zip archive")? as usize;
+        if at == 0xffff_ffff {
+            return Err("it's a Zip64 archive, which isn't supported".to_string());
+        }
+
+        let mut entries = HashMap::new();
+        for _ in 0..count {
+            if u32_at(&bytes, at) != Some(DIRECTORY_ENTRY) {
+                return Err("its directory is damaged".to_string());
+            }
+            let field = |offset| u16_at(&bytes, at + offset).map(usize::from);
+            let (Some(method), Some(name_len), Some(extra_len), Some(comment_len)) =
+                (field(10), field(28), field(30), field(32))
+            else {
+                return Err("its directory is damaged".to_string());
+            };
+            let compressed_size = u32_at(&bytes, at + 20).ok_or("its directory is damaged")?;
+            let size = u32_at(&bytes, at + 24).ok_or("its directory is damaged")?;
+            let local_header = u32_at(&bytes, at + 42).ok_or("its directory is damaged")?;
+            let name = bytes
+                .get(at + 46..at + 46 + name_len)
+                .ok_or("its directory is damaged")?;
+            entries.insert(
+                String::from_utf8_lossy(name).to_lowercase(),
+                Entry {
+                    method: method as u16,
+                    compressed_size: compressed_size as usize,
+                    size: size as usize,
+                    local_header: local_header as usize,
+                },
+            );
+            at += 46 + name_len + extra_len + comment_len;
+        }
+        Ok(Archive { bytes, entries })
+    }
+
+    /// The contents of a file, or None if the archive doesn't have it
+    pub(super) fn file(&self, name: &str) -> Result<Option<Vec<u8>>, String> {
+        let Some(entry) = self.entries.get(&name.to_lowercase()) else {
+            return Ok(None);
+        };
+        let at = entry.local_header;
+        if u32_at(&self.bytes, at) != Some(LOCAL_HEADER) {
+            return Err(format!("{} is damaged", name));
+        }
+        let name_len =
+            u16_at(&self.bytes, at + 26).ok_or_else(|| format!("{} is damaged", name))?;
+        let extra_len =
+            u16_at(&self.bytes, at + 28).ok_or_else(|| format!("{} is damaged", name))?;
+        let start = at + 30 + name_len as usize + extra_len as usize;
+        let data = self
+            .bytes
+            .get(start..start + entry.compressed_size)
+            .ok_or_else(|| format!("{} is cut short", name))?;
+        match entry.method {
+            0 => Ok(Some(data.to_vec())),
+            8 => inflate(data, entry.size)
+                .map(Some)
+                .map_err(|e| format!("{} can't be decompressed: {}", name, e)),
+            method => Err(format!(
+                "{} is compressed with method {}, which isn't supported",
+                name, method
+            )),
+        }
+    }
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> Option<u16> {
+    let bytes = bytes.get(at..at + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> Option<u32> {
+    let bytes = bytes.get(at..at + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::deflate::Deflate;
+    use super::*;
+    use std::io::Write;
+
+    // An archive of the files given, each stored (method 0) or deflated
+    // (method 8)
+    fn archive(files: &[(&str, u16, &[u8])]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut directory = Vec::new();
+        for &(name, method, data) in files {
+            let compressed = match method {
+                8 => {
+                    let mut deflate = Deflate::new(Vec::new());
+                    deflate.write_all(data).unwrap();
+                    deflate.finish().unwrap()
+                }
+                _ => data.to_vec(),
+            };
+            let offset = bytes.len() as u32;
+            bytes.extend_from_slice(&LOCAL_HEADER.to_le_bytes());
+            bytes.extend_from_slice(&[0; 22]);
+            bytes.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            bytes.extend_from_slice(&[0; 2]);
+            bytes.extend_from_slice(name.as_bytes());
+            bytes.extend_from_slice(&compressed);
+
+            directory.extend_from_slice(&DIRECTORY_ENTRY.to_le_bytes());
+            directory.extend_from_slice(&[0; 6]);
+            directory.extend_from_slice(&method.to_le_bytes());
+            directory.extend_from_slice(&[0; 8]);
+            directory.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+            directory.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            directory.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            directory.extend_from_slice(&[0; 12]);
+            directory.extend_from_slice(&offset.to_le_bytes());
+            directory.extend_from_slice(name.as_bytes());
+        }
+        let start = bytes.len() as u32;
+        bytes.extend_from_slice(&directory);
+        bytes.extend_from_slice(&END_OF_DIRECTORY.to_le_bytes());
+        bytes.extend_from_slice(&[0; 6]);
+        bytes.extend_from_slice(&(files.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&start.to_le_bytes());
+        bytes.extend_from_slice(&[0; 2]);
+        bytes
+    }
+
+    fn read(bytes: Vec<u8>, name: &str) -> Result<Option<Vec<u8>>, String> {
+        Archive::new(bytes)?.file(name)
+    }
+
+    #[test]
+    fn test_read_files() {
+        let text = b"<row><c>1</c></row>".repeat(20);
+        let bytes = archive(&[("a.xml", 0, b"stored"), ("B.xml", 8, &text)]);
+        assert_eq!(read(bytes.clone(), "A.XML").unwrap().unwrap(), b"stored");
+        assert_eq!(read(bytes.clone(), "b.xml").unwrap().unwrap(), text);
+        assert_eq!(read(bytes, "c.xml").unwrap(), None);
+    }
+
+    #[test]
+    fn test_damaged_archives() {
+        let text = b"<row><c>1</c></row>".repeat(20);
+        let bytes = archive(&[("a.xml", 0, b"stored"), ("b.xml", 8, &text)]);
+        for len in 0..bytes.len() {
+            let cut = bytes[..len].to_vec();
+            let files = Archive::new(cut)
+                .and_then(|archive| Ok((archive.file("a.xml")?, archive.file("b.xml")?)));
+            assert!(
+                !matches!(files, Ok((Some(_), Some(_)))),
+                "cut to {} bytes",
+                len
+            );
+        }
+        for i in 0..bytes.len() {
+            for change in [0x01, 0x80, 0xff] {
+                let mut damaged = bytes.clone();
+                damaged[i] ^= change;
+                if let Ok(archive) = Archive::new(damaged) {
+                    let _ = archive.file("a.xml");
+                    let _ = archive.file("b.xml");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_oversized_lengths() {
+        let text = b"<row><c>1</c></row>".repeat(20);
+        let bytes = archive(&[("b.xml", 8, &text)]);
+        let directory = bytes.len() - 22 - 46 - 5;
+        let patch = |offset: usize, value: u32| {
+            let mut bytes = bytes.clone();
+            bytes[directory + offset..directory + offset + 4].copy_from_slice(&value.to_le_bytes());
+            read(bytes, "b.xml")
+        };
+        // A file that decompresses to more than the directory says stops
+        // there, rather than filling memory
+        assert_eq!(
+            patch(24, 10).unwrap_err(),
+            "b.xml can't be decompressed: it decompresses to more than 10 bytes"
+        );
+        assert!(patch(24, u32::MAX).is_err());
+        assert_eq!(patch(20, u32::MAX).unwrap_err(), "b.xml is cut short");
+        assert_eq!(patch(42, u32::MAX).unwrap_err(), "b.xml is damaged");
+
+        // A directory said to hold more entries than it does
+        let mut count = bytes.clone();
+        let end = count.len() - 22;
+        count[end + 10..end + 12].copy_from_slice(&u16::MAX.to_le_bytes());
+        assert_eq!(
+            Archive::new(count).err().unwrap(),
+            "its directory is damaged"
+        );
+    }
+}
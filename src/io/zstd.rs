@@ -0,0 +1,883 @@
+// io/zstd.rs
+
+//! Zstandard files (RFC 8878), behind the `zstd` feature, decompressed as
+//! they're read and compressed as they're written, so no more than a block
+//! and the window copies can reach back into is held in memory.
+//!
+//! Reading takes frames as the `zstd` tool writes them, with every kind of
+//! block, literals and sequence coding, and skips skippable frames. A frame
+//! that needs a dictionary, or a window over 128 MB, is an error.
+//!
+//! Writing finds repeats through chains of earlier places with the same four
+//! bytes, as DEFLATE does, and codes them as sequences with the predefined
+//! FSE tables, leaving the literals as they are. A block that doesn't get
+//! any smaller that way is stored as it is.
+mod fse;
+mod huffman;
+mod xxhash;
+
+use self::fse::{read_table, Backward, BitWriter, Table};
+use self::huffman::Huffman;
+use self::xxhash::Xxh64;
+use std::io::{self, BufRead, Read, Write};
+
+const MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+// Skippable frames have 16 magic numbers, which differ in their low bits
+const SKIPPABLE: u32 = 0x184d_2a50;
+// The most a block holds, before or after compression
+const MAX_BLOCK: usize = 128 * 1024;
+// The largest window read, so a frame can't ask for memory without end
+const MAX_WINDOW: u64 = 1 << 27;
+
+// Baselines and extra bits of the literal length and match length codes
+const LITERAL_BASE: [u32; 36] = [
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 18, 20, 22, 24, 28, 32, 40, 48, 64,
+    128, 256, 512, 1024, 2048, 4096, 8192, 16384, 32768, 65536,
+];
+const LITERAL_EXTRA: [u8; 36] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 3, 3, 4, 6, 7, 8, 9, 10, 11,
+    12, 13, 14, 15, 16,
+];
+const MATCH_BASE: [u32; 53] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27,
+    28, 29, 30, 31, 32, 33, 34, 35, 37, 39, 41, 43, 47, 51, 59, 67, 83, 99, 131, 259, 515, 1027,
+    2051, 4099, 8195, 16387, 32771, 65539,
+];
+const MATCH_EXTRA: [u8; 53] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    1, 1, 1, 1, 2, 2, 3, 3, 4, 4, 5, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16,
+];
+
+// The tables of a frame's sequences, in the order a block describes them
+const LITERAL_LENGTHS: usize = 0;
+const OFFSETS: usize = 1;
+const MATCH_LENGTHS: usize = 2;
+
+/// Decompresses a zstd file as it's read. A file of several frames one after
+/// another, as `cat` makes of two, reads as their contents joined.
+pub(super) struct ZstdReader<R> {
+    reader: R,
+    // The output copies can still refer back to, then the output not yet read
+    out: Vec<u8>,
+    // Where in `out` the output not yet read starts
+    read: usize,
+    // The frame being read, or None between frames
+    frame: Option<Frame>,
+    done: bool,
+}
+
+// What a frame's blocks share
+struct Frame {
+    window: usize,
+    // The content size the header gives, if it gives one
+    size: Option<u64>,
+    checksum: Option<Xxh64>,
+    // How much the frame has decompressed to so far
+    produced: u64,
+    huffman: Option<Huffman>,
+    tables: [Option<Table>; 3],
+    repeats: [usize; 3],
+}
+
+impl<R: BufRead> ZstdReader<R> {
+    pub(super) fn new(mut reader: R) -> io::Result<Self> {
+        let frame = next_frame(&mut reader)?.ok_or_else(|| invalid("the file isn't zstd"))?;
+        Ok(ZstdReader {
+            reader,
+            out: Vec::new(),
+            read: 0,
+            frame: Some(frame),
+            done: false,
+        })
+    }
+
+    // Decode the next block, or start the next frame
+    fn decode(&mut self) -> io::Result<()> {
+        let Some(frame) = &mut self.frame else {
+            match next_frame(&mut self.reader)? {
+                Some(frame) => self.frame = Some(frame),
+                None => self.done = true,
+            }
+            return Ok(());
+        };
+        let header = read_bytes(&mut self.reader, 3)?;
+        let header = u32::from_le_bytes([header[0], header[1], header[2], 0]);
+        let last = header & 1 == 1;
+        let size = (header >> 3) as usize;
+        if size > MAX_BLOCK.min(frame.window.max(1)) {
+            return Err(invalid("a block is larger than the most a block can be"));
+        }
+        let start = self.out.len();
+        match header >> 1 & 3 {
+            0 => self.out.extend(read_bytes(&mut self.reader, size)?),
+            1 => {
+                let byte = read_bytes(&mut self.reader, 1)?[0];
+                self.out.resize(start + size, byte);
+            }
+            2 => {
+                let data = read_bytes(&mut self.reader, size)?;
+                decompress_block(frame, &data, &mut self.out).map_err(|e| invalid(&e))?;
+            }
+            _ => return Err(invalid("a block has the reserved type")),
+        }
+        let block = &self.out[start..];
+        if block.len() > MAX_BLOCK {
+            return Err(invalid(
+                "a block decompresses to more than a block can hold",
+            ));
+        }
+        frame.produced += block.len() as u64;
+        if let Some(checksum) = &mut frame.checksum {
+            checksum.update(block);
+        }
+
+        if last {
+            if frame.size.is_some_and(|size| size != frame.produced) {
+                return Err(invalid("the data doesn't match the size its frame gives"));
+            }
+            if let Some(checksum) = &frame.checksum {
+                let expected = read_bytes(&mut self.reader, 4)?;
+                if checksum.finish() as u32 != u32::from_le_bytes(expected.try_into().unwrap()) {
+                    return Err(invalid("the data doesn't match its checksum"));
+                }
+            }
+            self.frame = None;
+        }
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Read for ZstdReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.out.len() - self.read < buf.len() && !self.done {
+            self.decode()?;
+        }
+        let n = buf.len().min(self.out.len() - self.read);
+        buf[..n].copy_from_slice(&self.out[self.read..self.read + n]);
+        self.read += n;
+        // Let go of the output that's been read and is out of reach of copies
+        let window = self.frame.as_ref().map_or(0, |frame| frame.window);
+        if self.read > 2 * window + MAX_BLOCK {
+            let drop = self.read - window;
+            self.out.drain(..drop);
+            self.read -= drop;
+        }
+        Ok(n)
+    }
+}
+
+// Read the header of the next frame, past any skippable frames, or None at
+// the end of the file
+fn next_frame(reader: &mut impl BufRead) -> io::Result<Option<Frame>> {
+    loop {
+        if reader.fill_buf()?.is_empty() {
+            return Ok(None);
+        }
+        let magic = read_bytes(reader, 4).map_err(|_| invalid("the file isn't zstd"))?;
+        if magic == MAGIC {
+            return read_frame_header(reader).map(Some);
+        }
+        let magic = u32::from_le_bytes(magic.try_into().unwrap());
+        if magic & !0xf != SKIPPABLE {
+            return Err(invalid("the file isn't zstd"));
+        }
+        let size = read_bytes(reader, 4)?;
+        let size = u32::from_le_bytes(size.try_into().unwrap()) as u64;
+        if io::copy(&mut reader.take(size), &mut io::sink())? != size {
+            return Err(invalid("the file ends in a skippable frame"));
+        }
+    }
+}
+
+fn read_frame_header(reader: &mut impl BufRead) -> io::Result<Frame> {
+    let descriptor = read_bytes(reader, 1)?[0];
+    if descriptor & 0x08 != 0 {
+        return Err(invalid("a frame header has its reserved bit set"));
+    }
+    let single_segment = descriptor & 0x20 != 0;
+    let mut window = 0;
+    if !single_segment {
+        let byte = read_bytes(reader, 1)?[0];
+        let base = 1u64 << (10 + (byte >> 3));
+        window = base + base / 8 * (byte & 7) as u64;
+    }
+    let dictionary = read_bytes(reader, [0, 1, 2, 4][descriptor as usize & 3])?;
+    if dictionary.iter().any(|&byte| byte != 0) {
+        return Err(invalid("the file needs a dictionary to be decompressed"));
+    }
+    let size_bytes = match descriptor >> 6 {
+        0 => single_segment as usize,
+        n => 1 << n,
+    };
+    let size = read_bytes(reader, size_bytes)?;
+    let size = (size_bytes > 0).then(|| {
+        let mut value = [0; 8];
+        value[..size_bytes].copy_from_slice(&size);
+        u64::from_le_bytes(value) + if size_bytes == 2 { 256 } else { 0 }
+    });
+    if single_segment {
+        window = size.unwrap_or(0);
+    }
+    if window > MAX_WINDOW {
+        return Err(invalid("a frame's window is larger than 128 MB"));
+    }
+    Ok(Frame {
+        window: window as usize,
+        size,
+        checksum: (descriptor & 0x04 != 0).then(Xxh64::new),
+        produced: 0,
+        huffman: None,
+        tables: [None, None, None],
+        repeats: [1, 4, 8],
+    })
+}
+
+// Decompress a compressed block onto the end of `out`
+fn decompress_block(frame: &mut Frame, data: &[u8], out: &mut Vec<u8>) -> Result<(), String> {
+    let (literals, used) = read_literals(frame, data)?;
+    let data = &data[used..];
+    let (count, used) = match *data {
+        [0, ..] => (0, 1),
+        [n @ 0..=127, ..] => (n as usize, 1),
+        [n @ 128..=254, m, ..] => (((n as usize - 128) << 8) + m as usize, 2),
+        [255, m, n, ..] => (m as usize + ((n as usize) << 8) + 0x7f00, 3),
+        _ => return Err("a block ends before its sequences".to_string()),
+    };
+    if count == 0 {
+        out.extend_from_slice(&literals);
+        return Ok(());
+    }
+    let data = &data[used..];
+    let sequences = read_sequences(frame, data, count)?;
+
+    // The literals and copies in turn, and the literals left after the last
+    let start = out.len();
+    let mut next = 0;
+    for (literal_length, match_length, offset_value) in sequences {
+        let literals = literals
+            .get(next..next + literal_length)
+            .ok_or("a block's sequences use more literals than it has")?;
+        out.extend_from_slice(literals);
+        next += literal_length;
+
+        let offset = repeat_offset(&mut frame.repeats, offset_value, literal_length)?;
+        let produced = frame.produced as usize + out.len() - start;
+        if offset > produced || offset > out.len() || out.len() - start > MAX_BLOCK {
+            return Err("a block copies from before its frame starts".to_string());
+        }
+        let from = out.len() - offset;
+        if offset >= match_length {
+            out.extend_from_within(from..from + match_length);
+        } else {
+            for i in 0..match_length {
+                out.push(out[from + i]);
+            }
+        }
+    }
+    out.extend_from_slice(&literals[next..]);
+    Ok(())
+}
+
+// A block's literals, and how many bytes of it they took
+fn read_literals(frame: &mut Frame, data: &[u8]) -> Result<(Vec<u8>, usize), String> {
+    let header = |n: usize| {
+        data.get(..n)
+            .map(|bytes| {
+                let mut value = [0; 8];
+                value[..n].copy_from_slice(bytes);
+                u64::from_le_bytes(value)
+            })
+            .ok_or("a block ends in its literals' header")
+    };
+    let first = *data.first().ok_or("a block is empty")?;
+    let format = first >> 2 & 3;
+    if first & 3 < 2 {
+        // Stored, or one byte repeated
+        let (size, used) = match format {
+            0 | 2 => (first as usize >> 3, 1),
+            1 => (header(2)? as usize >> 4, 2),
+            _ => (header(3)? as usize >> 4, 3),
+        };
+        if size > MAX_BLOCK {
+            return Err("a block has more literals than a block can hold".to_string());
+        }
+        return if first & 3 == 0 {
+            let literals = data
+                .get(used..used + size)
+                .ok_or("a block's literals run past its end")?;
+            Ok((literals.to_vec(), used + size))
+        } else {
+            let byte = *data
+                .get(used)
+                .ok_or("a block's literals run past its end")?;
+            Ok((vec![byte; size], used + 1))
+        };
+    }
+
+    // Huffman coded, with a code of their own or the last block's
+    let (streams, used, bits) = match format {
+        0 => (1, 3, 10),
+        1 => (4, 3, 10),
+        2 => (4, 4, 14),
+        _ => (4, 5, 18),
+    };
+    let value = header(used)?;
+    let mask = (1 << bits) - 1;
+    let size = (value >> 4 & mask) as usize;
+    let compressed = (value >> (4 + bits) & mask) as usize;
+    if size > MAX_BLOCK {
+        return Err("a block has more literals than a block can hold".to_string());
+    }
+    let mut body = data
+        .get(used..used + compressed)
+        .ok_or("a block's literals run past its end")?;
+    if first & 3 == 2 {
+        let (code, described) = Huffman::read(body)?;
+        frame.huffman = Some(code);
+        body = &body[described..];
+    }
+    let code = frame
+        .huffman
+        .as_ref()
+        .ok_or("a block reuses a Huffman code before there is one")?;
+
+    let mut literals = Vec::with_capacity(size);
+    if streams == 1 {
+        code.decode(body, size, &mut literals)?;
+    } else {
+        // Three streams' sizes, then the four streams, the last taking what's
+        // left of the literals
+        let jump = body.get(..6).ok_or("a block's literals run past its end")?;
+        let mut ends = [0; 4];
+        let mut end = 6;
+        for i in 0..3 {
+            end += u16::from_le_bytes([jump[2 * i], jump[2 * i + 1]]) as usize;
+            ends[i] = end;
+        }
+        ends[3] = body.len();
+        let segment = size.div_ceil(4);
+        if ends[2] > body.len() || 3 * segment > size {
+            return Err("a block's streams of literals don't fit it".to_string());
+        }
+        let mut start = 6;
+        for (i, &end) in ends.iter().enumerate() {
+            let count = if i < 3 { segment } else { size - 3 * segment };
+            code.decode(&body[start..end], count, &mut literals)?;
+            start = end;
+        }
+    }
+    Ok((literals, used + compressed))
+}
+
+// The sequences of a block: each a literal length, match length and offset
+// value, which is a repeat or an offset plus 3
+fn read_sequences(
+    frame: &mut Frame,
+    data: &[u8],
+    count: usize,
+) -> Result<Vec<(usize, usize, usize)>, String> {
+    let (&modes, mut data) = data
+        .split_first()
+        .ok_or("a block ends before its sequences")?;
+    if modes & 3 != 0 {
+        return Err("a block's sequences have their reserved bits set".to_string());
+    }
+    let kinds = [
+        (LITERAL_LENGTHS, modes >> 6, fse::LITERAL_LENGTHS, 35, 9),
+        (OFFSETS, modes >> 4 & 3, fse::OFFSETS, 31, 8),
+        (MATCH_LENGTHS, modes >> 2 & 3, fse::MATCH_LENGTHS, 52, 9),
+    ];
+    for (kind, mode, (counts, log), max_symbol, max_log) in kinds {
+        let table = match mode {
+            0 => Table::new(counts, log)?,
+            1 => {
+                let (&symbol, rest) = data
+                    .split_first()
+                    .ok_or("a block ends before its sequences")?;
+                if symbol as usize > max_symbol {
+                    return Err("a block repeats a code that doesn't exist".to_string());
+                }
+                data = rest;
+                Table::single(symbol)
+            }
+            2 => {
+                let (table, used) = read_table(data, max_symbol, max_log)?;
+                data = &data[used..];
+                table
+            }
+            _ => frame.tables[kind]
+                .clone()
+                .ok_or("a block reuses a table before there is one")?,
+        };
+        frame.tables[kind] = Some(table);
+    }
+    let [Some(literal_lengths), Some(offsets), Some(match_lengths)] = &frame.tables else {
+        unreachable!("every table was just set");
+    };
+
+    let mut bits = Backward::new(data)?;
+    let mut literal_state = bits.take(literal_lengths.log) as usize;
+    let mut offset_state = bits.take(offsets.log) as usize;
+    let mut match_state = bits.take(match_lengths.log) as usize;
+    let mut sequences = Vec::with_capacity(count.min(MAX_BLOCK));
+    for i in 0..count {
+        let offset_code = offsets.symbol(offset_state) as u32;
+        let match_code = match_lengths.symbol(match_state) as usize;
+        let literal_code = literal_lengths.symbol(literal_state) as usize;
+        let offset_value = (1usize << offset_code) + bits.take(offset_code) as usize;
+        let match_length =
+            MATCH_BASE[match_code] as usize + bits.take(MATCH_EXTRA[match_code] as u32) as usize;
+        let literal_length = LITERAL_BASE[literal_code] as usize
+            + bits.take(LITERAL_EXTRA[literal_code] as u32) as usize;
+        sequences.push((literal_length, match_length, offset_value));
+        if i + 1 < count {
+            literal_state = literal_lengths.next(literal_state, &mut bits);
+            match_state = match_lengths.next(match_state, &mut bits);
+            offset_state = offsets.next(offset_state, &mut bits);
+        }
+        if bits.overflowed() {
+            return Err("a block's sequences run past its end".to_string());
+        }
+    }
+    if bits.left() != 0 {
+        return Err("a block's sequences don't match its size".to_string());
+    }
+    Ok(sequences)
+}
+
+// The offset an offset value stands for, keeping the three most recent
+// offsets up to date. Values 1 to 3 repeat one of those, shifted by one when
+// the sequence has no literals.
+fn repeat_offset(
+    repeats: &mut [usize; 3],
+    value: usize,
+    literal_length: usize,
+) -> Result<usize, String> {
+    if value > 3 {
+        *repeats = [value - 3, repeats[0], repeats[1]];
+        return Ok(value - 3);
+    }
+    let offset = match value - (literal_length > 0) as usize {
+        0 => return Ok(repeats[0]),
+        1 => repeats[1],
+        2 => repeats[2],
+        _ => repeats[0] - 1,
+    };
+    if offset == 0 {
+        return Err("a block copies from an offset of 0".to_string());
+    }
+    if value - (literal_length > 0) as usize == 1 {
+        *repeats = [offset, repeats[0], repeats[2]];
+    } else {
+        *repeats = [offset, repeats[0], repeats[1]];
+    }
+    Ok(offset)
+}
+
+fn read_bytes(reader: &mut impl BufRead, n: usize) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(n);
+    reader.take(n as u64).read_to_end(&mut bytes)?;
+    if bytes.len() < n {
+        return Err(invalid("the file ends in the middle of a frame"));
+    }
+    Ok(bytes)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("zstd: {}", message))
+}
+
+// How far back a copy made when writing can reach, as a log
+const WINDOW_LOG: u32 = 17;
+const WINDOW: usize = 1 << WINDOW_LOG;
+// How many earlier places are tried for each repeat
+const MAX_CHAIN: usize = 32;
+const MIN_MATCH: usize = 4;
+const HASH_BITS: u32 = 16;
+
+/// Compresses what's written to it as a zstd file of one frame, with a
+/// checksum. `finish` writes the end of the file, without which it can't be
+/// read.
+pub(super) struct ZstdWriter<W> {
+    writer: W,
+    // The last WINDOW bytes compressed, then the input not yet compressed
+    data: Vec<u8>,
+    // Where in `data` the input not yet compressed starts
+    start: usize,
+    checksum: Xxh64,
+    // The predefined tables, with which state leads to which for each
+    tables: [(Table, Vec<Vec<u16>>); 3],
+}
+
+impl<W: Write> ZstdWriter<W> {
+    pub(super) fn new(mut writer: W) -> io::Result<Self> {
+        // A checksum, no dictionary or content size, and the window
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&[0x04, ((WINDOW_LOG - 10) << 3) as u8])?;
+        let table = |(counts, log)| {
+            let table = Table::new(counts, log).expect("the predefined tables are valid");
+            let leading = table.leading();
+            (table, leading)
+        };
+        Ok(ZstdWriter {
+            writer,
+            data: Vec::with_capacity(WINDOW + MAX_BLOCK),
+            start: 0,
+            checksum: Xxh64::new(),
+            tables: [
+                table(fse::LITERAL_LENGTHS),
+                table(fse::OFFSETS),
+                table(fse::MATCH_LENGTHS),
+            ],
+        })
+    }
+
+    /// Compress the rest of what's been written as the last block, write
+    /// the checksum after it, and give back the writer
+    pub(super) fn finish(mut self) -> io::Result<W> {
+        self.compress(true)?;
+        let checksum = self.checksum.finish() as u32;
+        self.writer.write_all(&checksum.to_le_bytes())?;
+        Ok(self.writer)
+    }
+
+    // Compress the input not yet compressed as one block
+    fn compress(&mut self, last: bool) -> io::Result<()> {
+        let block = &self.data[self.start..];
+        self.checksum.update(block);
+        let (literals, sequences) = find_sequences(&self.data, self.start);
+        let compressed = encode_block(&literals, &sequences, &self.tables);
+        let (kind, content) = if compressed.len() < block.len() {
+            (2, &compressed[..])
+        } else {
+            (0, block)
+        };
+        let header = last as u32 | kind << 1 | (block.len() as u32) << 3;
+        let header = match kind {
+            2 => last as u32 | kind << 1 | (content.len() as u32) << 3,
+            _ => header,
+        };
+        self.writer.write_all(&header.to_le_bytes()[..3])?;
+        self.writer.write_all(content)?;
+
+        // Keep what copies in the next block can refer back to
+        let keep = self.data.len().saturating_sub(WINDOW);
+        self.data.drain(..keep);
+        self.start = self.data.len();
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for ZstdWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = buf.len().min(self.start + MAX_BLOCK - self.data.len());
+        self.data.extend_from_slice(&buf[..n]);
+        if self.data.len() - self.start == MAX_BLOCK {
+            self.compress(false)?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+// The repeats in the data from `start` on, as sequences of a literal length,
+// a match length and an offset, and the literals between them
+fn find_sequences(data: &[u8], start: usize) -> (Vec<u8>, Vec<(usize, usize, usize)>) {
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    // The earlier place with the same hash as each place
+    let mut previous = vec![usize::MAX; data.len()];
+    for i in start.saturating_sub(WINDOW)..start {
+        insert(data, i, &mut head, &mut previous);
+    }
+    let mut literals = Vec::new();
+    let mut sequences = Vec::new();
+    let mut literal_start = start;
+    let mut i = start;
+    while i < data.len() {
+        let (len, offset) = longest_match(data, i, &head, &previous);
+        if len >= MIN_MATCH {
+            literals.extend_from_slice(&data[literal_start..i]);
+            sequences.push((i - literal_start, len, offset));
+            for j in i..i + len {
+                insert(data, j, &mut head, &mut previous);
+            }
+            i += len;
+            literal_start = i;
+        } else {
+            insert(data, i, &mut head, &mut previous);
+            i += 1;
+        }
+    }
+    literals.extend_from_slice(&data[literal_start..]);
+    (literals, sequences)
+}
+
+fn hash(bytes: &[u8]) -> usize {
+    let key = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    (key.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize
+}
+
+// Add the place `i` to the front of the chain of places with its hash
+fn insert(data: &[u8], i: usize, head: &mut [usize], previous: &mut [usize]) {
+    if i + MIN_MATCH <= data.len() {
+        let hash = hash(&data[i..i + MIN_MATCH]);
+        previous[i] = head[hash];
+        head[hash] = i;
+    }
+}
+
+// The longest repeat of earlier data at `i`, as its length and how far back
+// it is; a length under MIN_MATCH if there's none
+fn longest_match(data: &[u8], i: usize, head: &[usize], previous: &[usize]) -> (usize, usize) {
+    let max = data.len() - i;
+    if max < MIN_MATCH {
+        return (0, 0);
+    }
+    let (mut best, mut offset) = (0, 0);
+    let mut candidate = head[hash(&data[i..i + MIN_MATCH])];
+    for _ in 0..MAX_CHAIN {
+        if candidate == usize::MAX || i - candidate > WINDOW {
+            break;
+        }
+        let len = data[candidate..]
+            .iter()
+            .zip(&data[i..])
+            .take_while(|(a, b)| a == b)
+            .count();
+        if len > best {
+            best = len;
+            offset = i - candidate;
+            if len == max {
+                break;
+            }
+        }
+        candidate = previous[candidate];
+    }
+    (best, offset)
+}
+
+// A compressed block of the literals stored as they are, then the sequences
+// coded with the predefined tables
+fn encode_block(
+    literals: &[u8],
+    sequences: &[(usize, usize, usize)],
+    tables: &[(Table, Vec<Vec<u16>>); 3],
+) -> Vec<u8> {
+    let mut out = Vec::with_capacity(literals.len() + 3 * sequences.len() + 16);
+    let size = literals.len();
+    match size {
+        0..=31 => out.push((size << 3) as u8),
+        32..=4095 => {
+            out.extend_from_slice(&[(1 << 2 | (size & 0xf) << 4) as u8, (size >> 4) as u8])
+        }
+        _ => out.extend_from_slice(&[
+            (3 << 2 | (size & 0xf) << 4) as u8,
+            (size >> 4) as u8,
+            (size >> 12) as u8,
+        ]),
+    }
+    out.extend_from_slice(literals);
+
+    let count = sequences.len();
+    match count {
+        0..=127 => out.push(count as u8),
+        128..=0x7eff => out.extend_from_slice(&[(count >> 8) as u8 + 128, count as u8]),
+        _ => out.extend_from_slice(&[255, (count - 0x7f00) as u8, ((count - 0x7f00) >> 8) as u8]),
+    }
+    if count == 0 {
+        return out;
+    }
+    // Every table predefined
+    out.push(0);
+
+    // Each sequence's codes, and the extra bits that go with each
+    let coded: Vec<[(u8, u64, u32); 3]> = sequences
+        .iter()
+        .map(|&(literal_length, match_length, offset)| {
+            let literal_code = LITERAL_BASE
+                .iter()
+                .rposition(|&base| base as usize <= literal_length)
+                .unwrap_or(0);
+            let match_code = MATCH_BASE
+                .iter()
+                .rposition(|&base| base as usize <= match_length)
+                .unwrap_or(0);
+            // Never a repeat, so the offset plus 3
+            let value = offset + 3;
+            let offset_code = usize::BITS - 1 - value.leading_zeros();
+            [
+                (
+                    literal_code as u8,
+                    (literal_length - LITERAL_BASE[literal_code] as usize) as u64,
+                    LITERAL_EXTRA[literal_code] as u32,
+                ),
+                (
+                    offset_code as u8,
+                    (value - (1 << offset_code)) as u64,
+                    offset_code,
+                ),
+                (
+                    match_code as u8,
+                    (match_length - MATCH_BASE[match_code] as usize) as u64,
+                    MATCH_EXTRA[match_code] as u32,
+                ),
+            ]
+        })
+        .collect();
+
+    // The states of each table, worked out from the last sequence back: the
+    // last can be any state of its code, and each before it is the state of
+    // its code that leads to the state after
+    let states: Vec<Vec<usize>> = (0..3)
+        .map(|kind| {
+            let (table, leading) = &tables[kind];
+            let mut states = vec![0; count];
+            states[count - 1] = table
+                .first(coded[count - 1][kind].0)
+                .expect("the predefined tables have every code");
+            for i in (0..count - 1).rev() {
+                states[i] = leading[coded[i][kind].0 as usize][states[i + 1]] as usize;
+            }
+            states
+        })
+        .collect();
+
+    // Written in the reverse of the order they're read in
+    let mut bits = BitWriter::default();
+    for i in (0..count).rev() {
+        if i + 1 < count {
+            for kind in [OFFSETS, MATCH_LENGTHS, LITERAL_LENGTHS] {
+                let (table, _) = &tables[kind];
+                let state = states[kind][i];
+                let step = states[kind][i + 1] - table.base(state);
+                bits.put(step as u64, table.bits(state));
+            }
+        }
+        for kind in [LITERAL_LENGTHS, MATCH_LENGTHS, OFFSETS] {
+            let (_, extra, n) = coded[i][kind];
+            bits.put(extra, n);
+        }
+    }
+    for kind in [MATCH_LENGTHS, OFFSETS, LITERAL_LENGTHS] {
+        bits.put(states[kind][0] as u64, tables[kind].0.log);
+    }
+    out.extend(bits.finish());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unzstd(data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        ZstdReader::new(data)?.read_to_end(&mut out)?;
+        Ok(out)
+    }
+
+    fn zstd(data: &[u8]) -> Vec<u8> {
+        let mut writer = ZstdWriter::new(Vec::new()).unwrap();
+        writer.write_all(data).unwrap();
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn test_write_and_read_back() {
+        assert_eq!(unzstd(&zstd(b"")).unwrap(), b"");
+        assert_eq!(
+            unzstd(&zstd(b"hello hello hello\n")).unwrap(),
+            b"hello hello hello\n"
+        );
+
+        // Over several blocks, with repeats reaching back into the one before
+        let data: Vec<u8> = (0..200_000u32)
+            .flat_map(|i| format!("row {},{}\n", i % 1000, i % 7).into_bytes())
+            .collect();
+        let compressed = zstd(&data);
+        assert!(compressed.len() < data.len() / 4);
+        assert_eq!(unzstd(&compressed).unwrap(), data);
+
+        // Long runs, which copy from just behind themselves, and data with
+        // no repeats, which is stored
+        let data = vec![b'x'; 300_000];
+        assert_eq!(unzstd(&zstd(&data)).unwrap(), data);
+        let data: Vec<u8> = (0..300_000u64)
+            .map(|i| (i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 56) as u8)
+            .collect();
+        assert_eq!(unzstd(&zstd(&data)).unwrap(), data);
+    }
+
+    #[test]
+    fn test_read_zstd() {
+        // `zstd` of "hello hello hello\n": a compressed block of literals and
+        // one sequence, and a checksum
+        let hello = [
+            0x28, 0xb5, 0x2f, 0xfd, 0x04, 0x58, 0x6d, 0x00, 0x00, 0x38, 0x68, 0x65, 0x6c, 0x6c,
+            0x6f, 0x20, 0x0a, 0x01, 0x00, 0x31, 0x4a, 0x11, 0xa3, 0xaa, 0x74, 0xce,
+        ];
+        assert_eq!(unzstd(&hello).unwrap(), b"hello hello hello\n");
+
+        // Two frames read as one, and skippable frames are skipped
+        let skippable = [0x5a, 0x2a, 0x4d, 0x18, 0x02, 0x00, 0x00, 0x00, 0xaa, 0xbb];
+        let twice = [&hello[..], &skippable[..], &hello[..]].concat();
+        assert_eq!(unzstd(&twice).unwrap(), b"hello hello hello\n".repeat(2));
+
+        let mut corrupt = hello;
+        corrupt[10] ^= 1;
+        assert!(unzstd(&corrupt).is_err());
+        assert!(unzstd(&hello[..20]).is_err());
+        assert!(unzstd(b"hello").is_err());
+        assert!(unzstd(b"").is_err());
+    }
+
+    #[test]
+    fn test_damaged_files() {
+        let data: Vec<u8> = (0..2_000u32)
+            .flat_map(|i| format!("{},{}\n", i % 50, i % 3).into_bytes())
+            .collect();
+        let compressed = zstd(&data);
+        for len in 0..compressed.len() {
+            assert!(unzstd(&compressed[..len]).is_err(), "{}", len);
+        }
+        // Every byte changed, which reads or errs but doesn't panic
+        for i in 0..compressed.len() {
+            for change in [0x01, 0x80, 0xff] {
+                let mut damaged = compressed.clone();
+                damaged[i] ^= change;
+                let _ = unzstd(&damaged);
+            }
+        }
+
+        // A dictionary, a window too large, and junk after the frame
+        let header = |descriptor: u8, rest: &[u8]| [&MAGIC[..], &[descriptor], rest].concat();
+        let error = |data: &[u8]| unzstd(data).unwrap_err().to_string();
+        assert!(error(&header(0x01, &[0x38, 0x07])).contains("dictionary"));
+        assert!(error(&header(0x00, &[0xf8])).contains("window"));
+        let mut trailing = zstd(b"hello");
+        trailing.extend_from_slice(b"junk");
+        assert_eq!(error(&trailing), "zstd: the file isn't zstd");
+    }
+
+    #[test]
+    fn test_repeat_offsets() {
+        let mut repeats = [1, 4, 8];
+        assert_eq!(repeat_offset(&mut repeats, 10, 1), Ok(7));
+        assert_eq!(repeats, [7, 1, 4]);
+        // With literals before it, 1 is the most recent offset
+        assert_eq!(repeat_offset(&mut repeats, 1, 3), Ok(7));
+        assert_eq!(repeats, [7, 1, 4]);
+        assert_eq!(repeat_offset(&mut repeats, 3, 3), Ok(4));
+        assert_eq!(repeats, [4, 7, 1]);
+        // Without, each is shifted along, and 3 is one less than the most
+        // recent
+        assert_eq!(repeat_offset(&mut repeats, 1, 0), Ok(7));
+        assert_eq!(repeats, [7, 4, 1]);
+        assert_eq!(repeat_offset(&mut repeats, 3, 0), Ok(6));
+        assert_eq!(repeats, [6, 7, 4]);
+        let mut repeats = [1, 4, 8];
+        assert!(repeat_offset(&mut repeats, 3, 0).is_err());
+    }
+}
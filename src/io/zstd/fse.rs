@@ -0,0 +1,394 @@
+// io/zstd/fse.rs
+
+//! Finite State Entropy, the tANS coding zstd uses for its sequences and for
+//! the weights of its Huffman codes: tables built from a distribution,
+//! distributions read from the data, the distributions zstd predefines, and
+//! the bitstreams the codes are read from backwards.
+
+/// The distributions zstd predefines for literal lengths, match lengths and
+/// offset codes, with their accuracy logs. -1 is a probability below 1.
+pub(super) const LITERAL_LENGTHS: (&[i16], u32) = (
+    &[
+        4, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 3, 2, 1, 1, 1,
+        1, 1, -1, -1, -1, -1,
+    ],
+    6,
+);
+pub(super) const MATCH_LENGTHS: (&[i16], u32) = (
+    &[
+        1, 4, 3, 2, 2, 2, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+        1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1, -1, -1, -1, -1, -1,
+    ],
+    6,
+);
+pub(super) const OFFSETS: (&[i16], u32) = (
+    &[
+        1, 1, 1, 1, 1, 1, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1, -1, -1, -1,
+    ],
+    5,
+);
+
+/// A decoding table: for each state, the symbol it stands for, and how the
+/// next state is found, as a base and a number of bits to add to it
+#[derive(Debug, Clone)]
+pub(super) struct Table {
+    pub(super) log: u32,
+    states: Vec<State>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct State {
+    symbol: u8,
+    bits: u8,
+    base: u16,
+}
+
+impl Table {
+    /// The table of a distribution whose probabilities add up to
+    /// `1 << log`, -1 standing for a probability below 1
+    pub(super) fn new(counts: &[i16], log: u32) -> Result<Self, String> {
+        let size = 1usize << log;
+        let mut states = vec![State::default(); size];
+        // Symbols below probability 1 take a state each at the top, and the
+        // rest are spread over the states below them
+        let mut high = size;
+        let mut next = vec![0u32; counts.len()];
+        for (symbol, &count) in counts.iter().enumerate() {
+            if count == -1 {
+                high -= 1;
+                states[high].symbol = symbol as u8;
+                next[symbol] = 1;
+            } else {
+                next[symbol] = count.max(0) as u32;
+            }
+        }
+        let step = (size >> 1) + (size >> 3) + 3;
+        let mut position = 0;
+        for (symbol, &count) in counts.iter().enumerate() {
+            for _ in 0..count.max(0) {
+                states[position].symbol = symbol as u8;
+                position = (position + step) & (size - 1);
+                while position >= high {
+                    position = (position + step) & (size - 1);
+                }
+            }
+        }
+        if position != 0 {
+            return Err("an FSE distribution doesn't add up".to_string());
+        }
+        for state in &mut states {
+            let n = next[state.symbol as usize];
+            next[state.symbol as usize] += 1;
+            let bits = log - (31 - n.leading_zeros());
+            state.bits = bits as u8;
+            state.base = ((n << bits) as usize - size) as u16;
+        }
+        Ok(Table { log, states })
+    }
+
+    /// A table whose every state is the one symbol, read with no bits
+    pub(super) fn single(symbol: u8) -> Self {
+        Table {
+            log: 0,
+            states: vec![State {
+                symbol,
+                bits: 0,
+                base: 0,
+            }],
+        }
+    }
+
+    pub(super) fn symbol(&self, state: usize) -> u8 {
+        self.states[state].symbol
+    }
+
+    /// The state after `state`, with the bits it reads
+    pub(super) fn next(&self, state: usize, bits: &mut Backward) -> usize {
+        let State { bits: n, base, .. } = self.states[state];
+        base as usize + bits.take(n as u32) as usize
+    }
+
+    /// For encoding: for each symbol, the state that leads to each state.
+    /// The states of a symbol split the next states between them, so every
+    /// state has one state of each symbol leading to it.
+    pub(super) fn leading(&self) -> Vec<Vec<u16>> {
+        let symbols = self
+            .states
+            .iter()
+            .map(|s| s.symbol as usize)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        let mut leading = vec![vec![0u16; self.states.len()]; symbols];
+        for (i, state) in self.states.iter().enumerate() {
+            let start = state.base as usize;
+            leading[state.symbol as usize][start..start + (1 << state.bits)].fill(i as u16);
+        }
+        leading
+    }
+
+    /// The first state of a symbol, None if it has none
+    pub(super) fn first(&self, symbol: u8) -> Option<usize> {
+        self.states.iter().position(|state| state.symbol == symbol)
+    }
+
+    /// How many bits a state reads for the next one
+    pub(super) fn bits(&self, state: usize) -> u32 {
+        self.states[state].bits as u32
+    }
+
+    pub(super) fn base(&self, state: usize) -> usize {
+        self.states[state].base as usize
+    }
+}
+
+/// Read a distribution from the start of `data`, for symbols up to
+/// `max_symbol` with an accuracy log up to `max_log`: the table, and how
+/// many bytes it took
+pub(super) fn read_table(
+    data: &[u8],
+    max_symbol: usize,
+    max_log: u32,
+) -> Result<(Table, usize), String> {
+    let mut bits = Forward { data, position: 0 };
+    let log = bits.take(4)? + 5;
+    if log > max_log {
+        return Err(format!(
+            "an FSE table's accuracy log is {}, over {}",
+            log, max_log
+        ));
+    }
+    let mut counts = Vec::new();
+    let mut remaining = (1i32 << log) + 1;
+    let mut threshold = 1i32 << log;
+    let mut n = log + 1;
+    while remaining > 1 {
+        if counts.len() > max_symbol {
+            return Err("an FSE table has too many symbols".to_string());
+        }
+        // The count, in n - 1 bits if it's small enough and n otherwise
+        let max = 2 * threshold - 1 - remaining;
+        let low = bits.peek(n - 1) as i32;
+        let value = if low < max {
+            bits.position += n as usize - 1;
+            low
+        } else {
+            let value = bits.peek(n) as i32;
+            bits.position += n as usize;
+            if value >= threshold {
+                value - max
+            } else {
+                value
+            }
+        };
+        let count = value - 1;
+        remaining -= count.abs();
+        counts.push(count as i16);
+        if count == 0 {
+            // Repeated zeros, three at a time while the two bits are all set
+            loop {
+                let repeat = bits.take(2)?;
+                counts.extend(std::iter::repeat_n(0, repeat as usize));
+                if repeat != 3 {
+                    break;
+                }
+            }
+        }
+        while remaining < threshold {
+            n -= 1;
+            threshold >>= 1;
+        }
+        if bits.position > data.len() * 8 {
+            return Err("an FSE table runs past the end of its block".to_string());
+        }
+    }
+    if remaining != 1 || counts.len() > max_symbol + 1 {
+        return Err("an FSE table's probabilities don't add up".to_string());
+    }
+    Ok((Table::new(&counts, log)?, bits.position.div_ceil(8)))
+}
+
+// Bits read from the start of some data, lowest first, reading zeros past
+// the end so the caller can check it went past
+struct Forward<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl Forward<'_> {
+    fn peek(&self, n: u32) -> u32 {
+        (0..n as usize).fold(0, |value, i| {
+            let bit = self.position + i;
+            let set = self
+                .data
+                .get(bit / 8)
+                .is_some_and(|byte| byte >> (bit % 8) & 1 == 1);
+            value | (set as u32) << i
+        })
+    }
+
+    fn take(&mut self, n: u32) -> Result<u32, String> {
+        if self.position + n as usize > self.data.len() * 8 {
+            return Err("an FSE table runs past the end of its block".to_string());
+        }
+        let value = self.peek(n);
+        self.position += n as usize;
+        Ok(value)
+    }
+}
+
+/// Bits read backwards from the end of some data, which a 1 bit after the
+/// last marks. Reading past the start gives zeros, and `overflowed` says if
+/// that happened.
+pub(super) struct Backward<'a> {
+    data: &'a [u8],
+    // How many bits are left to read, below zero once past the start
+    left: isize,
+}
+
+impl<'a> Backward<'a> {
+    pub(super) fn new(data: &'a [u8]) -> Result<Self, String> {
+        match data.last() {
+            Some(&last) if last != 0 => Ok(Backward {
+                data,
+                left: (data.len() * 8) as isize - last.leading_zeros() as isize - 1,
+            }),
+            _ => Err("a bitstream has no end marker".to_string()),
+        }
+    }
+
+    /// The next `n` bits, up to 56, without reading them
+    pub(super) fn peek(&self, n: u32) -> u64 {
+        if n == 0 {
+            return 0;
+        }
+        let end = self.left;
+        let start = end - n as isize;
+        if end <= 0 {
+            return 0;
+        }
+        // The bits from max(start, 0) up to end, then shifted up for those
+        // past the start
+        let from = start.max(0) as usize;
+        let count = (end as usize - from) as u32;
+        let mut word = [0u8; 8];
+        let first = from / 8;
+        let last = (end as usize).div_ceil(8).min(self.data.len());
+        word[..last - first].copy_from_slice(&self.data[first..last]);
+        let value = (u64::from_le_bytes(word) >> (from % 8)) & ((1u64 << count) - 1);
+        value << (n - count)
+    }
+
+    pub(super) fn take(&mut self, n: u32) -> u64 {
+        let value = self.peek(n);
+        self.left -= n as isize;
+        value
+    }
+
+    /// How many bits are left, 0 when every bit has been read
+    pub(super) fn left(&self) -> isize {
+        self.left
+    }
+
+    pub(super) fn overflowed(&self) -> bool {
+        self.left < 0
+    }
+}
+
+/// Bits written for a `Backward` to read: the writes come out in reverse
+/// order, each value as it went in
+#[derive(Default)]
+pub(super) struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u64,
+    count: u32,
+}
+
+impl BitWriter {
+    pub(super) fn put(&mut self, value: u64, n: u32) {
+        debug_assert!(n <= 32 && value >> n == 0);
+        self.buffer |= value << self.count;
+        self.count += n;
+        while self.count >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    /// The bytes, after the end marker
+    pub(super) fn finish(mut self) -> Vec<u8> {
+        self.put(1, 1);
+        if self.count > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_predefined_tables() {
+        for (counts, log) in [LITERAL_LENGTHS, MATCH_LENGTHS, OFFSETS] {
+            let table = Table::new(counts, log).unwrap();
+            // Every state of a symbol leads to as many states as it has
+            // probability, and between them they lead everywhere
+            let leading = table.leading();
+            assert_eq!(leading.len(), counts.len());
+            for (symbol, &count) in counts.iter().enumerate() {
+                let states = (0..1 << log)
+                    .filter(|&state| table.symbol(state) == symbol as u8)
+                    .count();
+                assert_eq!(states as i16, count.max(1));
+            }
+        }
+        // The first states of the predefined literal lengths, from RFC 8878
+        let table = Table::new(LITERAL_LENGTHS.0, 6).unwrap();
+        let first: Vec<(u8, u32, usize)> = (0..4)
+            .map(|state| (table.symbol(state), table.bits(state), table.base(state)))
+            .collect();
+        assert_eq!(first, [(0, 4, 0), (0, 4, 16), (1, 5, 32), (3, 5, 0)]);
+    }
+
+    #[test]
+    fn test_read_table() {
+        // Four symbols of probability 8 in 32, each a 9 written in fewer bits
+        // as the probability left shrinks, the last as 15 for being in the
+        // upper range of its bits
+        let mut bits = BitWriter::default();
+        bits.put(0, 4);
+        bits.put(9, 5);
+        bits.put(9, 5);
+        bits.put(9, 4);
+        bits.put(15, 4);
+        let data = bits.finish();
+        let (table, used) = read_table(&data, 255, 6).unwrap();
+        assert_eq!(table.log, 5);
+        assert_eq!(used, 3);
+        assert!(read_table(&data, 2, 6).is_err());
+        assert!(read_table(&[0x0f], 255, 6).is_err());
+        assert!(read_table(&data[..1], 255, 6).is_err());
+    }
+
+    #[test]
+    fn test_bits_read_back_in_reverse() {
+        let mut bits = BitWriter::default();
+        for (value, n) in [(5, 3), (0, 0), (1234, 11), (1, 1), (0xabcdef, 24)] {
+            bits.put(value, n);
+        }
+        let data = bits.finish();
+        let mut backward = Backward::new(&data).unwrap();
+        for (value, n) in [(0xabcdef, 24), (1, 1), (1234, 11), (0, 0), (5, 3)] {
+            assert_eq!(backward.take(n), value);
+        }
+        assert_eq!(backward.left(), 0);
+        // Past the start, zeros
+        assert_eq!(backward.take(4), 0);
+        assert!(backward.overflowed());
+        assert!(Backward::new(&[1, 0]).is_err());
+        assert!(Backward::new(&[]).is_err());
+    }
+}
@@ -0,0 +1,173 @@
+// io/zstd/huffman.rs
+
+//! The Huffman codes of zstd's compressed literals: the code a block
+//! describes, as the weights of its symbols, and the streams of literals
+//! coded with it, read backwards as FSE's are.
+use super::fse::{read_table, Backward};
+
+// The longest code zstd allows, in bits
+const MAX_BITS: u32 = 11;
+
+/// A code as a table indexed by the next `bits` bits of a stream, giving the
+/// symbol they start with and the length of its code
+#[derive(Debug, Clone)]
+pub(super) struct Huffman {
+    bits: u32,
+    entries: Vec<(u8, u8)>,
+}
+
+impl Huffman {
+    /// Read a code's description from the start of `data`: the code, and
+    /// how many bytes the description took
+    pub(super) fn read(data: &[u8]) -> Result<(Self, usize), String> {
+        let (&header, rest) = data
+            .split_first()
+            .ok_or("a block ends before its Huffman code")?;
+        let (weights, used) = if header < 128 {
+            let size = header as usize;
+            let weights = rest
+                .get(..size)
+                .ok_or("a Huffman code runs past the end of its block")?;
+            (fse_weights(weights)?, 1 + size)
+        } else {
+            // Four bits a weight, the first in the high bits of a byte
+            let count = header as usize - 127;
+            let bytes = rest
+                .get(..count.div_ceil(2))
+                .ok_or("a Huffman code runs past the end of its block")?;
+            let weights = (0..count)
+                .map(|i| match i % 2 {
+                    0 => bytes[i / 2] >> 4,
+                    _ => bytes[i / 2] & 0xf,
+                })
+                .collect();
+            (weights, 1 + count.div_ceil(2))
+        };
+        Ok((Huffman::from_weights(weights)?, used))
+    }
+
+    // The code of the weights of every symbol but the last, whose weight is
+    // what brings the total up to a power of two
+    fn from_weights(mut weights: Vec<u8>) -> Result<Self, String> {
+        if weights.len() > 255 || weights.iter().any(|&w| w > MAX_BITS as u8) {
+            return Err("a Huffman code has weights out of range".to_string());
+        }
+        let total: u32 = weights
+            .iter()
+            .filter(|&&w| w > 0)
+            .map(|&w| 1 << (w - 1))
+            .sum();
+        if total == 0 {
+            return Err("a Huffman code has no symbols".to_string());
+        }
+        let bits = 32 - total.leading_zeros();
+        let left = (1 << bits) - total;
+        if bits > MAX_BITS || !left.is_power_of_two() {
+            return Err("a Huffman code's weights don't add up".to_string());
+        }
+        weights.push(left.trailing_zeros() as u8 + 1);
+
+        // Codes go to the lightest symbols first, and in order of symbol
+        // among those of a weight
+        let mut entries = Vec::with_capacity(1 << bits);
+        for weight in 1..=bits as u8 {
+            for (symbol, _) in weights.iter().enumerate().filter(|(_, &w)| w == weight) {
+                let len = bits as u8 + 1 - weight;
+                entries.extend(std::iter::repeat_n((symbol as u8, len), 1 << (weight - 1)));
+            }
+        }
+        Ok(Huffman { bits, entries })
+    }
+
+    /// Decode `count` literals from a stream, which they must use all of
+    pub(super) fn decode(
+        &self,
+        stream: &[u8],
+        count: usize,
+        out: &mut Vec<u8>,
+    ) -> Result<(), String> {
+        let mut bits = Backward::new(stream)?;
+        for _ in 0..count {
+            let (symbol, len) = self.entries[bits.peek(self.bits) as usize];
+            bits.take(len as u32);
+            out.push(symbol);
+        }
+        if bits.left() != 0 {
+            return Err("a stream of literals doesn't match its size".to_string());
+        }
+        Ok(())
+    }
+}
+
+// Weights coded with FSE, decoded by two states in turn from the one stream
+// until it runs out
+fn fse_weights(data: &[u8]) -> Result<Vec<u8>, String> {
+    let (table, used) = read_table(data, 255, 6)?;
+    let mut bits = Backward::new(&data[used..])?;
+    let mut states = [0, 0];
+    for state in &mut states {
+        *state = bits.take(table.log) as usize;
+    }
+    let mut weights = Vec::new();
+    'decode: loop {
+        for turn in 0..2 {
+            weights.push(table.symbol(states[turn]));
+            states[turn] = table.next(states[turn], &mut bits);
+            if bits.overflowed() {
+                weights.push(table.symbol(states[1 - turn]));
+                break 'decode;
+            }
+        }
+        if weights.len() > 255 {
+            break;
+        }
+    }
+    Ok(weights)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::zstd::fse::BitWriter;
+
+    #[test]
+    fn test_weights_to_codes() {
+        // RFC 8878's example: symbols 0 to 5 with weights 4, 3, 2, 0, 1, and
+        // 1 for the last
+        let code = Huffman::from_weights(vec![4, 3, 2, 0, 1]).unwrap();
+        assert_eq!(code.bits, 4);
+        let codes: Vec<(u8, u8)> = [0b1000, 0b0100, 0b0010, 0b0000, 0b0001]
+            .iter()
+            .map(|&i| code.entries[i])
+            .collect();
+        assert_eq!(codes, [(0, 1), (1, 2), (2, 3), (4, 4), (5, 4)]);
+
+        // The weights written directly, two to a byte
+        let (direct, used) = Huffman::read(&[127 + 5, 0x43, 0x20, 0x10]).unwrap();
+        assert_eq!(used, 4);
+        assert_eq!(direct.entries, code.entries);
+
+        assert!(Huffman::from_weights(vec![]).is_err());
+        assert!(Huffman::from_weights(vec![12]).is_err());
+        assert!(Huffman::from_weights(vec![2, 2, 1]).is_err());
+        assert!(Huffman::read(&[127 + 5, 0x43]).is_err());
+        assert!(Huffman::read(&[20, 1, 2]).is_err());
+    }
+
+    #[test]
+    fn test_decode_a_stream() {
+        let (code, _) = Huffman::read(&[127 + 5, 0x43, 0x20, 0x10]).unwrap();
+        // Symbols 0, 5, 2 and 1, their codes written last first, each read
+        // from its highest bit
+        let mut bits = BitWriter::default();
+        for (value, len) in [(0b01, 2), (0b001, 3), (0b0001, 4), (0b1, 1)] {
+            bits.put(value, len);
+        }
+        let stream = bits.finish();
+        let mut out = Vec::new();
+        code.decode(&stream, 4, &mut out).unwrap();
+        assert_eq!(out, [0, 5, 2, 1]);
+        assert!(code.decode(&stream, 3, &mut Vec::new()).is_err());
+        assert!(code.decode(&stream, 5, &mut Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,148 @@
+// io/zstd/xxhash.rs
+
+//! XXH64, whose low 32 bits are the checksum at the end of a zstd frame,
+//! worked out as the data goes by.
+
+const PRIME_1: u64 = 0x9e37_79b1_85eb_ca87;
+const PRIME_2: u64 = 0xc2b2_ae3d_27d4_eb4f;
+const PRIME_3: u64 = 0x1656_67b1_9e37_79f9;
+const PRIME_4: u64 = 0x85eb_ca77_c2b2_ae63;
+const PRIME_5: u64 = 0x27d4_eb2f_1656_67c5;
+
+/// The XXH64 of everything passed to `update`, with a seed of 0
+#[derive(Debug, Clone)]
+pub(super) struct Xxh64 {
+    lanes: [u64; 4],
+    // Input short of a whole stripe of 32 bytes
+    pending: Vec<u8>,
+    total: u64,
+}
+
+impl Xxh64 {
+    pub(super) fn new() -> Self {
+        Xxh64 {
+            lanes: [
+                PRIME_1.wrapping_add(PRIME_2),
+                PRIME_2,
+                0,
+                PRIME_1.wrapping_neg(),
+            ],
+            pending: Vec::with_capacity(32),
+            total: 0,
+        }
+    }
+
+    pub(super) fn update(&mut self, mut data: &[u8]) {
+        self.total += data.len() as u64;
+        if !self.pending.is_empty() {
+            let n = data.len().min(32 - self.pending.len());
+            self.pending.extend_from_slice(&data[..n]);
+            data = &data[n..];
+            if self.pending.len() < 32 {
+                return;
+            }
+            let stripe = std::mem::take(&mut self.pending);
+            self.stripe(&stripe);
+            self.pending = stripe;
+            self.pending.clear();
+        }
+        let mut stripes = data.chunks_exact(32);
+        for stripe in &mut stripes {
+            self.stripe(stripe);
+        }
+        self.pending.extend_from_slice(stripes.remainder());
+    }
+
+    fn stripe(&mut self, stripe: &[u8]) {
+        for (lane, word) in self.lanes.iter_mut().zip(stripe.chunks_exact(8)) {
+            *lane = round(*lane, read_u64(word));
+        }
+    }
+
+    pub(super) fn finish(&self) -> u64 {
+        let mut hash = if self.total >= 32 {
+            let [a, b, c, d] = self.lanes;
+            let mut hash = a
+                .rotate_left(1)
+                .wrapping_add(b.rotate_left(7))
+                .wrapping_add(c.rotate_left(12))
+                .wrapping_add(d.rotate_left(18));
+            for lane in self.lanes {
+                hash = (hash ^ round(0, lane))
+                    .wrapping_mul(PRIME_1)
+                    .wrapping_add(PRIME_4);
+            }
+            hash
+        } else {
+            PRIME_5
+        };
+        hash = hash.wrapping_add(self.total);
+
+        let mut rest = &self.pending[..];
+        while rest.len() >= 8 {
+            hash ^= round(0, read_u64(rest));
+            hash = hash
+                .rotate_left(27)
+                .wrapping_mul(PRIME_1)
+                .wrapping_add(PRIME_4);
+            rest = &rest[8..];
+        }
+        if rest.len() >= 4 {
+            let word = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]) as u64;
+            hash ^= word.wrapping_mul(PRIME_1);
+            hash = hash
+                .rotate_left(23)
+                .wrapping_mul(PRIME_2)
+                .wrapping_add(PRIME_3);
+            rest = &rest[4..];
+        }
+        for &byte in rest {
+            hash ^= (byte as u64).wrapping_mul(PRIME_5);
+            hash = hash.rotate_left(11).wrapping_mul(PRIME_1);
+        }
+
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(PRIME_2);
+        hash ^= hash >> 29;
+        hash = hash.wrapping_mul(PRIME_3);
+        hash ^ hash >> 32
+    }
+}
+
+fn round(lane: u64, input: u64) -> u64 {
+    lane.wrapping_add(input.wrapping_mul(PRIME_2))
+        .rotate_left(31)
+        .wrapping_mul(PRIME_1)
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes[..8].try_into().expect("eight bytes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn xxh64(data: &[u8]) -> u64 {
+        let mut hash = Xxh64::new();
+        hash.update(data);
+        hash.finish()
+    }
+
+    #[test]
+    fn test_xxh64() {
+        assert_eq!(xxh64(b""), 0xef46_db37_51d8_e999);
+        assert_eq!(xxh64(b"a"), 0xd24e_c4f1_a98c_6e5b);
+        assert_eq!(xxh64(b"abc"), 0x44bc_2cf5_ad77_0999);
+
+        // The same in pieces of every size as all at once
+        let data: Vec<u8> = (0..1000u32).map(|i| (i * 7 % 251) as u8).collect();
+        for piece in [1, 3, 31, 32, 33, 100] {
+            let mut hash = Xxh64::new();
+            for chunk in data.chunks(piece) {
+                hash.update(chunk);
+            }
+            assert_eq!(hash.finish(), xxh64(&data));
+        }
+    }
+}
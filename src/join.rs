@@ -0,0 +1,215 @@
+// join.rs
+
+//! Joining two tables on key columns.
+//!
+//! Keys compare with `Value` equality, so Integer 3 matches Number 3.0, NaN
+//! matches NaN and Null matches Null. The join hashes the keys of the smaller
+//! table and looks up each row of the larger one, so it takes time in
+//! proportion to the rows in and out rather than to their product.
+//!
+//! The result has the left table's columns, then the right table's apart from
+//! its key columns. A key column holds whichever side's key the row has, so
+//! rows only the right table has still show their key. Other right columns
+//! whose names the left table already uses get a suffix.
+use crate::error::DaxError;
+use crate::table::ColumnValues;
+use crate::types::Value;
+use crate::Table;
+use std::collections::HashMap;
+
+/// The suffix `Table::join` adds to clashing column names from the right table
+pub const DEFAULT_SUFFIX: &str = "_right";
+
+/// Which rows a join keeps besides those matching on both sides
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinKind {
+    /// Only rows that match
+    Inner,
+    /// Every left row, with Null for the right columns where nothing matches
+    Left,
+    /// Every right row, with Null for the left columns where nothing matches
+    Right,
+    /// Every row of both tables
+    Full,
+}
+
+// A row of the result: the rows of each table it comes from, if any
+type Pair = (Option<usize>, Option<usize>);
+
+impl Table {
+    /// Join with another table where the left column of each `on` pair equals
+    /// the right one.
+    ///
+    /// Rows come in the order of the left table, a left row matching several
+    /// right rows giving one row for each in their order, and then any right
+    /// rows a right or full join keeps without a match.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dax_rust::join::JoinKind;
+    /// use dax_rust::table::Table;
+    /// use dax_rust::types::Value;
+    ///
+    /// let mut sales = Table::new();
+    /// sales.add_column("ProductId".to_string(), vec![Value::Integer(1), Value::Integer(2)]).unwrap();
+    /// sales.add_column("Amount".to_string(), vec![Value::Number(10.0), Value::Number(5.0)]).unwrap();
+    /// let mut products = Table::new();
+    /// products.add_column("Id".to_string(), vec![Value::Integer(1)]).unwrap();
+    /// products.add_column("Name".to_string(), vec!["Apple".into()]).unwrap();
+    ///
+    /// let joined = sales.join(&products, &[("ProductId", "Id")], JoinKind::Left).unwrap();
+    /// assert_eq!(joined.column_names(), ["ProductId", "Amount", "Name"]);
+    /// assert_eq!(joined.get_column("Name").unwrap(), ["Apple".into(), Value::Null]);
+    /// ```
+    pub fn join(
+        &self,
+        other: &Table,
+        on: &[(&str, &str)],
+        kind: JoinKind,
+    ) -> Result<Table, DaxError> {
+        self.join_with_suffix(other, on, kind, DEFAULT_SUFFIX)
+    }
+
+    /// Join as `join` does, adding `suffix` to the right table's column names
+    /// that clash with the left table's
+    pub fn join_with_suffix(
+        &self,
+        other: &Table,
+        on: &[(&str, &str)],
+        kind: JoinKind,
+        suffix: &str,
+    ) -> Result<Table, DaxError> {
+        let mut left_keys = Vec::with_capacity(on.len());
+        let mut right_keys = Vec::with_capacity(on.len());
+        for (left, right) in on {
+            left_keys.push(
+                self.get_column(left)
+                    .ok_or_else(|| self.unknown_column(left))?,
+            );
+            right_keys.push(
+                other
+                    .get_column(right)
+                    .ok_or_else(|| other.unknown_column(right))?,
+            );
+        }
+        let pairs = join_rows(
+            &left_keys,
+            &right_keys,
+            self.num_rows(),
+            other.num_rows(),
+            kind,
+        );
+
+        let mut table = Table::new();
+        for name in self.column_names() {
+            let Some(values) = self.get_column(name) else {
+                continue;
+            };
+            // A key column falls back on the right table's key
+            let right = on
+                .iter()
+                .position(|(left, _)| left == name)
+                .map(|i| right_keys[i]);
+            let column = pairs
+                .iter()
+                .map(|(l, r)| match (l, r, right) {
+                    (Some(l), _, _) => values.get(*l).unwrap_or(Value::Null),
+                    (None, Some(r), Some(right)) => right.get(*r).unwrap_or(Value::Null),
+                    _ => Value::Null,
+                })
+                .collect();
+            table.add_column(name.clone(), column)?;
+        }
+        for name in other.column_names() {
+            if on.iter().any(|(_, right)| right == name) {
+                continue;
+            }
+            let Some(values) = other.get_column(name) else {
+                continue;
+            };
+            let column = pairs
+                .iter()
+                .map(|(_, r)| r.and_then(|r| values.get(r)).unwrap_or(Value::Null))
+                .collect();
+            let renamed = match self.get_column(name) {
+                Some(_) => format!("{}{}", name, suffix),
+                None => name.clone(),
+            };
+            if table.has_column(&renamed) {
+                return Err(
+                    format!("The joined table would have two columns named {}", renamed).into(),
+                );
+            }
+            table.add_column(renamed, column)?;
+        }
+        Ok(table)
+    }
+}
+
+// The pairs of rows making up the join, hashing the keys of the smaller side
+fn join_rows(
+    left_keys: &[ColumnValues],
+    right_keys: &[ColumnValues],
+    left_rows: usize,
+    right_rows: usize,
+    kind: JoinKind,
+) -> Vec<Pair> {
+    let key = |columns: &[ColumnValues], row: usize| -> Vec<Value> {
+        columns
+            .iter()
+            .map(|values| values.get(row).unwrap_or(Value::Null))
+            .collect()
+    };
+    let index = |columns: &[ColumnValues], rows: usize| {
+        let mut index: HashMap<Vec<Value>, Vec<usize>> = HashMap::new();
+        for row in 0..rows {
+            index.entry(key(columns, row)).or_default().push(row);
+        }
+        index
+    };
+
+    let mut matched: Vec<(usize, usize)> = Vec::new();
+    if left_rows <= right_rows {
+        let index = index(left_keys, left_rows);
+        for r in 0..right_rows {
+            for &l in index.get(&key(right_keys, r)).into_iter().flatten() {
+                matched.push((l, r));
+            }
+        }
+        matched.sort_unstable();
+    } else {
+        let index = index(right_keys, right_rows);
+        for l in 0..left_rows {
+            for &r in index.get(&key(left_keys, l)).into_iter().flatten() {
+                matched.push((l, r));
+            }
+        }
+    }
+
+    let keep_left = matches!(kind, JoinKind::Left | JoinKind::Full);
+    let keep_right = matches!(kind, JoinKind::Right | JoinKind::Full);
+    let mut pairs = Vec::with_capacity(matched.len());
+    let mut right_matched = vec![false; right_rows];
+    let mut next = matched.iter().peekable();
+    for l in 0..left_rows {
+        let mut any = false;
+        while let Some(&&(_, r)) = next.peek().filter(|(ml, _)| *ml == l) {
+            pairs.push((Some(l), Some(r)));
+            right_matched[r] = true;
+            any = true;
+            next.next();
+        }
+        if !any && keep_left {
+            pairs.push((Some(l), None));
+        }
+    }
+    if keep_right {
+        pairs.extend(
+            (0..right_rows)
+                .filter(|&r| !right_matched[r])
+                .map(|r| (None, Some(r))),
+        );
+    }
+    pairs
+}
@@ -7,13 +7,70 @@
 //! - Parse and evaluate DAX expressions
 //! - Read and write data in various formats
 
+pub(crate) mod column;
+pub mod compiled;
+pub(crate) mod context;
+pub(crate) mod datatable;
+pub mod dates;
+pub(crate) mod describe;
+pub mod display;
 pub mod error;
+pub mod explain;
+pub mod format;
+pub mod functions;
+pub mod group;
+pub mod hyperloglog;
 pub mod io;
+pub mod join;
+pub mod memory;
+pub mod parser;
+pub(crate) mod pivot;
+pub(crate) mod query;
+#[cfg(feature = "serde")]
+pub(crate) mod records;
+pub mod rows;
+#[cfg(feature = "serde")]
+pub mod serialize;
+pub mod sort;
+pub(crate) mod stats;
 // pub mod macros;
+pub(crate) mod summarize;
 pub mod table;
+pub(crate) mod time_intelligence;
 pub mod types;
+pub(crate) mod window;
 
+pub use compiled::CompiledExpression;
 pub use error::DaxError;
+pub use format::format_dax;
+pub use functions::FunctionRegistry;
+pub use rows::{FromTable, IntoTable};
 pub use table::Table;
-pub use types::Value;
+pub use types::{DType, Value};
 
+// What the code `parse_dax!` and `dax!` expand to refers to, so that it builds
+// in crates that depend on `dax-rust` and `dax-macro` alone
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::parser::{BinaryOperator, DaxExpr, UnaryOperator};
+    pub use crate::types::Value;
+    use crate::DaxError;
+    pub use dax_macro_impl::DaxToken;
+
+    // `dax!` reports a column the table doesn't have as an evaluation error,
+    // with the same message
+    pub fn unknown_column_as_evaluation_error(error: DaxError) -> DaxError {
+        match error {
+            DaxError::UnknownColumn {
+                name, suggestions, ..
+            } => {
+                let mut message = format!("Column {} not found", name);
+                if !suggestions.is_empty() {
+                    message += &format!(" (did you mean {}?)", suggestions.join(", "));
+                }
+                DaxError::EvaluationError(message)
+            }
+            error => error,
+        }
+    }
+}
@@ -0,0 +1,120 @@
+// memory.rs
+
+//! How much memory a table takes, and giving back what it doesn't need.
+//!
+//! The figures are estimates from the sizes of the vectors and strings a
+//! table owns, capacity included; what the allocator adds on top of each
+//! allocation isn't counted.
+use crate::display::TableDisplayOptions;
+use crate::types::Value;
+use crate::Table;
+use std::fmt;
+use std::mem::size_of;
+
+/// The bytes a table takes, column by column; see `Table::memory_usage`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// Each column's name and bytes, in column order
+    pub columns: Vec<(String, usize)>,
+    /// The columns' bytes, plus what the table takes to keep their names
+    pub total: usize,
+}
+
+impl MemoryUsage {
+    /// The bytes one column takes, or None if there's no such column
+    pub fn column(&self, name: &str) -> Option<usize> {
+        self.columns
+            .iter()
+            .find(|(column, _)| column == name)
+            .map(|(_, bytes)| *bytes)
+    }
+}
+
+impl Table {
+    /// Roughly how many bytes the table's data takes, by column.
+    ///
+    /// Each column counts its vectors at their capacity, the text its strings
+    /// own, and for a column of `Value`s the size of the enum, tag included.
+    /// A typed column that has been indexed, as `table[("Sales", 0)]`, also
+    /// counts the `Value`s built for it then, until `shrink_to_fit` lets
+    /// them go.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dax_rust::{Table, Value};
+    ///
+    /// let mut table = Table::new();
+    /// table.add_column("Sales".to_string(), vec![Value::Number(1.0); 1000]).unwrap();
+    /// let usage = table.memory_usage();
+    /// assert!(usage.column("Sales").unwrap() >= 8 * 1000);
+    /// assert!(usage.total >= usage.column("Sales").unwrap());
+    /// ```
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let columns: Vec<(String, usize)> = self
+            .column_names()
+            .iter()
+            .map(|name| (name.clone(), self.column_memory_usage(name)))
+            .collect();
+        // Each name is kept twice, in the column order and as a column's key
+        let names: usize = self
+            .column_names()
+            .iter()
+            .map(|name| size_of::<String>() + name.capacity())
+            .sum();
+        let total = columns.iter().map(|(_, bytes)| bytes).sum::<usize>() + 2 * names;
+        MemoryUsage { columns, total }
+    }
+
+    /// Give back memory the table holds but doesn't need: spare capacity,
+    /// the `Value`s built when a typed column was indexed, and the strings a
+    /// text column's dictionary no longer uses. Text columns that repeat
+    /// their values enough are dictionary encoded, and columns that have
+    /// come to hold a single kind of value are stored as that kind.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dax_rust::{Table, Value};
+    ///
+    /// let mut table = Table::new();
+    /// table.add_column("Sales".to_string(), vec![Value::Number(1.0); 1000]).unwrap();
+    /// let _ = &table[("Sales", 0)];
+    /// let before = table.memory_usage().total;
+    /// table.shrink_to_fit();
+    /// assert!(table.memory_usage().total < before);
+    /// ```
+    pub fn shrink_to_fit(&mut self) {
+        self.shrink_storage();
+    }
+}
+
+/// A table of each column's bytes, then the total
+impl fmt::Display for MemoryUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names: Vec<Value> = self
+            .columns
+            .iter()
+            .map(|(name, _)| Value::from(name.as_str()))
+            .collect();
+        let mut bytes: Vec<Value> = self
+            .columns
+            .iter()
+            .map(|(_, bytes)| Value::Integer(*bytes as i64))
+            .collect();
+        names.push(Value::from("Total"));
+        bytes.push(Value::Integer(self.total as i64));
+
+        let mut table = Table::new();
+        for (name, values) in [("Column", names), ("Bytes", bytes)] {
+            table
+                .add_column(name.to_string(), values)
+                .expect("both columns have a row per column and the total");
+        }
+        let options = TableDisplayOptions {
+            max_rows: None,
+            ..Default::default()
+        };
+        write!(f, "{}", table.display_with(options))
+    }
+}
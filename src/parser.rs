@@ -0,0 +1,414 @@
+// parser.rs
+
+//! The `DaxExpr` tree an expression parses to.
+//!
+//! The parser itself is `dax_macro_impl::syntax`, so that `dax!` can run it
+//! when it expands; this module turns its tree into a `DaxExpr`. Its
+//! precedence, loosest first: comparisons, then `+` and `-`, then `*` and
+//! `/`, then a leading sign, then `^`, then a trailing `%`, so `-2^2` is -4
+//! and `-50%` is -0.5. Binary operators of equal precedence group from the
+//! left, except `^`, which groups from the right as `2^3^2` is `2^9`.
+//! Whitespace tokens are skipped, and positions in errors are character
+//! offsets into the expression.
+//!
+//! `VAR <name> = <expr>`, repeated, then `RETURN <expr>` can stand wherever an
+//! expression can.
+//!
+//! An expression can nest at most `MAX_DEPTH` levels deep, each bracket, call,
+//! sign and operator a level, so that neither parsing nor evaluating it can
+//! run out of stack.
+use crate::error::DaxError;
+use crate::types::Value;
+use dax_macro_impl::syntax::{self, Syntax};
+use dax_macro_impl::{tokenize_spanned, DaxToken};
+use std::ops::Range;
+
+pub use dax_macro_impl::syntax::{BinaryOperator, UnaryOperator, MAX_DEPTH};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DaxExpr {
+    Literal(Value),
+    /// `[Column]`, or `Table[Column]` with the table part kept in `table`. With
+    /// a single table in play the table part must be its name, if it has one.
+    ColumnRef {
+        table: Option<String>,
+        name: String,
+        /// Character offsets of the reference in the expression
+        span: Range<usize>,
+    },
+    /// A bare name: a table, a keyword argument such as `DESC`, or a variable
+    Variable(String),
+    FunctionCall {
+        name: String,
+        args: Vec<DaxExpr>,
+        /// Character offsets of the whole call, from its name to its `)`
+        span: Range<usize>,
+    },
+    BinaryOp {
+        op: BinaryOperator,
+        left: Box<DaxExpr>,
+        right: Box<DaxExpr>,
+    },
+    UnaryOp {
+        op: UnaryOperator,
+        operand: Box<DaxExpr>,
+    },
+    /// `{<value>, ...}`: a one-column table
+    TableConstructor(Vec<DaxExpr>),
+    /// `VAR <name> = <expr> ... RETURN <expr>`: each variable is evaluated
+    /// once where the block is, with the ones before it in view, then the body
+    VarBlock {
+        variables: Vec<(String, DaxExpr)>,
+        body: Box<DaxExpr>,
+    },
+}
+
+impl DaxExpr {
+    /// Every column the expression refers to, in order of first appearance
+    pub fn columns(&self) -> Vec<String> {
+        let mut columns = Vec::new();
+        self.collect_columns(&mut columns);
+        columns
+    }
+
+    /// The first node, in reading order, for which `predicate` holds
+    pub fn find(&self, predicate: &impl Fn(&DaxExpr) -> bool) -> Option<&DaxExpr> {
+        if predicate(self) {
+            return Some(self);
+        }
+        match self {
+            DaxExpr::FunctionCall { args, .. } | DaxExpr::TableConstructor(args) => {
+                args.iter().find_map(|arg| arg.find(predicate))
+            }
+            DaxExpr::BinaryOp { left, right, .. } => {
+                left.find(predicate).or_else(|| right.find(predicate))
+            }
+            DaxExpr::UnaryOp { operand, .. } => operand.find(predicate),
+            DaxExpr::VarBlock { variables, body } => variables
+                .iter()
+                .find_map(|(_, expr)| expr.find(predicate))
+                .or_else(|| body.find(predicate)),
+            DaxExpr::Literal(_) | DaxExpr::ColumnRef { .. } | DaxExpr::Variable(_) => None,
+        }
+    }
+
+    fn collect_columns(&self, columns: &mut Vec<String>) {
+        match self {
+            DaxExpr::ColumnRef { name, .. } => {
+                if !columns.contains(name) {
+                    columns.push(name.clone());
+                }
+            }
+            DaxExpr::FunctionCall { args, .. } | DaxExpr::TableConstructor(args) => {
+                for arg in args {
+                    arg.collect_columns(columns);
+                }
+            }
+            DaxExpr::BinaryOp { left, right, .. } => {
+                left.collect_columns(columns);
+                right.collect_columns(columns);
+            }
+            DaxExpr::UnaryOp { operand, .. } => operand.collect_columns(columns),
+            DaxExpr::VarBlock { variables, body } => {
+                for (_, expr) in variables {
+                    expr.collect_columns(columns);
+                }
+                body.collect_columns(columns);
+            }
+            DaxExpr::Literal(_) | DaxExpr::Variable(_) => {}
+        }
+    }
+}
+
+/// Split an expression into tokens. A character that can't start a token, a
+/// malformed number, or an unterminated column name, string, quoted table name
+/// or block comment is a `DaxError::ParseError` naming where it starts.
+pub fn tokenize(expression: &str) -> Result<Vec<DaxToken>, DaxError> {
+    let tokens = tokenize_spanned(expression).map_err(DaxError::ParseError)?;
+    Ok(tokens.into_iter().map(|(token, _)| token).collect())
+}
+
+/// Tokenize and parse an expression. An unterminated string literal is a
+/// `DaxError::ParseError` naming where it starts.
+pub fn parse_str(expression: &str) -> Result<DaxExpr, DaxError> {
+    parse(&tokenize_spanned(expression).map_err(DaxError::ParseError)?)
+}
+
+/// Parse a token stream, with each token's character offsets in the
+/// expression, into a single expression. Unbalanced brackets, misplaced tokens,
+/// anything left over after the expression and nesting more than `MAX_DEPTH`
+/// levels deep are `DaxError::ParseError`s naming the offending token's
+/// position.
+pub fn parse(tokens: &[(DaxToken, Range<usize>)]) -> Result<DaxExpr, DaxError> {
+    match syntax::parse(tokens) {
+        Ok(tree) => Ok(DaxExpr::from(tree)),
+        Err((message, _)) => Err(DaxError::ParseError(message)),
+    }
+}
+
+impl From<Syntax> for DaxExpr {
+    fn from(tree: Syntax) -> Self {
+        let all = |items: Vec<Syntax>| items.into_iter().map(DaxExpr::from).collect();
+        match tree {
+            Syntax::Number(n) => DaxExpr::Literal(Value::Number(n)),
+            Syntax::Text(text) => DaxExpr::Literal(Value::Text(text)),
+            Syntax::Column { table, name, span } => DaxExpr::ColumnRef { table, name, span },
+            Syntax::Name(name) => DaxExpr::Variable(name),
+            Syntax::Call { name, args, span } => DaxExpr::FunctionCall {
+                name,
+                args: all(args),
+                span,
+            },
+            Syntax::Binary { op, left, right } => binary(op, (*left).into(), (*right).into()),
+            Syntax::Unary { op, operand } => DaxExpr::UnaryOp {
+                op,
+                operand: Box::new((*operand).into()),
+            },
+            Syntax::Table(items) => DaxExpr::TableConstructor(all(items)),
+            Syntax::VarBlock { variables, body } => DaxExpr::VarBlock {
+                variables: variables
+                    .into_iter()
+                    .map(|(name, expr)| (name, expr.into()))
+                    .collect(),
+                body: Box::new((*body).into()),
+            },
+        }
+    }
+}
+
+fn binary(op: BinaryOperator, left: DaxExpr, right: DaxExpr) -> DaxExpr {
+    DaxExpr::BinaryOp {
+        op,
+        left: Box::new(left),
+        right: Box::new(right),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_calls_and_precedence() {
+        let expr = parse_str("DIVIDE(SUM([Sales]), COUNTROWS(Sales)) + 2 * 3").unwrap();
+        match expr {
+            DaxExpr::BinaryOp {
+                op: BinaryOperator::Add,
+                left,
+                right,
+            } => {
+                assert!(
+                    matches!(*left, DaxExpr::FunctionCall { ref name, ref args, .. }
+                    if name == "DIVIDE" && args.len() == 2)
+                );
+                assert!(matches!(
+                    *right,
+                    DaxExpr::BinaryOp {
+                        op: BinaryOperator::Multiply,
+                        ..
+                    }
+                ));
+            }
+            other => panic!("Expected an addition, got {:?}", other),
+        }
+        assert_eq!(
+            parse_str("ISEMPTY(FILTER(Sales, [Quantity] >= 15))")
+                .unwrap()
+                .columns(),
+            vec!["Quantity".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_signed_numbers() {
+        assert_eq!(
+            parse_str("-3.5e2").unwrap(),
+            DaxExpr::Literal(Value::Number(-350.0))
+        );
+        assert_eq!(
+            parse_str("2.5E-1 + 1e+1").unwrap(),
+            binary(
+                BinaryOperator::Add,
+                DaxExpr::Literal(Value::Number(0.25)),
+                DaxExpr::Literal(Value::Number(10.0))
+            )
+        );
+        // `-` after a value subtracts; the second one signs the literal
+        assert_eq!(
+            parse_str("5 - -3").unwrap(),
+            binary(
+                BinaryOperator::Subtract,
+                DaxExpr::Literal(Value::Number(5.0)),
+                DaxExpr::Literal(Value::Number(-3.0))
+            )
+        );
+        assert!(matches!(
+            parse_str("-[Cost]").unwrap(),
+            DaxExpr::UnaryOp {
+                op: UnaryOperator::Minus,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_percent_and_right_grouped_power() {
+        let number = |n| DaxExpr::Literal(Value::Number(n));
+        assert_eq!(parse_str("-50%").unwrap(), number(-0.5));
+        assert_eq!(
+            parse_str("2^3^2").unwrap(),
+            binary(
+                BinaryOperator::Power,
+                number(2.0),
+                binary(BinaryOperator::Power, number(3.0), number(2.0))
+            )
+        );
+        assert_eq!(
+            parse_str("[Rate]%").unwrap(),
+            binary(
+                BinaryOperator::Divide,
+                parse_str("[Rate]").unwrap(),
+                number(100.0)
+            )
+        );
+    }
+
+    #[test]
+    fn test_errors_name_the_position() {
+        for (expression, position) in [
+            ("AVERAGE([Quantity]", "character 18"),
+            ("SUM([Sales]))", "character 12"),
+            ("SUM([Sales]) [Quantity]", "character 13"),
+            ("SUM(,)", "character 4"),
+            ("(1 + 2", "character 6"),
+        ] {
+            match parse_str(expression) {
+                Err(DaxError::ParseError(message)) => {
+                    assert!(message.ends_with(position), "{}: {}", expression, message)
+                }
+                other => panic!("Expected a parse error for {}, got {:?}", expression, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_nesting_is_capped() {
+        let n = MAX_DEPTH;
+        let nested = |open: &str, inner: &str, close: &str, levels: usize| {
+            format!("{}{}{}", open.repeat(levels), inner, close.repeat(levels))
+        };
+        // Each shape at the cap parses, and a level more is an error naming
+        // where the level too many starts
+        let shapes = [
+            (nested("(", "1", ")", n), nested("(", "1", ")", n + 1), n),
+            (
+                nested("ABS(", "1", ")", n),
+                nested("ABS(", "1", ")", n + 1),
+                4 * n,
+            ),
+            (nested("{", "1", "}", n), nested("{", "1", "}", n + 1), n),
+            (nested("-", "1", "", n), nested("-", "1", "", n + 1), n),
+            (
+                nested("", "1", "+1", n),
+                nested("", "1", "+1", n + 1),
+                1 + 2 * n,
+            ),
+            (
+                nested("1^", "1", "", n),
+                nested("1^", "1", "", n + 1),
+                1 + 2 * n,
+            ),
+            (
+                nested("", "[A]", "%", n),
+                nested("", "[A]", "%", n + 1),
+                3 + n,
+            ),
+            (
+                nested("VAR x = ", "1", " RETURN x", n),
+                nested("VAR x = ", "1", " RETURN x", n + 1),
+                8 * n,
+            ),
+        ];
+        for (deepest, too_deep, at) in shapes {
+            assert!(parse_str(&deepest).is_ok(), "{}", &deepest[..20]);
+            match parse_str(&too_deep) {
+                Err(DaxError::ParseError(message)) => assert_eq!(
+                    message,
+                    format!(
+                        "The expression nests more than 256 levels deep at character {}",
+                        at
+                    )
+                ),
+                other => panic!("Expected a parse error, got {:?}", other),
+            }
+        }
+        // Bracketing a whole chain is no deeper than the chain
+        assert!(parse_str(&format!("({})", nested("", "1", "+1", n - 1))).is_ok());
+    }
+
+    #[test]
+    fn test_malformed_expressions_are_parse_errors() {
+        let malformed = [
+            "",
+            "   \t\n",
+            "AVERAGE([Quantity]",
+            "SUM([Sales]))",
+            "SUM([Sales]",
+            "SUM([Sales] [Quantity])",
+            "SUM([Sales]) [Quantity]",
+            "1 2",
+            "1 + ",
+            "* 2",
+            "1 +* 2",
+            "SUM(,[Sales])",
+            "SUM([Sales],)",
+            "DIVIDE(1,,2)",
+            "DIVIDE(1 2)",
+            ",",
+            "()",
+            "(1 + 2",
+            "1 + 2)",
+            "{1, 2",
+            "{1,, 2}",
+            "{1 2}",
+            "[Sales",
+            "Sales[Amount",
+            "'Sales Data'[Amount",
+            "\"unclosed",
+            "'Sales",
+            "SUM([Sales]) $ 2",
+            "SUM([Sales]) && TRUE",
+            "1.2.3 + 1",
+            "SUM([Sales]) ; 1",
+            "1 /* never closed",
+            "SUM\t(\n[Sales]\r\n",
+            "= 1",
+            "1 = = 1",
+        ];
+        // Nesting far past MAX_DEPTH, closed or not
+        let deep = 100_000;
+        let nested = [
+            "(".repeat(deep),
+            format!("{}1{}", "(".repeat(deep), ")".repeat(deep)),
+            format!("{}[Sales]{}", "SUM(".repeat(deep), ")".repeat(deep)),
+            format!("{}1{}", "{".repeat(deep), "}".repeat(deep)),
+            format!("{}1", "-".repeat(deep)),
+            format!("1{}", " ^ 1".repeat(deep)),
+            format!("{}1", "VAR x = 1 RETURN ".repeat(deep)),
+        ];
+        let malformed = malformed
+            .iter()
+            .copied()
+            .chain(nested.iter().map(String::as_str));
+        for expression in malformed {
+            assert!(
+                matches!(parse_str(expression), Err(DaxError::ParseError(_))),
+                "Expected a parse error for {:?}, got {:?}",
+                expression,
+                parse_str(expression)
+            );
+        }
+        // Tabs and line breaks are just whitespace
+        assert!(parse_str("SUM(\t[Sales]\n)\r\n* 2").is_ok());
+    }
+}
@@ -0,0 +1,172 @@
+// pivot.rs
+
+//! Reshaping a table between long and wide forms.
+//!
+//! `Table::pivot` turns the values of one column into columns of their own,
+//! with a row per value of an index column, and `Table::unpivot` melts
+//! columns back into rows of name and value.
+use crate::error::DaxError;
+use crate::group::{aggregate, Agg};
+use crate::types::Value;
+use crate::Table;
+use std::collections::{HashMap, HashSet};
+
+impl Table {
+    /// A wide table with a row per value of `index` and a column per value of
+    /// `columns`, each cell the `agg` of `values` over the rows with that pair.
+    ///
+    /// The rows and new columns come in the order their values first appear.
+    /// Each new column is named after its value as it displays, with Null as
+    /// `(Blank)`, and a name already taken gets ` (2)`, ` (3)` and so on. A
+    /// pair with no rows at all is Null.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dax_rust::group::Agg;
+    /// use dax_rust::table::Table;
+    /// use dax_rust::types::Value;
+    ///
+    /// let mut table = Table::new();
+    /// table.add_column("Region".to_string(), ["North", "North", "South"].map(Value::from).to_vec()).unwrap();
+    /// table.add_column("Year".to_string(), vec![Value::Integer(2023), Value::Integer(2024), Value::Integer(2024)]).unwrap();
+    /// table.add_column("Sales".to_string(), vec![Value::Integer(10), Value::Integer(20), Value::Integer(5)]).unwrap();
+    ///
+    /// let wide = table.pivot("Region", "Year", "Sales", Agg::Sum).unwrap();
+    /// assert_eq!(wide.column_names(), ["Region", "2023", "2024"]);
+    /// assert_eq!(wide.get_column("2023").unwrap(), [Value::Integer(10), Value::Null]);
+    /// ```
+    pub fn pivot(
+        &self,
+        index: &str,
+        columns: &str,
+        values: &str,
+        agg: Agg,
+    ) -> Result<Table, DaxError> {
+        let lookup = |name| {
+            self.get_column(name)
+                .ok_or_else(|| self.unknown_column(name))
+        };
+        let (keys, headers, cells) = (lookup(index)?, lookup(columns)?, lookup(values)?);
+
+        let mut rows: Vec<Value> = Vec::new();
+        let mut row_positions: HashMap<Value, usize> = HashMap::new();
+        let mut pivoted: Vec<Value> = Vec::new();
+        let mut column_positions: HashMap<Value, usize> = HashMap::new();
+        let mut groups: HashMap<(usize, usize), Vec<Value>> = HashMap::new();
+        for ((key, header), cell) in keys.iter().zip(headers).zip(cells) {
+            let row = *row_positions.entry(key).or_insert_with_key(|key| {
+                rows.push(key.clone());
+                rows.len() - 1
+            });
+            let column = *column_positions.entry(header).or_insert_with_key(|header| {
+                pivoted.push(header.clone());
+                pivoted.len() - 1
+            });
+            groups.entry((row, column)).or_default().push(cell);
+        }
+
+        let mut table = Table::new();
+        let row_count = rows.len();
+        table.add_column(index.to_string(), rows)?;
+        let mut taken: HashSet<String> = HashSet::from([index.to_string()]);
+        for (column, header) in pivoted.into_iter().enumerate() {
+            let results = (0..row_count)
+                .map(|row| match groups.get(&(row, column)) {
+                    Some(group) => aggregate(agg, group.iter()),
+                    None => Value::Null,
+                })
+                .collect();
+            table.add_column(column_name(&header, &mut taken), results)?;
+        }
+        Ok(table)
+    }
+
+    /// A long table with a row for each row and each of `value_cols`: the
+    /// `id_cols` values, then the column's name under `var_name` and its value
+    /// under `value_name`. No `value_cols` at all means every column that
+    /// isn't an id column.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dax_rust::table::Table;
+    /// use dax_rust::types::Value;
+    ///
+    /// let mut table = Table::new();
+    /// table.add_column("Region".to_string(), vec![Value::from("North")]).unwrap();
+    /// table.add_column("Q1".to_string(), vec![Value::Integer(10)]).unwrap();
+    /// table.add_column("Q2".to_string(), vec![Value::Integer(20)]).unwrap();
+    ///
+    /// let long = table.unpivot(&["Region"], &[], "Quarter", "Sales").unwrap();
+    /// assert_eq!(long.shape(), (2, 3));
+    /// assert_eq!(long.get_column("Quarter").unwrap(), [Value::from("Q1"), Value::from("Q2")]);
+    /// ```
+    pub fn unpivot(
+        &self,
+        id_cols: &[&str],
+        value_cols: &[&str],
+        var_name: &str,
+        value_name: &str,
+    ) -> Result<Table, DaxError> {
+        let value_cols: Vec<&str> = if value_cols.is_empty() {
+            self.column_names()
+                .iter()
+                .map(String::as_str)
+                .filter(|name| !id_cols.contains(name))
+                .collect()
+        } else {
+            value_cols.to_vec()
+        };
+        for name in id_cols.iter().chain(&value_cols) {
+            if !self.has_column(name) {
+                return Err(self.unknown_column(name));
+            }
+        }
+        if var_name == value_name || id_cols.contains(&var_name) || id_cols.contains(&value_name) {
+            return Err(format!(
+                "Cannot unpivot into {} and {}: the names must differ from each other and from the id columns",
+                var_name, value_name
+            )
+            .into());
+        }
+
+        let rows = self.num_rows() * value_cols.len();
+        let mut ids: Vec<Vec<Value>> = vec![Vec::with_capacity(rows); id_cols.len()];
+        let mut names = Vec::with_capacity(rows);
+        let mut values = Vec::with_capacity(rows);
+        for row in 0..self.num_rows() {
+            for name in &value_cols {
+                for (id, column) in ids.iter_mut().zip(id_cols) {
+                    id.push(self.cell(column, row).unwrap_or(Value::Null));
+                }
+                names.push(Value::from(*name));
+                values.push(self.cell(name, row).unwrap_or(Value::Null));
+            }
+        }
+
+        let mut table = Table::new();
+        for (id, column) in ids.into_iter().zip(id_cols) {
+            table.add_column(column.to_string(), id)?;
+        }
+        table.add_column(var_name.to_string(), names)?;
+        table.add_column(value_name.to_string(), values)?;
+        Ok(table)
+    }
+}
+
+// The name for a pivoted value's column, numbered if it's taken
+fn column_name(value: &Value, taken: &mut HashSet<String>) -> String {
+    let base = match value {
+        Value::Null => "(Blank)".to_string(),
+        other => other.to_string(),
+    };
+    let mut name = base.clone();
+    let mut n = 1;
+    while taken.contains(&name) {
+        n += 1;
+        name = format!("{} ({})", base, n);
+    }
+    taken.insert(name.clone());
+    name
+}
@@ -0,0 +1,368 @@
+// query.rs
+
+//! DEFINE / EVALUATE queries, as run in DAX Studio.
+//!
+//! A query is an optional `DEFINE` block of `MEASURE Table[Name] = <expr>` and
+//! `VAR <name> = <expr>` definitions, then `EVALUATE <table expression>` and an
+//! optional `ORDER BY <expr> [ASC|DESC], ...`. The keywords split the query
+//! into sections wherever they appear outside brackets, and each section's
+//! expression is parsed on its own, keeping character offsets into the query.
+//! A `VAR` that starts a `VAR ... RETURN` block in an expression, or comes
+//! inside one, belongs to the expression.
+//!
+//! Measures defined in the query are added to the table's own for the length
+//! of the query. A variable can refer to the variables before it and is
+//! evaluated without filters, wherever it's used.
+use crate::context::{EvalContext, FilterContext};
+use crate::error::DaxError;
+use crate::parser::{self, DaxExpr};
+use crate::table::{compare_results, locate, DaxResult, Measure};
+use crate::Table;
+use dax_macro_impl::{tokenize_spanned, DaxToken};
+use std::cmp::Ordering;
+use std::ops::Range;
+
+type Token = (DaxToken, Range<usize>);
+
+// A keyword, where it starts, and the tokens after it
+type Section<'a> = (Keyword, usize, &'a [Token]);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Keyword {
+    Define,
+    Measure,
+    Var,
+    Evaluate,
+    OrderBy,
+}
+
+struct Query {
+    measures: Vec<(String, Measure)>,
+    variables: Vec<(String, DaxExpr)>,
+    evaluate: DaxExpr,
+    order_by: Vec<(DaxExpr, bool)>,
+}
+
+impl Table {
+    /// Run a DEFINE / EVALUATE query, returning the table it evaluates to.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dax_rust::table::Table;
+    /// use dax_rust::types::Value;
+    ///
+    /// let mut table = Table::new();
+    /// table.add_column("Product".to_string(), vec!["Pear".into(), "Apple".into(), "Pear".into()]).unwrap();
+    /// table.add_column("Sales".to_string(), vec![Value::Number(1.0), Value::Number(2.0), Value::Number(3.0)]).unwrap();
+    ///
+    /// let result = table
+    ///     .execute_query(
+    ///         "DEFINE MEASURE Sales[Total] = SUM([Sales])
+    ///          EVALUATE SUMMARIZE(Sales, [Product], \"T\", [Total])
+    ///          ORDER BY [Product]",
+    ///     )
+    ///     .unwrap();
+    /// assert_eq!(result.get_column("T").unwrap(), [Value::Number(2.0), Value::Number(4.0)]);
+    /// ```
+    pub fn execute_query(&self, query: &str) -> Result<Table, DaxError> {
+        let parsed = parse_query(query)?;
+        let mut measures = self.measures().clone();
+        for (name, measure) in parsed.measures {
+            measures.insert(name, measure);
+        }
+        let ctx = EvalContext {
+            measures: Some(&measures),
+            variables: Some(&parsed.variables),
+            ..self.root_context()
+        };
+        let keys = parsed.order_by.iter().map(|(expr, _)| expr);
+        for expr in parsed.variables.iter().map(|(_, expr)| expr).chain(keys) {
+            self.check_table_names(expr)
+                .map_err(|e| locate(e, expr, query))?;
+        }
+        let table = match self
+            .check_table_names(&parsed.evaluate)
+            .and_then(|()| self.evaluate_expr(&parsed.evaluate, &ctx))
+        {
+            Ok(DaxResult::Table(table)) => table,
+            Ok(_) => return Err("EVALUATE needs a table expression".into()),
+            Err(e) => return Err(locate(e, &parsed.evaluate, query)),
+        };
+        if parsed.order_by.is_empty() {
+            return Ok(table);
+        }
+        let rows = table.ordered_rows(&parsed.order_by, &ctx).map_err(|e| {
+            match parsed.order_by.first() {
+                Some((expr, _)) => locate(e, expr, query),
+                None => e,
+            }
+        })?;
+        Ok(table.select_rows(&rows))
+    }
+
+    // A variable defined by a query, evaluated with no filters and only the
+    // variables before it in view. None if no variable has the name.
+    pub(crate) fn evaluate_variable(
+        &self,
+        name: &str,
+        ctx: &EvalContext,
+    ) -> Option<Result<DaxResult, DaxError>> {
+        let variables = ctx.variables?;
+        let index = variables
+            .iter()
+            .rposition(|(defined, _)| defined.eq_ignore_ascii_case(name))?;
+        let inner = EvalContext {
+            rows: Vec::new(),
+            filters: FilterContext::default(),
+            variables: Some(&variables[..index]),
+            locals: Vec::new(),
+            ..ctx.clone()
+        };
+        Some(self.evaluate_expr(&variables[index].1, &inner))
+    }
+
+    // Rows in the order of several sort keys, each ascending or descending.
+    // Rows that tie on every key keep their order.
+    fn ordered_rows(
+        &self,
+        order_by: &[(DaxExpr, bool)],
+        ctx: &EvalContext,
+    ) -> Result<Vec<usize>, DaxError> {
+        let mut keyed = Vec::with_capacity(self.num_rows());
+        for row in 0..self.num_rows() {
+            let row_ctx = ctx.with_row(self, row);
+            let mut keys = Vec::with_capacity(order_by.len());
+            for (expr, _) in order_by {
+                keys.push(self.evaluate_expr(expr, &row_ctx)?);
+            }
+            keyed.push((keys, row));
+        }
+        keyed.sort_by(|(a, _), (b, _)| {
+            a.iter()
+                .zip(b)
+                .zip(order_by)
+                .map(|((a, b), (_, descending))| {
+                    let ordering = compare_results(a, b);
+                    if *descending {
+                        ordering.reverse()
+                    } else {
+                        ordering
+                    }
+                })
+                .find(|ordering| *ordering != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        });
+        Ok(keyed.into_iter().map(|(_, row)| row).collect())
+    }
+}
+
+fn parse_query(query: &str) -> Result<Query, DaxError> {
+    let tokens: Vec<Token> = tokenize_spanned(query)
+        .map_err(DaxError::ParseError)?
+        .into_iter()
+        .filter(|(token, _)| !matches!(token, DaxToken::Whitespace))
+        .collect();
+
+    let mut parsed = Query {
+        measures: Vec::new(),
+        variables: Vec::new(),
+        evaluate: DaxExpr::Variable(String::new()),
+        order_by: Vec::new(),
+    };
+    let mut seen = Vec::new();
+    for (keyword, start, body) in sections(&tokens)? {
+        let error =
+            |message: &str| DaxError::ParseError(format!("{} at character {}", message, start));
+        match keyword {
+            Keyword::Define if !seen.is_empty() => {
+                return Err(error("DEFINE must start the query"))
+            }
+            Keyword::Define if !body.is_empty() => {
+                return Err(error("DEFINE must be followed by MEASURE or VAR"))
+            }
+            Keyword::Measure | Keyword::Var if seen.first() != Some(&Keyword::Define) => {
+                return Err(error("Definitions must come inside a DEFINE block"))
+            }
+            Keyword::Measure | Keyword::Var if seen.contains(&Keyword::Evaluate) => {
+                return Err(error("Definitions must come before EVALUATE"))
+            }
+            Keyword::Evaluate if seen.contains(&Keyword::Evaluate) => {
+                return Err(error("A query can only EVALUATE one expression"))
+            }
+            Keyword::OrderBy if seen.last() != Some(&Keyword::Evaluate) => {
+                return Err(error("ORDER BY must follow EVALUATE"))
+            }
+            Keyword::Define => {}
+            Keyword::Measure => {
+                let (name, expr) = definition(body, start, "MEASURE")?;
+                parsed.measures.push((name, Measure::new(query, expr)));
+            }
+            Keyword::Var => parsed.variables.push(definition(body, start, "VAR")?),
+            Keyword::Evaluate => parsed.evaluate = expression(body, start, "EVALUATE")?,
+            Keyword::OrderBy => {
+                for item in top_level_items(body) {
+                    parsed.order_by.push(sort_key(item, start)?);
+                }
+            }
+        }
+        seen.push(keyword);
+    }
+    if !seen.contains(&Keyword::Evaluate) {
+        return Err(DaxError::ParseError(
+            "A query needs an EVALUATE".to_string(),
+        ));
+    }
+    Ok(parsed)
+}
+
+// The query split at its keywords: each keyword, where it starts, and the
+// tokens up to the next one. Keywords inside brackets belong to the expression,
+// and so does a VAR where a value is expected, which starts a VAR / RETURN
+// block, or one inside such a block before its RETURN.
+fn sections(tokens: &[Token]) -> Result<Vec<Section<'_>>, DaxError> {
+    let mut found = Vec::new();
+    let mut depth = 0usize;
+    // VAR / RETURN blocks outside brackets still waiting for their RETURN
+    let mut blocks = 0usize;
+    let mut i = 0;
+    while i < tokens.len() {
+        let (token, span) = &tokens[i];
+        match token {
+            DaxToken::ParenOpen | DaxToken::BraceOpen => depth += 1,
+            DaxToken::ParenClose | DaxToken::BraceClose => depth = depth.saturating_sub(1),
+            DaxToken::Function(word) if depth == 0 => {
+                let keyword = match word.to_ascii_uppercase().as_str() {
+                    "DEFINE" => Some((Keyword::Define, 1)),
+                    "MEASURE" => Some((Keyword::Measure, 1)),
+                    "VAR" if i > 0 && expects_value(&tokens[i - 1].0) => {
+                        blocks += 1;
+                        None
+                    }
+                    "VAR" if blocks > 0 => None,
+                    "RETURN" => {
+                        blocks = blocks.saturating_sub(1);
+                        None
+                    }
+                    "VAR" => Some((Keyword::Var, 1)),
+                    "EVALUATE" => Some((Keyword::Evaluate, 1)),
+                    "ORDER" => match tokens.get(i + 1) {
+                        Some((DaxToken::Function(by), _)) if by.eq_ignore_ascii_case("BY") => {
+                            Some((Keyword::OrderBy, 2))
+                        }
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                if let Some((keyword, length)) = keyword {
+                    found.push((keyword, span.start, i, i + length));
+                    i += length;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    match found.first() {
+        Some((_, _, 0, _)) => {}
+        _ => {
+            let start = tokens.first().map_or(0, |(_, span)| span.start);
+            return Err(DaxError::ParseError(format!(
+                "A query must start with DEFINE or EVALUATE at character {}",
+                start
+            )));
+        }
+    }
+    Ok(found
+        .iter()
+        .enumerate()
+        .map(|(n, &(keyword, start, _, body_start))| {
+            let body_end = found.get(n + 1).map_or(tokens.len(), |next| next.2);
+            (keyword, start, &tokens[body_start..body_end])
+        })
+        .collect())
+}
+
+// Whether a value must come after the token, rather than the token ending one
+fn expects_value(token: &DaxToken) -> bool {
+    match token {
+        DaxToken::Comparison(_) => true,
+        DaxToken::Operator(op) => *op != '%',
+        DaxToken::Function(word) => {
+            word.eq_ignore_ascii_case("RETURN") || word.eq_ignore_ascii_case("EVALUATE")
+        }
+        _ => false,
+    }
+}
+
+// `Table[Name] = <expr>` after MEASURE, or `<name> = <expr>` after VAR
+fn definition(body: &[Token], start: usize, keyword: &str) -> Result<(String, DaxExpr), DaxError> {
+    let name = match (keyword, body.first()) {
+        ("MEASURE", Some((DaxToken::QualifiedColumn(_, name) | DaxToken::Column(name), _))) => {
+            name.clone()
+        }
+        ("VAR", Some((DaxToken::Function(name), _))) => name.clone(),
+        _ => {
+            return Err(DaxError::ParseError(format!(
+                "{} must be followed by a name at character {}",
+                keyword, start
+            )))
+        }
+    };
+    match body.get(1) {
+        Some((DaxToken::Comparison("="), _)) => {}
+        _ => {
+            return Err(DaxError::ParseError(format!(
+                "Expected '=' after {} {} at character {}",
+                keyword, name, start
+            )))
+        }
+    }
+    Ok((name, expression(&body[2..], start, keyword)?))
+}
+
+fn expression(body: &[Token], start: usize, keyword: &str) -> Result<DaxExpr, DaxError> {
+    if body.is_empty() {
+        return Err(DaxError::ParseError(format!(
+            "{} is missing its expression at character {}",
+            keyword, start
+        )));
+    }
+    parser::parse(body)
+}
+
+// Tokens split at the commas outside brackets
+fn top_level_items(tokens: &[Token]) -> Vec<&[Token]> {
+    let mut items = Vec::new();
+    let mut depth = 0usize;
+    let mut item_start = 0;
+    for (i, (token, _)) in tokens.iter().enumerate() {
+        match token {
+            DaxToken::ParenOpen | DaxToken::BraceOpen => depth += 1,
+            DaxToken::ParenClose | DaxToken::BraceClose => depth = depth.saturating_sub(1),
+            DaxToken::Comma if depth == 0 => {
+                items.push(&tokens[item_start..i]);
+                item_start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&tokens[item_start..]);
+    items
+}
+
+// An ORDER BY expression, with ASC or DESC after it
+fn sort_key(item: &[Token], start: usize) -> Result<(DaxExpr, bool), DaxError> {
+    let (descending, item) = match item.split_last() {
+        Some(((DaxToken::Function(order), _), rest)) if order.eq_ignore_ascii_case("ASC") => {
+            (false, rest)
+        }
+        Some(((DaxToken::Function(order), _), rest)) if order.eq_ignore_ascii_case("DESC") => {
+            (true, rest)
+        }
+        _ => (false, item),
+    };
+    Ok((expression(item, start, "ORDER BY")?, descending))
+}
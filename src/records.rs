@@ -0,0 +1,589 @@
+// records.rs
+
+//! Tables from and to slices of plain Rust structs, behind the `serde` feature.
+//!
+//! Each struct is one row. A row is serialized field by field, so field names
+//! become column names and each field's value goes through a serializer that
+//! only accepts what fits in one cell: numbers, text, booleans, `Option`s of
+//! those (None is Null), and enums without data (the variant's name as text).
+//! Anything nested, such as a `Vec` or another struct, is an error naming the
+//! field. Reading rows back deserializes each struct from its row's cells.
+use crate::error::DaxError;
+use crate::table::Table;
+use crate::types::{currency_amount, Value};
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, Visitor};
+use serde::ser::{self, Impossible, Serialize, SerializeMap, SerializeStruct, Serializer};
+use std::collections::HashMap;
+use std::fmt;
+
+impl Table {
+    /// A table with one row per item, and a column for each field.
+    ///
+    /// Items may be structs or string-keyed maps. A field one item has and
+    /// another doesn't is Null where it's missing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dax_rust::{Table, Value};
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    /// struct Sale {
+    ///     product: String,
+    ///     amount: f64,
+    ///     qty: Option<u32>,
+    /// }
+    ///
+    /// let sales = vec![
+    ///     Sale { product: "Pear".to_string(), amount: 2.5, qty: Some(3) },
+    ///     Sale { product: "Plum".to_string(), amount: 1.0, qty: None },
+    /// ];
+    /// let table = Table::from_serializable(&sales).unwrap();
+    /// assert_eq!(table.get("qty", 1), Some(Value::Null));
+    /// assert_eq!(table.to_deserializable::<Sale>().unwrap(), sales);
+    /// ```
+    pub fn from_serializable<T: Serialize>(rows: &[T]) -> Result<Table, DaxError> {
+        let mut columns: Vec<(String, Vec<Value>)> = Vec::new();
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        for (index, row) in rows.iter().enumerate() {
+            let fields = row
+                .serialize(RowSerializer)
+                .map_err(|e| DaxError::from(format!("Row {}: {}", index, e)))?;
+            for (name, value) in fields {
+                let position = *positions.entry(name.clone()).or_insert_with(|| {
+                    columns.push((name, vec![Value::Null; index]));
+                    columns.len() - 1
+                });
+                let values = &mut columns[position].1;
+                if values.len() > index {
+                    return Err(
+                        format!("Row {}: {} appears twice", index, columns[position].0).into(),
+                    );
+                }
+                values.push(value);
+            }
+            for (_, values) in &mut columns {
+                values.resize(index + 1, Value::Null);
+            }
+        }
+
+        let mut table = Table::new();
+        for (name, values) in columns {
+            table.add_column(name, values)?;
+        }
+        Ok(table)
+    }
+
+    /// Each row as a `T`, its fields read from the columns of the same names.
+    ///
+    /// A Null cell reads as `None` for an `Option` field, and a column the
+    /// struct has no field for is ignored.
+    pub fn to_deserializable<T: DeserializeOwned>(&self) -> Result<Vec<T>, DaxError> {
+        (0..self.num_rows())
+            .map(|row| {
+                T::deserialize(RowDeserializer { table: self, row })
+                    .map_err(|e| DaxError::from(format!("Row {}: {}", row, e)))
+            })
+            .collect()
+    }
+}
+
+// What goes wrong converting a row, which the caller prefixes with its index
+#[derive(Debug)]
+struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+type Fields = Vec<(String, Value)>;
+
+// Serializes a whole row, which must be a struct or a map
+struct RowSerializer;
+
+fn not_a_row(kind: &str) -> Error {
+    Error(format!("a row must be a struct or a map, not {}", kind))
+}
+
+impl Serializer for RowSerializer {
+    type Ok = Fields;
+    type Error = Error;
+    type SerializeSeq = Impossible<Fields, Error>;
+    type SerializeTuple = Impossible<Fields, Error>;
+    type SerializeTupleStruct = Impossible<Fields, Error>;
+    type SerializeTupleVariant = Impossible<Fields, Error>;
+    type SerializeMap = RowFields;
+    type SerializeStruct = RowFields;
+    type SerializeStructVariant = Impossible<Fields, Error>;
+
+    fn serialize_bool(self, _: bool) -> Result<Fields, Error> {
+        Err(not_a_row("a boolean"))
+    }
+    fn serialize_i8(self, _: i8) -> Result<Fields, Error> {
+        Err(not_a_row("a number"))
+    }
+    fn serialize_i16(self, _: i16) -> Result<Fields, Error> {
+        Err(not_a_row("a number"))
+    }
+    fn serialize_i32(self, _: i32) -> Result<Fields, Error> {
+        Err(not_a_row("a number"))
+    }
+    fn serialize_i64(self, _: i64) -> Result<Fields, Error> {
+        Err(not_a_row("a number"))
+    }
+    fn serialize_u8(self, _: u8) -> Result<Fields, Error> {
+        Err(not_a_row("a number"))
+    }
+    fn serialize_u16(self, _: u16) -> Result<Fields, Error> {
+        Err(not_a_row("a number"))
+    }
+    fn serialize_u32(self, _: u32) -> Result<Fields, Error> {
+        Err(not_a_row("a number"))
+    }
+    fn serialize_u64(self, _: u64) -> Result<Fields, Error> {
+        Err(not_a_row("a number"))
+    }
+    fn serialize_f32(self, _: f32) -> Result<Fields, Error> {
+        Err(not_a_row("a number"))
+    }
+    fn serialize_f64(self, _: f64) -> Result<Fields, Error> {
+        Err(not_a_row("a number"))
+    }
+    fn serialize_char(self, _: char) -> Result<Fields, Error> {
+        Err(not_a_row("text"))
+    }
+    fn serialize_str(self, _: &str) -> Result<Fields, Error> {
+        Err(not_a_row("text"))
+    }
+    fn serialize_bytes(self, _: &[u8]) -> Result<Fields, Error> {
+        Err(not_a_row("bytes"))
+    }
+    fn serialize_none(self) -> Result<Fields, Error> {
+        Err(not_a_row("None"))
+    }
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Fields, Error> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<Fields, Error> {
+        Err(not_a_row("()"))
+    }
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Fields, Error> {
+        Err(not_a_row(name))
+    }
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<Fields, Error> {
+        Err(not_a_row(variant))
+    }
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<Fields, Error> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: &T,
+    ) -> Result<Fields, Error> {
+        Err(not_a_row(variant))
+    }
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Err(not_a_row("a sequence"))
+    }
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, Error> {
+        Err(not_a_row("a tuple"))
+    }
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        Err(not_a_row(name))
+    }
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(not_a_row(variant))
+    }
+    fn serialize_map(self, _: Option<usize>) -> Result<RowFields, Error> {
+        Ok(RowFields::default())
+    }
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<RowFields, Error> {
+        Ok(RowFields::default())
+    }
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(not_a_row(variant))
+    }
+}
+
+// The fields of a row so far, and a map key waiting for its value
+#[derive(Default)]
+struct RowFields {
+    fields: Fields,
+    key: Option<String>,
+}
+
+impl SerializeStruct for RowFields {
+    type Ok = Fields;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        let value = value.serialize(CellSerializer { field: key })?;
+        self.fields.push((key.to_string(), value));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Fields, Error> {
+        Ok(self.fields)
+    }
+}
+
+impl SerializeMap for RowFields {
+    type Ok = Fields;
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Error> {
+        match key.serialize(CellSerializer { field: "key" })? {
+            Value::Text(key) => {
+                self.key = Some(key);
+                Ok(())
+            }
+            other => Err(Error(format!("map keys must be text, not {:?}", other))),
+        }
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Error("a map value came before its key".to_string()))?;
+        let value = value.serialize(CellSerializer { field: &key })?;
+        self.fields.push((key, value));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Fields, Error> {
+        Ok(self.fields)
+    }
+}
+
+// Serializes one field's value into a cell
+struct CellSerializer<'a> {
+    field: &'a str,
+}
+
+impl CellSerializer<'_> {
+    fn unsupported(&self, kind: &str) -> Error {
+        Error(format!(
+            "field {} is {}, which doesn't fit in a table cell",
+            self.field, kind
+        ))
+    }
+}
+
+impl Serializer for CellSerializer<'_> {
+    type Ok = Value;
+    type Error = Error;
+    type SerializeSeq = Impossible<Value, Error>;
+    type SerializeTuple = Impossible<Value, Error>;
+    type SerializeTupleStruct = Impossible<Value, Error>;
+    type SerializeTupleVariant = Impossible<Value, Error>;
+    type SerializeMap = Impossible<Value, Error>;
+    type SerializeStruct = Impossible<Value, Error>;
+    type SerializeStructVariant = Impossible<Value, Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, Error> {
+        Ok(Value::Boolean(v))
+    }
+    fn serialize_i8(self, v: i8) -> Result<Value, Error> {
+        Ok(Value::Integer(v.into()))
+    }
+    fn serialize_i16(self, v: i16) -> Result<Value, Error> {
+        Ok(Value::Integer(v.into()))
+    }
+    fn serialize_i32(self, v: i32) -> Result<Value, Error> {
+        Ok(Value::Integer(v.into()))
+    }
+    fn serialize_i64(self, v: i64) -> Result<Value, Error> {
+        Ok(Value::Integer(v))
+    }
+    fn serialize_u8(self, v: u8) -> Result<Value, Error> {
+        Ok(Value::Integer(v.into()))
+    }
+    fn serialize_u16(self, v: u16) -> Result<Value, Error> {
+        Ok(Value::Integer(v.into()))
+    }
+    fn serialize_u32(self, v: u32) -> Result<Value, Error> {
+        Ok(Value::Integer(v.into()))
+    }
+    fn serialize_u64(self, v: u64) -> Result<Value, Error> {
+        Ok(i64::try_from(v).map_or(Value::Number(v as f64), Value::Integer))
+    }
+    fn serialize_f32(self, v: f32) -> Result<Value, Error> {
+        Ok(Value::Number(v.into()))
+    }
+    fn serialize_f64(self, v: f64) -> Result<Value, Error> {
+        Ok(Value::Number(v))
+    }
+    fn serialize_char(self, v: char) -> Result<Value, Error> {
+        Ok(Value::Text(v.to_string()))
+    }
+    fn serialize_str(self, v: &str) -> Result<Value, Error> {
+        Ok(Value::Text(v.to_string()))
+    }
+    fn serialize_bytes(self, _: &[u8]) -> Result<Value, Error> {
+        Err(self.unsupported("bytes"))
+    }
+    fn serialize_none(self) -> Result<Value, Error> {
+        Ok(Value::Null)
+    }
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Value, Error> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<Value, Error> {
+        Ok(Value::Null)
+    }
+    fn serialize_unit_struct(self, _: &'static str) -> Result<Value, Error> {
+        Ok(Value::Null)
+    }
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<Value, Error> {
+        Ok(Value::Text(variant.to_string()))
+    }
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<Value, Error> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: &T,
+    ) -> Result<Value, Error> {
+        Err(self.unsupported(&format!("the enum variant {} with data", variant)))
+    }
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Err(self.unsupported("a sequence"))
+    }
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, Error> {
+        Err(self.unsupported("a tuple"))
+    }
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        Err(self.unsupported(&format!("the tuple struct {}", name)))
+    }
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(self.unsupported(&format!("the enum variant {} with data", variant)))
+    }
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Err(self.unsupported("a map"))
+    }
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        Err(self.unsupported(&format!("the struct {}", name)))
+    }
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(self.unsupported(&format!("the enum variant {} with data", variant)))
+    }
+}
+
+// Deserializes a row as a map of column names to cells
+struct RowDeserializer<'a> {
+    table: &'a Table,
+    row: usize,
+}
+
+impl<'de> de::Deserializer<'de> for RowDeserializer<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_map(RowAccess {
+            table: self.table,
+            row: self.row,
+            column: 0,
+        })
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+struct RowAccess<'a> {
+    table: &'a Table,
+    row: usize,
+    column: usize,
+}
+
+impl<'de> MapAccess<'de> for RowAccess<'_> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.table.column_names().get(self.column) {
+            Some(name) => seed
+                .deserialize(name.as_str().into_deserializer())
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let name = &self.table.column_names()[self.column];
+        self.column += 1;
+        let value = self.table.get(name, self.row).unwrap_or(Value::Null);
+        seed.deserialize(CellDeserializer { value: &value })
+            .map_err(|e| Error(format!("field {}: {}", name, e)))
+    }
+}
+
+// Deserializes one cell as whichever Rust type the field has
+struct CellDeserializer<'a> {
+    value: &'a Value,
+}
+
+impl CellDeserializer<'_> {
+    // A whole Number, for fields of integer types
+    fn integer(&self) -> Option<i64> {
+        match self.value {
+            Value::Number(n) if n.fract() == 0.0 && n.abs() < i64::MAX as f64 => Some(*n as i64),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! deserialize_integer {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                match self.integer() {
+                    Some(i) => visitor.visit_i64(i),
+                    None => self.deserialize_any(visitor),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for CellDeserializer<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Value::Number(n) => visitor.visit_f64(*n),
+            Value::Integer(i) => visitor.visit_i64(*i),
+            Value::Currency(units) => visitor.visit_f64(currency_amount(*units)),
+            Value::Text(s) => visitor.visit_str(s),
+            Value::Boolean(b) => visitor.visit_bool(*b),
+            Value::DateTime(d) => {
+                visitor.visit_string(d.format("%Y-%m-%dT%H:%M:%S%.f").to_string())
+            }
+            Value::Null => visitor.visit_unit(),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.value {
+            Value::Text(variant) => visitor.visit_enum(variant.as_str().into_deserializer()),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    deserialize_integer! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i128 u128 f32 f64 char str string bytes byte_buf unit unit_struct
+        seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
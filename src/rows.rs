@@ -0,0 +1,280 @@
+// rows.rs
+
+//! Row-by-row access to a table, for working with its data in plain Rust.
+//!
+//! `Table::rows` borrows the table and yields a `Row` per row, which looks up
+//! its cells by column name, and `Table::filter_rows` keeps the rows a closure
+//! accepts, with `distinct_rows` and `drop_nulls` for the usual cleaning and
+//! `head`, `tail`, `slice` and `sample` for a part of the rows.
+//! `Table::into_rows` consumes the table and yields
+//! each row's values by column name, ready to go back into `Table::from_rows`.
+//! `IntoTable` and `FromTable` go between a table and a struct per row.
+use crate::error::DaxError;
+use crate::types::Value;
+use crate::Table;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::{BuildHasher, RandomState};
+
+/// One row of a table, borrowed from it
+///
+/// # Examples
+///
+/// ```
+/// use dax_rust::table::Table;
+/// use dax_rust::types::Value;
+///
+/// let mut table = Table::new();
+/// table.add_column("Price".to_string(), vec![Value::Number(2.0), Value::Number(3.0)]).unwrap();
+/// table.add_column("Quantity".to_string(), vec![Value::Number(10.0), Value::Number(20.0)]).unwrap();
+///
+/// let revenue: f64 = table
+///     .rows()
+///     .map(|row| match (row.get("Price"), row.get("Quantity")) {
+///         (Some(Value::Number(p)), Some(Value::Number(q))) => p * q,
+///         _ => 0.0,
+///     })
+///     .sum();
+/// assert_eq!(revenue, 80.0);
+/// ```
+#[derive(Clone, Copy)]
+pub struct Row<'a> {
+    table: &'a Table,
+    index: usize,
+}
+
+impl<'a> Row<'a> {
+    /// The row's value in a column, or None if the table has no such column
+    pub fn get(&self, column: &str) -> Option<Value> {
+        self.table.get(column, self.index)
+    }
+
+    /// Where the row is in its table, counting from 0
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The row's values, paired with their column names, in column order
+    pub fn values(&self) -> impl Iterator<Item = (&'a str, Value)> + 'a {
+        let (table, index) = (self.table, self.index);
+        table
+            .column_names()
+            .iter()
+            .filter_map(move |name| Some((name.as_str(), table.get(name, index)?)))
+    }
+}
+
+/// Types whose values make up a table a row each, usually structs with
+/// `#[derive(IntoTable)]` from `dax_macro`
+///
+/// # Examples
+///
+/// ```
+/// use dax_macro::IntoTable;
+/// use dax_rust::{IntoTable, Table, Value};
+///
+/// #[derive(IntoTable)]
+/// struct Sale {
+///     product: String,
+///     #[dax(rename = "Unit Price")]
+///     price: f64,
+///     discount: Option<f64>,
+/// }
+///
+/// let table = Sale::into_table(vec![
+///     Sale { product: "Pear".to_string(), price: 2.5, discount: None },
+///     Sale { product: "Plum".to_string(), price: 1.0, discount: Some(0.1) },
+/// ]);
+/// assert_eq!(table.column_names(), ["product", "Unit Price", "discount"]);
+/// assert_eq!(table.get("discount", 0), Some(Value::Null));
+/// ```
+pub trait IntoTable: Sized {
+    /// A table with a row for each item, in order
+    fn into_table(rows: impl IntoIterator<Item = Self>) -> Table;
+}
+
+/// Types that can be read from a table a row at a time, usually structs with
+/// `#[derive(FromTable)]` from `dax_macro`
+///
+/// # Examples
+///
+/// ```
+/// use dax_macro::FromTable;
+/// use dax_rust::{FromTable, Table, Value};
+///
+/// #[derive(FromTable, Debug, PartialEq)]
+/// struct Sale {
+///     product: String,
+///     #[dax(rename = "Unit Price")]
+///     price: f64,
+///     discount: Option<f64>,
+/// }
+///
+/// let mut table = Table::new();
+/// table.add_column("product".to_string(), vec![Value::from("Pear")]).unwrap();
+/// table.add_column("Unit Price".to_string(), vec![Value::Number(2.5)]).unwrap();
+/// table.add_column("discount".to_string(), vec![Value::Null]).unwrap();
+/// let sales = Sale::from_table(&table).unwrap();
+/// assert_eq!(sales, [Sale { product: "Pear".to_string(), price: 2.5, discount: None }]);
+/// ```
+pub trait FromTable: Sized {
+    /// Each row as a value. A column that's missing, or a cell of the wrong
+    /// kind for its field, is an error naming the field.
+    fn from_table(table: &Table) -> Result<Vec<Self>, DaxError>;
+}
+
+// Shown as a map from column name to value, in column order
+impl fmt::Debug for Row<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.values()).finish()
+    }
+}
+
+impl Table {
+    /// The rows of the table, in order
+    pub fn rows(&self) -> impl Iterator<Item = Row<'_>> {
+        (0..self.num_rows()).map(move |index| Row { table: self, index })
+    }
+
+    /// A new table of the rows for which `keep` returns true, with the same
+    /// columns in the same order
+    pub fn filter_rows<F>(&self, mut keep: F) -> Table
+    where
+        F: FnMut(&Row) -> bool,
+    {
+        let kept: Vec<usize> = self
+            .rows()
+            .filter(|row| keep(row))
+            .map(|row| row.index)
+            .collect();
+        self.select_rows(&kept)
+    }
+
+    /// A new table without repeated rows, keeping the first of each in its
+    /// place. Rows repeat when every column has equal values, so NaN matches
+    /// NaN and `Integer(3)` matches `Number(3.0)`, as `Value` equality has it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dax_rust::table::Table;
+    /// use dax_rust::types::Value;
+    ///
+    /// let mut table = Table::new();
+    /// table.add_column("Product".to_string(), ["Pear", "Plum", "Pear"].map(Value::from).to_vec()).unwrap();
+    /// assert_eq!(table.distinct_rows().get_column("Product").unwrap(), [Value::from("Pear"), Value::from("Plum")]);
+    /// ```
+    pub fn distinct_rows(&self) -> Table {
+        let mut seen = HashSet::new();
+        let kept: Vec<usize> = (0..self.num_rows())
+            .filter(|&row| {
+                let key: Vec<Value> = self
+                    .column_names()
+                    .iter()
+                    .map(|name| self.cell(name, row).unwrap_or(Value::Null))
+                    .collect();
+                seen.insert(key)
+            })
+            .collect();
+        self.select_rows(&kept)
+    }
+
+    /// A new table without the rows that are Null in any column, or in any of
+    /// the `subset` columns if given. A name in `subset` that isn't one of the
+    /// table's columns has no Nulls, so it doesn't drop anything.
+    pub fn drop_nulls(&self, subset: Option<&[&str]>) -> Table {
+        let columns: Vec<&str> = match subset {
+            Some(names) => names.to_vec(),
+            None => self.column_names().iter().map(String::as_str).collect(),
+        };
+        self.filter_rows(|row| {
+            columns
+                .iter()
+                .all(|name| !row.get(name).is_some_and(|value| value.is_null()))
+        })
+    }
+
+    /// A new table of the first `n` rows, or all of them if there are fewer
+    pub fn head(&self, n: usize) -> Table {
+        self.slice(0, n)
+    }
+
+    /// A new table of the last `n` rows, or all of them if there are fewer
+    pub fn tail(&self, n: usize) -> Table {
+        self.slice(self.num_rows().saturating_sub(n), n)
+    }
+
+    /// A new table of up to `len` rows starting at row `offset`; rows past
+    /// the end are left out, so an offset beyond it gives an empty table.
+    pub fn slice(&self, offset: usize, len: usize) -> Table {
+        let start = offset.min(self.num_rows());
+        let end = start.saturating_add(len).min(self.num_rows());
+        self.select_rows(&(start..end).collect::<Vec<_>>())
+    }
+
+    /// A new table of `n` rows picked at random, or all of them if there are
+    /// fewer, kept in their order in this table.
+    ///
+    /// The same `seed` picks the same rows from the same table every time;
+    /// with no seed the rows differ from call to call.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dax_rust::table::Table;
+    /// use dax_rust::types::Value;
+    ///
+    /// let mut table = Table::new();
+    /// table.add_column("Id".to_string(), (0..100).map(Value::from).collect()).unwrap();
+    /// let sample = table.sample(10, Some(7));
+    /// assert_eq!(sample.num_rows(), 10);
+    /// assert_eq!(sample, table.sample(10, Some(7)));
+    /// ```
+    pub fn sample(&self, n: usize, seed: Option<u64>) -> Table {
+        let rows = self.num_rows();
+        let mut random = SplitMix64(seed.unwrap_or_else(|| RandomState::new().hash_one(rows)));
+        // A partial Fisher-Yates shuffle: the first n places end up a sample
+        let mut order: Vec<usize> = (0..rows).collect();
+        let n = n.min(rows);
+        for i in 0..n {
+            let j = i + (random.next() % (rows - i) as u64) as usize;
+            order.swap(i, j);
+        }
+        order.truncate(n);
+        order.sort_unstable();
+        self.select_rows(&order)
+    }
+
+    /// The rows of the table, in order, each as its values by column name
+    pub fn into_rows(mut self) -> impl Iterator<Item = HashMap<String, Value>> {
+        let rows = self.num_rows();
+        let mut columns: Vec<(String, std::vec::IntoIter<Value>)> = self
+            .column_names()
+            .to_vec()
+            .into_iter()
+            .map(|name| {
+                let values = self.remove_column(&name).unwrap_or_default();
+                (name, values.into_iter())
+            })
+            .collect();
+        (0..rows).map(move |_| {
+            columns
+                .iter_mut()
+                .map(|(name, values)| (name.clone(), values.next().unwrap_or(Value::Null)))
+                .collect()
+        })
+    }
+}
+
+// A small, fast generator, good enough for picking rows
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
@@ -0,0 +1,371 @@
+// serialize.rs
+
+//! Serde support for `Value`, `Table` and `DaxResult`, behind the `serde` feature.
+//!
+//! A `Value` serializes as the plain JSON kind it corresponds to: a number, an
+//! integer, a string, a boolean or null. Currency amounts and dates have no
+//! JSON kind of their own, so they are written as a single-entry map,
+//! `{"Currency": 12.5}` or `{"DateTime": "2024-01-31T00:00:00"}`, and read back
+//! as the same variant.
+//!
+//! JSON has no NaN or infinities. By default they are written as null, as
+//! `serde_json` does for a bare `f64`; `with_nan_policy` writes them as strings
+//! instead. Either way they read back as what was written, Null or Text.
+//!
+//! A table is columnar, `{"columns": {"Sales": [..], ..}}`, with the columns
+//! in the table's order. Measures aren't part of it. `Table::to_records` gives
+//! the same data a row at a time.
+use crate::table::{ColumnValues, DaxResult, Table};
+use crate::types::{currency_amount, currency_units, Value};
+use chrono::NaiveDateTime;
+use serde::de::{self, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{SerializeMap, SerializeSeq, SerializeStruct, Serializer};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// How numbers JSON can't hold, NaN and the infinities, are serialized
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NanPolicy {
+    /// As null
+    #[default]
+    Null,
+    /// As the strings "NaN", "inf" and "-inf"
+    String,
+}
+
+/// A value, table or result to serialize with a given `NanPolicy`
+#[derive(Debug, Clone, Copy)]
+pub struct WithNanPolicy<'a, T: ?Sized> {
+    inner: &'a T,
+    nan: NanPolicy,
+}
+
+impl Value {
+    /// This value, to serialize with non-finite numbers written as `nan` says.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dax_rust::serialize::NanPolicy;
+    /// use dax_rust::Value;
+    ///
+    /// let nan = Value::Number(f64::NAN);
+    /// assert_eq!(serde_json::to_string(&nan).unwrap(), "null");
+    /// let text = serde_json::to_string(&nan.with_nan_policy(NanPolicy::String)).unwrap();
+    /// assert_eq!(text, "\"NaN\"");
+    /// ```
+    pub fn with_nan_policy(&self, nan: NanPolicy) -> WithNanPolicy<'_, Value> {
+        WithNanPolicy { inner: self, nan }
+    }
+}
+
+impl Table {
+    /// This table, to serialize with non-finite numbers written as `nan` says
+    pub fn with_nan_policy(&self, nan: NanPolicy) -> WithNanPolicy<'_, Table> {
+        WithNanPolicy { inner: self, nan }
+    }
+
+    /// The table's rows as JSON objects of column name to value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dax_rust::{Table, Value};
+    ///
+    /// let mut table = Table::new();
+    /// table.add_column("Sales".to_string(), vec![Value::Number(1.5), Value::Null]).unwrap();
+    /// let records = table.to_records();
+    /// assert_eq!(records[0], serde_json::json!({"Sales": 1.5}));
+    /// assert_eq!(records[1], serde_json::json!({"Sales": null}));
+    /// ```
+    pub fn to_records(&self) -> Vec<serde_json::Value> {
+        self.rows()
+            .map(|row| {
+                let record = row
+                    .values()
+                    .map(|(name, value)| {
+                        let json = serde_json::to_value(&value)
+                            .expect("a Value always serializes to JSON");
+                        (name.to_string(), json)
+                    })
+                    .collect();
+                serde_json::Value::Object(record)
+            })
+            .collect()
+    }
+}
+
+impl DaxResult {
+    /// This result, to serialize with non-finite numbers written as `nan` says
+    pub fn with_nan_policy(&self, nan: NanPolicy) -> WithNanPolicy<'_, DaxResult> {
+        WithNanPolicy { inner: self, nan }
+    }
+}
+
+fn serialize_number<S: Serializer>(
+    n: f64,
+    nan: NanPolicy,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match nan {
+        NanPolicy::String if !n.is_finite() => serializer.serialize_str(&n.to_string()),
+        NanPolicy::Null if !n.is_finite() => serializer.serialize_none(),
+        _ => serializer.serialize_f64(n),
+    }
+}
+
+impl Serialize for WithNanPolicy<'_, Value> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.inner {
+            Value::Number(n) => serialize_number(*n, self.nan, serializer),
+            Value::Integer(i) => serializer.serialize_i64(*i),
+            Value::Currency(units) => serializer.serialize_newtype_variant(
+                "Value",
+                2,
+                "Currency",
+                &currency_amount(*units),
+            ),
+            Value::Text(s) => serializer.serialize_str(s),
+            Value::Boolean(b) => serializer.serialize_bool(*b),
+            Value::DateTime(d) => serializer.serialize_newtype_variant("Value", 5, "DateTime", d),
+            Value::Null => serializer.serialize_none(),
+        }
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.with_nan_policy(NanPolicy::default())
+            .serialize(serializer)
+    }
+}
+
+// The columns of a table as a map of name to values, in column order
+struct Columns<'a>(&'a Table, NanPolicy);
+
+impl Serialize for Columns<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Columns(table, nan) = *self;
+        let mut map = serializer.serialize_map(Some(table.num_columns()))?;
+        for name in table.column_names() {
+            if let Some(values) = table.get_column(name) {
+                map.serialize_entry(name, &Cells(values, nan))?;
+            }
+        }
+        map.end()
+    }
+}
+
+struct Cells<'a>(ColumnValues<'a>, NanPolicy);
+
+impl Serialize for Cells<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+        for value in self.0 {
+            seq.serialize_element(&value.with_nan_policy(self.1))?;
+        }
+        seq.end()
+    }
+}
+
+impl Serialize for WithNanPolicy<'_, Table> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut table = serializer.serialize_struct("Table", 1)?;
+        table.serialize_field("columns", &Columns(self.inner, self.nan))?;
+        table.end()
+    }
+}
+
+impl Serialize for Table {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.with_nan_policy(NanPolicy::default())
+            .serialize(serializer)
+    }
+}
+
+impl Serialize for WithNanPolicy<'_, DaxResult> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let value = match self.inner {
+            DaxResult::Table(table) => {
+                return table.with_nan_policy(self.nan).serialize(serializer)
+            }
+            DaxResult::Number(n) => Value::Number(*n),
+            DaxResult::Integer(i) => Value::Integer(*i),
+            DaxResult::Currency(units) => Value::Currency(*units),
+            DaxResult::Text(s) => Value::Text(s.clone()),
+            DaxResult::Boolean(b) => Value::Boolean(*b),
+            DaxResult::DateTime(d) => Value::DateTime(*d),
+            DaxResult::Blank => Value::Null,
+        };
+        value.with_nan_policy(self.nan).serialize(serializer)
+    }
+}
+
+impl Serialize for DaxResult {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.with_nan_policy(NanPolicy::default())
+            .serialize(serializer)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a number, string, boolean, null, or a Currency or DateTime map")
+    }
+
+    fn visit_bool<E: de::Error>(self, b: bool) -> Result<Value, E> {
+        Ok(Value::Boolean(b))
+    }
+
+    fn visit_i64<E: de::Error>(self, i: i64) -> Result<Value, E> {
+        Ok(Value::Integer(i))
+    }
+
+    fn visit_u64<E: de::Error>(self, u: u64) -> Result<Value, E> {
+        Ok(i64::try_from(u).map_or(Value::Number(u as f64), Value::Integer))
+    }
+
+    fn visit_f64<E: de::Error>(self, n: f64) -> Result<Value, E> {
+        Ok(Value::Number(n))
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Value, E> {
+        Ok(Value::Text(s.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, s: String) -> Result<Value, E> {
+        Ok(Value::Text(s))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let value = match map.next_key::<String>()?.as_deref() {
+            Some("Currency") => {
+                let amount: f64 = map.next_value()?;
+                currency_units(amount).map(Value::Currency).ok_or_else(|| {
+                    de::Error::custom(format!("{} is out of range for a currency amount", amount))
+                })?
+            }
+            Some("DateTime") => Value::DateTime(map.next_value::<NaiveDateTime>()?),
+            Some(other) => {
+                return Err(de::Error::unknown_variant(other, &["Currency", "DateTime"]))
+            }
+            None => return Err(de::Error::invalid_length(0, &self)),
+        };
+        if map.next_key::<de::IgnoredAny>()?.is_some() {
+            return Err(de::Error::custom(
+                "a Currency or DateTime map has a single entry",
+            ));
+        }
+        Ok(value)
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+// A table's columns as a map, read in the order they're written
+struct OrderedColumns(Vec<(String, Vec<Value>)>);
+
+impl<'de> Deserialize<'de> for OrderedColumns {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ColumnsVisitor;
+
+        impl<'de> Visitor<'de> for ColumnsVisitor {
+            type Value = OrderedColumns;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a map of column names to lists of values")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<OrderedColumns, A::Error> {
+                let mut columns = Vec::new();
+                while let Some((name, values)) = map.next_entry::<String, ValueList>()? {
+                    columns.push((name, values.0));
+                }
+                Ok(OrderedColumns(columns))
+            }
+        }
+
+        deserializer.deserialize_map(ColumnsVisitor)
+    }
+}
+
+struct ValueList(Vec<Value>);
+
+impl<'de> Deserialize<'de> for ValueList {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ListVisitor;
+
+        impl<'de> Visitor<'de> for ListVisitor {
+            type Value = ValueList;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a list of values")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<ValueList, A::Error> {
+                let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(value) = seq.next_element()? {
+                    values.push(value);
+                }
+                Ok(ValueList(values))
+            }
+        }
+
+        deserializer.deserialize_seq(ListVisitor)
+    }
+}
+
+#[derive(Deserialize)]
+struct TableRepr {
+    columns: OrderedColumns,
+}
+
+impl<'de> Deserialize<'de> for Table {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Table, D::Error> {
+        let TableRepr { columns } = TableRepr::deserialize(deserializer)?;
+        let mut table = Table::new();
+        for (name, values) in columns.0 {
+            if table.column_names().contains(&name) {
+                return Err(de::Error::custom(format!("Column {} appears twice", name)));
+            }
+            table.add_column(name, values).map_err(de::Error::custom)?;
+        }
+        Ok(table)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ResultRepr {
+    Table(Table),
+    Value(Value),
+}
+
+impl<'de> Deserialize<'de> for DaxResult {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<DaxResult, D::Error> {
+        Ok(match ResultRepr::deserialize(deserializer)? {
+            ResultRepr::Table(table) => DaxResult::Table(table),
+            ResultRepr::Value(value) => DaxResult::from(&value),
+        })
+    }
+}
@@ -0,0 +1,108 @@
+// sort.rs
+
+//! Sorting a table's rows by its columns.
+//!
+//! Keys are compared with the `Value` ordering, so a column holding several
+//! kinds of value sorts Boolean, then numbers, then Text, then DateTime rather
+//! than failing. Null is kept apart from that order: it goes after every other
+//! value, whichever way the key sorts, unless asked to go first. Rows that tie
+//! on every key keep their order.
+use crate::error::DaxError;
+use crate::types::Value;
+use crate::Table;
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+/// Where Null values go in a sorted column
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NullOrder {
+    First,
+    #[default]
+    Last,
+}
+
+impl Table {
+    /// Sort the rows by several columns, each ascending or descending, with
+    /// Null last.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dax_rust::sort::SortOrder;
+    /// use dax_rust::table::Table;
+    /// use dax_rust::types::Value;
+    ///
+    /// let mut table = Table::new();
+    /// table.add_column("Sales".to_string(), vec![Value::Number(5.0), Value::Null, Value::Number(9.0)]).unwrap();
+    /// table.sort_by(&[("Sales", SortOrder::Descending)]).unwrap();
+    /// assert_eq!(
+    ///     table.get_column("Sales").unwrap(),
+    ///     [Value::Number(9.0), Value::Number(5.0), Value::Null]
+    /// );
+    /// ```
+    pub fn sort_by(&mut self, keys: &[(&str, SortOrder)]) -> Result<(), DaxError> {
+        self.sort_by_with_nulls(keys, NullOrder::Last)
+    }
+
+    /// Sort the rows as `sort_by` does, with Null first or last
+    pub fn sort_by_with_nulls(
+        &mut self,
+        keys: &[(&str, SortOrder)],
+        nulls: NullOrder,
+    ) -> Result<(), DaxError> {
+        let order = self.rows_sorted_by(keys, nulls)?;
+        self.reorder_rows(&order);
+        Ok(())
+    }
+
+    /// A sorted copy of the table, leaving this one as it is
+    pub fn sorted_by(&self, keys: &[(&str, SortOrder)]) -> Result<Table, DaxError> {
+        Ok(self.select_rows(&self.rows_sorted_by(keys, NullOrder::Last)?))
+    }
+
+    // Row indices in sorted order
+    fn rows_sorted_by(
+        &self,
+        keys: &[(&str, SortOrder)],
+        nulls: NullOrder,
+    ) -> Result<Vec<usize>, DaxError> {
+        let mut columns = Vec::with_capacity(keys.len());
+        for (name, order) in keys {
+            // Copied out for the sort, which compares each row many times
+            match self.get_column(name) {
+                Some(values) => columns.push((values.to_vec(), *order)),
+                None => return Err(self.unknown_column(name)),
+            }
+        }
+        let mut rows: Vec<usize> = (0..self.num_rows()).collect();
+        rows.sort_by(|&a, &b| {
+            columns
+                .iter()
+                .map(|(values, order)| compare(&values[a], &values[b], *order, nulls))
+                .find(|ordering| *ordering != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        });
+        Ok(rows)
+    }
+}
+
+fn compare(a: &Value, b: &Value, order: SortOrder, nulls: NullOrder) -> Ordering {
+    let null_ordering = match nulls {
+        NullOrder::First => Ordering::Less,
+        NullOrder::Last => Ordering::Greater,
+    };
+    match (a, b) {
+        (Value::Null, Value::Null) => Ordering::Equal,
+        (Value::Null, _) => null_ordering,
+        (_, Value::Null) => null_ordering.reverse(),
+        _ => match order {
+            SortOrder::Ascending => a.cmp(b),
+            SortOrder::Descending => b.cmp(a),
+        },
+    }
+}
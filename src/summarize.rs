@@ -0,0 +1,162 @@
+// summarize.rs
+
+//! SUMMARIZECOLUMNS and SUMMARIZE: grouping with named measures.
+//!
+//! Groups are the combinations of the group-by columns found on the rows the
+//! filters leave visible. Each measure is then evaluated with the filter context
+//! narrowed to its group, exactly as CALCULATE would with one filter per group.
+//! SUMMARIZECOLUMNS takes filter tables and drops groups whose measures are all
+//! blank; SUMMARIZE groups the rows of a table expression and keeps every group.
+use crate::context::{EvalContext, Filter, FilterModifier};
+use crate::error::DaxError;
+use crate::parser::DaxExpr;
+use crate::table::{scalar_value, DaxResult};
+use crate::types::Value;
+use crate::Table;
+use std::collections::HashSet;
+
+impl Table {
+    pub(crate) fn evaluate_summarizecolumns(
+        &self,
+        name: &str,
+        args: &[&DaxExpr],
+        ctx: &EvalContext,
+    ) -> Result<DaxResult, DaxError> {
+        // Group-by columns come first, then filter tables, then name/expression pairs
+        let mut group_by = Vec::new();
+        let mut filters = Vec::new();
+        let mut rest = args;
+        while let Some((arg, tail)) = rest.split_first() {
+            match arg {
+                DaxExpr::Literal(Value::Text(_)) => break,
+                DaxExpr::ColumnRef { name: col_name, .. } if filters.is_empty() => {
+                    if !self.has_column(col_name) {
+                        return Err(self.unknown_column(col_name));
+                    }
+                    group_by.push(col_name.clone());
+                }
+                _ => filters.push(self.filter_argument(name, arg, ctx)?),
+            }
+            rest = tail;
+        }
+        if !rest.len().is_multiple_of(2) {
+            return Err(format!("{} expects name/expression pairs", name).into());
+        }
+        let mut measures = Vec::new();
+        for pair in rest.chunks(2) {
+            measures.push((self.text_argument(name, pair[0], ctx)?, pair[1]));
+        }
+        if group_by.is_empty() && measures.is_empty() {
+            return Err(format!("{} expects a column or a measure", name).into());
+        }
+
+        let mut filtered = self.transition_context(ctx);
+        filtered.filters.apply(filters);
+        self.summarize(&group_by, &measures, &filtered, false)
+            .map(DaxResult::Table)
+    }
+
+    pub(crate) fn evaluate_summarize(
+        &self,
+        name: &str,
+        args: &[&DaxExpr],
+        ctx: &EvalContext,
+    ) -> Result<DaxResult, DaxError> {
+        let (table_arg, mut rest) = args
+            .split_first()
+            .ok_or_else(|| DaxError::from(format!("{} expects a table", name)))?;
+        let table = self.table_argument(name, table_arg, ctx)?;
+        let mut group_by = Vec::new();
+        while let Some((DaxExpr::ColumnRef { name: col_name, .. }, tail)) = rest.split_first() {
+            if !table.has_column(col_name) {
+                return Err(table.unknown_column(col_name));
+            }
+            group_by.push(col_name.clone());
+            rest = tail;
+        }
+        if !rest.len().is_multiple_of(2) {
+            return Err(format!("{} expects name/expression pairs", name).into());
+        }
+        let mut measures = Vec::new();
+        for pair in rest.chunks(2) {
+            measures.push((self.text_argument(name, pair[0], ctx)?, pair[1]));
+        }
+        // The table expression has already applied the filters
+        let unfiltered = EvalContext {
+            rows: Vec::new(),
+            filters: Default::default(),
+            ..ctx.clone()
+        };
+        table
+            .summarize(&group_by, &measures, &unfiltered, true)
+            .map(DaxResult::Table)
+    }
+
+    // One row per group of the visible rows, with the measures evaluated in the
+    // group's filter context. Groups where every measure is blank are left out
+    // unless `keep_blank` is set.
+    fn summarize(
+        &self,
+        group_by: &[String],
+        measures: &[(String, &DaxExpr)],
+        ctx: &EvalContext,
+        keep_blank: bool,
+    ) -> Result<Table, DaxError> {
+        let mut seen = HashSet::new();
+        let mut groups = Vec::new();
+        let columns: Vec<_> = group_by.iter().map(|name| self.get_column(name)).collect();
+        for row in self.visible_rows(ctx) {
+            let key: Vec<Value> = columns
+                .iter()
+                .map(|column| column.and_then(|c| c.get(row)).unwrap_or(Value::Null))
+                .collect();
+            if seen.insert(key.clone()) {
+                groups.push(key);
+            }
+        }
+        // Measures alone give a single row over everything visible
+        if group_by.is_empty() {
+            groups = vec![Vec::new()];
+        }
+
+        let mut group_columns = vec![Vec::new(); group_by.len()];
+        let mut measure_columns = vec![Vec::new(); measures.len()];
+        for key in groups {
+            let mut inner = ctx.clone();
+            inner
+                .filters
+                .apply(vec![FilterModifier::Replace(Filter::new(
+                    group_by.to_vec(),
+                    HashSet::from([key.clone()]),
+                ))]);
+            let mut values = Vec::with_capacity(measures.len());
+            for (measure_name, expression) in measures {
+                let result = self.evaluate_expr(expression, &inner)?;
+                values.push(scalar_value(result).ok_or_else(|| {
+                    format!("Measure {} must evaluate to a single value", measure_name)
+                })?);
+            }
+            if !keep_blank
+                && !measures.is_empty()
+                && values.iter().all(|value| *value == Value::Null)
+            {
+                continue;
+            }
+            for (column, value) in group_columns.iter_mut().zip(key) {
+                column.push(value);
+            }
+            for (column, value) in measure_columns.iter_mut().zip(values) {
+                column.push(value);
+            }
+        }
+
+        let mut table = Table::new();
+        for (name, values) in group_by.iter().zip(group_columns) {
+            table.add_column(name.clone(), values)?;
+        }
+        for ((name, _), values) in measures.iter().zip(measure_columns) {
+            table.add_column(name.clone(), values)?;
+        }
+        Ok(table)
+    }
+}
@@ -19,13 +19,13 @@
 ///         Value::Number(200.0),
 ///         Value::Number(300.0)
 ///     ]
-/// );
+/// ).unwrap();
 ///
 /// // Calculate the sum using DAX
 /// match table.evaluate_dax("SUM([Sales])") {
-///     DaxResult::Number(n) => println!("Total sales: {}", n),
-///     DaxResult::Error(e) => println!("Error: {}", e),
-///     _ => println!("Unexpected result type"),
+///     Ok(DaxResult::Number(n)) => println!("Total sales: {}", n),
+///     Ok(other) => println!("Unexpected result: {:?}", other),
+///     Err(e) => println!("Error: {}", e),
 /// }
 /// ```
 ///
@@ -34,263 +34,2694 @@
 /// Currently supports the following DAX functions:
 /// - `SUM([column])`: Calculates the sum of numeric values in a column
 /// - `AVERAGE([column])`: Calculates the average of numeric values in a column
+/// - `MIN([column])` / `MAX([column])`: Smallest / largest number or date in a column
+/// - `AVERAGEA`, `MINA`, `MAXA`: Like their counterparts, counting TRUE as 1 and FALSE or text as 0
+/// - `DISTINCTCOUNT([column])`: Counts the distinct values in a column
+/// - `COUNTBLANK([column])`: Counts the blanks in a column
+/// - `APPROXIMATEDISTINCTCOUNT([column])`: Estimates distinct values, within about 0.8%
+/// - `DIVIDE(<numerator>, <denominator> [, <alternate>])`: Safe division
+/// - `CURRENCY(<value>)`: Converts to a fixed-point amount with four decimal places
+/// - `DATE`, `TIME`, `TODAY`, `NOW`: Build date/time values
+/// - `YEAR`, `MONTH`, `DAY`, `HOUR`, `MINUTE`, `SECOND`, `WEEKDAY`, `WEEKNUM`: Date parts
+/// - `DATEDIFF`, `DATEADD`, `EDATE`, `EOMONTH`: Date arithmetic
+/// - `NETWORKDAYS`, `YEARFRAC`: Working days and fractions of a year between two dates
+/// - `CALENDAR(<start>, <end>)` / `CALENDARAUTO()`: One-column date tables
+/// - `COUNTROWS([<table>])`: Counts the rows of a table expression
+/// - `ISEMPTY(<table>)`: Whether a table expression has no rows
+/// - `CALCULATE(<expression>, <filter>...)`: Evaluates with table or condition filters applied
+/// - `KEEPFILTERS(<filter>)`, `REMOVEFILTERS([<table or column>...])`: CALCULATE modifiers
+/// - `USERELATIONSHIP`, `CROSSFILTER`: Recognised, but rejected, as a single table has no relationships
+/// - `DATESMTD`/`QTD`/`YTD`, `TOTALMTD`/`QTD`/`YTD`: Period-to-date date sets and totals
+/// - `DATESBETWEEN`, `DATESINPERIOD`, `SAMEPERIODLASTYEAR`: Date ranges for CALCULATE filters
+/// - `FIRSTDATE`, `LASTDATE`, `STARTOF*`, `ENDOF*`, `PREVIOUS*`, `NEXT*`: Period navigation
+/// - `OPENINGBALANCE*`, `CLOSINGBALANCE*`: Semi-additive values at the edge of a period
+/// - `BLANK()`: The blank value
+/// - `TRUE()` / `FALSE()`: Boolean constants, also usable as bare keywords
+/// - `IF`, `SWITCH`: Conditional evaluation
+/// - `NORM.DIST`, `NORM.INV`, `NORM.S.DIST`, `NORM.S.INV`, `T.DIST`, `CHISQ.DIST`: Distributions
+/// - `ERROR(<text>)`, `IFERROR(<value>, <alternate>)`: Raise and catch errors
+/// - `FILTER(<table>, <condition>)`: Rows of a table where the condition holds
+/// - `{<value>, ...}`: A one-column table literal, with the column named Value
+/// - `DATATABLE(<name>, <type>, ..., {{<value>, ...}, ...})`: A table written out in full
+/// - `TREATAS(<table>, <column>...)`: A table's values applied as filters on the named columns
+/// - `SUMMARIZECOLUMNS(<column>..., <filter>..., <name>, <expression>...)`: Grouped measures
+/// - `SUMMARIZE(<table>, <column>..., <name>, <expression>...)`: Grouped measures over a table
+/// - `TOPN(<n>, <table>, <orderBy> [, <order>])`: The first n rows by an expression
+/// - `OFFSET`, `INDEX`, `WINDOW`: Rows relative to the current one, by `ORDERBY(<expr> [, ASC|DESC])`
+/// - `RANK([DENSE|SKIP], <table>, ORDERBY(...))`, `ROWNUMBER(<table>, ORDERBY(...))`: Row positions
+/// - `SAMPLE(<n>, <table>, <orderBy> [, <order>])`: n evenly spaced rows by an expression
+/// - `EXACT`, `REPT`, `UNICHAR`, `UNICODE`: Text comparison, repetition and code points
+/// - `COMBINEVALUES(<delimiter>, <expression>...)`: Joins values into a composite key
+/// - `ADDCOLUMNS(<table>, <name>, <expression>...)`: Adds columns evaluated per row
+/// - `SUMX(<table>, <expression>)`: Sums an expression evaluated per row
+///
+/// `execute_query` runs a whole `DEFINE ... EVALUATE ... ORDER BY ...` query.
+///
+/// Further scalar functions can be registered in a `FunctionRegistry` and
+/// called through `evaluate_dax_with`.
 ///
 /// # Error Handling
 ///
-/// Returns `DaxResult::Error` in the following cases:
-/// - Invalid column name
-/// - Unsupported function
-/// - Invalid DAX expression syntax
-use crate::types::Value;
-use dax_macro_impl::tokenize;
-use dax_macro_impl::DaxToken;
-use std::collections::HashMap;
+/// `evaluate_dax` returns `DaxError::ParseError` for invalid DAX expression
+/// syntax, and `DaxError::EvaluationError` for anything that goes wrong while
+/// evaluating, such as an invalid column name or an unsupported function. An
+/// expression with nothing to compute over, like `SUM` of an empty column, is
+/// `DaxResult::Blank` rather than an error.
+use crate::column::Column;
+pub use crate::column::{ColumnIter, ColumnValues};
+use crate::context::{EvalContext, Filter, FilterModifier};
+use crate::dates::{self, Interval};
+use crate::error::{DaxError, Span};
+use crate::functions::FunctionRegistry;
+use crate::hyperloglog::{HyperLogLog, DEFAULT_PRECISION};
+use crate::parser::{self, BinaryOperator, DaxExpr, UnaryOperator};
+use crate::stats;
+use crate::time_intelligence::date_table;
+use crate::types::{currency_amount, currency_units, DType, Value, CURRENCY_SCALE};
+use chrono::{Datelike, Local, NaiveDate, NaiveDateTime, NaiveTime};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::ops::{Index, Range};
+
+// Longest text REPT will build, to keep a large count from exhausting memory
+const MAX_TEXT_LENGTH: usize = 1 << 20;
+
+#[derive(Debug, Clone)]
+pub struct Table {
+    columns: HashMap<String, Column>,
+    /// Column names in the order the columns were added
+    names: Vec<String>,
+    measures: HashMap<String, Measure>,
+    null_policy: NullPolicy,
+    /// What `Name[Column]` and a bare `Name` refer to the table as
+    name: Option<String>,
+}
+
+/// What an aggregate such as SUM does about Null cells in its column.
+///
+/// Whatever the policy, cells of the wrong kind, such as text in a SUM, are
+/// left out, and an aggregate with nothing left to work on is blank rather
+/// than zero, as in Power BI.
+///
+/// | Column           | `Skip`         | `PropagateBlank` | `Error` |
+/// |------------------|----------------|------------------|---------|
+/// | empty            | blank          | blank            | blank   |
+/// | all Null         | blank          | blank            | error   |
+/// | numbers and Null | of the numbers | blank            | error   |
+/// | missing          | error          | error            | error   |
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NullPolicy {
+    /// Leave Nulls out, as DAX does
+    #[default]
+    Skip,
+    /// Any Null makes the result blank
+    PropagateBlank,
+    /// Any Null is an error naming its row
+    Error,
+}
+
+/// A named expression, evaluated wherever `[Name]` doesn't match a column
+#[derive(Debug, Clone)]
+pub(crate) struct Measure {
+    /// The text the expression's spans point into
+    expression: String,
+    expr: DaxExpr,
+}
+
+impl Measure {
+    pub(crate) fn new(expression: &str, expr: DaxExpr) -> Self {
+        Measure {
+            expression: expression.to_string(),
+            expr,
+        }
+    }
+}
+
+/// Tables are equal when they have the same column names, in any order, and
+/// each column has equal values in the same order. Column order is left out so
+/// that a computed table can be compared with one written out by hand, and
+/// measures are left out as they aren't data.
+impl PartialEq for Table {
+    fn eq(&self, other: &Table) -> bool {
+        self.names.len() == other.names.len()
+            && self.num_rows() == other.num_rows()
+            && self.columns.iter().all(|(name, column)| {
+                other.columns.get(name).is_some_and(|theirs| {
+                    (0..column.len()).all(|row| column.value(row) == theirs.value(row))
+                })
+            })
+    }
+}
+
+impl Default for Table {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Table {
+    pub fn new() -> Self {
+        Table {
+            columns: HashMap::new(),
+            names: Vec::new(),
+            measures: HashMap::new(),
+            null_policy: NullPolicy::default(),
+            name: None,
+        }
+    }
+
+    /// The name expressions refer to the table by, if it has been given one
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Name the table. Once named, `Table[Column]` and a bare table name in
+    /// an expression must use the name, matched without regard to case; a
+    /// table without a name answers to any.
+    pub fn set_name(&mut self, name: String) {
+        self.name = Some(name);
+    }
+
+    /// How aggregates treat Nulls, both in DAX and in `sum` and the other
+    /// aggregate methods; `NullPolicy::Skip` unless set
+    pub fn null_policy(&self) -> NullPolicy {
+        self.null_policy
+    }
+
+    pub fn set_null_policy(&mut self, policy: NullPolicy) {
+        self.null_policy = policy;
+    }
+
+    /// Whether the table has no rows
+    pub fn is_empty(&self) -> bool {
+        self.num_rows() == 0
+    }
+
+    /// Number of rows. Every column has this many values.
+    pub fn num_rows(&self) -> usize {
+        self.columns.values().next().map(Column::len).unwrap_or(0)
+    }
+
+    pub fn num_columns(&self) -> usize {
+        self.columns.len()
+    }
+
+    /// Number of rows and number of columns
+    pub fn shape(&self) -> (usize, usize) {
+        (self.num_rows(), self.num_columns())
+    }
+
+    /// A column's values, read in place; see `ColumnValues`
+    pub fn get_column(&self, name: &str) -> Option<ColumnValues<'_>> {
+        self.columns.get(name).map(ColumnValues::new)
+    }
+
+    pub(crate) fn has_column(&self, name: &str) -> bool {
+        self.columns.contains_key(name)
+    }
+
+    // One cell, read without building a typed column's `Value`s
+    pub(crate) fn cell(&self, column: &str, row: usize) -> Option<Value> {
+        self.columns.get(column)?.value(row)
+    }
+
+    // Bytes a column's storage takes; see `Table::memory_usage`
+    pub(crate) fn column_memory_usage(&self, name: &str) -> usize {
+        self.columns.get(name).map_or(0, Column::memory_usage)
+    }
+
+    // Store every column as compactly as it can be; see `Table::shrink_to_fit`
+    pub(crate) fn shrink_storage(&mut self) {
+        self.names.shrink_to_fit();
+        for column in self.columns.values_mut() {
+            column.shrink_to_fit();
+        }
+        self.columns.shrink_to_fit();
+    }
+
+    /// The value of one cell, or None if there's no such column or row.
+    /// Unlike indexing the table, this reads the one value without building
+    /// the whole column as `Value`s.
+    pub fn get(&self, column: &str, row: usize) -> Option<Value> {
+        self.cell(column, row)
+    }
+
+    /// Replace the value of one cell
+    pub fn set(&mut self, column: &str, row: usize, value: Value) -> Result<(), DaxError> {
+        let rows = self.num_rows();
+        let values = match self.columns.get_mut(column) {
+            Some(values) => values,
+            None => return Err(self.unknown_column(column)),
+        };
+        if row >= rows {
+            return Err(
+                format!("Row {} is out of range for a table with {} rows", row, rows).into(),
+            );
+        }
+        values.set(row, value);
+        Ok(())
+    }
+
+    /// Add a column, replacing any column of the same name.
+    ///
+    /// Every column has one value per row, so a column with a different
+    /// number of values from the others is rejected with
+    /// `DaxError::ColumnLength` and the table is left as it was. Use
+    /// `add_column_padded` when the data is meant to be ragged.
+    pub fn add_column(&mut self, name: String, values: Vec<Value>) -> Result<(), DaxError> {
+        if let Some(expected) = self.rows_besides(&name) {
+            if values.len() != expected {
+                return Err(DaxError::ColumnLength {
+                    column: name,
+                    expected,
+                    got: values.len(),
+                });
+            }
+        }
+        self.insert_column(name, values);
+        Ok(())
+    }
+
+    /// Add a column, filling whichever is shorter, the column or the rest of
+    /// the table, with `Value::Null` so they end up the same length
+    pub fn add_column_padded(&mut self, name: String, mut values: Vec<Value>) {
+        let rows = self.rows_besides(&name).unwrap_or(0);
+        if values.len() < rows {
+            values.resize(rows, Value::Null);
+        }
+        for (other, column) in self.columns.iter_mut() {
+            if *other != name {
+                column.pad_to(values.len());
+            }
+        }
+        self.insert_column(name, values);
+    }
+
+    /// Remove a column, returning its values, or None if there was no such column
+    pub fn remove_column(&mut self, name: &str) -> Option<Vec<Value>> {
+        let column = self.columns.remove(name)?;
+        self.names.retain(|other| other != name);
+        Some(column.into_values())
+    }
+
+    /// Give a column a new name, keeping its place among the others.
+    /// Fails if there's no such column or the new name is already taken.
+    pub fn rename_column(&mut self, old: &str, new: &str) -> Result<(), DaxError> {
+        if old == new && self.columns.contains_key(old) {
+            return Ok(());
+        }
+        if self.columns.contains_key(new) {
+            return Err(format!(
+                "Cannot rename {} to {}: column {} already exists",
+                old, new, new
+            )
+            .into());
+        }
+        let values = self
+            .columns
+            .remove(old)
+            .ok_or_else(|| self.unknown_column(old))?;
+        self.columns.insert(new.to_string(), values);
+        for name in self.names.iter_mut().filter(|name| *name == old) {
+            *name = new.to_string();
+        }
+        Ok(())
+    }
+
+    /// Put the named columns first, in the order given. Columns left out
+    /// follow them, in the order they were in. Fails, changing nothing, if a
+    /// name isn't one of the table's columns or is given twice.
+    pub fn reorder_columns(&mut self, order: &[&str]) -> Result<(), DaxError> {
+        for (i, name) in order.iter().enumerate() {
+            if !self.columns.contains_key(*name) {
+                return Err(self.unknown_column(name));
+            }
+            if order[..i].contains(name) {
+                return Err(format!("Column {} is listed twice", name).into());
+            }
+        }
+        let mut names: Vec<String> = order.iter().map(|name| name.to_string()).collect();
+        names.extend(
+            self.names
+                .iter()
+                .filter(|name| !order.contains(&name.as_str()))
+                .cloned(),
+        );
+        self.names = names;
+        Ok(())
+    }
+
+    /// The kind of values in a column, inferred from what it holds, or None
+    /// if there's no such column. See `DType::infer`.
+    pub fn dtype(&self, column: &str) -> Option<DType> {
+        Some(match self.columns.get(column)? {
+            // Every value of a typed column is its kind or, for numbers, Null
+            Column::F64 { .. } => DType::Number,
+            Column::Str { .. } | Column::Dict { .. } => DType::Text,
+            Column::Bool { .. } => DType::Boolean,
+            Column::Mixed(values) => DType::infer(values),
+        })
+    }
+
+    /// Convert every cell of a column to another kind, as `Value::cast` does.
+    ///
+    /// When a cell doesn't convert, a `strict` cast fails, naming the first
+    /// such row and leaving the column as it was; otherwise the cell becomes
+    /// Null.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dax_rust::{DType, Table, Value};
+    ///
+    /// let mut table = Table::new();
+    /// table.add_column("Sales".to_string(), vec![Value::from("12.5"), Value::from("n/a")]).unwrap();
+    /// assert!(table.clone().cast_column("Sales", DType::Number, true).is_err());
+    /// table.cast_column("Sales", DType::Number, false).unwrap();
+    /// assert_eq!(table.get_column("Sales").unwrap(), [Value::Number(12.5), Value::Null]);
+    /// ```
+    pub fn cast_column(&mut self, column: &str, to: DType, strict: bool) -> Result<(), DaxError> {
+        let values = self
+            .get_column(column)
+            .ok_or_else(|| self.unknown_column(column))?;
+        let mut cast = Vec::with_capacity(values.len());
+        for (row, value) in values.iter().enumerate() {
+            match value.cast(to) {
+                Some(value) => cast.push(value),
+                None if strict => {
+                    let shown = match value {
+                        Value::Text(s) => format!("{:?}", s),
+                        other => other.to_string(),
+                    };
+                    return Err(format!(
+                        "Cannot cast column {} to {}: row {} holds {}",
+                        column, to, row, shown
+                    )
+                    .into());
+                }
+                None => cast.push(Value::Null),
+            }
+        }
+        self.insert_column(column.to_string(), cast);
+        Ok(())
+    }
+
+    /// Add another table's rows after this one's.
+    ///
+    /// The columns are matched by name. A column only one of the tables has is
+    /// Null in the other's rows, and columns this table didn't have go after
+    /// its own, in the other table's order.
+    pub fn append(&mut self, other: Table) -> Result<(), DaxError> {
+        let rows = self.num_rows();
+        let added = other.num_rows();
+        let Table {
+            mut columns, names, ..
+        } = other;
+        for name in &self.names {
+            let column = self.columns.get_mut(name).expect("every name has a column");
+            match columns.remove(name) {
+                Some(more) => more.into_values().into_iter().for_each(|v| column.push(v)),
+                None => column.pad_to(rows + added),
+            }
+        }
+        for name in names {
+            if let Some(more) = columns.remove(&name) {
+                let mut values = vec![Value::Null; rows];
+                values.extend(more.into_values());
+                self.insert_column(name, values);
+            }
+        }
+        Ok(())
+    }
+
+    /// Append as `append` does, but fail unless both tables have the same
+    /// columns, whatever their order
+    pub fn append_strict(&mut self, other: Table) -> Result<(), DaxError> {
+        let differing = self
+            .names
+            .iter()
+            .filter(|name| !other.has_column(name))
+            .chain(other.names.iter().filter(|name| !self.has_column(name)))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !differing.is_empty() {
+            return Err(format!(
+                "Cannot append a table with different columns: {} only in one of them",
+                differing.join(", ")
+            )
+            .into());
+        }
+        self.append(other)
+    }
+
+    /// Stack tables' rows in order, as `append` would one after another
+    pub fn concat(tables: Vec<Table>) -> Result<Table, DaxError> {
+        let mut result = Table::new();
+        for table in tables {
+            result.append(table)?;
+        }
+        Ok(result)
+    }
+
+    /// A new table of just the named columns, in the order given. Fails if a
+    /// name isn't one of the table's columns, listing the ones it has.
+    pub fn select(&self, columns: &[&str]) -> Result<Table, DaxError> {
+        let mut table = Table::new();
+        for name in columns {
+            let column = self.columns.get(*name).ok_or_else(|| {
+                format!(
+                    "Column {} not found; the table has {}",
+                    name,
+                    self.names.join(", ")
+                )
+            })?;
+            table.store_column(name.to_string(), column.clone());
+        }
+        Ok(table)
+    }
+
+    // Number of rows in the columns other than `name`, if there are any
+    fn rows_besides(&self, name: &str) -> Option<usize> {
+        self.columns
+            .iter()
+            .find(|(other, _)| *other != name)
+            .map(|(_, values)| values.len())
+    }
+
+    // Store a column, keeping the place of any column it replaces
+    fn insert_column(&mut self, name: String, values: Vec<Value>) {
+        self.store_column(name, Column::from_values(values));
+    }
+
+    fn store_column(&mut self, name: String, column: Column) {
+        if !self.columns.contains_key(&name) {
+            self.names.push(name.clone());
+        }
+        self.columns.insert(name, column);
+    }
+
+    // A table of just one column, which can't disagree with any other
+    pub(crate) fn single_column(name: String, values: Vec<Value>) -> Table {
+        let mut table = Table::new();
+        table.insert_column(name, values);
+        table
+    }
+
+    /// Append a row, given as values by column name.
+    ///
+    /// Columns the row doesn't mention get `Value::Null`, and a name the table
+    /// hasn't seen becomes a new column, `Value::Null` in the earlier rows.
+    /// New columns go after the existing ones in order of name, since the
+    /// row itself has no order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dax_rust::table::Table;
+    /// use dax_rust::types::Value;
+    /// use std::collections::HashMap;
+    ///
+    /// let mut table = Table::new();
+    /// table.add_row(HashMap::from([("Product".to_string(), Value::from("Apple"))]));
+    /// table.add_row(HashMap::from([("Sales".to_string(), Value::Number(10.0))]));
+    /// assert_eq!(
+    ///     table.get_column("Product").unwrap(),
+    ///     [Value::from("Apple"), Value::Null]
+    /// );
+    /// assert_eq!(
+    ///     table.get_column("Sales").unwrap(),
+    ///     [Value::Null, Value::Number(10.0)]
+    /// );
+    /// ```
+    pub fn add_row(&mut self, row: HashMap<String, Value>) {
+        let rows = self.num_rows();
+        let mut row: Vec<(String, Value)> = row.into_iter().collect();
+        row.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (name, value) in row {
+            if !self.columns.contains_key(&name) {
+                self.insert_column(name.clone(), vec![Value::Null; rows]);
+            }
+            if let Some(column) = self.columns.get_mut(&name) {
+                column.push(value);
+            }
+        }
+        for column in self.columns.values_mut() {
+            column.pad_to(rows + 1);
+        }
+    }
+
+    /// Build a table from rows, as `add_row` would add them one at a time
+    pub fn from_rows<I>(rows: I) -> Self
+    where
+        I: IntoIterator<Item = HashMap<String, Value>>,
+    {
+        let mut table = Table::new();
+        for row in rows {
+            table.add_row(row);
+        }
+        table
+    }
+
+    /// The values of one row, by column name
+    pub fn row(&self, index: usize) -> Option<HashMap<&str, Value>> {
+        if index >= self.num_rows() {
+            return None;
+        }
+        self.columns
+            .iter()
+            .map(|(name, column)| Some((name.as_str(), column.value(index)?)))
+            .collect()
+    }
+
+    /// Define a measure, which expressions can then refer to as `[Name]`,
+    /// replacing any earlier measure of the same name.
+    ///
+    /// A reference is only resolved to a measure when the table has no column
+    /// of that name. Measures may refer to each other, as long as none ends up
+    /// referring to itself. Within a row context, a measure is evaluated with
+    /// the current row as a filter, as if wrapped in CALCULATE.
+    pub fn define_measure(&mut self, name: &str, expression: &str) -> Result<(), DaxError> {
+        let expr = parser::parse_str(expression)?;
+        self.measures
+            .insert(name.to_string(), Measure::new(expression, expr));
+        Ok(())
+    }
+
+    /// The expression of a measure, as it was defined
+    pub fn get_measure(&self, name: &str) -> Option<&str> {
+        self.measures.get(name).map(|m| m.expression.as_str())
+    }
+
+    pub(crate) fn measures(&self) -> &HashMap<String, Measure> {
+        &self.measures
+    }
+
+    /// Add a column computed by evaluating a DAX expression once per row.
+    ///
+    /// Column references in the expression resolve to the current row's cell,
+    /// so `DATEDIFF([BirthDate], TODAY(), YEAR)` yields one value per row.
+    /// The first row that fails returns its error as the expression raised
+    /// it, so the message of `ERROR("...")` comes back unchanged.
+    pub fn add_calculated_column(
+        &mut self,
+        name: String,
+        expression: &str,
+    ) -> Result<(), DaxError> {
+        let expr = parser::parse_str(expression)?;
+        self.check_table_names(&expr)
+            .map_err(|e| locate(e, &expr, expression))?;
+        let row_count = self.num_rows();
+
+        let mut values = Vec::with_capacity(row_count);
+        for row in 0..row_count {
+            let ctx = self.root_context().with_row(self, row);
+            let value = match self.evaluate_expr(&expr, &ctx) {
+                Ok(DaxResult::Table(_)) => {
+                    return Err(DaxError::EvaluationError(format!(
+                        "Calculated column {} returned a table instead of a value",
+                        name
+                    )))
+                }
+                Err(e) => return Err(locate(e, &expr, expression)),
+                Ok(result) => scalar_value(result).unwrap_or(Value::Null),
+            };
+            values.push(value);
+        }
+
+        self.add_column(name, values)?;
+        Ok(())
+    }
+
+    /// Sum of the numbers in a column under the table's null policy, ignoring
+    /// other values. None if there's no such column or the sum is blank or an
+    /// error; see `sum_with`.
+    pub fn sum(&self, column_name: &str) -> Option<f64> {
+        self.sum_with(column_name, self.null_policy).ok().flatten()
+    }
+
+    /// Sum of the numbers in a column, ignoring other values, with Nulls
+    /// treated as `policy` says. `Ok(None)` means blank: no numbers at all,
+    /// or a Null under `NullPolicy::PropagateBlank`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dax_rust::table::{NullPolicy, Table};
+    /// use dax_rust::types::Value;
+    ///
+    /// let mut table = Table::new();
+    /// table.add_column("Sales".to_string(), vec![Value::Number(2.0), Value::Null]).unwrap();
+    /// assert_eq!(table.sum_with("Sales", NullPolicy::Skip).unwrap(), Some(2.0));
+    /// assert_eq!(table.sum_with("Sales", NullPolicy::PropagateBlank).unwrap(), None);
+    /// assert!(table.sum_with("Sales", NullPolicy::Error).is_err());
+    /// ```
+    pub fn sum_with(&self, column_name: &str, policy: NullPolicy) -> Result<Option<f64>, DaxError> {
+        if !self.nulls_allowed(column_name, policy)? {
+            return Ok(None);
+        }
+        let column = &self.columns[column_name];
+        // A numeric column has 0.0 in place of Null, which adds nothing
+        if let Some((numbers, valid)) = column.numbers() {
+            let sum = numbers.iter().fold(0.0, |acc, n| acc + n);
+            return Ok((valid.count() > 0).then_some(sum));
+        }
+        let (sum, count) = self
+            .numbers(column_name)
+            .into_iter()
+            .flatten()
+            .fold((0.0, 0), |(sum, count), n| (sum + n, count + 1));
+        Ok((count > 0).then_some(sum))
+    }
+
+    /// Average of the numbers in a column with Nulls treated as `policy` says;
+    /// blank, `Ok(None)`, under the same conditions as `sum_with`
+    pub fn average_with(
+        &self,
+        column_name: &str,
+        policy: NullPolicy,
+    ) -> Result<Option<f64>, DaxError> {
+        if !self.nulls_allowed(column_name, policy)? {
+            return Ok(None);
+        }
+        let count = self.numbers(column_name).map_or(0, Iterator::count);
+        let sum = self.sum_with(column_name, NullPolicy::Skip)?;
+        Ok(sum.filter(|_| count > 0).map(|sum| sum / count as f64))
+    }
+
+    /// Smallest number in a column with Nulls treated as `policy` says
+    pub fn min_with(&self, column_name: &str, policy: NullPolicy) -> Result<Option<f64>, DaxError> {
+        if !self.nulls_allowed(column_name, policy)? {
+            return Ok(None);
+        }
+        Ok(self.min(column_name))
+    }
+
+    /// Largest number in a column with Nulls treated as `policy` says
+    pub fn max_with(&self, column_name: &str, policy: NullPolicy) -> Result<Option<f64>, DaxError> {
+        if !self.nulls_allowed(column_name, policy)? {
+            return Ok(None);
+        }
+        Ok(self.max(column_name))
+    }
+
+    // Whether an aggregate of a column goes ahead under a policy: false when a
+    // Null makes it blank, and an error when a Null isn't allowed
+    fn nulls_allowed(&self, column_name: &str, policy: NullPolicy) -> Result<bool, DaxError> {
+        let column = self
+            .columns
+            .get(column_name)
+            .ok_or_else(|| self.unknown_column(column_name))?;
+        if policy == NullPolicy::Skip {
+            return Ok(true);
+        }
+        let first_null = match column {
+            Column::F64 { values, valid, .. } => (0..values.len()).find(|&row| !valid.get(row)),
+            Column::Mixed(values) => values.iter().position(Value::is_null),
+            // Text and boolean columns have no Nulls
+            _ => None,
+        };
+        match (first_null, policy) {
+            (None, _) => Ok(true),
+            (Some(_), NullPolicy::PropagateBlank) => Ok(false),
+            (Some(row), _) => Err(format!(
+                "Column {} is blank in row {}, which the null policy doesn't allow",
+                column_name, row
+            )
+            .into()),
+        }
+    }
+
+    /// Sum of a column of currency values in ten-thousandths, added as integers
+    /// so that no rounding creeps in. Integers count as whole amounts. Returns
+    /// None when the column holds plain numbers, which make the total a number,
+    /// or when the total overflows.
+    pub fn sum_currency(&self, column_name: &str) -> Option<i64> {
+        let column = self.columns.get(column_name)?;
+        let Column::Mixed(column) = column else {
+            return None;
+        };
+        if !column
+            .iter()
+            .any(|value| matches!(value, Value::Currency(_)))
+            || column.iter().any(|value| matches!(value, Value::Number(_)))
+        {
+            return None;
+        }
+        column.iter().try_fold(0i64, |acc, value| match value {
+            Value::Currency(c) => acc.checked_add(*c),
+            Value::Integer(i) => acc.checked_add(i.checked_mul(CURRENCY_SCALE)?),
+            _ => Some(acc),
+        })
+    }
+
+    /// Sum of a column of integers, or None when it holds other numbers or the
+    /// total overflows
+    pub fn sum_integer(&self, column_name: &str) -> Option<i64> {
+        self.integer_values(column_name)?
+            .try_fold(0i64, |acc, i| acc.checked_add(i))
+    }
+
+    // The text values of a column, for MIN and MAX when it has no numbers or dates
+    fn text_values(&self, column_name: &str) -> Option<Box<dyn Iterator<Item = &str> + '_>> {
+        let column = self.columns.get(column_name)?;
+        Some(match column {
+            Column::Str { values, .. } => Box::new(values.iter().map(String::as_str)),
+            // Only the strings some row uses
+            Column::Dict { .. } => {
+                let (codes, strings) = column.codes()?;
+                let mut used = vec![false; strings.len()];
+                for &code in codes {
+                    used[code as usize] = true;
+                }
+                Box::new(
+                    strings
+                        .iter()
+                        .zip(used)
+                        .filter(|(_, used)| *used)
+                        .map(|(s, _)| s.as_str()),
+                )
+            }
+            Column::Mixed(values) => Box::new(values.iter().filter_map(|value| match value {
+                Value::Text(s) => Some(s.as_str()),
+                _ => None,
+            })),
+            Column::F64 { .. } | Column::Bool { .. } => Box::new(std::iter::empty()),
+        })
+    }
+
+    // The integers of a column whose only numeric values are integers
+    fn integer_values(&self, column_name: &str) -> Option<impl Iterator<Item = i64> + '_> {
+        let column: &[Value] = match self.columns.get(column_name)? {
+            Column::Mixed(values) => values,
+            // Integers are only ever kept as Values
+            Column::F64 { .. } => return None,
+            _ => &[],
+        };
+        let mut numeric = column.iter().filter(|value| numeric_value(value).is_some());
+        if !numeric.all(|value| matches!(value, Value::Integer(_))) {
+            return None;
+        }
+        Some(column.iter().filter_map(|value| match value {
+            Value::Integer(i) => Some(*i),
+            _ => None,
+        }))
+    }
+
+    /// Average of the numbers in a column under the table's null policy,
+    /// ignoring other values. None if there's no such column or the average
+    /// is blank or an error; see `average_with`.
+    pub fn average(&self, column_name: &str) -> Option<f64> {
+        self.average_with(column_name, self.null_policy)
+            .ok()
+            .flatten()
+    }
+
+    pub fn count(&self, column_name: &str) -> Option<usize> {
+        self.columns.get(column_name).map(Column::len)
+    }
+
+    pub fn distinctcount(&self, column_name: &str) -> Option<usize> {
+        let column = self.columns.get(column_name)?;
+        Some(match column {
+            // Numbers equal as values equal here: 0.0 and -0.0, and every NaN
+            Column::F64 { values, valid, .. } => {
+                let mut seen = HashSet::new();
+                for (row, n) in values.iter().enumerate() {
+                    if valid.get(row) {
+                        seen.insert(match n {
+                            n if n.is_nan() => f64::NAN.to_bits(),
+                            n if *n == 0.0 => 0,
+                            n => n.to_bits(),
+                        });
+                    }
+                }
+                seen.len() + usize::from(valid.count() < values.len())
+            }
+            Column::Str { values, .. } => values.iter().collect::<HashSet<_>>().len(),
+            // Codes stand for different strings, so counting them is enough
+            Column::Dict {
+                codes, dictionary, ..
+            } => {
+                let mut seen = vec![false; dictionary.len()];
+                codes
+                    .iter()
+                    .filter(|&&code| !std::mem::replace(&mut seen[code as usize], true))
+                    .count()
+            }
+            Column::Bool { values, .. } => values.iter().collect::<HashSet<_>>().len(),
+            Column::Mixed(values) => values.iter().collect::<HashSet<_>>().len(),
+        })
+    }
+
+    /// Estimate the number of distinct values in a column with a HyperLogLog
+    /// sketch of `2^precision` registers, using far less memory than
+    /// `distinctcount` on large columns. Returns None for a missing column or a
+    /// precision outside 4 to 18.
+    pub fn approx_distinctcount(&self, column_name: &str, precision: u8) -> Option<usize> {
+        let column = self.columns.get(column_name)?;
+        let mut sketch = HyperLogLog::new(precision)?;
+        match column {
+            Column::F64 { values, valid, .. } => {
+                for (row, n) in values.iter().enumerate() {
+                    sketch.insert(&if valid.get(row) {
+                        Value::Number(*n)
+                    } else {
+                        Value::Null
+                    });
+                }
+            }
+            // Adding a value again doesn't change the sketch, so each string
+            // in the dictionary is enough
+            Column::Dict { .. } => {
+                let (codes, strings) = column.codes()?;
+                let mut seen = vec![false; strings.len()];
+                for &code in codes {
+                    if !std::mem::replace(&mut seen[code as usize], true) {
+                        sketch.insert(&Value::Text(strings[code as usize].clone()));
+                    }
+                }
+            }
+            Column::Mixed(values) => values.iter().for_each(|value| sketch.insert(value)),
+            _ => {
+                for row in 0..column.len() {
+                    sketch.insert(&column.value(row)?);
+                }
+            }
+        }
+        Some(sketch.estimate())
+    }
+
+    // MIN function
+    pub fn min(&self, column_name: &str) -> Option<f64> {
+        self.numbers(column_name)?
+            .min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+    }
+
+    // MAX function
+    pub fn max(&self, column_name: &str) -> Option<f64> {
+        self.numbers(column_name)?
+            .max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+    }
+
+    // The numbers in a column, read straight from a numeric column's storage
+    fn numbers(&self, column_name: &str) -> Option<Box<dyn Iterator<Item = f64> + '_>> {
+        let column = self.columns.get(column_name)?;
+        Some(match column.numbers() {
+            Some((numbers, valid)) if valid.count() == numbers.len() => {
+                Box::new(numbers.iter().copied())
+            }
+            Some((numbers, valid)) => Box::new(
+                numbers
+                    .iter()
+                    .enumerate()
+                    .filter(|(row, _)| valid.get(*row))
+                    .map(|(_, n)| *n),
+            ),
+            None => match column {
+                Column::Mixed(values) => Box::new(values.iter().filter_map(numeric_value)),
+                // Text and booleans aren't numbers
+                _ => Box::new(std::iter::empty()),
+            },
+        })
+    }
+
+    // AVERAGEA function: booleans and text count as numbers, blanks are skipped
+    pub fn averagea(&self, column_name: &str) -> Option<f64> {
+        let (sum, count) = self
+            .a_values(column_name)?
+            .fold((0.0, 0), |(sum, count), n| (sum + n, count + 1));
+        if count == 0 {
+            Some(0.0)
+        } else {
+            Some(sum / count as f64)
+        }
+    }
+
+    // MINA function
+    pub fn mina(&self, column_name: &str) -> Option<f64> {
+        self.a_values(column_name)?
+            .min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+    }
+
+    // MAXA function
+    pub fn maxa(&self, column_name: &str) -> Option<f64> {
+        self.a_values(column_name)?
+            .max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+    }
+
+    // Values of a column as the "A" aggregates see them
+    fn a_values(&self, column_name: &str) -> Option<Box<dyn Iterator<Item = f64> + '_>> {
+        let column = self.columns.get(column_name)?;
+        Some(match column {
+            Column::F64 { .. } => self.numbers(column_name)?,
+            // Text counts as 0
+            Column::Str { .. } | Column::Dict { .. } => {
+                Box::new(std::iter::repeat_n(0.0, column.len()))
+            }
+            Column::Bool { values, .. } => {
+                Box::new(values.iter().map(|&b| if b { 1.0 } else { 0.0 }))
+            }
+            Column::Mixed(values) => Box::new(values.iter().filter_map(coerce_a)),
+        })
+    }
+
+    /// Earliest date in a column, ignoring non-date values
+    pub fn min_date(&self, column_name: &str) -> Option<NaiveDateTime> {
+        self.dates(column_name)?.min()
+    }
+
+    /// Latest date in a column, ignoring non-date values
+    pub fn max_date(&self, column_name: &str) -> Option<NaiveDateTime> {
+        self.dates(column_name)?.max()
+    }
+
+    // The dates in a column, which only a column kept as Values can have
+    fn dates(&self, column_name: &str) -> Option<impl Iterator<Item = NaiveDateTime> + '_> {
+        let values: &[Value] = match self.columns.get(column_name)? {
+            Column::Mixed(values) => values,
+            _ => &[],
+        };
+        Some(values.iter().filter_map(|value| match value {
+            Value::DateTime(d) => Some(*d),
+            _ => None,
+        }))
+    }
+
+    fn evaluate_divide(&self, args: &[&DaxExpr], ctx: &EvalContext) -> Result<DaxResult, DaxError> {
+        expect_arguments("DIVIDE", args, 2, 3)?;
+
+        // A blank operand is blank, as SUM over no rows is
+        let numerator = match self.evaluate_expr(args[0], ctx)? {
+            n @ (DaxResult::Number(_)
+            | DaxResult::Integer(_)
+            | DaxResult::Currency(_)
+            | DaxResult::Blank) => n,
+            _ => return Err("Numerator must be a number".into()),
+        };
+        let denominator = match self.evaluate_expr(args[1], ctx)? {
+            d @ (DaxResult::Number(_)
+            | DaxResult::Integer(_)
+            | DaxResult::Currency(_)
+            | DaxResult::Blank) => d,
+            _ => return Err("Denominator must be a number".into()),
+        };
+
+        // A zero or blank denominator gives the alternate result, which can be
+        // any single value, or BLANK without one
+        if matches!(denominator, DaxResult::Blank) || numeric_result(&denominator) == Some(0.0) {
+            return match args.get(2) {
+                Some(alternate) => match self.evaluate_expr(alternate, ctx)? {
+                    DaxResult::Table(_) => Err("Alternate result must be a single value".into()),
+                    alternate => Ok(alternate),
+                },
+                None => Ok(DaxResult::Blank),
+            };
+        }
+        if matches!(numerator, DaxResult::Blank) {
+            return Ok(DaxResult::Blank);
+        }
+        arithmetic(BinaryOperator::Divide, &numerator, &denominator)
+    }
+
+    // DIVIDE function with optional alternate result
+    pub fn divide(
+        &self,
+        numerator: f64,
+        denominator: f64,
+        alternate_result: Option<f64>,
+    ) -> Option<f64> {
+        if denominator == 0.0 {
+            alternate_result
+        } else {
+            Some(numerator / denominator)
+        }
+    }
+
+    /// Evaluate a DAX expression against the table
+    pub fn evaluate_dax(&self, expression: &str) -> Result<DaxResult, DaxError> {
+        let expr = parser::parse_str(expression)?;
+        self.check_table_names(&expr)
+            .and_then(|()| self.evaluate_expr(&expr, &self.root_context()))
+            .map_err(|e| locate(e, &expr, expression))
+    }
+
+    /// Evaluate a DAX expression that may call the functions in `registry` as
+    /// well as the built-ins
+    pub fn evaluate_dax_with(
+        &self,
+        registry: &FunctionRegistry,
+        expression: &str,
+    ) -> Result<DaxResult, DaxError> {
+        let expr = parser::parse_str(expression)?;
+        let ctx = EvalContext {
+            functions: Some(registry),
+            ..self.root_context()
+        };
+        self.check_table_names(&expr)
+            .and_then(|()| self.evaluate_expr(&expr, &ctx))
+            .map_err(|e| locate(e, &expr, expression))
+    }
+
+    pub(crate) fn evaluate_expr(
+        &self,
+        expr: &DaxExpr,
+        ctx: &EvalContext,
+    ) -> Result<DaxResult, DaxError> {
+        match ctx.trace {
+            Some(tracer) => self.evaluate_traced(expr, ctx, tracer),
+            None => self.evaluate_node(expr, ctx),
+        }
+    }
+
+    pub(crate) fn evaluate_node(
+        &self,
+        expr: &DaxExpr,
+        ctx: &EvalContext,
+    ) -> Result<DaxResult, DaxError> {
+        match expr {
+            DaxExpr::Literal(value) => Ok(DaxResult::from(value)),
+            DaxExpr::Variable(name) => match (ctx.local(name), boolean_keyword(name)) {
+                (Some(value), _) => Ok(value.clone()),
+                (None, Some(b)) => Ok(DaxResult::Boolean(b)),
+                (None, None) => match self.evaluate_variable(name, ctx) {
+                    Some(result) => result,
+                    // The table's name refers to the table itself, as far as the
+                    // filters let it be seen; a row context doesn't narrow it
+                    None if self.answers_to(name) => {
+                        Ok(DaxResult::Table(self.select_rows(&self.visible_rows(ctx))))
+                    }
+                    None => Err(DaxError::UnknownTable {
+                        name: name.clone(),
+                        span: None,
+                    }),
+                },
+            },
+            DaxExpr::ColumnRef { name: col_name, .. } => {
+                if !self.has_column(col_name)
+                    && ctx.measures.is_some_and(|m| m.contains_key(col_name))
+                {
+                    return self.evaluate_measure(col_name, ctx);
+                }
+                // The innermost row that has the column; an empty cell is blank
+                match ctx.cell(col_name) {
+                    Some(value) => Ok(DaxResult::from(&value)),
+                    None if ctx.rows.is_empty() => Err(format!(
+                        "Column [{}] cannot be used as a value outside a row context",
+                        col_name
+                    )
+                    .into()),
+                    None => Err(self.unknown_column(col_name)),
+                }
+            }
+            DaxExpr::FunctionCall { name, args, .. } => {
+                let args: Vec<&DaxExpr> = args.iter().collect();
+                self.evaluate_function(name, &args, ctx)
+            }
+            DaxExpr::VarBlock { variables, body } => self.evaluate_var_block(variables, body, ctx),
+            DaxExpr::TableConstructor(items) => {
+                let items: Vec<&DaxExpr> = items.iter().collect();
+                self.table_constructor(&items, ctx).map(DaxResult::Table)
+            }
+            DaxExpr::UnaryOp { op, operand } => {
+                let value = self.evaluate_expr(operand, ctx)?;
+                let op = match op {
+                    UnaryOperator::Plus => BinaryOperator::Add,
+                    UnaryOperator::Minus => BinaryOperator::Subtract,
+                };
+                // A sign keeps a blank blank, where 0 - BLANK() would be 0
+                match value {
+                    DaxResult::Blank => Ok(DaxResult::Blank),
+                    value => arithmetic(op, &DaxResult::Integer(0), &value),
+                }
+            }
+            DaxExpr::BinaryOp { op, left, right } => {
+                let left = self.evaluate_expr(left, ctx)?;
+                let right = self.evaluate_expr(right, ctx)?;
+                if !op.is_comparison() {
+                    return arithmetic(*op, &left, &right);
+                }
+                let ordering = compare_operands(*op, &left, &right)?;
+                Ok(DaxResult::Boolean(match op {
+                    BinaryOperator::Equal => ordering == Ordering::Equal,
+                    BinaryOperator::NotEqual => ordering != Ordering::Equal,
+                    BinaryOperator::Less => ordering == Ordering::Less,
+                    BinaryOperator::LessEqual => ordering != Ordering::Greater,
+                    BinaryOperator::Greater => ordering == Ordering::Greater,
+                    _ => ordering != Ordering::Less,
+                }))
+            }
+        }
+    }
+
+    // The context an expression starts in, with no row or filters but with the
+    // table's measures
+    // Each variable is evaluated once, in the context the block is evaluated
+    // in, and the body sees all of them
+    fn evaluate_var_block(
+        &self,
+        variables: &[(String, DaxExpr)],
+        body: &DaxExpr,
+        ctx: &EvalContext,
+    ) -> Result<DaxResult, DaxError> {
+        let mut values: Vec<(&str, DaxResult)> = Vec::with_capacity(variables.len());
+        for (name, expr) in variables {
+            let value = self.evaluate_expr(expr, &ctx.with_locals(&values))?;
+            values.push((name, value));
+        }
+        self.evaluate_expr(body, &ctx.with_locals(&values))
+    }
+
+    pub(crate) fn root_context(&self) -> EvalContext<'_> {
+        EvalContext {
+            measures: Some(&self.measures),
+            ..EvalContext::default()
+        }
+    }
+
+    // A measure turns the row context into filters, like CALCULATE. The chain
+    // of measures being evaluated is kept to catch one that refers to itself.
+    fn evaluate_measure(&self, name: &str, ctx: &EvalContext) -> Result<DaxResult, DaxError> {
+        let (name, measure) = match ctx.measures.and_then(|m| m.get_key_value(name)) {
+            Some(found) => found,
+            None => return Err(self.unknown_column(name)),
+        };
+        if let Some(start) = ctx.measure_chain.iter().position(|m| *m == name) {
+            let mut cycle: Vec<String> = ctx.measure_chain[start..]
+                .iter()
+                .map(|m| m.to_string())
+                .collect();
+            cycle.push(name.clone());
+            return Err(DaxError::CircularMeasure { cycle });
+        }
+        let mut inner = self.transition_context(ctx);
+        inner.measure_chain.push(name);
+        // The variables of the expression using the measure aren't in its view
+        inner.locals.clear();
+        self.check_table_names(&measure.expr)
+            .and_then(|()| self.evaluate_expr(&measure.expr, &inner))
+            .map_err(|e| locate(e, &measure.expr, &measure.expression))
+    }
+
+    // Function names are case-insensitive: dispatch on the upper-case name, but
+    // report an unknown function the way it was written
+    fn evaluate_function(
+        &self,
+        written: &str,
+        args: &[&DaxExpr],
+        ctx: &EvalContext,
+    ) -> Result<DaxResult, DaxError> {
+        let upper = written.to_ascii_uppercase();
+        let name = upper.as_str();
+        // Registered functions come first, as they may replace a built-in
+        if let Some(function) = ctx.functions.and_then(|registry| registry.get(name)) {
+            expect_arguments(name, args, function.min_args, function.max_args)?;
+            let mut values = Vec::with_capacity(args.len());
+            for arg in args {
+                values.push(self.evaluate_expr(arg, ctx)?);
+            }
+            return (function.function)(&values);
+        }
+        if let Some(b) = boolean_keyword(name) {
+            expect_arguments(name, args, 0, 0)?;
+            return Ok(DaxResult::Boolean(b));
+        }
+        match name {
+            "SUM"
+            | "AVERAGE"
+            | "MIN"
+            | "MAX"
+            | "AVERAGEA"
+            | "MINA"
+            | "MAXA"
+            | "DISTINCTCOUNT"
+            | "APPROXIMATEDISTINCTCOUNT"
+            | "COUNTBLANK" => self.evaluate_aggregate(name, args, ctx),
+            "DIVIDE" => self.evaluate_divide(args, ctx),
+            "DATATABLE" => self.evaluate_datatable(args, ctx),
+            "DATE" => {
+                expect_arguments(name, args, 3, 3)?;
+                let year = self.number_argument(name, args[0], ctx)?;
+                let month = self.number_argument(name, args[1], ctx)?;
+                let day = self.number_argument(name, args[2], ctx)?;
+                dates::date(year as i64, month as i64, day as i64)
+                    .map(DaxResult::DateTime)
+                    .ok_or_else(|| "DATE arguments are out of range".into())
+            }
+            "TIME" => {
+                expect_arguments(name, args, 3, 3)?;
+                let hour = self.number_argument(name, args[0], ctx)?;
+                let minute = self.number_argument(name, args[1], ctx)?;
+                let second = self.number_argument(name, args[2], ctx)?;
+                dates::time(hour as i64, minute as i64, second as i64)
+                    .map(DaxResult::DateTime)
+                    .ok_or_else(|| "TIME arguments are out of range".into())
+            }
+            "YEAR" | "MONTH" | "DAY" | "HOUR" | "MINUTE" | "SECOND" => {
+                expect_arguments(name, args, 1, 1)?;
+                let date = self.date_argument(name, args[0], ctx)?;
+                let (hour, minute, second) = dates::time_parts(&date);
+                let part = match name {
+                    "YEAR" => date.year() as u32,
+                    "MONTH" => date.month(),
+                    "DAY" => date.day(),
+                    "HOUR" => hour,
+                    "MINUTE" => minute,
+                    _ => second,
+                };
+                Ok(DaxResult::Number(part as f64))
+            }
+            "WEEKDAY" | "WEEKNUM" => {
+                expect_arguments(name, args, 1, 2)?;
+                let date = self.date_argument(name, args[0], ctx)?;
+                let return_type = match args.get(1) {
+                    Some(arg) => self.number_argument(name, arg, ctx)? as i64,
+                    None => 1,
+                };
+                let result = if name == "WEEKDAY" {
+                    dates::weekday(&date, return_type)
+                } else {
+                    dates::weeknum(&date, return_type)
+                };
+                result.map(|n| DaxResult::Number(n as f64)).ok_or_else(|| {
+                    format!("Unsupported {} return type {}", name, return_type).into()
+                })
+            }
+            "BLANK" => {
+                expect_arguments(name, args, 0, 0)?;
+                Ok(DaxResult::Blank)
+            }
+            "TODAY" => {
+                expect_arguments(name, args, 0, 0)?;
+                Ok(DaxResult::DateTime(
+                    Local::now().date_naive().and_time(NaiveTime::MIN),
+                ))
+            }
+            "NOW" => {
+                expect_arguments(name, args, 0, 0)?;
+                Ok(DaxResult::DateTime(Local::now().naive_local()))
+            }
+            "DATEDIFF" => {
+                expect_arguments(name, args, 3, 3)?;
+                let start = self.date_argument(name, args[0], ctx)?;
+                let end = self.date_argument(name, args[1], ctx)?;
+                let interval = interval_argument(name, args[2])?;
+                Ok(DaxResult::Number(
+                    dates::datediff(&start, &end, interval) as f64
+                ))
+            }
+            "NETWORKDAYS" => {
+                expect_arguments(name, args, 2, 4)?;
+                let start = self.date_argument(name, args[0], ctx)?;
+                let end = self.date_argument(name, args[1], ctx)?;
+                let code = match args.get(2) {
+                    Some(arg) => self.number_argument(name, arg, ctx)?.trunc() as i64,
+                    None => 1,
+                };
+                let weekend = dates::weekend_days(code)
+                    .ok_or_else(|| format!("NETWORKDAYS received an invalid weekend {}", code))?;
+                let mut holidays = HashSet::new();
+                if let Some(arg) = args.get(3) {
+                    let table = self.table_argument(name, arg, ctx)?;
+                    for column in table.columns.values().map(ColumnValues::new) {
+                        holidays.extend(column.iter().filter_map(|value| match value {
+                            Value::DateTime(d) => Some(d.date()),
+                            _ => None,
+                        }));
+                    }
+                }
+                let days = dates::networkdays(&start, &end, &weekend, &holidays);
+                Ok(DaxResult::Number(days as f64))
+            }
+            "YEARFRAC" => {
+                expect_arguments(name, args, 2, 3)?;
+                let start = self.date_argument(name, args[0], ctx)?;
+                let end = self.date_argument(name, args[1], ctx)?;
+                let basis = match args.get(2) {
+                    Some(arg) => self.number_argument(name, arg, ctx)?.trunc() as i64,
+                    None => 0,
+                };
+                dates::yearfrac(&start, &end, basis)
+                    .map(DaxResult::Number)
+                    .ok_or_else(|| format!("YEARFRAC received an invalid basis {}", basis).into())
+            }
+            "EDATE" | "EOMONTH" => {
+                expect_arguments(name, args, 2, 2)?;
+                let date = self.date_argument(name, args[0], ctx)?;
+                let months = self.number_argument(name, args[1], ctx)?.trunc() as i64;
+                let result = if name == "EDATE" {
+                    dates::add_months(&date, months)
+                } else {
+                    dates::end_of_month(&date, months)
+                };
+                result
+                    .map(DaxResult::DateTime)
+                    .ok_or_else(|| format!("{} result is out of range", name).into())
+            }
+            "DATEADD" => {
+                expect_arguments(name, args, 3, 3)?;
+                if let (None, DaxExpr::ColumnRef { name: col_name, .. }) = (ctx.row(), args[0]) {
+                    let count = self.number_argument(name, args[1], ctx)?.trunc() as i64;
+                    let interval = interval_argument(name, args[2])?;
+                    return Ok(DaxResult::Table(
+                        self.shifted_dates(col_name, count, interval, ctx),
+                    ));
+                }
+                let date = self.date_argument(name, args[0], ctx)?;
+                let count = self.number_argument(name, args[1], ctx)?.trunc() as i64;
+                let interval = interval_argument(name, args[2])?;
+                dates::shift(&date, count, interval)
+                    .map(DaxResult::DateTime)
+                    .ok_or_else(|| format!("{} result is out of range", name).into())
+            }
+            "CALENDAR" => {
+                expect_arguments(name, args, 2, 2)?;
+                let start = self.date_argument(name, args[0], ctx)?;
+                let end = self.date_argument(name, args[1], ctx)?;
+                if start > end {
+                    return Err("CALENDAR start date must not be after its end date".into());
+                }
+                Ok(DaxResult::Table(calendar(start, end)))
+            }
+            "CALENDARAUTO" => {
+                expect_arguments(name, args, 0, 1)?;
+                let year_end_month = match args.first() {
+                    Some(arg) => self.number_argument(name, arg, ctx)? as i64,
+                    None => 12,
+                };
+                if !(1..=12).contains(&year_end_month) {
+                    return Err("CALENDARAUTO fiscal year end month must be 1 to 12".into());
+                }
+                let dates = self.names.iter().flat_map(|col_name| {
+                    self.min_date(col_name)
+                        .into_iter()
+                        .chain(self.max_date(col_name))
+                });
+                let (first, last) = match (dates.clone().min(), dates.max()) {
+                    (Some(first), Some(last)) => (first, last),
+                    _ => return Err("CALENDARAUTO found no date columns".into()),
+                };
+                let start = dates::fiscal_year_start(&first, year_end_month)
+                    .ok_or_else(|| "CALENDARAUTO range is out of range".to_string())?;
+                let end = dates::fiscal_year_start(&last, year_end_month)
+                    .and_then(|next| dates::add_months(&next, 12))
+                    .and_then(|next| dates::shift(&next, -1, Interval::Day))
+                    .ok_or_else(|| "CALENDARAUTO range is out of range".to_string())?;
+                Ok(DaxResult::Table(calendar(start, end)))
+            }
+            "CALCULATE" => {
+                let (expression, filter_args) = args
+                    .split_first()
+                    .ok_or_else(|| "CALCULATE requires an expression".to_string())?;
+                let mut filters = Vec::new();
+                for arg in filter_args {
+                    filters.push(self.filter_argument(name, arg, ctx)?);
+                }
+                self.calculate(expression, filters, ctx)
+            }
+            "FIRSTDATE"
+            | "LASTDATE"
+            | "STARTOFMONTH"
+            | "STARTOFQUARTER"
+            | "STARTOFYEAR"
+            | "ENDOFMONTH"
+            | "ENDOFQUARTER"
+            | "ENDOFYEAR"
+            | "PREVIOUSDAY"
+            | "PREVIOUSMONTH"
+            | "PREVIOUSQUARTER"
+            | "PREVIOUSYEAR"
+            | "NEXTDAY"
+            | "NEXTMONTH"
+            | "NEXTQUARTER"
+            | "NEXTYEAR"
+            | "DATESMTD"
+            | "DATESQTD"
+            | "DATESYTD"
+            | "TOTALMTD"
+            | "TOTALQTD"
+            | "TOTALYTD"
+            | "DATESBETWEEN"
+            | "DATESINPERIOD"
+            | "SAMEPERIODLASTYEAR"
+            | "OPENINGBALANCEMONTH"
+            | "OPENINGBALANCEQUARTER"
+            | "OPENINGBALANCEYEAR"
+            | "CLOSINGBALANCEMONTH"
+            | "CLOSINGBALANCEQUARTER"
+            | "CLOSINGBALANCEYEAR" => self.evaluate_time_intelligence(name, args, ctx),
+            "IF" => {
+                expect_arguments(name, args, 2, 3)?;
+                if self.boolean_argument(name, args[0], ctx)? {
+                    self.evaluate_expr(args[1], ctx)
+                } else {
+                    args.get(2)
+                        .map_or(Ok(DaxResult::Blank), |arg| self.evaluate_expr(arg, ctx))
+                }
+            }
+            "SWITCH" => {
+                if args.len() < 3 {
+                    return Err("SWITCH expects at least 3 arguments".into());
+                }
+                let value = self.evaluate_expr(args[0], ctx)?;
+                let mut cases = args[1..].chunks_exact(2);
+                for case in cases.by_ref() {
+                    if same_value(&value, &self.evaluate_expr(case[0], ctx)?) {
+                        return self.evaluate_expr(case[1], ctx);
+                    }
+                }
+                // An odd trailing argument is the result when nothing matches
+                match cases.remainder() {
+                    [default] => self.evaluate_expr(default, ctx),
+                    _ => Ok(DaxResult::Blank),
+                }
+            }
+            "FILTER" => {
+                expect_arguments(name, args, 2, 2)?;
+                let table = self.table_argument(name, args[0], ctx)?;
+                let mut kept = Vec::new();
+                for row in 0..table.num_rows() {
+                    let row_ctx = ctx.with_row(&table, row);
+                    if table.boolean_argument(name, args[1], &row_ctx)? {
+                        kept.push(row);
+                    }
+                }
+                Ok(DaxResult::Table(table.select_rows(&kept)))
+            }
+            // The message is passed through untouched so it reaches the caller as written
+            "ERROR" => {
+                expect_arguments(name, args, 1, 1)?;
+                Err(self.text_argument(name, args[0], ctx)?.into())
+            }
+            "IFERROR" => {
+                expect_arguments(name, args, 2, 2)?;
+                match self.evaluate_expr(args[0], ctx) {
+                    Err(_) => self.evaluate_expr(args[1], ctx),
+                    result => result,
+                }
+            }
+            "NORM.DIST" | "NORM.S.DIST" | "NORM.INV" | "NORM.S.INV" | "T.DIST" | "CHISQ.DIST" => {
+                self.evaluate_distribution(name, args, ctx)
+            }
+            "EXACT" => {
+                expect_arguments(name, args, 2, 2)?;
+                let a = self.text_argument(name, args[0], ctx)?;
+                let b = self.text_argument(name, args[1], ctx)?;
+                Ok(DaxResult::Boolean(a == b))
+            }
+            "REPT" => {
+                expect_arguments(name, args, 2, 2)?;
+                let text = self.text_argument(name, args[0], ctx)?;
+                let count = self.number_argument(name, args[1], ctx)?.trunc();
+                if count < 0.0 {
+                    return Err("REPT count cannot be negative".into());
+                }
+                if text.len() as f64 * count > MAX_TEXT_LENGTH as f64 {
+                    return Err(
+                        format!("REPT result would exceed {} bytes", MAX_TEXT_LENGTH).into(),
+                    );
+                }
+                Ok(DaxResult::Text(text.repeat(count as usize)))
+            }
+            "UNICHAR" => {
+                expect_arguments(name, args, 1, 1)?;
+                let code = self.number_argument(name, args[0], ctx)?.trunc();
+                u32::try_from(code as i64)
+                    .ok()
+                    .filter(|&code| code > 0)
+                    .and_then(char::from_u32)
+                    .map(|c| DaxResult::Text(c.to_string()))
+                    .ok_or_else(|| {
+                        format!("UNICHAR received an invalid code point {}", code).into()
+                    })
+            }
+            "UNICODE" => {
+                expect_arguments(name, args, 1, 1)?;
+                let text = self.text_argument(name, args[0], ctx)?;
+                text.chars()
+                    .next()
+                    .map(|c| DaxResult::Number(c as u32 as f64))
+                    .ok_or_else(|| "UNICODE expects non-empty text".into())
+            }
+            "COMBINEVALUES" => {
+                if args.len() < 3 {
+                    return Err("COMBINEVALUES expects at least 3 arguments".into());
+                }
+                let delimiter = self.text_argument(name, args[0], ctx)?;
+                let parts = args[1..]
+                    .iter()
+                    .map(|arg| self.coerced_text_argument(name, arg, ctx))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(DaxResult::Text(parts.join(&delimiter)))
+            }
+            "ADDCOLUMNS" => {
+                if args.len() < 3 || args.len().is_multiple_of(2) {
+                    return Err("ADDCOLUMNS expects a table and name/expression pairs".into());
+                }
+                let mut table = self.table_argument(name, args[0], ctx)?;
+                for pair in args[1..].chunks(2) {
+                    let col_name = self.text_argument(name, pair[0], ctx)?;
+                    let mut values = Vec::with_capacity(table.num_rows());
+                    for row in 0..table.num_rows() {
+                        let row_ctx = ctx.with_row(&table, row);
+                        let result = table.evaluate_expr(pair[1], &row_ctx)?;
+                        values.push(scalar_value(result).ok_or_else(|| {
+                            format!("ADDCOLUMNS expression for {} must be a scalar", col_name)
+                        })?);
+                    }
+                    table.add_column(col_name, values)?;
+                }
+                Ok(DaxResult::Table(table))
+            }
+            "SUMX" => {
+                expect_arguments(name, args, 2, 2)?;
+                let table = self.table_argument(name, args[0], ctx)?;
+                let mut total = None;
+                for row in 0..table.num_rows() {
+                    let row_ctx = ctx.with_row(&table, row);
+                    // Blanks are skipped, as SUM skips empty cells
+                    match table.evaluate_expr(args[1], &row_ctx)? {
+                        DaxResult::Blank => {}
+                        result => {
+                            let n = numeric_result(&result)
+                                .ok_or("SUMX expression must evaluate to a number")?;
+                            total = Some(total.unwrap_or(0.0) + n);
+                        }
+                    }
+                }
+                Ok(total.map_or(DaxResult::Blank, DaxResult::Number))
+            }
+            "OFFSET" | "INDEX" | "WINDOW" => self.evaluate_window(name, args, ctx),
+            "RANK" | "ROWNUMBER" => self.evaluate_rank(name, args, ctx),
+            "ORDERBY" => Err("ORDERBY can only be used inside a window function".into()),
+            "SUMMARIZECOLUMNS" => self.evaluate_summarizecolumns(name, args, ctx),
+            "SUMMARIZE" => self.evaluate_summarize(name, args, ctx),
+            "TOPN" | "SAMPLE" => {
+                expect_arguments(name, args, 3, 4)?;
+                let count = self.number_argument(name, args[0], ctx)?.trunc().max(0.0) as usize;
+                let table = self.table_argument(name, args[1], ctx)?;
+                // TOPN takes the largest values unless told otherwise, SAMPLE goes in ascending order
+                let descending = match args.get(3) {
+                    Some(arg) => self.descending_argument(name, arg, ctx)?,
+                    None => name == "TOPN",
+                };
+                let sorted = table.sorted_rows(args[2], descending, ctx)?;
+                let rows = if name == "TOPN" {
+                    sorted.into_iter().take(count).collect()
+                } else {
+                    evenly_spaced(&sorted, count)
+                };
+                Ok(DaxResult::Table(table.select_rows(&rows)))
+            }
+            "COUNTROWS" => {
+                expect_arguments(name, args, 0, 1)?;
+                let row_count = match args.first() {
+                    Some(arg) => self.table_argument(name, arg, ctx)?.num_rows(),
+                    None => self.visible_rows(ctx).len(),
+                };
+                Ok(DaxResult::Number(row_count as f64))
+            }
+            "KEEPFILTERS" | "REMOVEFILTERS" => {
+                Err(format!("{} can only be used as a CALCULATE filter argument", name).into())
+            }
+            "USERELATIONSHIP" | "CROSSFILTER" => Err(no_relationships(name)),
+            "TREATAS" => self.evaluate_treatas(args, ctx),
+            "CURRENCY" => {
+                expect_arguments(name, args, 1, 1)?;
+                let amount = match self.evaluate_expr(args[0], ctx)? {
+                    DaxResult::Blank => return Ok(DaxResult::Blank),
+                    DaxResult::Currency(c) => return Ok(DaxResult::Currency(c)),
+                    DaxResult::Text(s) => s
+                        .trim()
+                        .parse::<f64>()
+                        .map_err(|_| format!("CURRENCY cannot convert \"{}\" to a number", s))?,
+                    _ => self.number_argument(name, args[0], ctx)?,
+                };
+                currency_units(amount)
+                    .map(DaxResult::Currency)
+                    .ok_or_else(|| "CURRENCY argument is out of range".into())
+            }
+            "ISEMPTY" => {
+                expect_arguments(name, args, 1, 1)?;
+                let table = self.table_argument(name, args[0], ctx)?;
+                Ok(DaxResult::Boolean(table.num_rows() == 0))
+            }
+            _ => Err(DaxError::UnknownFunction {
+                name: written.to_string(),
+                span: None,
+            }),
+        }
+    }
+
+    // A `{...}` literal: a one-column table named Value, one row per element
+    fn table_constructor(&self, args: &[&DaxExpr], ctx: &EvalContext) -> Result<Table, DaxError> {
+        let mut values = Vec::with_capacity(args.len());
+        for arg in args {
+            let value = scalar_value(self.evaluate_expr(arg, ctx)?)
+                .ok_or_else(|| "Table constructor elements must be single values".to_string())?;
+            values.push(value);
+        }
+        Ok(Table::single_column("Value".to_string(), values))
+    }
+
+    // The rows of a table with its columns taken as the named columns of this
+    // one, so that as a CALCULATE filter it restricts those columns
+    fn evaluate_treatas(
+        &self,
+        args: &[&DaxExpr],
+        ctx: &EvalContext,
+    ) -> Result<DaxResult, DaxError> {
+        if args.len() < 2 {
+            return Err("TREATAS expects a table and at least one column".into());
+        }
+        let source = self.table_argument("TREATAS", args[0], ctx)?;
+        let mut targets = Vec::new();
+        for arg in &args[1..] {
+            let name = column_name("TREATAS", arg)?;
+            if !self.has_column(name) {
+                return Err(self.unknown_column(name));
+            }
+            targets.push(name.to_string());
+        }
+
+        let source_names = source.column_names();
+        if source_names.len() != targets.len() {
+            return Err(format!(
+                "TREATAS was given {} columns for a table with {}",
+                targets.len(),
+                source_names.len()
+            )
+            .into());
+        }
+        // Several columns are matched by name, as tables don't keep their column order
+        let sources = if let [only] = source_names {
+            vec![only.clone()]
+        } else if targets.iter().all(|t| source_names.contains(t)) {
+            targets.clone()
+        } else {
+            return Err(
+                "TREATAS can only map several columns onto columns of the same names".into(),
+            );
+        };
+
+        let mut table = Table::new();
+        for (source_name, target) in sources.iter().zip(targets) {
+            let values = source
+                .get_column(source_name)
+                .map(|values| values.to_vec())
+                .unwrap_or_default();
+            table.add_column(target, values)?;
+        }
+        Ok(DaxResult::Table(table))
+    }
+
+    // Statistical distributions: densities or cumulative probabilities, and normal quantiles
+    fn evaluate_distribution(
+        &self,
+        name: &str,
+        args: &[&DaxExpr],
+        ctx: &EvalContext,
+    ) -> Result<DaxResult, DaxError> {
+        let (min, max) = match name {
+            "NORM.DIST" => (4, 4),
+            "NORM.INV" | "T.DIST" | "CHISQ.DIST" => (3, 3),
+            "NORM.S.DIST" => (2, 2),
+            _ => (1, 1),
+        };
+        expect_arguments(name, args, min, max)?;
+        let x = self.number_argument(name, args[0], ctx)?;
+        // The cumulative flag, where there is one, is always the last argument
+        let cumulative = match name {
+            "NORM.INV" | "NORM.S.INV" => false,
+            _ => self.boolean_argument(name, args[args.len() - 1], ctx)?,
+        };
+        let (mean, sd) = match name {
+            "NORM.DIST" | "NORM.INV" => {
+                let mean = self.number_argument(name, args[1], ctx)?;
+                let sd = self.number_argument(name, args[2], ctx)?;
+                if sd <= 0.0 {
+                    return Err(format!("{} requires a positive standard deviation", name).into());
+                }
+                (mean, sd)
+            }
+            _ => (0.0, 1.0),
+        };
+        let degrees_of_freedom = || -> Result<f64, DaxError> {
+            let df = self.number_argument(name, args[1], ctx)?.trunc();
+            if df < 1.0 {
+                return Err(format!("{} requires at least 1 degree of freedom", name).into());
+            }
+            Ok(df)
+        };
+
+        let result = match name {
+            "NORM.DIST" | "NORM.S.DIST" => {
+                let z = (x - mean) / sd;
+                if cumulative {
+                    stats::normal_cdf(z)
+                } else {
+                    stats::normal_pdf(z) / sd
+                }
+            }
+            "NORM.INV" | "NORM.S.INV" => {
+                let z = stats::normal_inverse(x)
+                    .ok_or_else(|| format!("{} requires a probability between 0 and 1", name))?;
+                mean + z * sd
+            }
+            "T.DIST" => {
+                let df = degrees_of_freedom()?;
+                if cumulative {
+                    stats::t_cdf(x, df)
+                } else {
+                    stats::t_pdf(x, df)
+                }
+            }
+            _ => {
+                let df = degrees_of_freedom()?;
+                if x < 0.0 {
+                    return Err("CHISQ.DIST requires a non-negative value".into());
+                }
+                if cumulative {
+                    stats::chi_squared_cdf(x, df)
+                } else {
+                    stats::chi_squared_pdf(x, df)
+                }
+            }
+        };
+        Ok(DaxResult::Number(result))
+    }
+
+    // Evaluate an expression with filters applied on top of the current context.
+    // A row context is turned into an equivalent filter first (context transition).
+    pub(crate) fn calculate(
+        &self,
+        expression: &DaxExpr,
+        filters: Vec<FilterModifier>,
+        ctx: &EvalContext,
+    ) -> Result<DaxResult, DaxError> {
+        let mut inner = self.transition_context(ctx);
+        inner.filters.apply(filters);
+        self.evaluate_expr(expression, &inner)
+    }
+
+    // Replace the row context, if any, with a filter on that row's values
+    pub(crate) fn transition_context<'a>(&self, ctx: &EvalContext<'a>) -> EvalContext<'a> {
+        let mut filters = ctx.filters.clone();
+        if let Some(row) = ctx.row() {
+            let columns = self.column_names().to_vec();
+            let values = columns
+                .iter()
+                .map(|name| self.columns[name].value(row).unwrap_or(Value::Null))
+                .collect();
+            filters.replace(Filter::new(columns, HashSet::from([values])));
+        }
+        EvalContext {
+            rows: Vec::new(),
+            filters,
+            ..ctx.clone()
+        }
+    }
+
+    // Rows that pass the filter context
+    pub(crate) fn visible_rows(&self, ctx: &EvalContext) -> Vec<usize> {
+        ctx.filters.visible_rows(self)
+    }
 
-#[derive(Debug)]
-pub struct Table {
-    columns: HashMap<String, Vec<Value>>,
-}
+    // The rows visible in the filter context as a new table, or None when nothing is filtered
+    fn visible_table(&self, ctx: &EvalContext) -> Option<Table> {
+        if ctx.filters.is_empty() {
+            return None;
+        }
+        Some(self.select_rows(&self.visible_rows(ctx)))
+    }
 
-impl Table {
-    pub fn new() -> Self {
-        Table {
-            columns: HashMap::new(),
+    // New table holding the given rows, in order
+    pub(crate) fn select_rows(&self, rows: &[usize]) -> Table {
+        let mut table = Table::new();
+        table.null_policy = self.null_policy;
+        table.name = self.name.clone();
+        for name in &self.names {
+            table.store_column(name.clone(), self.columns[name].take(rows));
         }
+        table
     }
 
-    pub fn get_column(&self, name: &str) -> Option<&Vec<Value>> {
-        self.columns.get(name)
+    // Rearrange every column's rows into the given order, in place
+    pub(crate) fn reorder_rows(&mut self, rows: &[usize]) {
+        for column in self.columns.values_mut() {
+            *column = column.take(rows);
+        }
     }
 
-    pub fn add_column(&mut self, name: String, values: Vec<Value>) {
-        self.columns.insert(name, values);
+    /// The names of the columns, in the order they were added
+    pub fn column_names(&self) -> &[String] {
+        &self.names
     }
 
-    /// Calculate sum of numeric values in a column, ignoring non-numeric values
-    pub fn sum(&self, column_name: &str) -> Option<f64> {
-        let column = self.get_column(column_name)?;
+    // Whether an expression may refer to the table by this name
+    pub(crate) fn answers_to(&self, name: &str) -> bool {
+        self.name
+            .as_deref()
+            .is_none_or(|own| own.eq_ignore_ascii_case(name))
+    }
 
-        let sum = column.iter().fold(0.0, |acc, value| {
-            if let Value::Number(n) = value {
-                acc + n
-            } else {
-                acc // Skip non-numeric values
-            }
-        });
+    // The first `Table[Column]` whose table isn't this one is an error, as
+    // only this table is in play
+    pub(crate) fn check_table_names(&self, expr: &DaxExpr) -> Result<(), DaxError> {
+        match expr.find(&|node| {
+            matches!(node, DaxExpr::ColumnRef { table: Some(table), .. } if !self.answers_to(table))
+        }) {
+            Some(DaxExpr::ColumnRef {
+                table: Some(table), ..
+            }) => Err(DaxError::UnknownTable {
+                name: table.clone(),
+                span: None,
+            }),
+            _ => Ok(()),
+        }
+    }
 
-        Some(sum) // Return Some even if sum is 0.0
+    // An unknown column error, suggesting the columns whose names are a couple
+    // of edits away, closest first
+    pub(crate) fn unknown_column(&self, name: &str) -> DaxError {
+        let mut candidates: Vec<(usize, &String)> = self
+            .columns
+            .keys()
+            .map(|column| (edit_distance(name, column), column))
+            .filter(|(distance, column)| {
+                *distance <= 2.max(name.chars().count() / 3) && *distance < column.len()
+            })
+            .collect();
+        candidates.sort();
+        DaxError::UnknownColumn {
+            name: name.to_string(),
+            span: None,
+            suggestions: candidates.into_iter().map(|(_, c)| c.clone()).collect(),
+        }
     }
 
-    /// Calculate average of numeric values in a column, ignoring non-numeric values
-    pub fn average(&self, column_name: &str) -> Option<f64> {
-        let column = self.get_column(column_name)?;
-        let mut sum = 0.0;
-        let mut count = 0;
+    // Column aggregates, computed over the rows visible in the filter context
+    fn evaluate_aggregate(
+        &self,
+        name: &str,
+        args: &[&DaxExpr],
+        ctx: &EvalContext,
+    ) -> Result<DaxResult, DaxError> {
+        let col_name = column_argument(name, args)?;
+        if !self.has_column(col_name) {
+            return Err(self.unknown_column(col_name));
+        }
+        let filtered = self.visible_table(ctx);
+        let table = filtered.as_ref().unwrap_or(self);
+        // Like DAX, aggregating no rows at all gives blank rather than zero
+        if table.num_rows() == 0 {
+            return Ok(DaxResult::Blank);
+        }
+        if matches!(name, "SUM" | "AVERAGE" | "MIN" | "MAX")
+            && !table.nulls_allowed(col_name, self.null_policy)?
+        {
+            return Ok(DaxResult::Blank);
+        }
 
-        for value in column {
-            if let Value::Number(n) = value {
-                sum += n;
-                count += 1;
+        match name {
+            // Nor do rows with no numbers in them
+            "SUM" | "AVERAGE"
+                if table
+                    .numbers(col_name)
+                    .is_none_or(|mut n| n.next().is_none()) =>
+            {
+                Ok(DaxResult::Blank)
             }
+            "SUM" => table
+                .sum_integer(col_name)
+                .map(DaxResult::Integer)
+                .or_else(|| table.sum_currency(col_name).map(DaxResult::Currency))
+                .or_else(|| table.sum(col_name).map(DaxResult::Number))
+                .ok_or_else(|| format!("Could not calculate SUM for column {}", col_name).into()),
+            "AVERAGE" => table
+                .average_with(col_name, NullPolicy::Skip)?
+                .map(DaxResult::Number)
+                .ok_or_else(|| {
+                    format!("Could not calculate AVERAGE for column {}", col_name).into()
+                }),
+            "MIN" => table
+                .integer_values(col_name)
+                .and_then(|mut values| values.by_ref().min())
+                .map(DaxResult::Integer)
+                .or_else(|| table.min(col_name).map(DaxResult::Number))
+                .or_else(|| table.min_date(col_name).map(DaxResult::DateTime))
+                .or_else(|| {
+                    Some(DaxResult::Text(
+                        table.text_values(col_name)?.min()?.to_string(),
+                    ))
+                })
+                .map_or(Ok(DaxResult::Blank), Ok),
+            "MAX" => table
+                .integer_values(col_name)
+                .and_then(|mut values| values.by_ref().max())
+                .map(DaxResult::Integer)
+                .or_else(|| table.max(col_name).map(DaxResult::Number))
+                .or_else(|| table.max_date(col_name).map(DaxResult::DateTime))
+                .or_else(|| {
+                    Some(DaxResult::Text(
+                        table.text_values(col_name)?.max()?.to_string(),
+                    ))
+                })
+                .map_or(Ok(DaxResult::Blank), Ok),
+            "AVERAGEA" => table
+                .averagea(col_name)
+                .map(DaxResult::Number)
+                .ok_or_else(|| {
+                    format!("Could not calculate AVERAGEA for column {}", col_name).into()
+                }),
+            "MINA" => table
+                .mina(col_name)
+                .map(DaxResult::Number)
+                .ok_or_else(|| format!("Could not calculate MINA for column {}", col_name).into()),
+            "MAXA" => table
+                .maxa(col_name)
+                .map(DaxResult::Number)
+                .ok_or_else(|| format!("Could not calculate MAXA for column {}", col_name).into()),
+            "APPROXIMATEDISTINCTCOUNT" => table
+                .approx_distinctcount(col_name, DEFAULT_PRECISION)
+                .map(|dc| DaxResult::Number(dc as f64))
+                .ok_or_else(|| {
+                    format!(
+                        "Could not calculate APPROXIMATEDISTINCTCOUNT for column {}",
+                        col_name
+                    )
+                    .into()
+                }),
+            // Empty text isn't blank, so it isn't counted
+            "COUNTBLANK" => {
+                let blanks = table.get_column(col_name).map_or(0, |values| {
+                    (0..values.len()).filter(|&row| values.is_null(row)).count()
+                });
+                Ok(DaxResult::Number(blanks as f64))
+            }
+            "DISTINCTCOUNT" => table
+                .distinctcount(col_name)
+                .map(|dc| DaxResult::Number(dc as f64))
+                .ok_or_else(|| {
+                    format!("Could not calculate DISTINCTCOUNT for column {}", col_name).into()
+                }),
+            _ => Err(DaxError::UnknownFunction {
+                name: name.to_string(),
+                span: None,
+            }),
         }
+    }
 
-        if count > 0 {
-            Some(sum / count as f64)
-        } else {
-            Some(0.0) // Return Some(0.0) for empty or non-numeric columns
+    pub(crate) fn number_argument(
+        &self,
+        function: &str,
+        arg: &DaxExpr,
+        ctx: &EvalContext,
+    ) -> Result<f64, DaxError> {
+        match self.evaluate_expr(arg, ctx)? {
+            DaxResult::Number(n) => Ok(n),
+            DaxResult::Integer(i) => Ok(i as f64),
+            DaxResult::Currency(c) => Ok(currency_amount(c)),
+            DaxResult::Boolean(b) => Ok(if b { 1.0 } else { 0.0 }),
+            DaxResult::DateTime(d) => Ok(dates::to_serial(&d)),
+            DaxResult::Blank => Ok(0.0),
+            _ => Err(format!("{} expects a numeric argument", function).into()),
         }
     }
 
-    pub fn count(&self, column_name: &str) -> Option<usize> {
-        self.get_column(column_name).map(|column| column.len())
+    // Any scalar converted to text the way DAX concatenation does; blank is empty text
+    fn coerced_text_argument(
+        &self,
+        function: &str,
+        arg: &DaxExpr,
+        ctx: &EvalContext,
+    ) -> Result<String, DaxError> {
+        match self.evaluate_expr(arg, ctx)? {
+            DaxResult::Text(s) => Ok(s),
+            DaxResult::Number(n) => Ok(n.to_string()),
+            DaxResult::Integer(i) => Ok(i.to_string()),
+            DaxResult::Currency(c) => Ok(currency_amount(c).to_string()),
+            DaxResult::Boolean(b) => Ok(if b { "True" } else { "False" }.to_string()),
+            DaxResult::DateTime(d) => Ok(dates::format(&d)),
+            DaxResult::Blank => Ok(String::new()),
+            _ => Err(format!("{} expects a scalar argument", function).into()),
+        }
     }
 
-    pub fn distinctcount(&self, column_name: &str) -> Option<usize> {
-        self.get_column(column_name).map(|column| {
-            let unique_values: std::collections::HashSet<&Value> = column.iter().collect();
-            unique_values.len()
-        })
+    // Row indices ordered by an expression evaluated in each row. The sort is
+    // stable, so ties keep the table's order.
+    pub(crate) fn sorted_rows(
+        &self,
+        order_by: &DaxExpr,
+        descending: bool,
+        ctx: &EvalContext,
+    ) -> Result<Vec<usize>, DaxError> {
+        let keyed = self.sorted_keys(order_by, descending, ctx)?;
+        Ok(keyed.into_iter().map(|(_, row)| row).collect())
     }
 
-    // MIN function
-    pub fn min(&self, column_name: &str) -> Option<f64> {
-        self.get_column(column_name).and_then(|column| {
-            column
-                .iter()
-                .filter_map(|value| {
-                    if let Value::Number(n) = value {
-                        Some(*n)
-                    } else {
-                        None
-                    }
-                })
-                .min_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
-        })
+    // Like `sorted_rows`, keeping each row's value of the expression alongside it
+    pub(crate) fn sorted_keys(
+        &self,
+        order_by: &DaxExpr,
+        descending: bool,
+        ctx: &EvalContext,
+    ) -> Result<Vec<(DaxResult, usize)>, DaxError> {
+        let mut keyed = Vec::with_capacity(self.num_rows());
+        for row in 0..self.num_rows() {
+            let row_ctx = ctx.with_row(self, row);
+            keyed.push((self.evaluate_expr(order_by, &row_ctx)?, row));
+        }
+        keyed.sort_by(|(a, _), (b, _)| {
+            let ordering = compare_results(a, b);
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+        Ok(keyed)
     }
 
-    // MAX function
-    pub fn max(&self, column_name: &str) -> Option<f64> {
-        self.get_column(column_name).and_then(|column| {
-            column
-                .iter()
-                .filter_map(|value| {
-                    if let Value::Number(n) = value {
-                        Some(*n)
-                    } else {
-                        None
-                    }
-                })
-                .max_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
-        })
+    // A sort order: the ASC / DESC keywords, or a number where 0 means descending
+    pub(crate) fn descending_argument(
+        &self,
+        function: &str,
+        arg: &DaxExpr,
+        ctx: &EvalContext,
+    ) -> Result<bool, DaxError> {
+        match arg {
+            DaxExpr::Variable(order) if order.eq_ignore_ascii_case("ASC") => Ok(false),
+            DaxExpr::Variable(order) if order.eq_ignore_ascii_case("DESC") => Ok(true),
+            _ => Ok(self.number_argument(function, arg, ctx)? == 0.0),
+        }
     }
 
-    fn evaluate_divide(&self, args: &[DaxToken]) -> Result<DaxResult, String> {
-        if args.len() < 2 || args.len() > 3 {
-            return Err("DIVIDE requires 2 or 3 arguments".to_string());
+    // A condition: numbers are true when non-zero and blank is false
+    fn boolean_argument(
+        &self,
+        function: &str,
+        arg: &DaxExpr,
+        ctx: &EvalContext,
+    ) -> Result<bool, DaxError> {
+        match self.evaluate_expr(arg, ctx)? {
+            DaxResult::Boolean(b) => Ok(b),
+            DaxResult::Number(n) => Ok(n != 0.0),
+            DaxResult::Integer(i) => Ok(i != 0),
+            DaxResult::Currency(c) => Ok(c != 0),
+            DaxResult::Blank => Ok(false),
+            _ => Err(format!("{} expects a boolean argument", function).into()),
         }
+    }
 
-        // Evaluate numerator
-        let numerator = match self.evaluate_dax(&args[0].to_string()) {
-            DaxResult::Number(n) => n,
-            _ => return Err("Numerator must be a number".to_string()),
-        };
-
-        // Evaluate denominator
-        let denominator = match self.evaluate_dax(&args[1].to_string()) {
-            DaxResult::Number(n) => n,
-            _ => return Err("Denominator must be a number".to_string()),
-        };
+    pub(crate) fn text_argument(
+        &self,
+        function: &str,
+        arg: &DaxExpr,
+        ctx: &EvalContext,
+    ) -> Result<String, DaxError> {
+        match self.evaluate_expr(arg, ctx)? {
+            DaxResult::Text(s) => Ok(s),
+            _ => Err(format!("{} expects a text argument", function).into()),
+        }
+    }
 
-        // Handle division
-        if denominator == 0.0 {
-            // If there's an alternate result specified
-            if args.len() == 3 {
-                match self.evaluate_dax(&args[2].to_string()) {
-                    DaxResult::Number(n) => Ok(DaxResult::Number(n)),
-                    _ => Err("Alternate result must be a number".to_string()),
+    // A CALCULATE filter argument: a table whose rows are the allowed values, a
+    // condition on columns, or one of the KEEPFILTERS / REMOVEFILTERS modifiers
+    pub(crate) fn filter_argument(
+        &self,
+        function: &str,
+        arg: &DaxExpr,
+        ctx: &EvalContext,
+    ) -> Result<FilterModifier, DaxError> {
+        if let DaxExpr::FunctionCall { name, args, .. } = arg {
+            let args: Vec<&DaxExpr> = args.iter().collect();
+            let name = name.to_ascii_uppercase();
+            match name.as_str() {
+                "KEEPFILTERS" => {
+                    expect_arguments(&name, &args, 1, 1)?;
+                    return match self.filter_argument(&name, args[0], ctx)? {
+                        FilterModifier::Replace(filter) => Ok(FilterModifier::Keep(filter)),
+                        _ => Err("KEEPFILTERS expects a filter argument".into()),
+                    };
                 }
-            } else {
-                // Return BLANK (represented as Error in this case)
-                Err("Division by zero".to_string())
+                "REMOVEFILTERS" => return removed_columns(&args).map(FilterModifier::Remove),
+                "USERELATIONSHIP" | "CROSSFILTER" => return Err(no_relationships(&name)),
+                _ => {}
             }
-        } else {
-            Ok(DaxResult::Number(numerator / denominator))
         }
+        self.plain_filter(function, arg, ctx)
+            .map(FilterModifier::Replace)
     }
 
-    // DIVIDE function with optional alternate result
-    pub fn divide(
+    fn plain_filter(
         &self,
-        numerator: f64,
-        denominator: f64,
-        alternate_result: Option<f64>,
-    ) -> Option<f64> {
-        if denominator == 0.0 {
-            alternate_result
-        } else {
-            Some(numerator / denominator)
+        function: &str,
+        arg: &DaxExpr,
+        ctx: &EvalContext,
+    ) -> Result<Filter, DaxError> {
+        if is_predicate(arg) {
+            return self.predicate_filter(arg, ctx);
+        }
+        match self.evaluate_expr(arg, ctx)? {
+            DaxResult::Table(table) => {
+                Ok(Filter::from_table(&table, table.column_names().to_vec()))
+            }
+            // A constant condition keeps every row or none, whatever the columns
+            DaxResult::Boolean(b) => {
+                let rows = if b {
+                    HashSet::from([Vec::new()])
+                } else {
+                    HashSet::new()
+                };
+                Ok(Filter::new(Vec::new(), rows))
+            }
+            _ => Err(format!("{} expects a table or boolean filter argument", function).into()),
         }
     }
 
-    // Updated evaluate_dax to handle the string literal requirement
-    pub fn evaluate_dax(&self, expression: &str) -> DaxResult {
-        // Use runtime tokenizer instead of proc macro
-        let tokens = tokenize(expression);
-
-        let mut iter = tokens.iter();
-        while let Some(token) = iter.next() {
-            match token {
-                DaxToken::Function(name) => match name.as_str() {
-                    "SUM" => {
-                        while let Some(token) = iter.next() {
-                            if let DaxToken::Column(col_name) = token {
-                                return match self.sum(&col_name) {
-                                    Some(sum) => DaxResult::Number(sum),
-                                    None => DaxResult::Error(format!(
-                                        "Could not calculate SUM for column {}",
-                                        col_name
-                                    )),
-                                };
-                            }
-                        }
-                    }
-                    "AVERAGE" => {
-                        while let Some(token) = iter.next() {
-                            if let DaxToken::Column(col_name) = token {
-                                return match self.average(&col_name) {
-                                    Some(avg) => DaxResult::Number(avg),
-                                    None => DaxResult::Error(format!(
-                                        "Could not calculate AVERAGE for column {}",
-                                        col_name
-                                    )),
-                                };
-                            }
-                        }
-                    }
-                    "MIN" => {
-                        while let Some(token) = iter.next() {
-                            if let DaxToken::Column(col_name) = token {
-                                return match self.min(&col_name) {
-                                    Some(min) => DaxResult::Number(min),
-                                    None => DaxResult::Error(format!(
-                                        "Could not calculate MIN for column {}",
-                                        col_name
-                                    )),
-                                };
-                            }
-                        }
-                    }
-                    "MAX" => {
-                        while let Some(token) = iter.next() {
-                            if let DaxToken::Column(col_name) = token {
-                                return match self.max(&col_name) {
-                                    Some(max) => DaxResult::Number(max),
-                                    None => DaxResult::Error(format!(
-                                        "Could not calculate MAX for column {}",
-                                        col_name
-                                    )),
-                                };
-                            }
-                        }
-                    }
-                    // "DIVIDE" => {
-                    //     while let Some(token) = iter.next() {
-                    //         if let DaxToken::Number(numerator) = token {
-                    //             while let Some(token) = iter.next() {
-                    //                 if let DaxToken::Number(denominator) = token {
-                    //                     return match self.evaluate_divide(*numerator, *denominator, None) {
-                    //                         Some(result) => DaxResult::Number(result),
-                    //                         None => DaxResult::Error(format!(
-                    //                             "Could not calculate DIVIDE for numerator {} and denominator {}",
-                    //                             numerator, denominator
-                    //                         )),
-                    //                     };
-                    //                 }
-                    //             }
-                    //         }
-                    //     }
-                    // }
-                    "DISTINCTCOUNT" => {
-                        while let Some(token) = iter.next() {
-                            if let DaxToken::Column(col_name) = token {
-                                return match self.distinctcount(&col_name) {
-                                    Some(dc) => DaxResult::Number(dc as f64),
-                                    None => DaxResult::Error(format!(
-                                        "Could not calculate DISTINCTCOUNT for column {}",
-                                        col_name
-                                    )),
-                                };
-                            }
-                        }
-                    }
-                    _ => return DaxResult::Error(format!("Unsupported function: {}", name)),
-                },
-                _ => continue,
+    // A condition such as `[Product] = "Apple"` as a filter: the combinations of
+    // the referenced columns' values, across the whole table, for which it holds
+    fn predicate_filter(&self, arg: &DaxExpr, ctx: &EvalContext) -> Result<Filter, DaxError> {
+        let columns = arg.columns();
+        if let Some(name) = columns.iter().find(|name| !self.has_column(name)) {
+            return Err(format!("Column {} not found", name).into());
+        }
+        let candidates: Vec<Vec<Value>> = Filter::from_table(self, columns.clone())
+            .rows()
+            .iter()
+            .cloned()
+            .collect();
+        let mut values = Table::new();
+        for (i, name) in columns.iter().enumerate() {
+            values.insert_column(
+                name.clone(),
+                candidates.iter().map(|key| key[i].clone()).collect(),
+            );
+        }
+
+        let mut rows = HashSet::new();
+        for (row, key) in candidates.iter().enumerate() {
+            let row_ctx = ctx.with_row(&values, row);
+            if values.boolean_argument("CALCULATE", arg, &row_ctx)? {
+                rows.insert(key.clone());
+            }
+        }
+        Ok(Filter::new(columns, rows))
+    }
+
+    pub(crate) fn table_argument(
+        &self,
+        function: &str,
+        arg: &DaxExpr,
+        ctx: &EvalContext,
+    ) -> Result<Table, DaxError> {
+        match self.evaluate_expr(arg, ctx)? {
+            DaxResult::Table(table) => Ok(table),
+            _ => Err(format!("{} expects a table argument", function).into()),
+        }
+    }
+
+    // A date argument where BLANK() means "no date"
+    pub(crate) fn optional_date_argument(
+        &self,
+        function: &str,
+        arg: &DaxExpr,
+        ctx: &EvalContext,
+    ) -> Result<Option<NaiveDateTime>, DaxError> {
+        match self.evaluate_expr(arg, ctx)? {
+            DaxResult::Blank => Ok(None),
+            result => to_date(function, result).map(Some),
+        }
+    }
+
+    pub(crate) fn date_argument(
+        &self,
+        function: &str,
+        arg: &DaxExpr,
+        ctx: &EvalContext,
+    ) -> Result<NaiveDateTime, DaxError> {
+        to_date(function, self.evaluate_expr(arg, ctx)?)
+    }
+}
+
+fn to_date(function: &str, result: DaxResult) -> Result<NaiveDateTime, DaxError> {
+    match result {
+        DaxResult::DateTime(d) => Ok(d),
+        DaxResult::Number(n) => dates::from_serial(n)
+            .ok_or_else(|| format!("{} received an invalid date serial {}", function, n).into()),
+        DaxResult::Integer(i) => to_date(function, DaxResult::Number(i as f64)),
+        DaxResult::Text(s) => dates::parse_iso(&s)
+            .ok_or_else(|| format!("{} cannot convert \"{}\" to a date", function, s).into()),
+        DaxResult::Blank => Ok(dates::epoch()),
+        // A one-row, one-column table such as FIRSTDATE's result converts to its value
+        DaxResult::Table(table) if table.num_rows() == 1 && table.column_names().len() == 1 => {
+            match table.columns.values().next().unwrap().value(0) {
+                Some(Value::Null) | None => Ok(dates::epoch()),
+                Some(value) => to_date(function, DaxResult::from(&value)),
             }
         }
+        _ => Err(format!("{} expects a date argument", function).into()),
+    }
+}
+
+// One-column table named Date holding every day from start to end at midnight
+fn calendar(start: NaiveDateTime, end: NaiveDateTime) -> Table {
+    let days = start
+        .date()
+        .iter_days()
+        .take_while(|day| *day <= end.date())
+        .map(|day| day.and_time(NaiveTime::MIN))
+        .collect();
+    date_table("Date", days)
+}
+
+// Number a cell counts as in AVERAGEA, MINA and MAXA: TRUE is 1, FALSE and text
+// are 0, dates are their serial number and blanks are skipped
+fn coerce_a(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => Some(*n),
+        Value::Integer(i) => Some(*i as f64),
+        Value::Currency(c) => Some(currency_amount(*c)),
+        Value::Boolean(b) => Some(if *b { 1.0 } else { 0.0 }),
+        Value::Text(_) => Some(0.0),
+        Value::DateTime(d) => Some(dates::to_serial(d)),
+        Value::Null => None,
+    }
+}
+
+// Amount of a number or currency result; None for anything else
+fn numeric_result(result: &DaxResult) -> Option<f64> {
+    match result {
+        DaxResult::Number(n) => Some(*n),
+        DaxResult::Integer(i) => Some(*i as f64),
+        DaxResult::Currency(c) => Some(currency_amount(*c)),
+        _ => None,
+    }
+}
+
+// Apply `+`, `-`, `*`, `/` or `^` the way DAX types the result. Integers stay
+// integers until a division (or an overflow) promotes them to numbers. Currency
+// stays exact when added to or subtracted from currency or integers, and
+// scaling an amount by a number keeps it currency; adding a number, or
+// dividing currency by currency, gives a number. Blank counts as zero and
+// TRUE/FALSE as 1/0. Dividing by zero is an error rather than infinity.
+pub(crate) fn arithmetic(
+    op: BinaryOperator,
+    left: &DaxResult,
+    right: &DaxResult,
+) -> Result<DaxResult, DaxError> {
+    let operand = |result: &DaxResult| match result {
+        DaxResult::Blank => Ok(DaxResult::Integer(0)),
+        DaxResult::Boolean(b) => Ok(DaxResult::Integer(*b as i64)),
+        DaxResult::Number(n) => Ok(DaxResult::Number(*n)),
+        DaxResult::Integer(i) => Ok(DaxResult::Integer(*i)),
+        DaxResult::Currency(c) => Ok(DaxResult::Currency(*c)),
+        DaxResult::Text(_) => Err(format!("Operator {} cannot be applied to text", op).into()),
+        DaxResult::DateTime(_) => {
+            Err(format!("Operator {} cannot be applied to a date", op).into())
+        }
+        _ => Err(format!("Operator {} expects numeric operands", op).into()),
+    };
+    let overflow = || DaxError::from(format!("Currency overflow in {}", op));
+    let currency = |amount: f64| {
+        currency_units(amount)
+            .map(DaxResult::Currency)
+            .ok_or_else(overflow)
+    };
+    // Next to currency, an integer is a whole amount when adding and a plain
+    // number when scaling
+    let promote = |value: &DaxResult, other: &DaxResult| match (value, other) {
+        (DaxResult::Integer(i), DaxResult::Currency(_))
+            if op == BinaryOperator::Add || op == BinaryOperator::Subtract =>
+        {
+            i.checked_mul(CURRENCY_SCALE)
+                .map(DaxResult::Currency)
+                .ok_or_else(overflow)
+        }
+        (DaxResult::Integer(i), DaxResult::Currency(_)) => Ok(DaxResult::Number(*i as f64)),
+        (value, _) => operand(value),
+    };
+    // As in DAX, scaling a blank or combining two blanks stays blank, where
+    // adding a blank to a number treats it as 0
+    match (left, right, op) {
+        (DaxResult::Blank, DaxResult::Blank, BinaryOperator::Add | BinaryOperator::Subtract)
+        | (DaxResult::Blank, _, BinaryOperator::Multiply | BinaryOperator::Divide)
+        | (_, DaxResult::Blank, BinaryOperator::Multiply) => return Ok(DaxResult::Blank),
+        _ => {}
+    }
+    let (left, right) = (operand(left)?, operand(right)?);
+    if op == BinaryOperator::Divide && numeric_result(&right) == Some(0.0) {
+        return Err("Division by zero".into());
+    }
+    let (left, right) = (promote(&left, &right)?, promote(&right, &left)?);
+
+    match (left, right, op) {
+        (
+            DaxResult::Integer(a),
+            DaxResult::Integer(b),
+            BinaryOperator::Add | BinaryOperator::Subtract | BinaryOperator::Multiply,
+        ) => {
+            let exact = match op {
+                BinaryOperator::Add => a.checked_add(b),
+                BinaryOperator::Subtract => a.checked_sub(b),
+                _ => a.checked_mul(b),
+            };
+            Ok(exact.map_or_else(
+                || DaxResult::Number(apply_operator(op, a as f64, b as f64)),
+                DaxResult::Integer,
+            ))
+        }
+        (DaxResult::Integer(a), DaxResult::Integer(b), BinaryOperator::Power) if b >= 0 => {
+            Ok(u32::try_from(b)
+                .ok()
+                .and_then(|b| a.checked_pow(b))
+                .map_or_else(
+                    || DaxResult::Number((a as f64).powf(b as f64)),
+                    DaxResult::Integer,
+                ))
+        }
+        (DaxResult::Currency(a), DaxResult::Currency(b), BinaryOperator::Add) => a
+            .checked_add(b)
+            .map(DaxResult::Currency)
+            .ok_or_else(overflow),
+        (DaxResult::Currency(a), DaxResult::Currency(b), BinaryOperator::Subtract) => a
+            .checked_sub(b)
+            .map(DaxResult::Currency)
+            .ok_or_else(overflow),
+        (DaxResult::Currency(a), DaxResult::Currency(b), BinaryOperator::Multiply) => {
+            // The product carries eight decimals; round it back to four
+            let product = (a as i128 * b as i128) as f64 / CURRENCY_SCALE as f64;
+            currency(product / CURRENCY_SCALE as f64)
+        }
+        (DaxResult::Currency(a), DaxResult::Number(n), BinaryOperator::Multiply)
+        | (DaxResult::Number(n), DaxResult::Currency(a), BinaryOperator::Multiply) => {
+            currency(currency_amount(a) * n)
+        }
+        (DaxResult::Currency(a), DaxResult::Number(n), BinaryOperator::Divide) => {
+            currency(currency_amount(a) / n)
+        }
+        (_, _, op) if op.is_comparison() => Err(format!("Unsupported operator: {}", op).into()),
+        (a, b, _) => Ok(DaxResult::Number(apply_operator(
+            op,
+            numeric_result(&a).unwrap_or(0.0),
+            numeric_result(&b).unwrap_or(0.0),
+        ))),
+    }
+}
+
+fn apply_operator(op: BinaryOperator, x: f64, y: f64) -> f64 {
+    match op {
+        BinaryOperator::Add => x + y,
+        BinaryOperator::Subtract => x - y,
+        BinaryOperator::Multiply => x * y,
+        BinaryOperator::Power => x.powf(y),
+        _ => x / y,
+    }
+}
+
+// Amount of a number or currency cell; None for anything else
+pub(crate) fn numeric_value(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => Some(*n),
+        Value::Integer(i) => Some(*i as f64),
+        Value::Currency(c) => Some(currency_amount(*c)),
+        _ => None,
+    }
+}
+
+// The cell value for a scalar result; None for tables and errors
+pub(crate) fn scalar_value(result: DaxResult) -> Option<Value> {
+    match result {
+        DaxResult::Number(n) => Some(Value::Number(n)),
+        DaxResult::Integer(i) => Some(Value::Integer(i)),
+        DaxResult::Currency(c) => Some(Value::Currency(c)),
+        DaxResult::Text(s) => Some(Value::Text(s)),
+        DaxResult::Boolean(b) => Some(Value::Boolean(b)),
+        DaxResult::DateTime(d) => Some(Value::DateTime(d)),
+        DaxResult::Blank => Some(Value::Null),
+        DaxResult::Table(_) => None,
+    }
+}
+
+// Whether an expression is a comparison, which as a filter argument restricts the columns it uses
+fn is_predicate(expr: &DaxExpr) -> bool {
+    matches!(expr, DaxExpr::BinaryOp { op, .. } if op.is_comparison())
+}
+
+// Order the operands of a comparison operator the way DAX does: numbers, dates
+// and TRUE/FALSE by value and text without regard to case. Blank stands in for
+// zero, FALSE or empty text, whichever the other side is; text and numbers
+// can't be compared.
+fn compare_operands(
+    op: BinaryOperator,
+    left: &DaxResult,
+    right: &DaxResult,
+) -> Result<Ordering, DaxError> {
+    enum Operand {
+        Blank,
+        Number(f64),
+        Boolean(bool),
+        Text(String),
+    }
+    let operand = |result: &DaxResult| match result {
+        DaxResult::Blank => Ok(Operand::Blank),
+        DaxResult::Boolean(b) => Ok(Operand::Boolean(*b)),
+        DaxResult::Text(s) => Ok(Operand::Text(s.to_lowercase())),
+        DaxResult::DateTime(d) => Ok(Operand::Number(dates::to_serial(d))),
+        other => numeric_result(other)
+            .map(Operand::Number)
+            .ok_or_else(|| DaxError::from(format!("Operator {} expects scalar operands", op))),
+    };
+    let compare_numbers = |a: f64, b: f64| {
+        a.partial_cmp(&b)
+            .unwrap_or_else(|| a.is_nan().cmp(&b.is_nan()))
+    };
+
+    match (operand(left)?, operand(right)?) {
+        (Operand::Blank, Operand::Blank) => Ok(Ordering::Equal),
+        (Operand::Number(a), Operand::Number(b)) => Ok(compare_numbers(a, b)),
+        (Operand::Blank, Operand::Number(b)) => Ok(compare_numbers(0.0, b)),
+        (Operand::Number(a), Operand::Blank) => Ok(compare_numbers(a, 0.0)),
+        (Operand::Boolean(a), Operand::Boolean(b)) => Ok(a.cmp(&b)),
+        (Operand::Blank, Operand::Boolean(b)) => Ok(false.cmp(&b)),
+        (Operand::Boolean(a), Operand::Blank) => Ok(a.cmp(&false)),
+        (Operand::Text(a), Operand::Text(b)) => Ok(a.cmp(&b)),
+        (Operand::Blank, Operand::Text(b)) => Ok("".cmp(b.as_str())),
+        (Operand::Text(a), Operand::Blank) => Ok(a.as_str().cmp("")),
+        _ => Err(format!("Operator {} cannot compare values of different types", op).into()),
+    }
+}
+
+// USERELATIONSHIP and CROSSFILTER change how filters travel between related
+// tables, and a table on its own has no relationships for them to act on
+fn no_relationships(function: &str) -> DaxError {
+    format!(
+        "{} needs a relationship between two tables, and a single table has none",
+        function
+    )
+    .into()
+}
+
+// Columns named by REMOVEFILTERS; None when the whole table is cleared
+fn removed_columns(args: &[&DaxExpr]) -> Result<Option<Vec<String>>, DaxError> {
+    let mut columns = Vec::new();
+    for arg in args {
+        match arg {
+            DaxExpr::ColumnRef { name, .. } => columns.push(name.clone()),
+            DaxExpr::Variable(_) => return Ok(None),
+            _ => return Err("REMOVEFILTERS expects a table or columns".into()),
+        }
+    }
+    Ok((!columns.is_empty()).then_some(columns))
+}
+
+// Ordering used when sorting by an expression: blanks first, then booleans,
+// numbers, text and dates
+pub(crate) fn compare_results(a: &DaxResult, b: &DaxResult) -> Ordering {
+    let rank = |result: &DaxResult| match result {
+        DaxResult::Blank => 0,
+        DaxResult::Boolean(_) => 1,
+        DaxResult::Number(_) | DaxResult::Integer(_) | DaxResult::Currency(_) => 2,
+        DaxResult::Text(_) => 3,
+        DaxResult::DateTime(_) => 4,
+        DaxResult::Table(_) => 5,
+    };
+    match (a, b) {
+        (DaxResult::Boolean(x), DaxResult::Boolean(y)) => x.cmp(y),
+        (DaxResult::Integer(x), DaxResult::Integer(y)) => x.cmp(y),
+        (DaxResult::Currency(x), DaxResult::Currency(y)) => x.cmp(y),
+        (DaxResult::Text(x), DaxResult::Text(y)) => x.cmp(y),
+        (DaxResult::DateTime(x), DaxResult::DateTime(y)) => x.cmp(y),
+        _ => match (numeric_result(a), numeric_result(b)) {
+            (Some(x), Some(y)) => x.total_cmp(&y),
+            _ => rank(a).cmp(&rank(b)),
+        },
+    }
+}
+
+// Pick `count` items spread evenly over the slice, always keeping the first and last
+fn evenly_spaced(items: &[usize], count: usize) -> Vec<usize> {
+    match count {
+        _ if count >= items.len() => items.to_vec(),
+        0 => Vec::new(),
+        1 => vec![items[0]],
+        _ => {
+            let last = items.len() - 1;
+            (0..count)
+                .map(|i| items[(i * last + (count - 1) / 2) / (count - 1)])
+                .collect()
+        }
+    }
+}
+
+// TRUE and FALSE, in any letter case
+fn boolean_keyword(name: &str) -> Option<bool> {
+    if name.eq_ignore_ascii_case("TRUE") {
+        Some(true)
+    } else if name.eq_ignore_ascii_case("FALSE") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+// Whether two scalar results are equal, as SWITCH compares them: text ignores case
+fn same_value(a: &DaxResult, b: &DaxResult) -> bool {
+    if let (Some(x), Some(y)) = (numeric_result(a), numeric_result(b)) {
+        return x == y;
+    }
+    match (a, b) {
+        (DaxResult::Text(x), DaxResult::Text(y)) => x.to_lowercase() == y.to_lowercase(),
+        (DaxResult::Boolean(x), DaxResult::Boolean(y)) => x == y,
+        (DaxResult::DateTime(x), DaxResult::DateTime(y)) => x == y,
+        (DaxResult::Blank, DaxResult::Blank) => true,
+        _ => false,
+    }
+}
+
+// Point an unknown function or column, or a call with the wrong number of
+// arguments, at the first place in the expression it can have come from
+pub(crate) fn locate(error: DaxError, expr: &DaxExpr, expression: &str) -> DaxError {
+    let span = |range: Range<usize>| Span {
+        expression: expression.to_string(),
+        start: range.start,
+        end: range.end,
+    };
+    match error {
+        DaxError::UnknownFunction { name, span: None } => DaxError::UnknownFunction {
+            // Underline just the name
+            span: expr.find(&|node| is_call(node, &name, None)).and_then(|node| match node {
+                DaxExpr::FunctionCall { span: call, .. } => {
+                    Some(span(call.start..call.start + name.chars().count()))
+                }
+                _ => None,
+            }),
+            name,
+        },
+        DaxError::UnknownColumn {
+            name,
+            span: None,
+            suggestions,
+        } => DaxError::UnknownColumn {
+            span: expr
+                .find(&|node| matches!(node, DaxExpr::ColumnRef { name: column, .. } if *column == name))
+                .and_then(|node| match node {
+                    DaxExpr::ColumnRef { span: reference, .. } => Some(span(reference.clone())),
+                    _ => None,
+                }),
+            name,
+            suggestions,
+        },
+        DaxError::UnknownTable { name, span: None } => DaxError::UnknownTable {
+            span: expr
+                .find(&|node| matches!(node, DaxExpr::ColumnRef { table: Some(table), .. } if *table == name))
+                .and_then(|node| match node {
+                    DaxExpr::ColumnRef { span: reference, .. } => Some(span(reference.clone())),
+                    _ => None,
+                }),
+            name,
+        },
+        DaxError::WrongArity {
+            function,
+            expected,
+            got,
+            span: None,
+        } => DaxError::WrongArity {
+            span: expr.find(&|node| is_call(node, &function, Some(got))).and_then(|node| match node {
+                DaxExpr::FunctionCall { span: call, .. } => Some(span(call.clone())),
+                _ => None,
+            }),
+            function,
+            expected,
+            got,
+        },
+        other => other,
+    }
+}
+
+// Whether a node calls the function, with that many arguments if given
+fn is_call(node: &DaxExpr, function: &str, arity: Option<usize>) -> bool {
+    matches!(node, DaxExpr::FunctionCall { name, args, .. }
+        if name.eq_ignore_ascii_case(function) && arity.is_none_or(|n| n == args.len()))
+}
+
+// Levenshtein distance between two names, ignoring case
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.to_lowercase().chars().collect();
+    let b: Vec<char> = b.to_lowercase().chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+pub(crate) fn expect_arguments(
+    function: &str,
+    args: &[&DaxExpr],
+    min: usize,
+    max: usize,
+) -> Result<(), DaxError> {
+    if args.len() < min || args.len() > max {
+        let expected = if min == max {
+            min.to_string()
+        } else {
+            format!("{} to {}", min, max)
+        };
+        return Err(DaxError::WrongArity {
+            function: function.to_string(),
+            expected,
+            got: args.len(),
+            span: None,
+        });
+    }
+    Ok(())
+}
+
+pub(crate) fn interval_argument(function: &str, arg: &DaxExpr) -> Result<Interval, DaxError> {
+    match arg {
+        DaxExpr::Variable(keyword) => Interval::from_name(keyword).ok_or_else(|| {
+            format!("{} does not support the interval {}", function, keyword).into()
+        }),
+        _ => Err(format!("{} expects an interval such as DAY or MONTH", function).into()),
+    }
+}
 
-        DaxResult::Error("Invalid or unsupported DAX expression".to_string())
+fn column_argument<'a>(function: &str, args: &[&'a DaxExpr]) -> Result<&'a str, DaxError> {
+    expect_arguments(function, args, 1, 1)?;
+    column_name(function, args[0])
+}
+
+pub(crate) fn column_name<'a>(function: &str, arg: &'a DaxExpr) -> Result<&'a str, DaxError> {
+    match arg {
+        DaxExpr::ColumnRef { name: col_name, .. } => Ok(col_name),
+        _ => Err(format!("{} expects a column reference", function).into()),
     }
 }
 
@@ -300,6 +2731,25 @@ impl From<f64> for Value {
     }
 }
 
+impl From<i64> for Value {
+    fn from(i: i64) -> Self {
+        Value::Integer(i)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(i: i32) -> Self {
+        Value::Integer(i.into())
+    }
+}
+
+/// None is Null
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
 impl From<&str> for Value {
     fn from(s: &str) -> Self {
         Value::Text(s.to_string())
@@ -318,140 +2768,89 @@ impl From<bool> for Value {
     }
 }
 
-/// Display
-
-impl fmt::Display for Table {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // First, calculate the maximum width for each column
-        let mut column_widths: std::collections::HashMap<&String, usize> =
-            std::collections::HashMap::new();
-
-        // Initialize with column name lengths
-        for column_name in self.columns.keys() {
-            column_widths.insert(column_name, column_name.len());
-        }
-
-        // Update with maximum value lengths in each column
-        for (column_name, values) in &self.columns {
-            for value in values {
-                let value_width = match value {
-                    Value::Text(s) => s.len(),
-                    Value::Number(n) => format!("{:.2}", n).len(),
-                    Value::Boolean(b) => {
-                        if b.to_owned() {
-                            4
-                        } else {
-                            5
-                        }
-                    }
-                    Value::Null => 0,
-                    // Add other value types as needed
-                };
-                let current_max = column_widths.get(column_name).copied().unwrap_or(0);
-                column_widths.insert(column_name, current_max.max(value_width));
-            }
-        }
+impl From<NaiveDateTime> for Value {
+    fn from(d: NaiveDateTime) -> Self {
+        Value::DateTime(d)
+    }
+}
 
-        // Get sorted column names for consistent ordering
-        let mut column_names: Vec<&String> = self.columns.keys().collect();
-        column_names.sort();
+impl From<NaiveDate> for Value {
+    fn from(d: NaiveDate) -> Self {
+        Value::DateTime(d.and_time(NaiveTime::MIN))
+    }
+}
 
-        // Write header
-        writeln!(
-            f,
-            "┌{}┐",
-            "─".repeat(
-                column_names
-                    .iter()
-                    .map(|name| column_widths[name] + 2)
-                    .sum::<usize>()
-                    + column_names.len()
-                    - 1
-            )
-        )?;
-
-        // Write column names
-        for (i, column_name) in column_names.iter().enumerate() {
-            if i > 0 {
-                write!(f, "│")?;
-            }
-            write!(
-                f,
-                " {:<width$} ",
-                column_name,
-                width = column_widths[column_name]
-            )?;
-        }
-        writeln!(f)?;
-
-        // Write separator
-        writeln!(
-            f,
-            "├{}┤",
-            "─".repeat(
-                column_names
-                    .iter()
-                    .map(|name| column_widths[name] + 2)
-                    .sum::<usize>()
-                    + column_names.len()
-                    - 1
-            )
-        )?;
+// Display
 
-        // Write data rows
-        let row_count = self.columns.values().next().map(|v| v.len()).unwrap_or(0);
+/// `table[("Sales", 0)]` is the first cell of the Sales column.
+///
+/// A reference has to point at a `Value`, so the first index into a column
+/// of numbers, text or booleans builds the whole column as `Value`s and keeps
+/// them until it changes. `Table::get` builds just the one value.
+///
+/// # Panics
+///
+/// If the table has no such column or row; `Table::get` returns None instead.
+impl Index<(&str, usize)> for Table {
+    type Output = Value;
 
-        for row in 0..row_count {
-            for (i, column_name) in column_names.iter().enumerate() {
-                if i > 0 {
-                    write!(f, "│")?;
-                }
-                if let Some(values) = self.columns.get(*column_name) {
-                    if let Some(value) = values.get(row) {
-                        match value {
-                            Value::Text(s) => {
-                                write!(f, " {:<width$} ", s, width = column_widths[column_name])?
-                            }
-                            Value::Number(n) => {
-                                write!(f, " {:>width$.2} ", n, width = column_widths[column_name])?
-                            }
-                            Value::Boolean(b) => {
-                                write!(f, " {:>width$} ", b, width = column_widths[column_name])?
-                            }
-                            Value::Null => {
-                                write!(f, " {:>width$} ", "", width = column_widths[column_name])?
-                            }
-                        }
-                    }
-                }
-            }
-            writeln!(f)?;
+    fn index(&self, (column, row): (&str, usize)) -> &Value {
+        match self.columns.get(column).map(Column::cached_values) {
+            Some(values) => values.get(row).unwrap_or_else(|| {
+                panic!(
+                    "Row {} is out of range for a table with {} rows",
+                    row,
+                    values.len()
+                )
+            }),
+            None => panic!("{}", self.unknown_column(column)),
         }
-
-        // Write bottom border
-        writeln!(
-            f,
-            "└{}┘",
-            "─".repeat(
-                column_names
-                    .iter()
-                    .map(|name| column_widths[name] + 2)
-                    .sum::<usize>()
-                    + column_names.len()
-                    - 1
-            )
-        )?;
-
-        Ok(())
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum DaxResult {
     Number(f64),
+    Integer(i64),
+    /// A currency amount in ten-thousandths, as in `Value::Currency`
+    Currency(i64),
     Text(String),
     Boolean(bool),
-    Error(String),
+    DateTime(NaiveDateTime),
+    Blank,
+    Table(Table),
+}
+
+/// A scalar renders as the `Value` it would be in a cell, Blank as nothing,
+/// and a table as the table does.
+impl fmt::Display for DaxResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = match self {
+            DaxResult::Table(table) => return table.fmt(f),
+            DaxResult::Number(n) => Value::Number(*n),
+            DaxResult::Integer(i) => Value::Integer(*i),
+            DaxResult::Currency(units) => Value::Currency(*units),
+            DaxResult::Text(s) => return f.pad(s),
+            DaxResult::Boolean(b) => Value::Boolean(*b),
+            DaxResult::DateTime(d) => Value::DateTime(*d),
+            DaxResult::Blank => Value::Null,
+        };
+        value.fmt(f)
+    }
+}
+
+impl From<&Value> for DaxResult {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Number(n) => DaxResult::Number(*n),
+            Value::Integer(i) => DaxResult::Integer(*i),
+            Value::Currency(c) => DaxResult::Currency(*c),
+            Value::Text(s) => DaxResult::Text(s.clone()),
+            Value::Boolean(b) => DaxResult::Boolean(*b),
+            Value::DateTime(d) => DaxResult::DateTime(*d),
+            Value::Null => DaxResult::Blank,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -461,13 +2860,15 @@ mod tests {
     #[test]
     fn test_dax_sum() {
         let mut table = Table::new();
-        table.add_column(
-            "Sales".to_string(),
-            vec![10.0.into(), 20.0.into(), 30.0.into()],
-        );
+        table
+            .add_column(
+                "Sales".to_string(),
+                vec![10.0.into(), 20.0.into(), 30.0.into()],
+            )
+            .unwrap();
 
         match table.evaluate_dax("SUM([Sales])") {
-            DaxResult::Number(n) => assert_eq!(n, 60.0),
+            Ok(DaxResult::Number(n)) => assert_eq!(n, 60.0),
             _ => panic!("Expected number result"),
         }
     }
@@ -475,14 +2876,68 @@ mod tests {
     #[test]
     fn test_dax_average() {
         let mut table = Table::new();
-        table.add_column(
-            "Sales".to_string(),
-            vec![10.0.into(), 20.0.into(), 30.0.into()],
-        );
+        table
+            .add_column(
+                "Sales".to_string(),
+                vec![10.0.into(), 20.0.into(), 30.0.into()],
+            )
+            .unwrap();
 
         match table.evaluate_dax("AVERAGE([Sales])") {
-            DaxResult::Number(n) => assert_eq!(n, 20.0),
+            Ok(DaxResult::Number(n)) => assert_eq!(n, 20.0),
             _ => panic!("Expected number result"),
         }
     }
+
+    #[test]
+    fn test_currency_arithmetic_types() {
+        let amount = DaxResult::Currency(15_000);
+        let number = DaxResult::Number(2.0);
+
+        assert!(matches!(
+            arithmetic(BinaryOperator::Add, &amount, &amount),
+            Ok(DaxResult::Currency(30_000))
+        ));
+        assert!(matches!(
+            arithmetic(BinaryOperator::Multiply, &amount, &number),
+            Ok(DaxResult::Currency(30_000))
+        ));
+        assert!(matches!(
+            arithmetic(BinaryOperator::Multiply, &amount, &amount),
+            Ok(DaxResult::Currency(22_500))
+        ));
+        match arithmetic(BinaryOperator::Add, &amount, &number) {
+            Ok(DaxResult::Number(n)) => assert_eq!(n, 3.5),
+            other => panic!("Expected number result, got {:?}", other),
+        }
+        assert!(arithmetic(
+            BinaryOperator::Subtract,
+            &DaxResult::Currency(i64::MIN),
+            &amount
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_integer_arithmetic_types() {
+        let three = DaxResult::Integer(3);
+
+        assert!(matches!(
+            arithmetic(BinaryOperator::Multiply, &three, &DaxResult::Integer(4)),
+            Ok(DaxResult::Integer(12))
+        ));
+        assert!(matches!(
+            arithmetic(BinaryOperator::Add, &three, &DaxResult::Currency(5_000)),
+            Ok(DaxResult::Currency(35_000))
+        ));
+        match arithmetic(BinaryOperator::Divide, &three, &DaxResult::Integer(2)) {
+            Ok(DaxResult::Number(n)) => assert_eq!(n, 1.5),
+            other => panic!("Expected number result, got {:?}", other),
+        }
+        // Overflow promotes rather than wrapping
+        match arithmetic(BinaryOperator::Add, &DaxResult::Integer(i64::MAX), &three) {
+            Ok(DaxResult::Number(n)) => assert!(n > 9.2e18),
+            other => panic!("Expected number result, got {:?}", other),
+        }
+    }
 }
@@ -0,0 +1,407 @@
+// time_intelligence.rs
+
+//! Time-intelligence functions over a date column.
+//!
+//! Almost every function here follows the same pattern: take the dates visible
+//! in the current filter context, pick an anchor (the first or the last), find
+//! the period around it, optionally shift that period, and return the dates of
+//! the column falling inside it as a one-column table usable as a CALCULATE filter.
+use crate::context::{EvalContext, Filter, FilterModifier};
+use crate::dates::{self, Interval};
+use crate::error::DaxError;
+use crate::parser::DaxExpr;
+use crate::table::{column_name, expect_arguments, interval_argument, DaxResult};
+use crate::types::Value;
+use crate::Table;
+use chrono::{Datelike, NaiveDateTime, NaiveTime};
+use std::collections::HashSet;
+
+/// Which visible date a period is derived from
+#[derive(Debug, Clone, Copy)]
+enum Anchor {
+    First,
+    Last,
+}
+
+/// Calendar periods the navigation functions work in
+#[derive(Debug, Clone, Copy)]
+enum Period {
+    Day,
+    Month,
+    Quarter,
+    Year,
+}
+
+// Year end used when no fiscal year end is given
+const CALENDAR_YEAR_END: (u32, u32) = (12, 31);
+
+/// First and last day of the period containing `date`, moved by `offset` whole periods
+fn period_range(
+    date: &NaiveDateTime,
+    period: Period,
+    offset: i64,
+    year_end: (u32, u32),
+) -> Option<(NaiveDateTime, NaiveDateTime)> {
+    let day = date.date().and_time(NaiveTime::MIN);
+    let (start, months) = match period {
+        Period::Day => {
+            let start = dates::shift(&day, offset, Interval::Day)?;
+            return Some((start, start));
+        }
+        Period::Month => (dates::date(day.year() as i64, day.month() as i64, 1)?, 1),
+        Period::Quarter => {
+            let first_month = (day.month0() / 3 * 3 + 1) as i64;
+            (dates::date(day.year() as i64, first_month, 1)?, 3)
+        }
+        Period::Year => (dates::year_start(&day, year_end.0, year_end.1)?, 12),
+    };
+    let start = dates::add_months(&start, offset.checked_mul(months)?)?;
+    let end = dates::shift(&dates::add_months(&start, months)?, -1, Interval::Day)?;
+    Some((start, end))
+}
+
+impl Table {
+    pub(crate) fn evaluate_time_intelligence(
+        &self,
+        name: &str,
+        args: &[&DaxExpr],
+        ctx: &EvalContext,
+    ) -> Result<DaxResult, DaxError> {
+        let table = match name {
+            "FIRSTDATE" | "LASTDATE" => {
+                expect_arguments(name, args, 1, 1)?;
+                let col_name = column_name(name, args[0])?;
+                let visible = self.visible_dates(col_name, ctx);
+                let date = if name == "FIRSTDATE" {
+                    visible.into_iter().min()
+                } else {
+                    visible.into_iter().max()
+                };
+                date_table(col_name, date.into_iter().collect())
+            }
+            "STARTOFMONTH" | "STARTOFQUARTER" | "STARTOFYEAR" | "ENDOFMONTH" | "ENDOFQUARTER"
+            | "ENDOFYEAR" => {
+                let (col_name, year_end) = self.dates_and_year_end(name, args, ctx)?;
+                let period = period_suffix(name);
+                let (anchor, pick): (Anchor, fn(Vec<_>) -> Option<_>) = if name.starts_with("START")
+                {
+                    (Anchor::First, |dates: Vec<_>| dates.into_iter().min())
+                } else {
+                    (Anchor::Last, |dates: Vec<_>| dates.into_iter().max())
+                };
+                let dates = self.period_dates(col_name, ctx, anchor, period, 0, year_end)?;
+                date_table(col_name, pick(dates).into_iter().collect())
+            }
+            "PREVIOUSDAY" | "PREVIOUSMONTH" | "PREVIOUSQUARTER" | "PREVIOUSYEAR" | "NEXTDAY"
+            | "NEXTMONTH" | "NEXTQUARTER" | "NEXTYEAR" => {
+                let (col_name, year_end) = self.dates_and_year_end(name, args, ctx)?;
+                let period = period_suffix(name);
+                // Previous periods step back from the first date, next periods forward from the last
+                let (anchor, offset) = if name.starts_with("PREVIOUS") {
+                    (Anchor::First, -1)
+                } else {
+                    (Anchor::Last, 1)
+                };
+                let dates = self.period_dates(col_name, ctx, anchor, period, offset, year_end)?;
+                date_table(col_name, dates)
+            }
+            "DATESMTD" | "DATESQTD" | "DATESYTD" => {
+                let (col_name, year_end) = self.dates_and_year_end(name, args, ctx)?;
+                self.dates_to_date(col_name, to_date_period(name), year_end, ctx)?
+            }
+            "TOTALMTD" | "TOTALQTD" | "TOTALYTD" => {
+                let (col_name, year_end) = self.total_arguments(name, args, ctx)?;
+                let period_to_date =
+                    self.dates_to_date(col_name, to_date_period(name), year_end, ctx)?;
+                return self.calculate_over(name, args, period_to_date, ctx);
+            }
+            "OPENINGBALANCEMONTH"
+            | "OPENINGBALANCEQUARTER"
+            | "OPENINGBALANCEYEAR"
+            | "CLOSINGBALANCEMONTH"
+            | "CLOSINGBALANCEQUARTER"
+            | "CLOSINGBALANCEYEAR" => {
+                let (col_name, year_end) = self.total_arguments(name, args, ctx)?;
+                // Opening balances are the closing balance of the previous period
+                let (anchor, offset) = if name.starts_with("OPENING") {
+                    (Anchor::First, -1)
+                } else {
+                    (Anchor::Last, 0)
+                };
+                let period = period_suffix(name);
+                let last = self
+                    .period_dates(col_name, ctx, anchor, period, offset, year_end)?
+                    .into_iter()
+                    .max();
+                let dates = date_table(col_name, last.into_iter().collect());
+                return self.calculate_over(name, args, dates, ctx);
+            }
+            "DATESBETWEEN" => {
+                expect_arguments(name, args, 3, 3)?;
+                let col_name = column_name(name, args[0])?;
+                // A blank bound leaves that side of the range open
+                let start = self.optional_date_argument(name, args[1], ctx)?;
+                let end = self.optional_date_argument(name, args[2], ctx)?;
+                self.dates_between(col_name, start, end)
+            }
+            "DATESINPERIOD" => {
+                expect_arguments(name, args, 4, 4)?;
+                let col_name = column_name(name, args[0])?;
+                let anchor = self.date_argument(name, args[1], ctx)?;
+                let count = self.number_argument(name, args[2], ctx)?.trunc() as i64;
+                let interval = interval_argument(name, args[3])?;
+                let out_of_range = || format!("{} period is out of range", name);
+                let other_end = dates::shift(&anchor, count, interval).ok_or_else(out_of_range)?;
+                // The period includes the anchor and stops one day short of the other end
+                let (start, end) = if count < 0 {
+                    (dates::shift(&other_end, 1, Interval::Day), Some(anchor))
+                } else {
+                    (Some(anchor), dates::shift(&other_end, -1, Interval::Day))
+                };
+                if start.is_none() || end.is_none() {
+                    return Err(out_of_range().into());
+                }
+                self.dates_between(col_name, start, end)
+            }
+            "SAMEPERIODLASTYEAR" => {
+                expect_arguments(name, args, 1, 1)?;
+                let col_name = column_name(name, args[0])?;
+                self.shifted_dates(col_name, -1, Interval::Year, ctx)
+            }
+            _ => {
+                return Err(DaxError::UnknownFunction {
+                    name: name.to_string(),
+                    span: None,
+                })
+            }
+        };
+        Ok(DaxResult::Table(table))
+    }
+
+    /// `DATEADD` over a column: every visible date shifted, keeping those present in the column
+    pub(crate) fn shifted_dates(
+        &self,
+        col_name: &str,
+        count: i64,
+        interval: Interval,
+        ctx: &EvalContext,
+    ) -> Table {
+        let shifted = self
+            .visible_dates(col_name, ctx)
+            .iter()
+            .filter_map(|date| dates::shift(date, count, interval))
+            .collect();
+        self.existing_dates(col_name, shifted)
+    }
+
+    // Arguments shared by the TOTAL and BALANCE functions: an expression, the dates
+    // column, an optional filter and, for years, an optional year-end string
+    fn total_arguments<'a>(
+        &self,
+        name: &str,
+        args: &[&'a DaxExpr],
+        ctx: &EvalContext,
+    ) -> Result<(&'a str, (u32, u32)), DaxError> {
+        let takes_year_end = name.ends_with("YEAR") || name.ends_with("YTD");
+        expect_arguments(name, args, 2, if takes_year_end { 4 } else { 3 })?;
+        let col_name = column_name(name, args[1])?;
+        let year_end = match &args[2..] {
+            [arg] if takes_year_end && matches!(arg, DaxExpr::Literal(Value::Text(_))) => {
+                Some(*arg)
+            }
+            [_, year_end] => Some(*year_end),
+            _ => None,
+        };
+        let year_end = match year_end {
+            Some(arg) => self.year_end_argument(name, arg, ctx)?,
+            None => CALENDAR_YEAR_END,
+        };
+        Ok((col_name, year_end))
+    }
+
+    // Evaluate the expression of a TOTAL or BALANCE function over the given dates,
+    // together with its optional filter argument
+    fn calculate_over(
+        &self,
+        name: &str,
+        args: &[&DaxExpr],
+        dates: Table,
+        ctx: &EvalContext,
+    ) -> Result<DaxResult, DaxError> {
+        let mut filters = vec![FilterModifier::Replace(Filter::from_table(
+            &dates,
+            dates.column_names().to_vec(),
+        ))];
+        let filter_arg = match &args[2..] {
+            [arg] if !matches!(arg, DaxExpr::Literal(Value::Text(_))) => Some(*arg),
+            [filter, _] => Some(*filter),
+            _ => None,
+        };
+        if let Some(arg) = filter_arg {
+            filters.push(self.filter_argument(name, arg, ctx)?);
+        }
+        self.calculate(args[0], filters, ctx)
+    }
+
+    // The dates column plus the optional year-end string of the year-based functions
+    fn dates_and_year_end<'a>(
+        &self,
+        name: &str,
+        args: &[&'a DaxExpr],
+        ctx: &EvalContext,
+    ) -> Result<(&'a str, (u32, u32)), DaxError> {
+        let takes_year_end = name.ends_with("YEAR") || name.ends_with("YTD");
+        expect_arguments(name, args, 1, if takes_year_end { 2 } else { 1 })?;
+        let col_name = column_name(name, args[0])?;
+        let year_end = match args.get(1) {
+            Some(arg) => self.year_end_argument(name, arg, ctx)?,
+            None => CALENDAR_YEAR_END,
+        };
+        Ok((col_name, year_end))
+    }
+
+    fn year_end_argument(
+        &self,
+        name: &str,
+        arg: &DaxExpr,
+        ctx: &EvalContext,
+    ) -> Result<(u32, u32), DaxError> {
+        let text = self.text_argument(name, arg, ctx)?;
+        dates::parse_year_end(&text)
+            .ok_or_else(|| format!("Invalid year end date \"{}\"", text).into())
+    }
+
+    // Dates of the column inside the anchor date's period, moved by `offset` periods
+    fn period_dates(
+        &self,
+        col_name: &str,
+        ctx: &EvalContext,
+        anchor: Anchor,
+        period: Period,
+        offset: i64,
+        year_end: (u32, u32),
+    ) -> Result<Vec<NaiveDateTime>, DaxError> {
+        let visible = self.visible_dates(col_name, ctx);
+        let anchor = match anchor {
+            Anchor::First => visible.into_iter().min(),
+            Anchor::Last => visible.into_iter().max(),
+        };
+        let Some(anchor) = anchor else {
+            return Ok(Vec::new());
+        };
+        let (start, end) = period_range(&anchor, period, offset, year_end)
+            .ok_or_else(|| format!("Period around {} is out of range", dates::format(&anchor)))?;
+        Ok(self.dates_in_range(col_name, Some(start), Some(end)))
+    }
+
+    // Dates of the column from the start of the last visible date's period up to that date
+    fn dates_to_date(
+        &self,
+        col_name: &str,
+        period: Period,
+        year_end: (u32, u32),
+        ctx: &EvalContext,
+    ) -> Result<Table, DaxError> {
+        let Some(last) = self.visible_dates(col_name, ctx).into_iter().max() else {
+            return Ok(date_table(col_name, Vec::new()));
+        };
+        let (start, _) = period_range(&last, period, 0, year_end)
+            .ok_or_else(|| format!("Period around {} is out of range", dates::format(&last)))?;
+        Ok(self.dates_between(col_name, Some(start), Some(last)))
+    }
+
+    // Dates in a column on the rows visible after context transition
+    fn visible_dates(&self, col_name: &str, ctx: &EvalContext) -> Vec<NaiveDateTime> {
+        let ctx = self.transition_context(ctx);
+        let Some(column) = self.get_column(col_name) else {
+            return Vec::new();
+        };
+        self.visible_rows(&ctx)
+            .into_iter()
+            .filter_map(|row| match column.get(row) {
+                Some(Value::DateTime(d)) => Some(d),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Date table of the given dates that also appear in the column
+    fn existing_dates(&self, col_name: &str, candidates: Vec<NaiveDateTime>) -> Table {
+        let candidates: HashSet<NaiveDateTime> = candidates.into_iter().collect();
+        date_table(
+            col_name,
+            self.distinct_dates(col_name)
+                .into_iter()
+                .filter(|d| candidates.contains(d))
+                .collect(),
+        )
+    }
+
+    // Date table of the column's dates within an inclusive range; None leaves a side open
+    fn dates_between(
+        &self,
+        col_name: &str,
+        start: Option<NaiveDateTime>,
+        end: Option<NaiveDateTime>,
+    ) -> Table {
+        date_table(col_name, self.dates_in_range(col_name, start, end))
+    }
+
+    fn dates_in_range(
+        &self,
+        col_name: &str,
+        start: Option<NaiveDateTime>,
+        end: Option<NaiveDateTime>,
+    ) -> Vec<NaiveDateTime> {
+        self.distinct_dates(col_name)
+            .into_iter()
+            .filter(|d| start.is_none_or(|start| *d >= start) && end.is_none_or(|end| *d <= end))
+            .collect()
+    }
+
+    // Every distinct date of the column, ignoring filters, in chronological order
+    fn distinct_dates(&self, col_name: &str) -> Vec<NaiveDateTime> {
+        let mut distinct: Vec<NaiveDateTime> = self
+            .get_column(col_name)
+            .into_iter()
+            .flatten()
+            .filter_map(|value| match value {
+                Value::DateTime(d) => Some(d),
+                _ => None,
+            })
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        distinct.sort();
+        distinct
+    }
+}
+
+// Period named at the end of a function name, such as PREVIOUSMONTH or ENDOFQUARTER
+fn period_suffix(name: &str) -> Period {
+    if name.ends_with("DAY") {
+        Period::Day
+    } else if name.ends_with("MONTH") {
+        Period::Month
+    } else if name.ends_with("QUARTER") {
+        Period::Quarter
+    } else {
+        Period::Year
+    }
+}
+
+fn to_date_period(name: &str) -> Period {
+    match &name[name.len() - 3..] {
+        "MTD" => Period::Month,
+        "QTD" => Period::Quarter,
+        _ => Period::Year,
+    }
+}
+
+/// One-column date table, named after the column the dates came from
+pub(crate) fn date_table(col_name: &str, dates: Vec<NaiveDateTime>) -> Table {
+    Table::single_column(
+        col_name.to_string(),
+        dates.into_iter().map(Value::DateTime).collect(),
+    )
+}
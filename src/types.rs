@@ -1,11 +1,26 @@
 // types.rs
+use crate::dates;
+use crate::error::DaxError;
+use chrono::NaiveDateTime;
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt;
 use std::hash::{Hash, Hasher};
 
+/// Currency amounts are stored in ten-thousandths, like DAX's fixed decimal type
+pub const CURRENCY_SCALE: i64 = 10_000;
+
 #[derive(Debug, Clone)]
 pub enum Value {
     Number(f64),
+    /// A whole number; equal to, and hashed like, a `Number` of exactly the
+    /// same amount
+    Integer(i64),
+    /// A fixed-point amount in ten-thousandths; see `Value::currency`
+    Currency(i64),
     Text(String),
     Boolean(bool),
+    DateTime(NaiveDateTime),
     Null,
 }
 
@@ -20,8 +35,14 @@ impl PartialEq for Value {
                     a == b
                 }
             }
+            (Value::Integer(a), Value::Integer(b)) => a == b,
+            (Value::Integer(a), Value::Number(b)) | (Value::Number(b), Value::Integer(a)) => {
+                compare_exactly(*a, *b) == Some(Ordering::Equal)
+            }
+            (Value::Currency(a), Value::Currency(b)) => a == b,
             (Value::Text(a), Value::Text(b)) => a == b,
             (Value::Boolean(a), Value::Boolean(b)) => a == b,
+            (Value::DateTime(a), Value::DateTime(b)) => a == b,
             (Value::Null, Value::Null) => true,
             _ => false,
         }
@@ -31,13 +52,56 @@ impl PartialEq for Value {
 // Custom Eq implementation that handles floating point numbers
 impl Eq for Value {}
 
+/// Values order by kind first: Null, then Boolean, then the numeric kinds, then
+/// Text, then DateTime. Within a kind they order by value, with text compared
+/// case-sensitively. Integer and Number compare by their exact amounts, as they
+/// do for equality, and a Currency tying with either sorts after it. NaN equals
+/// itself and sorts above every other number.
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Value::Boolean(a), Value::Boolean(b)) => a.cmp(b),
+            (Value::Integer(a), Value::Integer(b)) => a.cmp(b),
+            (Value::Currency(a), Value::Currency(b)) => a.cmp(b),
+            (Value::Text(a), Value::Text(b)) => a.cmp(b),
+            (Value::DateTime(a), Value::DateTime(b)) => a.cmp(b),
+            (Value::Integer(a), Value::Number(b)) => {
+                compare_exactly(*a, *b).unwrap_or(Ordering::Less)
+            }
+            (Value::Number(a), Value::Integer(b)) => {
+                compare_exactly(*b, *a).unwrap_or(Ordering::Less).reverse()
+            }
+            _ => match (self.as_number(), other.as_number()) {
+                (Some(a), Some(b)) => a
+                    .partial_cmp(&b)
+                    .unwrap_or_else(|| a.is_nan().cmp(&b.is_nan()))
+                    .then_with(|| {
+                        matches!(self, Value::Currency(_)).cmp(&matches!(other, Value::Currency(_)))
+                    }),
+                _ => self.kind_rank().cmp(&other.kind_rank()),
+            },
+        }
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 // Custom Hash implementation for Value that handles floating point numbers
 impl Hash for Value {
     fn hash<H: Hasher>(&self, state: &mut H) {
         match self {
+            // A whole number is hashed as the integer it equals, so that
+            // Integer(3), Number(3.0) and Number(-0.0) all collide
+            Value::Integer(i) => state.write_i64(*i),
             Value::Number(n) => {
                 // Handle NaN and infinite values
-                if n.is_nan() {
+                if let Some(i) = whole_number(*n) {
+                    state.write_i64(i);
+                } else if n.is_nan() {
                     state.write_u8(0);
                 } else if n.is_infinite() {
                     if n.is_sign_positive() {
@@ -50,9 +114,363 @@ impl Hash for Value {
                     state.write_u64(n.to_bits());
                 }
             }
+            Value::Currency(c) => {
+                state.write_u8(4);
+                c.hash(state);
+            }
             Value::Text(s) => s.hash(state),
             Value::Boolean(b) => b.hash(state),
+            Value::DateTime(d) => d.hash(state),
             Value::Null => state.write_u8(3),
         }
     }
 }
+
+impl Value {
+    /// Amount of a number, integer or currency value; None for other kinds
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            Value::Integer(i) => Some(*i as f64),
+            Value::Currency(c) => Some(currency_amount(*c)),
+            _ => None,
+        }
+    }
+
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Value::Text(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    /// The number this value stands for in DAX arithmetic: TRUE is 1 and
+    /// FALSE 0, blank is 0, a date is its serial number, and text is read as
+    /// a number, which is an error if it isn't one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dax_rust::Value;
+    ///
+    /// assert_eq!(Value::from(" 42 ").coerce_number().unwrap(), 42.0);
+    /// assert_eq!(Value::Boolean(true).coerce_number().unwrap(), 1.0);
+    /// assert!(Value::from("forty-two").coerce_number().is_err());
+    /// ```
+    pub fn coerce_number(&self) -> Result<f64, DaxError> {
+        match self {
+            Value::Boolean(b) => Ok(if *b { 1.0 } else { 0.0 }),
+            Value::Null => Ok(0.0),
+            Value::DateTime(d) => Ok(dates::to_serial(d)),
+            Value::Text(s) => s.trim().parse().map_err(|_| DaxError::TypeMismatch {
+                expected: "a number".to_string(),
+                got: format!("the text {:?}", s),
+            }),
+            number => Ok(number.as_number().unwrap_or_default()),
+        }
+    }
+
+    // What kind of value this is, for error messages
+    fn kind(&self) -> &'static str {
+        match self {
+            Value::Number(_) => "a number",
+            Value::Integer(_) => "an integer",
+            Value::Currency(_) => "a currency amount",
+            Value::Text(_) => "text",
+            Value::Boolean(_) => "a boolean",
+            Value::DateTime(_) => "a date",
+            Value::Null => "blank",
+        }
+    }
+
+    fn mismatch(&self, expected: &str) -> DaxError {
+        DaxError::TypeMismatch {
+            expected: expected.to_string(),
+            got: self.kind().to_string(),
+        }
+    }
+
+    fn kind_rank(&self) -> u8 {
+        match self {
+            Value::Null => 0,
+            Value::Boolean(_) => 1,
+            Value::Number(_) | Value::Integer(_) | Value::Currency(_) => 2,
+            Value::Text(_) => 3,
+            Value::DateTime(_) => 4,
+        }
+    }
+
+    /// A currency value for an amount, rounded to four decimal places, or None
+    /// when the amount is not finite or out of range
+    pub fn currency(amount: f64) -> Option<Value> {
+        currency_units(amount).map(Value::Currency)
+    }
+}
+
+/// DAX-style rendering: numbers to 15 significant digits without trailing
+/// zeros, currency with at least two decimals, booleans as TRUE and FALSE,
+/// and Null as nothing. Width and alignment flags pad the text.
+///
+/// # Examples
+///
+/// ```
+/// use dax_rust::Value;
+///
+/// assert_eq!(Value::Number(100.0).to_string(), "100");
+/// assert_eq!(Value::Number(0.1 + 0.2).to_string(), "0.3");
+/// assert_eq!(Value::currency(2.5).unwrap().to_string(), "2.50");
+/// assert_eq!(Value::Boolean(true).to_string(), "TRUE");
+/// assert_eq!(Value::Null.to_string(), "");
+/// ```
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Number(n) => f.pad(&format_number(*n)),
+            Value::Integer(i) => f.pad(&i.to_string()),
+            Value::Currency(units) => f.pad(&format_currency(*units)),
+            Value::Text(s) => f.pad(s),
+            Value::Boolean(b) => f.pad(if *b { "TRUE" } else { "FALSE" }),
+            Value::DateTime(d) => f.pad(&dates::format(d)),
+            Value::Null => f.pad(""),
+        }
+    }
+}
+
+// The first float past i64::MAX; its negation is i64::MIN itself
+const TWO_TO_63: f64 = 9_223_372_036_854_775_808.0;
+
+// `f` as the integer it equals exactly, if it's whole and in range
+fn whole_number(f: f64) -> Option<i64> {
+    (f.fract() == 0.0 && (-TWO_TO_63..TWO_TO_63).contains(&f)).then_some(f as i64)
+}
+
+// How an integer compares with a float, exactly rather than through the
+// float it rounds to, which past 2^53 would make 2^53 + 1 equal 2^53 as a
+// float and so not transitive. None against NaN
+fn compare_exactly(i: i64, f: f64) -> Option<Ordering> {
+    if f.is_nan() {
+        return None;
+    }
+    let whole = f.trunc();
+    if whole >= TWO_TO_63 {
+        return Some(Ordering::Less);
+    }
+    if whole < -TWO_TO_63 {
+        return Some(Ordering::Greater);
+    }
+    // The whole part is in range, so converting it loses nothing, and a
+    // fraction breaks a tie
+    Some(
+        i.cmp(&(whole as i64))
+            .then_with(|| 0.0.partial_cmp(&(f - whole)).unwrap_or(Ordering::Equal)),
+    )
+}
+
+// A number rounded to the 15 significant digits DAX shows, written without
+// trailing zeros
+fn format_number(n: f64) -> String {
+    if n.is_nan() {
+        return "NaN".to_string();
+    }
+    if n.is_infinite() {
+        return if n > 0.0 { "Infinity" } else { "-Infinity" }.to_string();
+    }
+    let rounded: f64 = format!("{:.14e}", n).parse().unwrap_or(n);
+    // No "-0"
+    (rounded + 0.0).to_string()
+}
+
+// Ten-thousandths as an amount with two to four decimals
+fn format_currency(units: i64) -> String {
+    let sign = if units < 0 { "-" } else { "" };
+    let scale = CURRENCY_SCALE.unsigned_abs();
+    let (whole, fraction) = (units.unsigned_abs() / scale, units.unsigned_abs() % scale);
+    let mut decimals = format!("{:04}", fraction);
+    while decimals.len() > 2 && decimals.ends_with('0') {
+        decimals.pop();
+    }
+    format!("{}{}.{}", sign, whole, decimals)
+}
+
+/// A number, integer or currency value's amount
+impl TryFrom<Value> for f64 {
+    type Error = DaxError;
+
+    fn try_from(value: Value) -> Result<f64, DaxError> {
+        value.as_number().ok_or_else(|| value.mismatch("a number"))
+    }
+}
+
+/// An integer, or a number with no fractional part
+impl TryFrom<Value> for i64 {
+    type Error = DaxError;
+
+    fn try_from(value: Value) -> Result<i64, DaxError> {
+        match value {
+            Value::Integer(i) => Ok(i),
+            Value::Number(n) if n.fract() == 0.0 && n.abs() < i64::MAX as f64 => Ok(n as i64),
+            other => Err(other.mismatch("an integer")),
+        }
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = DaxError;
+
+    fn try_from(value: Value) -> Result<String, DaxError> {
+        match value {
+            Value::Text(s) => Ok(s),
+            other => Err(other.mismatch("text")),
+        }
+    }
+}
+
+impl TryFrom<Value> for bool {
+    type Error = DaxError;
+
+    fn try_from(value: Value) -> Result<bool, DaxError> {
+        value.as_bool().ok_or_else(|| value.mismatch("a boolean"))
+    }
+}
+
+/// The kind of values in a column, as `Table::dtype` infers it and
+/// `Table::cast_column` converts to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DType {
+    Number,
+    Integer,
+    Currency,
+    Text,
+    Boolean,
+    Date,
+    /// Values of more than one kind
+    Mixed,
+    /// Nothing but Nulls
+    Null,
+}
+
+impl DType {
+    /// The kind every non-Null value has. Integers and numbers together are
+    /// `Number`, other combinations are `Mixed`, and no values at all `Null`.
+    pub fn infer<V: Borrow<Value>>(values: impl IntoIterator<Item = V>) -> DType {
+        values
+            .into_iter()
+            .map(|value| value.borrow().dtype())
+            .fold(DType::Null, |seen, dtype| match (seen, dtype) {
+                (DType::Null, dtype) | (dtype, DType::Null) => dtype,
+                (a, b) if a == b => a,
+                (DType::Integer, DType::Number) | (DType::Number, DType::Integer) => DType::Number,
+                _ => DType::Mixed,
+            })
+    }
+}
+
+impl fmt::Display for DType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match self {
+            DType::Number => "number",
+            DType::Integer => "integer",
+            DType::Currency => "currency",
+            DType::Text => "text",
+            DType::Boolean => "boolean",
+            DType::Date => "date",
+            DType::Mixed => "mixed",
+            DType::Null => "null",
+        })
+    }
+}
+
+impl Value {
+    /// The kind of this value; never `Mixed`
+    pub fn dtype(&self) -> DType {
+        match self {
+            Value::Number(_) => DType::Number,
+            Value::Integer(_) => DType::Integer,
+            Value::Currency(_) => DType::Currency,
+            Value::Text(_) => DType::Text,
+            Value::Boolean(_) => DType::Boolean,
+            Value::DateTime(_) => DType::Date,
+            Value::Null => DType::Null,
+        }
+    }
+
+    /// This value converted to another kind, or None if it doesn't convert.
+    ///
+    /// Text is trimmed and read as a number, TRUE or FALSE in any case, or an
+    /// ISO date. Numbers are TRUE unless zero and dates count as their serial
+    /// numbers, while an integer has to be whole. Anything converts to text,
+    /// as it displays, and to `Mixed` unchanged. Null stays Null, and nothing
+    /// else converts to `Null`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dax_rust::types::{DType, Value};
+    ///
+    /// assert_eq!(Value::from(" 12.5").cast(DType::Number), Some(Value::Number(12.5)));
+    /// assert_eq!(Value::Number(1.0).cast(DType::Boolean), Some(Value::Boolean(true)));
+    /// assert_eq!(Value::Number(1.5).cast(DType::Integer), None);
+    /// ```
+    pub fn cast(&self, to: DType) -> Option<Value> {
+        if self.is_null() || self.dtype() == to {
+            return Some(self.clone());
+        }
+        match to {
+            DType::Number => self.cast_number().map(Value::Number),
+            DType::Integer => self
+                .cast_number()
+                .filter(|n| n.fract() == 0.0 && n.abs() < i64::MAX as f64)
+                .map(|n| Value::Integer(n as i64)),
+            DType::Currency => self.cast_number().and_then(Value::currency),
+            DType::Text => Some(Value::Text(self.to_string())),
+            DType::Boolean => match self {
+                Value::Text(s) if s.trim().eq_ignore_ascii_case("true") => {
+                    Some(Value::Boolean(true))
+                }
+                Value::Text(s) if s.trim().eq_ignore_ascii_case("false") => {
+                    Some(Value::Boolean(false))
+                }
+                other => other.as_number().map(|n| Value::Boolean(n != 0.0)),
+            },
+            DType::Date => match self {
+                Value::Text(s) => dates::parse_iso(s.trim()).map(Value::DateTime),
+                other => other
+                    .as_number()
+                    .and_then(dates::from_serial)
+                    .map(Value::DateTime),
+            },
+            DType::Mixed => Some(self.clone()),
+            DType::Null => None,
+        }
+    }
+
+    // The amount for a cast to one of the numeric kinds
+    fn cast_number(&self) -> Option<f64> {
+        match self {
+            Value::Text(s) if s.trim().is_empty() => None,
+            other => other.coerce_number().ok(),
+        }
+    }
+}
+
+/// Ten-thousandths in an amount, rounded half away from zero
+pub fn currency_units(amount: f64) -> Option<i64> {
+    let units = (amount * CURRENCY_SCALE as f64).round();
+    (units.is_finite() && units.abs() < i64::MAX as f64).then_some(units as i64)
+}
+
+/// The amount a number of ten-thousandths stands for
+pub fn currency_amount(units: i64) -> f64 {
+    units as f64 / CURRENCY_SCALE as f64
+}
@@ -0,0 +1,228 @@
+// window.rs
+
+//! Window functions: OFFSET, INDEX and WINDOW, plus the RANK and ROWNUMBER scalars.
+//!
+//! Each sorts the rows of a table by an `ORDERBY` clause and returns the rows at
+//! positions counted either from the ends of that ordering or from the current
+//! row. The current row is the one whose values match the row context on every
+//! column the two tables share.
+use crate::context::EvalContext;
+use crate::error::DaxError;
+use crate::parser::DaxExpr;
+use crate::table::{compare_results, expect_arguments, ColumnValues, DaxResult};
+use crate::types::Value;
+use crate::Table;
+use std::cmp::Ordering;
+
+impl Table {
+    pub(crate) fn evaluate_window(
+        &self,
+        name: &str,
+        args: &[&DaxExpr],
+        ctx: &EvalContext,
+    ) -> Result<DaxResult, DaxError> {
+        // WINDOW takes two bounds before the table, the others a single position
+        let table_arg = if name == "WINDOW" { 4 } else { 1 };
+        expect_arguments(name, args, table_arg + 2, table_arg + 2)?;
+        let table = self.table_argument(name, args[table_arg], ctx)?;
+        let (order_by, descending) = self.order_by_argument(name, args[table_arg + 1], ctx)?;
+        let sorted = table.sorted_rows(order_by, descending, ctx)?;
+        let last = sorted.len() as i64 - 1;
+
+        let (start, end) = match name {
+            "INDEX" => {
+                let position = self.position_argument(name, args[0], ctx)?;
+                let index = absolute_index(position, sorted.len());
+                (index, index)
+            }
+            "OFFSET" => {
+                let delta = self.position_argument(name, args[0], ctx)?;
+                let index = self.current_position(name, &table, &sorted, ctx)? + delta;
+                (index, index)
+            }
+            _ => {
+                let from = self.window_bound(name, args[0], args[1], &table, &sorted, ctx)?;
+                let to = self.window_bound(name, args[2], args[3], &table, &sorted, ctx)?;
+                // A window reaching past either end is cut short rather than emptied
+                (from.max(0), to.min(last))
+            }
+        };
+
+        let rows = if 0 <= start && start <= end && end <= last {
+            sorted[start as usize..=end as usize].to_vec()
+        } else {
+            Vec::new()
+        };
+        Ok(DaxResult::Table(table.select_rows(&rows)))
+    }
+
+    // Position of the current row in the ordering of a table, counting from 1
+    pub(crate) fn evaluate_rank(
+        &self,
+        name: &str,
+        args: &[&DaxExpr],
+        ctx: &EvalContext,
+    ) -> Result<DaxResult, DaxError> {
+        // RANK may start with how ties are ranked: SKIP (the default) or DENSE
+        let (dense, args) = match args {
+            [DaxExpr::Variable(ties), rest @ ..]
+                if name == "RANK"
+                    && (ties.eq_ignore_ascii_case("DENSE")
+                        || ties.eq_ignore_ascii_case("SKIP")) =>
+            {
+                (ties.eq_ignore_ascii_case("DENSE"), rest)
+            }
+            _ => (false, args),
+        };
+        expect_arguments(name, args, 2, 2)?;
+        let table = self.table_argument(name, args[0], ctx)?;
+        let (order_by, descending) = self.order_by_argument(name, args[1], ctx)?;
+        let keyed = table.sorted_keys(order_by, descending, ctx)?;
+        let sorted: Vec<usize> = keyed.iter().map(|(_, row)| *row).collect();
+        let position = self.current_position(name, &table, &sorted, ctx)? as usize;
+
+        // Rows tied with the current one sit together in the ordering
+        let key = &keyed[position].0;
+        let tied = |(other, _): &(DaxResult, usize)| compare_results(other, key) == Ordering::Equal;
+        let first_tied = keyed.iter().position(tied).unwrap_or(position);
+
+        let rank = match name {
+            "RANK" if dense => {
+                let distinct_before = keyed[..first_tied]
+                    .chunk_by(|a, b| compare_results(&a.0, &b.0) == Ordering::Equal)
+                    .count();
+                distinct_before + 1
+            }
+            "RANK" => first_tied + 1,
+            _ => {
+                let ties: Vec<usize> = keyed
+                    .iter()
+                    .filter(|k| tied(k))
+                    .map(|(_, row)| *row)
+                    .collect();
+                first_tied + table.tie_break_position(&ties, sorted[position])? + 1
+            }
+        };
+        Ok(DaxResult::Number(rank as f64))
+    }
+
+    // Position of a row among tied rows, ordering them by the rest of their
+    // columns. Rows equal on every column can't be told apart.
+    fn tie_break_position(&self, ties: &[usize], row: usize) -> Result<usize, DaxError> {
+        let mut names = self.column_names().to_vec();
+        names.sort();
+        let columns: Vec<ColumnValues> = names.iter().filter_map(|n| self.get_column(n)).collect();
+        let cell =
+            |values: &ColumnValues, row| DaxResult::from(&values.get(row).unwrap_or(Value::Null));
+        let compare_rows = |a: usize, b: usize| {
+            columns
+                .iter()
+                .map(|values| compare_results(&cell(values, a), &cell(values, b)))
+                .find(|ordering| *ordering != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        };
+        let mut ordered = ties.to_vec();
+        ordered.sort_by(|&a, &b| compare_rows(a, b));
+        if ordered
+            .windows(2)
+            .any(|pair| compare_rows(pair[0], pair[1]) == Ordering::Equal)
+        {
+            return Err("ROWNUMBER cannot uniquely identify every row of its table".into());
+        }
+        Ok(ordered.iter().position(|&r| r == row).unwrap_or(0))
+    }
+
+    // The expression and direction of an ORDERBY clause
+    fn order_by_argument<'a>(
+        &self,
+        function: &str,
+        arg: &'a DaxExpr,
+        ctx: &EvalContext,
+    ) -> Result<(&'a DaxExpr, bool), DaxError> {
+        let inner = match arg {
+            DaxExpr::FunctionCall { name, args, .. } if name.eq_ignore_ascii_case("ORDERBY") => {
+                args
+            }
+            _ => return Err(format!("{} expects an ORDERBY clause", function).into()),
+        };
+        match inner.as_slice() {
+            [expression] => Ok((expression, false)),
+            [expression, order] => {
+                Ok((expression, self.descending_argument(function, order, ctx)?))
+            }
+            _ => Err("ORDERBY takes one expression and an optional ASC or DESC".into()),
+        }
+    }
+
+    fn position_argument(
+        &self,
+        function: &str,
+        arg: &DaxExpr,
+        ctx: &EvalContext,
+    ) -> Result<i64, DaxError> {
+        Ok(self.number_argument(function, arg, ctx)?.trunc() as i64)
+    }
+
+    // Index into the ordering of a WINDOW bound, given as a position and ABS or REL
+    fn window_bound(
+        &self,
+        function: &str,
+        position: &DaxExpr,
+        bound_type: &DaxExpr,
+        table: &Table,
+        sorted: &[usize],
+        ctx: &EvalContext,
+    ) -> Result<i64, DaxError> {
+        let position = self.position_argument(function, position, ctx)?;
+        match bound_type {
+            DaxExpr::Variable(t) if t.eq_ignore_ascii_case("ABS") => {
+                Ok(absolute_index(position, sorted.len()))
+            }
+            DaxExpr::Variable(t) if t.eq_ignore_ascii_case("REL") => {
+                Ok(self.current_position(function, table, sorted, ctx)? + position)
+            }
+            _ => Err(format!("{} bounds must be ABS or REL", function).into()),
+        }
+    }
+
+    // Where the row context's row sits in the ordering of the table
+    fn current_position(
+        &self,
+        function: &str,
+        table: &Table,
+        sorted: &[usize],
+        ctx: &EvalContext,
+    ) -> Result<i64, DaxError> {
+        let row = ctx.row().ok_or_else(|| {
+            format!(
+                "{} needs a current row, such as inside ADDCOLUMNS or a calculated column",
+                function
+            )
+        })?;
+        let shared: Vec<(ColumnValues, ColumnValues)> = table
+            .column_names()
+            .iter()
+            .filter_map(|name| Some((self.get_column(name)?, table.get_column(name)?)))
+            .collect();
+        sorted
+            .iter()
+            .position(|&candidate| {
+                shared
+                    .iter()
+                    .all(|(current, other)| current.get(row) == other.get(candidate))
+            })
+            .map(|position| position as i64)
+            .ok_or_else(|| {
+                format!("{} could not find the current row in its table", function).into()
+            })
+    }
+}
+
+// Zero-based index of a one-based position, where negative positions count back from the end
+fn absolute_index(position: i64, len: usize) -> i64 {
+    if position < 0 {
+        len as i64 + position
+    } else {
+        position - 1
+    }
+}
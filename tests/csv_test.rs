@@ -0,0 +1,885 @@
+// tests/csv_test.rs
+use dax_rust::error::DaxError;
+use dax_rust::group::Agg;
+use dax_rust::io::{
+    aggregate_csv, read_csv, read_csv_auto, read_csv_chunks, read_csv_from, read_csv_from_with,
+    read_csv_lossy, read_csv_with, read_tsv, sniff_csv, write_csv, write_csv_with, Compression,
+    CsvOptions, CsvWarning, ErrorPolicy, LineEnding,
+};
+use dax_rust::table::DaxResult;
+use dax_rust::types::{DType, Value};
+use std::path::{Path, PathBuf};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+// Write CSV text to a temporary file, read it, and remove the file
+fn read_text(name: &str, text: &str) -> Result<dax_rust::Table, DaxError> {
+    read_text_with(name, text, &CsvOptions::default())
+}
+
+fn read_text_with(
+    name: &str,
+    text: &str,
+    options: &CsvOptions,
+) -> Result<dax_rust::Table, DaxError> {
+    let path = std::env::temp_dir().join(name);
+    std::fs::write(&path, text).unwrap();
+    let table = read_csv_with(&path, options);
+    std::fs::remove_file(&path).unwrap();
+    table
+}
+
+#[test]
+fn test_quoted_fields() {
+    let table = read_csv(&fixture("quoted.csv")).unwrap();
+    assert_eq!(table.column_names(), ["Name", "Note", "Amount", "Code"]);
+    assert_eq!(table.num_rows(), 3);
+    assert_eq!(
+        table.get_column("Name").unwrap(),
+        vec!["Smith, John".into(), "Plain".into(), "".into()]
+    );
+    assert_eq!(
+        table.get_column("Note").unwrap(),
+        vec![
+            "He said \"hi\"".into(),
+            "Line one\nLine two".into(),
+            Value::Null
+        ]
+    );
+    // A number in quotes it doesn't need is text, as write_csv writes text
+    // that looks like a number; quotes around a delimiter don't make it text
+    assert_eq!(
+        table.get_column("Amount").unwrap(),
+        vec![Value::Number(12.5), Value::Integer(3), Value::from("7")]
+    );
+    let options = CsvOptions::default().thousands_separator(',');
+    let amounts = read_csv_from_with(
+        "Amount
+\"12,500\"\n"
+            .as_bytes(),
+        &options,
+    )
+    .unwrap();
+    assert_eq!(amounts.get("Amount", 0), Some(Value::Integer(12_500)));
+    // An empty quoted field is empty text, an empty unquoted one Null
+    assert_eq!(
+        table.get_column("Code").unwrap(),
+        vec!["".into(), Value::Null, "X".into()]
+    );
+    assert_eq!(table.drop_nulls(Some(&["Code"])).num_rows(), 2);
+    assert!(matches!(
+        table.evaluate_dax("SUM([Amount])"),
+        Ok(DaxResult::Number(n)) if n == 15.5
+    ));
+}
+
+#[test]
+fn test_crlf_line_endings() {
+    let table = read_text("dax_rust_crlf_test.csv", "A,B\r\n1,\"x\r\ny\"\r\n2,z\r\n").unwrap();
+    assert_eq!(
+        table.get_column("B").unwrap(),
+        vec!["x\r\ny".into(), "z".into()]
+    );
+    assert_eq!(
+        table.get_column("A").unwrap(),
+        vec![Value::Integer(1), Value::Integer(2)]
+    );
+}
+
+#[test]
+fn test_malformed_quoting_gives_the_line() {
+    let cases = [
+        (
+            "A,B\n1,2\n3,x\"y\n",
+            "CSV line 3: a quote inside an unquoted field",
+        ),
+        (
+            "A,B\n\"1\"2,3\n",
+            "CSV line 2: '2' after the closing quote of a field",
+        ),
+        (
+            "A,B\n1,2\n3,\"open\n\nstill open\n",
+            "CSV line 3: a quoted field isn't closed",
+        ),
+    ];
+    for (text, expected) in cases {
+        match read_text("dax_rust_malformed_test.csv", text) {
+            Err(DaxError::ParseError(message)) => {
+                assert!(message.starts_with(expected), "{}", message)
+            }
+            other => panic!("Expected a parse error for {:?}, got {:?}", text, other),
+        }
+    }
+}
+
+#[test]
+fn test_european_excel_export() {
+    let options = CsvOptions::default()
+        .delimiter(';')
+        .decimal_separator(',')
+        .skip_rows(2);
+    let table = read_csv_with(&fixture("european.csv"), &options).unwrap();
+    assert_eq!(table.column_names(), ["Region", "Product", "Price", "Qty"]);
+    assert_eq!(
+        table.get_column("Price").unwrap(),
+        vec![Value::Number(2.5), Value::Integer(10), Value::Number(0.75)]
+    );
+    assert_eq!(table.get("Product", 0), Some(Value::from("Pear; green")));
+    assert!(matches!(
+        table.evaluate_dax("SUMX(Sales, [Price] * [Qty])"),
+        Ok(DaxResult::Number(n)) if n == 20.5
+    ));
+}
+
+#[test]
+fn test_header_row_and_column_options() {
+    let text = "1|Pear|2.5\n2|Plum|1\n3|Fig|4\n";
+    let path = std::env::temp_dir().join("dax_rust_csv_options_test.csv");
+    std::fs::write(&path, text).unwrap();
+
+    let options = CsvOptions::default().delimiter('|').has_header(false);
+    let table = read_csv_with(&path, &options).unwrap();
+    assert_eq!(table.column_names(), ["Column1", "Column2", "Column3"]);
+    assert_eq!(table.num_rows(), 3);
+
+    let table = read_csv_with(
+        &path,
+        &options.clone().max_rows(2).usecols(["Column3", "Column1"]),
+    )
+    .unwrap();
+    // Kept in the file's order
+    assert_eq!(table.column_names(), ["Column1", "Column3"]);
+    assert_eq!(
+        table.get_column("Column3").unwrap(),
+        vec![Value::Number(2.5), Value::Integer(1)]
+    );
+
+    let error = read_csv_with(&path, &options.clone().usecols(["Price"])).unwrap_err();
+    assert!(matches!(error, DaxError::ParseError(_)));
+    assert!(error.to_string().contains("no column Price"), "{}", error);
+    let tabs = CsvOptions {
+        delimiter: '\t',
+        ..Default::default()
+    };
+    assert_eq!(read_csv_with(&path, &tabs).unwrap().num_columns(), 1);
+    assert!(read_csv_with(&path, &CsvOptions::default().delimiter('"')).is_err());
+    assert!(read_csv_with(&path, &CsvOptions::default().decimal_separator(',')).is_err());
+    std::fs::remove_file(&path).unwrap();
+
+    // max_rows counts the records after the header
+    let path = std::env::temp_dir().join("dax_rust_csv_max_rows_test.csv");
+    std::fs::write(&path, "A,B\n1,2\n3,4\n5,6\n").unwrap();
+    let table = read_csv_with(&path, &CsvOptions::default().max_rows(2)).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(
+        table.get_column("A").unwrap(),
+        vec![Value::Integer(1), Value::Integer(3)]
+    );
+}
+
+#[test]
+fn test_repeated_column_names() {
+    match read_text("dax_rust_csv_repeated_test.csv", "id,a,b,a\n1,2,3,4\n") {
+        Err(DaxError::CsvError {
+            line,
+            column,
+            message,
+        }) => {
+            assert_eq!((line, column), (1, 4));
+            assert_eq!(message, "the column name \"a\" is already used by column 2");
+        }
+        other => panic!("Expected a CSV error, got {:?}", other),
+    }
+    let options = CsvOptions::default().skip_rows(1);
+    let error = read_text_with(
+        "dax_rust_csv_repeated_skip_test.csv",
+        "title\na,a\n1,2\n",
+        &options,
+    )
+    .unwrap_err();
+    assert!(
+        matches!(
+            error,
+            DaxError::CsvError {
+                line: 2,
+                column: 2,
+                ..
+            }
+        ),
+        "{:?}",
+        error
+    );
+    // Without a header the columns are numbered, so never repeat
+    let options = CsvOptions::default().has_header(false);
+    let table = read_text_with(
+        "dax_rust_csv_repeated_numbered_test.csv",
+        "a,a\n1,2\n",
+        &options,
+    )
+    .unwrap();
+    assert_eq!(table.column_names(), ["Column1", "Column2"]);
+}
+
+#[test]
+fn test_write_and_read_back() {
+    let table = read_csv(&fixture("quoted.csv")).unwrap();
+    let path = std::env::temp_dir().join("dax_rust_write_csv_test.csv");
+    write_csv(&table, &path).unwrap();
+    let back = read_csv(&path).unwrap();
+    assert_eq!(back, table);
+    assert_eq!(back.column_names(), table.column_names());
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        table.to_csv_string()
+    );
+
+    let options = CsvOptions::default()
+        .delimiter(';')
+        .decimal_separator(',')
+        .line_ending(LineEnding::CrLf);
+    write_csv_with(&table, &path, &options).unwrap();
+    let text = std::fs::read_to_string(&path).unwrap();
+    // Commas no longer need quotes
+    assert!(
+        text.starts_with("Name;Note;Amount;Code\r\nSmith, John;"),
+        "{}",
+        text
+    );
+    assert!(text.contains(";12,5;\"\"\r\n"), "{}", text);
+    assert_eq!(read_csv_with(&path, &options).unwrap(), table);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_csv_text_of_each_kind() {
+    let date = chrono::NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+    let mut table = dax_rust::Table::new();
+    table
+        .add_column(
+            "Value".to_string(),
+            vec![
+                Value::Number(0.1 + 0.2),
+                Value::Number(2.0),
+                Value::Integer(-7),
+                Value::currency(12.5).unwrap(),
+                Value::Boolean(true),
+                Value::from(date),
+                Value::from(date.and_hms_opt(9, 30, 0).unwrap()),
+                Value::from("say \"hi\""),
+                Value::from(""),
+                Value::Null,
+            ],
+        )
+        .unwrap();
+    table
+        .add_column("Row".to_string(), (0..10).map(Value::from).collect())
+        .unwrap();
+    assert_eq!(
+        table.to_csv_string(),
+        "Value,Row\n0.30000000000000004,0\n2,1\n-7,2\n12.50,3\ntrue,4\n2024-01-31,5\n\
+         2024-01-31 09:30:00,6\n\"say \"\"hi\"\"\",7\n\"\",8\n,9\n"
+    );
+    let back = read_text("dax_rust_csv_kinds_test.csv", &table.to_csv_string()).unwrap();
+    // Currency reads back as its amount, the rest as they were
+    let mut expected = table.clone();
+    expected.set("Value", 3, Value::Number(12.5)).unwrap();
+    assert_eq!(back, expected);
+}
+
+#[test]
+fn test_text_and_nulls_read_back_as_written() {
+    // One column, so a Null is a blank line
+    let mut table = dax_rust::Table::new();
+    let values = vec![
+        Value::from(""),
+        Value::Null,
+        Value::from(" lead"),
+        Value::from("12"),
+        Value::from("2024-01-31"),
+        Value::from("true"),
+        Value::Null,
+    ];
+    table
+        .add_column("Text".to_string(), values.clone())
+        .unwrap();
+    let text = table.to_csv_string();
+    assert_eq!(
+        text,
+        "Text\n\"\"\n\n lead\n\"12\"\n\"2024-01-31\"\n\"true\"\n\n"
+    );
+    let back = read_text("dax_rust_csv_one_column_test.csv", &text).unwrap();
+    assert_eq!(back.get_column("Text").unwrap(), values);
+    assert_eq!(back, table);
+
+    // Beside another column, the same values
+    table
+        .add_column("Row".to_string(), (0..7).map(Value::from).collect())
+        .unwrap();
+    let back = read_text("dax_rust_csv_two_columns_test.csv", &table.to_csv_string()).unwrap();
+    assert_eq!(back, table);
+}
+
+#[test]
+fn test_dtype_overrides() {
+    let text = "zip,amount\n02134,10\n90210,N/A\n,2.5\n";
+    // Guessed, the ZIP code loses its leading zero
+    let table = read_text("dax_rust_csv_dtypes_guess.csv", text).unwrap();
+    assert_eq!(table.get("zip", 0), Some(Value::Integer(2134)));
+    assert_eq!(table.get("amount", 1), Some(Value::from("N/A")));
+
+    let options =
+        CsvOptions::default().with_dtypes([("zip", DType::Text), ("amount", DType::Number)]);
+    let error = read_text_with("dax_rust_csv_dtypes_strict.csv", text, &options)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        error.contains("line 3") && error.contains("\"N/A\" in column amount"),
+        "{}",
+        error
+    );
+
+    let table = read_text_with(
+        "dax_rust_csv_dtypes_lenient.csv",
+        text,
+        &options.strict(false),
+    )
+    .unwrap();
+    assert_eq!(table.get("zip", 0), Some(Value::from("02134")));
+    assert_eq!(table.get("zip", 2), Some(Value::Null));
+    assert_eq!(table.dtype("amount"), Some(DType::Number));
+    assert_eq!(table.get("amount", 0), Some(Value::Number(10.0)));
+    assert_eq!(table.get("amount", 1), Some(Value::Null));
+
+    let options = CsvOptions::default().with_dtypes([("postcode", DType::Text)]);
+    assert!(read_text_with("dax_rust_csv_dtypes_unknown.csv", text, &options).is_err());
+}
+
+#[test]
+fn test_infer_schema_rows() {
+    let text = "id,qty\n1,5\n2,7\n3,n/a\n4,\n";
+    let options = CsvOptions::default().infer_schema_rows(2);
+    let error = read_text_with("dax_rust_csv_infer_strict.csv", text, &options)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        error.contains("line 4") && error.contains("integer"),
+        "{}",
+        error
+    );
+
+    let table = read_text_with(
+        "dax_rust_csv_infer_lenient.csv",
+        text,
+        &options.strict(false),
+    )
+    .unwrap();
+    assert_eq!(table.num_rows(), 4);
+    assert_eq!(table.dtype("qty"), Some(DType::Integer));
+    assert_eq!(table.get("qty", 2), Some(Value::Null));
+
+    // Inferring from every record agrees with no schema at all when the
+    // columns are mixed
+    let options = CsvOptions::default().infer_schema_rows(10);
+    let table = read_text_with("dax_rust_csv_infer_mixed.csv", text, &options).unwrap();
+    assert_eq!(
+        table,
+        read_text("dax_rust_csv_infer_none.csv", text).unwrap()
+    );
+}
+
+#[test]
+fn test_chunked_reading() {
+    let path = std::env::temp_dir().join("dax_rust_csv_chunks_test.csv");
+    let mut text = String::from("Region,Sales\n");
+    for i in 0..100_000 {
+        text.push_str(&format!(
+            "{},{}\n",
+            ["North", "South", "East"][i % 3],
+            i % 1000
+        ));
+    }
+    std::fs::write(&path, text).unwrap();
+
+    let full = read_csv(&path).unwrap();
+    let chunks: Vec<_> = read_csv_chunks(&path, 30_000)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        chunks
+            .iter()
+            .map(|chunk| chunk.num_rows())
+            .collect::<Vec<_>>(),
+        [30_000, 30_000, 30_000, 10_000]
+    );
+    let chunked_sum: f64 = chunks.iter().filter_map(|chunk| chunk.sum("Sales")).sum();
+    assert_eq!(Some(chunked_sum), full.sum("Sales"));
+
+    let mut appended = dax_rust::Table::new();
+    for chunk in chunks {
+        appended.append(chunk).unwrap();
+    }
+    assert_eq!(appended, full);
+
+    let aggregations = [
+        ("Sales", Agg::Sum),
+        ("Sales", Agg::Mean),
+        ("Sales", Agg::Max),
+        ("Region", Agg::Count),
+        ("Region", Agg::DistinctCount),
+        ("Region", Agg::Last),
+    ];
+    let streamed = aggregate_csv(&path, &aggregations).unwrap();
+    let whole = full.group_by(&[]).unwrap().agg(&aggregations).unwrap();
+    assert_eq!(streamed, whole);
+    assert_eq!(
+        streamed.get("Sum of Sales", 0),
+        Some(Value::Integer(49_950_000))
+    );
+    assert!(aggregate_csv(&path, &[("Profit", Agg::Sum)]).is_err());
+    std::fs::remove_file(&path).unwrap();
+
+    let mut missing = read_csv_chunks(Path::new("no/such/file.csv"), 10);
+    assert!(matches!(missing.next(), Some(Err(_))));
+    assert!(missing.next().is_none());
+}
+
+#[test]
+fn test_reading_from_readers_and_strings() {
+    let text = "Product,Qty\r\nPear,3\r\n\"Plum, red\",\r\n";
+    let table = dax_rust::Table::from_csv_str(text).unwrap();
+    assert_eq!(table.column_names(), ["Product", "Qty"]);
+    assert_eq!(table.get("Product", 1), Some(Value::from("Plum, red")));
+    assert_eq!(table.get("Qty", 1), Some(Value::Null));
+
+    let from_reader = read_csv_from(std::io::Cursor::new(text.as_bytes().to_vec())).unwrap();
+    assert_eq!(from_reader, table);
+    assert_eq!(
+        dax_rust::Table::from_csv_str(&table.to_csv_string()).unwrap(),
+        table
+    );
+    assert_eq!(dax_rust::Table::from_csv_str("").unwrap().shape(), (0, 0));
+
+    let options = CsvOptions::default().has_header(false).max_rows(1);
+    let table = read_csv_from_with("1,2\n3,4\n".as_bytes(), &options).unwrap();
+    assert_eq!(table.get("Column2", 0), Some(Value::Integer(2)));
+    assert_eq!(table.num_rows(), 1);
+}
+
+#[test]
+fn test_byte_order_mark_is_dropped() {
+    let text = "\u{feff}Product,Qty\nPear,3\n";
+    let table = dax_rust::Table::from_csv_str(text).unwrap();
+    assert_eq!(table.column_names(), ["Product", "Qty"]);
+    assert_eq!(read_text("dax_rust_csv_bom_test.csv", text).unwrap(), table);
+
+    // Only at the start of the input
+    let table = dax_rust::Table::from_csv_str("A\n\u{feff}x\n").unwrap();
+    assert_eq!(table.get("A", 0), Some(Value::from("\u{feff}x")));
+    let options = CsvOptions::default().skip_rows(1);
+    let table = read_csv_from_with("\u{feff}Exported\nA\n1\n".as_bytes(), &options).unwrap();
+    assert_eq!(table.column_names(), ["A"]);
+}
+
+#[test]
+fn test_read_tsv() {
+    let table = read_tsv(&fixture("tabs.tsv")).unwrap();
+    assert_eq!(table.column_names(), ["Region", "Product", "Qty"]);
+    assert_eq!(table.get("Product", 1), Some(Value::from("Plum, red")));
+    assert_eq!(table.get("Qty", 2), Some(Value::Integer(4)));
+}
+
+#[test]
+fn test_sniffing_each_delimiter_style() {
+    let options = sniff_csv(&fixture("quoted.csv")).unwrap();
+    assert_eq!(options, CsvOptions::default());
+
+    let options = sniff_csv(&fixture("european.csv")).unwrap();
+    assert_eq!(
+        options,
+        CsvOptions::default()
+            .delimiter(';')
+            .decimal_separator(',')
+            .skip_rows(2)
+    );
+
+    let options = sniff_csv(&fixture("tabs.tsv")).unwrap();
+    assert_eq!(options, CsvOptions::default().delimiter('\t'));
+
+    // Single quotes and no header
+    let options = sniff_csv(&fixture("pipes.csv")).unwrap();
+    assert_eq!(
+        options,
+        CsvOptions::default()
+            .delimiter('|')
+            .quote('\'')
+            .has_header(false)
+    );
+}
+
+#[test]
+fn test_read_csv_auto() {
+    let table = read_csv_auto(&fixture("european.csv")).unwrap();
+    assert_eq!(
+        table,
+        read_csv_with(
+            &fixture("european.csv"),
+            &CsvOptions::default()
+                .delimiter(';')
+                .decimal_separator(',')
+                .skip_rows(2)
+        )
+        .unwrap()
+    );
+
+    let table = read_csv_auto(&fixture("pipes.csv")).unwrap();
+    assert_eq!(table.column_names(), ["Column1", "Column2", "Column3"]);
+    assert_eq!(table.get("Column2", 0), Some(Value::from("Smith | Jones")));
+    assert_eq!(table.get("Column2", 1), Some(Value::from("O'Brien")));
+
+    // One column can't be told apart from any delimiter
+    let path = std::env::temp_dir().join("dax_rust_csv_auto_test.csv");
+    std::fs::write(&path, "Sales\n1\n2\n").unwrap();
+    let options = sniff_csv(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(options.unwrap(), CsvOptions::default());
+}
+
+#[test]
+fn test_custom_quote() {
+    let mut table = dax_rust::Table::new();
+    table
+        .add_column(
+            "Name".to_string(),
+            vec![
+                Value::from("O'Brien"),
+                Value::from("a|b"),
+                Value::from("\"x\""),
+            ],
+        )
+        .unwrap();
+    let options = CsvOptions::default().delimiter('|').quote('\'');
+    let path = std::env::temp_dir().join("dax_rust_csv_quote_test.csv");
+    write_csv_with(&table, &path, &options).unwrap();
+    let text = std::fs::read_to_string(&path).unwrap();
+    let read = read_csv_with(&path, &options).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(text, "Name\n'O''Brien'\n'a|b'\n\"x\"\n");
+    assert_eq!(read, table);
+
+    let options = CsvOptions::default().quote(',');
+    assert!(read_text_with("dax_rust_csv_quote_error_test.csv", "A\n", &options).is_err());
+}
+
+#[test]
+fn test_gzipped_csv() {
+    let rows = 100_000;
+    let mut table = dax_rust::Table::new();
+    table
+        .add_column(
+            "Region".to_string(),
+            (0..rows)
+                .map(|i| Value::from(["North", "South, East", "West"][i % 3]))
+                .collect(),
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Sales".to_string(),
+            (0..rows).map(|i| Value::Number(i as f64 / 4.0)).collect(),
+        )
+        .unwrap();
+
+    let dir = std::env::temp_dir().join("dax_rust_csv_gzip_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("sales.csv.gz");
+    let options = CsvOptions::default().compression(Compression::Gzip);
+    write_csv_with(&table, &path, &options).unwrap();
+    let compressed = std::fs::metadata(&path).unwrap().len();
+    let back = read_csv(&path).unwrap();
+    let chunks = read_csv_chunks(&path, 30_000).count();
+    let totals = aggregate_csv(&path, &[("Sales", Agg::Sum)]).unwrap();
+    let sniffed = sniff_csv(&path).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!((compressed as usize) < table.to_csv_string().len() / 2);
+    assert_eq!(back, table);
+    assert_eq!(chunks, 4);
+    assert_eq!(
+        totals.get("Sum of Sales", 0),
+        Some(Value::Number((0..rows).sum::<usize>() as f64 / 4.0))
+    );
+    assert_eq!(sniffed.delimiter, ',');
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn test_zstd_files() {
+    // Written by `zstd -19`, with its literals Huffman coded
+    let table = read_csv(&fixture("sales.csv.zst")).unwrap();
+    assert_eq!(table.num_rows(), 300);
+    assert_eq!(table.get("Product", 2), Some(Value::from("Sprocket")));
+    let totals = aggregate_csv(&fixture("sales.csv.zst"), &[("Units", Agg::Sum)]).unwrap();
+    assert_eq!(totals.get("Sum of Units", 0), Some(Value::Number(14_889.0)));
+
+    let dir = std::env::temp_dir().join("dax_rust_csv_zstd_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("sales.csv.zst");
+    let options = CsvOptions::default().compression(Compression::Zstd);
+    write_csv_with(&table, &path, &options).unwrap();
+    let compressed = std::fs::metadata(&path).unwrap().len();
+    let back = read_csv(&path).unwrap();
+    let chunks = read_csv_chunks(&path, 100).count();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!((compressed as usize) < table.to_csv_string().len() / 2);
+    assert_eq!(back, table);
+    assert_eq!(chunks, 3);
+}
+
+#[test]
+fn test_compressed_files_that_cant_be_read() {
+    let dir = std::env::temp_dir().join("dax_rust_csv_compressed_errors_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    // Named as gzip, but not
+    let fake = dir.join("plain.csv.gz");
+    std::fs::write(&fake, "A\n1\n").unwrap();
+    // Cut off after the magic number
+    let zstd = dir.join("sales.csv.zst");
+    std::fs::write(&zstd, [0x28, 0xb5, 0x2f, 0xfd]).unwrap();
+    let fake_error = read_csv(&fake).unwrap_err().to_string();
+    let zstd_error = read_csv(&zstd).unwrap_err().to_string();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(fake_error.contains("isn't gzip"), "{}", fake_error);
+    if cfg!(feature = "zstd") {
+        assert!(
+            zstd_error.contains("in the middle of a frame"),
+            "{}",
+            zstd_error
+        );
+    } else {
+        assert!(zstd_error.contains("the `zstd` feature"), "{}", zstd_error);
+    }
+}
+
+#[test]
+fn test_bad_rows_under_each_policy() {
+    let path = fixture("bad_rows.csv");
+    match read_csv(&path) {
+        Err(DaxError::CsvError {
+            line,
+            column,
+            message,
+        }) => {
+            assert_eq!((line, column), (3, 3));
+            assert_eq!(message, "expected 3 fields, found 2");
+        }
+        other => panic!("Expected a CSV error, got {:?}", other),
+    }
+    let error = read_csv(&path).unwrap_err().to_string();
+    assert_eq!(
+        error,
+        "Parse error: CSV line 3, column 3: expected 3 fields, found 2"
+    );
+
+    let warnings = vec![
+        CsvWarning {
+            line: 3,
+            column: 3,
+            message: "expected 3 fields, found 2".to_string(),
+        },
+        CsvWarning {
+            line: 4,
+            column: 4,
+            message: "expected 3 fields, found 4".to_string(),
+        },
+        CsvWarning {
+            line: 6,
+            column: 1,
+            message: "the field isn't valid UTF-8".to_string(),
+        },
+    ];
+
+    let options = CsvOptions::default().on_error(ErrorPolicy::SkipRow);
+    let (table, skipped) = read_csv_lossy(&path, &options).unwrap();
+    assert_eq!(skipped, warnings);
+    assert_eq!(
+        table.get_column("Product").unwrap(),
+        vec![
+            Value::from("Pear"),
+            Value::from("Kiwi"),
+            Value::from("Date")
+        ]
+    );
+    assert_eq!(read_csv_with(&path, &options).unwrap(), table);
+
+    let options = CsvOptions::default().on_error(ErrorPolicy::NullFill);
+    let (table, filled) = read_csv_lossy(&path, &options).unwrap();
+    assert_eq!(filled, warnings);
+    assert_eq!(table.num_rows(), 6);
+    assert_eq!(table.get("Price", 1), Some(Value::Null));
+    assert_eq!(table.get("Price", 2), Some(Value::Number(0.75)));
+    assert_eq!(table.get("Product", 4), Some(Value::Null));
+    assert_eq!(table.get("Qty", 4), Some(Value::Integer(5)));
+
+    // A file without problems has nothing to report
+    let (table, none) = read_csv_lossy(&fixture("quoted.csv"), &CsvOptions::default()).unwrap();
+    assert!(none.is_empty());
+    assert_eq!(table.num_rows(), 3);
+}
+
+#[test]
+fn test_invalid_utf8_gives_the_field() {
+    let path = std::env::temp_dir().join("dax_rust_csv_invalid_utf8_test.csv");
+    std::fs::write(&path, b"A,B\n1,\"two\nlines \xff\"\n").unwrap();
+    let error = read_csv(&path).unwrap_err();
+    let chunks: Vec<_> = read_csv_chunks(&path, 10).collect();
+    std::fs::remove_file(&path).unwrap();
+    assert!(
+        matches!(
+            error,
+            DaxError::CsvError {
+                line: 3,
+                column: 2,
+                ..
+            }
+        ),
+        "{:?}",
+        error
+    );
+    assert!(matches!(chunks[..], [Err(DaxError::CsvError { .. })]));
+}
+
+#[test]
+fn test_german_and_us_conventions() {
+    let german_text = "Datum;Konto;Betrag\n\
+        31.12.2024;Miete;(1.234,56)\n\
+        01.02.2025;Umsatz;12.500\n\
+        15.03.2025;Zinsen;0,75\n";
+    let us_text = "Datum,Konto,Betrag\n\
+        12/31/2024,Miete,\"(1,234.56)\"\n\
+        02/01/2025,Umsatz,\"12,500\"\n\
+        03/15/2025,Zinsen,0.75\n";
+    let german = CsvOptions::default()
+        .delimiter(';')
+        .decimal_separator(',')
+        .thousands_separator('.')
+        .negative_parentheses(true)
+        .date_format("%d.%m.%Y");
+    let us = CsvOptions::default()
+        .thousands_separator(',')
+        .negative_parentheses(true)
+        .date_format("%m/%d/%Y");
+
+    let table = read_csv_from_with(german_text.as_bytes(), &german).unwrap();
+    assert_eq!(read_csv_from_with(us_text.as_bytes(), &us).unwrap(), table);
+    assert_eq!(
+        table.get_column("Betrag").unwrap(),
+        vec![
+            Value::Number(-1234.56),
+            Value::Integer(12500),
+            Value::Number(0.75)
+        ]
+    );
+    assert_eq!(
+        table.get("Datum", 0),
+        Some(Value::DateTime(
+            chrono::NaiveDate::from_ymd_opt(2024, 12, 31)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+        ))
+    );
+
+    // Written back the way each was read
+    for (options, text) in [(&german, german_text), (&us, us_text)] {
+        let path = std::env::temp_dir().join("dax_rust_csv_locale_test.csv");
+        write_csv_with(&table, &path, options).unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(written, text);
+    }
+
+    // Without the locale's settings, its numbers and dates are text
+    let table = read_csv_from_with(
+        german_text.as_bytes(),
+        &CsvOptions::default().delimiter(';').decimal_separator(','),
+    )
+    .unwrap();
+    assert_eq!(table.get("Betrag", 1), Some(Value::from("12.500")));
+    assert_eq!(table.get("Datum", 0), Some(Value::from("31.12.2024")));
+
+    // Separators only group whole threes
+    let table = read_csv_from_with("A\n12.34\n1.234.567\n".as_bytes(), &german).unwrap();
+    assert_eq!(
+        table.get_column("A").unwrap(),
+        vec![Value::from("12.34"), Value::Integer(1234567)]
+    );
+    assert!(read_csv_from_with(
+        "A\n1\n".as_bytes(),
+        &german.clone().thousands_separator(',')
+    )
+    .is_err());
+    assert!(read_csv_from_with("A\n1\n".as_bytes(), &us.clone().date_format("%Q")).is_err());
+
+    // Typed columns use the same conventions
+    let typed = german.with_dtypes([("Betrag", DType::Currency), ("Datum", DType::Date)]);
+    let table = read_csv_from_with(german_text.as_bytes(), &typed).unwrap();
+    assert_eq!(table.get("Betrag", 0).unwrap().to_string(), "-1234.56");
+    assert!(matches!(table.get("Betrag", 1), Some(Value::Currency(_))));
+}
+
+#[test]
+fn test_null_values() {
+    let text = "Region,Sales\nNorth,NA\nSouth,\nEast,\"\"\nWest,n/a\n\"NA\",3\n";
+    let count_blank = |table: &dax_rust::Table, column: &str| match table
+        .evaluate_dax(&format!("COUNTBLANK([{}])", column))
+    {
+        Ok(DaxResult::Number(n)) => n,
+        other => panic!("Expected a count, got {:?}", other),
+    };
+
+    // Only the unquoted empty field is missing by default
+    let table = read_csv_from(text.as_bytes()).unwrap();
+    assert_eq!(count_blank(&table, "Sales"), 1.0);
+    assert_eq!(table.get("Sales", 0), Some(Value::from("NA")));
+    assert_eq!(table.get("Sales", 2), Some(Value::from("")));
+
+    let options = CsvOptions::default().null_values(["", "NA", "N/A"]);
+    let table = read_csv_from_with(text.as_bytes(), &options).unwrap();
+    assert_eq!(count_blank(&table, "Sales"), 3.0);
+    assert_eq!(table.get("Sales", 3), Some(Value::Null));
+    // Quoted, NA is text
+    assert_eq!(count_blank(&table, "Region"), 0.0);
+    assert_eq!(table.get("Region", 4), Some(Value::from("NA")));
+
+    // Without the empty field, it's empty text; with "", so is a quoted one
+    let options = CsvOptions::default().null_values(["NA"]);
+    let table = read_csv_from_with(text.as_bytes(), &options).unwrap();
+    assert_eq!(table.get("Sales", 1), Some(Value::from("")));
+    assert_eq!(count_blank(&table, "Sales"), 1.0);
+    let options = CsvOptions::default().null_values(["", "\"\""]);
+    let table = read_csv_from_with(text.as_bytes(), &options).unwrap();
+    assert_eq!(count_blank(&table, "Sales"), 2.0);
+
+    // Written as NULL, and read back with it
+    let options = CsvOptions::default().null_values(["NULL"]).null_as("NULL");
+    let mut table = dax_rust::Table::new();
+    table
+        .add_column(
+            "Note".to_string(),
+            vec![Value::Null, Value::from("null"), Value::from("")],
+        )
+        .unwrap();
+    let path = std::env::temp_dir().join("dax_rust_csv_null_as_test.csv");
+    write_csv_with(&table, &path, &options).unwrap();
+    let written = std::fs::read_to_string(&path).unwrap();
+    let back = read_csv_with(&path, &options).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(written, "Note\nNULL\n\"null\"\n\"\"\n");
+    assert_eq!(back, table);
+}
@@ -0,0 +1,141 @@
+// tests/datatable_test.rs
+use chrono::NaiveDate;
+use dax_macro::table;
+use dax_rust::error::DaxError;
+use dax_rust::table::{DaxResult, Table};
+use dax_rust::types::Value;
+
+fn demo_table() -> Table {
+    table! {
+        "Sales" => [100.0, 150.0, 200.0],
+        "Discount" => [0.0, 0.0, 0.0],
+        "Quantity" => [10.0, 15.0, 15.0],
+        "Product" => ["Apple", "Banana", "Orange"]
+    }
+}
+
+#[test]
+fn test_demo_table_round_trip() {
+    let table = demo_table();
+    let expr = table.to_datatable_expr();
+    assert_eq!(
+        expr,
+        r#"DATATABLE(
+    "Sales", DOUBLE,
+    "Discount", DOUBLE,
+    "Quantity", DOUBLE,
+    "Product", STRING,
+    {
+        {100, 0, 10, "Apple"},
+        {150, 0, 15, "Banana"},
+        {200, 0, 15, "Orange"}
+    }
+)"#
+    );
+    assert_eq!(Table::from_datatable_expr(&expr).unwrap(), table);
+}
+
+#[test]
+fn test_round_trip_of_each_type() {
+    let date = NaiveDate::from_ymd_opt(2024, 2, 29)
+        .unwrap()
+        .and_hms_opt(0, 0, 0)
+        .unwrap();
+    let mut table = Table::new();
+    let columns = [
+        (
+            "Id",
+            vec![Value::Integer(1), Value::Integer(2), Value::Null],
+        ),
+        (
+            "Name",
+            vec![Value::from("Say \"hi\""), Value::Null, Value::from("")],
+        ),
+        (
+            "Price",
+            vec![Value::Currency(12_500), Value::Currency(-5), Value::Null],
+        ),
+        (
+            "Active",
+            vec![Value::Boolean(true), Value::Boolean(false), Value::Null],
+        ),
+        (
+            "Day",
+            vec![Value::DateTime(date), Value::Null, Value::DateTime(date)],
+        ),
+        (
+            "Ratio",
+            vec![Value::Number(0.125), Value::Number(-3.0), Value::Null],
+        ),
+        (
+            "Mixed",
+            vec![
+                Value::Integer(7),
+                Value::from("seven"),
+                Value::Boolean(true),
+            ],
+        ),
+    ];
+    for (name, values) in columns {
+        table.add_column(name.to_string(), values).unwrap();
+    }
+
+    let expr = table.to_datatable_expr();
+    assert!(expr.contains("\"Id\", INTEGER"));
+    assert!(expr.contains("\"Price\", CURRENCY"));
+    assert!(expr.contains("\"Active\", BOOLEAN"));
+    assert!(expr.contains("\"Day\", DATETIME"));
+    assert!(expr.contains("\"Mixed\", STRING"));
+    assert!(expr.contains("{1, \"Say \"\"hi\"\"\", 1.25, TRUE, \"2024-02-29\", 0.125, \"7\"}"));
+
+    let read = Table::from_datatable_expr(&expr).unwrap();
+    for name in ["Id", "Name", "Price", "Active", "Day", "Ratio"] {
+        assert_eq!(read.get_column(name), table.get_column(name), "{}", name);
+    }
+    assert_eq!(
+        read.get_column("Mixed").unwrap(),
+        vec![Value::from("7"), Value::from("seven"), Value::from("TRUE")]
+    );
+}
+
+#[test]
+fn test_datatable_in_expressions() {
+    let table = demo_table();
+    let rows = table
+        .evaluate_dax(
+            r#"COUNTROWS(
+                FILTER(
+                    datatable("Fruit", STRING, "Kg", integer, {{"Apple", 3}, {"Pear", BLANK()}}),
+                    [Kg] > 1
+                )
+            )"#,
+        )
+        .unwrap();
+    assert!(matches!(rows, DaxResult::Number(n) if n == 1.0));
+
+    // One column's rows can be written without their own braces
+    let single = Table::from_datatable_expr(r#"DATATABLE("N", DOUBLE, {1, 2.5, 1 + 1})"#).unwrap();
+    assert_eq!(
+        single.get_column("N").unwrap(),
+        vec![Value::Number(1.0), Value::Number(2.5), Value::Number(2.0)]
+    );
+}
+
+#[test]
+fn test_datatable_errors() {
+    let message = |expr: &str| match Table::from_datatable_expr(expr) {
+        Err(e) => e.to_string(),
+        Ok(table) => panic!("{} read as {:?}", expr, table),
+    };
+    assert!(message("SUM([Sales])").contains("Expected a DATATABLE expression"));
+    assert!(message(r#"DATATABLE("A", STRING)"#).contains("name and a type"));
+    assert!(message(r#"DATATABLE("A", TEXT, {{1}})"#).contains("column A needs a type"));
+    assert!(message(r#"DATATABLE("A", STRING, "B", STRING, {{"x"}})"#)
+        .contains("row 1 has 1 values for 2 columns"));
+    assert!(message(r#"DATATABLE("A", DOUBLE, {{"x"}})"#)
+        .contains("row 1 has a value for A that isn't"));
+    assert!(matches!(
+        Table::from_datatable_expr("DATATABLE(\"A\", DOUBLE, {{1}"),
+        Err(DaxError::ParseError(_))
+    ));
+}
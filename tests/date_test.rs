@@ -0,0 +1,484 @@
+// tests/date_test.rs
+use chrono::{NaiveDate, NaiveDateTime};
+use dax_rust::io::read_csv;
+use dax_rust::table::{DaxResult, Table};
+use dax_rust::types::Value;
+
+use std::path::PathBuf;
+
+fn datetime(y: i32, m: u32, d: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(y, m, d)
+        .unwrap()
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+}
+
+fn evaluate_number(table: &Table, expr: &str) -> f64 {
+    match table.evaluate_dax(expr) {
+        Ok(DaxResult::Number(n)) => n,
+        other => panic!("Expected number result for {}, got {:?}", expr, other),
+    }
+}
+
+#[test]
+fn test_date_construction() {
+    let table = Table::new();
+    match table.evaluate_dax("DATE(2024, 2, 29)") {
+        Ok(DaxResult::DateTime(d)) => assert_eq!(d, datetime(2024, 2, 29)),
+        other => panic!("Expected date result, got {:?}", other),
+    }
+
+    // Months past December roll into the next year
+    match table.evaluate_dax("DATE(2024, 13, 1)") {
+        Ok(DaxResult::DateTime(d)) => assert_eq!(d, datetime(2025, 1, 1)),
+        other => panic!("Expected date result, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_date_arguments_out_of_range() {
+    let table = Table::new();
+    for (expr, message) in [
+        ("DATE(2024, -1E19, 1)", "DATE arguments are out of range"),
+        ("DATE(1E19, 1, 1)", "DATE arguments are out of range"),
+        ("DATE(2024, 1, -1E19)", "DATE arguments are out of range"),
+        ("TIME(1E18, 0, 0)", "TIME arguments are out of range"),
+        ("TIME(0, -1E18, 0)", "TIME arguments are out of range"),
+        (
+            "EDATE(DATE(2024, 1, 1), 1E19)",
+            "EDATE result is out of range",
+        ),
+        (
+            "EDATE(DATE(2024, 1, 1), -1E19)",
+            "EDATE result is out of range",
+        ),
+        (
+            "EOMONTH(DATE(2024, 1, 1), 1E19)",
+            "EOMONTH result is out of range",
+        ),
+        (
+            "DATEADD(DATE(2024, 1, 1), 1E19, MONTH)",
+            "DATEADD result is out of range",
+        ),
+        (
+            "DATEADD(DATE(2024, 1, 1), -1E19, YEAR)",
+            "DATEADD result is out of range",
+        ),
+    ] {
+        match table.evaluate_dax(expr) {
+            Err(e) => assert!(e.to_string().contains(message), "{}: {}", expr, e),
+            other => panic!("Expected an error for {}, got {:?}", expr, other),
+        }
+    }
+}
+
+#[test]
+fn test_date_part_extraction() {
+    let table = Table::new();
+    assert_eq!(evaluate_number(&table, "YEAR(DATE(2024, 3, 15))"), 2024.0);
+    assert_eq!(evaluate_number(&table, "MONTH(DATE(2024, 3, 15))"), 3.0);
+    assert_eq!(evaluate_number(&table, "DAY(DATE(2024, 3, 15))"), 15.0);
+    assert_eq!(evaluate_number(&table, "HOUR(TIME(13, 45, 30))"), 13.0);
+    assert_eq!(evaluate_number(&table, "MINUTE(TIME(13, 45, 30))"), 45.0);
+    assert_eq!(evaluate_number(&table, "SECOND(TIME(13, 45, 30))"), 30.0);
+}
+
+#[test]
+fn test_weekday_and_weeknum() {
+    let table = Table::new();
+    // 2024-03-15 is a Friday
+    assert_eq!(evaluate_number(&table, "WEEKDAY(DATE(2024, 3, 15))"), 6.0);
+    assert_eq!(
+        evaluate_number(&table, "WEEKDAY(DATE(2024, 3, 15), 2)"),
+        5.0
+    );
+    assert_eq!(
+        evaluate_number(&table, "WEEKDAY(DATE(2024, 3, 15), 3)"),
+        4.0
+    );
+    // 2024-01-01 is a Monday, so Sunday 2024-01-07 starts week 2
+    assert_eq!(evaluate_number(&table, "WEEKNUM(DATE(2024, 1, 6))"), 1.0);
+    assert_eq!(evaluate_number(&table, "WEEKNUM(DATE(2024, 1, 7))"), 2.0);
+    assert_eq!(evaluate_number(&table, "WEEKNUM(DATE(2024, 1, 7), 2)"), 1.0);
+}
+
+#[test]
+fn test_today_and_now() {
+    let table = Table::new();
+    match (table.evaluate_dax("TODAY()"), table.evaluate_dax("NOW()")) {
+        (Ok(DaxResult::DateTime(today)), Ok(DaxResult::DateTime(now))) => {
+            assert_eq!(today.date(), now.date());
+            assert!(today <= now);
+        }
+        other => panic!("Expected date results, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_min_max_over_dates() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "OrderDate".to_string(),
+            vec![
+                Value::DateTime(datetime(2024, 5, 1)),
+                Value::DateTime(datetime(2023, 11, 20)),
+                Value::Null,
+                Value::DateTime(datetime(2024, 1, 2)),
+            ],
+        )
+        .unwrap();
+
+    match table.evaluate_dax("MIN([OrderDate])") {
+        Ok(DaxResult::DateTime(d)) => assert_eq!(d, datetime(2023, 11, 20)),
+        other => panic!("Expected date result, got {:?}", other),
+    }
+    match table.evaluate_dax("MAX([OrderDate])") {
+        Ok(DaxResult::DateTime(d)) => assert_eq!(d, datetime(2024, 5, 1)),
+        other => panic!("Expected date result, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_csv_reads_iso_dates() {
+    let path: PathBuf = std::env::temp_dir().join("dax_rust_date_test.csv");
+    std::fs::write(&path, "OrderDate,Sales\n2024-01-05,100\n2024-02-11,200\n").unwrap();
+
+    let table = read_csv(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        table.get_column("OrderDate").unwrap(),
+        vec![
+            Value::DateTime(datetime(2024, 1, 5)),
+            Value::DateTime(datetime(2024, 2, 11)),
+        ]
+    );
+    // Whole numbers are read as integers and summed as such
+    match table.evaluate_dax("SUM([Sales])") {
+        Ok(DaxResult::Integer(i)) => assert_eq!(i, 300),
+        other => panic!("Expected integer result, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_dates_display_sort_and_group() {
+    let path: PathBuf = std::env::temp_dir().join("dax_rust_date_display_test.csv");
+    std::fs::write(
+        &path,
+        "When,Sales\n2024-02-11,1\n2023-12-31T18:05:00,2\n2024-02-11,3\n,4\n",
+    )
+    .unwrap();
+    let mut table = read_csv(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let evening = datetime(2023, 12, 31) + chrono::Duration::minutes(18 * 60 + 5);
+    assert_eq!(table.get("When", 1), Some(Value::DateTime(evening)));
+    let rendered = table.to_string();
+    assert!(rendered.contains(" 2024-02-11 "), "{}", rendered);
+    assert!(rendered.contains(" 2023-12-31 18:05:00 "), "{}", rendered);
+    // The column is as wide as its longest date, not as its header
+    let header = rendered.lines().nth(1).unwrap();
+    assert!(
+        header.starts_with(&format!(" {:<19} │", "When")),
+        "{}",
+        rendered
+    );
+
+    match table.evaluate_dax("MIN([When])") {
+        Ok(DaxResult::DateTime(d)) => assert_eq!(d, evening),
+        other => panic!("Expected date result, got {:?}", other),
+    }
+    assert_eq!(
+        table.evaluate_dax("MAX([When])").unwrap().to_string(),
+        "2024-02-11"
+    );
+    assert_eq!(
+        table
+            .evaluate_dax("DISTINCTCOUNT([When])")
+            .unwrap()
+            .to_string(),
+        "3"
+    );
+
+    // Chronologically, with the blank date last
+    table
+        .sort_by(&[("When", dax_rust::sort::SortOrder::Ascending)])
+        .unwrap();
+    assert_eq!(
+        table.get_column("Sales").unwrap(),
+        vec![
+            Value::Integer(2),
+            Value::Integer(1),
+            Value::Integer(3),
+            Value::Integer(4),
+        ]
+    );
+    let totals = table
+        .group_by(&["When"])
+        .unwrap()
+        .agg(&[("Sales", dax_rust::group::Agg::Sum)])
+        .unwrap();
+    assert_eq!(totals.num_rows(), 3);
+    assert_eq!(
+        totals.get("When", 1),
+        Some(Value::DateTime(datetime(2024, 2, 11)))
+    );
+    assert_eq!(totals.get("Sum of Sales", 1), Some(Value::Integer(4)));
+}
+
+#[test]
+fn test_datediff_intervals() {
+    let table = Table::new();
+    let cases = [
+        ("DAY", 366.0),
+        ("MONTH", 12.0),
+        ("QUARTER", 4.0),
+        ("YEAR", 1.0),
+        ("HOUR", 8784.0),
+    ];
+    for (interval, expected) in cases {
+        let expr = format!("DATEDIFF(DATE(2024, 1, 1), DATE(2025, 1, 1), {})", interval);
+        assert_eq!(evaluate_number(&table, &expr), expected, "{}", interval);
+    }
+
+    // Boundaries crossed, not elapsed time: Dec 31 to Jan 1 is one year
+    assert_eq!(
+        evaluate_number(
+            &table,
+            "DATEDIFF(DATE(2023, 12, 31), DATE(2024, 1, 1), YEAR)"
+        ),
+        1.0
+    );
+    assert_eq!(
+        evaluate_number(
+            &table,
+            "DATEDIFF(DATE(2024, 3, 1), DATE(2024, 1, 1), MONTH)"
+        ),
+        -2.0
+    );
+}
+
+#[test]
+fn test_edate_and_eomonth_clamp() {
+    let table = Table::new();
+    match table.evaluate_dax("EDATE(DATE(2024, 1, 31), 1)") {
+        Ok(DaxResult::DateTime(d)) => assert_eq!(d, datetime(2024, 2, 29)),
+        other => panic!("Expected date result, got {:?}", other),
+    }
+    match table.evaluate_dax("EDATE(DATE(2023, 1, 31), 1)") {
+        Ok(DaxResult::DateTime(d)) => assert_eq!(d, datetime(2023, 2, 28)),
+        other => panic!("Expected date result, got {:?}", other),
+    }
+    match table.evaluate_dax("EOMONTH(DATE(2024, 1, 15), 1)") {
+        Ok(DaxResult::DateTime(d)) => assert_eq!(d, datetime(2024, 2, 29)),
+        other => panic!("Expected date result, got {:?}", other),
+    }
+    match table.evaluate_dax("EOMONTH(DATE(2024, 3, 15), 0)") {
+        Ok(DaxResult::DateTime(d)) => assert_eq!(d, datetime(2024, 3, 31)),
+        other => panic!("Expected date result, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_date_calculated_columns_from_csv() {
+    let path: PathBuf = std::env::temp_dir().join("dax_rust_calculated_date_test.csv");
+    std::fs::write(
+        &path,
+        "Name,BirthDate,Start\nAnn,1990-05-20,2024-01-31\nBob,2001-12-01,2023-11-30\n",
+    )
+    .unwrap();
+
+    let mut table = read_csv(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    table
+        .add_calculated_column(
+            "Age".to_string(),
+            "DATEDIFF([BirthDate], DATE(2024, 6, 1), YEAR)",
+        )
+        .unwrap();
+    table
+        .add_calculated_column("Review".to_string(), "DATEADD([Start], 1, MONTH)")
+        .unwrap();
+
+    assert_eq!(
+        table.get_column("Age").unwrap(),
+        vec![Value::Number(34.0), Value::Number(23.0)]
+    );
+    assert_eq!(
+        table.get_column("Review").unwrap(),
+        vec![
+            Value::DateTime(datetime(2024, 2, 29)),
+            Value::DateTime(datetime(2023, 12, 30)),
+        ]
+    );
+}
+
+#[test]
+fn test_calculated_column_reports_errors() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Start".to_string(),
+            vec![Value::DateTime(datetime(2024, 1, 1))],
+        )
+        .unwrap();
+
+    assert!(table
+        .add_calculated_column("Bad".to_string(), "DATEADD([Start], 1, FORTNIGHT)")
+        .is_err());
+    assert!(table.get_column("Bad").is_none());
+}
+
+#[test]
+fn test_calendar_leap_year() {
+    let table = Table::new();
+    assert_eq!(
+        evaluate_number(
+            &table,
+            "COUNTROWS(CALENDAR(DATE(2024, 1, 1), DATE(2024, 12, 31)))"
+        ),
+        366.0
+    );
+
+    match table.evaluate_dax("CALENDAR(DATE(2024, 2, 27), DATE(2024, 3, 1))") {
+        Ok(DaxResult::Table(calendar)) => assert_eq!(
+            calendar.get_column("Date").unwrap(),
+            vec![
+                Value::DateTime(datetime(2024, 2, 27)),
+                Value::DateTime(datetime(2024, 2, 28)),
+                Value::DateTime(datetime(2024, 2, 29)),
+                Value::DateTime(datetime(2024, 3, 1)),
+            ]
+        ),
+        other => panic!("Expected table result, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_calendar_rejects_reversed_range() {
+    let table = Table::new();
+    assert!(table
+        .evaluate_dax("CALENDAR(DATE(2024, 2, 1), DATE(2024, 1, 1))")
+        .is_err());
+}
+
+#[test]
+fn test_calendarauto_spans_whole_years() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "OrderDate".to_string(),
+            vec![
+                Value::DateTime(datetime(2023, 3, 14)),
+                Value::DateTime(datetime(2023, 9, 1)),
+            ],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "ShipDate".to_string(),
+            vec![
+                Value::DateTime(datetime(2023, 3, 20)),
+                Value::DateTime(datetime(2024, 1, 10)),
+            ],
+        )
+        .unwrap();
+
+    match table.evaluate_dax("CALENDARAUTO()") {
+        Ok(DaxResult::Table(calendar)) => {
+            assert_eq!(calendar.min_date("Date"), Some(datetime(2023, 1, 1)));
+            assert_eq!(calendar.max_date("Date"), Some(datetime(2024, 12, 31)));
+        }
+        other => panic!("Expected table result, got {:?}", other),
+    }
+
+    // Fiscal years ending in June
+    match table.evaluate_dax("CALENDARAUTO(6)") {
+        Ok(DaxResult::Table(calendar)) => {
+            assert_eq!(calendar.min_date("Date"), Some(datetime(2022, 7, 1)));
+            assert_eq!(calendar.max_date("Date"), Some(datetime(2024, 6, 30)));
+        }
+        other => panic!("Expected table result, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_networkdays() {
+    let table = Table::new();
+    // Monday January 29th to Friday February 9th 2024, across the month boundary
+    let span = "DATE(2024, 1, 29), DATE(2024, 2, 9)";
+    assert_eq!(
+        evaluate_number(&table, &format!("NETWORKDAYS({})", span)),
+        10.0
+    );
+    // Only Sundays off
+    assert_eq!(
+        evaluate_number(&table, &format!("NETWORKDAYS({}, 11)", span)),
+        11.0
+    );
+    // Friday and Saturday off: two Fridays and a Saturday fall in the span
+    assert_eq!(
+        evaluate_number(&table, &format!("NETWORKDAYS({}, 7)", span)),
+        9.0
+    );
+    // The leap day is a Thursday, here taken as a holiday
+    assert_eq!(
+        evaluate_number(
+            &table,
+            "NETWORKDAYS(DATE(2024, 2, 26), DATE(2024, 3, 1), 1, CALENDAR(DATE(2024, 2, 29), DATE(2024, 2, 29)))"
+        ),
+        4.0
+    );
+    assert_eq!(
+        evaluate_number(&table, "NETWORKDAYS(DATE(2024, 2, 9), DATE(2024, 1, 29))"),
+        -10.0
+    );
+    assert!(table
+        .evaluate_dax(&format!("NETWORKDAYS({}, 9)", span))
+        .is_err());
+}
+
+#[test]
+fn test_yearfrac_bases() {
+    let table = Table::new();
+    let yearfrac = |start: &str, end: &str, basis: i64| {
+        evaluate_number(&table, &format!("YEARFRAC({}, {}, {})", start, end, basis))
+    };
+    let close = |actual: f64, expected: f64| (actual - expected).abs() < 1e-9;
+
+    // Across the end of February in a leap year
+    let (start, end) = ("DATE(2024, 1, 31)", "DATE(2024, 2, 29)");
+    assert!(close(yearfrac(start, end, 0), 29.0 / 360.0));
+    assert!(close(yearfrac(start, end, 1), 29.0 / 366.0));
+    assert!(close(yearfrac(start, end, 3), 29.0 / 365.0));
+
+    // A full year containing February 29th
+    assert!(close(
+        yearfrac("DATE(2023, 3, 15)", "DATE(2024, 3, 15)", 1),
+        1.0
+    ));
+    assert!(close(
+        yearfrac("DATE(2024, 1, 1)", "DATE(2024, 12, 31)", 1),
+        365.0 / 366.0
+    ));
+    // End of February to end of February counts as a whole 30/360 year
+    assert!(close(
+        yearfrac("DATE(2023, 2, 28)", "DATE(2024, 2, 29)", 0),
+        1.0
+    ));
+    // Several years average their lengths
+    assert!(close(
+        yearfrac("DATE(2022, 6, 30)", "DATE(2024, 6, 30)", 1),
+        731.0 / (1096.0 / 3.0)
+    ));
+    // The order of the dates doesn't matter, and the basis defaults to 30/360
+    assert!(close(
+        evaluate_number(&table, "YEARFRAC(DATE(2024, 2, 29), DATE(2024, 1, 31))"),
+        29.0 / 360.0
+    ));
+    assert!(table
+        .evaluate_dax("YEARFRAC(DATE(2024, 1, 1), DATE(2024, 2, 1), 5)")
+        .is_err());
+}
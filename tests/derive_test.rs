@@ -0,0 +1,103 @@
+// tests/derive_test.rs
+use dax_macro::{FromTable, IntoTable};
+use dax_rust::error::DaxError;
+use dax_rust::table::{DaxResult, Table};
+use dax_rust::types::Value;
+use dax_rust::{FromTable, IntoTable};
+
+#[derive(IntoTable, FromTable, Debug, Clone, PartialEq)]
+struct Sale {
+    product: String,
+    #[dax(rename = "Unit Price")]
+    price: f64,
+    quantity: i64,
+    returned: bool,
+    discount: Option<f64>,
+    region: Option<String>,
+}
+
+fn sales() -> Vec<Sale> {
+    vec![
+        Sale {
+            product: "Pear".to_string(),
+            price: 2.5,
+            quantity: 4,
+            returned: false,
+            discount: None,
+            region: Some("North".to_string()),
+        },
+        Sale {
+            product: "Plum".to_string(),
+            price: 1.0,
+            quantity: 10,
+            returned: true,
+            discount: Some(0.1),
+            region: None,
+        },
+    ]
+}
+
+#[test]
+fn test_into_table_and_back() {
+    let table = Sale::into_table(sales());
+    assert_eq!(
+        table.column_names(),
+        [
+            "product",
+            "Unit Price",
+            "quantity",
+            "returned",
+            "discount",
+            "region"
+        ]
+    );
+    assert_eq!(table.get("quantity", 1), Some(Value::Integer(10)));
+    assert_eq!(table.get("discount", 0), Some(Value::Null));
+    assert_eq!(table.get("region", 1), Some(Value::Null));
+    match table.evaluate_dax("SUMX(Sales, [Unit Price] * [quantity])") {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 20.0),
+        other => panic!("Expected a number, got {:?}", other),
+    }
+
+    assert_eq!(Sale::from_table(&table).unwrap(), sales());
+    assert!(Sale::from_table(&Sale::into_table(Vec::new()))
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_from_table_errors_name_the_field() {
+    let message = |table: &Table| match Sale::from_table(table) {
+        Err(DaxError::EvaluationError(message)) => message,
+        other => panic!("Expected an error, got {:?}", other),
+    };
+
+    let mut table = Sale::into_table(sales());
+    table.remove_column("Unit Price");
+    assert_eq!(message(&table), "Field price: column Unit Price not found");
+
+    let mut table = Sale::into_table(sales());
+    table
+        .add_column(
+            "quantity".to_string(),
+            vec![Value::Integer(1), Value::from("ten")],
+        )
+        .unwrap();
+    assert_eq!(
+        message(&table),
+        "Row 1, field quantity: Type mismatch: expected an integer, got text"
+    );
+
+    // Only an Option field can be Null
+    let mut table = Sale::into_table(sales());
+    table
+        .add_column(
+            "product".to_string(),
+            vec![Value::Null, Value::from("Plum")],
+        )
+        .unwrap();
+    assert_eq!(
+        message(&table),
+        "Row 0, field product: Type mismatch: expected text, got blank"
+    );
+}
@@ -0,0 +1,27 @@
+// tests/downstream/src/lib.rs
+
+//! A crate depending on `dax-rust` and `dax-macro` alone, with none of the
+//! macros' own imports, so their expansions must name everything through
+//! `dax_rust`.
+
+#[cfg(test)]
+mod tests {
+    use dax_macro::{dax, measures, parse_dax, table};
+    use dax_rust::table::DaxResult;
+
+    #[test]
+    fn test_macros_build_without_dax_macro_impl() {
+        let tokens = parse_dax!("SUM([Sales]) / 2");
+        assert_eq!(tokens.len(), 8);
+
+        let mut sales = table! {
+            "Sales" => [100.0, 150.0, 200.0],
+            "Quantity" => [10, 15, 25]
+        };
+        measures! { on sales: "Total Sales" => "SUM([Sales])" }.unwrap();
+        match dax!(sales, "[Total Sales] / COUNTROWS()") {
+            Ok(DaxResult::Number(n)) => assert_eq!(n, 150.0),
+            other => panic!("Expected a number, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,136 @@
+// tests/format_test.rs
+use dax_rust::format_dax;
+use dax_rust::table::{DaxResult, Table};
+use dax_rust::types::Value;
+
+fn sales() -> Table {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Region".to_string(),
+            vec!["North".into(), "South".into(), "North".into()],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Sales".to_string(),
+            [100.0, 200.0, 300.0].map(Value::Number).to_vec(),
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Cost".to_string(),
+            [60.0, 150.0, 90.0].map(Value::Number).to_vec(),
+        )
+        .unwrap();
+    table.set_name("Sales".to_string());
+    table
+}
+
+fn number(table: &Table, expr: &str) -> f64 {
+    match table.evaluate_dax(expr) {
+        Ok(DaxResult::Number(n)) => n,
+        other => panic!("Expected number result for {}, got {:?}", expr, other),
+    }
+}
+
+#[test]
+fn test_canonical_spacing_and_case() {
+    assert_eq!(
+        format_dax("sum([Sales])*1.1").unwrap(),
+        "SUM([Sales]) * 1.1"
+    );
+    assert_eq!(
+        format_dax("  Divide ( [a]+[b] ,2 ) // halved").unwrap(),
+        "DIVIDE([a] + [b], 2)"
+    );
+    assert_eq!(
+        format_dax("'Sales Data'[Amount]>=100").unwrap(),
+        "'Sales Data'[Amount] >= 100"
+    );
+    assert_eq!(
+        format_dax("\"say \"\"hi\"\"\"").unwrap(),
+        "\"say \"\"hi\"\"\""
+    );
+    assert_eq!(format_dax("{1,2 , 3}").unwrap(), "{1, 2, 3}");
+    assert!(format_dax("SUM([Sales]").is_err());
+}
+
+#[test]
+fn test_brackets_only_where_needed() {
+    assert_eq!(format_dax("((1 + 2)) * 3").unwrap(), "(1 + 2) * 3");
+    assert_eq!(format_dax("1 + (2 * 3)").unwrap(), "1 + 2 * 3");
+    assert_eq!(format_dax("1 - (2 - 3)").unwrap(), "1 - (2 - 3)");
+    assert_eq!(format_dax("(1 - 2) - 3").unwrap(), "1 - 2 - 3");
+    assert_eq!(format_dax("(-2)^2").unwrap(), "(-2) ^ 2");
+    assert_eq!(format_dax("-2^2").unwrap(), "-2 ^ 2");
+    assert_eq!(format_dax("2^-1").unwrap(), "2 ^ -1");
+    assert_eq!(format_dax("2^(3^2)").unwrap(), "2 ^ 3 ^ 2");
+    assert_eq!(format_dax("(2^3)^2").unwrap(), "(2 ^ 3) ^ 2");
+    assert_eq!(format_dax("-50%").unwrap(), "-0.5");
+    assert_eq!(format_dax("1 - -[x]").unwrap(), "1 - -[x]");
+    assert_eq!(format_dax("-(-[x])").unwrap(), "-(-[x])");
+    assert_eq!(format_dax("-([a] + [b])").unwrap(), "-([a] + [b])");
+}
+
+#[test]
+fn test_long_calls_are_broken_onto_lines() {
+    let formatted = format_dax(
+        "calculate(divide(sum([Sales]) - sum([Cost]), sum([Sales])), [Region] = \"North\", keepfilters([Sales] > 100))",
+    )
+    .unwrap();
+    assert_eq!(
+        formatted,
+        "CALCULATE(\n    DIVIDE(SUM([Sales]) - SUM([Cost]), SUM([Sales])),\n    [Region] = \"North\",\n    KEEPFILTERS([Sales] > 100)\n)"
+    );
+}
+
+#[test]
+fn test_var_and_return_start_their_own_lines() {
+    assert_eq!(
+        format_dax("var x=2 var Total = sum([Sales]) return x*Total").unwrap(),
+        "VAR x = 2\nVAR Total = SUM([Sales])\nRETURN\n    x * Total"
+    );
+    assert_eq!(
+        format_dax("calculate(var s = sum([Sales]) return s*2, [Region] = \"North\")").unwrap(),
+        "CALCULATE(\n    VAR s = SUM([Sales])\n    RETURN\n        s * 2,\n    [Region] = \"North\"\n)"
+    );
+}
+
+#[test]
+fn test_formatting_round_trips() {
+    let table = sales();
+    let expressions = [
+        "SUM([Sales])*1.1",
+        "divide(sum([Sales])-sum([Cost]),sum([Sales]))",
+        "(-2)^2 + -2^2 + 2^-1",
+        "10 - (4 - 3) - 2 / (4 * 2)",
+        "if(sum([Sales]) > 500, 1, 0)",
+        "calculate(divide(sum([Sales]) - sum([Cost]), sum([Sales])), [Region] = \"North\", keepfilters([Sales] > 100))",
+        "countrows(filter(addcolumns(Sales, \"Margin\", [Sales] - [Cost]), [Margin] >= 40))",
+        "var x = sum([Sales]) var y = x / 2 return if(y > 100, x - y, 0)",
+        "calculate(var s = sum([Sales]) return s * 2, [Region] = \"North\") + 1",
+        "1 + (var x = 2 return var y = x * 2 return x + y) * 3",
+    ];
+    for expression in expressions {
+        let formatted = format_dax(expression).unwrap();
+        assert_eq!(format_dax(&formatted).unwrap(), formatted);
+        match table.evaluate_dax(expression) {
+            Ok(DaxResult::Number(n)) => assert_eq!(number(&table, &formatted), n),
+            other => assert_eq!(
+                format!("{:?}", table.evaluate_dax(&formatted)),
+                format!("{:?}", other)
+            ),
+        }
+    }
+}
+
+#[test]
+fn test_formatting_deeply_nested_calls() {
+    // Each call is laid out once, so this finishes however deep it goes
+    let depth = dax_rust::parser::MAX_DEPTH;
+    let expression = format!("{}[Sales]{}", "abs(".repeat(depth), ")".repeat(depth));
+    let formatted = format_dax(&expression).unwrap();
+    assert!(formatted.starts_with("ABS(\n    ABS(\n"));
+    assert_eq!(format_dax(&formatted).unwrap(), formatted);
+}
@@ -0,0 +1,219 @@
+// tests/json_test.rs
+#![cfg(feature = "serde")]
+use dax_rust::error::DaxError;
+use dax_rust::io::{
+    read_json, read_ndjson, read_ndjson_chunks, read_ndjson_with, write_json, write_json_with,
+    write_ndjson, write_ndjson_with, Compression, JsonLayout, NdjsonOptions,
+};
+use dax_rust::table::Table;
+use dax_rust::types::Value;
+
+fn read_text(name: &str, text: &str) -> Result<Table, DaxError> {
+    let path = std::env::temp_dir().join(name);
+    std::fs::write(&path, text).unwrap();
+    let table = read_json(&path);
+    std::fs::remove_file(&path).unwrap();
+    table
+}
+
+fn sales() -> Table {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Product".to_string(),
+            vec!["Apple".into(), "Pear".into(), Value::Null],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Sales".to_string(),
+            vec![Value::Integer(100), Value::Number(2.5), Value::Null],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Organic".to_string(),
+            vec![Value::Boolean(true), Value::Null, Value::Boolean(false)],
+        )
+        .unwrap();
+    table
+}
+
+#[test]
+fn test_json_round_trips() {
+    let table = sales();
+    for (layout, name) in [
+        (JsonLayout::Records, "dax_rust_json_records_test.json"),
+        (JsonLayout::Columns, "dax_rust_json_columns_test.json"),
+    ] {
+        let path = std::env::temp_dir().join(name);
+        write_json_with(&table, &path, layout).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        let back = read_json(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(back.column_names(), table.column_names(), "{}", text);
+        assert_eq!(back, table, "{}", text);
+    }
+
+    let path = std::env::temp_dir().join("dax_rust_json_default_test.json");
+    write_json(&table, &path).unwrap();
+    let text = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(
+        text.starts_with(r#"[{"Product":"Apple","Sales":100,"Organic":true}"#),
+        "{}",
+        text
+    );
+}
+
+#[test]
+fn test_json_layouts_are_detected() {
+    let records = read_text(
+        "dax_rust_json_detect_records.json",
+        r#"[{"Sales": 100, "Product": "Apple"}, {"Product": "Pear", "Organic": false}]"#,
+    )
+    .unwrap();
+    assert_eq!(records.column_names(), ["Sales", "Product", "Organic"]);
+    assert_eq!(records.get("Sales", 1), Some(Value::Null));
+    assert_eq!(records.get("Organic", 0), Some(Value::Null));
+
+    let columns = read_text(
+        "dax_rust_json_detect_columns.json",
+        r#"{"Sales": [100, null], "Product": ["Apple", "Pear"], "Organic": [null, false]}"#,
+    )
+    .unwrap();
+    assert_eq!(columns, records);
+
+    assert_eq!(
+        read_text("dax_rust_json_detect_empty.json", "[]")
+            .unwrap()
+            .shape(),
+        (0, 0)
+    );
+}
+
+#[test]
+fn test_unsupported_json() {
+    let error = read_text(
+        "dax_rust_json_nested_record.json",
+        r#"[{"Product": "Apple"}, {"Product": {"Name": "Pear"}}]"#,
+    )
+    .unwrap_err()
+    .to_string();
+    assert!(
+        error.contains("[1].Product") && error.contains("object"),
+        "{}",
+        error
+    );
+
+    let error = read_text(
+        "dax_rust_json_nested_column.json",
+        r#"{"Sales": [1, [2, 3]]}"#,
+    )
+    .unwrap_err()
+    .to_string();
+    assert!(
+        error.contains("Sales[1]") && error.contains("array"),
+        "{}",
+        error
+    );
+
+    let error = read_text(
+        "dax_rust_json_big_number.json",
+        r#"[{"Id": 18446744073709551615}]"#,
+    )
+    .unwrap_err()
+    .to_string();
+    assert!(
+        error.contains("too big") && error.contains("[0].Id"),
+        "{}",
+        error
+    );
+
+    assert!(read_text(
+        "dax_rust_json_short_column.json",
+        r#"{"A": [1, 2], "B": [1]}"#
+    )
+    .is_err());
+    assert!(read_text("dax_rust_json_scalar.json", "3").is_err());
+    assert!(read_text("dax_rust_json_twice.json", r#"[{"A": 1, "A": 2}]"#).is_err());
+}
+
+#[test]
+fn test_ndjson_round_trip() {
+    let table = sales();
+    let path = std::env::temp_dir().join("dax_rust_ndjson_round_trip.ndjson");
+    write_ndjson(&table, &path).unwrap();
+    let text = std::fs::read_to_string(&path).unwrap();
+    let back = read_ndjson(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(text.lines().count(), 3);
+    assert_eq!(
+        text.lines().last(),
+        Some(r#"{"Product":null,"Sales":null,"Organic":false}"#)
+    );
+    assert_eq!(back.column_names(), table.column_names());
+    assert_eq!(back, table);
+}
+
+#[test]
+fn test_gzipped_ndjson() {
+    let table = sales();
+    let path = std::env::temp_dir().join("dax_rust_ndjson_gzip.ndjson.gz");
+    let options = NdjsonOptions::default().compression(Compression::Gzip);
+    write_ndjson_with(&table, &path, &options).unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+    let back = read_ndjson(&path).unwrap();
+    let chunks: Vec<Table> = read_ndjson_chunks(&path, 2).map(Result::unwrap).collect();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(bytes[..2], [0x1f, 0x8b]);
+    assert_eq!(back, table);
+    assert_eq!(chunks.len(), 2);
+}
+
+#[test]
+fn test_ndjson_keys_and_malformed_lines() {
+    let path = std::env::temp_dir().join("dax_rust_ndjson_malformed.ndjson");
+    let text = "{\"level\": \"info\", \"ms\": 12}\n\n{\"level\": \"warn\"\n\
+                {\"level\": \"error\", \"code\": 500}\n{\"tags\": [1]}\n{\"ms\": 3}\n";
+    std::fs::write(&path, text).unwrap();
+
+    let error = read_ndjson(&path).unwrap_err().to_string();
+    assert!(error.contains("NDJSON line 3"), "{}", error);
+
+    let options = NdjsonOptions::default().skip_malformed(true);
+    let table = read_ndjson_with(&path, &options).unwrap();
+    assert_eq!(table.column_names(), ["level", "ms", "code"]);
+    assert_eq!(table.num_rows(), 3);
+    assert_eq!(table.get("code", 0), Some(Value::Null));
+    assert_eq!(table.get("code", 1), Some(Value::Integer(500)));
+    assert_eq!(table.get("level", 2), Some(Value::Null));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_ndjson_chunks() {
+    let path = std::env::temp_dir().join("dax_rust_ndjson_chunks.ndjson");
+    let mut text = String::new();
+    for i in 0..1000 {
+        match i {
+            700 => text.push_str(&format!("{{\"n\": {}, \"late\": true}}\n", i)),
+            _ => text.push_str(&format!("{{\"n\": {}}}\n", i)),
+        }
+    }
+    std::fs::write(&path, text).unwrap();
+
+    let chunks: Vec<Table> = read_ndjson_chunks(&path, 300)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        chunks.iter().map(Table::num_rows).collect::<Vec<_>>(),
+        [300, 300, 300, 100]
+    );
+    assert_eq!(chunks[1].column_names(), ["n"]);
+    assert_eq!(chunks[3].column_names(), ["n", "late"]);
+    let whole = read_ndjson(&path).unwrap();
+    assert_eq!(Table::concat(chunks).unwrap(), whole);
+    assert_eq!(whole.sum("n"), Some(499_500.0));
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,202 @@
+// tests/parquet_test.rs
+#![cfg(feature = "parquet")]
+use chrono::NaiveDate;
+use dax_rust::io::{read_parquet, write_parquet};
+use dax_rust::table::Table;
+use dax_rust::types::{DType, Value};
+
+fn round_trip(table: &Table, name: &str) -> Table {
+    let path = std::env::temp_dir().join(name);
+    write_parquet(table, &path).unwrap();
+    let back = read_parquet(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    back
+}
+
+#[test]
+fn test_parquet_round_trip_of_every_kind() {
+    let date = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+    let columns = [
+        (
+            "Number",
+            vec![Value::Number(1.5), Value::Null, Value::Number(-0.1)],
+        ),
+        (
+            "Integer",
+            vec![Value::Integer(i64::MIN), Value::Integer(7), Value::Null],
+        ),
+        (
+            "Currency",
+            vec![
+                Value::Null,
+                Value::currency(12.3456).unwrap(),
+                Value::currency(-1.0).unwrap(),
+            ],
+        ),
+        ("Text", vec!["Pear".into(), "".into(), Value::Null]),
+        (
+            "Boolean",
+            vec![Value::Boolean(true), Value::Null, Value::Boolean(false)],
+        ),
+        (
+            "Date",
+            vec![
+                Value::from(date),
+                Value::Null,
+                Value::from(NaiveDate::from_ymd_opt(1900, 1, 1).unwrap()),
+            ],
+        ),
+        (
+            "DateTime",
+            vec![
+                Value::Null,
+                Value::from(date.and_hms_micro_opt(9, 30, 0, 250).unwrap()),
+                Value::from(date),
+            ],
+        ),
+        ("Null", vec![Value::Null; 3]),
+    ];
+    let mut table = Table::new();
+    for (name, values) in columns {
+        table.add_column(name.to_string(), values).unwrap();
+    }
+
+    let back = round_trip(&table, "dax_rust_parquet_kinds.parquet");
+    assert_eq!(back.column_names(), table.column_names());
+    assert_eq!(back, table);
+    for name in ["Integer", "Currency", "Boolean", "Date"] {
+        assert_eq!(back.dtype(name), table.dtype(name), "{}", name);
+    }
+
+    assert_eq!(
+        round_trip(&Table::new(), "dax_rust_parquet_empty.parquet").shape(),
+        (0, 0)
+    );
+}
+
+#[test]
+fn test_parquet_row_groups() {
+    let mut table = Table::new();
+    let rows = 1_100_000;
+    table
+        .add_column(
+            "N".to_string(),
+            (0..rows)
+                .map(|i| {
+                    if i % 7 == 0 {
+                        Value::Null
+                    } else {
+                        Value::Integer(i)
+                    }
+                })
+                .collect(),
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Even".to_string(),
+            (0..rows).map(|i| Value::Boolean(i % 2 == 0)).collect(),
+        )
+        .unwrap();
+    let back = round_trip(&table, "dax_rust_parquet_row_groups.parquet");
+    assert_eq!(back.shape(), (rows as usize, 2));
+    assert_eq!(back, table);
+}
+
+#[test]
+fn test_parquet_errors() {
+    let mut table = Table::new();
+    table
+        .add_column("Mixed".to_string(), vec!["a".into(), Value::Boolean(true)])
+        .unwrap();
+    let path = std::env::temp_dir().join("dax_rust_parquet_mixed.parquet");
+    let error = write_parquet(&table, &path).unwrap_err().to_string();
+    assert!(error.contains("Column Mixed"), "{}", error);
+
+    // Integers and numbers together are written as numbers
+    let mut table = Table::new();
+    table
+        .add_column("N".to_string(), vec![Value::Integer(1), Value::Number(2.5)])
+        .unwrap();
+    let back = round_trip(&table, "dax_rust_parquet_numbers.parquet");
+    assert_eq!(back.dtype("N"), Some(DType::Number));
+    assert_eq!(back.get("N", 0), Some(Value::Number(1.0)));
+
+    let path = std::env::temp_dir().join("dax_rust_parquet_not.parquet");
+    std::fs::write(&path, "Product,Qty\nPear,3\n").unwrap();
+    let error = read_parquet(&path).unwrap_err().to_string();
+    std::fs::remove_file(&path).unwrap();
+    assert!(error.contains("PAR1"), "{}", error);
+}
+
+#[test]
+fn test_damaged_parquet_files() {
+    let mut table = Table::new();
+    table
+        .add_column("Qty".to_string(), vec![Value::Integer(3), Value::Null])
+        .unwrap();
+    table
+        .add_column("Product".to_string(), vec!["Pear".into(), "Fig".into()])
+        .unwrap();
+    let path = std::env::temp_dir().join("dax_rust_parquet_damaged.parquet");
+    write_parquet(&table, &path).unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+
+    // Cut short anywhere, the file is an error
+    for len in 0..bytes.len() {
+        std::fs::write(&path, &bytes[..len]).unwrap();
+        assert!(read_parquet(&path).is_err(), "cut to {} bytes", len);
+    }
+    // Any byte changed, lengths and counts included, the file reads or
+    // errs without panicking or reserving what the file doesn't hold
+    for i in 0..bytes.len() {
+        for change in [0x01, 0x80, 0xff] {
+            let mut damaged = bytes.clone();
+            damaged[i] ^= change;
+            std::fs::write(&path, &damaged).unwrap();
+            let _ = read_parquet(&path);
+        }
+    }
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_oversized_lengths() {
+    let mut table = Table::new();
+    table
+        .add_column("Qty".to_string(), vec![Value::Integer(3)])
+        .unwrap();
+    let path = std::env::temp_dir().join("dax_rust_parquet_lengths.parquet");
+    write_parquet(&table, &path).unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+    let read = |bytes: &[u8]| {
+        std::fs::write(&path, bytes).unwrap();
+        read_parquet(&path).unwrap_err().to_string()
+    };
+
+    // A footer four gigabytes long, in a file of a hundred bytes
+    let mut footer = bytes.clone();
+    let at = footer.len() - 8;
+    footer[at..at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+    let error = read(&footer);
+    assert!(
+        error.contains("metadata is longer than the file"),
+        "{}",
+        error
+    );
+
+    // The page header comes first: its type, its size, then its size
+    // compressed, each a field header and a zigzag varint
+    assert_eq!(bytes[4..10], [0x15, 0x00, 0x15, 0x1c, 0x15, 0x1c]);
+    for size in [0x7e, 0x01] {
+        let mut page = bytes.clone();
+        page[9] = size;
+        let error = read(&page);
+        assert!(
+            error.contains("a page runs past the end of its column"),
+            "{}",
+            error
+        );
+    }
+    std::fs::remove_file(&path).unwrap();
+}
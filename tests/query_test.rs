@@ -0,0 +1,165 @@
+// tests/query_test.rs
+use dax_rust::error::DaxError;
+use dax_rust::table::Table;
+use dax_rust::types::Value;
+
+fn sales() -> Table {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Product".to_string(),
+            ["Pear", "Apple", "Plum", "Apple", "Pear"]
+                .map(Value::from)
+                .to_vec(),
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Sales".to_string(),
+            [10.0, 20.0, 5.0, 30.0, 15.0].map(Value::Number).to_vec(),
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Cost".to_string(),
+            [4.0, 12.0, 1.0, 18.0, 5.0].map(Value::Number).to_vec(),
+        )
+        .unwrap();
+    table.set_name("Sales".to_string());
+    table
+}
+
+fn column(table: &Table, name: &str) -> Vec<Value> {
+    table
+        .get_column(name)
+        .unwrap_or_else(|| panic!("Expected a {} column", name))
+        .to_vec()
+}
+
+#[test]
+fn test_define_and_evaluate() {
+    let result = sales()
+        .execute_query(
+            "DEFINE
+                MEASURE Sales[Total] = SUM([Sales])
+                MEASURE Sales[Margin] = DIVIDE([Total] - SUM([Cost]), [Total])
+            EVALUATE
+                SUMMARIZE(Sales, [Product], \"T\", [Total], \"M\", [Margin])
+            ORDER BY [T] DESC",
+        )
+        .unwrap();
+    assert_eq!(
+        column(&result, "Product"),
+        ["Apple", "Pear", "Plum"].map(Value::from).to_vec()
+    );
+    assert_eq!(
+        column(&result, "T"),
+        [50.0, 25.0, 5.0].map(Value::Number).to_vec()
+    );
+    assert_eq!(
+        column(&result, "M"),
+        [0.4, 0.64, 0.8].map(Value::Number).to_vec()
+    );
+}
+
+#[test]
+fn test_variables_and_ordering() {
+    let table = sales();
+    let result = table
+        .execute_query(
+            "define
+                var Threshold = 10
+                var Big = FILTER(Sales, [Sales] >= Threshold)
+            evaluate Big
+            order by [Product], [Sales] desc",
+        )
+        .unwrap();
+    assert_eq!(
+        column(&result, "Product"),
+        ["Apple", "Apple", "Pear", "Pear"].map(Value::from).to_vec()
+    );
+    assert_eq!(
+        column(&result, "Sales"),
+        [30.0, 20.0, 15.0, 10.0].map(Value::Number).to_vec()
+    );
+
+    // Without DEFINE, and with the table's own measures
+    let mut table = sales();
+    table.define_measure("Total", "SUM([Sales])").unwrap();
+    let result = table
+        .execute_query("EVALUATE SUMMARIZECOLUMNS([Product], \"T\", [Total]) ORDER BY [Product]")
+        .unwrap();
+    assert_eq!(
+        column(&result, "T"),
+        [50.0, 25.0, 5.0].map(Value::Number).to_vec()
+    );
+    // Query measures don't stay on the table
+    assert!(table
+        .execute_query("DEFINE MEASURE Sales[Extra] = 1 EVALUATE {[Extra]}")
+        .is_ok());
+    assert_eq!(table.get_measure("Extra"), None);
+}
+
+#[test]
+fn test_var_blocks_inside_definitions() {
+    // The VARs of a block belong to its expression rather than to DEFINE
+    let result = sales()
+        .execute_query(
+            "DEFINE
+                MEASURE Sales[Margin] =
+                    VAR Revenue = SUM([Sales])
+                    VAR Cost = SUM([Cost])
+                    RETURN DIVIDE(Revenue - Cost, Revenue)
+            EVALUATE SUMMARIZECOLUMNS([Product], \"M\", [Margin])
+            ORDER BY [Product]",
+        )
+        .unwrap();
+    assert_eq!(
+        column(&result, "M"),
+        [0.4, 0.64, 0.8].map(Value::Number).to_vec()
+    );
+
+    let result = sales()
+        .execute_query(
+            "DEFINE
+                VAR Limit = VAR Half = 10 RETURN Half * 2
+                VAR Step = 5
+            EVALUATE
+                VAR Big = FILTER(Sales, [Sales] >= Limit + Step)
+                RETURN Big",
+        )
+        .unwrap();
+    assert_eq!(column(&result, "Sales"), vec![Value::Number(30.0)]);
+}
+
+#[test]
+fn test_malformed_queries() {
+    let table = sales();
+    for (query, message) in [
+        ("SUM([Sales])", "must start with DEFINE or EVALUATE"),
+        ("DEFINE MEASURE Sales[T] = 1", "needs an EVALUATE"),
+        (
+            "MEASURE Sales[T] = 1 EVALUATE Sales",
+            "must come inside a DEFINE block",
+        ),
+        ("DEFINE MEASURE Sales[T] 1 EVALUATE Sales", "Expected '='"),
+        (
+            "DEFINE VAR = 1 EVALUATE Sales",
+            "VAR must be followed by a name",
+        ),
+        ("EVALUATE", "EVALUATE is missing its expression"),
+        ("EVALUATE Sales EVALUATE Sales", "only EVALUATE one"),
+        (
+            "EVALUATE Sales DEFINE VAR x = 1",
+            "DEFINE must start the query",
+        ),
+        ("DEFINE EVALUATE Sales ORDER BY", "ORDER BY is missing"),
+        ("EVALUATE FILTER(Sales, [Sales] > 1", "Expected ')'"),
+    ] {
+        match table.execute_query(query) {
+            Err(DaxError::ParseError(e)) => assert!(e.contains(message), "{}: {}", query, e),
+            other => panic!("Expected a parse error for {}, got {:?}", query, other),
+        }
+    }
+    assert!(table.execute_query("EVALUATE SUM([Sales])").is_err());
+}
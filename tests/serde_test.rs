@@ -0,0 +1,212 @@
+// tests/serde_test.rs
+#![cfg(feature = "serde")]
+use chrono::NaiveDate;
+use dax_rust::serialize::NanPolicy;
+use dax_rust::table::{DaxResult, Table};
+use dax_rust::types::Value;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+
+fn every_kind() -> Table {
+    let date = NaiveDate::from_ymd_opt(2024, 1, 31)
+        .unwrap()
+        .and_hms_opt(9, 30, 0)
+        .unwrap();
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Value".to_string(),
+            vec![
+                Value::Number(1.5),
+                Value::Integer(-7),
+                Value::currency(12.3456).unwrap(),
+                Value::from("North"),
+                Value::Boolean(true),
+                Value::DateTime(date),
+                Value::Null,
+            ],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Sales".to_string(),
+            [3.0, 2.0, 1.0, 0.5, 0.0, -1.0, 2.5]
+                .map(Value::Number)
+                .to_vec(),
+        )
+        .unwrap();
+    table
+}
+
+#[test]
+fn test_values_serialize_naturally() {
+    let json = serde_json::to_value(every_kind()).unwrap();
+    assert_eq!(
+        json["columns"]["Value"],
+        json!([
+            1.5,
+            -7,
+            {"Currency": 12.3456},
+            "North",
+            true,
+            {"DateTime": "2024-01-31T09:30:00"},
+            null
+        ])
+    );
+    // Columns keep the table's order
+    let text = serde_json::to_string(&every_kind()).unwrap();
+    assert!(text.find("\"Value\"").unwrap() < text.find("\"Sales\"").unwrap());
+}
+
+#[test]
+fn test_round_trips() {
+    let table = every_kind();
+    let text = serde_json::to_string(&table).unwrap();
+    let back: Table = serde_json::from_str(&text).unwrap();
+    assert_eq!(back.column_names(), table.column_names());
+    for name in table.column_names() {
+        assert_eq!(back.get_column(name), table.get_column(name), "{}", name);
+    }
+
+    for value in table.get_column("Value").unwrap() {
+        let text = serde_json::to_string(&value).unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&text).unwrap(), value);
+    }
+
+    let result: DaxResult =
+        serde_json::from_str(&serde_json::to_string(&DaxResult::Table(table)).unwrap()).unwrap();
+    assert!(matches!(result, DaxResult::Table(t) if t.shape() == (7, 2)));
+    let result: DaxResult = serde_json::from_str("null").unwrap();
+    assert!(matches!(result, DaxResult::Blank));
+    let result: DaxResult = serde_json::from_str(r#"{"Currency": 2.5}"#).unwrap();
+    assert!(matches!(result, DaxResult::Currency(25_000)));
+
+    assert!(serde_json::from_str::<Table>(r#"{"columns": {"A": [1, 2], "B": [1]}}"#).is_err());
+    assert!(serde_json::from_str::<Table>(r#"{"columns": {"A": [1], "A": [2]}}"#).is_err());
+    assert!(serde_json::from_str::<Value>(r#"{"Euro": 2}"#).is_err());
+}
+
+#[test]
+fn test_nan_policy() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "N".to_string(),
+            vec![
+                Value::Number(f64::NAN),
+                Value::Number(f64::INFINITY),
+                Value::Number(1.0),
+            ],
+        )
+        .unwrap();
+
+    let as_null = serde_json::to_value(&table).unwrap();
+    assert_eq!(as_null, json!({"columns": {"N": [null, null, 1.0]}}));
+    let back: Table = serde_json::from_value(as_null).unwrap();
+    assert_eq!(back.get("N", 0), Some(Value::Null));
+
+    let as_string = serde_json::to_value(table.with_nan_policy(NanPolicy::String)).unwrap();
+    assert_eq!(as_string, json!({"columns": {"N": ["NaN", "inf", 1.0]}}));
+    let back: Table = serde_json::from_value(as_string).unwrap();
+    assert_eq!(back.get("N", 0), Some(Value::from("NaN")));
+
+    let result = DaxResult::Number(f64::NEG_INFINITY);
+    assert_eq!(
+        serde_json::to_value(result.with_nan_policy(NanPolicy::String)).unwrap(),
+        json!("-inf")
+    );
+}
+
+#[test]
+fn test_to_records() {
+    let records = every_kind().to_records();
+    assert_eq!(records.len(), 7);
+    assert_eq!(records[0], json!({"Value": 1.5, "Sales": 3.0}));
+    assert_eq!(records[6], json!({"Value": null, "Sales": 2.5}));
+    assert_eq!(records[2]["Value"], json!({"Currency": 12.3456}));
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Channel {
+    Online,
+    Store,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Sale {
+    product: String,
+    amount: f64,
+    qty: u32,
+    discount: Option<f64>,
+    channel: Channel,
+}
+
+#[test]
+fn test_tables_from_and_to_structs() {
+    let sales = vec![
+        Sale {
+            product: "Pear".to_string(),
+            amount: 2.5,
+            qty: 3,
+            discount: None,
+            channel: Channel::Online,
+        },
+        Sale {
+            product: "Plum".to_string(),
+            amount: 1.0,
+            qty: 10,
+            discount: Some(0.1),
+            channel: Channel::Store,
+        },
+    ];
+    let table = Table::from_serializable(&sales).unwrap();
+    assert_eq!(
+        table.column_names(),
+        ["product", "amount", "qty", "discount", "channel"]
+    );
+    assert_eq!(table.get("qty", 1), Some(Value::Integer(10)));
+    assert_eq!(table.get("discount", 0), Some(Value::Null));
+    assert_eq!(table.get("channel", 1), Some(Value::from("Store")));
+    assert!(matches!(
+        table.evaluate_dax("SUM([qty])"),
+        Ok(DaxResult::Integer(13))
+    ));
+    assert_eq!(table.to_deserializable::<Sale>().unwrap(), sales);
+
+    // Whole numbers read into integer fields, whatever produced them
+    let mut table = table;
+    table.set("qty", 0, Value::Number(4.0)).unwrap();
+    assert_eq!(table.to_deserializable::<Sale>().unwrap()[0].qty, 4);
+    table.set("qty", 0, Value::Number(4.5)).unwrap();
+    let error = table.to_deserializable::<Sale>().unwrap_err().to_string();
+    assert!(
+        error.contains("Row 0") && error.contains("qty"),
+        "{}",
+        error
+    );
+
+    let rows: Vec<HashMap<String, i64>> = vec![HashMap::from([("A".to_string(), 1)])];
+    assert_eq!(Table::from_serializable(&rows).unwrap().shape(), (1, 1));
+    assert_eq!(
+        Table::from_serializable::<Sale>(&[]).unwrap().shape(),
+        (0, 0)
+    );
+}
+
+#[test]
+fn test_nested_fields_are_rejected() {
+    #[derive(Serialize)]
+    struct Order {
+        id: u32,
+        lines: Vec<f64>,
+    }
+    let error = Table::from_serializable(&[Order {
+        id: 1,
+        lines: vec![1.0],
+    }])
+    .unwrap_err()
+    .to_string();
+    assert!(error.contains("field lines is a sequence"), "{}", error);
+    assert!(Table::from_serializable(&[1, 2]).is_err());
+}
@@ -0,0 +1,210 @@
+// tests/sqlite_test.rs
+#![cfg(feature = "sqlite")]
+use dax_rust::io::{read_sqlite, read_sqlite_params};
+use dax_rust::table::DaxResult;
+use dax_rust::types::Value;
+use std::path::{Path, PathBuf};
+
+// A database with 512-byte pages: products, with a column added after its
+// first five rows; a thousand orders, four with notes long enough to spill
+// onto overflow pages; a view, a table of blobs and a WITHOUT ROWID table
+fn shop() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join("shop.db")
+}
+
+fn error(query: &str) -> String {
+    read_sqlite(&shop(), query).unwrap_err().to_string()
+}
+
+#[test]
+fn test_read_table() {
+    let table = read_sqlite(&shop(), "SELECT * FROM products").unwrap();
+    assert_eq!(
+        table.column_names(),
+        ["id", "name", "price", "stock", "category", "discontinued"]
+    );
+    assert_eq!(table.num_rows(), 6);
+    assert_eq!(
+        table.get_column("name").unwrap(),
+        vec![
+            "Apple".into(),
+            "Pear".into(),
+            "Carrot".into(),
+            "Bread".into(),
+            "Cheese".into(),
+            "Quince".into()
+        ]
+    );
+    assert_eq!(table.get("id", 0), Some(Value::Integer(1)));
+    assert_eq!(table.get("price", 0), Some(Value::Number(1.25)));
+    // A whole REAL is still a number
+    assert_eq!(table.get("price", 4), Some(Value::Number(7.0)));
+    assert_eq!(table.get("stock", 1), Some(Value::Null));
+    assert_eq!(table.get("category", 3), Some(Value::Null));
+    // Rows from before the column was added take its default
+    assert_eq!(table.get("discontinued", 0), Some(Value::Integer(0)));
+    assert_eq!(table.get("discontinued", 5), Some(Value::Integer(1)));
+
+    assert!(matches!(
+        table.evaluate_dax("SUM([stock])"),
+        Ok(DaxResult::Integer(157))
+    ));
+
+    // Enough rows for interior pages, and notes on overflow pages
+    let orders = read_sqlite(&shop(), "SELECT * FROM orders").unwrap();
+    assert_eq!(orders.num_rows(), 1000);
+    assert!(matches!(
+        orders.evaluate_dax("SUM([quantity])"),
+        Ok(DaxResult::Integer(4003))
+    ));
+    let notes = read_sqlite(
+        &shop(),
+        "SELECT id, length(note) AS chars, note FROM orders WHERE note IS NOT NULL",
+    )
+    .unwrap();
+    assert_eq!(notes.num_rows(), 4);
+    assert_eq!(notes.get("chars", 3), Some(Value::Integer(1500)));
+    assert_eq!(notes.get("note", 0), Some(Value::from("x".repeat(1500))));
+}
+
+#[test]
+fn test_select() {
+    let table = read_sqlite(
+        &shop(),
+        "SELECT name, price * 2 AS double, upper(category), rowid \
+         FROM products p \
+         WHERE p.category IN ('Fruit', 'Dairy') AND price BETWEEN 1 AND 5 \
+         ORDER BY price DESC LIMIT 2 OFFSET 1",
+    )
+    .unwrap();
+    assert_eq!(
+        table.column_names(),
+        ["name", "double", "upper(category)", "id"]
+    );
+    assert_eq!(
+        table.get_column("name").unwrap(),
+        vec!["Pear".into(), "Apple".into()]
+    );
+    assert_eq!(table.get("double", 0), Some(Value::Number(5.0)));
+    assert_eq!(table.get("upper(category)", 1), Some(Value::from("FRUIT")));
+    assert_eq!(table.get("id", 1), Some(Value::Integer(1)));
+
+    let categories = read_sqlite(
+        &shop(),
+        "SELECT DISTINCT category FROM products ORDER BY 1 NULLS LAST",
+    )
+    .unwrap();
+    assert_eq!(
+        categories.get_column("category").unwrap(),
+        vec![
+            "Dairy".into(),
+            "Fruit".into(),
+            "Vegetable".into(),
+            Value::Null
+        ]
+    );
+
+    // No table at all
+    let table = read_sqlite(&shop(), "SELECT 1 + 1 AS two, 'a' || 'b'").unwrap();
+    assert_eq!(table.get("two", 0), Some(Value::Integer(2)));
+    assert_eq!(table.get("'a' || 'b'", 0), Some(Value::from("ab")));
+}
+
+#[test]
+fn test_parameters() {
+    let query = "SELECT name FROM products WHERE price > ? AND category = ? ORDER BY name";
+    let table = read_sqlite_params(&shop(), query, &[Value::Number(2.0), "Fruit".into()]).unwrap();
+    assert_eq!(
+        table.get_column("name").unwrap(),
+        vec!["Pear".into(), "Quince".into()]
+    );
+
+    // A name used twice is one parameter
+    let query = "SELECT name FROM products WHERE stock > :least OR id < :least";
+    let table = read_sqlite_params(&shop(), query, &[Value::Integer(50)]).unwrap();
+    assert_eq!(table.num_rows(), 6);
+
+    // Text that looks like SQL is only text
+    let query = "SELECT name FROM products WHERE name = ?";
+    for injection in ["' OR '1'='1", "Pear' --", "Pear'; DROP TABLE products; --"] {
+        let table = read_sqlite_params(&shop(), query, &[injection.into()]).unwrap();
+        assert_eq!(table.num_rows(), 0, "{}", injection);
+    }
+    let table = read_sqlite_params(&shop(), query, &["Pear".into()]).unwrap();
+    assert_eq!(table.num_rows(), 1);
+
+    let error = read_sqlite_params(&shop(), query, &[])
+        .unwrap_err()
+        .to_string();
+    assert!(
+        error.contains("takes 1 parameters, but 0 were given"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn test_what_cant_be_read() {
+    for (query, expected) in [
+        ("SELECT * FROM suppliers", "no such table: suppliers"),
+        ("SELECT nosuch FROM products", "no such column: nosuch"),
+        ("SELECT * FROM fruit", "is a view"),
+        ("SELECT * FROM images", "holds a BLOB"),
+        ("SELECT * FROM codes", "WITHOUT ROWID"),
+        (
+            "SELECT id AS x, name AS x FROM products",
+            "tell them apart with AS",
+        ),
+        (
+            "SELECT count(*) FROM orders",
+            "aggregate the table once it's read",
+        ),
+        (
+            "SELECT * FROM orders JOIN products",
+            "joins aren't supported",
+        ),
+        (
+            "SELECT product_id FROM orders GROUP BY product_id",
+            "GROUP BY",
+        ),
+        (
+            "SELECT * FROM products; DELETE FROM products",
+            "goes on after its end",
+        ),
+        ("DELETE FROM products", "expected SELECT"),
+    ] {
+        let error = error(query);
+        assert!(error.contains(expected), "{}: {}", query, error);
+    }
+
+    let path = std::env::temp_dir().join("dax_rust_not_a_database.db");
+    std::fs::write(&path, "id,name\n").unwrap();
+    let error = read_sqlite(&path, "SELECT 1").unwrap_err().to_string();
+    std::fs::remove_file(&path).unwrap();
+    assert!(error.contains("isn't a SQLite database"), "{}", error);
+}
+
+#[test]
+fn test_damaged_databases() {
+    let bytes = std::fs::read(shop()).unwrap();
+    let path = std::env::temp_dir().join("dax_rust_damaged.db");
+    let query = "SELECT * FROM orders WHERE note IS NOT NULL";
+    for len in (0..bytes.len()).step_by(97) {
+        std::fs::write(&path, &bytes[..len]).unwrap();
+        let _ = read_sqlite(&path, query);
+    }
+    // Any byte changed, in the header, the schema, a page's cells or an
+    // overflow chain, the database reads or errs without panicking: each of
+    // the first page's and a spread of the rest
+    let spread = (0..512).chain((512..bytes.len()).step_by(11));
+    for (i, change) in spread.zip([0x01, 0x80, 0xff].into_iter().cycle()) {
+        let mut damaged = bytes.clone();
+        damaged[i] ^= change;
+        std::fs::write(&path, &damaged).unwrap();
+        let _ = read_sqlite(&path, query);
+    }
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,70 @@
+// tests/stats_test.rs
+use dax_rust::table::{DaxResult, Table};
+
+fn evaluate_number(expr: &str) -> f64 {
+    match Table::new().evaluate_dax(expr) {
+        Ok(DaxResult::Number(n)) => n,
+        other => panic!("Expected number result for {}, got {:?}", expr, other),
+    }
+}
+
+// Compare against reference values to 6 decimal places
+fn assert_dax(expr: &str, expected: f64) {
+    let actual = evaluate_number(expr);
+    assert!(
+        (actual - expected).abs() < 1e-6,
+        "{} gave {}, expected {}",
+        expr,
+        actual,
+        expected
+    );
+}
+
+#[test]
+fn test_norm_dist() {
+    assert_dax("NORM.DIST(42, 40, 1.5, TRUE())", 0.908788780);
+    assert_dax("NORM.DIST(42, 40, 1.5, FALSE())", 0.109340050);
+    assert_dax("NORM.S.DIST(1.333333, TRUE)", 0.908788726);
+    assert_dax("NORM.S.DIST(1, FALSE)", 0.241970725);
+    assert_dax("NORM.S.DIST(-2.5, TRUE)", 0.006209665);
+}
+
+#[test]
+fn test_norm_inv() {
+    assert_dax("NORM.INV(0.908789, 40, 1.5)", 42.000002010);
+    assert_dax("NORM.S.INV(0.975)", 1.959963985);
+    assert_dax("NORM.S.INV(0.001)", -3.090232306);
+}
+
+#[test]
+fn test_t_dist() {
+    assert_dax("T.DIST(60, 1, TRUE)", 0.994695326);
+    assert_dax("T.DIST(8, 3, FALSE)", 0.000736907);
+    assert_dax("T.DIST(2.228, 10, TRUE)", 0.974994114);
+    assert_dax("T.DIST(-1.5, 4, TRUE)", 0.104);
+    assert_dax("T.DIST(0.5, 30, TRUE)", 0.689638498);
+}
+
+#[test]
+fn test_chisq_dist() {
+    assert_dax("CHISQ.DIST(0.5, 1, TRUE)", 0.520499878);
+    assert_dax("CHISQ.DIST(2, 3, FALSE)", 0.207553749);
+    assert_dax("CHISQ.DIST(3.84, 1, TRUE)", 0.949956479);
+    assert_dax("CHISQ.DIST(18.307, 10, TRUE)", 0.949999411);
+    assert_dax("CHISQ.DIST(40, 5, TRUE)", 0.999999851);
+}
+
+#[test]
+fn test_invalid_distribution_arguments() {
+    let table = Table::new();
+    for expr in [
+        "NORM.DIST(1, 0, 0, TRUE)",
+        "NORM.INV(1.5, 0, 1)",
+        "NORM.S.INV(0)",
+        "T.DIST(1, 0, TRUE)",
+        "CHISQ.DIST(-1, 2, TRUE)",
+        "NORM.S.DIST(1)",
+    ] {
+        assert!(table.evaluate_dax(expr).is_err(), "{}", expr);
+    }
+}
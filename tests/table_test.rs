@@ -1,15 +1,21 @@
 // tests/table_test.rs
+use chrono::{Datelike, NaiveDate};
 use dax_macro_impl::DaxToken;
-use dax_rust::table::{DaxResult, Table};
-use dax_rust::types::Value;
+use dax_rust::error::DaxError;
+use dax_rust::functions::FunctionRegistry;
+use dax_rust::group::Agg;
+use dax_rust::join::JoinKind;
+use dax_rust::sort::{NullOrder, SortOrder};
+use dax_rust::table::{DaxResult, NullPolicy, Table};
+use dax_rust::types::{DType, Value};
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 #[test]
 fn test_empty_table() {
     let table = Table::new();
     match table.evaluate_dax("SUM([Sales])") {
-        DaxResult::Error(e) => assert!(e.contains("Could not calculate")),
+        Err(DaxError::UnknownColumn { name, .. }) => assert_eq!(name, "Sales"),
         _ => panic!("Expected error for empty table"),
     }
 }
@@ -17,35 +23,74 @@ fn test_empty_table() {
 #[test]
 fn test_mixed_data_types() {
     let mut table = Table::new();
-    table.add_column(
-        "Mixed".to_string(),
-        vec![
-            Value::Number(100.0),
-            Value::Text("Not a number".to_string()),
-            Value::Number(300.0),
-        ],
-    );
+    table
+        .add_column(
+            "Mixed".to_string(),
+            vec![
+                Value::Number(100.0),
+                Value::Text("Not a number".to_string()),
+                Value::Number(300.0),
+            ],
+        )
+        .unwrap();
 
     match table.evaluate_dax("SUM([Mixed])") {
-        DaxResult::Number(n) => assert_eq!(n, 400.0), // Should only sum the numbers
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 400.0), // Should only sum the numbers
+        _ => panic!("Expected number result"),
+    }
+
+    // AVERAGE skips the text, AVERAGEA counts it as zero
+    match table.evaluate_dax("AVERAGE([Mixed])") {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 200.0),
+        _ => panic!("Expected number result"),
+    }
+    match table.evaluate_dax("AVERAGEA([Mixed])") {
+        Ok(DaxResult::Number(n)) => assert!((n - 400.0 / 3.0).abs() < 1e-9),
+        _ => panic!("Expected number result"),
+    }
+    match table.evaluate_dax("MINA([Mixed])") {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 0.0),
         _ => panic!("Expected number result"),
     }
 }
 
+#[test]
+fn test_a_aggregates_coerce_booleans() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Flags".to_string(),
+            vec![
+                Value::Boolean(true),
+                Value::Boolean(false),
+                Value::Null,
+                Value::Boolean(true),
+            ],
+        )
+        .unwrap();
+
+    assert_eq!(table.averagea("Flags"), Some(2.0 / 3.0));
+    assert_eq!(table.mina("Flags"), Some(0.0));
+    assert_eq!(table.maxa("Flags"), Some(1.0));
+    assert_eq!(table.max("Flags"), None);
+}
+
 #[test]
 fn test_large_numbers() {
     let mut table = Table::new();
-    table.add_column(
-        "Large".to_string(),
-        vec![
-            Value::Number(1_000_000.0),
-            Value::Number(2_000_000.0),
-            Value::Number(3_000_000.0),
-        ],
-    );
+    table
+        .add_column(
+            "Large".to_string(),
+            vec![
+                Value::Number(1_000_000.0),
+                Value::Number(2_000_000.0),
+                Value::Number(3_000_000.0),
+            ],
+        )
+        .unwrap();
 
     match table.evaluate_dax("SUM([Large])") {
-        DaxResult::Number(n) => assert_eq!(n, 6_000_000.0),
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 6_000_000.0),
         _ => panic!("Expected number result for large numbers"),
     }
 }
@@ -53,13 +98,15 @@ fn test_large_numbers() {
 #[test]
 fn test_zero_values() {
     let mut table = Table::new();
-    table.add_column(
-        "Zeros".to_string(),
-        vec![Value::Number(0.0), Value::Number(0.0), Value::Number(0.0)],
-    );
+    table
+        .add_column(
+            "Zeros".to_string(),
+            vec![Value::Number(0.0), Value::Number(0.0), Value::Number(0.0)],
+        )
+        .unwrap();
 
     match table.evaluate_dax("AVERAGE([Zeros])") {
-        DaxResult::Number(n) => assert_eq!(n, 0.0),
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 0.0),
         _ => panic!("Expected zero average"),
     }
 }
@@ -67,10 +114,12 @@ fn test_zero_values() {
 #[test]
 fn test_single_value() {
     let mut table = Table::new();
-    table.add_column("Single".to_string(), vec![Value::Number(42.0)]);
+    table
+        .add_column("Single".to_string(), vec![Value::Number(42.0)])
+        .unwrap();
 
     match table.evaluate_dax("AVERAGE([Single])") {
-        DaxResult::Number(n) => assert_eq!(n, 42.0),
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 42.0),
         _ => panic!("Expected single value average"),
     }
 }
@@ -84,7 +133,7 @@ fn test_distinct_count_with_special_floats() {
         Value::Number(f64::INFINITY),
         Value::Number(f64::NEG_INFINITY),
     ];
-    table.add_column("special".to_string(), values);
+    table.add_column("special".to_string(), values).unwrap();
 
     assert_eq!(table.distinctcount("special"), Some(3));
 }
@@ -111,7 +160,7 @@ fn test_distinct_count_with_floats() {
         Value::Number(3.7),
         Value::Number(3.7), // Duplicate
     ];
-    table.add_column("test".to_string(), values);
+    table.add_column("test".to_string(), values).unwrap();
 
     assert_eq!(table.distinctcount("test"), Some(3));
 }
@@ -119,13 +168,15 @@ fn test_distinct_count_with_floats() {
 #[test]
 fn test_invalid_function() {
     let mut table = Table::new();
-    table.add_column(
-        "Sales".to_string(),
-        vec![Value::Number(100.0), Value::Number(200.0)],
-    );
+    table
+        .add_column(
+            "Sales".to_string(),
+            vec![Value::Number(100.0), Value::Number(200.0)],
+        )
+        .unwrap();
 
     match table.evaluate_dax("INVALID([Sales])") {
-        DaxResult::Error(e) => assert!(e.contains("Unsupported")),
+        Err(e) => assert!(e.to_string().contains("Unsupported")),
         _ => panic!("Expected error for invalid function"),
     }
 }
@@ -147,22 +198,26 @@ fn test_basic_table_operations() {
     let mut table = Table::new();
 
     // Add columns
-    table.add_column(
-        "Sales".to_string(),
-        vec![
-            Value::Number(100.0),
-            Value::Number(200.0),
-            Value::Number(300.0),
-        ],
-    );
-    table.add_column(
-        "Quantity".to_string(),
-        vec![
-            Value::Number(10.0),
-            Value::Number(20.0),
-            Value::Number(30.0),
-        ],
-    );
+    table
+        .add_column(
+            "Sales".to_string(),
+            vec![
+                Value::Number(100.0),
+                Value::Number(200.0),
+                Value::Number(300.0),
+            ],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Quantity".to_string(),
+            vec![
+                Value::Number(10.0),
+                Value::Number(20.0),
+                Value::Number(30.0),
+            ],
+        )
+        .unwrap();
 
     // Test SUM
     let tokens = dax_macro::parse_dax!("SUM([Sales])");
@@ -198,17 +253,19 @@ fn test_basic_table_operations() {
 #[test]
 fn test_dax_evaluation() {
     let mut table = Table::new();
-    table.add_column(
-        "Sales".to_string(),
-        vec![
-            Value::Number(100.0),
-            Value::Number(200.0),
-            Value::Number(300.0),
-        ],
-    );
+    table
+        .add_column(
+            "Sales".to_string(),
+            vec![
+                Value::Number(100.0),
+                Value::Number(200.0),
+                Value::Number(300.0),
+            ],
+        )
+        .unwrap();
 
     match table.evaluate_dax("SUM([Sales])") {
-        DaxResult::Number(n) => assert_eq!(n, 600.0),
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 600.0),
         _ => panic!("Expected number result"),
     }
 }
@@ -216,17 +273,19 @@ fn test_dax_evaluation() {
 #[test]
 fn test_dax_average() {
     let mut table = Table::new();
-    table.add_column(
-        "Sales".to_string(),
-        vec![
-            Value::Number(100.0),
-            Value::Number(200.0),
-            Value::Number(300.0),
-        ],
-    );
+    table
+        .add_column(
+            "Sales".to_string(),
+            vec![
+                Value::Number(100.0),
+                Value::Number(200.0),
+                Value::Number(300.0),
+            ],
+        )
+        .unwrap();
 
     match table.evaluate_dax("AVERAGE([Sales])") {
-        DaxResult::Number(n) => assert_eq!(n, 200.0),
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 200.0),
         _ => panic!("Expected number result"),
     }
 }
@@ -234,17 +293,3839 @@ fn test_dax_average() {
 #[test]
 fn test_invalid_column() {
     let mut table = Table::new();
-    table.add_column(
-        "Sales".to_string(),
-        vec![
-            Value::Number(100.0),
-            Value::Number(200.0),
-            Value::Number(300.0),
-        ],
-    );
+    table
+        .add_column(
+            "Sales".to_string(),
+            vec![
+                Value::Number(100.0),
+                Value::Number(200.0),
+                Value::Number(300.0),
+            ],
+        )
+        .unwrap();
 
     match table.evaluate_dax("SUM([NonExistent])") {
-        DaxResult::Error(_) => (),
+        Err(_) => (),
         _ => panic!("Expected error for non-existent column"),
     }
 }
+
+#[test]
+fn test_true_and_false() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Sales".to_string(),
+            vec![Value::Number(100.0), Value::Number(200.0)],
+        )
+        .unwrap();
+
+    for (expr, expected) in [
+        ("TRUE()", true),
+        ("FALSE()", false),
+        ("TRUE", true),
+        ("true()", true),
+        ("False()", false),
+    ] {
+        match table.evaluate_dax(expr) {
+            Ok(DaxResult::Boolean(b)) => assert_eq!(b, expected, "{}", expr),
+            other => panic!("Expected boolean result for {}, got {:?}", expr, other),
+        }
+    }
+
+    match table.evaluate_dax("IF(TRUE(), 1, 2)") {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 1.0),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+    match table.evaluate_dax("SWITCH(TRUE(), FALSE(), \"no\", TRUE(), \"yes\", \"other\")") {
+        Ok(DaxResult::Text(s)) => assert_eq!(s, "yes"),
+        other => panic!("Expected text result, got {:?}", other),
+    }
+    match table.evaluate_dax("COUNTROWS(FILTER(Sales, TRUE()))") {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 2.0),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+    match table.evaluate_dax("COUNTROWS(FILTER(Sales, FALSE))") {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 0.0),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_boolean_calculate_filters() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Sales".to_string(),
+            vec![Value::Number(100.0), Value::Number(200.0)],
+        )
+        .unwrap();
+
+    match table.evaluate_dax("CALCULATE(SUM([Sales]), TRUE())") {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 300.0),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+    assert!(matches!(
+        table.evaluate_dax("CALCULATE(SUM([Sales]), FALSE())"),
+        Ok(DaxResult::Blank)
+    ));
+}
+
+#[test]
+fn test_text_utilities() {
+    let table = Table::new();
+    let text = |expr: &str| match table.evaluate_dax(expr) {
+        Ok(DaxResult::Text(s)) => s,
+        other => panic!("Expected text result for {}, got {:?}", expr, other),
+    };
+
+    assert!(matches!(
+        table.evaluate_dax("EXACT(\"Word\", \"Word\")"),
+        Ok(DaxResult::Boolean(true))
+    ));
+    assert!(matches!(
+        table.evaluate_dax("EXACT(\"Word\", \"word\")"),
+        Ok(DaxResult::Boolean(false))
+    ));
+
+    assert_eq!(text("REPT(\"ab\", 3)"), "ababab");
+    assert_eq!(text("REPT(\"ab\", 0)"), "");
+    assert!(table.evaluate_dax("REPT(\"ab\", 1000000000000)").is_err());
+
+    assert_eq!(text("UNICHAR(65)"), "A");
+    assert_eq!(text("UNICHAR(9731)"), "☃");
+    assert!(table.evaluate_dax("UNICHAR(55296)").is_err());
+    match table.evaluate_dax("UNICODE(\"☃ snow\")") {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 9731.0),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_combinevalues() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "A".to_string(),
+            vec![Value::Text("a".to_string()), Value::Text("b".to_string())],
+        )
+        .unwrap();
+    table
+        .add_column("B".to_string(), vec![Value::Null, Value::Number(2.0)])
+        .unwrap();
+
+    match table.evaluate_dax("COMBINEVALUES(\"|\", \"x\", 1.5, TRUE())") {
+        Ok(DaxResult::Text(s)) => assert_eq!(s, "x|1.5|True"),
+        other => panic!("Expected text result, got {:?}", other),
+    }
+
+    // Blanks keep their position as empty text
+    table
+        .add_calculated_column("Key".to_string(), "COMBINEVALUES(\"|\", [A], [B])")
+        .unwrap();
+    assert_eq!(
+        table.get_column("Key").unwrap(),
+        vec![
+            Value::Text("a|".to_string()),
+            Value::Text("b|2".to_string()),
+        ]
+    );
+
+    match table.evaluate_dax("ADDCOLUMNS(Data, \"Key2\", COMBINEVALUES(\"-\", [B], [A]))") {
+        Ok(DaxResult::Table(result)) => assert_eq!(
+            result.get_column("Key2").unwrap(),
+            vec![
+                Value::Text("-a".to_string()),
+                Value::Text("2-b".to_string()),
+            ]
+        ),
+        other => panic!("Expected table result, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_isempty_and_countrows_on_expressions() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Quantity".to_string(),
+            vec![
+                Value::Number(20.0),
+                Value::Number(150.0),
+                Value::Number(5.0),
+            ],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Large".to_string(),
+            vec![
+                Value::Boolean(false),
+                Value::Boolean(true),
+                Value::Boolean(false),
+            ],
+        )
+        .unwrap();
+
+    match table.evaluate_dax("IF(ISEMPTY(FILTER(Sales, [Large])), \"none\", \"some\")") {
+        Ok(DaxResult::Text(s)) => assert_eq!(s, "some"),
+        other => panic!("Expected text result, got {:?}", other),
+    }
+    match table.evaluate_dax("IF(ISEMPTY(FILTER(Sales, FALSE())), \"none\", \"some\")") {
+        Ok(DaxResult::Text(s)) => assert_eq!(s, "none"),
+        other => panic!("Expected text result, got {:?}", other),
+    }
+    match table.evaluate_dax("COUNTROWS(FILTER(Sales, [Large]))") {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 1.0),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+    assert!(table.evaluate_dax("ISEMPTY(1)").is_err());
+}
+
+#[test]
+fn test_keepfilters_and_removefilters() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Product".to_string(),
+            vec![
+                Value::Text("Apple".to_string()),
+                Value::Text("Banana".to_string()),
+                Value::Text("Apple".to_string()),
+            ],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Sales".to_string(),
+            vec![
+                Value::Number(10.0),
+                Value::Number(20.0),
+                Value::Number(30.0),
+            ],
+        )
+        .unwrap();
+
+    let in_apple =
+        |expr: &str| table.evaluate_dax(&format!("CALCULATE({}, [Product] = \"Apple\")", expr));
+
+    // A plain filter replaces the outer filter on the same column
+    match in_apple("CALCULATE(SUM([Sales]), [Product] = \"Banana\")") {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 20.0),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+    // KEEPFILTERS intersects with it, leaving nothing
+    assert!(matches!(
+        in_apple("CALCULATE(SUM([Sales]), KEEPFILTERS([Product] = \"Banana\"))"),
+        Ok(DaxResult::Blank)
+    ));
+    match in_apple("CALCULATE(SUM([Sales]), KEEPFILTERS([Product] = \"apple\"))") {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 40.0),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+
+    for expr in [
+        "CALCULATE(SUM([Sales]), REMOVEFILTERS())",
+        "CALCULATE(SUM([Sales]), REMOVEFILTERS(Sales))",
+        "CALCULATE(SUM([Sales]), REMOVEFILTERS([Product]))",
+    ] {
+        match in_apple(expr) {
+            Ok(DaxResult::Number(n)) => assert_eq!(n, 60.0, "{}", expr),
+            other => panic!("Expected number result for {}, got {:?}", expr, other),
+        }
+    }
+
+    assert!(table.evaluate_dax("REMOVEFILTERS([Product])").is_err());
+
+    // Relationship modifiers have nothing to act on in a single table
+    for expr in [
+        "CALCULATE(SUM([Sales]), USERELATIONSHIP([Product], [Sales]))",
+        "CALCULATE(SUM([Sales]), CROSSFILTER([Product], [Sales], BOTH))",
+        "USERELATIONSHIP([Product], [Sales])",
+    ] {
+        match table.evaluate_dax(expr) {
+            Err(DaxError::EvaluationError(e)) => assert!(e.contains("relationship"), "{}", e),
+            other => panic!("Expected an error for {}, got {:?}", expr, other),
+        }
+    }
+}
+
+#[test]
+fn test_approximate_distinct_count() {
+    let mut table = Table::new();
+    // 100k rows holding 50k distinct values
+    table
+        .add_column(
+            "Id".to_string(),
+            (0..100_000)
+                .map(|i| Value::Number((i % 50_000) as f64))
+                .collect(),
+        )
+        .unwrap();
+
+    let estimate = table.approx_distinctcount("Id", 14).unwrap();
+    let error = (estimate as f64 - 50_000.0).abs() / 50_000.0;
+    assert!(
+        error < 0.03,
+        "estimate {} is off by {:.1}%",
+        estimate,
+        error * 100.0
+    );
+
+    match table.evaluate_dax("APPROXIMATEDISTINCTCOUNT([Id])") {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, estimate as f64),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+    assert_eq!(table.approx_distinctcount("Id", 2), None);
+    assert_eq!(table.approx_distinctcount("Missing", 14), None);
+}
+
+#[test]
+fn test_error_messages_propagate() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Rate".to_string(),
+            vec![Value::Number(0.5), Value::Number(1.5)],
+        )
+        .unwrap();
+
+    match table.evaluate_dax("IF(TRUE(), ERROR(\"Rate cannot exceed 100%\"), 1)") {
+        Err(DaxError::EvaluationError(e)) => assert_eq!(e, "Rate cannot exceed 100%"),
+        other => panic!("Expected error result, got {:?}", other),
+    }
+    // Nested evaluation leaves the message as written
+    match table.evaluate_dax("CALCULATE(IF(TRUE(), ERROR(\"Stop\"), 1), TRUE())") {
+        Err(DaxError::EvaluationError(e)) => assert_eq!(e, "Stop"),
+        other => panic!("Expected error result, got {:?}", other),
+    }
+
+    match table.evaluate_dax("IFERROR(ERROR(\"Stop\"), 42)") {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 42.0),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+    match table.evaluate_dax("IFERROR(SUM([Missing]), \"fallback\")") {
+        Ok(DaxResult::Text(s)) => assert_eq!(s, "fallback"),
+        other => panic!("Expected text result, got {:?}", other),
+    }
+    match table.evaluate_dax("IFERROR(1, 2)") {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 1.0),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+
+    let err = table
+        .add_calculated_column(
+            "Checked".to_string(),
+            "IF([Rate] = 1.5, ERROR(\"Too high\"), [Rate])",
+        )
+        .unwrap_err();
+    assert_eq!(err.to_string(), "Evaluation error: Too high");
+    assert!(table.get_column("Checked").is_none());
+}
+
+#[test]
+fn test_sample_and_topn() {
+    let mut table = Table::new();
+    let days = [7.0, 2.0, 10.0, 1.0, 5.0, 3.0, 9.0, 4.0, 8.0, 6.0];
+    table
+        .add_column(
+            "Day".to_string(),
+            days.iter().map(|&d| Value::Number(d)).collect(),
+        )
+        .unwrap();
+
+    let days_of = |expr: &str| match table.evaluate_dax(expr) {
+        Ok(DaxResult::Table(result)) => result.get_column("Day").unwrap().to_vec(),
+        other => panic!("Expected table result for {}, got {:?}", expr, other),
+    };
+    let numbers = |values: &[f64]| values.iter().map(|&d| Value::Number(d)).collect::<Vec<_>>();
+
+    // The first and last rows of the ordering are always kept
+    assert_eq!(
+        days_of("SAMPLE(4, Data, [Day])"),
+        numbers(&[1.0, 4.0, 7.0, 10.0])
+    );
+    assert_eq!(
+        days_of("SAMPLE(4, Data, [Day], ASC)"),
+        numbers(&[1.0, 4.0, 7.0, 10.0])
+    );
+    assert_eq!(
+        days_of("SAMPLE(4, Data, [Day], DESC)"),
+        numbers(&[10.0, 7.0, 4.0, 1.0])
+    );
+    assert_eq!(days_of("SAMPLE(2, Data, [Day], 0)"), numbers(&[10.0, 1.0]));
+    assert_eq!(days_of("SAMPLE(50, Data, [Day])").len(), 10);
+    assert!(days_of("SAMPLE(0, Data, [Day])").is_empty());
+
+    assert_eq!(days_of("TOPN(3, Data, [Day])"), numbers(&[10.0, 9.0, 8.0]));
+    assert_eq!(days_of("TOPN(2, Data, [Day], ASC)"), numbers(&[1.0, 2.0]));
+}
+
+#[test]
+fn test_summarizecolumns_drops_blank_groups() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Region".to_string(),
+            vec![
+                Value::Text("North".to_string()),
+                Value::Text("North".to_string()),
+                Value::Text("South".to_string()),
+                Value::Text("East".to_string()),
+            ],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Channel".to_string(),
+            vec![
+                Value::Text("Web".to_string()),
+                Value::Text("Store".to_string()),
+                Value::Text("Store".to_string()),
+                Value::Text("Web".to_string()),
+            ],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Amount".to_string(),
+            vec![
+                Value::Number(10.0),
+                Value::Number(20.0),
+                Value::Number(30.0),
+                Value::Number(40.0),
+            ],
+        )
+        .unwrap();
+
+    // South has no web sales, so its only measure is blank and the group goes
+    match table.evaluate_dax(
+        "SUMMARIZECOLUMNS([Region], \"Web\", CALCULATE(SUM([Amount]), [Channel] = \"Web\"))",
+    ) {
+        Ok(DaxResult::Table(t)) => {
+            assert_eq!(
+                t.get_column("Region").unwrap(),
+                vec![
+                    Value::Text("North".to_string()),
+                    Value::Text("East".to_string()),
+                ]
+            );
+            assert_eq!(
+                t.get_column("Web").unwrap(),
+                vec![Value::Number(10.0), Value::Number(40.0)]
+            );
+        }
+        other => panic!("Expected table result, got {:?}", other),
+    }
+
+    // A group stays while any of its measures has a value
+    match table.evaluate_dax(
+        "SUMMARIZECOLUMNS([Region], \"Web\", CALCULATE(SUM([Amount]), [Channel] = \"Web\"), \"Total\", SUM([Amount]))",
+    ) {
+        Ok(DaxResult::Table(t)) => {
+            assert_eq!(
+                t.get_column("Web").unwrap(),
+                vec![Value::Number(10.0), Value::Null, Value::Number(40.0)]
+            );
+            assert_eq!(
+                t.get_column("Total").unwrap(),
+                vec![Value::Number(30.0), Value::Number(30.0), Value::Number(40.0)]
+            );
+        }
+        other => panic!("Expected table result, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_summarizecolumns_filter_tables() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Region".to_string(),
+            vec![
+                Value::Text("North".to_string()),
+                Value::Text("North".to_string()),
+                Value::Text("South".to_string()),
+            ],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Channel".to_string(),
+            vec![
+                Value::Text("Web".to_string()),
+                Value::Text("Store".to_string()),
+                Value::Text("Store".to_string()),
+            ],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Amount".to_string(),
+            vec![
+                Value::Number(10.0),
+                Value::Number(20.0),
+                Value::Number(30.0),
+            ],
+        )
+        .unwrap();
+
+    // The filter table hides South entirely and North's store sale
+    match table.evaluate_dax(
+        "SUMMARIZECOLUMNS([Region], [Channel], FILTER(Sales, [Channel] = \"Web\"), \"Total\", SUM([Amount]))",
+    ) {
+        Ok(DaxResult::Table(t)) => {
+            assert_eq!(
+                t.get_column("Region").unwrap(),
+                vec![Value::Text("North".to_string())]
+            );
+            assert_eq!(
+                t.get_column("Channel").unwrap(),
+                vec![Value::Text("Web".to_string())]
+            );
+            assert_eq!(t.get_column("Total").unwrap(), vec![Value::Number(10.0)]);
+        }
+        other => panic!("Expected table result, got {:?}", other),
+    }
+
+    // Without measures every group of the visible rows is kept
+    match table.evaluate_dax("COUNTROWS(SUMMARIZECOLUMNS([Region]))") {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 2.0),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+    assert!(table
+        .evaluate_dax("SUMMARIZECOLUMNS([Region], \"Total\")")
+        .is_err());
+    assert!(table
+        .evaluate_dax("SUMMARIZECOLUMNS([Missing], \"Total\", SUM([Amount]))")
+        .is_err());
+}
+
+#[test]
+fn test_table_constructor() {
+    let table = Table::new();
+
+    match table.evaluate_dax("{\"Apple\", \"Banana\", 3}") {
+        Ok(DaxResult::Table(t)) => assert_eq!(
+            t.get_column("Value").unwrap(),
+            vec![
+                Value::Text("Apple".to_string()),
+                Value::Text("Banana".to_string()),
+                Value::Number(3.0),
+            ]
+        ),
+        other => panic!("Expected table result, got {:?}", other),
+    }
+    // Commas inside the braces don't split the outer arguments
+    match table.evaluate_dax("COUNTROWS(FILTER({1, 2, 3}, TRUE()))") {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 3.0),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+    assert!(table.evaluate_dax("{1, {2}}").is_err());
+}
+
+#[test]
+fn test_treatas() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Product".to_string(),
+            vec![
+                Value::Text("Apple".to_string()),
+                Value::Text("Banana".to_string()),
+                Value::Text("Cherry".to_string()),
+                Value::Text("Apple".to_string()),
+            ],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Sales".to_string(),
+            vec![
+                Value::Number(10.0),
+                Value::Number(20.0),
+                Value::Number(30.0),
+                Value::Number(40.0),
+            ],
+        )
+        .unwrap();
+
+    // Behaves like an IN filter on the target column
+    match table.evaluate_dax("CALCULATE(SUM([Sales]), TREATAS({\"Apple\", \"Banana\"}, [Product]))")
+    {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 70.0),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+    // Values with no match in the column filter everything out
+    match table.evaluate_dax("CALCULATE(SUM([Sales]), TREATAS({\"Durian\"}, [Product]))") {
+        Ok(DaxResult::Blank) => {}
+        other => panic!("Expected blank result, got {:?}", other),
+    }
+    match table.evaluate_dax("TREATAS({\"Apple\"}, [Product])") {
+        Ok(DaxResult::Table(t)) => assert_eq!(
+            t.get_column("Product").unwrap(),
+            vec![Value::Text("Apple".to_string())]
+        ),
+        other => panic!("Expected table result, got {:?}", other),
+    }
+
+    assert!(table
+        .evaluate_dax("TREATAS({\"Apple\"}, [Product], [Sales])")
+        .is_err());
+    assert!(table
+        .evaluate_dax("TREATAS({\"Apple\"}, [Missing])")
+        .is_err());
+}
+
+#[test]
+fn test_currency_sum_is_exact() {
+    let amounts = [0.1, 0.2];
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Price".to_string(),
+            amounts.iter().map(|&a| Value::Number(a)).collect(),
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Amount".to_string(),
+            amounts
+                .iter()
+                .map(|&a| Value::currency(a).unwrap())
+                .collect(),
+        )
+        .unwrap();
+
+    // Floating point lands an ulp away from 0.3
+    match table.evaluate_dax("SUM([Price])") {
+        Ok(DaxResult::Number(n)) => assert_ne!(n, 0.3),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+    match table.evaluate_dax("SUM([Amount])") {
+        Ok(DaxResult::Currency(c)) => assert_eq!(c, 3_000),
+        other => panic!("Expected currency result, got {:?}", other),
+    }
+    assert!(table.to_string().contains("0.20"));
+}
+
+#[test]
+fn test_currency_conversion_and_division() {
+    let table = Table::new();
+
+    // Rounded to four decimal places
+    match table.evaluate_dax("CURRENCY(1.23456)") {
+        Ok(DaxResult::Currency(c)) => assert_eq!(c, 12_346),
+        other => panic!("Expected currency result, got {:?}", other),
+    }
+    match table.evaluate_dax("CURRENCY(\"2.5\")") {
+        Ok(DaxResult::Currency(c)) => assert_eq!(c, 25_000),
+        other => panic!("Expected currency result, got {:?}", other),
+    }
+    // An amount split by a number stays currency; currency over currency is a ratio
+    match table.evaluate_dax("DIVIDE(CURRENCY(10), 3)") {
+        Ok(DaxResult::Currency(c)) => assert_eq!(c, 33_333),
+        other => panic!("Expected currency result, got {:?}", other),
+    }
+    match table.evaluate_dax("DIVIDE(CURRENCY(10), CURRENCY(4))") {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 2.5),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+    assert!(table.evaluate_dax("CURRENCY(\"ten\")").is_err());
+}
+
+#[test]
+fn test_integer_columns() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Quantity".to_string(),
+            vec![Value::Integer(3), Value::Integer(4), Value::Integer(8)],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Mixed".to_string(),
+            vec![Value::Integer(3), Value::Number(3.0), Value::Integer(5)],
+        )
+        .unwrap();
+
+    match table.evaluate_dax("SUM([Quantity])") {
+        Ok(DaxResult::Integer(i)) => assert_eq!(i, 15),
+        other => panic!("Expected integer result, got {:?}", other),
+    }
+    match table.evaluate_dax("MAX([Quantity])") {
+        Ok(DaxResult::Integer(i)) => assert_eq!(i, 8),
+        other => panic!("Expected integer result, got {:?}", other),
+    }
+    // Division promotes to a number
+    match table.evaluate_dax("AVERAGE([Quantity])") {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 5.0),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+    match table.evaluate_dax("DIVIDE(SUM([Quantity]), 2)") {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 7.5),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+    // A float anywhere in the column makes the total a number
+    match table.evaluate_dax("SUM([Mixed])") {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 11.0),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+    // Integer(3) and Number(3.0) are the same value
+    assert_eq!(table.distinctcount("Mixed"), Some(2));
+    assert_eq!(Value::Integer(3), Value::Number(3.0));
+
+    let rendered = table.to_string();
+    assert!(rendered.contains(" 8 "));
+    assert!(!rendered.contains("8.00"));
+}
+
+#[test]
+fn test_integer_and_number_compare_exactly() {
+    use std::cmp::Ordering;
+    use std::hash::{BuildHasher, RandomState};
+
+    // 2^53 + 1 has no float of its own, so it rounds to 2^53 as a float
+    let big = 1_i64 << 53;
+    let (low, high) = (Value::Integer(big), Value::Integer(big + 1));
+    let float = Value::Number(big as f64);
+    assert_eq!(low, float);
+    assert_ne!(high, float);
+    assert_ne!(low, high);
+    assert_eq!(high.cmp(&float), Ordering::Greater);
+    assert_eq!(float.cmp(&high), Ordering::Less);
+
+    let hasher = RandomState::new();
+    for (integer, number) in [
+        (3, 3.0),
+        (0, -0.0),
+        (big, big as f64),
+        (i64::MIN, -(2f64.powi(63))),
+    ] {
+        assert_eq!(Value::Integer(integer), Value::Number(number));
+        assert_eq!(
+            hasher.hash_one(Value::Integer(integer)),
+            hasher.hash_one(Value::Number(number))
+        );
+    }
+    assert_eq!(Value::Number(0.0), Value::Number(-0.0));
+    assert_eq!(
+        hasher.hash_one(Value::Number(0.0)),
+        hasher.hash_one(Value::Number(-0.0))
+    );
+    for (integer, number) in [(i64::MAX, 2f64.powi(63)), (2, 2.5), (-3, -2.5)] {
+        assert_ne!(Value::Integer(integer), Value::Number(number));
+        assert_eq!(
+            Value::Integer(integer).cmp(&Value::Number(number)),
+            Ordering::Less
+        );
+    }
+    assert_eq!(
+        Value::Integer(i64::MAX).cmp(&Value::Number(f64::NAN)),
+        Ordering::Less
+    );
+
+    // Grouping keeps the two apart
+    let mut table = Table::new();
+    table
+        .add_column(
+            "N".to_string(),
+            vec![high.clone(), float.clone(), low.clone()],
+        )
+        .unwrap();
+    assert_eq!(table.distinctcount("N"), Some(2));
+}
+
+#[test]
+fn test_comparison_tokens() {
+    let tokens = dax_macro::parse_dax!("[Quantity] >= 15 <> [A] <= [B]");
+    let ops: Vec<&str> = tokens
+        .iter()
+        .filter_map(|t| match t {
+            DaxToken::Comparison(op) => Some(*op),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(ops, vec![">=", "<>", "<="]);
+}
+
+#[test]
+fn test_comparison_operators() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Product".to_string(),
+            vec![
+                Value::Text("Apple".to_string()),
+                Value::Text("Banana".to_string()),
+                Value::Text("Apple".to_string()),
+            ],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Quantity".to_string(),
+            vec![Value::Integer(10), Value::Integer(15), Value::Integer(20)],
+        )
+        .unwrap();
+
+    let cases = [
+        ("[Product] = \"apple\"", 2.0),
+        ("[Product] <> \"Apple\"", 1.0),
+        ("[Quantity] >= 15", 2.0),
+        ("[Quantity] > 15", 1.0),
+        ("[Quantity] <= 15", 2.0),
+        ("[Quantity] < 15", 1.0),
+    ];
+    for (condition, expected) in cases {
+        let expr = format!("COUNTROWS(FILTER(Sales, {}))", condition);
+        match table.evaluate_dax(&expr) {
+            Ok(DaxResult::Number(n)) => assert_eq!(n, expected, "{}", condition),
+            other => panic!("Expected number result for {}, got {:?}", condition, other),
+        }
+    }
+
+    // Comparisons are CALCULATE filters too
+    match table.evaluate_dax("CALCULATE(SUM([Quantity]), [Quantity] >= 15)") {
+        Ok(DaxResult::Integer(i)) => assert_eq!(i, 35),
+        other => panic!("Expected integer result, got {:?}", other),
+    }
+    match table.evaluate_dax("BLANK() < 1") {
+        Ok(DaxResult::Boolean(b)) => assert!(b),
+        other => panic!("Expected boolean result, got {:?}", other),
+    }
+    match table.evaluate_dax("COUNTROWS(FILTER(Sales, [Product] > 1))") {
+        Err(e) => assert!(e.to_string().contains(">")),
+        other => panic!("Expected error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_value_ordering() {
+    let mut values = vec![
+        Value::Text("b".to_string()),
+        Value::Number(f64::NAN),
+        Value::Integer(2),
+        Value::Null,
+        Value::Number(1.5),
+        Value::Boolean(true),
+        Value::Text("a".to_string()),
+    ];
+    values.sort();
+    assert_eq!(
+        values,
+        vec![
+            Value::Null,
+            Value::Boolean(true),
+            Value::Number(1.5),
+            Value::Integer(2),
+            Value::Number(f64::NAN),
+            Value::Text("a".to_string()),
+            Value::Text("b".to_string()),
+        ]
+    );
+    assert_eq!(
+        Value::Integer(3).cmp(&Value::Number(3.0)),
+        std::cmp::Ordering::Equal
+    );
+
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Product".to_string(),
+            vec![
+                Value::Text("Cherry".to_string()),
+                Value::Text("Apple".to_string()),
+            ],
+        )
+        .unwrap();
+    match table.evaluate_dax("MIN([Product])") {
+        Ok(DaxResult::Text(s)) => assert_eq!(s, "Apple"),
+        other => panic!("Expected text result, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_arithmetic_expressions() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Sales".to_string(),
+            vec![Value::Number(100.0), Value::Number(200.0)],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Quantity".to_string(),
+            vec![Value::Integer(10), Value::Integer(30)],
+        )
+        .unwrap();
+
+    let cases = [
+        ("SUM([Sales]) / SUM([Quantity])", 7.5),
+        ("2 + 3 * 4", 14.0),
+        ("(2 + 3) * 4", 20.0),
+        ("10 - 4 - 3", 3.0),
+        ("-2 ^ 2", -4.0),
+        ("2 ^ 3 ^ 2", 512.0),
+        ("(2 ^ 3) ^ 2", 64.0),
+        ("1 - -1", 2.0),
+        ("SUM([Sales]) * 1.5 + 1", 451.0),
+    ];
+    for (expr, expected) in cases {
+        match table.evaluate_dax(expr) {
+            Ok(DaxResult::Number(n)) => assert_eq!(n, expected, "{}", expr),
+            other => panic!("Expected number result for {}, got {:?}", expr, other),
+        }
+    }
+
+    // Integers stay integers until a division
+    match table.evaluate_dax("SUM([Quantity]) * MAX([Quantity]) - MIN([Quantity])") {
+        Ok(DaxResult::Integer(i)) => assert_eq!(i, 1190),
+        other => panic!("Expected integer result, got {:?}", other),
+    }
+    match table.evaluate_dax("COUNTROWS(FILTER(Sales, [Sales] / [Quantity] > 8))") {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 1.0),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+
+    match table.evaluate_dax("SUM([Sales]) / 0") {
+        Err(e) => assert!(e.to_string().contains("Division by zero")),
+        other => panic!("Expected error, got {:?}", other),
+    }
+    match table.evaluate_dax("\"a\" + 1") {
+        Err(e) => assert!(e.to_string().contains("+")),
+        other => panic!("Expected error, got {:?}", other),
+    }
+    assert!(table.evaluate_dax("1 +").is_err());
+}
+
+#[test]
+fn test_nested_calls_and_parse_errors() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Sales".to_string(),
+            vec![Value::Number(100.0), Value::Number(200.0)],
+        )
+        .unwrap();
+
+    match table.evaluate_dax("DIVIDE(SUM([Sales]), COUNTROWS(Sales))") {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 150.0),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+    match table.evaluate_dax("DIVIDE(SUM([Sales]), MAX([Sales]) - 200, -1)") {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, -1.0),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+
+    // Blank in, blank out, as DAX has it, and any value as the alternate
+    for expression in [
+        "DIVIDE(1, 0)",
+        "DIVIDE(1, BLANK())",
+        "DIVIDE(1, 0, BLANK())",
+        "DIVIDE(BLANK(), 2)",
+        "CALCULATE(DIVIDE(SUM([Sales]), 2), [Sales] > 1000)",
+        "CALCULATE(SUM([Sales]), [Sales] > 1000) / 2",
+        "CALCULATE(SUM([Sales]), [Sales] > 1000) * 2",
+        "BLANK() + BLANK()",
+    ] {
+        match table.evaluate_dax(expression) {
+            Ok(DaxResult::Blank) => {}
+            other => panic!("Expected blank for {}, got {:?}", expression, other),
+        }
+    }
+    match table.evaluate_dax("DIVIDE(1, 0, \"none\")") {
+        Ok(DaxResult::Text(s)) => assert_eq!(s, "none"),
+        other => panic!("Expected text result, got {:?}", other),
+    }
+    match table.evaluate_dax("CALCULATE(SUM([Sales]), [Sales] > 1000) + 2") {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 2.0),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+    assert!(table.evaluate_dax("DIVIDE(\"a\", 2)").is_err());
+
+    for expression in ["AVERAGE([Sales]", "SUM([Sales]))", "SUM([Sales]) [Sales]"] {
+        match table.evaluate_dax(expression) {
+            Err(e) => assert!(matches!(e, DaxError::ParseError(_)), "{}", e),
+            other => panic!("Expected parse error for {}, got {:?}", expression, other),
+        }
+    }
+}
+
+#[test]
+fn test_string_literal_tokens() {
+    let tokens = dax_macro::parse_dax!(r#"EXACT("say ""hi""", "a, (b) + c")"#);
+    let texts: Vec<&str> = tokens
+        .iter()
+        .filter_map(|t| match t {
+            DaxToken::Text(text) => Some(text.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(texts, vec!["say \"hi\"", "a, (b) + c"]);
+    assert_eq!(tokens[2].to_string(), r#""say ""hi""""#);
+
+    let mut table = Table::new();
+    table
+        .add_column("Sales".to_string(), vec![Value::Number(1.0)])
+        .unwrap();
+    match table.evaluate_dax(r#"COMBINEVALUES(", ", "say ""hi""", "(ok)")"#) {
+        Ok(DaxResult::Text(s)) => assert_eq!(s, "say \"hi\", (ok)"),
+        other => panic!("Expected text result, got {:?}", other),
+    }
+    match table.evaluate_dax(r#"EXACT("a", "b)"#) {
+        Err(DaxError::ParseError(e)) => assert!(e.contains("starting at character 11"), "{}", e),
+        other => panic!("Expected parse error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_qualified_column_references() {
+    let tokens = dax_macro::parse_dax!("SUM(Sales[Amount]) + SUM('Order Details'[Qty])");
+    let qualified: Vec<String> = tokens
+        .iter()
+        .filter(|t| matches!(t, DaxToken::QualifiedColumn(..)))
+        .map(|t| t.to_string())
+        .collect();
+    assert_eq!(qualified, vec!["Sales[Amount]", "'Order Details'[Qty]"]);
+
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Amount".to_string(),
+            vec![Value::Number(10.0), Value::Number(20.0)],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Qty".to_string(),
+            vec![Value::Integer(1), Value::Integer(3)],
+        )
+        .unwrap();
+    for (expr, expected) in [
+        ("SUM(Sales[Amount])", 30.0),
+        ("SUM([Amount])", 30.0),
+        ("SUM('Order Details'[Qty]) * 2", 8.0),
+        (
+            "COUNTROWS(FILTER(Sales, Sales[Amount] * 'Sales'[Qty] > 15))",
+            1.0,
+        ),
+    ] {
+        match table.evaluate_dax(expr) {
+            Ok(DaxResult::Number(n)) => assert_eq!(n, expected, "{}", expr),
+            Ok(DaxResult::Integer(i)) => assert_eq!(i as f64, expected, "{}", expr),
+            other => panic!("Expected number result for {}, got {:?}", expr, other),
+        }
+    }
+    assert!(matches!(
+        table.evaluate_dax("SUM('Order Details[Qty])"),
+        Err(DaxError::ParseError(e)) if e.contains("Unterminated table name")
+    ));
+}
+
+#[test]
+fn test_unary_minus() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Sales".to_string(),
+            vec![Value::Number(100.0), Value::Number(200.0)],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Cost".to_string(),
+            vec![Value::Number(40.0), Value::Number(50.0)],
+        )
+        .unwrap();
+
+    for (expr, expected) in [
+        ("5 - -3", 8.0),
+        ("-(SUM([Sales]))", -300.0),
+        ("SUM([Sales]) * -1", -300.0),
+        ("DIVIDE(SUM([Sales]), -2)", -150.0),
+        ("-3.5e2 + +1", -349.0),
+        ("COUNTROWS(FILTER(Sales, -[Cost] < -45))", 1.0),
+    ] {
+        match table.evaluate_dax(expr) {
+            Ok(DaxResult::Number(n)) => assert_eq!(n, expected, "{}", expr),
+            other => panic!("Expected number result for {}, got {:?}", expr, other),
+        }
+    }
+
+    // A sign keeps a blank blank, though subtracting one from 0 gives 0
+    for expr in [
+        "-BLANK()",
+        "+BLANK()",
+        "--BLANK()",
+        "-CALCULATE(SUM([Sales]), [Sales] > 1000)",
+    ] {
+        match table.evaluate_dax(expr) {
+            Ok(DaxResult::Blank) => {}
+            other => panic!("Expected blank result for {}, got {:?}", expr, other),
+        }
+    }
+    assert!(matches!(
+        table.evaluate_dax("0 - BLANK()"),
+        Ok(DaxResult::Number(n)) if n == 0.0
+    ));
+    assert!(matches!(
+        table.evaluate_dax("-TRUE()"),
+        Ok(DaxResult::Integer(-1))
+    ));
+}
+
+#[test]
+fn test_function_names_ignore_case() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Sales".to_string(),
+            vec![Value::Number(100.0), Value::Number(200.0)],
+        )
+        .unwrap();
+
+    for (expr, expected) in [
+        ("Sum([Sales])", 300.0),
+        ("AVERAGE([Sales])", 150.0),
+        ("aVeRaGe([Sales])", 150.0),
+        ("calculate(sum([Sales]), keepFilters([Sales] > 150))", 200.0),
+    ] {
+        match table.evaluate_dax(expr) {
+            Ok(DaxResult::Number(n)) => assert_eq!(n, expected, "{}", expr),
+            other => panic!("Expected number result for {}, got {:?}", expr, other),
+        }
+    }
+    // Column names stay case-sensitive
+    assert!(table.evaluate_dax("SUM([sales])").is_err());
+    match table.evaluate_dax("Avrage([Sales])") {
+        Err(e @ DaxError::UnknownFunction { .. }) => {
+            assert!(e.to_string().contains("Unsupported function: Avrage"))
+        }
+        other => panic!("Expected error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_comments_are_dropped() {
+    let commented = "VAR Total = SUM([Sales]) // all rows\n/* the share\n   of the total */\nRETURN DIVIDE(Total, 2)";
+    let stripped = "VAR Total = SUM([Sales]) \n\nRETURN DIVIDE(Total, 2)";
+    assert_eq!(
+        format!("{:?}", dax_macro_impl::try_tokenize(commented).unwrap()),
+        format!("{:?}", dax_macro_impl::try_tokenize(stripped).unwrap())
+    );
+
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Sales".to_string(),
+            vec![Value::Number(100.0), Value::Number(200.0)],
+        )
+        .unwrap();
+    match table.evaluate_dax("SUM([Sales]) /* halved */ / 2 // done") {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 150.0),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+    match table.evaluate_dax("COMBINEVALUES(\"//\", \"a\", \"/*b*/\")") {
+        Ok(DaxResult::Text(s)) => assert_eq!(s, "a///*b*/"),
+        other => panic!("Expected text result, got {:?}", other),
+    }
+    match table.evaluate_dax("SUM([Sales]) /* never closed") {
+        Err(DaxError::ParseError(e)) => assert!(e.contains("Unterminated block comment"), "{}", e),
+        other => panic!("Expected parse error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_identifiers_with_digits_and_underscores() {
+    let tokens = dax_macro_impl::try_tokenize("LOG10(100)").unwrap();
+    assert!(matches!(&tokens[0], DaxToken::Function(name) if name == "LOG10"));
+    assert!(matches!(tokens[2], DaxToken::Number(n) if n == 100.0));
+
+    let tokens = dax_macro_impl::try_tokenize("Sales_2024[Amount] + NORM.S.INV(0.5)").unwrap();
+    assert!(
+        matches!(&tokens[0], DaxToken::QualifiedColumn(table, column)
+        if table == "Sales_2024" && column == "Amount")
+    );
+    assert_eq!(tokens[0].to_string(), "Sales_2024[Amount]");
+    assert!(matches!(&tokens[4], DaxToken::Function(name) if name == "NORM.S.INV"));
+
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Amount".to_string(),
+            vec![Value::Number(5.0), Value::Number(7.0)],
+        )
+        .unwrap();
+    match table.evaluate_dax("COUNTROWS(FILTER(Sales2024, Sales2024[Amount] > 6))") {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 1.0),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_errors_are_typed() {
+    let mut table = Table::new();
+    table
+        .add_column("Sales".to_string(), vec![Value::Number(1.0)])
+        .unwrap();
+
+    assert!(matches!(
+        table.evaluate_dax("SUM([Missing])"),
+        Err(DaxError::UnknownColumn { name, .. }) if name == "Missing"
+    ));
+    assert!(matches!(
+        table.evaluate_dax("NOPE([Sales])"),
+        Err(DaxError::UnknownFunction { .. })
+    ));
+    assert!(matches!(
+        table.evaluate_dax("SUM([Sales]"),
+        Err(DaxError::ParseError(_))
+    ));
+    assert!(matches!(
+        table.evaluate_dax("CALCULATE(SUM([Sales]), FALSE())"),
+        Ok(DaxResult::Blank)
+    ));
+}
+
+#[test]
+fn test_errors_point_into_the_expression() {
+    let mut table = Table::new();
+    table
+        .add_column("Sales".to_string(), vec![Value::Number(1.0)])
+        .unwrap();
+    table
+        .add_column("Quantity".to_string(), vec![Value::Integer(2)])
+        .unwrap();
+
+    match table.evaluate_dax("COUNTROWS(FILTERX(Sales, [Quantity] > 1))") {
+        Err(e @ DaxError::UnknownFunction { .. }) => assert_eq!(
+            e.to_string(),
+            "Evaluation error: Unsupported function: FILTERX\n  \
+             COUNTROWS(FILTERX(Sales, [Quantity] > 1))\n            ^^^^^^^"
+        ),
+        other => panic!("Expected unknown function, got {:?}", other),
+    }
+
+    match table.evaluate_dax("SUM([Sales]) + SUM([Quantiy])") {
+        Err(DaxError::UnknownColumn {
+            name,
+            span: Some(span),
+            suggestions,
+        }) => {
+            assert_eq!(name, "Quantiy");
+            assert_eq!((span.start, span.end), (19, 28));
+            assert_eq!(suggestions, vec!["Quantity".to_string()]);
+        }
+        other => panic!("Expected unknown column, got {:?}", other),
+    }
+
+    match table.evaluate_dax("SUM([Sales]) + DIVIDE(1)") {
+        Err(DaxError::WrongArity {
+            function,
+            got,
+            span: Some(span),
+            ..
+        }) => {
+            assert_eq!((function.as_str(), got), ("DIVIDE", 1));
+            assert_eq!((span.start, span.end), (15, 24));
+        }
+        other => panic!("Expected wrong arity, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_compiled_expressions() {
+    let mut table = Table::new();
+    table
+        .add_column("Sales".to_string(), vec![10.0.into(), 20.0.into()])
+        .unwrap();
+    let total = table.compile("SUM([Sales]) * 2").unwrap();
+    assert_eq!(total.columns(), ["Sales".to_string()]);
+
+    // The same compiled expression follows the table as it's refreshed
+    for refresh in 1..=3 {
+        let mut refreshed = Table::new();
+        refreshed
+            .add_column(
+                "Sales".to_string(),
+                vec![Value::Number(refresh as f64); refresh],
+            )
+            .unwrap();
+        match total.evaluate(&refreshed) {
+            Ok(DaxResult::Number(n)) => assert_eq!(n, (2 * refresh * refresh) as f64),
+            other => panic!("Expected number result, got {:?}", other),
+        }
+    }
+
+    // Columns the expression builds itself don't have to be in the table
+    let doubled = table
+        .compile("COUNTROWS(FILTER(ADDCOLUMNS(Sales, \"Double\", [Sales] * 2), [Double] > 30))")
+        .unwrap();
+    assert_eq!(doubled.columns(), ["Sales".to_string()]);
+    assert!(matches!(doubled.evaluate(&table), Ok(DaxResult::Number(n)) if n == 1.0));
+    assert!(table
+        .compile("COUNTROWS(FILTER({1, 2, 3}, [Value] > 1))")
+        .is_ok());
+}
+
+#[test]
+fn test_compiled_expressions_check_columns() {
+    let mut table = Table::new();
+    table
+        .add_column("Quantity".to_string(), vec![1.0.into()])
+        .unwrap();
+
+    // Caught when compiling, before anything is evaluated
+    match table.compile("IF(FALSE, SUM([Quantiy]), 0)") {
+        Err(DaxError::UnknownColumn {
+            name,
+            span: Some(span),
+            suggestions,
+        }) => {
+            assert_eq!(name, "Quantiy");
+            assert_eq!((span.start, span.end), (14, 23));
+            assert_eq!(suggestions, vec!["Quantity".to_string()]);
+        }
+        other => panic!("Expected unknown column, got {:?}", other),
+    }
+
+    // And when evaluating against a table without the column
+    let compiled = table.compile("SUM([Quantity])").unwrap();
+    let mut other = Table::new();
+    other
+        .add_column("Amount".to_string(), vec![1.0.into()])
+        .unwrap();
+    match compiled.evaluate(&other) {
+        Err(e @ DaxError::UnknownColumn { .. }) => {
+            assert!(e.to_string().contains("Column Quantity not found"));
+            assert_eq!(e.span().map(|s| (s.start, s.end)), Some((4, 14)));
+        }
+        other => panic!("Expected unknown column, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_registered_functions() {
+    let mut registry = FunctionRegistry::new();
+    // Quarter of a fiscal year starting in July
+    registry
+        .register("FiscalQuarter", 1..=1, |args| match &args[0] {
+            DaxResult::DateTime(d) => Ok(DaxResult::Number(((d.month() + 5) % 12 / 3 + 1) as f64)),
+            _ => Err("FISCALQUARTER expects a date".into()),
+        })
+        .unwrap();
+    registry
+        .register("SCORE", 2..=2, |args| match (&args[0], &args[1]) {
+            (DaxResult::Number(a), DaxResult::Number(b)) => Ok(DaxResult::Number(a * 10.0 + b)),
+            _ => Err("SCORE expects numbers".into()),
+        })
+        .unwrap();
+    assert!(registry.contains("fiscalquarter") && registry.contains("SUM"));
+
+    let mut table = Table::new();
+    let date = |y, m, d| {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+    };
+    table
+        .add_column(
+            "Date".to_string(),
+            vec![
+                Value::DateTime(date(2024, 1, 15)),
+                Value::DateTime(date(2024, 7, 1)),
+                Value::DateTime(date(2024, 11, 30)),
+            ],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Sales".to_string(),
+            vec![1.0.into(), 2.0.into(), 3.0.into()],
+        )
+        .unwrap();
+
+    // Called per row inside SUMX, and nested in another registered function
+    match table.evaluate_dax_with(
+        &registry,
+        "SUMX(Sales, SCORE(fiscalquarter([Date]), [Sales]))",
+    ) {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 31.0 + 12.0 + 23.0),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+    assert!(matches!(
+        table.evaluate_dax_with(&registry, "SCORE(1)"),
+        Err(DaxError::WrongArity { got: 1, .. })
+    ));
+    // Without the registry the function doesn't exist
+    assert!(matches!(
+        table.evaluate_dax("SUMX(Sales, FISCALQUARTER([Date]))"),
+        Err(DaxError::UnknownFunction { .. })
+    ));
+
+    let compiled = table.compile("SUMX(Sales, FISCALQUARTER([Date]))").unwrap();
+    assert!(
+        matches!(compiled.evaluate_with(&table, &registry), Ok(DaxResult::Number(n)) if n == 6.0)
+    );
+}
+
+#[test]
+fn test_builtin_functions_need_an_override() {
+    let mut registry = FunctionRegistry::new();
+    // Division by zero as zero rather than blank
+    let divide = |args: &[DaxResult]| match (&args[0], &args[1]) {
+        (_, DaxResult::Number(d)) if *d == 0.0 => Ok(DaxResult::Number(0.0)),
+        (DaxResult::Number(n), DaxResult::Number(d)) => Ok(DaxResult::Number(n / d)),
+        _ => Err("DIVIDE expects numbers".into()),
+    };
+    assert!(registry.register("divide", 2..=2, divide).is_err());
+
+    registry
+        .allow_overrides(true)
+        .register("DIVIDE", 2..=2, divide)
+        .unwrap();
+    let table = Table::new();
+    assert!(matches!(
+        table.evaluate_dax_with(&registry, "DIVIDE(1, 0)"),
+        Ok(DaxResult::Number(n)) if n == 0.0
+    ));
+    assert!(matches!(
+        table.evaluate_dax("DIVIDE(1, 0)"),
+        Ok(DaxResult::Blank)
+    ));
+}
+
+#[test]
+fn test_measures() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Region".to_string(),
+            vec!["North".into(), "South".into(), "North".into()],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Sales".to_string(),
+            vec![100.0.into(), 200.0.into(), 300.0.into()],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Cost".to_string(),
+            vec![60.0.into(), 150.0.into(), 90.0.into()],
+        )
+        .unwrap();
+    table.define_measure("Total Sales", "SUM([Sales])").unwrap();
+    table
+        .define_measure(
+            "Margin",
+            "DIVIDE([Total Sales] - SUM([Cost]), [Total Sales])",
+        )
+        .unwrap();
+
+    match table.evaluate_dax("[Total Sales] * 1.1") {
+        Ok(DaxResult::Number(n)) => assert!((n - 660.0).abs() < 1e-9),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+    match table.evaluate_dax("[Margin]") {
+        Ok(DaxResult::Number(n)) => assert!((n - 0.5).abs() < 1e-9),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+    // Filters reach into the measures, and a row context becomes a filter
+    match table.evaluate_dax("CALCULATE([Margin], [Region] = \"North\")") {
+        Ok(DaxResult::Number(n)) => assert!((n - 0.625).abs() < 1e-9),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+    match table.evaluate_dax("SUMX(Sales, [Total Sales])") {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 600.0),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+
+    // Redefining replaces the old definition, and columns win over measures
+    table.define_measure("Total Sales", "SUM([Cost])").unwrap();
+    assert_eq!(table.get_measure("Total Sales"), Some("SUM([Cost])"));
+    assert!(matches!(table.evaluate_dax("[Total Sales]"), Ok(DaxResult::Number(n)) if n == 300.0));
+    table.define_measure("Sales", "1").unwrap();
+    assert!(
+        matches!(table.evaluate_dax("SUMX(Sales, [Sales])"), Ok(DaxResult::Number(n)) if n == 600.0)
+    );
+    assert!(table.define_measure("Broken", "SUM([Sales]").is_err());
+}
+
+#[test]
+fn test_measure_cycles() {
+    let mut table = Table::new();
+    table
+        .add_column("Sales".to_string(), vec![1.0.into()])
+        .unwrap();
+    table.define_measure("A", "[B] + 1").unwrap();
+    table.define_measure("B", "[C] * 2").unwrap();
+    table.define_measure("C", "[A]").unwrap();
+    table
+        .define_measure("Uses A", "SUM([Sales]) + [A]")
+        .unwrap();
+
+    match table.evaluate_dax("[Uses A]") {
+        Err(e @ DaxError::CircularMeasure { .. }) => {
+            assert!(e
+                .to_string()
+                .contains("Measures refer to each other in a cycle: A -> B -> C -> A"));
+        }
+        other => panic!("Expected a measure cycle, got {:?}", other),
+    }
+    table.define_measure("Self", "[Self]").unwrap();
+    assert!(matches!(
+        table.evaluate_dax("[Self]"),
+        Err(DaxError::CircularMeasure { cycle }) if cycle == ["Self", "Self"]
+    ));
+}
+
+#[test]
+fn test_explain_dax() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Product".to_string(),
+            vec!["Apple".into(), "Pear".into(), "Apple".into(), "Plum".into()],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Sales".to_string(),
+            vec![10.0.into(), 5.0.into(), 20.0.into(), 8.0.into()],
+        )
+        .unwrap();
+    table.define_measure("Total", "SUM([Sales])").unwrap();
+
+    let trace = table
+        .explain_dax("CALCULATE(SUM([Sales]), [Product] = \"Apple\") / [Total]")
+        .unwrap();
+    assert!(matches!(trace.result, DaxResult::Number(n) if (n - 30.0 / 43.0).abs() < 1e-12));
+
+    // The narrowed row count and partial sum show up under CALCULATE
+    let calculate = &trace.root.children[0];
+    assert_eq!(calculate.result, "30");
+    let sum = &calculate.children[0];
+    assert_eq!(sum.expression, "SUM([Sales])");
+    assert_eq!(sum.result, "30");
+    assert_eq!((sum.visible_rows, sum.total_rows), (2, 4));
+    assert_eq!(sum.filters, vec!["[Product] in {\"Apple\"}".to_string()]);
+
+    let expected = "\
+CALCULATE(SUM([Sales]), [Product] = \"Apple\") / [Total] = 0.6976744186046512 (4 of 4 rows)
+  CALCULATE(SUM([Sales]), [Product] = \"Apple\") = 30 (4 of 4 rows)
+    SUM([Sales]) = 30 (2 of 4 rows, [Product] in {\"Apple\"})
+  [Total] = 43 (4 of 4 rows)
+    SUM([Sales]) = 43 (4 of 4 rows)
+";
+    assert_eq!(trace.to_string(), expected);
+
+    // Tables are summarized by their size
+    let trace = table
+        .explain_dax("COUNTROWS(FILTER(Sales, [Sales] > 6))")
+        .unwrap();
+    assert_eq!(trace.root.children[0].result, "Table: 3 rows");
+    assert!(table.explain_dax("SUM([Nope])").is_err());
+}
+
+#[test]
+fn test_stray_characters_are_reported() {
+    use dax_macro_impl::try_tokenize;
+
+    assert_eq!(
+        try_tokenize("SUM([Sales]) $ 2").err().as_deref(),
+        Some("Unexpected character '$' at character 13")
+    );
+    assert_eq!(
+        try_tokenize("SUM([Sales").err().as_deref(),
+        Some("Unterminated column name starting at character 4")
+    );
+    assert_eq!(
+        try_tokenize("1.2.3").err().as_deref(),
+        Some("Invalid number '1.2.3' at character 0")
+    );
+    // The deprecated lossy tokenizer still skips what it can't read, where
+    // the evaluator's doesn't
+    #[allow(deprecated)]
+    let lossy = dax_macro_impl::tokenize("1 $");
+    assert_eq!(lossy.len(), 2);
+    match dax_rust::parser::tokenize("SUM([Sales]) @ 2") {
+        Err(DaxError::ParseError(e)) => {
+            assert_eq!(e, "Unexpected character '@' at character 13")
+        }
+        other => panic!("Expected parse error, got {:?}", other),
+    }
+    assert_eq!(dax_rust::parser::tokenize("1 + 2").unwrap().len(), 5);
+
+    let mut table = Table::new();
+    table
+        .add_column("Sales".to_string(), vec![1.0.into()])
+        .unwrap();
+    match table.evaluate_dax("SUM([Sales]) # 2") {
+        Err(DaxError::ParseError(e)) => assert!(e.contains("Unexpected character '#'"), "{}", e),
+        other => panic!("Expected parse error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_parse_dax_keeps_names_and_text_as_written() {
+    let tokens = dax_macro::parse_dax!(r#"[Size "L"] + 'C:\Data'[Größe] + "say ""hi"" \n""#);
+    let tokens: Vec<&DaxToken> = tokens
+        .iter()
+        .filter(|token| !matches!(token, DaxToken::Whitespace))
+        .collect();
+    assert!(matches!(tokens[0], DaxToken::Column(name) if name == "Size \"L\""));
+    assert!(matches!(tokens[2], DaxToken::QualifiedColumn(table, column)
+        if table == "C:\\Data" && column == "Größe"));
+    assert!(matches!(tokens[4], DaxToken::Text(text) if text == "say \"hi\" \\n"));
+
+    let tokens = dax_macro::parse_dax!("1e999 > 2.5");
+    assert!(matches!(tokens[0], DaxToken::Number(n) if n == f64::INFINITY));
+    assert!(matches!(tokens[4], DaxToken::Number(n) if n == 2.5));
+}
+
+#[test]
+fn test_percent_and_power() {
+    let tokens = dax_macro::parse_dax!("50% * 2^10");
+    let operators: Vec<String> = tokens
+        .iter()
+        .filter(|t| matches!(t, DaxToken::Operator(_)))
+        .map(|t| t.to_string())
+        .collect();
+    assert_eq!(operators, vec!["%", "*", "^"]);
+
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Rate".to_string(),
+            vec![Value::Number(20.0), Value::Number(30.0)],
+        )
+        .unwrap();
+    for (expr, expected) in [
+        ("50%", 0.5),
+        ("-50%", -0.5),
+        ("2^10", 1024.0),
+        ("2^3^2", 512.0),
+        ("-2^2", -4.0),
+        ("2^-1", 0.5),
+        ("200 * 15%", 30.0),
+        ("10%^2", 0.01),
+        ("SUM([Rate])%", 0.5),
+    ] {
+        match table.evaluate_dax(expr) {
+            Ok(DaxResult::Number(n)) => assert!((n - expected).abs() < 1e-12, "{}: {}", expr, n),
+            other => panic!("Expected number result for {}, got {:?}", expr, other),
+        }
+    }
+    assert!(matches!(
+        table.evaluate_dax("%50"),
+        Err(DaxError::ParseError(_))
+    ));
+}
+
+#[test]
+fn test_table_qualifiers_must_name_the_table() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "A".to_string(),
+            vec![Value::Number(1.0), Value::Number(2.0)],
+        )
+        .unwrap();
+    // A table without a name answers to any
+    assert_eq!(table.name(), None);
+    assert!(matches!(table.evaluate_dax("SUM(Other[A])"), Ok(DaxResult::Number(n)) if n == 3.0));
+
+    table.set_name("Sales".to_string());
+    assert_eq!(table.name(), Some("Sales"));
+    for expr in [
+        "SUM(Sales[A])",
+        "SUM(sales[A])",
+        "SUM('Sales'[A])",
+        "SUMX(FILTER(Sales, Sales[A] > 0), Sales[A])",
+    ] {
+        assert!(
+            matches!(table.evaluate_dax(expr), Ok(DaxResult::Number(n)) if n == 3.0),
+            "{}",
+            expr
+        );
+    }
+    match table.evaluate_dax("SUM(Other[A])") {
+        Err(DaxError::UnknownTable {
+            name,
+            span: Some(span),
+        }) => {
+            assert_eq!(name, "Other");
+            assert_eq!((span.start, span.end), (4, 12));
+        }
+        other => panic!("Expected an unknown table, got {:?}", other),
+    }
+    assert!(matches!(
+        table.evaluate_dax("SUMX(Sales, Other[A] * 2)"),
+        Err(DaxError::UnknownTable { .. })
+    ));
+    assert!(matches!(
+        table
+            .clone()
+            .add_calculated_column("B".to_string(), "Other[A] + 1"),
+        Err(DaxError::UnknownTable { .. })
+    ));
+    table.define_measure("Bad", "SUM(Other[A])").unwrap();
+    assert!(matches!(
+        table.evaluate_dax("[Bad] + 1"),
+        Err(DaxError::UnknownTable { .. })
+    ));
+    assert!(matches!(
+        table.execute_query("EVALUATE Sales ORDER BY Other[A]"),
+        Err(DaxError::UnknownTable { .. })
+    ));
+    assert!(matches!(
+        table.compile("SUM(Other[A])"),
+        Err(DaxError::UnknownTable { .. })
+    ));
+}
+
+#[test]
+fn test_bare_names_must_name_the_table() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "A".to_string(),
+            vec![Value::Number(1.0), Value::Number(2.0)],
+        )
+        .unwrap();
+    table.set_name("Sales".to_string());
+    for (expr, expected) in [
+        ("COUNTROWS(Sales)", 2.0),
+        ("COUNTROWS(sales)", 2.0),
+        ("CALCULATE(COUNTROWS(Sales), REMOVEFILTERS(Sales))", 2.0),
+        (
+            "VAR Big = FILTER(Sales, [A] > 1) RETURN COUNTROWS(Big)",
+            1.0,
+        ),
+        ("IF(TRUE, 1, 0)", 1.0),
+    ] {
+        match table.evaluate_dax(expr) {
+            Ok(DaxResult::Number(n)) => assert_eq!(n, expected, "{}", expr),
+            Ok(DaxResult::Integer(i)) => assert_eq!(i as f64, expected, "{}", expr),
+            other => panic!("Expected number result for {}, got {:?}", expr, other),
+        }
+    }
+    for expr in [
+        "COUNTROWS(Typo)",
+        "SUMX(Sale, [A])",
+        "VAR x = 1 RETURN COUNTROWS(y)",
+    ] {
+        match table.evaluate_dax(expr) {
+            Err(DaxError::UnknownTable { .. }) => {}
+            other => panic!("Expected an unknown table for {}, got {:?}", expr, other),
+        }
+    }
+    assert_eq!(
+        table
+            .evaluate_dax("COUNTROWS(Typo)")
+            .unwrap_err()
+            .to_string(),
+        "Evaluation error: Table Typo not found"
+    );
+}
+
+#[test]
+fn test_var_return_blocks() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Rate".to_string(),
+            vec![Value::Number(20.0), Value::Number(30.0)],
+        )
+        .unwrap();
+    for (expr, expected) in [
+        ("VAR x = 2 RETURN x*3", 6.0),
+        (
+            "var _total = SUM([Rate]) VAR half = _total / 2 RETURN half + 1",
+            26.0,
+        ),
+        // A variable is evaluated where it's defined, not where it's used
+        (
+            "VAR all = SUM([Rate]) RETURN CALCULATE(all, [Rate] > 25)",
+            50.0,
+        ),
+        ("CALCULATE(VAR s = SUM([Rate]) RETURN s, [Rate] > 25)", 30.0),
+        ("SUMX({20, 30}, VAR r = [Value] RETURN r * 2)", 100.0),
+        (
+            "VAR big = FILTER({20, 30}, [Value] > 25) RETURN COUNTROWS(big)",
+            1.0,
+        ),
+        ("VAR x = 1 RETURN VAR y = x + 1 RETURN x + y", 3.0),
+        ("VAR x = 1 RETURN VAR X = 5 RETURN x", 5.0),
+        ("1 + (VAR x = 2 RETURN x) * 3", 7.0),
+    ] {
+        match table.evaluate_dax(expr) {
+            Ok(DaxResult::Number(n)) => assert_eq!(n, expected, "{}", expr),
+            Ok(DaxResult::Integer(i)) => assert_eq!(i as f64, expected, "{}", expr),
+            other => panic!("Expected number result for {}, got {:?}", expr, other),
+        }
+    }
+    for (expr, message) in [
+        ("VAR x 2 RETURN x", "Expected '=' after VAR x"),
+        ("VAR x = 2", "Expected RETURN"),
+        ("VAR = 2 RETURN 1", "VAR must be followed by a name"),
+        ("VAR RETURN 1", "VAR must be followed by a name"),
+    ] {
+        match table.evaluate_dax(expr) {
+            Err(DaxError::ParseError(e)) => assert!(e.contains(message), "{}: {}", expr, e),
+            other => panic!("Expected parse error for {}, got {:?}", expr, other),
+        }
+    }
+}
+
+#[test]
+fn test_row_context_resolution() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Price".to_string(),
+            vec![Value::Number(2.0), Value::Number(3.0), Value::Null],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Quantity".to_string(),
+            [10.0, 20.0, 5.0].map(Value::Number).to_vec(),
+        )
+        .unwrap();
+
+    // Inside an iterator [Price] is the current row's cell, outside it's the column
+    for (expr, expected) in [
+        ("SUMX(Sales, [Price] * [Quantity])", 80.0),
+        ("SUM([Price])", 5.0),
+        // An outer row's column is still visible from an inner iterator
+        (
+            "SUMX(Sales, SUMX(SUMMARIZE(Sales, [Quantity]), [Quantity] * [Price]))",
+            175.0,
+        ),
+        // The innermost row that has a column wins
+        (
+            "SUMX(ADDCOLUMNS(Sales, \"Limit\", [Quantity]), \
+             COUNTROWS(FILTER(SUMMARIZE(Sales, [Quantity]), [Quantity] <= [Limit])))",
+            6.0,
+        ),
+    ] {
+        match table.evaluate_dax(expr) {
+            Ok(DaxResult::Number(n)) => assert_eq!(n, expected, "{}", expr),
+            other => panic!("Expected number result for {}, got {:?}", expr, other),
+        }
+    }
+    assert!(table.evaluate_dax("[Price] * 2").is_err());
+
+    // An empty cell in row context is blank
+    match table.evaluate_dax("ADDCOLUMNS(Sales, \"Copy\", [Price])") {
+        Ok(DaxResult::Table(result)) => assert_eq!(
+            result.get_column("Copy").unwrap(),
+            vec![Value::Number(2.0), Value::Number(3.0), Value::Null]
+        ),
+        other => panic!("Expected a table, got {:?}", other),
+    }
+    assert!(matches!(
+        table.evaluate_dax("SUMX(FILTER(Sales, [Quantity] < 10), [Price])"),
+        Ok(DaxResult::Blank)
+    ));
+    assert!(matches!(
+        table.evaluate_dax("SUMX(SUMMARIZE(Sales, [Quantity]), [Missing])"),
+        Err(DaxError::UnknownColumn { .. })
+    ));
+}
+
+#[test]
+fn test_row_oriented_construction() {
+    let record = |pairs: &[(&str, Value)]| -> HashMap<String, Value> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect()
+    };
+    let mut table = Table::from_rows([
+        record(&[("Product", "Apple".into()), ("Sales", Value::Number(10.0))]),
+        record(&[("Product", "Pear".into())]),
+    ]);
+    table.add_row(record(&[
+        ("Sales", Value::Number(5.0)),
+        ("Region", "North".into()),
+    ]));
+
+    assert_eq!(
+        table.get_column("Product").unwrap(),
+        vec!["Apple".into(), "Pear".into(), Value::Null]
+    );
+    assert_eq!(
+        table.get_column("Sales").unwrap(),
+        vec![Value::Number(10.0), Value::Null, Value::Number(5.0)]
+    );
+    assert_eq!(
+        table.get_column("Region").unwrap(),
+        vec![Value::Null, Value::Null, "North".into()]
+    );
+    // New columns follow the existing ones by name, whatever the map's order
+    assert_eq!(table.column_names(), ["Product", "Sales", "Region"]);
+    for _ in 0..10 {
+        let mut copy = table.clone();
+        copy.add_row(record(&[
+            ("Zone", 1.into()),
+            ("Area", 2.into()),
+            ("Month", 3.into()),
+            ("Sales", 4.into()),
+        ]));
+        assert_eq!(
+            copy.column_names(),
+            ["Product", "Sales", "Region", "Area", "Month", "Zone"]
+        );
+    }
+    assert!(matches!(
+        table.evaluate_dax("SUM([Sales])"),
+        Ok(DaxResult::Number(n)) if n == 15.0
+    ));
+
+    let row = table.row(2).unwrap();
+    assert_eq!(row.len(), 3);
+    assert_eq!(row["Region"], Value::from("North"));
+    assert_eq!(row["Product"], Value::Null);
+    assert!(table.row(3).is_none());
+    assert!(Table::from_rows(Vec::new()).row(0).is_none());
+}
+
+#[test]
+fn test_columns_must_have_equal_lengths() {
+    let mut table = Table::new();
+    assert_eq!(table.shape(), (0, 0));
+    table
+        .add_column("A".to_string(), [1.0, 2.0, 3.0].map(Value::Number).to_vec())
+        .unwrap();
+    match table.add_column("B".to_string(), vec![Value::Number(1.0)]) {
+        Err(DaxError::ColumnLength {
+            column,
+            expected,
+            got,
+        }) => assert_eq!((column.as_str(), expected, got), ("B", 3, 1)),
+        other => panic!("Expected a length mismatch, got {:?}", other),
+    }
+    assert!(table.get_column("B").is_none());
+    assert_eq!(table.shape(), (3, 1));
+
+    // The only column can be replaced by one of any length
+    table
+        .add_column("A".to_string(), vec![Value::Number(1.0)])
+        .unwrap();
+    assert_eq!((table.num_rows(), table.num_columns()), (1, 1));
+
+    // Padding fills whichever side is shorter
+    table.add_column_padded("B".to_string(), vec![Value::Null, Value::Number(2.0)]);
+    assert_eq!(
+        table.get_column("A").unwrap(),
+        vec![Value::Number(1.0), Value::Null]
+    );
+    table.add_column_padded("C".to_string(), vec![Value::from("x")]);
+    assert_eq!(
+        table.get_column("C").unwrap(),
+        vec!["x".into(), Value::Null]
+    );
+    assert_eq!(table.shape(), (2, 3));
+    assert!(matches!(
+        table.evaluate_dax("COUNTROWS(Sales)"),
+        Ok(DaxResult::Number(n)) if n == 2.0
+    ));
+}
+
+#[test]
+fn test_columns_keep_their_order() {
+    let mut table = Table::new();
+    for name in ["Sales", "Discount", "Quantity"] {
+        table
+            .add_column(name.to_string(), vec![Value::Integer(1)])
+            .unwrap();
+    }
+    // Replacing a column keeps its place
+    table
+        .add_column("Discount".to_string(), vec![Value::Integer(2)])
+        .unwrap();
+    assert_eq!(table.column_names(), ["Sales", "Discount", "Quantity"]);
+    let header = table.to_string().lines().nth(1).unwrap().to_string();
+    let names: Vec<&str> = header.split('│').map(str::trim).collect();
+    assert_eq!(names, ["Sales", "Discount", "Quantity"]);
+
+    // A filtered table keeps the order too
+    match table.evaluate_dax("FILTER(Sales, [Sales] = 1)") {
+        Ok(DaxResult::Table(filtered)) => {
+            assert_eq!(filtered.column_names(), table.column_names())
+        }
+        other => panic!("Expected a table, got {:?}", other),
+    }
+
+    let path = std::env::temp_dir().join("dax_rust_column_order_test.csv");
+    std::fs::write(&path, "Zone,Amount,Code\nNorth,1,A\n").unwrap();
+    let table = dax_rust::io::read_csv(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(table.column_names(), ["Zone", "Amount", "Code"]);
+    assert!(table.to_string().contains(" Zone  │ Amount │ Code "));
+}
+
+#[test]
+fn test_remove_rename_and_reorder_columns() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Amount".to_string(),
+            [1.0, 2.0, 3.0].map(Value::Number).to_vec(),
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Qty".to_string(),
+            [4.0, 5.0, 6.0].map(Value::Number).to_vec(),
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Note".to_string(),
+            ["a", "b", "c"].map(Value::from).to_vec(),
+        )
+        .unwrap();
+
+    table.rename_column("Amount", "Sales").unwrap();
+    assert_eq!(table.column_names(), ["Sales", "Qty", "Note"]);
+    assert_eq!(table.sum("Sales"), Some(6.0));
+    assert_eq!(table.sum("Amount"), None);
+    assert!(matches!(
+        table.evaluate_dax("SUMX(Sales, [Sales] * [Qty])"),
+        Ok(DaxResult::Number(n)) if n == 32.0
+    ));
+    assert!(table.evaluate_dax("SUM([Amount])").is_err());
+    assert!(table.rename_column("Sales", "Qty").is_err());
+    assert!(matches!(
+        table.rename_column("Missing", "Other"),
+        Err(DaxError::UnknownColumn { .. })
+    ));
+    assert_eq!(table.column_names(), ["Sales", "Qty", "Note"]);
+
+    table.reorder_columns(&["Note", "Sales"]).unwrap();
+    assert_eq!(table.column_names(), ["Note", "Sales", "Qty"]);
+    assert!(table
+        .to_string()
+        .lines()
+        .nth(1)
+        .unwrap()
+        .trim_start()
+        .starts_with("Note"));
+    assert!(table.reorder_columns(&["Qty", "Qty"]).is_err());
+    assert!(table.reorder_columns(&["Nope"]).is_err());
+    assert_eq!(table.column_names(), ["Note", "Sales", "Qty"]);
+
+    assert_eq!(
+        table.remove_column("Qty"),
+        Some([4.0, 5.0, 6.0].map(Value::Number).to_vec())
+    );
+    assert_eq!(table.remove_column("Qty"), None);
+    assert_eq!(table.column_names(), ["Note", "Sales"]);
+    assert_eq!(table.average("Sales"), Some(2.0));
+    assert!(!table.to_string().contains("Qty"));
+    assert!(table.evaluate_dax("SUM([Qty])").is_err());
+}
+
+#[test]
+fn test_get_and_set_cells() {
+    let path = std::env::temp_dir().join("dax_rust_patch_cells_test.csv");
+    std::fs::write(&path, "Product,Sales\nApple,10\nPear,-999\nPlum,5\n").unwrap();
+    let mut table = dax_rust::io::read_csv(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(matches!(
+        table.evaluate_dax("SUM([Sales])"),
+        Ok(DaxResult::Integer(-984))
+    ));
+    assert_eq!(table.get("Sales", 1), Some(Value::Integer(-999)));
+    assert_eq!(table[("Product", 1)], Value::from("Pear"));
+
+    table.set("Sales", 1, Value::Integer(20)).unwrap();
+    assert_eq!(table[("Sales", 1)], Value::Integer(20));
+    assert!(matches!(
+        table.evaluate_dax("SUM([Sales])"),
+        Ok(DaxResult::Integer(35))
+    ));
+
+    assert_eq!(table.get("Sales", 3), None);
+    assert_eq!(table.get("Cost", 0), None);
+    match table.set("Sales", 3, Value::Null) {
+        Err(DaxError::EvaluationError(e)) => assert!(e.contains("Row 3 is out of range"), "{}", e),
+        other => panic!("Expected a range error, got {:?}", other),
+    }
+    assert!(matches!(
+        table.set("Sale", 0, Value::Null),
+        Err(DaxError::UnknownColumn { .. })
+    ));
+}
+
+#[test]
+#[should_panic(expected = "Column Cost not found")]
+fn test_indexing_a_missing_column_panics() {
+    let mut table = Table::new();
+    table
+        .add_column("Sales".to_string(), vec![Value::Number(1.0)])
+        .unwrap();
+    let _ = &table[("Cost", 0)];
+}
+
+#[test]
+fn test_row_iteration() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Product".to_string(),
+            ["Apple", "Pear"].map(Value::from).to_vec(),
+        )
+        .unwrap();
+    table
+        .add_column("Sales".to_string(), vec![Value::Number(10.0), Value::Null])
+        .unwrap();
+
+    let rows: Vec<_> = table.rows().collect();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[1].index(), 1);
+    assert_eq!(rows[0].get("Product"), Some(Value::from("Apple")));
+    assert_eq!(rows[1].get("Sales"), Some(Value::Null));
+    assert_eq!(rows[0].get("Cost"), None);
+    assert_eq!(
+        format!("{:?}", rows[0]),
+        "{\"Product\": Text(\"Apple\"), \"Sales\": Number(10.0)}"
+    );
+    let blanks = table
+        .rows()
+        .filter(|row| row.get("Sales") == Some(Value::Null))
+        .count();
+    assert_eq!(blanks, 1);
+
+    let owned: Vec<HashMap<String, Value>> = table.into_rows().collect();
+    assert_eq!(owned[1]["Product"], Value::from("Pear"));
+    let rebuilt = Table::from_rows(owned);
+    assert_eq!(rebuilt.shape(), (2, 2));
+    assert_eq!(rebuilt.get("Sales", 0), Some(Value::Number(10.0)));
+    assert_eq!(Table::new().rows().count(), 0);
+}
+
+#[test]
+fn test_filter_rows_and_select() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Product".to_string(),
+            ["Apple", "Pear", "Apricot"].map(Value::from).to_vec(),
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Region".to_string(),
+            ["N", "S", "N"].map(Value::from).to_vec(),
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Sales".to_string(),
+            vec![Value::Integer(10), Value::Integer(20), Value::Integer(5)],
+        )
+        .unwrap();
+
+    let a_products = table.filter_rows(
+        |row| matches!(row.get("Product"), Some(Value::Text(s)) if s.starts_with('A')),
+    );
+    assert_eq!(a_products.column_names(), ["Product", "Region", "Sales"]);
+    assert_eq!(a_products.num_rows(), 2);
+
+    let projected = a_products.select(&["Sales", "Product"]).unwrap();
+    assert_eq!(projected.column_names(), ["Sales", "Product"]);
+    assert_eq!(
+        projected.get_column("Sales").unwrap(),
+        vec![Value::Integer(10), Value::Integer(5)]
+    );
+    assert!(matches!(
+        projected.evaluate_dax("SUM([Sales])"),
+        Ok(DaxResult::Integer(15))
+    ));
+    assert_eq!(table.num_rows(), 3);
+
+    match table.select(&["Sales", "Cost"]) {
+        Err(DaxError::EvaluationError(e)) => {
+            assert_eq!(
+                e,
+                "Column Cost not found; the table has Product, Region, Sales"
+            )
+        }
+        other => panic!("Expected an unknown column error, got {:?}", other),
+    }
+    assert_eq!(table.filter_rows(|_| false).shape(), (0, 3));
+}
+
+#[test]
+fn test_distinct_rows_and_drop_nulls() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Product".to_string(),
+            vec![
+                "Pear".into(),
+                "Plum".into(),
+                "Pear".into(),
+                "Fig".into(),
+                "Kiwi".into(),
+                "Kiwi".into(),
+            ],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Sales".to_string(),
+            vec![
+                Value::Integer(10),
+                Value::Integer(20),
+                Value::Number(10.0),
+                Value::Null,
+                Value::Number(f64::NAN),
+                Value::Number(f64::NAN),
+            ],
+        )
+        .unwrap();
+
+    // Integer(10) repeats Number(10.0), and NaN repeats NaN
+    let distinct = table.distinct_rows();
+    assert_eq!(
+        distinct.get_column("Product").unwrap(),
+        ["Pear", "Plum", "Fig", "Kiwi"].map(Value::from).to_vec()
+    );
+    assert_eq!(distinct.get("Sales", 0), Some(Value::Integer(10)));
+
+    let cleaned = distinct.drop_nulls(None);
+    assert_eq!(cleaned.num_rows(), 3);
+    assert_eq!(
+        cleaned
+            .filter_rows(|row| row.get("Product") != Some(Value::from("Kiwi")))
+            .evaluate_dax("SUM([Sales])")
+            .unwrap()
+            .to_string(),
+        "30"
+    );
+    assert_eq!(table.sum("Sales").map(f64::is_nan), Some(true));
+
+    assert_eq!(table.drop_nulls(Some(&["Product"])).num_rows(), 6);
+    assert_eq!(table.drop_nulls(Some(&["Sales"])).num_rows(), 5);
+    assert_eq!(table.drop_nulls(Some(&["Cost"])).num_rows(), 6);
+    assert_eq!(Table::new().distinct_rows(), Table::new());
+}
+
+#[test]
+fn test_dtype_and_cast_column() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Amount".to_string(),
+            vec![" 12.5".into(), "3".into(), "n/a".into(), Value::Null],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Qty".to_string(),
+            vec![
+                Value::Integer(1),
+                Value::Number(0.0),
+                Value::Integer(2),
+                Value::Null,
+            ],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "When".to_string(),
+            vec![
+                "2024-03-01".into(),
+                Value::Integer(1),
+                true.into(),
+                Value::Null,
+            ],
+        )
+        .unwrap();
+    table
+        .add_column("Empty".to_string(), vec![Value::Null; 4])
+        .unwrap();
+
+    assert_eq!(table.dtype("Amount"), Some(DType::Text));
+    assert_eq!(table.dtype("Qty"), Some(DType::Number));
+    assert_eq!(table.dtype("When"), Some(DType::Mixed));
+    assert_eq!(table.dtype("Empty"), Some(DType::Null));
+    assert_eq!(table.dtype("Cost"), None);
+
+    match table.cast_column("Amount", DType::Number, true) {
+        Err(DaxError::EvaluationError(e)) => {
+            assert_eq!(
+                e,
+                "Cannot cast column Amount to number: row 2 holds \"n/a\""
+            )
+        }
+        other => panic!("Expected a cast error, got {:?}", other),
+    }
+    assert_eq!(table.dtype("Amount"), Some(DType::Text));
+    table.cast_column("Amount", DType::Number, false).unwrap();
+    assert_eq!(
+        table.get_column("Amount").unwrap(),
+        vec![
+            Value::Number(12.5),
+            Value::Number(3.0),
+            Value::Null,
+            Value::Null
+        ]
+    );
+    assert!(matches!(
+        table.evaluate_dax("SUM([Amount])"),
+        Ok(DaxResult::Number(n)) if n == 15.5
+    ));
+
+    table.cast_column("Qty", DType::Boolean, true).unwrap();
+    assert_eq!(
+        table.get_column("Qty").unwrap().to_vec()[..3],
+        [true, false, true].map(Value::Boolean)
+    );
+    table.cast_column("Qty", DType::Integer, true).unwrap();
+    assert_eq!(table.dtype("Qty"), Some(DType::Integer));
+    table.cast_column("Qty", DType::Text, true).unwrap();
+    assert_eq!(table.get("Qty", 0), Some(Value::from("1")));
+
+    let mut dates = table.clone();
+    dates.cast_column("When", DType::Date, false).unwrap();
+    assert_eq!(
+        dates.get("When", 0),
+        Some(Value::from(NaiveDate::from_ymd_opt(2024, 3, 1).unwrap()))
+    );
+    assert_eq!(
+        dates.get("When", 1),
+        Some(Value::from(NaiveDate::from_ymd_opt(1899, 12, 31).unwrap()))
+    );
+    assert_eq!(dates.get("When", 2), Some(Value::Null));
+
+    assert!(table.cast_column("Amount", DType::Integer, true).is_err());
+    assert!(table.cast_column("Cost", DType::Text, true).is_err());
+    assert_eq!(
+        Value::from("FALSE").cast(DType::Boolean),
+        Some(Value::Boolean(false))
+    );
+    assert_eq!(Value::from("").cast(DType::Number), None);
+    assert_eq!(Value::Number(2.5).cast(DType::Null), None);
+}
+
+#[test]
+fn test_head_tail_slice_and_sample() {
+    let mut csv = String::from("Id,Sales\n");
+    for i in 0..250 {
+        csv.push_str(&format!("{},{}\n", i, i % 10));
+    }
+    let path = std::env::temp_dir().join("dax_rust_head_test.csv");
+    std::fs::write(&path, csv).unwrap();
+    let table = dax_rust::io::read_csv(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let head = table.head(100);
+    assert_eq!(head.shape(), (100, 2));
+    assert!(matches!(
+        head.evaluate_dax("SUM([Sales])"),
+        Ok(DaxResult::Integer(450))
+    ));
+    assert_eq!(
+        table.tail(5).evaluate_dax("MIN([Id])").unwrap().to_string(),
+        "245"
+    );
+    assert_eq!(
+        table.slice(10, 3).get_column("Id"),
+        table.head(13).tail(3).get_column("Id")
+    );
+    assert_eq!(table.slice(248, 10).num_rows(), 2);
+    assert_eq!(table.slice(400, 10).shape(), (0, 2));
+    assert_eq!(table.head(1000), table);
+    assert_eq!(table.tail(1000), table);
+
+    let sample = table.sample(20, Some(42));
+    assert_eq!(sample.shape(), (20, 2));
+    assert_eq!(sample, table.sample(20, Some(42)));
+    assert_ne!(sample, table.sample(20, Some(43)));
+    assert_eq!(sample.distinct_rows().num_rows(), 20);
+    let ids: Vec<i64> = sample
+        .get_column("Id")
+        .unwrap()
+        .iter()
+        .map(|id| i64::try_from(id.clone()).unwrap())
+        .collect();
+    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    assert_eq!(table.sample(1000, None), table);
+    assert_eq!(table.sample(20, None).num_rows(), 20);
+}
+
+#[test]
+fn test_pivot_and_unpivot() {
+    let mut wide = Table::new();
+    wide.add_column(
+        "Region".to_string(),
+        ["North", "South"].map(Value::from).to_vec(),
+    )
+    .unwrap();
+    wide.add_column(
+        "Q1".to_string(),
+        vec![Value::Integer(10), Value::Integer(5)],
+    )
+    .unwrap();
+    wide.add_column("Q2".to_string(), vec![Value::Integer(20), Value::Null])
+        .unwrap();
+
+    let long = wide
+        .unpivot(&["Region"], &["Q1", "Q2"], "Quarter", "Sales")
+        .unwrap();
+    assert_eq!(long.column_names(), ["Region", "Quarter", "Sales"]);
+    assert_eq!(
+        long.get_column("Quarter").unwrap(),
+        ["Q1", "Q2", "Q1", "Q2"].map(Value::from).to_vec()
+    );
+    assert!(matches!(
+        long.evaluate_dax("SUM([Sales])"),
+        Ok(DaxResult::Integer(35))
+    ));
+    assert_eq!(
+        long.pivot("Region", "Quarter", "Sales", Agg::Sum).unwrap(),
+        wide
+    );
+    assert_eq!(
+        wide.unpivot(&["Region"], &[], "Quarter", "Sales").unwrap(),
+        long
+    );
+
+    // Repeated pairs are aggregated, and missing ones are Null
+    let mut sales = Table::new();
+    sales
+        .add_column(
+            "Product".to_string(),
+            ["Pear", "Pear", "Plum", "Pear"].map(Value::from).to_vec(),
+        )
+        .unwrap();
+    sales
+        .add_column(
+            "Year".to_string(),
+            vec![
+                Value::Integer(2024),
+                Value::from("2024"),
+                Value::Null,
+                Value::Integer(2024),
+            ],
+        )
+        .unwrap();
+    sales
+        .add_column(
+            "Sales".to_string(),
+            [1.0, 2.0, 3.0, 4.0].map(Value::Number).to_vec(),
+        )
+        .unwrap();
+    let pivoted = sales.pivot("Product", "Year", "Sales", Agg::Count).unwrap();
+    assert_eq!(
+        pivoted.column_names(),
+        ["Product", "2024", "2024 (2)", "(Blank)"]
+    );
+    assert_eq!(
+        pivoted.get_column("2024").unwrap(),
+        vec![Value::Integer(2), Value::Null]
+    );
+    assert_eq!(
+        pivoted.get_column("(Blank)").unwrap(),
+        vec![Value::Null, Value::Integer(1)]
+    );
+
+    assert!(sales.pivot("Product", "Month", "Sales", Agg::Sum).is_err());
+    assert!(wide
+        .unpivot(&["Region"], &["Q3"], "Quarter", "Sales")
+        .is_err());
+    assert!(wide.unpivot(&["Region"], &[], "Region", "Sales").is_err());
+}
+
+#[test]
+fn test_null_policy_matrix() {
+    let mut table = Table::new();
+    table.add_column("Empty".to_string(), vec![]).unwrap();
+    let mut columns = Table::new();
+    columns
+        .add_column("Nulls".to_string(), vec![Value::Null; 3])
+        .unwrap();
+    columns
+        .add_column(
+            "Mixed".to_string(),
+            vec![Value::Number(2.0), Value::Null, Value::Number(4.0)],
+        )
+        .unwrap();
+    columns
+        .add_column(
+            "Text".to_string(),
+            ["a", "b", "c"].map(Value::from).to_vec(),
+        )
+        .unwrap();
+
+    // Empty and all-Null columns, and ones with nothing to add, are blank
+    assert_eq!(table.sum("Empty"), None);
+    assert!(matches!(
+        table.evaluate_dax("SUM([Empty])"),
+        Ok(DaxResult::Blank)
+    ));
+    for expression in [
+        "SUM([Nulls])",
+        "AVERAGE([Nulls])",
+        "MIN([Nulls])",
+        "MAX([Nulls])",
+    ] {
+        assert!(
+            matches!(columns.evaluate_dax(expression), Ok(DaxResult::Blank)),
+            "{}",
+            expression
+        );
+    }
+    assert_eq!(columns.average("Text"), None);
+    assert!(matches!(
+        columns.evaluate_dax("AVERAGE([Text])"),
+        Ok(DaxResult::Blank)
+    ));
+    assert_eq!(columns.sum("Mixed"), Some(6.0));
+    assert_eq!(columns.average("Mixed"), Some(3.0));
+
+    // The policy decides what a Null does
+    let cases = [
+        (NullPolicy::Skip, Some(6.0), Some(3.0)),
+        (NullPolicy::PropagateBlank, None, None),
+    ];
+    for (policy, sum, average) in cases {
+        assert_eq!(columns.sum_with("Mixed", policy).unwrap(), sum);
+        assert_eq!(columns.average_with("Mixed", policy).unwrap(), average);
+        assert_eq!(columns.sum_with("Nulls", policy).unwrap(), None);
+        assert_eq!(table.sum_with("Empty", policy).unwrap(), None);
+    }
+    assert_eq!(
+        columns.min_with("Mixed", NullPolicy::Skip).unwrap(),
+        Some(2.0)
+    );
+    assert_eq!(
+        columns
+            .max_with("Mixed", NullPolicy::PropagateBlank)
+            .unwrap(),
+        None
+    );
+    let error = columns.sum_with("Mixed", NullPolicy::Error).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Evaluation error: Column Mixed is blank in row 1, which the null policy doesn't allow"
+    );
+    assert!(columns.sum_with("Nulls", NullPolicy::Error).is_err());
+    assert_eq!(table.sum_with("Empty", NullPolicy::Error).unwrap(), None);
+    for policy in [
+        NullPolicy::Skip,
+        NullPolicy::PropagateBlank,
+        NullPolicy::Error,
+    ] {
+        assert!(matches!(
+            columns.sum_with("Cost", policy),
+            Err(DaxError::UnknownColumn { .. })
+        ));
+    }
+    assert_eq!(columns.sum("Cost"), None);
+
+    // Set on the table, it applies to DAX too, over the visible rows only
+    columns.set_null_policy(NullPolicy::PropagateBlank);
+    assert_eq!(columns.sum("Mixed"), None);
+    assert!(matches!(
+        columns.evaluate_dax("SUM([Mixed])"),
+        Ok(DaxResult::Blank)
+    ));
+    assert!(matches!(
+        columns.evaluate_dax("CALCULATE(SUM([Mixed]), [Mixed] > 3)"),
+        Ok(DaxResult::Number(n)) if n == 4.0
+    ));
+    columns.set_null_policy(NullPolicy::Error);
+    assert!(columns.evaluate_dax("AVERAGE([Mixed])").is_err());
+    assert_eq!(columns.clone().null_policy(), NullPolicy::Error);
+}
+
+#[test]
+fn test_sort_by_several_columns() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Product".to_string(),
+            vec![
+                "Pear".into(),
+                "Apple".into(),
+                Value::Null,
+                "Pear".into(),
+                "Apple".into(),
+                "Apple".into(),
+            ],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Sales".to_string(),
+            vec![
+                Value::Integer(5),
+                Value::Number(2.5),
+                Value::Integer(1),
+                Value::Integer(9),
+                Value::Null,
+                Value::Integer(7),
+            ],
+        )
+        .unwrap();
+    table
+        .add_column("Id".to_string(), (1..=6).map(Value::Integer).collect())
+        .unwrap();
+
+    let keys = [
+        ("Product", SortOrder::Ascending),
+        ("Sales", SortOrder::Descending),
+    ];
+    let copy = table.sorted_by(&keys).unwrap();
+    table.sort_by(&keys).unwrap();
+    let ids = |table: &Table| table.get_column("Id").unwrap().to_vec();
+    assert_eq!(ids(&table), [6, 2, 5, 4, 1, 3].map(Value::Integer).to_vec());
+    assert_eq!(ids(&copy), ids(&table));
+
+    table
+        .sort_by_with_nulls(&[("Sales", SortOrder::Ascending)], NullOrder::First)
+        .unwrap();
+    assert_eq!(ids(&table), [5, 3, 2, 1, 6, 4].map(Value::Integer).to_vec());
+
+    // Mixed kinds follow the Value order; equal keys keep their order
+    let mut mixed = Table::new();
+    mixed
+        .add_column(
+            "Key".to_string(),
+            vec![
+                "b".into(),
+                Value::Integer(2),
+                Value::Boolean(true),
+                "a".into(),
+                Value::Number(2.0),
+            ],
+        )
+        .unwrap();
+    mixed
+        .add_column("Id".to_string(), (1..=5).map(Value::Integer).collect())
+        .unwrap();
+    mixed.sort_by(&[("Key", SortOrder::Ascending)]).unwrap();
+    assert_eq!(ids(&mixed), [3, 2, 5, 4, 1].map(Value::Integer).to_vec());
+
+    assert!(matches!(
+        mixed.sort_by(&[("Missing", SortOrder::Ascending)]),
+        Err(DaxError::UnknownColumn { .. })
+    ));
+}
+
+#[test]
+fn test_group_by_and_aggregate() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Region".to_string(),
+            vec!["N".into(), "S".into(), "N".into(), Value::Null, "S".into()],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Product".to_string(),
+            vec![
+                "Pear".into(),
+                "Apple".into(),
+                "Apple".into(),
+                "Plum".into(),
+                Value::Null,
+            ],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Sales".to_string(),
+            [10, 20, 30, 5, 40].map(Value::Integer).to_vec(),
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Quantity".to_string(),
+            vec![
+                Value::Number(1.0),
+                Value::Number(2.0),
+                Value::Number(4.0),
+                Value::Null,
+                Value::Null,
+            ],
+        )
+        .unwrap();
+
+    let grouped = table.group_by(&["Region"]).unwrap();
+    assert_eq!(grouped.len(), 3);
+    let result = grouped
+        .agg(&[
+            ("Sales", Agg::Sum),
+            ("Quantity", Agg::Mean),
+            ("Product", Agg::Count),
+            ("Product", Agg::DistinctCount),
+            ("Product", Agg::Min),
+            ("Sales", Agg::Max),
+            ("Product", Agg::First),
+            ("Product", Agg::Last),
+        ])
+        .unwrap();
+    assert_eq!(
+        result.column_names(),
+        [
+            "Region",
+            "Sum of Sales",
+            "Average of Quantity",
+            "Count of Product",
+            "Distinct Count of Product",
+            "Min of Product",
+            "Max of Sales",
+            "First of Product",
+            "Last of Product"
+        ]
+    );
+    let column = |name: &str| result.get_column(name).unwrap().to_vec();
+    assert_eq!(column("Region"), vec!["N".into(), "S".into(), Value::Null]);
+    assert_eq!(
+        column("Sum of Sales"),
+        [40, 60, 5].map(Value::Integer).to_vec()
+    );
+    assert_eq!(
+        column("Average of Quantity"),
+        vec![Value::Number(2.5), Value::Number(2.0), Value::Null]
+    );
+    assert_eq!(
+        column("Count of Product"),
+        [2, 1, 1].map(Value::Integer).to_vec()
+    );
+    assert_eq!(
+        column("Distinct Count of Product"),
+        [2, 2, 1].map(Value::Integer).to_vec()
+    );
+    assert_eq!(
+        column("Min of Product"),
+        ["Apple", "Apple", "Plum"].map(Value::from).to_vec()
+    );
+    assert_eq!(
+        column("Max of Sales"),
+        [30, 40, 5].map(Value::Integer).to_vec()
+    );
+    assert_eq!(
+        column("First of Product"),
+        ["Pear", "Apple", "Plum"].map(Value::from).to_vec()
+    );
+    assert_eq!(
+        column("Last of Product"),
+        ["Apple", "Apple", "Plum"].map(Value::from).to_vec()
+    );
+
+    // Several keys, and the result can be evaluated like any other table
+    let by_both = table
+        .group_by(&["Region", "Product"])
+        .unwrap()
+        .agg(&[("Sales", Agg::Sum)])
+        .unwrap();
+    assert_eq!(by_both.num_rows(), 5);
+    assert!(matches!(
+        by_both.evaluate_dax("SUM([Sum of Sales])"),
+        Ok(DaxResult::Integer(105))
+    ));
+
+    assert!(table.group_by(&["Missing"]).is_err());
+    assert!(grouped.agg(&[("Missing", Agg::Sum)]).is_err());
+    assert!(grouped
+        .agg(&[("Sales", Agg::Sum), ("Sales", Agg::Sum)])
+        .is_err());
+}
+
+#[test]
+fn test_joins() {
+    let mut orders = Table::new();
+    orders
+        .add_column(
+            "Key".to_string(),
+            vec![
+                Value::Integer(1),
+                Value::Integer(1),
+                "x".into(),
+                Value::Number(f64::NAN),
+                Value::Integer(9),
+            ],
+        )
+        .unwrap();
+    orders
+        .add_column("Amount".to_string(), (1..=5).map(Value::Integer).collect())
+        .unwrap();
+    orders
+        .add_column(
+            "Name".to_string(),
+            ["a", "b", "c", "d", "e"].map(Value::from).to_vec(),
+        )
+        .unwrap();
+
+    let mut lookup = Table::new();
+    lookup
+        .add_column(
+            "Id".to_string(),
+            vec![
+                Value::Number(1.0),
+                "x".into(),
+                Value::Number(1.0),
+                Value::Number(f64::NAN),
+                Value::Integer(7),
+            ],
+        )
+        .unwrap();
+    lookup
+        .add_column(
+            "Name".to_string(),
+            ["p", "q", "r", "s", "t"].map(Value::from).to_vec(),
+        )
+        .unwrap();
+
+    let on = [("Key", "Id")];
+    let inner = orders.join(&lookup, &on, JoinKind::Inner).unwrap();
+    assert_eq!(
+        inner.column_names(),
+        ["Key", "Amount", "Name", "Name_right"]
+    );
+    // Two left rows with key 1 times two right rows, then "x" and NaN
+    assert_eq!(
+        inner.get_column("Amount").unwrap(),
+        [1, 1, 2, 2, 3, 4].map(Value::Integer).to_vec()
+    );
+    assert_eq!(
+        inner.get_column("Name_right").unwrap(),
+        ["p", "r", "p", "r", "q", "s"].map(Value::from).to_vec()
+    );
+
+    let left = orders.join(&lookup, &on, JoinKind::Left).unwrap();
+    assert_eq!(left.num_rows(), 7);
+    assert_eq!(left.get("Name_right", 6), Some(Value::Null));
+    assert_eq!(left.get("Key", 6), Some(Value::Integer(9)));
+
+    let right = orders
+        .join_with_suffix(&lookup, &on, JoinKind::Right, " (lookup)")
+        .unwrap();
+    assert_eq!(right.num_rows(), 7);
+    assert_eq!(right.column_names()[3], "Name (lookup)");
+    // The unmatched right row keeps its key and has no left values
+    assert_eq!(right.get("Key", 6), Some(Value::Integer(7)));
+    assert_eq!(right.get("Amount", 6), Some(Value::Null));
+
+    let full = orders.join(&lookup, &on, JoinKind::Full).unwrap();
+    assert_eq!(full.num_rows(), 8);
+    assert_eq!(
+        full.get_column("Key").unwrap().to_vec()[6..],
+        [Value::Integer(9), Value::Integer(7)]
+    );
+    assert!(matches!(
+        full.evaluate_dax("SUM([Amount])"),
+        Ok(DaxResult::Integer(18))
+    ));
+
+    assert!(matches!(
+        orders.join(&lookup, &[("Key", "Missing")], JoinKind::Inner),
+        Err(DaxError::UnknownColumn { .. })
+    ));
+}
+
+#[test]
+fn test_large_join() {
+    let rows = 100_000;
+    let mut left = Table::new();
+    left.add_column("Id".to_string(), (0..rows).map(Value::Integer).collect())
+        .unwrap();
+    let mut right = Table::new();
+    right
+        .add_column(
+            "Id".to_string(),
+            (0..rows).rev().map(Value::Integer).collect(),
+        )
+        .unwrap();
+    right
+        .add_column(
+            "Double".to_string(),
+            (0..rows).rev().map(|i| Value::Integer(i * 2)).collect(),
+        )
+        .unwrap();
+    let joined = left.join(&right, &[("Id", "Id")], JoinKind::Inner).unwrap();
+    assert_eq!(joined.shape(), (rows as usize, 2));
+    assert_eq!(joined.get("Double", 12_345), Some(Value::Integer(24_690)));
+}
+
+#[test]
+fn test_append_and_concat() {
+    let month = |sales: &[i64], extra: Option<&str>| {
+        let mut table = Table::new();
+        table
+            .add_column("Month".to_string(), vec![Value::from("m"); sales.len()])
+            .unwrap();
+        table
+            .add_column(
+                "Sales".to_string(),
+                sales.iter().map(|s| Value::Integer(*s)).collect(),
+            )
+            .unwrap();
+        if let Some(name) = extra {
+            table
+                .add_column(name.to_string(), vec![Value::Boolean(true); sales.len()])
+                .unwrap();
+        }
+        table
+    };
+    let sum = |table: &Table| match table.evaluate_dax("SUM([Sales])") {
+        Ok(DaxResult::Integer(i)) => i,
+        other => panic!("Expected integer result, got {:?}", other),
+    };
+    let (jan, feb, mar) = (
+        month(&[10, 20], None),
+        month(&[5], Some("Promo")),
+        month(&[1, 2, 3], None),
+    );
+    let expected = sum(&jan) + sum(&feb) + sum(&mar);
+
+    let quarter = Table::concat(vec![jan, feb, mar]).unwrap();
+    assert_eq!(sum(&quarter), expected);
+    assert_eq!(quarter.column_names(), ["Month", "Sales", "Promo"]);
+    assert_eq!(
+        quarter.get_column("Promo").unwrap(),
+        [
+            Value::Null,
+            Value::Null,
+            Value::Boolean(true),
+            Value::Null,
+            Value::Null,
+            Value::Null
+        ]
+    );
+
+    // Columns in a different order still line up by name
+    let mut table = month(&[1], None);
+    let mut swapped = Table::new();
+    swapped
+        .add_column("Sales".to_string(), vec![Value::Integer(2)])
+        .unwrap();
+    swapped
+        .add_column("Month".to_string(), vec!["n".into()])
+        .unwrap();
+    table.append_strict(swapped).unwrap();
+    assert_eq!(
+        table.get_column("Month").unwrap(),
+        vec!["m".into(), "n".into()]
+    );
+    match table.append_strict(month(&[3], Some("Promo"))) {
+        Err(DaxError::EvaluationError(e)) => assert!(e.contains("Promo only in one"), "{}", e),
+        other => panic!("Expected an error, got {:?}", other),
+    }
+    assert_eq!(table.shape(), (2, 2));
+}
+
+#[test]
+fn test_typed_columns_read_back_unchanged() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Sales".to_string(),
+            vec![Value::Number(1.5), Value::Null, Value::Number(-0.0)],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Name".to_string(),
+            ["a", "b", "a"].map(Value::from).to_vec(),
+        )
+        .unwrap();
+    table
+        .add_column("Flag".to_string(), vec![Value::Boolean(true); 3])
+        .unwrap();
+
+    assert_eq!(table.get("Sales", 1), Some(Value::Null));
+    assert_eq!(table.sum("Sales"), Some(1.5));
+    assert_eq!(table.average("Sales"), Some(0.75));
+    assert_eq!(table.min("Sales"), Some(-0.0));
+    assert_eq!(table.distinctcount("Sales"), Some(3));
+    assert_eq!(table.distinctcount("Name"), Some(2));
+    assert_eq!(table.distinctcount("Flag"), Some(1));
+
+    // A value of another kind turns the column back into plain values
+    table.set("Sales", 1, Value::from("n/a")).unwrap();
+    assert_eq!(table.get("Sales", 1), Some(Value::from("n/a")));
+    assert_eq!(table.sum("Sales"), Some(1.5));
+    table.set("Sales", 1, Value::Number(2.0)).unwrap();
+    assert_eq!(table.sum("Sales"), Some(3.5));
+
+    let mut row = HashMap::new();
+    row.insert("Sales".to_string(), Value::Number(4.0));
+    row.insert("Name".to_string(), Value::Integer(7));
+    table.add_row(row);
+    assert_eq!(table.get("Name", 3), Some(Value::Integer(7)));
+    assert_eq!(table.get("Flag", 3), Some(Value::Null));
+    assert!(matches!(
+        table.evaluate_dax("SUM([Sales])"),
+        Ok(DaxResult::Number(n)) if n == 7.5
+    ));
+}
+
+#[test]
+#[ignore = "timing comparison; run with --ignored --nocapture"]
+fn bench_typed_column_sum() {
+    let rows = 1_000_000;
+    let numbers: Vec<Value> = (0..rows).map(|i| Value::Number(i as f64)).collect();
+    let mut typed = Table::new();
+    typed.add_column("N".to_string(), numbers.clone()).unwrap();
+    // One text value keeps the whole column as plain values
+    let mut mixed = Table::new();
+    let mut values = numbers;
+    values[0] = Value::from("x");
+    mixed.add_column("N".to_string(), values).unwrap();
+
+    let time = |table: &Table| {
+        let start = std::time::Instant::now();
+        for _ in 0..10 {
+            assert!(table.sum("N").is_some());
+        }
+        start.elapsed()
+    };
+    let (typed_time, mixed_time) = (time(&typed), time(&mixed));
+    println!(
+        "SUM over {} rows: typed {:?}, mixed {:?}",
+        rows, typed_time, mixed_time
+    );
+    assert!(typed_time < mixed_time);
+}
+
+fn categories(rows: usize) -> Vec<Value> {
+    (0..rows)
+        .map(|i| Value::Text(format!("Category number {:02}", i % 20)))
+        .collect()
+}
+
+#[test]
+fn test_repeated_text_is_dictionary_encoded() {
+    let rows = 100_000;
+    let mut table = Table::new();
+    table
+        .add_column("Category".to_string(), categories(rows))
+        .unwrap();
+
+    // A String per row would take at least its own size plus 20 bytes of text
+    let plain = rows * (std::mem::size_of::<String>() + 20);
+    let encoded = table.memory_usage().total;
+    assert!(encoded * 10 < plain, "{} bytes against {}", encoded, plain);
+
+    table
+        .add_column(
+            "Id".to_string(),
+            (0..rows as i64).map(Value::from).collect(),
+        )
+        .unwrap();
+
+    assert_eq!(table.distinctcount("Category"), Some(20));
+    let counts = table
+        .group_by(&["Category"])
+        .unwrap()
+        .agg(&[("Id", Agg::Count), ("Id", Agg::Min)])
+        .unwrap();
+    assert_eq!(counts.num_rows(), 20);
+    assert_eq!(
+        counts.get("Category", 3),
+        Some(Value::from("Category number 03"))
+    );
+    assert_eq!(counts.get("Count of Id", 3), Some(Value::Integer(5000)));
+    assert_eq!(counts.get("Min of Id", 3), Some(Value::Integer(3)));
+
+    // Reads, writes and filters see plain text
+    table.set("Category", 0, Value::from("New")).unwrap();
+    assert_eq!(table.get("Category", 0), Some(Value::from("New")));
+    assert_eq!(
+        table.get("Category", 21),
+        Some(Value::from("Category number 01"))
+    );
+    assert_eq!(table.distinctcount("Category"), Some(21));
+    let first = table.head(3);
+    assert_eq!(first.distinctcount("Category"), Some(3));
+    assert_eq!(
+        first.get_column("Category").unwrap(),
+        vec![
+            "New".into(),
+            "Category number 01".into(),
+            "Category number 02".into()
+        ]
+    );
+    assert!(matches!(
+        table.evaluate_dax("COUNTROWS(FILTER(Sales, [Category] = \"Category number 05\"))"),
+        Ok(DaxResult::Number(n)) if n == 5000.0
+    ));
+    table.set("Category", 1, Value::Integer(1)).unwrap();
+    assert_eq!(table.distinctcount("Category"), Some(22));
+    assert_eq!(table.get("Category", 1), Some(Value::Integer(1)));
+}
+
+#[test]
+#[ignore = "memory comparison; run with --ignored --nocapture"]
+fn bench_dictionary_memory() {
+    let rows = 5_000_000;
+    let mut encoded = Table::new();
+    encoded
+        .add_column("Category".to_string(), categories(rows))
+        .unwrap();
+    // Distinct strings can't share a dictionary entry
+    let mut plain = Table::new();
+    plain
+        .add_column(
+            "Category".to_string(),
+            (0..rows)
+                .map(|i| Value::Text(format!("Category number {:08}", i)))
+                .collect(),
+        )
+        .unwrap();
+    println!(
+        "{} rows of text: {} bytes encoded, {} bytes plain",
+        rows,
+        encoded.memory_usage().total,
+        plain.memory_usage().total
+    );
+    assert!(encoded.memory_usage().total * 10 < plain.memory_usage().total);
+}
+
+#[test]
+fn test_memory_usage_estimate() {
+    use std::mem::size_of;
+    let rows = 1000;
+    let mut table = Table::new();
+    table
+        .add_column("Amount".to_string(), vec![Value::Number(1.5); rows])
+        .unwrap();
+    table
+        .add_column("Flag".to_string(), vec![Value::Boolean(true); rows])
+        .unwrap();
+    table
+        .add_column(
+            "Code".to_string(),
+            (0..rows)
+                .map(|i| Value::Text(format!("code-{:04}", i)))
+                .collect(),
+        )
+        .unwrap();
+    table
+        .add_column("Qty".to_string(), vec![Value::Integer(3); rows])
+        .unwrap();
+    let _ = &table[("Amount", 0)];
+    let before = table.memory_usage();
+    table.shrink_to_fit();
+    let usage = table.memory_usage();
+    assert!(usage.column("Amount").unwrap() < before.column("Amount").unwrap());
+
+    // Numbers with a bit each for Null, a byte per boolean, a String and its
+    // nine bytes of text per code, and a tagged Value per integer
+    let expected = [
+        ("Amount", rows * 8 + rows.div_ceil(64) * 8),
+        ("Flag", rows),
+        ("Code", rows * (size_of::<String>() + 9)),
+        ("Qty", rows * size_of::<Value>()),
+    ];
+    for (name, bytes) in expected {
+        let estimate = usage.column(name).unwrap();
+        assert!(
+            estimate.abs_diff(bytes) * 20 <= bytes,
+            "{}: {} bytes, expected about {}",
+            name,
+            estimate,
+            bytes
+        );
+    }
+    let columns: usize = expected.iter().map(|(_, bytes)| bytes).sum();
+    assert!(usage.total >= columns && usage.total < columns + 1000);
+    assert_eq!(usage.column("Cost"), None);
+
+    let shown = usage.to_string();
+    assert!(shown.contains(" Column "), "{}", shown);
+    assert!(shown.contains(" Total "), "{}", shown);
+    assert!(shown.contains(&format!(" {} ", usage.total)), "{}", shown);
+
+    // Shrinking drops the strings a dictionary no longer uses
+    let mut repeated = Table::new();
+    repeated
+        .add_column("Category".to_string(), categories(10_000))
+        .unwrap();
+    let mut few = repeated.head(100);
+    few.set("Category", 0, Value::from("Other")).unwrap();
+    let before = few.memory_usage().total;
+    few.shrink_to_fit();
+    assert!(few.memory_usage().total < before);
+    assert_eq!(few.get("Category", 0), Some(Value::from("Other")));
+    assert_eq!(few.get("Category", 41), repeated.get("Category", 41));
+    assert_eq!(few.distinctcount("Category"), Some(21));
+}
+
+#[test]
+fn test_cell_access_keeps_columns_typed() {
+    let mut table = Table::new();
+    table
+        .add_column("Category".to_string(), categories(100_000))
+        .unwrap();
+    table
+        .add_column(
+            "Amount".to_string(),
+            (0..100_000).map(|i| Value::Number(i as f64)).collect(),
+        )
+        .unwrap();
+    let before = table.memory_usage().total;
+
+    assert_eq!(table.get("Amount", 7), Some(Value::Number(7.0)));
+    assert!(table.get("Category", 0).is_some());
+    assert_eq!(table.row(3).unwrap()["Amount"], Value::Number(3.0));
+    assert_eq!(
+        table.rows().nth(5).unwrap().get("Amount"),
+        Some(Value::Number(5.0))
+    );
+    assert_eq!(table.memory_usage().total, before);
+
+    // Reading a whole column doesn't copy it either; only indexing, which
+    // hands out a reference, builds its Values
+    let amounts = table.get_column("Amount").unwrap();
+    assert_eq!(amounts.len(), 100_000);
+    assert_eq!(amounts.get(7), Some(Value::Number(7.0)));
+    assert_eq!(amounts.iter().rev().nth(1), Some(Value::Number(99_998.0)));
+    assert_eq!(table.memory_usage().total, before);
+    assert_eq!(table[("Amount", 7)], Value::Number(7.0));
+    assert!(table.memory_usage().total > before);
+}
+
+#[test]
+fn test_aggregates_keep_columns_typed() {
+    let mut table = Table::new();
+    table
+        .add_column("Category".to_string(), categories(10_000))
+        .unwrap();
+    table
+        .add_column(
+            "Amount".to_string(),
+            (0..10_000)
+                .map(|i| {
+                    if i % 2 == 0 {
+                        Value::Null
+                    } else {
+                        Value::Number(i as f64)
+                    }
+                })
+                .collect(),
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Flag".to_string(),
+            (0..10_000).map(|i| Value::Boolean(i % 4 == 0)).collect(),
+        )
+        .unwrap();
+    let before = table.memory_usage().total;
+
+    assert_eq!(table.approx_distinctcount("Category", 12), Some(20));
+    assert_eq!(
+        table.approx_distinctcount("Amount", 12).map(|n| n > 4000),
+        Some(true)
+    );
+    assert_eq!(table.averagea("Flag"), Some(0.25));
+    assert_eq!(table.maxa("Category"), Some(0.0));
+    assert_eq!(table.mina("Amount"), Some(1.0));
+    assert_eq!(table.min_date("Amount"), None);
+    assert_eq!(table.count("Category"), Some(10_000));
+    assert_eq!(table.dtype("Amount"), Some(DType::Number));
+    assert_eq!(table.sum_with("Flag", NullPolicy::Error).unwrap(), None);
+    assert!(table.sum_with("Amount", NullPolicy::Error).is_err());
+    match table.evaluate_dax("MAX([Category])") {
+        Ok(DaxResult::Text(s)) => assert_eq!(s, "Category number 19"),
+        other => panic!("Expected text, got {:?}", other),
+    }
+    assert_eq!(table.clone(), table);
+
+    // Nor do filters, groupings and sorts, which read dictionary-encoded
+    // text by its codes
+    for (expression, expected) in [
+        (
+            "CALCULATE(SUM([Amount]), [Category] = \"Category number 03\")",
+            2_496_500.0,
+        ),
+        ("CALCULATE(COUNTBLANK([Amount]), [Flag] = TRUE())", 2_500.0),
+    ] {
+        match table.evaluate_dax(expression) {
+            Ok(DaxResult::Number(n)) => assert_eq!(n, expected, "{}", expression),
+            other => panic!("Expected number for {}, got {:?}", expression, other),
+        }
+    }
+    assert!(matches!(
+        table.evaluate_dax("CALCULATE(SUM([Amount]), [Category] = \"No such category\")"),
+        Ok(DaxResult::Blank)
+    ));
+    let totals = table
+        .group_by(&["Category", "Flag"])
+        .unwrap()
+        .agg(&[("Amount", Agg::Sum)])
+        .unwrap();
+    assert_eq!(totals.num_rows(), 20);
+    let second: Vec<Value> = ["Category", "Flag", "Sum of Amount"]
+        .iter()
+        .map(|name| totals.get_column(name).unwrap().get(1).unwrap())
+        .collect();
+    assert_eq!(
+        second,
+        [
+            Value::from("Category number 01"),
+            Value::Boolean(false),
+            Value::Number(2_495_500.0),
+        ]
+    );
+    assert_eq!(
+        table
+            .sorted_by(&[("Category", SortOrder::Ascending)])
+            .unwrap()
+            .num_rows(),
+        10_000
+    );
+    assert_eq!(table.memory_usage().total, before);
+}
+
+#[test]
+fn test_value_conversions() {
+    assert_eq!(f64::try_from(Value::Number(1.5)).unwrap(), 1.5);
+    assert_eq!(f64::try_from(Value::Integer(2)).unwrap(), 2.0);
+    assert_eq!(f64::try_from(Value::currency(2.25).unwrap()).unwrap(), 2.25);
+    assert_eq!(i64::try_from(Value::Integer(-3)).unwrap(), -3);
+    assert_eq!(i64::try_from(Value::Number(4.0)).unwrap(), 4);
+    assert_eq!(String::try_from(Value::from("North")).unwrap(), "North");
+    assert!(bool::try_from(Value::Boolean(true)).unwrap());
+
+    for (result, expected, got) in [
+        (
+            f64::try_from(Value::from("1")).map(|_| ()),
+            "a number",
+            "text",
+        ),
+        (
+            i64::try_from(Value::Number(4.5)).map(|_| ()),
+            "an integer",
+            "a number",
+        ),
+        (String::try_from(Value::Null).map(|_| ()), "text", "blank"),
+        (
+            bool::try_from(Value::Integer(1)).map(|_| ()),
+            "a boolean",
+            "an integer",
+        ),
+    ] {
+        match result {
+            Err(DaxError::TypeMismatch {
+                expected: e,
+                got: g,
+            }) => {
+                assert_eq!((e.as_str(), g.as_str()), (expected, got))
+            }
+            other => panic!("Expected a type mismatch, got {:?}", other),
+        }
+    }
+
+    let text = Value::from("Apple");
+    assert_eq!(text.as_text(), Some("Apple"));
+    assert_eq!(text.as_number(), None);
+    assert_eq!(Value::Integer(7).as_number(), Some(7.0));
+    assert_eq!(Value::Boolean(false).as_bool(), Some(false));
+    assert_eq!(Value::Number(1.0).as_bool(), None);
+    assert!(Value::Null.is_null() && !Value::Number(0.0).is_null());
+
+    assert_eq!(Value::from("42").coerce_number().unwrap(), 42.0);
+    assert_eq!(Value::from(" -1.5 ").coerce_number().unwrap(), -1.5);
+    assert_eq!(Value::Boolean(true).coerce_number().unwrap(), 1.0);
+    assert_eq!(Value::Null.coerce_number().unwrap(), 0.0);
+    assert_eq!(
+        Value::from(NaiveDate::from_ymd_opt(1900, 1, 1).unwrap())
+            .coerce_number()
+            .unwrap(),
+        2.0
+    );
+    let error = Value::from("n/a").coerce_number().unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Type mismatch: expected a number, got the text \"n/a\""
+    );
+
+    assert_eq!(Value::from(3i32), Value::Integer(3));
+    assert_eq!(Value::from(Some(2.5)), Value::Number(2.5));
+    assert_eq!(Value::from(None::<&str>), Value::Null);
+
+    let table = dax_macro::table! {
+        "Qty" => [1, 2, 3],
+        "Discount" => [Some(0.1), None, Some(0.2)],
+        "Region" => [Some("North"), Some("South"), None],
+        "Units" => [3_000_000_000, -7, null]
+    };
+    assert_eq!(table.get("Qty", 0), Some(Value::Integer(1)));
+    assert_eq!(table.get("Units", 0), Some(Value::Integer(3_000_000_000)));
+    assert_eq!(table.get("Units", 1), Some(Value::Integer(-7)));
+    assert_eq!(table.get("Units", 2), Some(Value::Null));
+    assert_eq!(table.get("Discount", 1), Some(Value::Null));
+    assert_eq!(table.get("Region", 2), Some(Value::Null));
+}
+
+#[test]
+fn test_value_and_result_display() {
+    let date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+    for (value, text) in [
+        (Value::Number(100.0), "100"),
+        (Value::Number(2.50), "2.5"),
+        (Value::Number(0.1 + 0.2), "0.3"),
+        (Value::Number(-0.0), "0"),
+        (Value::Number(1e-7), "0.0000001"),
+        (Value::Number(f64::NAN), "NaN"),
+        (Value::Number(f64::NEG_INFINITY), "-Infinity"),
+        (Value::Integer(-42), "-42"),
+        (Value::currency(1234.5).unwrap(), "1234.50"),
+        (Value::currency(-0.1234).unwrap(), "-0.1234"),
+        (Value::from("North"), "North"),
+        (Value::Boolean(false), "FALSE"),
+        (Value::from(date), "2024-03-01"),
+        (Value::Null, ""),
+    ] {
+        assert_eq!(value.to_string(), text);
+        assert_eq!(DaxResult::from(&value).to_string(), text);
+    }
+    assert_eq!(format!("[{:>5}]", Value::Boolean(true)), "[ TRUE]");
+
+    // A cell renders in a table as the same value does on its own
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Ratio".to_string(),
+            vec![Value::Number(1.0 / 3.0), Value::Boolean(true), Value::Null],
+        )
+        .unwrap();
+    let result = table.evaluate_dax("SUM([Ratio])").unwrap();
+    assert_eq!(result.to_string(), "0.333333333333333");
+    let rendered = table.to_string();
+    assert!(rendered.contains(" 0.333333333333333 "), "{}", rendered);
+    assert!(rendered.contains(" TRUE "), "{}", rendered);
+    let result = table.evaluate_dax("FILTER(Ratio, TRUE())").unwrap();
+    assert_eq!(result.to_string(), rendered);
+}
+
+#[test]
+fn test_display_options() {
+    use dax_rust::display::TableDisplayOptions;
+
+    let mut table = Table::new();
+    table
+        .add_column("Id".to_string(), (0..2000).map(Value::Integer).collect())
+        .unwrap();
+    let rendered = table.to_string();
+    let lines: Vec<&str> = rendered.lines().collect();
+    // Frame and header, ten rows, the marker and five rows
+    assert_eq!(lines.len(), 4 + 10 + 1 + 5);
+    assert!(
+        lines[13].trim_end().ends_with("… 1,985 more rows"),
+        "{}",
+        rendered
+    );
+    assert!(lines[12].contains(" 9 ") && lines[14].contains(" 1995 "));
+    let all = table.display_with(TableDisplayOptions {
+        max_rows: None,
+        ..Default::default()
+    });
+    assert_eq!(all.to_string().lines().count(), 4 + 2000);
+
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Product".to_string(),
+            ["苹果汁", "Pear", "A very long product name indeed"]
+                .map(Value::from)
+                .to_vec(),
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Price".to_string(),
+            vec![Value::Number(2.0 / 3.0), Value::Null, Value::Number(10.0)],
+        )
+        .unwrap();
+    let options = TableDisplayOptions {
+        max_col_width: Some(12),
+        float_precision: Some(2),
+        show_row_numbers: true,
+        null_text: "-".to_string(),
+        ..Default::default()
+    };
+    let rendered = table.display_with(options).to_string();
+    assert!(rendered.contains(" A very long… "), "{}", rendered);
+    assert!(rendered.contains(" 0.67 ") && rendered.contains(" 10.00 "));
+    assert!(rendered.contains("    - "), "{}", rendered);
+    assert!(rendered.lines().nth(1).unwrap().starts_with(" # │ Product"));
+    // The wide characters take two cells each, so the rows line up
+    let widths: Vec<usize> = rendered
+        .lines()
+        .filter(|line| !line.starts_with(['┌', '├', '└']))
+        .map(|line| {
+            line.chars()
+                .map(|c| {
+                    if ('\u{4E00}'..='\u{9FFF}').contains(&c) {
+                        2
+                    } else {
+                        1
+                    }
+                })
+                .sum()
+        })
+        .collect();
+    assert!(widths.iter().all(|w| *w == widths[0]), "{}", rendered);
+}
+
+#[test]
+fn test_markdown_and_html_export() {
+    use dax_rust::display::TableDisplayOptions;
+
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Product".to_string(),
+            ["Fish & Chips", "a|b", "<b>Pie</b>"]
+                .map(Value::from)
+                .to_vec(),
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Sales".to_string(),
+            vec![Value::Number(2.0 / 3.0), Value::Null, Value::Integer(3)],
+        )
+        .unwrap();
+
+    assert_eq!(
+        table.to_markdown(),
+        "| Product | Sales |\n\
+         | :--- | ---: |\n\
+         | Fish &amp; Chips | 0.666666666666667 |\n\
+         | a\\|b |  |\n\
+         | &lt;b&gt;Pie&lt;/b&gt; | 3 |\n"
+    );
+    let html = table.to_html();
+    assert!(html.starts_with("<table>\n  <thead>\n    <tr><th>Product</th><th>Sales</th></tr>"));
+    assert!(html.contains(
+        "<tr><td>&lt;b&gt;Pie&lt;/b&gt;</td><td style=\"text-align: right\">3</td></tr>"
+    ));
+    assert!(html.contains("<td>Fish &amp; Chips</td>"));
+    assert!(!html.contains("<b>"));
+
+    let mut long = Table::new();
+    long.add_column(
+        "Id".to_string(),
+        (0..100).map(|i| Value::Number(i as f64 / 8.0)).collect(),
+    )
+    .unwrap();
+    let options = TableDisplayOptions {
+        max_rows: Some(6),
+        float_precision: Some(1),
+        ..Default::default()
+    };
+    let markdown = long.display_with(options.clone()).to_markdown();
+    let lines: Vec<&str> = markdown.lines().collect();
+    assert_eq!(lines.len(), 2 + 6 + 1);
+    assert_eq!(lines[3], "| 0.1 |");
+    assert_eq!(lines[6], "| … 94 more rows |");
+    assert_eq!(lines[8], "| 12.4 |");
+    let html = long.display_with(options).to_html();
+    assert!(html.contains("<tr><td colspan=\"1\">… 94 more rows</td></tr>"));
+}
+
+#[test]
+fn test_describe() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Product".to_string(),
+            ["Apple", "Pear", "Apple", "Plum"].map(Value::from).to_vec(),
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Sales".to_string(),
+            vec![
+                Value::Number(10.0),
+                Value::Null,
+                Value::Number(20.0),
+                Value::Number(30.0),
+            ],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Qty".to_string(),
+            vec![
+                Value::Integer(1),
+                Value::Number(2.5),
+                Value::Integer(1),
+                Value::Null,
+            ],
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Shipped".to_string(),
+            vec![
+                Value::from(NaiveDate::from_ymd_opt(2024, 2, 1).unwrap()),
+                Value::from(NaiveDate::from_ymd_opt(2024, 1, 15).unwrap()),
+                Value::Null,
+                Value::Null,
+            ],
+        )
+        .unwrap();
+    table
+        .add_column("Notes".to_string(), vec![Value::Null; 4])
+        .unwrap();
+
+    let summary = table.describe();
+    assert_eq!(summary.shape(), (5, 9));
+    assert_eq!(
+        summary.to_string(),
+        "\
+┌────────────────────────────────────────────────────────────────────────────────────────────────────┐
+ name    │ dtype    │ count │ nulls │ distinct │ min        │ max        │ mean │ stddev            
+├────────────────────────────────────────────────────────────────────────────────────────────────────┤
+ Product │ text     │     4 │     0 │        3 │            │            │      │                   
+ Sales   │ number   │     3 │     1 │        3 │         10 │         30 │   20 │                10 
+ Qty     │ mixed    │     3 │     1 │        2 │          1 │        2.5 │  1.5 │ 0.866025403784439 
+ Shipped │ datetime │     2 │     2 │        2 │ 2024-01-15 │ 2024-02-01 │      │                   
+ Notes   │ null     │     0 │     4 │        0 │            │            │      │                   
+└────────────────────────────────────────────────────────────────────────────────────────────────────┘
+"
+    );
+}
+
+#[test]
+fn test_table_clone_and_equality() {
+    let mut expected = Table::new();
+    expected
+        .add_column(
+            "Sales".to_string(),
+            vec![Value::Integer(30), Value::Number(20.0)],
+        )
+        .unwrap();
+    expected
+        .add_column(
+            "Product".to_string(),
+            ["Apple", "Pear"].map(Value::from).to_vec(),
+        )
+        .unwrap();
+
+    let mut sales = Table::new();
+    sales
+        .add_column(
+            "Product".to_string(),
+            ["Apple", "Pear", "Apple"].map(Value::from).to_vec(),
+        )
+        .unwrap();
+    sales
+        .add_column(
+            "Sales".to_string(),
+            [10.0, 20.0, 20.0].map(Value::Number).to_vec(),
+        )
+        .unwrap();
+    sales.define_measure("Total", "SUM([Sales])").unwrap();
+
+    // Column order doesn't matter, and Integer(30) equals Number(30.0)
+    match sales.evaluate_dax("SUMMARIZE(Sales, [Product], \"Sales\", [Total])") {
+        Ok(DaxResult::Table(summary)) => assert_eq!(summary, expected),
+        other => panic!("Expected a table, got {:?}", other),
+    }
+
+    let mut changed = sales.clone();
+    assert_eq!(changed, sales);
+    assert_eq!(changed.get_measure("Total"), sales.get_measure("Total"));
+    changed.set("Sales", 0, Value::Number(11.0)).unwrap();
+    assert_ne!(changed, sales);
+    assert_eq!(sales.get("Sales", 0), Some(Value::Number(10.0)));
+
+    // Row order does matter
+    let mut reversed = sales.clone();
+    reversed
+        .sort_by(&[("Sales", SortOrder::Descending)])
+        .unwrap();
+    assert_ne!(reversed, sales);
+    let mut extra = sales.clone();
+    extra
+        .add_column("Qty".to_string(), vec![Value::Null; 3])
+        .unwrap();
+    assert_ne!(extra, sales);
+
+    assert_eq!(Table::default(), Table::new());
+    assert!(Table::default().is_empty());
+    assert!(!sales.is_empty());
+    assert!(sales.filter_rows(|_| false).is_empty());
+}
+
+#[test]
+fn test_typed_table_macro_columns() {
+    let orders = dax_macro::table! {
+        "OrderDate": date => ["2024-01-05", "2024-02-29 09:30:00", null],
+        "Qty": int => [1, -2, 3],
+        "Price": number => [10, 2.5, None],
+        "Code": text => [1, "B", 'c'],
+        "Shipped": bool => [true, false, null],
+        "Untyped" => [1, 2.5, "x"]
+    };
+    let day = NaiveDate::from_ymd_opt(2024, 1, 5).unwrap();
+    assert_eq!(orders.get("OrderDate", 0), Some(Value::from(day)));
+    assert_eq!(
+        orders.get("OrderDate", 1),
+        Some(Value::DateTime(
+            NaiveDate::from_ymd_opt(2024, 2, 29)
+                .unwrap()
+                .and_hms_opt(9, 30, 0)
+                .unwrap()
+        ))
+    );
+    assert_eq!(orders.get("OrderDate", 2), Some(Value::Null));
+    assert_eq!(orders.dtype("Qty"), Some(DType::Integer));
+    assert_eq!(orders.get("Price", 0), Some(Value::Number(10.0)));
+    assert_eq!(orders.get("Code", 0), Some(Value::from("1")));
+    assert_eq!(orders.get("Shipped", 2), Some(Value::Null));
+    assert_eq!(orders.dtype("Untyped"), Some(DType::Mixed));
+}
+
+#[test]
+fn test_row_oriented_table_macro() {
+    let rows = dax_macro::table! {
+        columns: ["Product", "Sales", "Qty": int, "Note"],
+        rows: [
+            ["Apple", 100.0, 10, "fresh"],
+            ["Banana", 150.0, 15, 2],
+            ["Orange", 200.0, null, None],
+        ]
+    };
+    let columns = dax_macro::table! {
+        "Product" => ["Apple", "Banana", "Orange"],
+        "Sales" => [100.0, 150.0, 200.0],
+        "Qty": int => [10, 15, null],
+        "Note" => ["fresh", 2, None]
+    };
+    assert_eq!(rows, columns);
+    assert_eq!(rows.column_names(), ["Product", "Sales", "Qty", "Note"]);
+
+    let empty = dax_macro::table! { columns: ["A", "B"], rows: [] };
+    assert_eq!(empty.column_names(), ["A", "B"]);
+    assert_eq!(empty.num_rows(), 0);
+}
+
+#[test]
+fn test_dax_macro() {
+    let mut sales = dax_macro::table! {
+        "Sales" => [100.0, 150.0, 200.0],
+        "Quantity" => [10, 15, 15]
+    };
+    let average = |table: &Table| dax_macro::dax!(table, "SUM([Sales]) / COUNTROWS()");
+    assert!(matches!(average(&sales), Ok(DaxResult::Number(n)) if n == 150.0));
+
+    // The same expression against a changed table
+    sales
+        .add_column(
+            "Sales".to_string(),
+            vec![Value::Number(1.0), Value::Number(2.0), Value::Number(3.0)],
+        )
+        .unwrap();
+    assert!(matches!(average(&sales), Ok(DaxResult::Number(n)) if n == 2.0));
+
+    let result = dax_macro::dax!(sales, "SUMX(FILTER(Sales, [Quantity] > 10), [Sales])");
+    assert!(matches!(result, Ok(DaxResult::Number(n)) if n == 5.0));
+
+    // The tree built when the macro expands is the one the parser builds
+    // at run time
+    let expression = "VAR Big = 15 RETURN COUNTROWS({1, 2}) * (-2^2 % < -1) + (\"x\" = \"x\") \
+                      + SUMX(FILTER(Sales, Sales[Quantity] >= Big), 10%)";
+    let expanded = dax_macro::dax!(
+        sales,
+        "VAR Big = 15 RETURN COUNTROWS({1, 2}) * (-2^2 % < -1) + (\"x\" = \"x\") \
+         + SUMX(FILTER(Sales, Sales[Quantity] >= Big), 10%)"
+    );
+    assert!(
+        matches!(expanded, Ok(DaxResult::Number(n)) if n == 3.2),
+        "{:?}",
+        expanded
+    );
+    assert_eq!(
+        format!("{:?}", expanded),
+        format!("{:?}", sales.evaluate_dax(expression))
+    );
+
+    // Columns can only be checked against the table at run time
+    let other = Table::new();
+    match dax_macro::dax!(other, "SUM([Sales])") {
+        Err(DaxError::EvaluationError(message)) => {
+            assert_eq!(message, "Column Sales not found")
+        }
+        other => panic!("Expected an evaluation error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_measures_macro() {
+    let mut sales = dax_macro::table! {
+        "Sales" => [100.0, 150.0, 200.0],
+        "Quantity" => [10, 15, 25]
+    };
+    // Declared before the measure they refer to
+    dax_macro::measures! {
+        on sales:
+        "Avg Price" => "DIVIDE([Total Sales], SUM([Quantity]))",
+        "Total Sales" => "SUM([Sales])",
+    }
+    .unwrap();
+    assert_eq!(sales.get_measure("Total Sales"), Some("SUM([Sales])"));
+    match sales.evaluate_dax("[Avg Price]") {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 9.0),
+        other => panic!("Expected a number, got {:?}", other),
+    }
+
+    // A name that's neither a measure nor a column is found at run time
+    match dax_macro::measures! { on sales: "Margin" => "[Total Sales] - SUM([Cost])" } {
+        Err(DaxError::UnknownColumn { name, .. }) => assert_eq!(name, "Cost"),
+        other => panic!("Expected an unknown column, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_deeply_nested_expressions() {
+    use dax_rust::parser::MAX_DEPTH;
+    let table = Table::new();
+    // At the cap, chains of operators and signs evaluate as usual
+    let sum = format!("1{}", "+1".repeat(MAX_DEPTH));
+    let signs = format!("{}1", "-".repeat(MAX_DEPTH));
+    let brackets = format!("{}2{}", "(".repeat(MAX_DEPTH), ")".repeat(MAX_DEPTH));
+    for (expression, expected) in [(sum, 257.0), (signs, 1.0), (brackets, 2.0)] {
+        match table.evaluate_dax(&expression) {
+            Ok(DaxResult::Number(n)) => assert_eq!(n, expected),
+            other => panic!("Expected {}, got {:?}", expected, other),
+        }
+    }
+    // Past it, however deep, the expression is a parse error rather than a
+    // stack overflow
+    let deep = format!("{}1{}", "ABS(".repeat(100_000), ")".repeat(100_000));
+    match table.evaluate_dax(&deep) {
+        Err(DaxError::ParseError(message)) => {
+            assert!(
+                message.contains("nests more than 256 levels"),
+                "{}",
+                message
+            )
+        }
+        other => panic!("Expected a parse error, got {:?}", other),
+    }
+}
@@ -0,0 +1,406 @@
+// tests/time_intelligence_test.rs
+use chrono::{Datelike, NaiveDate};
+use dax_rust::error::DaxError;
+use dax_rust::table::{DaxResult, Table};
+use dax_rust::types::Value;
+
+// One row per month from January 2023 to December 2024, selling 10 × month number
+fn monthly_sales() -> Table {
+    let mut dates = Vec::new();
+    let mut sales = Vec::new();
+    for year in [2023, 2024] {
+        for month in 1..=12 {
+            dates.push(Value::from(
+                NaiveDate::from_ymd_opt(year, month, 1).unwrap(),
+            ));
+            sales.push(Value::Number(10.0 * month as f64));
+        }
+    }
+
+    let mut table = Table::new();
+    table.add_column("Date".to_string(), dates).unwrap();
+    table.add_column("Sales".to_string(), sales).unwrap();
+    table
+}
+
+// Evaluate an expression with the filter context narrowed to one month
+fn in_month(table: &Table, year: i32, month: u32, expr: &str) -> Result<DaxResult, DaxError> {
+    table.evaluate_dax(&format!(
+        "CALCULATE({}, CALENDAR(DATE({}, {}, 1), EOMONTH(DATE({}, {}, 1), 0)))",
+        expr, year, month, year, month
+    ))
+}
+
+fn number(result: Result<DaxResult, DaxError>) -> f64 {
+    match result {
+        Ok(DaxResult::Number(n)) => n,
+        other => panic!("Expected number result, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_calculate_narrows_to_filter_table() {
+    let table = monthly_sales();
+    assert_eq!(number(in_month(&table, 2024, 3, "SUM([Sales])")), 30.0);
+    assert_eq!(number(table.evaluate_dax("SUM([Sales])")), 1560.0);
+}
+
+#[test]
+fn test_totalytd_accumulates_and_resets() {
+    let table = monthly_sales();
+    let ytd = |year, month| {
+        number(in_month(
+            &table,
+            year,
+            month,
+            "TOTALYTD(SUM([Sales]), [Date])",
+        ))
+    };
+
+    assert_eq!(ytd(2023, 1), 10.0);
+    assert_eq!(ytd(2023, 2), 30.0);
+    assert_eq!(ytd(2023, 12), 780.0);
+    // A new year starts from scratch
+    assert_eq!(ytd(2024, 1), 10.0);
+    assert_eq!(ytd(2024, 6), 210.0);
+}
+
+#[test]
+fn test_datesytd_matches_totalytd() {
+    let table = monthly_sales();
+    assert_eq!(
+        number(in_month(
+            &table,
+            2024,
+            4,
+            "CALCULATE(SUM([Sales]), DATESYTD([Date]))"
+        )),
+        100.0
+    );
+
+    match in_month(&table, 2024, 4, "COUNTROWS(DATESYTD([Date]))") {
+        Ok(DaxResult::Number(n)) => assert_eq!(n, 4.0),
+        other => panic!("Expected number result, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_fiscal_year_end() {
+    let table = monthly_sales();
+    let fiscal_ytd = |year, month| {
+        number(in_month(
+            &table,
+            year,
+            month,
+            "TOTALYTD(SUM([Sales]), [Date], \"6/30\")",
+        ))
+    };
+
+    // The fiscal year runs July to June
+    assert_eq!(fiscal_ytd(2023, 7), 70.0);
+    assert_eq!(
+        fiscal_ytd(2023, 12),
+        70.0 + 80.0 + 90.0 + 100.0 + 110.0 + 120.0
+    );
+    assert_eq!(fiscal_ytd(2024, 2), 570.0 + 10.0 + 20.0);
+    assert_eq!(fiscal_ytd(2024, 7), 70.0);
+}
+
+#[test]
+fn test_sameperiodlastyear() {
+    let mut table = monthly_sales();
+    // Tag each row with its year so the shifted period is visible
+    table
+        .add_calculated_column("Year".to_string(), "YEAR([Date])")
+        .unwrap();
+
+    assert_eq!(
+        number(in_month(
+            &table,
+            2024,
+            3,
+            "CALCULATE(MAX([Year]), SAMEPERIODLASTYEAR([Date]))"
+        )),
+        2023.0
+    );
+    assert_eq!(
+        number(in_month(
+            &table,
+            2024,
+            3,
+            "CALCULATE(SUM([Sales]), SAMEPERIODLASTYEAR([Date]))"
+        )),
+        30.0
+    );
+}
+
+#[test]
+fn test_invalid_year_end() {
+    let table = monthly_sales();
+    assert!(table
+        .evaluate_dax("TOTALYTD(SUM([Sales]), [Date], \"13/45\")")
+        .is_err());
+}
+
+#[test]
+fn test_rolling_three_month_total() {
+    let table = monthly_sales();
+    let rolling = |year, month| {
+        number(in_month(
+            &table,
+            year,
+            month,
+            "CALCULATE(SUM([Sales]), DATESINPERIOD([Date], MAX([Date]), -3, MONTH))",
+        ))
+    };
+
+    assert_eq!(rolling(2024, 3), 10.0 + 20.0 + 30.0);
+    // The window reaches back across the year boundary
+    assert_eq!(rolling(2024, 2), 120.0 + 10.0 + 20.0);
+    assert_eq!(rolling(2023, 1), 10.0);
+}
+
+#[test]
+fn test_datesinperiod_includes_anchor() {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Date".to_string(),
+            (1..=31)
+                .map(|day| Value::from(NaiveDate::from_ymd_opt(2024, 1, day).unwrap()))
+                .collect(),
+        )
+        .unwrap();
+
+    let count = |expr: &str| number(table.evaluate_dax(&format!("COUNTROWS({})", expr)));
+    assert_eq!(
+        count("DATESINPERIOD([Date], DATE(2024, 1, 10), -7, DAY)"),
+        7.0
+    );
+    assert_eq!(
+        count("DATESINPERIOD([Date], DATE(2024, 1, 10), 7, DAY)"),
+        7.0
+    );
+    match table.evaluate_dax("DATESINPERIOD([Date], DATE(2024, 1, 10), -3, DAY)") {
+        Ok(DaxResult::Table(dates)) => assert_eq!(
+            dates.get_column("Date").unwrap(),
+            vec![
+                Value::from(NaiveDate::from_ymd_opt(2024, 1, 8).unwrap()),
+                Value::from(NaiveDate::from_ymd_opt(2024, 1, 9).unwrap()),
+                Value::from(NaiveDate::from_ymd_opt(2024, 1, 10).unwrap()),
+            ]
+        ),
+        other => panic!("Expected table result, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_datesbetween_with_open_ends() {
+    let table = monthly_sales();
+    let between = |start: &str, end: &str| {
+        number(table.evaluate_dax(&format!(
+            "CALCULATE(SUM([Sales]), DATESBETWEEN([Date], {}, {}))",
+            start, end
+        )))
+    };
+
+    assert_eq!(between("DATE(2024, 1, 1)", "DATE(2024, 3, 31)"), 60.0);
+    assert_eq!(between("BLANK()", "DATE(2023, 2, 1)"), 30.0);
+    assert_eq!(between("DATE(2024, 11, 1)", "BLANK()"), 230.0);
+    assert_eq!(between("BLANK()", "BLANK()"), 1560.0);
+}
+
+// One row per day of 2024
+fn daily_dates() -> Table {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Date".to_string(),
+            NaiveDate::from_ymd_opt(2024, 1, 1)
+                .unwrap()
+                .iter_days()
+                .take(366)
+                .map(Value::from)
+                .collect(),
+        )
+        .unwrap();
+    table
+}
+
+// Evaluate a date table expression with the filter context narrowed to Feb 10–20, 2024
+fn mid_february(table: &Table, expr: &str) -> Vec<Value> {
+    let result = table.evaluate_dax(&format!(
+        "CALCULATE({}, DATESBETWEEN([Date], DATE(2024, 2, 10), DATE(2024, 2, 20)))",
+        expr
+    ));
+    match result {
+        Ok(DaxResult::Table(dates)) => dates.get_column("Date").unwrap().to_vec(),
+        other => panic!("Expected table result for {}, got {:?}", expr, other),
+    }
+}
+
+fn day(y: i32, m: u32, d: u32) -> Value {
+    Value::from(NaiveDate::from_ymd_opt(y, m, d).unwrap())
+}
+
+#[test]
+fn test_first_and_last_date() {
+    let table = daily_dates();
+    assert_eq!(
+        mid_february(&table, "FIRSTDATE([Date])"),
+        vec![day(2024, 2, 10)]
+    );
+    assert_eq!(
+        mid_february(&table, "LASTDATE([Date])"),
+        vec![day(2024, 2, 20)]
+    );
+}
+
+#[test]
+fn test_start_and_end_of_period() {
+    let table = daily_dates();
+    let cases = [
+        ("STARTOFMONTH", day(2024, 2, 1)),
+        ("ENDOFMONTH", day(2024, 2, 29)),
+        ("STARTOFQUARTER", day(2024, 1, 1)),
+        ("ENDOFQUARTER", day(2024, 3, 31)),
+        ("STARTOFYEAR", day(2024, 1, 1)),
+        ("ENDOFYEAR", day(2024, 12, 31)),
+    ];
+    for (function, expected) in cases {
+        let expr = format!("{}([Date])", function);
+        assert_eq!(mid_february(&table, &expr), vec![expected], "{}", function);
+    }
+
+    // Fiscal years ending in June
+    assert_eq!(
+        mid_february(&table, "ENDOFYEAR([Date], \"6/30\")"),
+        vec![day(2024, 6, 30)]
+    );
+}
+
+#[test]
+fn test_previous_and_next_periods() {
+    let table = daily_dates();
+    let bounds = |expr: &str| {
+        let dates = mid_february(&table, expr);
+        (dates.len(), dates.first().cloned(), dates.last().cloned())
+    };
+
+    assert_eq!(
+        bounds("PREVIOUSDAY([Date])"),
+        (1, Some(day(2024, 2, 9)), Some(day(2024, 2, 9)))
+    );
+    assert_eq!(
+        bounds("NEXTDAY([Date])"),
+        (1, Some(day(2024, 2, 21)), Some(day(2024, 2, 21)))
+    );
+    assert_eq!(
+        bounds("PREVIOUSMONTH([Date])"),
+        (31, Some(day(2024, 1, 1)), Some(day(2024, 1, 31)))
+    );
+    assert_eq!(
+        bounds("NEXTMONTH([Date])"),
+        (31, Some(day(2024, 3, 1)), Some(day(2024, 3, 31)))
+    );
+    assert_eq!(
+        bounds("NEXTQUARTER([Date])"),
+        (91, Some(day(2024, 4, 1)), Some(day(2024, 6, 30)))
+    );
+    // The table only covers 2024, so there is no previous or next calendar year
+    assert_eq!(bounds("PREVIOUSYEAR([Date])"), (0, None, None));
+    assert_eq!(bounds("NEXTYEAR([Date])"), (0, None, None));
+}
+
+#[test]
+fn test_period_to_date() {
+    let table = daily_dates();
+    assert_eq!(mid_february(&table, "DATESMTD([Date])").len(), 20);
+    assert_eq!(mid_february(&table, "DATESQTD([Date])").len(), 51);
+    assert_eq!(
+        number(table.evaluate_dax(
+            "CALCULATE(TOTALMTD(COUNTROWS(), [Date]), DATESBETWEEN([Date], DATE(2024, 3, 1), DATE(2024, 3, 15)))"
+        )),
+        15.0
+    );
+}
+
+#[test]
+fn test_navigation_result_as_date() {
+    let table = daily_dates();
+    assert_eq!(
+        number(table.evaluate_dax(
+            "CALCULATE(DAY(ENDOFMONTH([Date])), DATESBETWEEN([Date], DATE(2024, 2, 10), DATE(2024, 2, 20)))"
+        )),
+        29.0
+    );
+}
+
+#[test]
+fn test_closing_and_opening_balances() {
+    // Stock is counted on weekdays only; the level equals the day of the year
+    let mut dates = Vec::new();
+    let mut levels = Vec::new();
+    for (ordinal, date) in NaiveDate::from_ymd_opt(2024, 1, 1)
+        .unwrap()
+        .iter_days()
+        .take(91)
+        .enumerate()
+    {
+        if date.weekday().number_from_monday() <= 5 {
+            dates.push(Value::from(date));
+            levels.push(Value::Number(ordinal as f64 + 1.0));
+        }
+    }
+    let mut table = Table::new();
+    table.add_column("Date".to_string(), dates).unwrap();
+    table.add_column("Stock".to_string(), levels).unwrap();
+
+    // February 29th 2024 is a Thursday, March 31st a Sunday
+    assert_eq!(
+        number(in_month(
+            &table,
+            2024,
+            2,
+            "CLOSINGBALANCEMONTH(SUM([Stock]), [Date])"
+        )),
+        60.0
+    );
+    assert_eq!(
+        number(in_month(
+            &table,
+            2024,
+            3,
+            "CLOSINGBALANCEMONTH(SUM([Stock]), [Date])"
+        )),
+        89.0
+    );
+    // The opening balance is the closing balance of the previous month
+    assert_eq!(
+        number(in_month(
+            &table,
+            2024,
+            3,
+            "OPENINGBALANCEMONTH(SUM([Stock]), [Date])"
+        )),
+        60.0
+    );
+    assert_eq!(
+        number(in_month(
+            &table,
+            2024,
+            2,
+            "CLOSINGBALANCEQUARTER(SUM([Stock]), [Date])"
+        )),
+        89.0
+    );
+    assert_eq!(
+        number(table.evaluate_dax("CLOSINGBALANCEYEAR(SUM([Stock]), [Date])")),
+        89.0
+    );
+    // Nothing precedes the first month in the table
+    assert!(matches!(
+        in_month(&table, 2024, 1, "OPENINGBALANCEMONTH(SUM([Stock]), [Date])"),
+        Ok(DaxResult::Blank)
+    ));
+}
@@ -0,0 +1,176 @@
+// tests/window_test.rs
+use dax_rust::table::{DaxResult, Table};
+use dax_rust::types::Value;
+
+// Five days of sales, deliberately out of order
+fn daily_values() -> Table {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Day".to_string(),
+            [3.0, 1.0, 2.0, 5.0, 4.0].map(Value::Number).to_vec(),
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Value".to_string(),
+            [30.0, 10.0, 20.0, 50.0, 40.0].map(Value::Number).to_vec(),
+        )
+        .unwrap();
+    table
+}
+
+fn column_of(table: &Table, expr: &str, column: &str) -> Vec<Value> {
+    match table.evaluate_dax(expr) {
+        Ok(DaxResult::Table(result)) => result.get_column(column).unwrap().to_vec(),
+        other => panic!("Expected table result for {}, got {:?}", expr, other),
+    }
+}
+
+#[test]
+fn test_offset_gives_previous_row() {
+    let table = daily_values();
+    let previous = column_of(
+        &table,
+        "ADDCOLUMNS(Data, \"Previous\", CALCULATE(SUM([Value]), OFFSET(-1, Data, ORDERBY([Day]))))",
+        "Previous",
+    );
+    assert_eq!(
+        previous,
+        vec![
+            Value::Number(20.0),
+            Value::Null,
+            Value::Number(10.0),
+            Value::Number(40.0),
+            Value::Number(30.0),
+        ]
+    );
+}
+
+#[test]
+fn test_window_running_three_rows() {
+    let mut table = daily_values();
+    table
+        .add_calculated_column(
+            "Rolling".to_string(),
+            "CALCULATE(SUM([Value]), WINDOW(-2, REL, 0, REL, Data, ORDERBY([Day])))",
+        )
+        .unwrap();
+    assert_eq!(
+        table.get_column("Rolling").unwrap(),
+        [60.0, 10.0, 30.0, 120.0, 90.0].map(Value::Number).to_vec()
+    );
+
+    // Absolute bounds count from either end of the ordering
+    assert_eq!(
+        column_of(
+            &table,
+            "WINDOW(2, ABS, -2, ABS, Data, ORDERBY([Day]))",
+            "Day"
+        ),
+        [2.0, 3.0, 4.0].map(Value::Number).to_vec()
+    );
+}
+
+#[test]
+fn test_index_by_position() {
+    let table = daily_values();
+    assert_eq!(
+        column_of(&table, "INDEX(1, Data, ORDERBY([Day], DESC))", "Day"),
+        vec![Value::Number(5.0)]
+    );
+    assert_eq!(
+        column_of(&table, "INDEX(-1, Data, ORDERBY([Day]))", "Value"),
+        vec![Value::Number(50.0)]
+    );
+    assert!(column_of(&table, "INDEX(9, Data, ORDERBY([Day]))", "Day").is_empty());
+}
+
+#[test]
+fn test_window_function_errors() {
+    let table = daily_values();
+    for expr in [
+        "OFFSET(-1, Data, ORDERBY([Day]))",
+        "INDEX(1, Data, [Day])",
+        "WINDOW(1, ABS, 2, SOMETIMES, Data, ORDERBY([Day]))",
+        "ORDERBY([Day])",
+    ] {
+        assert!(table.evaluate_dax(expr).is_err(), "{}", expr);
+    }
+}
+
+// Sales by customer with a tie for first place
+fn sales_with_tie() -> Table {
+    let mut table = Table::new();
+    table
+        .add_column(
+            "Customer".to_string(),
+            ["a", "b", "c", "d", "e"].map(Value::from).to_vec(),
+        )
+        .unwrap();
+    table
+        .add_column(
+            "Sales".to_string(),
+            [100.0, 300.0, 200.0, 300.0, 50.0]
+                .map(Value::Number)
+                .to_vec(),
+        )
+        .unwrap();
+    table
+}
+
+fn numbers(values: &[f64]) -> Vec<Value> {
+    values.iter().map(|&n| Value::Number(n)).collect()
+}
+
+#[test]
+fn test_rank_ties() {
+    let table = sales_with_tie();
+    assert_eq!(
+        column_of(
+            &table,
+            "ADDCOLUMNS(Sales, \"Rank\", RANK(Sales, ORDERBY([Sales], DESC)))",
+            "Rank"
+        ),
+        numbers(&[4.0, 1.0, 3.0, 1.0, 5.0])
+    );
+    assert_eq!(
+        column_of(
+            &table,
+            "ADDCOLUMNS(Sales, \"Rank\", RANK(SKIP, Sales, ORDERBY([Sales], DESC)))",
+            "Rank"
+        ),
+        numbers(&[4.0, 1.0, 3.0, 1.0, 5.0])
+    );
+    assert_eq!(
+        column_of(
+            &table,
+            "ADDCOLUMNS(Sales, \"Rank\", RANK(DENSE, Sales, ORDERBY([Sales], DESC)))",
+            "Rank"
+        ),
+        numbers(&[3.0, 1.0, 2.0, 1.0, 4.0])
+    );
+}
+
+#[test]
+fn test_rownumber() {
+    let table = sales_with_tie();
+    // The tie is broken by the other columns
+    assert_eq!(
+        column_of(
+            &table,
+            "ADDCOLUMNS(Sales, \"Rank\", ROWNUMBER(Sales, ORDERBY([Sales], DESC)))",
+            "Rank"
+        ),
+        numbers(&[4.0, 1.0, 3.0, 2.0, 5.0])
+    );
+
+    // Identical rows can't be numbered
+    let mut duplicates = Table::new();
+    duplicates
+        .add_column("Sales".to_string(), numbers(&[1.0, 1.0]))
+        .unwrap();
+    assert!(duplicates
+        .evaluate_dax("ADDCOLUMNS(Sales, \"N\", ROWNUMBER(Sales, ORDERBY([Sales])))")
+        .is_err());
+}
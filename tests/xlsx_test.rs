@@ -0,0 +1,128 @@
+// tests/xlsx_test.rs
+#![cfg(feature = "excel")]
+use chrono::NaiveDate;
+use dax_rust::io::read_xlsx;
+use dax_rust::table::DaxResult;
+use dax_rust::types::Value;
+use std::path::{Path, PathBuf};
+
+// A workbook with a Sales sheet of text, numbers, dates, booleans, formulas,
+// a merged cell, blanks and an empty row, and a Notes sheet
+fn workbook() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join("mixed.xlsx")
+}
+
+#[test]
+fn test_read_first_sheet() {
+    let table = read_xlsx(&workbook(), None).unwrap();
+    assert_eq!(
+        table.column_names(),
+        ["Product", "Qty", "Price", "Sold", "Organic", "Total", "Note"]
+    );
+    assert_eq!(table.num_rows(), 5);
+
+    // The merged cell's value fills its range
+    assert_eq!(
+        table.get_column("Product").unwrap(),
+        vec![
+            "Apple".into(),
+            "Apple".into(),
+            "Pear".into(),
+            Value::Null,
+            "Plum".into()
+        ]
+    );
+    assert_eq!(
+        table.get_column("Qty").unwrap(),
+        vec![
+            Value::Integer(3),
+            Value::Integer(10),
+            Value::Null,
+            Value::Null,
+            Value::Integer(-4)
+        ]
+    );
+    assert_eq!(table.get("Price", 0), Some(Value::Number(2.5)));
+    assert_eq!(table.get("Price", 1), Some(Value::Null));
+    assert_eq!(table.get("Price", 4), Some(Value::Number(0.001)));
+
+    let day = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+    assert_eq!(table.get("Sold", 0), Some(Value::from(day)));
+    let evening = NaiveDate::from_ymd_opt(2024, 1, 31)
+        .unwrap()
+        .and_hms_opt(18, 0, 0)
+        .unwrap();
+    assert_eq!(table.get("Sold", 1), Some(Value::from(evening)));
+    assert_eq!(table.get("Sold", 2), Some(Value::Null));
+
+    assert_eq!(table.get("Organic", 0), Some(Value::Boolean(true)));
+    assert_eq!(table.get("Organic", 1), Some(Value::Boolean(false)));
+
+    // Formulas are their saved results
+    assert_eq!(table.get("Total", 0), Some(Value::Number(7.5)));
+    assert_eq!(table.get("Total", 1), Some(Value::Integer(0)));
+    assert_eq!(table.get("Total", 2), Some(Value::from("#N/A")));
+    assert_eq!(
+        table.get_column("Note").unwrap(),
+        vec![
+            "fresh & crisp".into(),
+            "bulk".into(),
+            "Big sale".into(),
+            Value::Null,
+            Value::Null
+        ]
+    );
+
+    assert!(matches!(
+        table.evaluate_dax("SUM([Qty])"),
+        Ok(DaxResult::Integer(9))
+    ));
+}
+
+#[test]
+fn test_read_named_sheet() {
+    let notes = read_xlsx(&workbook(), Some("Notes")).unwrap();
+    assert_eq!(notes.column_names(), ["Note"]);
+    assert_eq!(notes.get("Note", 0), Some(Value::from("Check the totals")));
+
+    let error = read_xlsx(&workbook(), Some("Returns"))
+        .unwrap_err()
+        .to_string();
+    assert!(
+        error.contains("no sheet Returns") && error.contains("Sales, Notes"),
+        "{}",
+        error
+    );
+
+    let path = std::env::temp_dir().join("dax_rust_not_a_workbook.xlsx");
+    std::fs::write(&path, "Product,Qty\n").unwrap();
+    let error = read_xlsx(&path, None).unwrap_err().to_string();
+    std::fs::remove_file(&path).unwrap();
+    assert!(error.contains("isn't a zip archive"), "{}", error);
+}
+
+#[test]
+fn test_damaged_workbooks() {
+    let bytes = std::fs::read(workbook()).unwrap();
+    let path = std::env::temp_dir().join("dax_rust_damaged.xlsx");
+    // Cut short, the central directory is gone
+    for len in (0..bytes.len()).step_by(7) {
+        std::fs::write(&path, &bytes[..len]).unwrap();
+        assert!(read_xlsx(&path, None).is_err(), "cut to {} bytes", len);
+    }
+    // Any byte changed, in the zip's headers, the compressed streams or
+    // their Huffman tables, the workbook reads or errs without panicking
+    for (i, change) in [0x01, 0x80, 0xff].into_iter().cycle().enumerate() {
+        if i == bytes.len() {
+            break;
+        }
+        let mut damaged = bytes.clone();
+        damaged[i] ^= change;
+        std::fs::write(&path, &damaged).unwrap();
+        let _ = read_xlsx(&path, None);
+    }
+    std::fs::remove_file(&path).unwrap();
+}